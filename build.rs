@@ -0,0 +1,4 @@
+fn main() {
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/deoxy.proto").expect("Failed to compile proto/deoxy.proto");
+}
@@ -0,0 +1,575 @@
+//! Converting protocols to and from [Autoprotocol](http://autoprotocol.org), so that protocols
+//! written with other lab-automation tools can be run on the buffer exchanger (and vice versa).
+//!
+//! Only a small subset of Autoprotocol makes sense for a buffer exchanger, so only two instructions
+//! are understood:
+//!
+//! - `dispense` perfuses the sample with the reagent (a buffer, by its configured name or number)
+//!   until the total volume of its columns has been pumped. Dispensing no volume just chooses the
+//!   buffer for the instructions which follow.
+//! - `incubate` holds the temperature of its location (`warm_37`, `cold_4`, and so on) for its
+//!   duration or, at `ambient`, keeps perfusing with the last buffer dispensed for its duration.
+//!
+//! The objects instructions act on and the protocol's refs are ignored (there being only the one
+//! sample), and imported protocols finish by perfusing with the last buffer dispensed until
+//! stopped:
+//!
+//! ```json
+//! {
+//!   "refs": {"heart": {}},
+//!   "instructions": [
+//!     {"op": "dispense", "object": "heart", "reagent": "PBS",
+//!      "columns": [{"column": 0, "volume": "50:milliliter"}]},
+//!     {"op": "incubate", "object": "heart", "where": "warm_37", "duration": "30:minute"},
+//!     {"op": "dispense", "object": "heart", "reagent": "1% SDS",
+//!      "columns": [{"column": 0, "volume": "0:microliter"}]},
+//!     {"op": "incubate", "object": "heart", "where": "ambient", "duration": "12:hour"}
+//!   ]
+//! }
+//! ```
+use std::{collections::BTreeMap, error, fmt, time::Duration};
+
+use serde_json::Value;
+
+use crate::{
+    quantity::{Time, Volume},
+    Amount, BufferRef, FileStep, Hardware, MotorId, Perfusion, Protocol, ProtocolFile, PumpId,
+    Step, MAX_ACTIONS,
+};
+
+/// The ref exported protocols act on.
+const SAMPLE: &str = "sample";
+
+/// The tolerance (in K) of temperatures held by imported protocols.
+const TOLERANCE: f64 = 0.5;
+
+/// The locations below room temperature (by their temperatures in °C).
+const COLD: &[(&str, f64)] = &[("cold_4", 4.0), ("cold_20", -20.0), ("cold_80", -80.0)];
+
+/// A protocol in Autoprotocol.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Autoprotocol {
+    /// The containers used, by name (which are ignored).
+    #[serde(default)]
+    pub refs: BTreeMap<String, Value>,
+    /// The instructions, in order.
+    pub instructions: Vec<Instruction>,
+}
+
+/// An instruction in Autoprotocol (of those understood).
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum Instruction {
+    /// Perfuses with the given reagent until the volume of the columns has been pumped.
+    Dispense {
+        /// The ref acted on.
+        object: String,
+        /// The buffer, by its configured name or number.
+        reagent: String,
+        /// The volumes to dispense.
+        columns: Vec<Column>,
+    },
+    /// Holds the temperature of the given location for the given duration.
+    Incubate {
+        /// The ref acted on.
+        object: String,
+        /// The location (e.g. `warm_37` or `ambient`).
+        #[serde(rename = "where")]
+        location: String,
+        /// How long to incubate for (e.g. `30:minute`).
+        duration: String,
+        /// Whether to shake the sample (which is ignored).
+        #[serde(default)]
+        shaking: bool,
+    },
+}
+
+/// A column dispensed into.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Column {
+    /// The column's index.
+    pub column: usize,
+    /// The volume to dispense (e.g. `5:milliliter`).
+    pub volume: String,
+}
+
+/// An error encountered in converting a protocol to or from Autoprotocol.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AutoprotocolError {
+    /// An instruction gives a quantity which isn't a valid volume or duration.
+    InvalidQuantity {
+        /// The instruction in question.
+        instruction: usize,
+        /// The quantity (e.g. `5:milliliter`).
+        quantity: String,
+    },
+    /// An instruction incubates at a location with no known temperature.
+    UnknownLocation {
+        /// The instruction in question.
+        instruction: usize,
+        /// The location in question.
+        location: String,
+    },
+    /// An instruction incubates at room temperature before any buffer has been dispensed.
+    NoBuffer {
+        /// The instruction in question.
+        instruction: usize,
+    },
+    /// No buffer is ever dispensed.
+    Empty,
+    /// A step can't be written in Autoprotocol (e.g. it prompts the user, or holds a temperature
+    /// which isn't one of Autoprotocol's locations).
+    Unsupported {
+        /// The step in question.
+        step: usize,
+    },
+    /// The protocol would be written out as more than [`MAX_ACTIONS`] instructions (usually
+    /// because of a repeat with a very large count).
+    ///
+    /// [`MAX_ACTIONS`]: constant.MAX_ACTIONS.html
+    TooLong,
+}
+
+impl fmt::Display for AutoprotocolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidQuantity {
+                instruction,
+                quantity,
+            } => write!(
+                f,
+                "instruction {}: invalid quantity \"{}\"",
+                instruction, quantity
+            ),
+            Self::UnknownLocation {
+                instruction,
+                location,
+            } => write!(
+                f,
+                "instruction {}: unknown location \"{}\"",
+                instruction, location
+            ),
+            Self::NoBuffer { instruction } => write!(
+                f,
+                "instruction {}: no buffer has been dispensed to perfuse with",
+                instruction
+            ),
+            Self::Empty => write!(f, "no buffer is dispensed"),
+            Self::Unsupported { step } => {
+                write!(f, "step {}: can't be written in Autoprotocol", step)
+            }
+            Self::TooLong => write!(f, "more than {} instructions are needed", MAX_ACTIONS),
+        }
+    }
+}
+
+impl error::Error for AutoprotocolError {}
+
+/// Parses the given quantity (e.g. `5:milliliter`), given the factor converting each of the
+/// allowed units to the unit wanted.
+fn quantity(
+    instruction: usize,
+    quantity: &str,
+    units: &[(&str, f64)],
+) -> Result<f64, AutoprotocolError> {
+    let invalid = || AutoprotocolError::InvalidQuantity {
+        instruction,
+        quantity: quantity.into(),
+    };
+    let mut parts = quantity.splitn(2, ':');
+    let value = parts
+        .next()
+        .and_then(|value| value.trim().parse::<f64>().ok())
+        .filter(|value| value.is_finite() && *value >= 0.0)
+        .ok_or_else(invalid)?;
+    let unit = parts.next().ok_or_else(invalid)?.trim();
+    units
+        .iter()
+        .find(|(name, _)| *name == unit)
+        .map(|(_, factor)| value * factor)
+        .ok_or_else(invalid)
+}
+
+/// Parses the given volume, in mL.
+fn volume(instruction: usize, volume: &str) -> Result<f64, AutoprotocolError> {
+    let units = [
+        ("nanoliter", 1e-6),
+        ("microliter", 1e-3),
+        ("milliliter", 1.0),
+        ("liter", 1e3),
+    ];
+    quantity(instruction, volume, &units)
+}
+
+/// Parses the given duration, in seconds.
+fn duration(instruction: usize, duration: &str) -> Result<f64, AutoprotocolError> {
+    let units = [
+        ("millisecond", 1e-3),
+        ("second", 1.0),
+        ("minute", 60.0),
+        ("hour", 3600.0),
+    ];
+    quantity(instruction, duration, &units)
+}
+
+/// The temperature (in °C) of the given location, or `None` for room temperature.
+fn temperature(instruction: usize, location: &str) -> Result<Option<f64>, AutoprotocolError> {
+    if location == "ambient" {
+        return Ok(None);
+    }
+    let warm = location
+        .strip_prefix("warm_")
+        .and_then(|target| target.parse::<f64>().ok());
+    let cold = COLD
+        .iter()
+        .find(|(name, _)| *name == location)
+        .map(|(_, target)| *target);
+    warm.or(cold)
+        .map(Some)
+        .ok_or_else(|| AutoprotocolError::UnknownLocation {
+            instruction,
+            location: location.into(),
+        })
+}
+
+/// The location with the given temperature (in °C), if there is one.
+fn location(target: f64) -> Option<String> {
+    let cold = COLD
+        .iter()
+        .find(|(_, temperature)| (*temperature - target).abs() < f64::EPSILON)
+        .map(|(name, _)| name.to_string());
+    let whole = target.fract() == 0.0 && target > 0.0;
+    cold.or_else(|| Some(format!("warm_{}", target)).filter(|_| whole))
+}
+
+impl Autoprotocol {
+    /// Converts the instructions to a protocol file (which can then be converted for the hardware
+    /// at hand; see [`ProtocolFile::resolve`]).
+    ///
+    /// [`ProtocolFile::resolve`]: struct.ProtocolFile.html#method.resolve
+    pub fn to_file(&self) -> Result<ProtocolFile, AutoprotocolError> {
+        let mut steps = Vec::new();
+        let mut buffer = None::<BufferRef>;
+        let perfusion = |buffer: &BufferRef, duration: Option<f64>, volume: Option<f64>| {
+            FileStep::Perfusion(Perfusion {
+                name: None,
+                buffer: buffer.clone(),
+                duration: duration.map(|secs| Amount::Value(Time::from_secs(secs))),
+                volume: volume.map(|volume| Amount::Value(Volume::from_millilitres(volume))),
+                notes: None,
+                begin: vec![],
+                end: vec![],
+                overrun: None,
+            })
+        };
+        for (index, instruction) in self.instructions.iter().enumerate() {
+            match instruction {
+                Instruction::Dispense {
+                    reagent, columns, ..
+                } => {
+                    let reagent = match reagent.trim().parse::<MotorId>() {
+                        Ok(number) => BufferRef::Number(number),
+                        Err(_) => BufferRef::Name(reagent.clone()),
+                    };
+                    let total = columns.iter().try_fold(0.0, |total, column| {
+                        Ok::<_, AutoprotocolError>(total + volume(index, &column.volume)?)
+                    })?;
+                    if total > 0.0 {
+                        steps.push(perfusion(&reagent, None, Some(total)));
+                    }
+                    buffer = Some(reagent);
+                }
+                Instruction::Incubate {
+                    location,
+                    duration: time,
+                    ..
+                } => {
+                    let seconds = duration(index, time)?;
+                    match temperature(index, location)? {
+                        Some(target) => steps.push(FileStep::Step(Step::HoldTemperature {
+                            target,
+                            tolerance: TOLERANCE,
+                            duration: Duration::from_secs_f64(seconds),
+                        })),
+                        None => {
+                            let buffer = buffer
+                                .as_ref()
+                                .ok_or(AutoprotocolError::NoBuffer { instruction: index })?;
+                            steps.push(perfusion(buffer, Some(seconds), None));
+                        }
+                    }
+                }
+            }
+        }
+        let last = buffer.ok_or(AutoprotocolError::Empty)?;
+        steps.push(perfusion(&last, None, None));
+        Ok(ProtocolFile {
+            steps,
+            ..ProtocolFile::default()
+        })
+    }
+    /// Converts the given protocol to Autoprotocol, naming buffers as configured for the given
+    /// hardware and giving perfusions as volumes where the pump's flow rate is calibrated.
+    ///
+    /// Repeated steps are written out once for each pass (so protocols too long to run can't be
+    /// converted either). Steps other than perfusions, temperature holds, and switching pumps
+    /// can't be converted.
+    pub fn from_protocol(
+        protocol: &Protocol,
+        hardware: &Hardware,
+    ) -> Result<Self, AutoprotocolError> {
+        // Each step is written as at most as many instructions as it expands to actions.
+        if protocol.expanded_len() > MAX_ACTIONS {
+            return Err(AutoprotocolError::TooLong);
+        }
+        let mut exporter = Exporter {
+            hardware,
+            pump: 0,
+            buffer: None,
+            last: protocol.steps.len().saturating_sub(1),
+            instructions: Vec::new(),
+        };
+        for (index, step) in protocol.steps.iter().enumerate() {
+            exporter.export(index, step)?;
+        }
+        let mut refs = BTreeMap::new();
+        refs.insert(SAMPLE.into(), Value::Object(Default::default()));
+        Ok(Self {
+            refs,
+            instructions: exporter.instructions,
+        })
+    }
+}
+
+/// Converts steps to instructions in order, keeping track of the pump in use and the last buffer
+/// dispensed.
+struct Exporter<'a> {
+    hardware: &'a Hardware,
+    pump: PumpId,
+    buffer: Option<MotorId>,
+    /// The index of the last step.
+    last: usize,
+    instructions: Vec<Instruction>,
+}
+
+impl Exporter<'_> {
+    /// Converts the given step (the protocol's `index`th, or part of it for repeated steps).
+    fn export(&mut self, index: usize, step: &Step) -> Result<(), AutoprotocolError> {
+        let unsupported = AutoprotocolError::Unsupported { step: index };
+        match step {
+            Step::Perfuse(buffer, Some(duration)) => {
+                let seconds = duration.as_secs_f64();
+                let rate = self.hardware.flow_rates.get(self.pump).cloned().flatten();
+                match rate {
+                    Some(rate) => {
+                        let volume = rate.volume_in(Time::from_secs(seconds));
+                        self.dispense(*buffer, volume.millilitres())
+                    }
+                    None => {
+                        self.dispense(*buffer, 0.0);
+                        self.incubate("ambient".into(), seconds);
+                    }
+                }
+            }
+            // The protocol ends by perfusing with the last buffer dispensed until stopped.
+            Step::Perfuse(buffer, None) if index == self.last => {
+                if self.buffer != Some(*buffer) {
+                    self.dispense(*buffer, 0.0);
+                }
+            }
+            Step::UsePump(pump) => self.pump = *pump,
+            Step::HoldTemperature {
+                target, duration, ..
+            } => {
+                let location = location(*target).ok_or(unsupported)?;
+                self.incubate(location, duration.as_secs_f64());
+            }
+            // Hooks have no counterpart in Autoprotocol, so only steps without any are converted.
+            Step::Hooked { step, begin, end } if begin.is_empty() && end.is_empty() => {
+                self.export(index, step)?
+            }
+            // Overrun policies govern how strictly this system keeps to a step's timing rather
+            // than the step itself, so they're left out.
+            Step::Timed { step, .. } => self.export(index, step)?,
+            Step::Repeat { count, steps } => {
+                for _ in 0..*count {
+                    for step in steps {
+                        self.export(index, step)?;
+                    }
+                }
+            }
+            Step::Perfuse(..)
+            | Step::PerfusePrompt(..)
+            | Step::Gradient { .. }
+            | Step::Mix { .. }
+            | Step::SetPosition { .. }
+            | Step::WaitUntil { .. }
+            | Step::IfElse { .. }
+            | Step::Hooked { .. } => return Err(unsupported),
+        }
+        Ok(())
+    }
+    /// Perfuses with the given buffer until the given volume (in mL) has been pumped.
+    fn dispense(&mut self, buffer: MotorId, volume: f64) {
+        let reagent = self
+            .hardware
+            .buffers
+            .iter()
+            .find(|(_, &valve)| valve == buffer)
+            .map_or_else(|| buffer.to_string(), |(name, _)| name.clone());
+        self.buffer = Some(buffer);
+        self.instructions.push(Instruction::Dispense {
+            object: SAMPLE.into(),
+            reagent,
+            columns: vec![Column {
+                column: 0,
+                volume: format!("{}:milliliter", volume),
+            }],
+        });
+    }
+    /// Holds the temperature of the given location for the given number of seconds.
+    fn incubate(&mut self, location: String, seconds: f64) {
+        self.instructions.push(Instruction::Incubate {
+            object: SAMPLE.into(),
+            location,
+            duration: format!("{}:second", seconds),
+            shaking: false,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quantity::FlowRate;
+    fn hardware() -> Hardware {
+        Hardware {
+            positions: vec![vec![]; 4],
+            pumps: 2,
+            thermostat: true,
+            buffers: vec![("PBS".into(), 0), ("1% SDS".into(), 1)]
+                .into_iter()
+                .collect(),
+            flow_rates: vec![Some(FlowRate::from_millilitres_per_minute(100.0)), None],
+            ..Hardware::default()
+        }
+    }
+    #[test]
+    fn imports_instructions() {
+        let autoprotocol: Autoprotocol = serde_json::from_str(
+            r#"{
+                "refs": {"heart": {}},
+                "instructions": [
+                    {"op": "dispense", "object": "heart", "reagent": "PBS",
+                     "columns": [{"column": 0, "volume": "50:milliliter"}]},
+                    {"op": "incubate", "object": "heart", "where": "warm_37",
+                     "duration": "30:minute"},
+                    {"op": "dispense", "object": "heart", "reagent": "2",
+                     "columns": [{"column": 0, "volume": "0:microliter"}]},
+                    {"op": "incubate", "object": "heart", "where": "ambient",
+                     "duration": "1:hour", "shaking": false}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let protocol = autoprotocol
+            .to_file()
+            .unwrap()
+            .resolve(&hardware())
+            .unwrap();
+        assert_eq!(
+            protocol.steps,
+            vec![
+                Step::Perfuse(0, Some(Duration::from_secs(30))),
+                Step::HoldTemperature {
+                    target: 37.0,
+                    tolerance: TOLERANCE,
+                    duration: Duration::from_secs(1800),
+                },
+                Step::Perfuse(2, Some(Duration::from_secs(3600))),
+                Step::Perfuse(2, None),
+            ]
+        );
+        let mut invalid = autoprotocol.clone();
+        invalid.instructions.remove(0);
+        invalid.instructions.swap(1, 2);
+        assert_eq!(
+            invalid.to_file(),
+            Err(AutoprotocolError::NoBuffer { instruction: 1 })
+        );
+        let unknown: Autoprotocol = serde_json::from_str(
+            r#"{"instructions": [{"op": "incubate", "object": "heart", "where": "warm",
+                "duration": "1:minute"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            unknown.to_file(),
+            Err(AutoprotocolError::UnknownLocation {
+                instruction: 0,
+                location: "warm".into(),
+            })
+        );
+    }
+    #[test]
+    fn exports_protocols() {
+        let protocol = Protocol {
+            steps: vec![
+                Step::Repeat {
+                    count: 2,
+                    steps: vec![Step::Perfuse(0, Some(Duration::from_secs(60)))],
+                },
+                Step::UsePump(1),
+                Step::HoldTemperature {
+                    target: 4.0,
+                    tolerance: 1.0,
+                    duration: Duration::from_secs(60),
+                },
+                Step::Perfuse(1, Some(Duration::from_secs(120))),
+                Step::Perfuse(2, None),
+            ],
+        };
+        let exported = Autoprotocol::from_protocol(&protocol, &hardware()).unwrap();
+        let ops = exported
+            .instructions
+            .iter()
+            .map(|instruction| match instruction {
+                Instruction::Dispense {
+                    reagent, columns, ..
+                } => format!("dispense {} {}", reagent, columns[0].volume),
+                Instruction::Incubate {
+                    location, duration, ..
+                } => format!("incubate {} {}", location, duration),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ops,
+            vec![
+                "dispense PBS 100:milliliter",
+                "dispense PBS 100:milliliter",
+                "incubate cold_4 60:second",
+                "dispense 1% SDS 0:milliliter",
+                "incubate ambient 120:second",
+                "dispense 2 0:milliliter",
+            ]
+        );
+        let imported = exported.to_file().unwrap().resolve(&hardware()).unwrap();
+        assert_eq!(imported.steps.len(), 5);
+        assert_eq!(imported.steps[4], Step::Perfuse(2, None));
+        let position = Protocol::with_step(Step::SetPosition {
+            motor: 1,
+            position: "bypass".into(),
+        });
+        assert_eq!(
+            Autoprotocol::from_protocol(&position, &hardware()),
+            Err(AutoprotocolError::Unsupported { step: 0 })
+        );
+        let endless = Protocol::with_step(Step::Repeat {
+            count: usize::MAX,
+            steps: vec![Step::Perfuse(0, Some(Duration::from_secs(60)))],
+        });
+        assert_eq!(
+            Autoprotocol::from_protocol(&endless, &hardware()),
+            Err(AutoprotocolError::TooLong)
+        );
+    }
+}
@@ -0,0 +1,847 @@
+//! Reading protocols from files.
+//!
+//! Protocol files are written in TOML or JSON (going by their extension), so that protocols can be
+//! kept under version control alongside the experiments they belong to. Each perfusion is written
+//! as a table naming the buffer (by its configured name or its number) and how long to perfuse
+//! for, either as a duration or as a volume to pump, each with its unit (see [`quantity`]) or as a
+//! bare number of seconds or mL:
+//!
+//! ```toml
+//! name = "Decellularization"
+//!
+//! [[steps]]
+//! name = "Rinse"
+//! buffer = "PBS"
+//! duration = "10min"
+//!
+//! [[steps]]
+//! buffer = "1% SDS"
+//! volume = 500 # mL
+//! notes = "Check for leaks after the first few minutes."
+//!
+//! [[steps]]
+//! usepump = 1
+//!
+//! [[steps]]
+//! repeat = 3 # wash cycles
+//! steps = [{ buffer = "PBS", duration = "5min" }, { buffer = "water", duration = "5min" }]
+//!
+//! [[steps]]
+//! buffer = "water" # without a duration or volume, until stopped
+//! ```
+//!
+//! Steps other than perfusions (e.g. `usepump` or `holdtemperature`) are written as they are in the
+//! HTTP API.
+//!
+//! Perfusions may fire hooks as they begin and once they've ended, e.g. to have the operator load
+//! the sample before perfusing:
+//!
+//! ```toml
+//! [[steps]]
+//! buffer = "PBS"
+//! duration = 600
+//! begin = [{ confirm = { subject = "Load the sample", message = "Continue once loaded." } }]
+//! end = [{ webhook = "rinsed" }]
+//! ```
+//!
+//! Perfusions may also be given their own overrun policy (see [`Overrun`]), in place of the
+//! configured one:
+//!
+//! ```toml
+//! [[steps]]
+//! buffer = "1% SDS"
+//! duration = 3600
+//! overrun = { tolerance = { secs = 120, nanos = 0 }, policy = "abort" }
+//! ```
+//!
+//! A protocol file may also serve as a template for many experiments by declaring parameters, each
+//! of which is given a value (or left at its default) when the protocol is run. Parameters are
+//! referred to as `$name` wherever a buffer, duration, volume, or repeat count is expected:
+//!
+//! ```toml
+//! [parameters.soak]
+//! kind = "duration" # or "volume", "buffer", or "count"
+//! description = "How long to perfuse with detergent."
+//! default = "1h"
+//! min = 600 # s
+//! max = 14400
+//!
+//! [[steps]]
+//! buffer = "1% SDS"
+//! duration = "$soak"
+//! ```
+//!
+//! [`Overrun`]: struct.Overrun.html
+//! [`quantity`]: quantity/index.html
+use std::{collections::BTreeMap, error, fmt, fs, io, path::Path};
+
+use crate::{
+    migrate::{self, UnknownVersion, VERSION},
+    quantity::{Quantity, QuantityError, Time, Volume},
+    Hardware, Hook, MotorId, Overrun, Protocol, PumpId, Step,
+};
+
+/// The values given for a protocol file's parameters, by name.
+pub type Bindings = BTreeMap<String, ParameterValue>;
+
+/// A protocol as written in a protocol file.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ProtocolFile {
+    /// The version of the protocol format the file was written in (see [`PROTOCOL_VERSION`]).
+    ///
+    /// Files without one are taken to be version 1, and files written in older versions are
+    /// upgraded as they're read.
+    ///
+    /// [`PROTOCOL_VERSION`]: constant.PROTOCOL_VERSION.html
+    #[serde(default = "migrate::unversioned")]
+    pub version: u32,
+    /// A human-readable name for the protocol.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// A description of the protocol (e.g. what it's for).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The parameters the protocol takes, by name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub parameters: BTreeMap<String, Parameter>,
+    /// The steps of the protocol.
+    pub steps: Vec<FileStep>,
+}
+
+impl Default for ProtocolFile {
+    fn default() -> Self {
+        Self {
+            version: VERSION,
+            name: None,
+            description: None,
+            parameters: BTreeMap::new(),
+            steps: Vec::new(),
+        }
+    }
+}
+
+/// A step in a protocol file.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum FileStep {
+    /// A perfusion with the given buffer.
+    Perfusion(Perfusion),
+    /// Steps to be run several times over.
+    Repeat(Repetition),
+    /// Any other step, as written in the HTTP API.
+    Step(Step),
+}
+
+/// A perfusion in a protocol file.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Perfusion {
+    /// A human-readable name for the step.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The buffer to perfuse with.
+    pub buffer: BufferRef,
+    /// How long to perfuse for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<Amount<Time>>,
+    /// How much to pump (which requires the pump's flow rate to be calibrated).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub volume: Option<Amount<Volume>>,
+    /// Notes for whoever's running the protocol.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// The hooks fired before the perfusion begins (see [`Hook`]).
+    ///
+    /// [`Hook`]: enum.Hook.html
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub begin: Vec<Hook>,
+    /// The hooks fired once the perfusion has ended.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub end: Vec<Hook>,
+    /// How long the perfusion may overrun, and what to do if it runs longer (see [`Overrun`]), if
+    /// not as configured.
+    ///
+    /// [`Overrun`]: struct.Overrun.html
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub overrun: Option<Overrun>,
+}
+
+/// Steps to be run several times over in a protocol file (see [`Step::Repeat`]).
+///
+/// [`Step::Repeat`]: enum.Step.html#variant.Repeat
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Repetition {
+    /// How many times to run the steps.
+    pub repeat: Amount<usize>,
+    /// The steps to run.
+    pub steps: Vec<FileStep>,
+}
+
+/// Refers to a buffer in a protocol file.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum BufferRef {
+    /// The buffer with the given number.
+    Number(MotorId),
+    /// The buffer with the given name (as configured), or the parameter with the given name if it
+    /// begins with `$`.
+    Name(String),
+}
+
+/// A number in a protocol file.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Amount<T> {
+    /// The number itself.
+    Value(T),
+    /// A reference to a parameter (written `$name`).
+    Parameter(String),
+}
+
+/// A parameter declared by a protocol file.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Parameter {
+    /// What the parameter stands for.
+    pub kind: ParameterKind,
+    /// A description of the parameter, for whoever's running the protocol.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The value used if none is given (without which a value must be given).
+    ///
+    /// Durations and volumes may be given with their units (e.g. `"10min"`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<ParameterValue>,
+    /// The smallest value allowed (for durations, volumes, and counts), in seconds or mL.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    /// The largest value allowed (for durations, volumes, and counts), in seconds or mL.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+}
+
+/// What a parameter stands for.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParameterKind {
+    /// A duration, in seconds.
+    Duration,
+    /// A volume, in mL.
+    Volume,
+    /// A buffer, by name or number.
+    Buffer,
+    /// How many times to repeat steps.
+    Count,
+}
+
+impl fmt::Display for ParameterKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Duration => write!(f, "duration (in s)"),
+            Self::Volume => write!(f, "volume (in mL)"),
+            Self::Buffer => write!(f, "buffer"),
+            Self::Count => write!(f, "count"),
+        }
+    }
+}
+
+/// The value of a parameter.
+///
+/// Numbers may also be given as text (e.g. as typed into a form).
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum ParameterValue {
+    /// A number.
+    Number(f64),
+    /// Anything else (e.g. a buffer's name).
+    Text(String),
+}
+
+impl ParameterValue {
+    /// The value as a number, if it is one.
+    pub fn number(&self) -> Option<f64> {
+        match self {
+            Self::Number(number) => Some(*number),
+            Self::Text(text) => text.trim().parse().ok(),
+        }
+    }
+    /// The value as a number of the given kind (in seconds for durations, or mL for volumes),
+    /// where durations and volumes may be written with their units (e.g. `"10min"`).
+    pub fn amount(&self, kind: ParameterKind) -> Option<f64> {
+        match (self, kind) {
+            (Self::Text(text), ParameterKind::Duration) => text.parse().ok().map(Time::get),
+            (Self::Text(text), ParameterKind::Volume) => text.parse().ok().map(Volume::get),
+            _ => self.number(),
+        }
+    }
+}
+
+impl fmt::Display for ParameterValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Number(number) => write!(f, "{}", number),
+            Self::Text(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+impl Parameter {
+    /// Whether the given value is allowed.
+    pub fn accepts(&self, value: &ParameterValue) -> bool {
+        let number = match (self.kind, value.amount(self.kind)) {
+            (ParameterKind::Buffer, Some(number)) => return number >= 0.0 && number.fract() == 0.0,
+            (ParameterKind::Buffer, None) => return true,
+            (_, Some(number)) => number,
+            (_, None) => return false,
+        };
+        let whole = self.kind != ParameterKind::Count || number.fract() == 0.0;
+        number.is_finite()
+            && number >= 0.0
+            && whole
+            && self.min.map_or(true, |min| number >= min)
+            && self.max.map_or(true, |max| number <= max)
+    }
+    /// Describes the values allowed (e.g. "a duration (in s) from 60 to 600").
+    pub fn expected(&self) -> String {
+        let range = match (self.min, self.max) {
+            (Some(min), Some(max)) => format!(" from {} to {}", min, max),
+            (Some(min), None) => format!(" of at least {}", min),
+            (None, Some(max)) => format!(" of at most {}", max),
+            (None, None) => String::new(),
+        };
+        match self.kind {
+            ParameterKind::Buffer => "a buffer's name or number".into(),
+            kind => format!("a {}{}", kind, range),
+        }
+    }
+}
+
+/// An error encountered in reading a protocol file.
+#[derive(Debug)]
+pub enum FileError {
+    /// The file could not be read.
+    Io(io::Error),
+    /// The file isn't a valid TOML protocol.
+    Toml(toml::de::Error),
+    /// The file isn't a valid JSON protocol.
+    Json(serde_json::Error),
+    /// The file was written in a newer version of the protocol format.
+    Version(UnknownVersion),
+    /// The file was written in an older version of the protocol format, and isn't a valid
+    /// protocol once upgraded.
+    Upgrade(serde_json::Error),
+    /// A step names a buffer which isn't configured.
+    NoSuchBuffer {
+        /// The step in question.
+        step: usize,
+        /// The name of the buffer.
+        name: String,
+    },
+    /// A step gives both a duration and a volume.
+    Ambiguous {
+        /// The step in question.
+        step: usize,
+    },
+    /// A step's duration or volume is negative or not a number.
+    InvalidAmount {
+        /// The step in question.
+        step: usize,
+    },
+    /// A step's duration or volume is written with a unit which isn't understood (or isn't of the
+    /// right kind, e.g. a volume given as a duration).
+    Unit {
+        /// The step in question.
+        step: usize,
+        /// The problem with the unit.
+        error: QuantityError,
+    },
+    /// A step gives a volume, but the pump's flow rate isn't calibrated.
+    NoFlowRate {
+        /// The step in question.
+        step: usize,
+        /// The pump in question.
+        pump: PumpId,
+    },
+    /// A step refers to a parameter which isn't declared (or the reference lacks its `$`).
+    NoSuchParameter {
+        /// The step in question.
+        step: usize,
+        /// The reference in question.
+        name: String,
+    },
+    /// A step refers to a parameter of the wrong kind (e.g. a buffer where a duration belongs).
+    MismatchedParameter {
+        /// The step in question.
+        step: usize,
+        /// The name of the parameter.
+        name: String,
+        /// The kind expected.
+        kind: ParameterKind,
+    },
+    /// A value was given for a parameter which isn't declared.
+    UnknownParameter(String),
+    /// No value was given for a parameter without a default.
+    MissingParameter(String),
+    /// The value of a parameter isn't allowed.
+    InvalidParameter {
+        /// The name of the parameter.
+        name: String,
+        /// The values allowed (see [`Parameter::expected`]).
+        ///
+        /// [`Parameter::expected`]: struct.Parameter.html#method.expected
+        expected: String,
+    },
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(_) => write!(f, "Failed to read the protocol"),
+            Self::Toml(_) => write!(f, "Failed to parse the protocol as TOML"),
+            Self::Json(_) => write!(f, "Failed to parse the protocol as JSON"),
+            Self::Version(_) => write!(f, "Failed to read the protocol version"),
+            Self::Upgrade(_) => write!(f, "Failed to upgrade the protocol"),
+            Self::NoSuchBuffer { step, name } => {
+                write!(f, "step {}: no buffer named \"{}\"", step, name)
+            }
+            Self::Ambiguous { step } => {
+                write!(f, "step {}: give either a duration or a volume", step)
+            }
+            Self::InvalidAmount { step } => write!(f, "step {}: invalid duration or volume", step),
+            Self::Unit { step, .. } => write!(f, "step {}: invalid quantity", step),
+            Self::NoFlowRate { step, pump } => write!(
+                f,
+                "step {}: pump {} has no calibrated flow rate to pump a volume with",
+                step, pump
+            ),
+            Self::NoSuchParameter { step, name } => {
+                write!(f, "step {}: no parameter named \"{}\"", step, name)
+            }
+            Self::MismatchedParameter { step, name, kind } => {
+                write!(f, "step {}: parameter {} isn't a {}", step, name, kind)
+            }
+            Self::UnknownParameter(name) => write!(f, "no parameter named \"{}\"", name),
+            Self::MissingParameter(name) => write!(f, "parameter {} needs a value", name),
+            Self::InvalidParameter { name, expected } => {
+                write!(f, "parameter {} should be {}", name, expected)
+            }
+        }
+    }
+}
+
+impl error::Error for FileError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Toml(err) => Some(err),
+            Self::Json(err) | Self::Upgrade(err) => Some(err),
+            Self::Version(err) => Some(err),
+            Self::Unit { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for FileError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl ProtocolFile {
+    /// Reads the protocol file at the given path, as JSON if its extension is `.json` (or else as
+    /// TOML).
+    ///
+    /// Files written in older versions of the protocol format are upgraded to the current version
+    /// (see [`PROTOCOL_VERSION`]).
+    ///
+    /// [`PROTOCOL_VERSION`]: constant.PROTOCOL_VERSION.html
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, FileError> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)?;
+        let json = path.extension().and_then(|extension| extension.to_str()) == Some("json");
+        let mut document: serde_json::Value = if json {
+            serde_json::from_str(&contents).map_err(FileError::Json)?
+        } else {
+            toml::from_str(&contents).map_err(FileError::Toml)?
+        };
+        if migrate::version(&document) == VERSION {
+            // Parsed again as written, for errors pointing at the offending line.
+            return if json {
+                serde_json::from_str(&contents).map_err(FileError::Json)
+            } else {
+                toml::from_str(&contents).map_err(FileError::Toml)
+            };
+        }
+        migrate::migrate(&mut document, "steps").map_err(FileError::Version)?;
+        serde_json::from_value(document).map_err(FileError::Upgrade)
+    }
+    /// Checks the given values for the file's parameters, filling in defaults for any not given.
+    pub fn bind(&self, values: &Bindings) -> Result<Bindings, FileError> {
+        if let Some(name) = values
+            .keys()
+            .find(|name| !self.parameters.contains_key(*name))
+        {
+            return Err(FileError::UnknownParameter(name.clone()));
+        }
+        let mut bound = Bindings::new();
+        for (name, parameter) in &self.parameters {
+            let value = values
+                .get(name)
+                .or_else(|| parameter.default.as_ref())
+                .ok_or_else(|| FileError::MissingParameter(name.clone()))?;
+            if !parameter.accepts(value) {
+                return Err(FileError::InvalidParameter {
+                    name: name.clone(),
+                    expected: parameter.expected(),
+                });
+            }
+            bound.insert(name.clone(), value.clone());
+        }
+        Ok(bound)
+    }
+    /// Converts the file's steps to a protocol for the given hardware, looking up buffers by name
+    /// and converting volumes to durations at the flow rate of the pump in use.
+    ///
+    /// Parameters are left at their defaults (see [`resolve_with`](#method.resolve_with)). The
+    /// protocol isn't otherwise checked (see [`Protocol::check`]).
+    ///
+    /// [`Protocol::check`]: struct.Protocol.html#method.check
+    pub fn resolve(&self, hardware: &Hardware) -> Result<Protocol, FileError> {
+        self.resolve_with(hardware, &Bindings::new())
+    }
+    /// Converts the file's steps to a protocol for the given hardware (as [`resolve`] does), with
+    /// the given values for its parameters (see [`bind`]).
+    ///
+    /// [`resolve`]: #method.resolve
+    /// [`bind`]: #method.bind
+    pub fn resolve_with(
+        &self,
+        hardware: &Hardware,
+        values: &Bindings,
+    ) -> Result<Protocol, FileError> {
+        let mut resolver = Resolver {
+            hardware,
+            parameters: &self.parameters,
+            values: self.bind(values)?,
+            pump: 0,
+        };
+        let steps = self
+            .steps
+            .iter()
+            .enumerate()
+            .map(|(index, step)| resolver.resolve(index, step))
+            .collect::<Result<_, _>>()?;
+        Ok(Protocol { steps })
+    }
+}
+
+/// Converts steps in order, keeping track of the pump in use.
+struct Resolver<'a> {
+    hardware: &'a Hardware,
+    parameters: &'a BTreeMap<String, Parameter>,
+    /// The value of every parameter.
+    values: Bindings,
+    pump: PumpId,
+}
+
+impl Resolver<'_> {
+    /// Converts the given step (the protocol's `step`th, or part of it for repeated steps) to a
+    /// protocol step.
+    fn resolve(&mut self, step: usize, spec: &FileStep) -> Result<Step, FileError> {
+        let resolved = match spec {
+            FileStep::Perfusion(perfusion) => self.perfusion(step, perfusion)?,
+            FileStep::Repeat(repetition) => Step::Repeat {
+                count: match &repetition.repeat {
+                    Amount::Value(count) => *count,
+                    Amount::Parameter(name) => {
+                        let count = self.number(step, name, ParameterKind::Count)?;
+                        count as usize
+                    }
+                },
+                steps: repetition
+                    .steps
+                    .iter()
+                    .map(|inner| self.resolve(step, inner))
+                    .collect::<Result<_, _>>()?,
+            },
+            FileStep::Step(step) => step.clone(),
+        };
+        if let Step::UsePump(next) = resolved {
+            self.pump = next;
+        }
+        Ok(resolved)
+    }
+    /// The value of the parameter the given reference (`$name`) refers to, which should be of the
+    /// given kind.
+    fn parameter(
+        &self,
+        step: usize,
+        reference: &str,
+        kind: ParameterKind,
+    ) -> Result<&ParameterValue, FileError> {
+        let missing = || FileError::NoSuchParameter {
+            step,
+            name: reference.into(),
+        };
+        let name = reference.strip_prefix('$').ok_or_else(missing)?;
+        match self.parameters.get(name) {
+            Some(parameter) if parameter.kind != kind => Err(FileError::MismatchedParameter {
+                step,
+                name: name.into(),
+                kind,
+            }),
+            Some(_) => self.values.get(name).ok_or_else(missing),
+            None => Err(missing()),
+        }
+    }
+    /// The number the given reference refers to, which should be a parameter of the given kind.
+    fn number(&self, step: usize, reference: &str, kind: ParameterKind) -> Result<f64, FileError> {
+        self.parameter(step, reference, kind)?
+            .amount(kind)
+            .ok_or(FileError::InvalidAmount { step })
+    }
+    /// The given amount, which (if it refers to a parameter) should be of the given kind.
+    fn amount<T: Quantity>(
+        &self,
+        step: usize,
+        amount: &Option<Amount<T>>,
+        kind: ParameterKind,
+    ) -> Result<Option<T>, FileError> {
+        match amount {
+            Some(Amount::Value(value)) => Ok(Some(*value)),
+            // Anything but a reference was read as a parameter only for not being a quantity.
+            Some(Amount::Parameter(text)) if !text.starts_with('$') => text
+                .parse()
+                .map(Some)
+                .map_err(|error| FileError::Unit { step, error }),
+            Some(Amount::Parameter(reference)) => {
+                self.number(step, reference, kind).map(T::new).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+    /// The buffer the given reference (a name, or a parameter as `$name`) refers to.
+    fn buffer(&self, step: usize, reference: &str) -> Result<MotorId, FileError> {
+        if !reference.starts_with('$') {
+            return self.named(step, reference);
+        }
+        let value = self.parameter(step, reference, ParameterKind::Buffer)?;
+        match value.number() {
+            // Checked to be a whole number when bound.
+            Some(number) => Ok(number as MotorId),
+            None => self.named(step, &value.to_string()),
+        }
+    }
+    /// The buffer with the given name.
+    fn named(&self, step: usize, name: &str) -> Result<MotorId, FileError> {
+        self.hardware
+            .buffers
+            .iter()
+            .find(|(candidate, _)| candidate.eq_ignore_ascii_case(name))
+            .map(|(_, &buffer)| buffer)
+            .ok_or_else(|| FileError::NoSuchBuffer {
+                step,
+                name: name.into(),
+            })
+    }
+    /// Converts the given perfusion (the protocol's `step`th, or part of it) to a protocol step.
+    fn perfusion(&self, step: usize, perfusion: &Perfusion) -> Result<Step, FileError> {
+        let buffer = match &perfusion.buffer {
+            BufferRef::Number(buffer) => *buffer,
+            BufferRef::Name(name) => self.buffer(step, name)?,
+        };
+        let duration = self.amount(step, &perfusion.duration, ParameterKind::Duration)?;
+        let volume = self.amount(step, &perfusion.volume, ParameterKind::Volume)?;
+        let pump = self.pump;
+        let time = match (duration, volume) {
+            (Some(_), Some(_)) => return Err(FileError::Ambiguous { step }),
+            (Some(duration), None) => Some(duration),
+            (None, Some(volume)) => {
+                let rate = self
+                    .hardware
+                    .flow_rates
+                    .get(pump)
+                    .cloned()
+                    .flatten()
+                    .ok_or(FileError::NoFlowRate { step, pump })?;
+                Some(rate.time_to_pump(volume))
+            }
+            (None, None) => None,
+        };
+        let perfuse = match time.map(Time::to_duration) {
+            Some(None) => return Err(FileError::InvalidAmount { step }),
+            Some(duration) => Step::Perfuse(buffer, duration),
+            None => Step::Perfuse(buffer, None),
+        };
+        let perfuse = match perfusion.overrun {
+            Some(overrun) => Step::Timed {
+                step: Box::new(perfuse),
+                overrun,
+            },
+            None => perfuse,
+        };
+        if perfusion.begin.is_empty() && perfusion.end.is_empty() {
+            return Ok(perfuse);
+        }
+        Ok(Step::Hooked {
+            step: Box::new(perfuse),
+            begin: perfusion.begin.clone(),
+            end: perfusion.end.clone(),
+        })
+    }
+}
+
+impl Protocol {
+    /// Reads the protocol file at the given path (see [`ProtocolFile`]) for the given hardware.
+    ///
+    /// [`ProtocolFile`]: struct.ProtocolFile.html
+    pub fn from_path(path: impl AsRef<Path>, hardware: &Hardware) -> Result<Self, FileError> {
+        ProtocolFile::from_path(path)?.resolve(hardware)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quantity::FlowRate;
+    use std::time::Duration;
+    fn hardware() -> Hardware {
+        Hardware {
+            positions: vec![vec![]; 4],
+            pumps: 2,
+            thermostat: false,
+            buffers: vec![("PBS".into(), 0), ("1% SDS".into(), 1)]
+                .into_iter()
+                .collect(),
+            flow_rates: vec![Some(FlowRate::from_millilitres_per_minute(100.0)), None],
+            sensors: vec![],
+        }
+    }
+    #[test]
+    fn resolves_steps() {
+        let file: ProtocolFile = toml::from_str(
+            r#"
+            name = "Decellularization"
+            [[steps]]
+            name = "Rinse"
+            buffer = "pbs"
+            duration = "10min"
+            [[steps]]
+            buffer = "1% SDS"
+            volume = 500
+            notes = "Check for leaks."
+            [[steps]]
+            usepump = 1
+            [[steps]]
+            repeat = 2
+            steps = [{ buffer = "PBS", duration = 60 }, { buffer = 2, duration = "1 min" }]
+            [[steps]]
+            buffer = 2
+            "#,
+        )
+        .unwrap();
+        assert_eq!(file.name.as_deref(), Some("Decellularization"));
+        let protocol = file.resolve(&hardware()).unwrap();
+        assert_eq!(
+            protocol.steps,
+            vec![
+                Step::Perfuse(0, Some(Duration::from_secs(600))),
+                Step::Perfuse(1, Some(Duration::from_secs(300))),
+                Step::UsePump(1),
+                Step::Repeat {
+                    count: 2,
+                    steps: vec![
+                        Step::Perfuse(0, Some(Duration::from_secs(60))),
+                        Step::Perfuse(2, Some(Duration::from_secs(60))),
+                    ],
+                },
+                Step::Perfuse(2, None),
+            ]
+        );
+    }
+    #[test]
+    fn reports_errors() {
+        let file: ProtocolFile = serde_json::from_str(
+            r#"{"steps": [{"usepump": 1}, {"buffer": "water"}, {"buffer": 0, "volume": 5}]}"#,
+        )
+        .unwrap();
+        match file.resolve(&hardware()) {
+            Err(FileError::NoSuchBuffer { step: 1, name }) => assert_eq!(name, "water"),
+            other => panic!("Expected a missing buffer, got {:?}", other),
+        }
+        let mut file = file;
+        file.steps.remove(1);
+        match file.resolve(&hardware()) {
+            Err(FileError::NoFlowRate { step: 1, pump: 1 }) => {}
+            other => panic!("Expected a missing flow rate, got {:?}", other),
+        }
+        file.steps[1] = serde_json::from_str(r#"{"buffer": 0, "duration": "5ml"}"#).unwrap();
+        match file.resolve(&hardware()) {
+            Err(FileError::Unit { step: 1, error }) => assert_eq!(error.text, "5ml"),
+            other => panic!("Expected a volume given as a duration, got {:?}", other),
+        }
+    }
+    #[test]
+    fn binds_parameters() {
+        let file: ProtocolFile = toml::from_str(
+            r#"
+            [parameters.soak]
+            kind = "duration"
+            default = 600
+            min = 60
+            max = 3600
+            [parameters.detergent]
+            kind = "buffer"
+            [parameters.cycles]
+            kind = "count"
+            default = 2
+            [[steps]]
+            buffer = "$detergent"
+            duration = "$soak"
+            [[steps]]
+            repeat = "$cycles"
+            steps = [{ buffer = "PBS", duration = 60 }]
+            "#,
+        )
+        .unwrap();
+        match file.resolve(&hardware()) {
+            Err(FileError::MissingParameter(name)) => assert_eq!(name, "detergent"),
+            other => panic!("Expected a missing parameter, got {:?}", other),
+        }
+        let mut values = Bindings::new();
+        values.insert("detergent".into(), ParameterValue::Text("1% sds".into()));
+        values.insert("soak".into(), ParameterValue::Text("2min".into()));
+        let protocol = file.resolve_with(&hardware(), &values).unwrap();
+        assert_eq!(
+            protocol.steps,
+            vec![
+                Step::Perfuse(1, Some(Duration::from_secs(120))),
+                Step::Repeat {
+                    count: 2,
+                    steps: vec![Step::Perfuse(0, Some(Duration::from_secs(60)))],
+                },
+            ]
+        );
+        values.insert("soak".into(), ParameterValue::Number(10.0));
+        match file.resolve_with(&hardware(), &values) {
+            Err(FileError::InvalidParameter { name, .. }) => assert_eq!(name, "soak"),
+            other => panic!("Expected an invalid parameter, got {:?}", other),
+        }
+        values.insert("soak".into(), ParameterValue::Number(60.0));
+        values.insert("rinse".into(), ParameterValue::Number(60.0));
+        match file.bind(&values) {
+            Err(FileError::UnknownParameter(name)) => assert_eq!(name, "rinse"),
+            other => panic!("Expected an unknown parameter, got {:?}", other),
+        }
+        let mut file = file;
+        file.steps[0] = serde_json::from_str(r#"{"buffer": 0, "duration": "$cycles"}"#).unwrap();
+        file.parameters.get_mut("detergent").unwrap().default = Some(ParameterValue::Number(2.0));
+        match file.resolve(&hardware()) {
+            Err(FileError::MismatchedParameter { step: 0, name, .. }) => assert_eq!(name, "cycles"),
+            other => panic!("Expected a mismatched parameter, got {:?}", other),
+        }
+    }
+}
@@ -21,9 +21,38 @@
 /// Used to uniquely identify motors/valves.
 pub type MotorId = usize;
 
+/// Used to uniquely identify pumps.
+pub type PumpId = usize;
+
+#[cfg(feature = "files")]
+mod autoprotocol;
+#[cfg(feature = "files")]
+mod file;
+#[cfg(feature = "files")]
+mod migrate;
 mod program;
+pub mod quantity;
+mod validate;
 pub use self::program::{
-    Action, Notification, Program, Protocol, Step, ValidateError as ValidateProtocolError,
+    Action, Hook, Iteration, Notification, Overrun, OverrunPolicy, Predicate, Program, Protocol,
+    Sensor, Step, ValidateError as ValidateProtocolError, MAX_ACTIONS,
+};
+pub use self::validate::{has_buffer, Hardware, Issue as ProtocolIssue, Validation};
+
+#[cfg(feature = "files")]
+pub use self::autoprotocol::{
+    Autoprotocol, AutoprotocolError, Column as AutoprotocolColumn,
+    Instruction as AutoprotocolInstruction,
+};
+#[cfg(feature = "files")]
+pub use self::file::{
+    Amount, Bindings, BufferRef, FileError as ProtocolFileError, FileStep, Parameter,
+    ParameterKind, ParameterValue, Perfusion, ProtocolFile, Repetition,
+};
+#[cfg(feature = "files")]
+pub use self::migrate::{
+    migrate as migrate_protocol, version as protocol_version,
+    UnknownVersion as UnknownProtocolVersion, VERSION as PROTOCOL_VERSION,
 };
 
 #[cfg(feature = "use_serde")]
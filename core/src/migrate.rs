@@ -0,0 +1,139 @@
+//! Upgrading protocols written in older versions of the protocol format.
+//!
+//! Stored protocols and protocol files record the version of the format they were written in (see
+//! [`VERSION`]), where those without one are taken to be version 1. Whenever the step model changes
+//! in a way older protocols can't be read as (e.g. a step gains a field without a default), the
+//! version is bumped and a migration is added to [`MIGRATIONS`], upgrading each step from the
+//! previous version, so that older protocols are read as if they had been written in the current
+//! version.
+//!
+//! [`VERSION`]: constant.VERSION.html
+//! [`MIGRATIONS`]: constant.MIGRATIONS.html
+use std::{error, fmt};
+
+use serde_json::{Map, Value};
+
+/// The current version of the protocol format.
+pub const VERSION: u32 = 1;
+
+/// The version of documents without one.
+pub(crate) fn unversioned() -> u32 {
+    1
+}
+
+/// Upgrades a step (or, within a protocol file, a table of steps) from one version to the next.
+type Migration = fn(&mut Map<String, Value>);
+
+/// The migrations from each version to the next, where the first upgrades version 1 to version 2.
+const MIGRATIONS: &[Migration] = &[];
+
+/// A protocol was written in a version of the format newer than this version of deoxy knows.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct UnknownVersion(pub u32);
+
+impl fmt::Display for UnknownVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "protocol format version {} is newer than the latest supported ({})",
+            self.0, VERSION
+        )
+    }
+}
+
+impl error::Error for UnknownVersion {}
+
+/// The version of the format the given document (a stored protocol or protocol file) was written
+/// in, going by its `version` field.
+pub fn version(document: &Value) -> u32 {
+    document
+        .get("version")
+        .and_then(Value::as_u64)
+        .map_or_else(unversioned, |version| version as u32)
+}
+
+/// Upgrades the steps of the given document (a stored protocol or protocol file, as JSON) to the
+/// current version of the format, where the steps are found under the given key.
+///
+/// The document's version is set to the current version. Documents already in the current version
+/// are left as they are.
+pub fn migrate(document: &mut Value, key: &str) -> Result<(), UnknownVersion> {
+    upgrade(document, key, MIGRATIONS)
+}
+
+/// Upgrades the given document using the given migrations (see [`migrate`](fn.migrate.html)).
+fn upgrade(
+    document: &mut Value,
+    key: &str,
+    migrations: &[Migration],
+) -> Result<(), UnknownVersion> {
+    let current = migrations.len() as u32 + 1;
+    let version = version(document);
+    if version > current {
+        return Err(UnknownVersion(version));
+    }
+    if let Value::Object(object) = document {
+        for migration in migrations.iter().skip(version.saturating_sub(1) as usize) {
+            if let Some(steps) = object.get_mut(key) {
+                visit(steps, *migration);
+            }
+        }
+        object.insert("version".into(), current.into());
+    }
+    Ok(())
+}
+
+/// Calls the given migration on every table in the given steps, including those nested within
+/// steps (e.g. the steps of a repeat).
+fn visit(value: &mut Value, migration: Migration) {
+    match value {
+        Value::Array(values) => {
+            for value in values {
+                visit(value, migration);
+            }
+        }
+        Value::Object(object) => {
+            migration(object);
+            for value in object.values_mut() {
+                visit(value, migration);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    /// Gives temperature holds a tolerance, as if they once lacked one.
+    fn tolerance(step: &mut Map<String, Value>) {
+        if let Some(Value::Object(hold)) = step.get_mut("holdtemperature") {
+            hold.entry("tolerance").or_insert_with(|| json!(0.5));
+        }
+    }
+    #[test]
+    fn upgrades_steps() {
+        let hold = json!({"target": 37.0, "duration": {"secs": 60, "nanos": 0}});
+        let mut document = json!({
+            "name": "Warm",
+            "protocol": [{"repeat": {"count": 2, "steps": [{"holdtemperature": hold}]}}],
+        });
+        upgrade(&mut document, "protocol", &[tolerance]).unwrap();
+        assert_eq!(version(&document), 2);
+        let repeat = &document["protocol"][0]["repeat"];
+        assert_eq!(repeat["steps"][0]["holdtemperature"]["tolerance"], 0.5);
+        // Documents already in the current version aren't migrated again.
+        document["protocol"][0]["repeat"]["steps"][0]["holdtemperature"]["tolerance"] = json!(1.0);
+        upgrade(&mut document, "protocol", &[tolerance]).unwrap();
+        let repeat = &document["protocol"][0]["repeat"];
+        assert_eq!(repeat["steps"][0]["holdtemperature"]["tolerance"], 1.0);
+        assert_eq!(
+            upgrade(&mut json!({"version": 3}), "steps", &[tolerance]),
+            Err(UnknownVersion(3))
+        );
+        let mut current = json!({"steps": []});
+        migrate(&mut current, "steps").unwrap();
+        assert_eq!(current["version"], VERSION);
+    }
+}
@@ -1,10 +1,16 @@
 //! Utilities for scheduling actions.
-use std::time::Duration;
+use std::{error, fmt, time::Duration};
 
-use crate::MotorId;
+use crate::{MotorId, PumpId};
+
+#[cfg(feature = "schema")]
+use schemars::{schema::RootSchema, JsonSchema};
+
+/// The most actions a protocol may expand to (repeats being written out once for each pass).
+pub const MAX_ACTIONS: usize = 1_000_000;
 
 /// Represents an error encountered while validating a protocol.
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
 #[cfg_attr(feature = "use_serde", serde(rename_all = "lowercase"))]
 pub enum ValidateError {
@@ -14,10 +20,124 @@ pub enum ValidateError {
     Last(Step),
     /// A perfusion has a duration of zero.
     ZeroDuration,
+    /// A repeat has no steps or is run zero times.
+    EmptyRepeat,
+    /// A conditional step has no steps in either branch.
+    EmptyBranch,
+    /// The protocol expands to more than [`MAX_ACTIONS`](constant.MAX_ACTIONS.html) actions
+    /// (usually because of a repeat with a very large count).
+    TooLong,
+}
+
+impl fmt::Display for ValidateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "the protocol has no steps"),
+            Self::Last(_) => write!(f, "the last step must be an indefinite perfusion"),
+            Self::ZeroDuration => write!(f, "a perfusion has a duration of zero"),
+            Self::EmptyRepeat => write!(f, "a repeat has no steps or is run zero times"),
+            Self::EmptyBranch => write!(f, "a conditional step has no steps in either branch"),
+            Self::TooLong => write!(
+                f,
+                "the protocol expands to more than {} actions",
+                MAX_ACTIONS
+            ),
+        }
+    }
+}
+
+impl error::Error for ValidateError {}
+
+/// A sensor whose readings are published (and which protocols can wait or branch on).
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "use_serde", serde(rename_all = "lowercase"))]
+pub enum Sensor {
+    /// The line pressure.
+    Pressure,
+    /// The volume measured by the flow sensor during the current step.
+    Flow,
+    /// The temperature of the chamber.
+    Temperature,
+}
+
+impl Sensor {
+    /// The unit of the sensor's readings.
+    pub fn unit(self) -> &'static str {
+        match self {
+            Self::Pressure => "kPa",
+            Self::Flow => "mL",
+            Self::Temperature => "°C",
+        }
+    }
+}
+
+impl fmt::Display for Sensor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Pressure => write!(f, "pressure"),
+            Self::Flow => write!(f, "flow"),
+            Self::Temperature => write!(f, "temperature"),
+        }
+    }
+}
+
+/// A test of a sensor's reading (in the sensor's unit; see [`Sensor::unit`]).
+///
+/// [`Sensor::unit`]: enum.Sensor.html#method.unit
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "use_serde", serde(rename_all = "lowercase"))]
+pub enum Predicate {
+    /// The reading is above the given value.
+    Above(f64),
+    /// The reading is below the given value.
+    Below(f64),
+    /// The reading is within the given tolerance of the given target.
+    Within {
+        /// The target value.
+        target: f64,
+        /// The largest tolerated deviation from the target.
+        tolerance: f64,
+    },
+}
+
+impl Predicate {
+    /// Whether the given reading satisfies the predicate.
+    pub fn holds(self, value: f64) -> bool {
+        match self {
+            Self::Above(threshold) => value > threshold,
+            Self::Below(threshold) => value < threshold,
+            Self::Within { target, tolerance } => (value - target).abs() <= tolerance,
+        }
+    }
+    /// Whether the predicate's numbers make sense (i.e. they're finite and the tolerance isn't
+    /// negative).
+    pub fn is_valid(self) -> bool {
+        match self {
+            Self::Above(threshold) | Self::Below(threshold) => threshold.is_finite(),
+            Self::Within { target, tolerance } => {
+                target.is_finite() && tolerance.is_finite() && tolerance >= 0.0
+            }
+        }
+    }
+}
+
+impl fmt::Display for Predicate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Above(threshold) => write!(f, "above {}", threshold),
+            Self::Below(threshold) => write!(f, "below {}", threshold),
+            Self::Within { target, tolerance } => write!(f, "within {} of {}", tolerance, target),
+        }
+    }
 }
 
 /// Encodes a notification to users.
 #[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Notification {
     /// The subject of the notification.
@@ -26,9 +146,95 @@ pub struct Notification {
     pub message: String,
 }
 
-/// Represents a high-level step to be taken in a protocol.
+/// Something done as a step begins or ends (see [`Step::Hooked`]).
+///
+/// [`Step::Hooked`]: enum.Step.html#variant.Hooked
 #[derive(Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "use_serde", serde(rename_all = "lowercase"))]
+pub enum Hook {
+    /// The user should be sent the given notification.
+    Notify(Notification),
+    /// The given event should be posted to the webhooks (e.g. to ping a chat channel).
+    Webhook(String),
+    /// The user should be sent the given notification, and the program should wait for them to
+    /// continue (e.g. once they've loaded a sample).
+    Confirm(Notification),
+}
+
+impl Hook {
+    /// Appends the actions firing this hook to the given list.
+    fn expand(&self, actions: &mut Vec<Action>) {
+        match self {
+            Hook::Notify(notification) => actions.push(Action::Notify(notification.clone())),
+            Hook::Webhook(event) => actions.push(Action::Hook(event.clone())),
+            Hook::Confirm(notification) => {
+                actions.push(Action::Notify(notification.clone()));
+                actions.push(Action::Hail);
+            }
+        }
+    }
+}
+
+/// What to do when a timed step runs longer than it should have.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "use_serde", serde(rename_all = "lowercase"))]
+pub enum OverrunPolicy {
+    /// Log the overrun and carry on.
+    Continue,
+    /// Report the overrun (to the run summary and the notifiers) and carry on.
+    Warn,
+    /// Report the overrun and halt execution.
+    Abort,
+}
+
+impl Default for OverrunPolicy {
+    fn default() -> Self {
+        Self::Continue
+    }
+}
+
+impl fmt::Display for OverrunPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Continue => write!(f, "continue"),
+            Self::Warn => write!(f, "warn"),
+            Self::Abort => write!(f, "abort"),
+        }
+    }
+}
+
+/// How long a timed step (a wait or a temperature hold) may run past its duration, and what to do
+/// if it runs longer (e.g. because the hardware was slow to respond or the program was paused).
+///
+/// For temperature holds, the time taken to reach the target counts toward the step.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "use_serde", serde(default))]
+pub struct Overrun {
+    /// How long past its duration the step may run.
+    pub tolerance: Duration,
+    /// What to do if the step runs longer.
+    pub policy: OverrunPolicy,
+}
+
+impl Default for Overrun {
+    fn default() -> Self {
+        Self {
+            tolerance: Duration::new(60, 0),
+            policy: OverrunPolicy::default(),
+        }
+    }
+}
+
+/// Represents a high-level step to be taken in a protocol.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 #[cfg_attr(feature = "use_serde", serde(rename_all = "lowercase"))]
 pub enum Step {
     /// The specified motor should fully perfuse the tissue for the given duration (or until
@@ -38,13 +244,331 @@ pub enum Step {
     /// the given message, await acknowledgement, wait for the specified duration, and then notify
     /// the user again.
     PerfusePrompt(MotorId, Notification, Duration, Notification),
+    /// The system should perfuse the tissue for the given duration, ramping from the first buffer
+    /// to the second (e.g. for a gentle solvent exchange), and then drain.
+    ///
+    /// The buffers are blended by time-slicing: in each period, the first buffer's valve is open
+    /// for a shrinking share of the time and the second's for the rest.
+    Gradient {
+        /// The buffer to begin with.
+        from: MotorId,
+        /// The buffer to end with.
+        to: MotorId,
+        /// How long the ramp takes.
+        duration: Duration,
+        /// How long each time slice lasts.
+        period: Duration,
+    },
+    /// The specified pump should be used for all subsequent steps.
+    UsePump(PumpId),
+    /// The sample should be brought to the target temperature (in °C) and held within the given
+    /// tolerance (in K) for the given duration.
+    ///
+    /// The temperature continues to be held during subsequent steps until another temperature is
+    /// requested or the program finishes.
+    HoldTemperature {
+        /// The target temperature, in °C.
+        target: f64,
+        /// The largest tolerated deviation from the target, in K.
+        tolerance: f64,
+        /// How long to hold the temperature once it has been reached.
+        duration: Duration,
+    },
+    /// The chamber should be agitated by running the pump forward and then backward (as when
+    /// draining) for the given durations, the given number of times over.
+    ///
+    /// This is run as a single step, rather than as many short perfusions and drains.
+    Mix {
+        /// How many times to run the pump forward and back.
+        cycles: usize,
+        /// How long to run the pump forward each time.
+        forward: Duration,
+        /// How long to run the pump backward each time.
+        backward: Duration,
+    },
+    /// The specified motor should be moved to the named position (as configured for the motor).
+    ///
+    /// This is useful for multi-port valves with positions beyond open, closed, and shut.
+    SetPosition {
+        /// The motor to move (motor 0 is the waste valve).
+        motor: MotorId,
+        /// The name of the position.
+        position: String,
+    },
+    /// The given steps should be run the given number of times (e.g. for wash cycles), one pass
+    /// after another.
+    Repeat {
+        /// How many times to run the steps.
+        count: usize,
+        /// The steps to run.
+        steps: Vec<Step>,
+    },
+    /// The program should wait until the given sensor's reading satisfies the given predicate.
+    ///
+    /// If the timeout (if any) passes first, the operator is notified and must continue the
+    /// program.
+    WaitUntil {
+        /// The sensor to read.
+        sensor: Sensor,
+        /// The test the reading must pass.
+        predicate: Predicate,
+        /// How long to wait before asking the operator.
+        timeout: Option<Duration>,
+    },
+    /// The given sensor should be read, running the first series of steps if its reading satisfies
+    /// the given predicate and the second otherwise.
+    IfElse {
+        /// The sensor to read.
+        sensor: Sensor,
+        /// The test the reading is put to.
+        predicate: Predicate,
+        /// The steps to run if the reading passes.
+        then: Vec<Step>,
+        /// The steps to run if it doesn't.
+        #[cfg_attr(feature = "use_serde", serde(default))]
+        otherwise: Vec<Step>,
+    },
+    /// The given step should be taken, firing the given hooks (in order) as it begins and once it
+    /// has ended.
+    ///
+    /// The last step of a protocol never ends, and so may only have hooks fired as it begins.
+    Hooked {
+        /// The step itself.
+        step: Box<Step>,
+        /// The hooks fired before the step begins.
+        #[cfg_attr(feature = "use_serde", serde(default))]
+        begin: Vec<Hook>,
+        /// The hooks fired once the step has ended.
+        #[cfg_attr(feature = "use_serde", serde(default))]
+        end: Vec<Hook>,
+    },
+    /// The given step should be taken, tolerating overruns of its timed actions as given (rather
+    /// than as configured).
+    ///
+    /// Since the last step of a protocol never ends, it can't be given its own overrun policy.
+    Timed {
+        /// The step itself.
+        step: Box<Step>,
+        /// How long the step may overrun, and what to do if it runs longer.
+        overrun: Overrun,
+    },
+}
+
+impl Step {
+    /// Calls the given function with this step and, for repeats, conditional steps, hooked steps,
+    /// and timed steps, each of the steps within.
+    fn visit<'a>(&'a self, f: &mut impl FnMut(&'a Step)) {
+        f(self);
+        let (first, second): (&[Step], &[Step]) = match self {
+            Step::Repeat { steps, .. } => (steps, &[]),
+            Step::IfElse {
+                then, otherwise, ..
+            } => (then, otherwise),
+            Step::Hooked { step, .. } | Step::Timed { step, .. } => {
+                (std::slice::from_ref(&**step), &[])
+            }
+            _ => return,
+        };
+        for step in first.iter().chain(second) {
+            step.visit(f);
+        }
+    }
+    /// Appends the actions taking this step to the given list, where `pass` is the pass through
+    /// the repeat (if any) this step belongs to.
+    fn expand(&self, pass: Option<Iteration>, actions: &mut Vec<Action>) {
+        match self {
+            &Step::Perfuse(motor, duration) => {
+                actions.push(Action::Perfuse(motor));
+                actions.push(duration.map(Action::Sleep).unwrap_or(Action::Hail));
+                actions.push(Action::Drain);
+            }
+            Step::PerfusePrompt(motor, begin, duration, end) => {
+                actions.push(Action::Perfuse(*motor));
+                actions.push(Action::Notify(begin.clone()));
+                actions.push(Action::Hail);
+                actions.push(Action::Sleep(*duration));
+                actions.push(Action::Notify(end.clone()));
+                actions.push(Action::Hail);
+                actions.push(Action::Drain);
+            }
+            &Step::Gradient {
+                from,
+                to,
+                duration,
+                period,
+            } => {
+                actions.push(Action::Gradient {
+                    from,
+                    to,
+                    duration,
+                    period,
+                });
+                actions.push(Action::Drain);
+            }
+            &Step::UsePump(pump) => actions.push(Action::UsePump(pump)),
+            &Step::HoldTemperature {
+                target,
+                tolerance,
+                duration,
+            } => actions.push(Action::HoldTemperature {
+                target,
+                tolerance,
+                duration,
+            }),
+            &Step::Mix {
+                cycles,
+                forward,
+                backward,
+            } => actions.push(Action::Mix {
+                cycles,
+                forward,
+                backward,
+            }),
+            Step::SetPosition { motor, position } => actions.push(Action::SetPosition {
+                motor: *motor,
+                position: position.clone(),
+            }),
+            &Step::Repeat { count, ref steps } => {
+                for iteration in 1..=count {
+                    let inner = Iteration { iteration, count };
+                    actions.push(Action::Iterate(Some(inner)));
+                    for step in steps {
+                        step.expand(Some(inner), actions);
+                    }
+                }
+                // The enclosing pass (if any) carries on.
+                actions.push(Action::Iterate(pass));
+            }
+            &Step::WaitUntil {
+                sensor,
+                predicate,
+                timeout,
+            } => actions.push(Action::WaitUntil {
+                sensor,
+                predicate,
+                timeout,
+            }),
+            &Step::IfElse {
+                sensor,
+                predicate,
+                ref then,
+                ref otherwise,
+            } => {
+                let mut taken = vec![];
+                for step in then {
+                    step.expand(pass, &mut taken);
+                }
+                let mut other = vec![];
+                for step in otherwise {
+                    step.expand(pass, &mut other);
+                }
+                // The first branch ends by skipping the second (if there is one).
+                if !other.is_empty() {
+                    taken.push(Action::Skip(other.len()));
+                }
+                actions.push(Action::Branch {
+                    sensor,
+                    predicate,
+                    skip: taken.len(),
+                });
+                actions.extend(taken);
+                actions.extend(other);
+            }
+            Step::Hooked { step, begin, end } => {
+                for hook in begin {
+                    hook.expand(actions);
+                }
+                step.expand(pass, actions);
+                for hook in end {
+                    hook.expand(actions);
+                }
+            }
+            Step::Timed { step, overrun } => {
+                actions.push(Action::Overrun(Some(*overrun)));
+                step.expand(pass, actions);
+                // Timed steps don't nest, so the configured policy applies again.
+                actions.push(Action::Overrun(None));
+            }
+        }
+    }
+    /// How many actions [`expand`](#method.expand) appends for this step (saturating rather than
+    /// overflowing, so that it can be checked before expanding anything).
+    fn expanded_len(&self) -> usize {
+        match self {
+            Step::Perfuse(..) => 3,
+            Step::PerfusePrompt(..) => 7,
+            Step::Gradient { .. } => 2,
+            Step::UsePump(_)
+            | Step::HoldTemperature { .. }
+            | Step::Mix { .. }
+            | Step::SetPosition { .. }
+            | Step::WaitUntil { .. } => 1,
+            Step::Repeat { count, steps } => {
+                let pass = steps.iter().fold(1, |len: usize, step| {
+                    len.saturating_add(step.expanded_len())
+                });
+                count.saturating_mul(pass).saturating_add(1)
+            }
+            Step::IfElse {
+                then, otherwise, ..
+            } => {
+                let then = then.iter().fold(0, |len: usize, step| {
+                    len.saturating_add(step.expanded_len())
+                });
+                let otherwise = otherwise.iter().fold(0, |len: usize, step| {
+                    len.saturating_add(step.expanded_len())
+                });
+                let skip = if otherwise > 0 { 1 } else { 0 };
+                then.saturating_add(otherwise).saturating_add(skip + 1)
+            }
+            Step::Hooked { step, begin, end } => {
+                let hooks = begin
+                    .iter()
+                    .chain(end)
+                    .map(|hook| match hook {
+                        Hook::Confirm(_) => 2,
+                        Hook::Notify(_) | Hook::Webhook(_) => 1,
+                    })
+                    .sum::<usize>();
+                step.expanded_len().saturating_add(hooks)
+            }
+            Step::Timed { step, .. } => step.expanded_len().saturating_add(2),
+        }
+    }
+    /// Whether this step perfuses until continued (as the last step of a protocol must).
+    fn is_bath(&self) -> bool {
+        match self {
+            Step::Perfuse(_, duration) => duration.is_none(),
+            Step::Hooked { step, end, .. } => end.is_empty() && step.is_bath(),
+            _ => false,
+        }
+    }
+}
+
+/// A pass through a repeated series of steps (see [`Step::Repeat`]).
+///
+/// [`Step::Repeat`]: enum.Step.html#variant.Repeat
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+pub struct Iteration {
+    /// The pass, counting from 1.
+    pub iteration: usize,
+    /// How many passes there are.
+    pub count: usize,
+}
+
+impl fmt::Display for Iteration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "iteration {} of {}", self.iteration, self.count)
+    }
 }
 
 /// A high-level description of a series of actions to be taken.
 ///
 /// This is what the end user will feed in (by way of a form).
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 #[cfg_attr(feature = "use_serde", serde(rename_all = "lowercase", transparent))]
 pub struct Protocol {
     /// The component steps of the protocol.
@@ -52,6 +576,14 @@ pub struct Protocol {
 }
 
 impl Protocol {
+    /// Describes the protocol file format as a JSON Schema, so that protocols can be checked (e.g.
+    /// by an editor or in CI) before they're run.
+    ///
+    /// This requires the `schema` feature.
+    #[cfg(feature = "schema")]
+    pub fn json_schema() -> RootSchema {
+        schemars::schema_for!(Protocol)
+    }
     /// Creates a single-step protocol.
     pub fn with_step(step: Step) -> Self {
         Self { steps: vec![step] }
@@ -65,69 +597,82 @@ impl Protocol {
     /// All protocols should end with a perfusion (in the final solution, usually water) for an
     /// unspecified duration (i.e. a bath). If this is not the case, something's wrong with the
     /// protocol and we should refuse to run it.
+    ///
+    /// Protocols expanding to more than [`MAX_ACTIONS`](constant.MAX_ACTIONS.html) actions are
+    /// refused too, since they'd have to be written out in full before they could be run.
     pub fn validate(&self) -> Result<(), ValidateError> {
-        let is_zero_perfusion = |step: &Step| {
-            if let Step::Perfuse(_, duration) = step {
-                if let Some(duration) = *duration {
-                    duration == Duration::new(0, 0)
-                } else {
-                    false
+        let mut zero_perfusion = false;
+        let mut empty_repeat = false;
+        let mut empty_branch = false;
+        for step in &self.steps {
+            step.visit(&mut |step| match step {
+                Step::Perfuse(_, Some(duration)) if *duration == Duration::new(0, 0) => {
+                    zero_perfusion = true
                 }
-            } else {
-                false
-            }
-        };
-        if self.steps.iter().any(is_zero_perfusion) {
+                Step::Repeat { count, steps } if *count == 0 || steps.is_empty() => {
+                    empty_repeat = true
+                }
+                Step::IfElse {
+                    then, otherwise, ..
+                } if then.is_empty() && otherwise.is_empty() => empty_branch = true,
+                _ => {}
+            });
+        }
+        if zero_perfusion {
             Err(ValidateError::ZeroDuration)
+        } else if empty_repeat {
+            Err(ValidateError::EmptyRepeat)
+        } else if empty_branch {
+            Err(ValidateError::EmptyBranch)
+        } else if self.expanded_len() > MAX_ACTIONS {
+            Err(ValidateError::TooLong)
         } else if let Some(last) = self.steps.last() {
-            match last {
-                Step::Perfuse(_, duration) => {
-                    if duration.is_none() {
-                        Ok(())
-                    } else {
-                        Err(ValidateError::Last(last.clone()))
-                    }
-                }
-                Step::PerfusePrompt(_, _, _, _) => Err(ValidateError::Last(last.clone())),
+            if last.is_bath() {
+                Ok(())
+            } else {
+                Err(ValidateError::Last(last.clone()))
             }
         } else {
             Err(ValidateError::Empty)
         }
     }
+    /// How many actions the protocol's steps expand to (saturating at `usize::MAX`).
+    pub(crate) fn expanded_len(&self) -> usize {
+        self.steps
+            .iter()
+            .fold(0, |len, step| len.saturating_add(step.expanded_len()))
+    }
     /// Attempts to convert the protocol to a [`program`](struct.Program.html).
     ///
     /// The protocol will first be validated.
     pub fn as_program(&self) -> Result<Program, ValidateError> {
         self.validate()?;
-        let mut actions = self
-            .steps
-            .iter()
-            .flat_map(|step| {
-                let mut actions = vec![];
-                match step {
-                    &Step::Perfuse(motor, duration) => {
-                        actions.push(Action::Perfuse(motor));
-                        actions.push(duration.map(Action::Sleep).unwrap_or(Action::Hail));
-                        actions.push(Action::Drain);
-                    }
-                    Step::PerfusePrompt(motor, begin, duration, end) => {
-                        actions.push(Action::Perfuse(*motor));
-                        actions.push(Action::Notify(begin.clone()));
-                        actions.push(Action::Hail);
-                        actions.push(Action::Sleep(*duration));
-                        actions.push(Action::Notify(end.clone()));
-                        actions.push(Action::Hail);
-                        actions.push(Action::Drain);
-                    }
-                }
-                actions.into_iter()
-            })
-            .collect::<Vec<_>>();
+        let mut actions = vec![];
+        for step in &self.steps {
+            step.expand(None, &mut actions);
+        }
         let _ = actions.pop();
         let _ = actions.pop();
         actions.push(Action::Finish);
         assert!(actions.len() > 1);
-        if let Action::Perfuse(_) = actions[0] {
+        // Pump selection, valve positioning, waiting on a sensor, hooks, and overrun policies may
+        // precede the initial perfusion (which may also be the first step of a repeat or a branch).
+        let first = actions.iter().find(|action| {
+            !matches!(
+                action,
+                Action::UsePump(_)
+                    | Action::SetPosition { .. }
+                    | Action::Iterate(_)
+                    | Action::WaitUntil { .. }
+                    | Action::Branch { .. }
+                    | Action::Skip(_)
+                    | Action::Notify(_)
+                    | Action::Hail
+                    | Action::Hook(_)
+                    | Action::Overrun(_)
+            )
+        });
+        if let Some(Action::Perfuse(_)) | Some(Action::Gradient { .. }) = first {
             Ok(Program { actions })
         } else {
             // This shouldn't be able to happen, so it's more than user error; it's on us.
@@ -138,13 +683,26 @@ impl Protocol {
 }
 
 /// Represents a specific action to be run.
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
 #[cfg_attr(feature = "use_serde", serde(rename_all = "lowercase"))]
 pub enum Action {
     /// Perfuse with the specified solution until a full volume is reached, then close the valve
     /// and turn off the pump.
     Perfuse(MotorId),
+    /// Perfuse for the given duration, ramping from the first buffer to the second by opening the
+    /// first buffer's valve for a shrinking share of each period (and the second's for the rest),
+    /// then close the valves and turn off the pump.
+    Gradient {
+        /// The buffer to begin with.
+        from: MotorId,
+        /// The buffer to end with.
+        to: MotorId,
+        /// How long the ramp takes.
+        duration: Duration,
+        /// How long each time slice lasts.
+        period: Duration,
+    },
     /// Wait for the specified duration.
     Sleep(Duration),
     /// Wait for the user to continue.
@@ -155,6 +713,64 @@ pub enum Action {
     Finish,
     /// Notify the user.
     Notify(Notification),
+    /// Use the specified pump from now on.
+    UsePump(PumpId),
+    /// Bring the sample to the target temperature (in °C), then wait for the specified duration
+    /// while holding it within the given tolerance (in K).
+    HoldTemperature {
+        /// The target temperature, in °C.
+        target: f64,
+        /// The largest tolerated deviation from the target, in K.
+        tolerance: f64,
+        /// How long to hold the temperature once it has been reached.
+        duration: Duration,
+    },
+    /// Run the pump forward and then backward for the given durations, the given number of times
+    /// over (agitating the chamber).
+    Mix {
+        /// How many times to run the pump forward and back.
+        cycles: usize,
+        /// How long to run the pump forward each time.
+        forward: Duration,
+        /// How long to run the pump backward each time.
+        backward: Duration,
+    },
+    /// Move the specified motor to the named position.
+    SetPosition {
+        /// The motor to move (motor 0 is the waste valve).
+        motor: MotorId,
+        /// The name of the position.
+        position: String,
+    },
+    /// Begin the given pass through a repeat (or, if `None`, leave the outermost repeat).
+    Iterate(Option<Iteration>),
+    /// Wait until the given sensor's reading satisfies the given predicate, or (once the timeout,
+    /// if any, passes) for the user to continue.
+    WaitUntil {
+        /// The sensor to read.
+        sensor: Sensor,
+        /// The test the reading must pass.
+        predicate: Predicate,
+        /// How long to wait before asking the user.
+        timeout: Option<Duration>,
+    },
+    /// Read the given sensor, skipping the given number of actions (the first branch of a
+    /// conditional step) unless its reading satisfies the given predicate.
+    Branch {
+        /// The sensor to read.
+        sensor: Sensor,
+        /// The test the reading is put to.
+        predicate: Predicate,
+        /// How many actions to skip if the reading fails the test.
+        skip: usize,
+    },
+    /// Skip the given number of actions (the branch of a conditional step not taken).
+    Skip(usize),
+    /// Post the given event to the webhooks.
+    Hook(String),
+    /// Apply the given overrun policy to the timed actions which follow (or, if `None`, the
+    /// configured one).
+    Overrun(Option<Overrun>),
 }
 
 impl Action {
@@ -165,16 +781,28 @@ impl Action {
         match self {
             // These actions come after perfusing, so we can stop after the prior step if need be.
             Self::Sleep(_) | Self::Hail | Self::Finish | Self::Drain => true,
+            // Switching pumps and moving valves only matter for the steps that follow.
+            Self::UsePump(_) | Self::SetPosition { .. } => true,
+            // Passes through a repeat (and branches) begin with whatever step comes first.
+            Self::Iterate(_) | Self::Branch { .. } | Self::Skip(_) => true,
+            // Overrun policies only matter for the steps that follow.
+            Self::Overrun(_) => true,
+            // Like sleeping, waiting on a sensor comes after perfusing.
+            Self::WaitUntil { .. } => true,
+            // Like sleeping, holding a temperature comes after perfusing.
+            Self::HoldTemperature { .. } => true,
+            // Like draining, mixing leaves the chamber as it found it.
+            Self::Mix { .. } => true,
             // Don't stop before perfusing (the sample should not be dry when we're done)
-            Self::Perfuse(_) => false,
+            Self::Perfuse(_) | Self::Gradient { .. } => false,
             // Don't stop without notifying
-            Self::Notify(_) => false,
+            Self::Notify(_) | Self::Hook(_) => false,
         }
     }
 }
 
 /// A sequence of fine-grained actions.
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
 #[cfg_attr(feature = "use_serde", serde(rename_all = "lowercase", transparent))]
 pub struct Program {
@@ -203,4 +831,305 @@ mod tests {
         protocol.steps.clear();
         assert_eq!(protocol.as_program(), Err(ValidateError::Empty));
     }
+    #[test]
+    fn use_pump() {
+        let mut protocol = Protocol {
+            steps: vec![Step::UsePump(1), Step::Perfuse(0, None)],
+        };
+        let actions: Vec<Action> = protocol.as_program().unwrap().into();
+        assert_eq!(actions[0], Action::UsePump(1));
+        protocol.steps.push(Step::UsePump(0));
+        assert_eq!(
+            protocol.as_program(),
+            Err(ValidateError::Last(Step::UsePump(0)))
+        );
+    }
+    #[test]
+    fn hold_temperature() {
+        let hold = Step::HoldTemperature {
+            target: 37.0,
+            tolerance: 0.5,
+            duration: Duration::new(60, 0),
+        };
+        let mut protocol = Protocol {
+            steps: vec![Step::Perfuse(0, Some(Duration::new(10, 0))), hold.clone()],
+        };
+        assert_eq!(protocol.as_program(), Err(ValidateError::Last(hold)));
+        protocol.steps.push(Step::Perfuse(1, None));
+        let actions: Vec<Action> = protocol.as_program().unwrap().into();
+        assert!(actions.contains(&Action::HoldTemperature {
+            target: 37.0,
+            tolerance: 0.5,
+            duration: Duration::new(60, 0),
+        }));
+    }
+    #[test]
+    fn set_position() {
+        let bypass = Step::SetPosition {
+            motor: 2,
+            position: "bypass".into(),
+        };
+        let protocol = Protocol {
+            steps: vec![bypass, Step::Perfuse(0, None)],
+        };
+        let actions: Vec<Action> = protocol.as_program().unwrap().into();
+        assert_eq!(
+            actions[0],
+            Action::SetPosition {
+                motor: 2,
+                position: "bypass".into(),
+            }
+        );
+    }
+    #[test]
+    fn repeat() {
+        let wash = Step::Repeat {
+            count: 2,
+            steps: vec![Step::Perfuse(1, Some(Duration::new(300, 0)))],
+        };
+        let protocol = Protocol {
+            steps: vec![wash, Step::Perfuse(0, None)],
+        };
+        let actions: Vec<Action> = protocol.as_program().unwrap().into();
+        let pass = |iteration| {
+            Action::Iterate(Some(Iteration {
+                iteration,
+                count: 2,
+            }))
+        };
+        let perfusion = vec![
+            Action::Perfuse(1),
+            Action::Sleep(Duration::new(300, 0)),
+            Action::Drain,
+        ];
+        let mut expected = vec![pass(1)];
+        expected.extend(perfusion.clone());
+        expected.push(pass(2));
+        expected.extend(perfusion);
+        expected.push(Action::Iterate(None));
+        expected.extend(vec![Action::Perfuse(0), Action::Finish]);
+        assert_eq!(actions, expected);
+        let empty = Protocol {
+            steps: vec![
+                Step::Repeat {
+                    count: 0,
+                    steps: vec![Step::Perfuse(1, None)],
+                },
+                Step::Perfuse(0, None),
+            ],
+        };
+        assert_eq!(empty.as_program(), Err(ValidateError::EmptyRepeat));
+        let endless = Protocol {
+            steps: vec![
+                Step::Repeat {
+                    count: usize::MAX,
+                    steps: vec![Step::Repeat {
+                        count: usize::MAX,
+                        steps: vec![Step::Perfuse(1, Some(Duration::new(300, 0)))],
+                    }],
+                },
+                Step::Perfuse(0, None),
+            ],
+        };
+        assert_eq!(endless.as_program(), Err(ValidateError::TooLong));
+        // The final perfusion's last two actions give way to finishing.
+        assert_eq!(protocol.expanded_len(), actions.len() + 1);
+    }
+    #[test]
+    fn conditions() {
+        let warm = Predicate::Within {
+            target: 37.0,
+            tolerance: 0.5,
+        };
+        assert!(warm.holds(36.6));
+        assert!(!warm.holds(36.4));
+        let branch = Step::IfElse {
+            sensor: Sensor::Pressure,
+            predicate: Predicate::Above(20.0),
+            then: vec![Step::Perfuse(1, Some(Duration::new(60, 0)))],
+            otherwise: vec![
+                Step::UsePump(1),
+                Step::Perfuse(2, Some(Duration::new(60, 0))),
+            ],
+        };
+        let wait = Step::WaitUntil {
+            sensor: Sensor::Temperature,
+            predicate: warm,
+            timeout: None,
+        };
+        let protocol = Protocol {
+            steps: vec![wait, branch, Step::Perfuse(0, None)],
+        };
+        let actions: Vec<Action> = protocol.as_program().unwrap().into();
+        let perfusion = |buffer| {
+            vec![
+                Action::Perfuse(buffer),
+                Action::Sleep(Duration::new(60, 0)),
+                Action::Drain,
+            ]
+        };
+        let mut expected = vec![
+            Action::WaitUntil {
+                sensor: Sensor::Temperature,
+                predicate: warm,
+                timeout: None,
+            },
+            Action::Branch {
+                sensor: Sensor::Pressure,
+                predicate: Predicate::Above(20.0),
+                skip: 4,
+            },
+        ];
+        expected.extend(perfusion(1));
+        expected.push(Action::Skip(4));
+        expected.push(Action::UsePump(1));
+        expected.extend(perfusion(2));
+        expected.extend(vec![Action::Perfuse(0), Action::Finish]);
+        assert_eq!(actions, expected);
+        let empty = Protocol {
+            steps: vec![
+                Step::IfElse {
+                    sensor: Sensor::Flow,
+                    predicate: Predicate::Below(1.0),
+                    then: vec![],
+                    otherwise: vec![],
+                },
+                Step::Perfuse(0, None),
+            ],
+        };
+        assert_eq!(empty.as_program(), Err(ValidateError::EmptyBranch));
+    }
+    #[test]
+    fn hooks() {
+        let loaded = Notification {
+            subject: "Load the sample".into(),
+            message: "Continue once the sample is cannulated.".into(),
+        };
+        let protocol = Protocol {
+            steps: vec![
+                Step::Hooked {
+                    step: Box::new(Step::Perfuse(0, Some(Duration::new(60, 0)))),
+                    begin: vec![Hook::Confirm(loaded.clone())],
+                    end: vec![Hook::Webhook("rinsed".into())],
+                },
+                Step::Hooked {
+                    step: Box::new(Step::Perfuse(1, None)),
+                    begin: vec![Hook::Notify(loaded.clone())],
+                    end: vec![],
+                },
+            ],
+        };
+        let actions: Vec<Action> = protocol.as_program().unwrap().into();
+        assert_eq!(
+            actions,
+            vec![
+                Action::Notify(loaded.clone()),
+                Action::Hail,
+                Action::Perfuse(0),
+                Action::Sleep(Duration::new(60, 0)),
+                Action::Drain,
+                Action::Hook("rinsed".into()),
+                Action::Notify(loaded.clone()),
+                Action::Perfuse(1),
+                Action::Finish,
+            ]
+        );
+        let mut unending = protocol.clone();
+        if let Some(Step::Hooked { end, .. }) = unending.steps.last_mut() {
+            end.push(Hook::Notify(loaded));
+        }
+        assert!(matches!(
+            unending.as_program(),
+            Err(ValidateError::Last(Step::Hooked { .. }))
+        ));
+    }
+    #[test]
+    fn timed() {
+        let overrun = Overrun {
+            tolerance: Duration::new(30, 0),
+            policy: OverrunPolicy::Abort,
+        };
+        let incubate = Step::Timed {
+            step: Box::new(Step::Perfuse(0, Some(Duration::new(600, 0)))),
+            overrun,
+        };
+        let protocol = Protocol {
+            steps: vec![incubate.clone(), Step::Perfuse(1, None)],
+        };
+        let actions: Vec<Action> = protocol.as_program().unwrap().into();
+        assert_eq!(
+            actions,
+            vec![
+                Action::Overrun(Some(overrun)),
+                Action::Perfuse(0),
+                Action::Sleep(Duration::new(600, 0)),
+                Action::Drain,
+                Action::Overrun(None),
+                Action::Perfuse(1),
+                Action::Finish,
+            ]
+        );
+        let unending = Protocol {
+            steps: vec![Step::Timed {
+                step: Box::new(Step::Perfuse(1, None)),
+                overrun,
+            }],
+        };
+        assert!(matches!(
+            unending.as_program(),
+            Err(ValidateError::Last(Step::Timed { .. }))
+        ));
+    }
+    #[test]
+    fn mix() {
+        let protocol = Protocol {
+            steps: vec![
+                Step::Perfuse(0, Some(Duration::new(60, 0))),
+                Step::Mix {
+                    cycles: 10,
+                    forward: Duration::new(2, 0),
+                    backward: Duration::new(2, 0),
+                },
+                Step::Perfuse(1, None),
+            ],
+        };
+        let actions: Vec<Action> = protocol.as_program().unwrap().into();
+        assert_eq!(
+            actions[3],
+            Action::Mix {
+                cycles: 10,
+                forward: Duration::new(2, 0),
+                backward: Duration::new(2, 0),
+            }
+        );
+        assert_eq!(actions.len(), 6);
+        assert!(actions[3].is_disjoint());
+    }
+    #[test]
+    fn gradient() {
+        let gradient = Step::Gradient {
+            from: 0,
+            to: 1,
+            duration: Duration::new(600, 0),
+            period: Duration::new(10, 0),
+        };
+        let protocol = Protocol {
+            steps: vec![gradient, Step::Perfuse(1, None)],
+        };
+        let actions: Vec<Action> = protocol.as_program().unwrap().into();
+        assert_eq!(
+            actions,
+            vec![
+                Action::Gradient {
+                    from: 0,
+                    to: 1,
+                    duration: Duration::new(600, 0),
+                    period: Duration::new(10, 0),
+                },
+                Action::Drain,
+                Action::Perfuse(1),
+                Action::Finish,
+            ]
+        );
+    }
 }
@@ -0,0 +1,384 @@
+//! Volumes, flow rates, and durations, as written in protocol files and configurations.
+//!
+//! Each quantity may be written as a number with its unit (e.g. `"1.5ml"`, `"200ul/min"`, or
+//! `"10min"`), or as a bare number in the quantity's unit (see [`Quantity::UNIT`]), so that files
+//! written before units could be given are read as they always were. Units are case-insensitive,
+//! and may be separated from the number by spaces.
+//!
+//! | Quantity     | Bare numbers | Units                                              |
+//! |--------------|--------------|----------------------------------------------------|
+//! | [`Volume`]   | mL           | `nl`, `ul` (or `µl`), `ml`, `l`                    |
+//! | [`FlowRate`] | mL/min       | any volume per `ms`, `s`, `min`, or `h`            |
+//! | [`Time`]     | s            | `ms`, `s` (or `sec`), `min`, `h` (or `hr`)         |
+//!
+//! [`Quantity::UNIT`]: trait.Quantity.html#associatedconstant.UNIT
+//! [`Volume`]: struct.Volume.html
+//! [`FlowRate`]: struct.FlowRate.html
+//! [`Time`]: struct.Time.html
+use std::{error, fmt, str::FromStr, time::Duration};
+
+#[cfg(feature = "schema")]
+use schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
+#[cfg(feature = "use_serde")]
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// The volumes understood, in µL.
+const VOLUMES: &[(&str, f64)] = &[
+    ("nl", 1e-3),
+    ("ul", 1.0),
+    ("µl", 1.0),
+    ("μl", 1.0),
+    ("ml", 1e3),
+    ("l", 1e6),
+];
+
+/// The times understood, in seconds.
+const TIMES: &[(&str, f64)] = &[
+    ("ms", 1e-3),
+    ("s", 1.0),
+    ("sec", 1.0),
+    ("min", 60.0),
+    ("h", 3600.0),
+    ("hr", 3600.0),
+];
+
+/// A quantity with a unit.
+pub trait Quantity: Copy + FromStr<Err = QuantityError> {
+    /// The unit bare numbers are taken to be in (e.g. `mL`).
+    const UNIT: &'static str;
+    /// The quantity of the given amount of the unit.
+    fn new(value: f64) -> Self;
+    /// The amount of the unit.
+    fn get(self) -> f64;
+}
+
+/// A volume.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct Volume(f64);
+
+impl Volume {
+    /// The given volume, in µL.
+    pub fn from_microlitres(microlitres: f64) -> Self {
+        Self(microlitres)
+    }
+    /// The given volume, in mL.
+    pub fn from_millilitres(millilitres: f64) -> Self {
+        Self(millilitres * 1e3)
+    }
+    /// The volume, in µL.
+    pub fn microlitres(self) -> f64 {
+        self.0
+    }
+    /// The volume, in mL.
+    pub fn millilitres(self) -> f64 {
+        self.0 / 1e3
+    }
+}
+
+impl Quantity for Volume {
+    const UNIT: &'static str = "mL";
+    fn new(value: f64) -> Self {
+        Self::from_millilitres(value)
+    }
+    fn get(self) -> f64 {
+        self.millilitres()
+    }
+}
+
+impl FromStr for Volume {
+    type Err = QuantityError;
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || QuantityError::new(text, "a volume (e.g. 1.5ml or 200ul)");
+        let (value, unit) = split(text).ok_or_else(invalid)?;
+        if unit.is_empty() {
+            return Ok(Self::new(value));
+        }
+        factor(VOLUMES, &unit)
+            .map(|factor| Self(value * factor))
+            .ok_or_else(invalid)
+    }
+}
+
+impl fmt::Display for Volume {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} mL", self.millilitres())
+    }
+}
+
+/// A flow rate.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct FlowRate(f64);
+
+impl FlowRate {
+    /// The given flow rate, in mL/min.
+    pub fn from_millilitres_per_minute(rate: f64) -> Self {
+        Self(rate)
+    }
+    /// The flow rate, in mL/min.
+    pub fn millilitres_per_minute(self) -> f64 {
+        self.0
+    }
+    /// The flow rate, in mL/s.
+    pub fn millilitres_per_second(self) -> f64 {
+        self.0 / 60.0
+    }
+    /// How long it takes to pump the given volume at this rate.
+    pub fn time_to_pump(self, volume: Volume) -> Time {
+        Time(volume.millilitres() / self.0 * 60.0)
+    }
+    /// The volume pumped at this rate in the given time.
+    pub fn volume_in(self, time: Time) -> Volume {
+        Volume::from_millilitres(self.0 * time.secs() / 60.0)
+    }
+}
+
+impl Quantity for FlowRate {
+    const UNIT: &'static str = "mL/min";
+    fn new(value: f64) -> Self {
+        Self::from_millilitres_per_minute(value)
+    }
+    fn get(self) -> f64 {
+        self.millilitres_per_minute()
+    }
+}
+
+impl FromStr for FlowRate {
+    type Err = QuantityError;
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || QuantityError::new(text, "a flow rate (e.g. 1.5ml/min or 200ul/s)");
+        let (value, unit) = split(text).ok_or_else(invalid)?;
+        if unit.is_empty() {
+            return Ok(Self::new(value));
+        }
+        let mut parts = unit.splitn(2, '/');
+        let volume = parts.next().and_then(|unit| factor(VOLUMES, unit));
+        let time = parts.next().and_then(|unit| factor(TIMES, unit));
+        match (volume, time) {
+            // From µL/s to mL/min.
+            (Some(volume), Some(time)) => Ok(Self(value * volume * 60.0 / time / 1e3)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for FlowRate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} mL/min", self.millilitres_per_minute())
+    }
+}
+
+/// A length of time.
+///
+/// Unlike a [`Duration`], a time may be negative or not a number, as written; see
+/// [`to_duration`](#method.to_duration).
+///
+/// [`Duration`]: https://doc.rust-lang.org/std/time/struct.Duration.html
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct Time(f64);
+
+impl Time {
+    /// The given time, in seconds.
+    pub fn from_secs(secs: f64) -> Self {
+        Self(secs)
+    }
+    /// The time, in seconds.
+    pub fn secs(self) -> f64 {
+        self.0
+    }
+    /// The time as a duration, unless it's negative, not a number, or too long to represent.
+    pub fn to_duration(self) -> Option<Duration> {
+        // Converting panics beyond the largest duration (just shy of 2^64 seconds).
+        if self.0 >= 0.0 && self.0 < u64::MAX as f64 {
+            Some(Duration::from_secs_f64(self.0))
+        } else {
+            None
+        }
+    }
+}
+
+impl From<Duration> for Time {
+    fn from(duration: Duration) -> Self {
+        Self(duration.as_secs_f64())
+    }
+}
+
+impl Quantity for Time {
+    const UNIT: &'static str = "s";
+    fn new(value: f64) -> Self {
+        Self::from_secs(value)
+    }
+    fn get(self) -> f64 {
+        self.secs()
+    }
+}
+
+impl FromStr for Time {
+    type Err = QuantityError;
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || QuantityError::new(text, "a duration (e.g. 30s or 10min)");
+        let (value, unit) = split(text).ok_or_else(invalid)?;
+        if unit.is_empty() {
+            return Ok(Self::new(value));
+        }
+        factor(TIMES, &unit)
+            .map(|factor| Self(value * factor))
+            .ok_or_else(invalid)
+    }
+}
+
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} s", self.secs())
+    }
+}
+
+/// Splits the given text into its number and its unit (in lowercase, without spaces), where the
+/// number must be finite and nonnegative.
+fn split(text: &str) -> Option<(f64, String)> {
+    let text = text.trim();
+    let end = text
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(text.len());
+    // Enough digits parse as infinity.
+    let value = text[..end]
+        .parse::<f64>()
+        .ok()
+        .filter(|value| value.is_finite())?;
+    let unit = text[end..]
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+    Some((value, unit))
+}
+
+/// The factor converting the given unit, if it's one of the given units.
+fn factor(units: &[(&str, f64)], unit: &str) -> Option<f64> {
+    units
+        .iter()
+        .find(|(name, _)| *name == unit)
+        .map(|(_, factor)| *factor)
+}
+
+/// Some text isn't a quantity of the kind expected (e.g. a volume without a known unit).
+#[derive(Clone, Debug, PartialEq)]
+pub struct QuantityError {
+    /// The text in question.
+    pub text: String,
+    /// What was expected (e.g. "a volume (e.g. 1.5ml or 200ul)").
+    pub expected: &'static str,
+}
+
+impl QuantityError {
+    fn new(text: &str, expected: &'static str) -> Self {
+        Self {
+            text: text.into(),
+            expected,
+        }
+    }
+}
+
+impl fmt::Display for QuantityError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "\"{}\" should be {}", self.text, self.expected)
+    }
+}
+
+impl error::Error for QuantityError {}
+
+/// A quantity as written: either a bare number or a number with its unit.
+#[cfg(feature = "use_serde")]
+#[derive(Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(untagged)]
+enum Written {
+    Number(f64),
+    Text(String),
+}
+
+/// Quantities are written as bare numbers, for older versions to read.
+#[cfg(feature = "use_serde")]
+macro_rules! written {
+    ($($quantity:ident),*) => {
+        $(
+            impl Serialize for $quantity {
+                fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    serializer.serialize_f64(self.get())
+                }
+            }
+
+            impl<'de> Deserialize<'de> for $quantity {
+                fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    match Written::deserialize(deserializer)? {
+                        Written::Number(value) => Ok(Self::new(value)),
+                        Written::Text(text) => text.parse().map_err(de::Error::custom),
+                    }
+                }
+            }
+
+            #[cfg(feature = "schema")]
+            impl JsonSchema for $quantity {
+                fn schema_name() -> String {
+                    stringify!($quantity).into()
+                }
+                fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+                    Written::json_schema(gen)
+                }
+            }
+        )*
+    };
+}
+
+#[cfg(feature = "use_serde")]
+written!(Volume, FlowRate, Time);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn parses_units() {
+        assert_eq!("1.5ml".parse(), Ok(Volume::from_microlitres(1500.0)));
+        assert_eq!(" 200 uL ".parse(), Ok(Volume::from_microlitres(200.0)));
+        assert_eq!("2".parse(), Ok(Volume::from_millilitres(2.0)));
+        assert_eq!("0.5L".parse::<Volume>().unwrap().millilitres(), 500.0);
+        assert_eq!(
+            "200ul/min".parse(),
+            Ok(FlowRate::from_millilitres_per_minute(0.2))
+        );
+        assert_eq!(
+            "1 ml/s".parse(),
+            Ok(FlowRate::from_millilitres_per_minute(60.0))
+        );
+        assert_eq!(
+            "12".parse(),
+            Ok(FlowRate::from_millilitres_per_minute(12.0))
+        );
+        assert_eq!("10min".parse(), Ok(Time::from_secs(600.0)));
+        assert_eq!("1.5 h".parse(), Ok(Time::from_secs(5400.0)));
+        assert_eq!("30".parse(), Ok(Time::from_secs(30.0)));
+        assert!("1.5 gal".parse::<Volume>().is_err());
+        assert!("200ul".parse::<FlowRate>().is_err());
+        assert!("-5s".parse::<Time>().is_err());
+        assert!("ml".parse::<Volume>().is_err());
+        assert!(format!("{}s", "9".repeat(400)).parse::<Time>().is_err());
+    }
+    #[test]
+    fn bounds_durations() {
+        assert_eq!(
+            Time::from_secs(1.5).to_duration(),
+            Some(Duration::from_millis(1500))
+        );
+        assert_eq!(Time::from_secs(-1.0).to_duration(), None);
+        assert_eq!(Time::from_secs(f64::NAN).to_duration(), None);
+        assert_eq!(Time::from_secs(f64::INFINITY).to_duration(), None);
+        assert_eq!(Time::from_secs(1e20).to_duration(), None);
+    }
+    #[test]
+    fn converts_volumes() {
+        let rate = FlowRate::from_millilitres_per_minute(100.0);
+        let time = rate.time_to_pump(Volume::from_millilitres(500.0));
+        assert_eq!(time.to_duration(), Some(Duration::from_secs(300)));
+        assert_eq!(rate.volume_in(time), Volume::from_millilitres(500.0));
+        assert_eq!(Time::from_secs(-1.0).to_duration(), None);
+    }
+}
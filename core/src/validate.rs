@@ -0,0 +1,489 @@
+//! Checking protocols against the hardware they'll be run on.
+use std::{collections::BTreeMap, fmt, time::Duration};
+
+use crate::{quantity::FlowRate, MotorId, Protocol, PumpId, Sensor, Step, ValidateProtocolError};
+
+/// Describes the hardware a protocol will be run on, as far as validation is concerned.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+pub struct Hardware {
+    /// The names of each motor's additional positions, with one entry per motor (where motor 0
+    /// controls the waste valve).
+    pub positions: Vec<Vec<String>>,
+    /// The number of pumps.
+    pub pumps: usize,
+    /// Whether a thermostat is installed.
+    pub thermostat: bool,
+    /// The configured names of the buffers (for protocol files referring to them by name).
+    #[cfg_attr(feature = "use_serde", serde(default))]
+    pub buffers: BTreeMap<String, MotorId>,
+    /// Each pump's flow rate at full speed, if calibrated (for protocol files giving volumes rather
+    /// than durations).
+    #[cfg_attr(feature = "use_serde", serde(default))]
+    pub flow_rates: Vec<Option<FlowRate>>,
+    /// The sensors installed (for steps waiting or branching on their readings).
+    #[cfg_attr(feature = "use_serde", serde(default))]
+    pub sensors: Vec<Sensor>,
+}
+
+impl Hardware {
+    /// Whether the given buffer has a valve (see [`has_buffer`](fn.has_buffer.html)).
+    pub fn has_buffer(&self, buffer: MotorId) -> bool {
+        has_buffer(self.positions.len(), buffer)
+    }
+}
+
+/// Whether a system with the given number of motors has a valve for the given buffer.
+///
+/// Motor 0 controls the waste valve, so buffer `n` is controlled by motor `n + 1`.
+pub fn has_buffer(motors: usize, buffer: MotorId) -> bool {
+    buffer + 1 < motors
+}
+
+/// A problem found while checking a protocol.
+///
+/// Each issue refers to the index of the offending step (in the protocol's steps).
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "use_serde", serde(rename_all = "kebab-case"))]
+pub enum Issue {
+    /// The protocol is structurally invalid (see [`Protocol::validate`]).
+    ///
+    /// [`Protocol::validate`]: struct.Protocol.html#method.validate
+    Structure(ValidateProtocolError),
+    /// A perfusion names a buffer with no valve, so the pump would run with every valve shut.
+    NoSuchBuffer {
+        /// The step in question.
+        step: usize,
+        /// The buffer in question.
+        buffer: MotorId,
+    },
+    /// The step switches to a pump that doesn't exist.
+    NoSuchPump {
+        /// The step in question.
+        step: usize,
+        /// The pump in question.
+        pump: PumpId,
+    },
+    /// The step moves a motor that doesn't exist, or to a position it doesn't have.
+    NoSuchPosition {
+        /// The step in question.
+        step: usize,
+        /// The motor in question.
+        motor: MotorId,
+        /// The name of the position.
+        position: String,
+    },
+    /// The step holds a temperature, but no thermostat is installed.
+    NoThermostat {
+        /// The step in question.
+        step: usize,
+    },
+    /// The step waits or branches on a sensor that isn't installed.
+    NoSuchSensor {
+        /// The step in question.
+        step: usize,
+        /// The sensor in question.
+        sensor: Sensor,
+    },
+    /// The step's predicate is not made of sensible numbers.
+    InvalidPredicate {
+        /// The step in question.
+        step: usize,
+    },
+    /// The step has a duration of zero (or, for a mix, no cycles).
+    ZeroDuration {
+        /// The step in question.
+        step: usize,
+    },
+    /// The step's target temperature or tolerance is not a sensible number.
+    InvalidTemperature {
+        /// The step in question.
+        step: usize,
+    },
+    /// The step perfuses with the same buffer as the previous perfusion (a warning).
+    RepeatedBuffer {
+        /// The step in question.
+        step: usize,
+        /// The buffer in question.
+        buffer: MotorId,
+    },
+    /// The step switches to the pump that's already in use (a warning).
+    RedundantPump {
+        /// The step in question.
+        step: usize,
+        /// The pump in question.
+        pump: PumpId,
+    },
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Structure(err) => write!(f, "invalid protocol: {:?}", err),
+            Self::NoSuchBuffer { step, buffer } => {
+                write!(f, "step {}: buffer {} has no valve", step, buffer)
+            }
+            Self::NoSuchPump { step, pump } => write!(f, "step {}: no pump {}", step, pump),
+            Self::NoSuchPosition {
+                step,
+                motor,
+                position,
+            } => write!(
+                f,
+                "step {}: motor {} has no position \"{}\"",
+                step, motor, position
+            ),
+            Self::NoThermostat { step } => write!(f, "step {}: no thermostat installed", step),
+            Self::NoSuchSensor { step, sensor } => {
+                write!(f, "step {}: no {} sensor installed", step, sensor)
+            }
+            Self::InvalidPredicate { step } => write!(f, "step {}: invalid predicate", step),
+            Self::ZeroDuration { step } => write!(f, "step {}: duration is zero", step),
+            Self::InvalidTemperature { step } => {
+                write!(f, "step {}: invalid temperature or tolerance", step)
+            }
+            Self::RepeatedBuffer { step, buffer } => write!(
+                f,
+                "step {}: buffer {} was already used for the previous perfusion",
+                step, buffer
+            ),
+            Self::RedundantPump { step, pump } => {
+                write!(f, "step {}: pump {} is already in use", step, pump)
+            }
+        }
+    }
+}
+
+/// The outcome of checking a protocol against the hardware.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+pub struct Validation {
+    /// Problems which prevent the protocol from being run.
+    pub errors: Vec<Issue>,
+    /// Oddities which don't prevent the protocol from being run, but probably aren't intended.
+    pub warnings: Vec<Issue>,
+}
+
+impl Validation {
+    /// Whether the protocol may be run (i.e. there are no errors).
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+impl Protocol {
+    /// Checks the protocol against the given hardware, collecting every problem found.
+    ///
+    /// Besides the structural checks of [`validate`](#method.validate), this makes sure that every
+    /// buffer, pump, motor position, and sensor referenced exists, that durations are nonzero, and
+    /// that temperature steps have a thermostat to run on.
+    pub fn check(&self, hardware: &Hardware) -> Validation {
+        let mut validation = Validation::default();
+        if let Err(err) = self.validate() {
+            validation.errors.push(Issue::Structure(err));
+        }
+        let mut checker = Checker {
+            hardware,
+            validation,
+            last_buffer: None,
+            pump: 0,
+        };
+        for (step, spec) in self.steps.iter().enumerate() {
+            checker.check(step, spec);
+        }
+        checker.validation
+    }
+}
+
+/// Checks steps in order, keeping track of the buffer and pump in use.
+struct Checker<'a> {
+    hardware: &'a Hardware,
+    validation: Validation,
+    last_buffer: Option<MotorId>,
+    pump: PumpId,
+}
+
+impl Checker<'_> {
+    /// Checks the given step (the protocol's `step`th, or part of it for repeated steps).
+    fn check(&mut self, step: usize, spec: &Step) {
+        let zero = Duration::new(0, 0);
+        let hardware = self.hardware;
+        let buffer = match spec {
+            Step::Perfuse(buffer, duration) => {
+                if *duration == Some(zero) {
+                    self.validation.errors.push(Issue::ZeroDuration { step });
+                }
+                Some(*buffer)
+            }
+            Step::PerfusePrompt(buffer, _, duration, _) => {
+                if *duration == zero {
+                    self.validation.errors.push(Issue::ZeroDuration { step });
+                }
+                Some(*buffer)
+            }
+            &Step::Gradient {
+                from,
+                to,
+                duration,
+                period,
+            } => {
+                if duration == zero || period == zero {
+                    self.validation.errors.push(Issue::ZeroDuration { step });
+                }
+                if !hardware.has_buffer(from) {
+                    self.validation
+                        .errors
+                        .push(Issue::NoSuchBuffer { step, buffer: from });
+                } else if self.last_buffer == Some(from) {
+                    self.validation
+                        .warnings
+                        .push(Issue::RepeatedBuffer { step, buffer: from });
+                }
+                // The gradient ends with the second buffer, which is checked against the first.
+                self.last_buffer = Some(from);
+                Some(to)
+            }
+            &Step::UsePump(next) => {
+                if next >= hardware.pumps {
+                    self.validation
+                        .errors
+                        .push(Issue::NoSuchPump { step, pump: next });
+                } else if next == self.pump {
+                    self.validation
+                        .warnings
+                        .push(Issue::RedundantPump { step, pump: next });
+                }
+                self.pump = next;
+                None
+            }
+            &Step::HoldTemperature {
+                target,
+                tolerance,
+                duration,
+            } => {
+                if !hardware.thermostat {
+                    self.validation.errors.push(Issue::NoThermostat { step });
+                }
+                if duration == zero {
+                    self.validation.errors.push(Issue::ZeroDuration { step });
+                }
+                if !target.is_finite() || !tolerance.is_finite() || tolerance < 0.0 {
+                    self.validation
+                        .errors
+                        .push(Issue::InvalidTemperature { step });
+                }
+                None
+            }
+            &Step::Mix {
+                cycles,
+                forward,
+                backward,
+            } => {
+                if cycles == 0 || forward == zero || backward == zero {
+                    self.validation.errors.push(Issue::ZeroDuration { step });
+                }
+                None
+            }
+            Step::SetPosition { motor, position } => {
+                let known = hardware
+                    .positions
+                    .get(*motor)
+                    .map(|names| names.contains(position))
+                    .unwrap_or(false);
+                if !known {
+                    self.validation.errors.push(Issue::NoSuchPosition {
+                        step,
+                        motor: *motor,
+                        position: position.clone(),
+                    });
+                }
+                None
+            }
+            Step::Repeat { steps, .. } => {
+                // Each pass runs the same steps, so checking them once finds every problem.
+                for inner in steps {
+                    self.check(step, inner);
+                }
+                None
+            }
+            &Step::WaitUntil {
+                sensor,
+                predicate,
+                timeout,
+            } => {
+                self.check_condition(step, sensor, predicate.is_valid());
+                if timeout == Some(zero) {
+                    self.validation.errors.push(Issue::ZeroDuration { step });
+                }
+                None
+            }
+            Step::IfElse {
+                sensor,
+                predicate,
+                then,
+                otherwise,
+            } => {
+                self.check_condition(step, *sensor, predicate.is_valid());
+                // Either branch may follow the steps before, so each is checked from there.
+                let (last_buffer, pump) = (self.last_buffer, self.pump);
+                for inner in then {
+                    self.check(step, inner);
+                }
+                let taken = self.last_buffer;
+                self.last_buffer = last_buffer;
+                self.pump = pump;
+                for inner in otherwise {
+                    self.check(step, inner);
+                }
+                if self.last_buffer != taken {
+                    self.last_buffer = None;
+                }
+                None
+            }
+            Step::Hooked { step: inner, .. } | Step::Timed { step: inner, .. } => {
+                self.check(step, inner);
+                None
+            }
+        };
+        if let Some(buffer) = buffer {
+            if !hardware.has_buffer(buffer) {
+                self.validation
+                    .errors
+                    .push(Issue::NoSuchBuffer { step, buffer });
+            } else if self.last_buffer == Some(buffer) {
+                self.validation
+                    .warnings
+                    .push(Issue::RepeatedBuffer { step, buffer });
+            }
+            self.last_buffer = Some(buffer);
+        }
+    }
+    /// Checks that the given sensor is installed and that the predicate put to it is valid.
+    fn check_condition(&mut self, step: usize, sensor: Sensor, valid: bool) {
+        if !self.hardware.sensors.contains(&sensor) {
+            self.validation
+                .errors
+                .push(Issue::NoSuchSensor { step, sensor });
+        }
+        if !valid {
+            self.validation
+                .errors
+                .push(Issue::InvalidPredicate { step });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Predicate;
+    fn hardware() -> Hardware {
+        Hardware {
+            positions: vec![vec![], vec![], vec!["bypass".into()]],
+            pumps: 1,
+            thermostat: false,
+            ..Hardware::default()
+        }
+    }
+    #[test]
+    fn check_protocol() {
+        let protocol = Protocol {
+            steps: vec![Step::Perfuse(0, None), Step::Perfuse(1, None)],
+        };
+        assert_eq!(protocol.check(&hardware()), Validation::default());
+        let protocol = Protocol {
+            steps: vec![
+                Step::UsePump(1),
+                Step::SetPosition {
+                    motor: 2,
+                    position: "bypass".into(),
+                },
+                Step::HoldTemperature {
+                    target: 37.0,
+                    tolerance: -1.0,
+                    duration: Duration::new(60, 0),
+                },
+                Step::Perfuse(1, Some(Duration::new(10, 0))),
+                Step::Perfuse(1, Some(Duration::new(0, 0))),
+                Step::Perfuse(2, None),
+            ],
+        };
+        let validation = protocol.check(&hardware());
+        assert!(!validation.is_ok());
+        assert_eq!(
+            validation.errors,
+            vec![
+                Issue::Structure(ValidateProtocolError::ZeroDuration),
+                Issue::NoSuchPump { step: 0, pump: 1 },
+                Issue::NoThermostat { step: 2 },
+                Issue::InvalidTemperature { step: 2 },
+                Issue::ZeroDuration { step: 4 },
+                Issue::NoSuchBuffer { step: 5, buffer: 2 },
+            ]
+        );
+        assert_eq!(
+            validation.warnings,
+            vec![Issue::RepeatedBuffer { step: 4, buffer: 1 }]
+        );
+    }
+    #[test]
+    fn check_repeat() {
+        let protocol = Protocol {
+            steps: vec![
+                Step::Perfuse(0, Some(Duration::new(60, 0))),
+                Step::Repeat {
+                    count: 3,
+                    steps: vec![
+                        Step::Perfuse(1, Some(Duration::new(60, 0))),
+                        Step::Perfuse(2, Some(Duration::new(60, 0))),
+                    ],
+                },
+                Step::Perfuse(0, None),
+            ],
+        };
+        assert_eq!(
+            protocol.check(&hardware()).errors,
+            vec![Issue::NoSuchBuffer { step: 1, buffer: 2 }]
+        );
+    }
+    #[test]
+    fn check_conditions() {
+        let protocol = Protocol {
+            steps: vec![
+                Step::Perfuse(0, Some(Duration::new(60, 0))),
+                Step::WaitUntil {
+                    sensor: Sensor::Temperature,
+                    predicate: Predicate::Above(30.0),
+                    timeout: Some(Duration::new(0, 0)),
+                },
+                Step::IfElse {
+                    sensor: Sensor::Pressure,
+                    predicate: Predicate::Within {
+                        target: 10.0,
+                        tolerance: -1.0,
+                    },
+                    then: vec![Step::Perfuse(1, Some(Duration::new(60, 0)))],
+                    otherwise: vec![Step::Perfuse(1, Some(Duration::new(30, 0)))],
+                },
+                Step::Perfuse(0, None),
+            ],
+        };
+        let mut hardware = hardware();
+        hardware.sensors = vec![Sensor::Pressure];
+        let validation = protocol.check(&hardware);
+        assert_eq!(
+            validation.errors,
+            vec![
+                Issue::NoSuchSensor {
+                    step: 1,
+                    sensor: Sensor::Temperature
+                },
+                Issue::ZeroDuration { step: 1 },
+                Issue::InvalidPredicate { step: 2 },
+            ]
+        );
+        // Neither branch follows the other.
+        assert_eq!(validation.warnings, vec![]);
+    }
+}
@@ -4,56 +4,129 @@ use deoxy::Tui;
 use futures::Future;
 use std::time::Duration;
 
-use deoxy::{actix::*, Config, CoordMessage, Coordinator, MotorConfig, Protocol, PumpConfig, Step};
+use deoxy::{
+    actix::*, BackendConfig, Config, CoordMessage, Coordinator, MotorConfig, Protocol, PumpConfig,
+    PwmMode, Step,
+};
 
 fn main() {
     pretty_env_logger::init();
 
-    let pump = PumpConfig {
-        pins: [1, 2, 3, 4],
-        invert: false,
-    };
+    let pump = PumpConfig::new([1, 2, 3, 4]);
     let motor1 = MotorConfig {
         pin: 5,
         period: Duration::new(1, 0),
         range: [Duration::from_millis(500), Duration::from_millis(750)],
         label: None,
+        pwm: PwmMode::Software,
+        inverted: false,
+        range_of_motion: 180,
+        angles: None,
+        positions: Default::default(),
+        ramp: None,
+        detach_after: None,
+        feedback: None,
     };
     let motor2 = MotorConfig {
         pin: 6,
         period: Duration::new(1, 0),
         range: [Duration::from_millis(500), Duration::from_millis(750)],
         label: None,
+        pwm: PwmMode::Software,
+        inverted: false,
+        range_of_motion: 180,
+        angles: None,
+        positions: Default::default(),
+        ramp: None,
+        detach_after: None,
+        feedback: None,
     };
     let motor3 = MotorConfig {
         pin: 7,
         period: Duration::new(1, 0),
         range: [Duration::from_millis(500), Duration::from_millis(750)],
         label: None,
+        pwm: PwmMode::Software,
+        inverted: false,
+        range_of_motion: 180,
+        angles: None,
+        positions: Default::default(),
+        ramp: None,
+        detach_after: None,
+        feedback: None,
     };
     let motor4 = MotorConfig {
         pin: 8,
         period: Duration::new(1, 0),
         range: [Duration::from_millis(500), Duration::from_millis(750)],
         label: None,
+        pwm: PwmMode::Software,
+        inverted: false,
+        range_of_motion: 180,
+        angles: None,
+        positions: Default::default(),
+        ramp: None,
+        detach_after: None,
+        feedback: None,
     };
-    let motors = vec![motor1, motor2, motor3, motor4];
+    let motors = vec![motor1.into(), motor2.into(), motor3.into(), motor4.into()];
     let config = Config {
+        name: None,
         motors,
-        pump,
+        calibration: None,
+        journal: None,
+        history: None,
+        library: None,
+        protocols: None,
+        event_log: None,
+        mqtt: None,
+        webhooks: vec![],
+        grpc: None,
+        modbus: None,
+        mail: None,
+        mail_templates: None,
+        fault_report: Default::default(),
+        chat: vec![],
+        sms: None,
+        push: vec![],
+        notification_throttle: Default::default(),
+        pumps: vec![pump],
+        drain_pump: None,
         admins: vec![],
+        gpio: BackendConfig::default(),
+        prime: Default::default(),
+        flow_sensor: None,
+        pressure_sensor: None,
+        bubble_detector: None,
+        reservoirs: vec![],
+        thermal: None,
+        estop: None,
+        watchdog: None,
+        supervision: Default::default(),
+        overrun: Default::default(),
+        teardown: vec![],
+        wash: None,
+        tokens: vec![],
+        bootstrap_token: None,
+        cors: None,
+        throttle: Default::default(),
+        buffers: vec![],
+        manifolds: vec![],
     };
 
     let step1 = Step::Perfuse(0, Some(Duration::new(5, 0)));
     let step2 = Step::Perfuse(1, None);
-    let step3 = Step::Perfuse(3, Some(Duration::new(3, 0)));
+    let step3 = Step::Perfuse(0, Some(Duration::new(3, 0)));
     let step4 = Step::Perfuse(2, None);
     let steps = vec![step1, step2, step3, step4];
     let proto = Protocol { steps };
 
     let system = System::new("pause");
 
-    let coord = Coordinator::try_new(config).unwrap().start();
+    let coord = Coordinator::try_new(config)
+        .unwrap()
+        .handle_signals()
+        .start();
     #[cfg(not(feature = "server"))]
     {
         let tui = Box::new(Tui {});
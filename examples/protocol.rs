@@ -1,16 +1,27 @@
 use std::error::Error;
 use std::time::Duration;
 
-use deoxy::{actix::*, Config, CoordMessage, Coordinator, MotorConfig, Protocol, PumpConfig, Step};
+use deoxy::{
+    actix::*, BackendConfig, Config, CoordMessage, Coordinator, MotorConfig, Protocol, PumpConfig,
+    PwmMode, Step, ValveConfig,
+};
 
 macro_rules! motor {
     ($pin:expr) => {
-        MotorConfig {
+        ValveConfig::Servo(MotorConfig {
             label: None,
-            period: Duration::from_millis(50),
+            pwm: PwmMode::Software,
+            inverted: false,
+            range_of_motion: 180,
+            angles: None,
+            positions: Default::default(),
+            ramp: None,
+            detach_after: None,
+            feedback: None,
+            period: Duration::from_millis(20),
             pin: $pin,
-            range: [Duration::from_millis(1), Duration::from_millis(100)],
-        }
+            range: [Duration::from_millis(1), Duration::from_millis(2)],
+        })
     };
 }
 
@@ -23,12 +34,47 @@ macro_rules! secs {
 fn main() -> Result<(), Box<dyn Error>> {
     pretty_env_logger::init();
     let config = Config {
-        pump: PumpConfig {
-            pins: [24, 25, 5, 6],
-            invert: false,
-        },
-        motors: vec![motor!(4), motor!(27), motor!(21), motor!(13)],
+        name: None,
+        pumps: vec![PumpConfig::new([24, 25, 5, 6])],
+        drain_pump: None,
+        motors: vec![motor!(4), motor!(27), motor!(21), motor!(13), motor!(19)],
+        calibration: None,
+        journal: None,
+        history: None,
+        library: None,
+        protocols: None,
+        event_log: None,
+        mqtt: None,
+        webhooks: vec![],
+        grpc: None,
+        modbus: None,
+        mail: None,
+        mail_templates: None,
+        fault_report: Default::default(),
+        chat: vec![],
+        sms: None,
+        push: vec![],
+        notification_throttle: Default::default(),
         admins: vec![],
+        gpio: BackendConfig::default(),
+        prime: Default::default(),
+        flow_sensor: None,
+        pressure_sensor: None,
+        bubble_detector: None,
+        reservoirs: vec![],
+        thermal: None,
+        estop: None,
+        watchdog: None,
+        supervision: Default::default(),
+        overrun: Default::default(),
+        teardown: vec![],
+        wash: None,
+        tokens: vec![],
+        bootstrap_token: None,
+        cors: None,
+        throttle: Default::default(),
+        buffers: vec![],
+        manifolds: vec![],
     };
     let proto = Protocol {
         steps: vec![
@@ -38,7 +84,7 @@ fn main() -> Result<(), Box<dyn Error>> {
             Step::Perfuse(3, None),
         ],
     };
-    let coord = Coordinator::try_new(config)?;
+    let coord = Coordinator::try_new(config)?.handle_signals();
     let system = System::new("deoxy-protocol-example");
     let addr = coord.start();
     addr.do_send(CoordMessage::Start(proto, None));
@@ -0,0 +1,27 @@
+//! Prints the JSON Schema of the configuration format (or, given `protocol`, of the protocol
+//! format), for checking files in an editor or in CI before they're deployed:
+//!
+//! ```sh
+//! cargo run --example schema --features schema > config.schema.json
+//! cargo run --example schema --features schema -- protocol > protocol.schema.json
+//! ```
+
+#[cfg(feature = "schema")]
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    use deoxy::{Config, Protocol};
+
+    let schema = match std::env::args().nth(1).as_deref() {
+        Some("protocol") => Protocol::json_schema(),
+        Some("config") | None => Config::json_schema(),
+        Some(other) => {
+            return Err(format!("Unknown format {} (try config or protocol).", other).into())
+        }
+    };
+    println!("{}", serde_json::to_string_pretty(&schema)?);
+    Ok(())
+}
+
+#[cfg(not(feature = "schema"))]
+fn main() {
+    eprintln!("This example requires the `schema` feature.");
+}
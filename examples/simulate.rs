@@ -0,0 +1,124 @@
+#[cfg(feature = "dashboard")]
+use deoxy::Dashboard;
+#[cfg(all(not(feature = "server"), not(feature = "dashboard")))]
+use deoxy::Tui;
+
+use std::error::Error;
+use std::time::Duration;
+
+use deoxy::{
+    actix::*, BackendConfig, Config, CoordMessage, Coordinator, MotorConfig, Protocol, PumpConfig,
+    PwmMode, Step, ValveConfig,
+};
+
+macro_rules! motor {
+    ($pin:expr) => {
+        ValveConfig::Servo(MotorConfig {
+            label: None,
+            pwm: PwmMode::Software,
+            inverted: false,
+            range_of_motion: 180,
+            angles: None,
+            positions: Default::default(),
+            ramp: None,
+            detach_after: None,
+            feedback: None,
+            period: Duration::from_millis(20),
+            pin: $pin,
+            range: [Duration::from_millis(1), Duration::from_millis(2)],
+        })
+    };
+}
+
+macro_rules! hours {
+    ($h:expr) => {
+        Some(Duration::new($h * 3600, 0))
+    };
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    #[cfg(not(feature = "dashboard"))]
+    pretty_env_logger::init();
+    #[cfg(feature = "dashboard")]
+    Dashboard::capture_logs(log::LevelFilter::Debug).map_err(|err| err.to_string())?;
+    let time_scale = match std::env::args().nth(1) {
+        Some(scale) => scale.parse()?,
+        None => 100.0,
+    };
+    let config = Config {
+        name: None,
+        pumps: vec![PumpConfig::new([24, 25, 5, 6])],
+        drain_pump: None,
+        motors: vec![motor!(4), motor!(27), motor!(21), motor!(13), motor!(19)],
+        calibration: None,
+        journal: None,
+        history: None,
+        library: None,
+        protocols: None,
+        event_log: None,
+        mqtt: None,
+        webhooks: vec![],
+        grpc: None,
+        modbus: None,
+        mail: None,
+        mail_templates: None,
+        fault_report: Default::default(),
+        chat: vec![],
+        sms: None,
+        push: vec![],
+        notification_throttle: Default::default(),
+        admins: vec![],
+        gpio: BackendConfig::default(),
+        prime: Default::default(),
+        flow_sensor: None,
+        pressure_sensor: None,
+        bubble_detector: None,
+        reservoirs: vec![],
+        thermal: None,
+        estop: None,
+        watchdog: None,
+        supervision: Default::default(),
+        overrun: Default::default(),
+        teardown: vec![],
+        wash: None,
+        tokens: vec![],
+        bootstrap_token: None,
+        cors: None,
+        throttle: Default::default(),
+        buffers: vec![],
+        manifolds: vec![],
+    };
+    let proto = Protocol {
+        steps: vec![
+            Step::Perfuse(1, hours!(4)),
+            Step::HoldTemperature {
+                target: 37.0,
+                tolerance: 0.5,
+                duration: Duration::new(2 * 3600, 0),
+            },
+            Step::Perfuse(2, hours!(4)),
+            Step::Perfuse(3, None),
+        ],
+    };
+    let coord = Coordinator::simulate(config, time_scale)?.handle_signals();
+    #[cfg(feature = "dashboard")]
+    let dashboard = Dashboard::open(
+        coord.library().ok().cloned(),
+        coord.catalog().ok().cloned(),
+        coord.hardware(),
+    )?;
+    let system = System::new("deoxy-simulation-example");
+    let addr = coord.start();
+    #[cfg(all(not(feature = "server"), not(feature = "dashboard")))]
+    {
+        let tui = Box::new(Tui {});
+        addr.do_send(CoordMessage::Subscribe(tui));
+    }
+    #[cfg(feature = "dashboard")]
+    {
+        addr.do_send(CoordMessage::Subscribe(Box::new(dashboard)));
+    }
+    addr.do_send(CoordMessage::Start(proto, None));
+    system.run();
+    Ok(())
+}
@@ -0,0 +1,40 @@
+/* A C interface to deoxy (see ffi/src/lib.rs for details). */
+#ifndef DEOXY_H
+#define DEOXY_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* A running coordinator. */
+typedef struct DeoxyHandle DeoxyHandle;
+
+/* Starts a coordinator using the configuration (TOML, JSON, or YAML, by its extension) at the
+   given path, returning NULL on failure. Given NULL, the configuration is looked for in
+   ./deoxy.toml, $XDG_CONFIG_HOME/deoxy/config.toml, and /etc/deoxy/config.toml, in that order.
+   Any DEOXY_* environment variables override the configuration's keys. */
+DeoxyHandle *deoxy_init(const char *config_path);
+
+/* Starts the given protocol (as JSON), returning 0 on success and -1 on failure. */
+int deoxy_submit(DeoxyHandle *handle, const char *protocol_json);
+
+/* The latest status (as JSON), to be freed with deoxy_string_free, or NULL on failure. */
+char *deoxy_status(const DeoxyHandle *handle);
+
+/* Aborts the running protocol, returning 0 on success and -1 on failure. */
+int deoxy_abort(DeoxyHandle *handle);
+
+/* Stops the coordinator and frees the handle. */
+void deoxy_free(DeoxyHandle *handle);
+
+/* Frees a string returned by deoxy_status. */
+void deoxy_string_free(char *string);
+
+/* Describes the last failure on this thread, or NULL; valid until the next call on this thread. */
+const char *deoxy_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,324 @@
+//! A C interface to deoxy, for embedding it in other instrument-control software (e.g. LabVIEW or
+//! C#) without going over HTTP.
+//!
+//! A coordinator is started with `deoxy_init` (from a TOML, JSON, or YAML configuration file, like
+//! `config-example.toml`), runs on a thread of its own, and is stopped with `deoxy_free`. Functions
+//! which can fail return `-1` (or `NULL`), after which `deoxy_last_error` describes what went wrong.
+//! Strings are UTF-8 and NUL-terminated. The declarations are in `include/deoxy.h`.
+#![deny(
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unused_import_braces
+)]
+#![deny(clippy::use_self, clippy::wildcard_dependencies)]
+#![warn(unused_qualifications)]
+
+#[macro_use]
+extern crate serde_derive;
+
+use deoxy::{
+    actix::{Actor, Addr, System},
+    event_log::Event,
+    Config, CoordMessage as Message, Coordinator, Protocol, Status, Subscribers, Update,
+};
+use futures::Future;
+
+use std::{
+    cell::RefCell,
+    ffi::{CStr, CString},
+    iter,
+    os::raw::{c_char, c_int},
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    ptr,
+    sync::{mpsc, Arc, Mutex, MutexGuard},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = RefCell::new(None);
+}
+
+/// Records the given failure for `deoxy_last_error`.
+fn fail(message: impl Into<String>) {
+    // Interior NULs would truncate the message anyway.
+    let message = message.into().replace('\0', " ");
+    let message = CString::new(message).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// Runs the given function, turning failures (and panics, which mustn't cross the FFI boundary)
+/// into the given value.
+fn guard<T>(failed: T, f: impl FnOnce() -> Result<T, String>) -> T {
+    // The coordinator lives on its own thread, so a panic here can't leave it in a bad state.
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => value,
+        Ok(Err(err)) => {
+            fail(err);
+            failed
+        }
+        Err(_) => {
+            fail("deoxy panicked");
+            failed
+        }
+    }
+}
+
+/// Reads the given C string.
+///
+/// # Safety
+///
+/// The pointer must be null or point to a NUL-terminated string.
+unsafe fn read<'a>(string: *const c_char, what: &str) -> Result<&'a str, String> {
+    if string.is_null() {
+        return Err(format!("No {} given", what));
+    }
+    CStr::from_ptr(string)
+        .to_str()
+        .map_err(|_| format!("The {} isn't valid UTF-8", what))
+}
+
+/// The given duration, in seconds.
+fn seconds(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) * 1e-9
+}
+
+/// What's known about the coordinator, as reported by `deoxy_status`.
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct Snapshot {
+    /// The latest status update, if there's been one.
+    last: Option<Event>,
+    /// Roughly how long until the program finishes (in seconds), if one is running.
+    remaining: Option<f64>,
+    /// How many jobs are waiting to be run after the current one.
+    queued: usize,
+}
+
+/// Keeps the snapshot up to date.
+#[derive(Debug)]
+struct Recorder {
+    snapshot: Arc<Mutex<Snapshot>>,
+}
+
+/// Locks the given snapshot.
+fn lock(snapshot: &Mutex<Snapshot>) -> MutexGuard<Snapshot> {
+    // A panic while holding the lock can't leave the snapshot in a bad state.
+    snapshot
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+impl Update for Recorder {
+    fn handle(&self, status: &Status, _coord: &Subscribers) {
+        *lock(&self.snapshot) = Snapshot {
+            last: Some(Event::new(status)),
+            remaining: status.remaining.map(seconds),
+            queued: status.queue.len(),
+        };
+    }
+}
+
+/// A running coordinator.
+#[derive(Debug)]
+pub struct DeoxyHandle {
+    addr: Addr<Coordinator>,
+    system: System,
+    thread: Option<JoinHandle<()>>,
+    snapshot: Arc<Mutex<Snapshot>>,
+}
+
+impl DeoxyHandle {
+    /// Starts a coordinator with the configuration at the given path (or else at the first of the
+    /// standard paths with a file).
+    fn start(path: Option<&Path>) -> Result<Self, String> {
+        let path = Config::locate(path).map_err(|err| err.to_string())?;
+        let config = Config::load(&path, iter::empty())
+            .map_err(|err| format!("{} ({})", err, path.display()))?;
+        config.validate().map_err(|err| err.to_string())?;
+        let snapshot = Arc::new(Mutex::new(Snapshot::default()));
+        let recorder = Recorder {
+            snapshot: Arc::clone(&snapshot),
+        };
+        let (sender, receiver) = mpsc::channel();
+        // The coordinator can't leave the thread it's created on, so the system lives there too.
+        let thread = thread::spawn(move || {
+            let runner = System::new("deoxy");
+            match Coordinator::try_new(config) {
+                Ok(coord) => {
+                    let addr = coord.start();
+                    addr.do_send(Message::Subscribe(Box::new(recorder)));
+                    let _ = sender.send(Ok((addr, System::current())));
+                }
+                Err(err) => {
+                    let _ = sender.send(Err(format!("Failed to start: {}", err)));
+                    return;
+                }
+            }
+            runner.run();
+        });
+        let (addr, system) = receiver
+            .recv()
+            .map_err(|_| "The coordinator thread died".to_string())??;
+        Ok(Self {
+            addr,
+            system,
+            thread: Some(thread),
+            snapshot,
+        })
+    }
+    /// Sends the given message to the coordinator on behalf of `ffi`, waiting for the result.
+    fn send(&self, message: Message) -> Result<c_int, String> {
+        let message = Message::Acting {
+            user: "ffi".into(),
+            message: Box::new(message),
+        };
+        match self.addr.send(message).wait() {
+            Ok(Ok(())) => Ok(0),
+            Ok(Err(err)) => Err(err.to_string()),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+}
+
+impl Drop for DeoxyHandle {
+    fn drop(&mut self) {
+        self.system.stop();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Starts a coordinator using the configuration (TOML, JSON, or YAML, by its extension) at the
+/// given path, returning null on failure.
+///
+/// Given a null path, the configuration is looked for at the standard paths (see
+/// `Config::locate`). Any `DEOXY_*` environment variables override the configuration's keys (see
+/// `Config::load`).
+///
+/// # Safety
+///
+/// The path must be null or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn deoxy_init(config_path: *const c_char) -> *mut DeoxyHandle {
+    guard(ptr::null_mut(), || {
+        let path = if config_path.is_null() {
+            None
+        } else {
+            Some(Path::new(read(config_path, "configuration path")?))
+        };
+        let handle = DeoxyHandle::start(path)?;
+        Ok(Box::into_raw(Box::new(handle)))
+    })
+}
+
+/// Starts the given protocol (as JSON, like the HTTP API takes), returning `0` on success and `-1`
+/// on failure.
+///
+/// # Safety
+///
+/// The handle must have come from `deoxy_init` (and not yet been freed), and the protocol must be
+/// null or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn deoxy_submit(
+    handle: *mut DeoxyHandle,
+    protocol_json: *const c_char,
+) -> c_int {
+    guard(-1, || {
+        let handle = handle.as_ref().ok_or("No handle given")?;
+        let protocol = read(protocol_json, "protocol")?;
+        let protocol = serde_json::from_str::<Protocol>(protocol)
+            .map_err(|err| format!("Invalid protocol: {}", err))?;
+        handle.send(Message::Start(protocol, None))
+    })
+}
+
+/// The latest status (as JSON), which must be freed with `deoxy_string_free`, or null on failure.
+///
+/// # Safety
+///
+/// The handle must have come from `deoxy_init` (and not yet been freed).
+#[no_mangle]
+pub unsafe extern "C" fn deoxy_status(handle: *const DeoxyHandle) -> *mut c_char {
+    guard(ptr::null_mut(), || {
+        let handle = handle.as_ref().ok_or("No handle given")?;
+        let snapshot = lock(&handle.snapshot).clone();
+        let json = serde_json::to_string(&snapshot).map_err(|err| err.to_string())?;
+        CString::new(json)
+            .map(CString::into_raw)
+            .map_err(|err| err.to_string())
+    })
+}
+
+/// Aborts the running protocol, returning `0` on success and `-1` on failure.
+///
+/// # Safety
+///
+/// The handle must have come from `deoxy_init` (and not yet been freed).
+#[no_mangle]
+pub unsafe extern "C" fn deoxy_abort(handle: *mut DeoxyHandle) -> c_int {
+    guard(-1, || {
+        let handle = handle.as_ref().ok_or("No handle given")?;
+        handle.send(Message::Abort)
+    })
+}
+
+/// Stops the coordinator and frees the handle.
+///
+/// # Safety
+///
+/// The handle must be null or have come from `deoxy_init` (and not yet been freed).
+#[no_mangle]
+pub unsafe extern "C" fn deoxy_free(handle: *mut DeoxyHandle) {
+    if !handle.is_null() {
+        guard((), || {
+            drop(Box::from_raw(handle));
+            Ok(())
+        })
+    }
+}
+
+/// Frees a string returned by `deoxy_status`.
+///
+/// # Safety
+///
+/// The string must be null or have come from `deoxy_status` (and not yet been freed).
+#[no_mangle]
+pub unsafe extern "C" fn deoxy_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/// Describes the last failure on the calling thread, or returns null if nothing has failed.
+///
+/// The string is valid until the next call into this library on the same thread.
+#[no_mangle]
+pub extern "C" fn deoxy_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |err| err.as_ptr())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn failures() {
+        let path = CString::new("/nonexistent/deoxy.toml").unwrap();
+        let handle = unsafe { deoxy_init(path.as_ptr()) };
+        assert!(handle.is_null());
+        let err = unsafe { CStr::from_ptr(deoxy_last_error()) };
+        assert!(err.to_str().unwrap().starts_with("Failed to read"));
+        assert_eq!(unsafe { deoxy_abort(ptr::null_mut()) }, -1);
+        let err = unsafe { CStr::from_ptr(deoxy_last_error()) };
+        assert_eq!(err.to_str().unwrap(), "No handle given");
+    }
+}
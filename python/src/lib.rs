@@ -0,0 +1,751 @@
+//! Python bindings for building deoxy protocols and driving deoxy servers.
+//!
+//! Protocols are built from `Step`s (durations are in seconds), checked locally with
+//! `Protocol.validate` (or against a description of the hardware with `Protocol.check`), and run
+//! with a `Client`, which talks to the server's HTTP API:
+//!
+//! ```python
+//! import deoxy
+//!
+//! protocol = deoxy.Protocol([deoxy.Step.perfuse(0, 600), deoxy.Step.perfuse(1)])
+//! client = deoxy.Client("http://deoxy.local:8080", token="...")
+//! job = client.start(protocol)
+//! ```
+//!
+//! Build with [maturin](https://github.com/PyO3/maturin) (`maturin develop` in this directory).
+#![deny(
+    missing_copy_implementations,
+    missing_debug_implementations,
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unused_import_braces
+)]
+#![deny(clippy::use_self, clippy::wildcard_dependencies)]
+#![warn(unused_qualifications)]
+
+use deoxy_core::{
+    Hardware as CoreHardware, Hook as CoreHook, MotorId, Notification, Overrun, OverrunPolicy,
+    Predicate, Protocol as CoreProtocol, ProtocolIssue, PumpId, Sensor, Step as CoreStep,
+    Validation as CoreValidation,
+};
+use pyo3::{class::basic::PyObjectProtocol, exceptions::ValueError, prelude::*};
+use reqwest::{
+    blocking::{Client as HttpClient, RequestBuilder, Response},
+    header::{ACCEPT, LOCATION},
+    Method,
+};
+
+use std::time::Duration;
+
+mod errors {
+    use pyo3::create_exception;
+    // Raised when a request to the server fails.
+    create_exception!(deoxy, ApiError, pyo3::exceptions::Exception);
+}
+
+use self::errors::ApiError;
+
+/// The given number of seconds, as a duration.
+fn duration(seconds: f64) -> PyResult<Duration> {
+    if !seconds.is_finite() || seconds < 0.0 {
+        return Err(ValueError::py_err(format!("Invalid duration: {}", seconds)));
+    }
+    Ok(Duration::new(
+        seconds.trunc() as u64,
+        (seconds.fract() * 1e9) as u32,
+    ))
+}
+
+/// The given duration, in seconds.
+fn seconds(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) * 1e-9
+}
+
+/// The sensor with the given name ("pressure", "flow", or "temperature").
+fn sensor(name: &str) -> PyResult<Sensor> {
+    match name {
+        "pressure" => Ok(Sensor::Pressure),
+        "flow" => Ok(Sensor::Flow),
+        "temperature" => Ok(Sensor::Temperature),
+        _ => Err(ValueError::py_err(format!("Invalid sensor: {}", name))),
+    }
+}
+
+/// The overrun policy with the given name ("continue", "warn", or "abort").
+fn policy(name: &str) -> PyResult<OverrunPolicy> {
+    match name {
+        "continue" => Ok(OverrunPolicy::Continue),
+        "warn" => Ok(OverrunPolicy::Warn),
+        "abort" => Ok(OverrunPolicy::Abort),
+        _ => Err(ValueError::py_err(format!("Invalid policy: {}", name))),
+    }
+}
+
+/// The predicate given by exactly one of a threshold above, a threshold below, or a target (with
+/// a tolerance).
+fn predicate(
+    above: Option<f64>,
+    below: Option<f64>,
+    target: Option<f64>,
+    tolerance: f64,
+) -> PyResult<Predicate> {
+    let predicate = match (above, below, target) {
+        (Some(threshold), None, None) => Predicate::Above(threshold),
+        (None, Some(threshold), None) => Predicate::Below(threshold),
+        (None, None, Some(target)) => Predicate::Within { target, tolerance },
+        _ => {
+            return Err(ValueError::py_err(
+                "Exactly one of above, below, or target must be given",
+            ))
+        }
+    };
+    if !predicate.is_valid() {
+        return Err(ValueError::py_err(format!(
+            "Invalid condition: {}",
+            predicate
+        )));
+    }
+    Ok(predicate)
+}
+
+/// The keyword arguments giving the given predicate (as accepted by `predicate`).
+fn predicate_args(predicate: &Predicate) -> String {
+    match predicate {
+        Predicate::Above(threshold) => format!("above={}", threshold),
+        Predicate::Below(threshold) => format!("below={}", threshold),
+        Predicate::Within { target, tolerance } => {
+            format!("target={}, tolerance={}", target, tolerance)
+        }
+    }
+}
+
+/// A step in a protocol.
+#[pyclass(module = "deoxy")]
+#[derive(Clone, Debug)]
+pub struct Step {
+    inner: CoreStep,
+}
+
+#[pymethods]
+impl Step {
+    /// Perfuses the given buffer for the given number of seconds (or until told otherwise).
+    #[staticmethod]
+    #[args(seconds = "None")]
+    fn perfuse(buffer: MotorId, seconds: Option<f64>) -> PyResult<Self> {
+        let duration = seconds.map(duration).transpose()?;
+        Ok(Self {
+            inner: CoreStep::Perfuse(buffer, duration),
+        })
+    }
+    /// Perfuses the given buffer, prompts the user with the given subject and message, waits for
+    /// acknowledgement and the given number of seconds, and notifies the user again.
+    #[staticmethod]
+    fn perfuse_prompt(
+        buffer: MotorId,
+        subject: String,
+        message: String,
+        seconds: f64,
+        done_subject: String,
+        done_message: String,
+    ) -> PyResult<Self> {
+        let prompt = Notification { subject, message };
+        let done = Notification {
+            subject: done_subject,
+            message: done_message,
+        };
+        Ok(Self {
+            inner: CoreStep::PerfusePrompt(buffer, prompt, duration(seconds)?, done),
+        })
+    }
+    /// Uses the given pump for all subsequent steps.
+    #[staticmethod]
+    fn use_pump(pump: PumpId) -> Self {
+        Self {
+            inner: CoreStep::UsePump(pump),
+        }
+    }
+    /// Brings the sample to the target temperature (in °C) and holds it within the given
+    /// tolerance (in K) for the given number of seconds.
+    #[staticmethod]
+    fn hold_temperature(target: f64, tolerance: f64, seconds: f64) -> PyResult<Self> {
+        Ok(Self {
+            inner: CoreStep::HoldTemperature {
+                target,
+                tolerance,
+                duration: duration(seconds)?,
+            },
+        })
+    }
+    /// Perfuses for the given number of seconds, ramping from the first buffer to the second by
+    /// switching between them every `period` seconds.
+    #[staticmethod]
+    #[args(period = "10.0")]
+    fn gradient(first: MotorId, second: MotorId, seconds: f64, period: f64) -> PyResult<Self> {
+        Ok(Self {
+            inner: CoreStep::Gradient {
+                from: first,
+                to: second,
+                duration: duration(seconds)?,
+                period: duration(period)?,
+            },
+        })
+    }
+    /// Agitates the chamber by running the pump forward and then backward for the given numbers of
+    /// seconds, the given number of times over.
+    #[staticmethod]
+    fn mix(cycles: usize, forward: f64, backward: f64) -> PyResult<Self> {
+        Ok(Self {
+            inner: CoreStep::Mix {
+                cycles,
+                forward: duration(forward)?,
+                backward: duration(backward)?,
+            },
+        })
+    }
+    /// Moves the given motor to the named position.
+    #[staticmethod]
+    fn set_position(motor: MotorId, position: String) -> Self {
+        Self {
+            inner: CoreStep::SetPosition { motor, position },
+        }
+    }
+    /// Runs the given steps the given number of times (e.g. for wash cycles).
+    #[staticmethod]
+    fn repeat(count: usize, steps: Vec<Step>) -> Self {
+        Self {
+            inner: CoreStep::Repeat {
+                count,
+                steps: steps.into_iter().map(|step| step.inner).collect(),
+            },
+        }
+    }
+    /// Waits until the reading of the named sensor ("pressure" in kPa, "flow" in mL, or
+    /// "temperature" in °C) is above or below a threshold, or within the tolerance of a target,
+    /// waiting for the user instead once the timeout (if any, in seconds) has passed.
+    #[staticmethod]
+    #[args(
+        above = "None",
+        below = "None",
+        target = "None",
+        tolerance = "0.0",
+        timeout = "None"
+    )]
+    fn wait_until(
+        sensor: &str,
+        above: Option<f64>,
+        below: Option<f64>,
+        target: Option<f64>,
+        tolerance: f64,
+        timeout: Option<f64>,
+    ) -> PyResult<Self> {
+        Ok(Self {
+            inner: CoreStep::WaitUntil {
+                sensor: self::sensor(sensor)?,
+                predicate: predicate(above, below, target, tolerance)?,
+                timeout: timeout.map(duration).transpose()?,
+            },
+        })
+    }
+    /// Runs the first steps if the reading of the named sensor satisfies the condition (given as
+    /// for `wait_until`), and the others otherwise.
+    #[staticmethod]
+    #[args(
+        otherwise = "Vec::new()",
+        above = "None",
+        below = "None",
+        target = "None",
+        tolerance = "0.0"
+    )]
+    fn if_else(
+        sensor: &str,
+        then: Vec<Step>,
+        otherwise: Vec<Step>,
+        above: Option<f64>,
+        below: Option<f64>,
+        target: Option<f64>,
+        tolerance: f64,
+    ) -> PyResult<Self> {
+        Ok(Self {
+            inner: CoreStep::IfElse {
+                sensor: self::sensor(sensor)?,
+                predicate: predicate(above, below, target, tolerance)?,
+                then: then.into_iter().map(|step| step.inner).collect(),
+                otherwise: otherwise.into_iter().map(|step| step.inner).collect(),
+            },
+        })
+    }
+    /// Takes the given step, firing the given hooks (in order) as it begins and once it has ended.
+    #[staticmethod]
+    #[args(begin = "Vec::new()", end = "Vec::new()")]
+    fn hooked(step: Step, begin: Vec<Hook>, end: Vec<Hook>) -> Self {
+        Self {
+            inner: CoreStep::Hooked {
+                step: Box::new(step.inner),
+                begin: begin.into_iter().map(|hook| hook.inner).collect(),
+                end: end.into_iter().map(|hook| hook.inner).collect(),
+            },
+        }
+    }
+    /// Takes the given step, letting its waits and temperature holds run up to `tolerance` seconds
+    /// long before applying the given policy ("continue", "warn", or "abort").
+    #[staticmethod]
+    fn timed(step: Step, tolerance: f64, policy: &str) -> PyResult<Self> {
+        Ok(Self {
+            inner: CoreStep::Timed {
+                step: Box::new(step.inner),
+                overrun: Overrun {
+                    tolerance: duration(tolerance)?,
+                    policy: self::policy(policy)?,
+                },
+            },
+        })
+    }
+}
+
+/// Something done as a step begins or ends (see `Step.hooked`).
+#[pyclass(module = "deoxy")]
+#[derive(Clone, Debug)]
+pub struct Hook {
+    inner: CoreHook,
+}
+
+#[pymethods]
+impl Hook {
+    /// Sends the user a notification with the given subject and message.
+    #[staticmethod]
+    fn notify(subject: String, message: String) -> Self {
+        Self {
+            inner: CoreHook::Notify(Notification { subject, message }),
+        }
+    }
+    /// Posts the given event to the server's webhooks.
+    #[staticmethod]
+    fn webhook(event: String) -> Self {
+        Self {
+            inner: CoreHook::Webhook(event),
+        }
+    }
+    /// Sends the user a notification with the given subject and message, and waits for them to
+    /// continue.
+    #[staticmethod]
+    fn confirm(subject: String, message: String) -> Self {
+        Self {
+            inner: CoreHook::Confirm(Notification { subject, message }),
+        }
+    }
+}
+
+#[pyproto]
+impl PyObjectProtocol for Hook {
+    fn __repr__(&self) -> PyResult<String> {
+        Ok(match &self.inner {
+            CoreHook::Notify(notification) => format!(
+                "Hook.notify({:?}, {:?})",
+                notification.subject, notification.message
+            ),
+            CoreHook::Webhook(event) => format!("Hook.webhook({:?})", event),
+            CoreHook::Confirm(notification) => format!(
+                "Hook.confirm({:?}, {:?})",
+                notification.subject, notification.message
+            ),
+        })
+    }
+}
+
+/// The representations of the given hooks, as a Python list.
+fn hook_reprs(hooks: &[CoreHook]) -> PyResult<String> {
+    let hooks = hooks
+        .iter()
+        .map(|hook| Hook {
+            inner: hook.clone(),
+        })
+        .map(|hook| hook.__repr__())
+        .collect::<PyResult<Vec<_>>>()?;
+    Ok(format!("[{}]", hooks.join(", ")))
+}
+
+/// The representations of the given steps, as a Python list.
+fn reprs(steps: &[CoreStep]) -> PyResult<String> {
+    let steps = steps
+        .iter()
+        .map(|step| Step {
+            inner: step.clone(),
+        })
+        .map(|step| step.__repr__())
+        .collect::<PyResult<Vec<_>>>()?;
+    Ok(format!("[{}]", steps.join(", ")))
+}
+
+#[pyproto]
+impl PyObjectProtocol for Step {
+    fn __repr__(&self) -> PyResult<String> {
+        Ok(match &self.inner {
+            CoreStep::Perfuse(buffer, None) => format!("Step.perfuse({})", buffer),
+            CoreStep::Perfuse(buffer, Some(duration)) => {
+                format!("Step.perfuse({}, {})", buffer, seconds(*duration))
+            }
+            CoreStep::PerfusePrompt(buffer, prompt, duration, done) => format!(
+                "Step.perfuse_prompt({}, {:?}, {:?}, {}, {:?}, {:?})",
+                buffer,
+                prompt.subject,
+                prompt.message,
+                seconds(*duration),
+                done.subject,
+                done.message
+            ),
+            CoreStep::UsePump(pump) => format!("Step.use_pump({})", pump),
+            CoreStep::HoldTemperature {
+                target,
+                tolerance,
+                duration,
+            } => format!(
+                "Step.hold_temperature({}, {}, {})",
+                target,
+                tolerance,
+                seconds(*duration)
+            ),
+            CoreStep::Gradient {
+                from,
+                to,
+                duration,
+                period,
+            } => format!(
+                "Step.gradient({}, {}, {}, period={})",
+                from,
+                to,
+                seconds(*duration),
+                seconds(*period)
+            ),
+            CoreStep::Mix {
+                cycles,
+                forward,
+                backward,
+            } => format!(
+                "Step.mix({}, {}, {})",
+                cycles,
+                seconds(*forward),
+                seconds(*backward)
+            ),
+            CoreStep::SetPosition { motor, position } => {
+                format!("Step.set_position({}, {:?})", motor, position)
+            }
+            CoreStep::Repeat { count, steps } => {
+                format!("Step.repeat({}, {})", count, reprs(steps)?)
+            }
+            CoreStep::WaitUntil {
+                sensor,
+                predicate,
+                timeout,
+            } => match timeout {
+                Some(timeout) => format!(
+                    "Step.wait_until({:?}, {}, timeout={})",
+                    sensor.to_string(),
+                    predicate_args(predicate),
+                    seconds(*timeout)
+                ),
+                None => format!(
+                    "Step.wait_until({:?}, {})",
+                    sensor.to_string(),
+                    predicate_args(predicate)
+                ),
+            },
+            CoreStep::IfElse {
+                sensor,
+                predicate,
+                then,
+                otherwise,
+            } => format!(
+                "Step.if_else({:?}, {}, {}, {})",
+                sensor.to_string(),
+                reprs(then)?,
+                reprs(otherwise)?,
+                predicate_args(predicate)
+            ),
+            CoreStep::Hooked { step, begin, end } => format!(
+                "Step.hooked({}, begin={}, end={})",
+                Step {
+                    inner: (**step).clone(),
+                }
+                .__repr__()?,
+                hook_reprs(begin)?,
+                hook_reprs(end)?
+            ),
+            CoreStep::Timed { step, overrun } => format!(
+                "Step.timed({}, {}, {:?})",
+                Step {
+                    inner: (**step).clone(),
+                }
+                .__repr__()?,
+                seconds(overrun.tolerance),
+                overrun.policy.to_string()
+            ),
+        })
+    }
+}
+
+/// The hardware a protocol will be run on, as far as checking it is concerned.
+#[pyclass(module = "deoxy")]
+#[derive(Clone, Debug)]
+pub struct Hardware {
+    inner: CoreHardware,
+}
+
+#[pymethods]
+impl Hardware {
+    /// Describes hardware with the given motors (given as each one's additional positions, where
+    /// motor 0 is the waste valve), number of pumps, and thermostat (or lack thereof).
+    #[new]
+    #[args(pumps = "1", thermostat = "false")]
+    fn new(positions: Vec<Vec<String>>, pumps: usize, thermostat: bool) -> Self {
+        Self {
+            inner: CoreHardware {
+                positions,
+                pumps,
+                thermostat,
+                ..CoreHardware::default()
+            },
+        }
+    }
+}
+
+/// The outcome of checking a protocol.
+#[pyclass(module = "deoxy")]
+#[derive(Clone, Debug)]
+pub struct Validation {
+    /// Problems which prevent the protocol from being run.
+    #[pyo3(get)]
+    errors: Vec<String>,
+    /// Oddities which probably aren't intended.
+    #[pyo3(get)]
+    warnings: Vec<String>,
+}
+
+#[pymethods]
+impl Validation {
+    /// Whether the protocol may be run.
+    #[getter]
+    fn ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+impl From<CoreValidation> for Validation {
+    fn from(validation: CoreValidation) -> Self {
+        let describe =
+            |issues: Vec<ProtocolIssue>| issues.iter().map(ToString::to_string).collect();
+        Self {
+            errors: describe(validation.errors),
+            warnings: describe(validation.warnings),
+        }
+    }
+}
+
+/// A series of steps to run.
+#[pyclass(module = "deoxy")]
+#[derive(Clone, Debug)]
+pub struct Protocol {
+    inner: CoreProtocol,
+}
+
+#[pymethods]
+impl Protocol {
+    /// Creates a protocol from the given steps.
+    #[new]
+    fn new(steps: Vec<Step>) -> Self {
+        Self {
+            inner: CoreProtocol {
+                steps: steps.into_iter().map(|step| step.inner).collect(),
+            },
+        }
+    }
+    /// The protocol's steps.
+    #[getter]
+    fn steps(&self) -> Vec<Step> {
+        self.inner
+            .steps
+            .iter()
+            .map(|step| Step {
+                inner: step.clone(),
+            })
+            .collect()
+    }
+    /// Raises `ValueError` if the protocol is structurally invalid (e.g. it doesn't end with an
+    /// indefinite perfusion).
+    fn validate(&self) -> PyResult<()> {
+        self.inner
+            .validate()
+            .map_err(|err| ValueError::py_err(ProtocolIssue::Structure(err).to_string()))
+    }
+    /// Checks the protocol against the given hardware, collecting every problem found.
+    fn check(&self, hardware: PyRef<Hardware>) -> Validation {
+        self.inner.check(&hardware.inner).into()
+    }
+    /// The protocol as JSON, as taken by the server.
+    fn to_json(&self) -> PyResult<String> {
+        serde_json::to_string(&self.inner).map_err(|err| ValueError::py_err(err.to_string()))
+    }
+    /// Reads a protocol from JSON.
+    #[staticmethod]
+    fn from_json(json: &str) -> PyResult<Self> {
+        serde_json::from_str(json)
+            .map(|inner| Self { inner })
+            .map_err(|err| ValueError::py_err(format!("Invalid protocol: {}", err)))
+    }
+}
+
+#[pyproto]
+impl PyObjectProtocol for Protocol {
+    fn __repr__(&self) -> PyResult<String> {
+        let steps = self
+            .steps()
+            .iter()
+            .map(|step| step.__repr__())
+            .collect::<PyResult<Vec<_>>>()?;
+        Ok(format!("Protocol([{}])", steps.join(", ")))
+    }
+}
+
+/// A client for a deoxy server's HTTP API.
+///
+/// Failed requests raise `ApiError`.
+#[pyclass(module = "deoxy")]
+#[derive(Debug)]
+pub struct Client {
+    http: HttpClient,
+    /// The server's address.
+    root: String,
+    /// Where the manifold's jobs are handled.
+    base: String,
+    token: Option<String>,
+}
+
+/// Turns a failed request into an exception.
+fn api(err: reqwest::Error) -> PyErr {
+    ApiError::py_err(err.to_string())
+}
+
+impl Client {
+    fn request(&self, method: Method, url: String) -> RequestBuilder {
+        let request = self.http.request(method, &url);
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+    /// Sends the given request, raising `ApiError` unless it succeeds.
+    fn send(request: RequestBuilder) -> PyResult<Response> {
+        let response = request.send().map_err(api)?;
+        let status = response.status();
+        if status.is_success() {
+            Ok(response)
+        } else {
+            let body = response.text().unwrap_or_default();
+            Err(ApiError::py_err(format!("{}: {}", status, body)))
+        }
+    }
+    /// Sends the given protocol to the given path, returning the ID of the job created.
+    fn submit(&self, path: &str, protocol: &Protocol) -> PyResult<String> {
+        let url = format!("{}{}", self.base, path);
+        let response = Self::send(self.request(Method::POST, url).json(&protocol.inner))?;
+        response
+            .headers()
+            .get(LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .map(|location| location.rsplit('/').next().unwrap_or(location).to_string())
+            .ok_or_else(|| ApiError::py_err("The server didn't say where the job is"))
+    }
+    /// Sends the given method to the given job's endpoint.
+    fn control(&self, method: Method, job: &str, action: &str) -> PyResult<()> {
+        let url = format!("{}/{}{}", self.base, job, action);
+        Self::send(self.request(method, url)).map(|_| ())
+    }
+}
+
+#[pymethods]
+impl Client {
+    /// Connects to the server at the given address (e.g. `http://deoxy.local:8080`), presenting
+    /// the given API token (if any) and addressing the named manifold (or the main one).
+    #[new]
+    #[args(token = "None", manifold = "None")]
+    fn new(url: &str, token: Option<String>, manifold: Option<&str>) -> Self {
+        let root = url.trim_end_matches('/').to_string();
+        let base = match manifold {
+            Some(name) => format!("{}/manifolds/{}", root, name),
+            None => root.clone(),
+        };
+        Self {
+            http: HttpClient::new(),
+            root,
+            base,
+            token,
+        }
+    }
+    /// The current (or most recent) job, as a dictionary, or `None` if there hasn't been one.
+    fn status(&self, py: Python) -> PyResult<PyObject> {
+        let url = format!("{}/", self.base);
+        let request = self
+            .request(Method::GET, url)
+            .header(ACCEPT, "application/json");
+        let body = Self::send(request)?.text().map_err(api)?;
+        let json = py.import("json")?;
+        Ok(json.call1("loads", (body,))?.to_object(py))
+    }
+    /// Checks the given protocol against the server's hardware.
+    fn validate(&self, protocol: PyRef<Protocol>) -> PyResult<Validation> {
+        let url = format!("{}/protocols/validate", self.root);
+        let request = self.request(Method::POST, url).json(&protocol.inner);
+        let validation = Self::send(request)?.json::<CoreValidation>().map_err(api)?;
+        Ok(validation.into())
+    }
+    /// Starts the given protocol, returning the job's ID.
+    fn start(&self, protocol: PyRef<Protocol>) -> PyResult<String> {
+        self.submit("/", &protocol)
+    }
+    /// Adds the given protocol to the queue, returning the job's ID.
+    fn enqueue(&self, protocol: PyRef<Protocol>) -> PyResult<String> {
+        self.submit("/queue", &protocol)
+    }
+    /// Pauses the given job.
+    fn pause(&self, job: &str) -> PyResult<()> {
+        self.control(Method::POST, job, "/pause")
+    }
+    /// Picks the given paused job back up.
+    fn unpause(&self, job: &str) -> PyResult<()> {
+        self.control(Method::POST, job, "/unpause")
+    }
+    /// Continues the given job, which is waiting for confirmation.
+    fn resume(&self, job: &str) -> PyResult<()> {
+        self.control(Method::POST, job, "/resume")
+    }
+    /// Skips the rest of the current step of the given paused job.
+    fn skip(&self, job: &str) -> PyResult<()> {
+        self.control(Method::POST, job, "/skip")
+    }
+    /// Aborts the given job, running the teardown sequence.
+    fn abort(&self, job: &str) -> PyResult<()> {
+        self.control(Method::POST, job, "/abort")
+    }
+    /// Stops the given job cleanly.
+    fn stop(&self, job: &str) -> PyResult<()> {
+        self.control(Method::DELETE, job, "")
+    }
+    /// Clears a latched fault (such as an emergency stop).
+    fn reset(&self) -> PyResult<()> {
+        let url = format!("{}/reset", self.base);
+        Self::send(self.request(Method::POST, url)).map(|_| ())
+    }
+}
+
+/// Builds deoxy protocols and drives deoxy servers.
+#[pymodule]
+fn deoxy(py: Python, module: &PyModule) -> PyResult<()> {
+    module.add_class::<Step>()?;
+    module.add_class::<Hook>()?;
+    module.add_class::<Hardware>()?;
+    module.add_class::<Validation>()?;
+    module.add_class::<Protocol>()?;
+    module.add_class::<Client>()?;
+    module.add("ApiError", py.get_type::<ApiError>())?;
+    Ok(())
+}
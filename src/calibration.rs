@@ -0,0 +1,149 @@
+//! Motor calibration.
+//!
+//! Servo endpoints vary from unit to unit, so the pulse widths corresponding to each valve
+//! position can be found interactively (see
+//! [`CoordMessage::Calibrate`](../enum.CoordMessage.html#variant.Calibrate)) and persisted to a
+//! sidecar calibration file.
+//!
+//! The file contains one line per motor, giving the motor's index followed by its open, closed,
+//! and shut pulse widths in microseconds:
+//!
+//! ```text
+//! # motor open close shut
+//! 1 620 1480 2390
+//! ```
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+    time::Duration,
+};
+
+use crate::MotorId;
+
+/// A valve position that can be marked during calibration.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "use_serde", serde(rename_all = "lowercase"))]
+pub enum Mark {
+    /// Fluid from the associated buffer flows through the valve.
+    Open,
+    /// Fluid flows through the valve, but not from the associated buffer.
+    Close,
+    /// No fluid flows through the valve.
+    Shut,
+}
+
+/// The pulse widths corresponding to each position of a motor.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+pub struct Calibration {
+    /// The pulse width at the open position.
+    pub open: Duration,
+    /// The pulse width at the closed position.
+    pub close: Duration,
+    /// The pulse width at the shut position.
+    pub shut: Duration,
+}
+
+impl Calibration {
+    /// The pulse width for the given position.
+    pub fn get(&self, mark: Mark) -> Duration {
+        match mark {
+            Mark::Open => self.open,
+            Mark::Close => self.close,
+            Mark::Shut => self.shut,
+        }
+    }
+    /// Sets the pulse width for the given position.
+    pub fn set(&mut self, mark: Mark, pulse_width: Duration) {
+        match mark {
+            Mark::Open => self.open = pulse_width,
+            Mark::Close => self.close = pulse_width,
+            Mark::Shut => self.shut = pulse_width,
+        }
+    }
+}
+
+/// The calibrations of a set of motors, keyed by motor.
+pub type Calibrations = BTreeMap<MotorId, Calibration>;
+
+fn micros(duration: Duration) -> u64 {
+    duration.as_secs() * 1_000_000 + u64::from(duration.subsec_micros())
+}
+
+/// Parses the contents of a calibration file.
+fn parse(contents: &str) -> Result<Calibrations, String> {
+    let mut calibrations = Calibrations::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let fields = line
+            .split_whitespace()
+            .map(str::parse::<u64>)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| format!("line {}: {}", number + 1, err))?;
+        match fields[..] {
+            [motor, open, close, shut] => {
+                calibrations.insert(
+                    motor as MotorId,
+                    Calibration {
+                        open: Duration::from_micros(open),
+                        close: Duration::from_micros(close),
+                        shut: Duration::from_micros(shut),
+                    },
+                );
+            }
+            _ => return Err(format!("line {}: expected four fields", number + 1)),
+        }
+    }
+    Ok(calibrations)
+}
+
+/// Loads the calibrations from the given file.
+///
+/// A missing file is treated as empty.
+pub fn load(path: impl AsRef<Path>) -> io::Result<Calibrations> {
+    match fs::read_to_string(path) {
+        Ok(contents) => parse(&contents).map_err(|err| io::Error::new(ErrorKind::InvalidData, err)),
+        Err(ref err) if err.kind() == ErrorKind::NotFound => Ok(Calibrations::new()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Saves the given calibrations to the given file, replacing its contents.
+pub fn save(path: impl AsRef<Path>, calibrations: &Calibrations) -> io::Result<()> {
+    let mut contents = String::from("# motor open close shut (µs)\n");
+    for (motor, calibration) in calibrations {
+        contents.push_str(&format!(
+            "{} {} {} {}\n",
+            motor,
+            micros(calibration.open),
+            micros(calibration.close),
+            micros(calibration.shut)
+        ));
+    }
+    fs::write(path, contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn parse_calibrations() {
+        let calibrations = parse("# motor open close shut\n\n1 620 1480 2390 # waste\n").unwrap();
+        assert_eq!(
+            calibrations.get(&1),
+            Some(&Calibration {
+                open: Duration::from_micros(620),
+                close: Duration::from_micros(1480),
+                shut: Duration::from_micros(2390),
+            })
+        );
+        assert!(parse("1 620 1480").is_err());
+        assert!(parse("1 620 1480 shut").is_err());
+    }
+}
@@ -0,0 +1,147 @@
+//! A catalog of protocol files.
+//!
+//! The catalog indexes a directory of protocol files (see [`ProtocolFile`]), naming each protocol
+//! for its file (so `rinse.toml` is `rinse`). Unlike the [`Library`], which the interfaces write
+//! to, the directory is meant to be managed by hand (e.g. as a checkout of a repository of
+//! protocols): the coordinator checks it for changes, so a protocol can be launched by name as soon
+//! as its file is saved.
+//!
+//! [`ProtocolFile`]: ../struct.ProtocolFile.html
+//! [`Library`]: ../struct.Library.html
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock, RwLockReadGuard},
+    time::SystemTime,
+};
+
+use crate::ProtocolFile;
+
+/// A protocol file in the catalog.
+#[derive(Clone, Debug, Serialize)]
+pub struct CatalogEntry {
+    /// The name of the protocol (the file's name, without its extension).
+    pub name: String,
+    /// The path of the file.
+    pub path: PathBuf,
+    /// The protocol, as written in the file.
+    pub file: ProtocolFile,
+}
+
+/// The indexed files.
+#[derive(Debug, Default)]
+struct Index {
+    entries: BTreeMap<String, CatalogEntry>,
+    /// When each file in the directory was last modified, as of the last check.
+    modified: BTreeMap<PathBuf, Option<SystemTime>>,
+}
+
+/// A directory of protocol files, indexed by name.
+///
+/// Clones share the index, so a catalog refreshed by the coordinator is up to date wherever it's
+/// been handed out.
+#[derive(Clone, Debug)]
+pub struct Catalog {
+    dir: PathBuf,
+    index: Arc<RwLock<Index>>,
+}
+
+impl Catalog {
+    /// Indexes the protocol files in the given directory.
+    pub fn open(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let catalog = Self {
+            dir: dir.into(),
+            index: Arc::default(),
+        };
+        catalog.refresh()?;
+        Ok(catalog)
+    }
+    /// The directory indexed.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+    fn index(&self) -> RwLockReadGuard<Index> {
+        // A panic while holding the lock can't leave the index in a bad state.
+        self.index
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+    /// Indexes the directory again if any protocol file has been added, removed, or modified since
+    /// the last check, returning whether anything changed.
+    ///
+    /// Files which can't be read are skipped (with a warning).
+    pub fn refresh(&self) -> io::Result<bool> {
+        let mut modified = BTreeMap::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            let extension = path.extension().and_then(|extension| extension.to_str());
+            if let Some("toml") | Some("json") = extension {
+                let time = fs::metadata(&path).and_then(|metadata| metadata.modified());
+                modified.insert(path, time.ok());
+            }
+        }
+        if self.index().modified == modified {
+            return Ok(false);
+        }
+        let mut entries = BTreeMap::new();
+        for path in modified.keys() {
+            let name = match path.file_stem().and_then(|stem| stem.to_str()) {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+            if entries.contains_key(&name) {
+                log::warn!("Skipping {}: {} is already cataloged", path.display(), name);
+                continue;
+            }
+            match ProtocolFile::from_path(path) {
+                Ok(file) => {
+                    let path = path.clone();
+                    entries.insert(name.clone(), CatalogEntry { name, path, file });
+                }
+                Err(err) => log::warn!("Skipping {}: {}", path.display(), err),
+            }
+        }
+        let mut index = self
+            .index
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        *index = Index { entries, modified };
+        Ok(true)
+    }
+    /// Lists every protocol in the catalog, ordered by name.
+    pub fn list(&self) -> Vec<CatalogEntry> {
+        self.index().entries.values().cloned().collect()
+    }
+    /// Fetches the protocol with the given name, if there is one.
+    pub fn get(&self, name: &str) -> Option<CatalogEntry> {
+        self.index().entries.get(name).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+    #[test]
+    fn indexes_files() {
+        let dir = std::env::temp_dir().join(format!("deoxy-catalog-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("rinse.toml"), "[[steps]]\nbuffer = 0\n").unwrap();
+        fs::write(dir.join("notes.txt"), "Not a protocol").unwrap();
+        let catalog = Catalog::open(&dir).unwrap();
+        let names = catalog.list().into_iter().map(|entry| entry.name);
+        assert_eq!(names.collect::<Vec<_>>(), vec!["rinse"]);
+        assert!(!catalog.refresh().unwrap());
+        fs::write(dir.join("wash.json"), r#"{"steps": [{"buffer": "PBS"}]}"#).unwrap();
+        fs::write(dir.join("broken.toml"), "steps = 1").unwrap();
+        assert!(catalog.clone().refresh().unwrap());
+        assert!(catalog.get("wash").is_some());
+        assert!(catalog.get("broken").is_none());
+        fs::remove_file(dir.join("rinse.toml")).unwrap();
+        assert!(catalog.refresh().unwrap());
+        assert!(catalog.get("rinse").is_none());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,18 +1,75 @@
 //! Communication utilities.
 use crate::actix::*;
 use crate::{
-    mail, Action, Config, Motor, MotorId, MotorMessage, PinError, Program, Protocol, Pump,
-    PumpMessage, Step, ValidateProtocolError,
+    calibration::{self, Calibration, Calibrations},
+    config::{
+        BubbleDetectorConfig, Buffers, ConfigError, EStopConfig, FlowAlarm, FlowSensorConfig,
+        NotificationEvent, PressureSensorConfig, PrimeConfig, TeardownStep, WashConfig,
+    },
+    has_buffer,
+    journal::{self, Recovery},
+    mail::{self, Email, FaultReport, Mailer, StepTiming, Summary, Templates},
+    motor::{Calibrate, CalibrationState, Position, Recalibrate, Verify},
+    notify::{Notification, Notifiers, Status as Notice},
+    pin::{Edge, EdgeEvent, Input, InputPin},
+    quantity::FlowRate,
+    schedule::{Schedule, ScheduledProtocol, MIN_SCHEDULE_INTERVAL},
+    sensor::{
+        flow::Message as FlowMessage,
+        level::{Level, Message as LevelMessage, Reservoir},
+        pressure::Message as PressureMessage,
+        BubbleDetector, FlowSensor, PressureSensor, Reservoirs,
+    },
+    stepper::StepperMotor,
+    thermal::{Message as ThermalMessage, Thermostat},
+    watchdog::{self, Ping, Reason, Tripped, Watchdog},
+    Action, BackendConfig, Config, Device, DeviceError, DeviceFailed, GpioBackend, Hardware,
+    Iteration, Motor, MotorId, MotorMessage, Overrun, OverrunPolicy, PinError, Predicate, Program,
+    Protocol, Pump, PumpConfig, PumpDirection, PumpId, PumpMessage, Sensor, Step,
+    SupervisionConfig, ValidateProtocolError, Validation, ValveConfig, WatchdogConfig,
+};
+
+#[cfg(feature = "history")]
+use crate::history::{self, History, Outcome, Run};
+#[cfg(feature = "modbus")]
+use crate::modbus::Modbus;
+#[cfg(feature = "use_serde")]
+use crate::{
+    catalog::Catalog, event_log::EventLog, library::Library, Autoprotocol, AutoprotocolError,
+    Bindings, ProtocolFileError,
 };
+#[cfg(feature = "mqtt")]
+use crate::{config::MqttConfig, mqtt::Mqtt};
+#[cfg(feature = "webhooks")]
+use crate::{config::WebhookConfig, webhook::Webhooks};
+#[cfg(feature = "grpc")]
+use crate::{grpc, TokenConfig};
+#[cfg(any(feature = "grpc", feature = "modbus"))]
+use std::net::SocketAddr;
 
+use actix_web::actix::{
+    fut,
+    signal::{ProcessSignals, Signal, SignalType, Subscribe},
+    ActorFuture, MailboxError, Recipient, SystemService, WrapFuture,
+};
+use futures::{future, Future};
 use lazy_static::lazy_static;
 use uom::si::f64::*;
+use uom::si::pressure::kilopascal;
+use uom::si::thermodynamic_temperature::degree_celsius;
 use uom::si::time::second;
 use uom::si::volume::milliliter;
 use uom::si::volume_rate::milliliter_per_second;
 use uuid::Uuid;
 
-use std::{fmt, ops::Index, time::Duration};
+use std::{
+    collections::HashMap,
+    fmt, fs, mem,
+    ops::Index,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
 
 lazy_static! {
     static ref VOLUME: Volume = Volume::new::<milliliter>(500.0);
@@ -26,6 +83,22 @@ lazy_static! {
     };
     // Motor delay after motor motion before the pump starts
     static ref PUMP_DELAY: Duration = Duration::new(2, 0);
+    // How long the line is cleared through the waste valve after perfusion
+    static ref CLEAR_DURATION: Duration = Duration::new(10, 0);
+    // How long a motor is given to reach a named position
+    static ref SETTLE_DELAY: Duration = Duration::new(5, 0);
+    // How often reservoir levels are checked
+    static ref LEVEL_INTERVAL: Duration = Duration::new(5, 0);
+    // How often summaries of suppressed notifications are sent, once their storms are over
+    static ref NOTIFICATION_FLUSH_INTERVAL: Duration = Duration::new(30, 0);
+    // How often the watched configuration file and the catalog (if any) are checked for changes
+    static ref CONFIG_POLL_INTERVAL: Duration = Duration::new(2, 0);
+    // How often the temperature is checked while waiting for it to be reached
+    static ref TEMPERATURE_INTERVAL: Duration = Duration::new(1, 0);
+    // How often a sensor is read while waiting for its reading to satisfy a condition
+    static ref CONDITION_INTERVAL: Duration = Duration::new(1, 0);
+    // How long valves are given to shut before their signals are turned off at shutdown
+    static ref SHUTDOWN_DELAY: Duration = Duration::new(5, 0);
 }
 
 type Result<T> = std::result::Result<T, Error>;
@@ -50,10 +123,121 @@ type CoordContext = Context<Coordinator>;
 pub enum Error {
     /// An error was encountered in converting a protocol to a program.
     ProtocolConversion(ValidateProtocolError),
+    /// The protocol can't be run on this system (see [`Protocol::check`]).
+    ///
+    /// [`Protocol::check`]: struct.Protocol.html#method.check
+    Invalid(Validation),
     /// We tried to start a new protocol while one was already running.
     Busy,
     /// A pin-related initialization error occured.
     Pin(PinError),
+    /// A motor or pump couldn't be set up as configured.
+    Device(DeviceError),
+    /// The configuration has mistakes (every one found is listed).
+    Config(ConfigError),
+    /// The given pump does not exist.
+    NoSuchPump(PumpId),
+    /// The protocol requires temperature control, but none is configured.
+    NoThermostat,
+    /// The given motor does not exist (or cannot be calibrated).
+    NoSuchMotor(MotorId),
+    /// The given motor can't be moved to the given angle (in degrees).
+    OutOfRange {
+        /// The motor in question.
+        motor: MotorId,
+        /// The angle requested.
+        angle: u16,
+    },
+    /// A calibration request was made while no motor was being calibrated.
+    NotCalibrating,
+    /// The calibration file could not be read or written.
+    Calibration(std::io::Error),
+    /// The given motor does not exist or has no position with the given name.
+    NoSuchPosition {
+        /// The motor in question.
+        motor: MotorId,
+        /// The name of the position.
+        position: String,
+    },
+    /// A fault has been latched and must be reset before continuing.
+    Faulted,
+    /// The request requires the program to be paused first.
+    NotPaused,
+    /// The program has no step with the given index.
+    NoSuchStep(usize),
+    /// Jumping to the given step would repeat steps, which requires forcing the jump.
+    Backward(usize),
+    /// The emergency stop is still pressed.
+    EmergencyStop,
+    /// The given job is not in the queue.
+    NotQueued(Uuid),
+    /// No pending schedule has the given label.
+    NotScheduled(Uuid),
+    /// The schedule recurs at the given interval, which is shorter than
+    /// [`MIN_SCHEDULE_INTERVAL`](constant.MIN_SCHEDULE_INTERVAL.html).
+    IntervalTooShort(Duration),
+    /// The simulation time-scale factor must be positive (and finite).
+    InvalidTimeScale(f64),
+    /// An interrupted run must be recovered (see [`Message::Recover`]) before starting another.
+    ///
+    /// [`Message::Recover`]: enum.Message.html#variant.Recover
+    Interrupted,
+    /// There is no interrupted run to recover.
+    NotInterrupted,
+    /// No run history is kept.
+    NoHistory,
+    /// The event log could not be opened.
+    EventLog(std::io::Error),
+    /// No protocol library is kept.
+    NoLibrary,
+    /// The protocol library could not be read or written.
+    Library(std::io::Error),
+    /// No protocol files are cataloged.
+    NoCatalog,
+    /// No wash routine is configured.
+    NoWash,
+    /// The catalog's directory could not be read.
+    Catalog(std::io::Error),
+    /// No protocol file has the given name.
+    NoSuchProtocol(String),
+    /// The protocol file could not be applied to this system's hardware (e.g. it names a buffer
+    /// that isn't configured).
+    #[cfg(feature = "use_serde")]
+    ProtocolFile(ProtocolFileError),
+    /// The protocol could not be converted to or from Autoprotocol.
+    #[cfg(feature = "use_serde")]
+    Autoprotocol(AutoprotocolError),
+    /// The notification templates could not be loaded.
+    Templates(mail::TemplateError),
+    /// More than one manifold has the given name.
+    DuplicateManifold(String),
+    /// The new configuration uses the pins differently, which takes a restart (see
+    /// [`Message::ReloadConfig`]).
+    ///
+    /// [`Message::ReloadConfig`]: enum.Message.html#variant.ReloadConfig
+    RestartRequired,
+    /// The run history could not be read or written.
+    #[cfg(feature = "history")]
+    History(history::Error),
+    /// The protocol needs more of the given buffer than its reservoir holds.
+    InsufficientBuffer {
+        /// The buffer in question.
+        buffer: MotorId,
+        /// The volume the protocol needs.
+        required: Volume,
+        /// The volume remaining in the reservoir.
+        available: Volume,
+    },
+    /// The level of the given buffer's reservoir hasn't been measured yet, so it can't be known to
+    /// hold enough for the protocol.
+    LevelUnknown(MotorId),
+}
+
+#[cfg(feature = "history")]
+impl From<history::Error> for Error {
+    fn from(err: history::Error) -> Self {
+        Self::History(err)
+    }
 }
 
 impl From<ValidateProtocolError> for Error {
@@ -68,13 +252,123 @@ impl From<PinError> for Error {
     }
 }
 
+impl From<DeviceError> for Error {
+    fn from(err: DeviceError) -> Self {
+        Self::Device(err)
+    }
+}
+
+impl From<ConfigError> for Error {
+    fn from(err: ConfigError) -> Self {
+        Self::Config(err)
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Coordinator error: {:?}", self)
+        match self {
+            Self::ProtocolConversion(_) => write!(f, "Couldn't convert the protocol to a program"),
+            Self::Invalid(_) => write!(f, "The protocol can't be run on this system"),
+            Self::Busy => write!(f, "A protocol is already running"),
+            Self::Pin(_) => write!(f, "Couldn't set up the pins"),
+            Self::Device(_) => write!(f, "Couldn't set up a device"),
+            Self::Config(_) => write!(f, "The configuration is invalid"),
+            Self::NoSuchPump(pump) => write!(f, "There is no pump {}", pump),
+            Self::NoThermostat => write!(f, "No thermostat is configured"),
+            Self::NoSuchMotor(motor) => write!(f, "There is no motor {}", motor),
+            Self::OutOfRange { motor, angle } => {
+                write!(f, "Motor {} can't be moved to {}°", motor, angle)
+            }
+            Self::NotCalibrating => write!(f, "No motor is being calibrated"),
+            Self::Calibration(_) => write!(f, "Couldn't read or write the calibration file"),
+            Self::NoSuchPosition { motor, position } => {
+                write!(f, "Motor {} has no position named {}", motor, position)
+            }
+            Self::Faulted => write!(f, "A fault must be reset before continuing"),
+            Self::NotPaused => write!(f, "The program must be paused first"),
+            Self::NoSuchStep(step) => write!(f, "There is no step {}", step),
+            Self::Backward(step) => write!(f, "Jumping back to step {} must be forced", step),
+            Self::EmergencyStop => write!(f, "The emergency stop is pressed"),
+            Self::NotQueued(job) => write!(f, "Job {} is not queued", job),
+            Self::NotScheduled(label) => write!(f, "No schedule is labeled {}", label),
+            Self::IntervalTooShort(interval) => write!(
+                f,
+                "Schedules can't recur more often than every {}s (requested every {}s)",
+                MIN_SCHEDULE_INTERVAL.as_secs(),
+                interval.as_secs_f64()
+            ),
+            Self::InvalidTimeScale(scale) => {
+                write!(
+                    f,
+                    "The time scale must be positive and finite (got {})",
+                    scale
+                )
+            }
+            Self::Interrupted => write!(f, "The interrupted run must be recovered first"),
+            Self::NotInterrupted => write!(f, "There is no interrupted run to recover"),
+            Self::NoHistory => write!(f, "No run history is kept"),
+            Self::EventLog(_) => write!(f, "Couldn't open the event log"),
+            Self::NoLibrary => write!(f, "No protocol library is kept"),
+            Self::Library(_) => write!(f, "Couldn't read or write the protocol library"),
+            Self::NoCatalog => write!(f, "No protocol files are cataloged"),
+            Self::NoWash => write!(f, "No wash routine is configured"),
+            Self::Catalog(_) => write!(f, "Couldn't read the catalog"),
+            Self::NoSuchProtocol(name) => write!(f, "There is no protocol named {}", name),
+            #[cfg(feature = "use_serde")]
+            Self::ProtocolFile(_) => write!(f, "The protocol file doesn't fit this system"),
+            #[cfg(feature = "use_serde")]
+            Self::Autoprotocol(_) => {
+                write!(f, "Couldn't convert the protocol to or from Autoprotocol")
+            }
+            Self::Templates(_) => write!(f, "Couldn't load the notification templates"),
+            Self::DuplicateManifold(name) => {
+                write!(f, "More than one manifold is named {}", name)
+            }
+            Self::RestartRequired => write!(f, "The new configuration takes a restart to apply"),
+            #[cfg(feature = "history")]
+            Self::History(_) => write!(f, "Couldn't read or write the run history"),
+            Self::InsufficientBuffer {
+                buffer,
+                required,
+                available,
+            } => write!(
+                f,
+                "The protocol needs {} mL of buffer {}, but only {} mL remains",
+                required.get::<milliliter>(),
+                buffer,
+                available.get::<milliliter>()
+            ),
+            Self::LevelUnknown(buffer) => write!(
+                f,
+                "The reservoir for buffer {} hasn't been measured yet",
+                buffer
+            ),
+        }
     }
 }
 
-impl std::error::Error for Error {}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::ProtocolConversion(err) => Some(err),
+            Self::Pin(err) => Some(err),
+            Self::Device(err) => Some(err),
+            Self::Config(err) => Some(err),
+            Self::Calibration(err)
+            | Self::EventLog(err)
+            | Self::Library(err)
+            | Self::Catalog(err) => Some(err),
+            #[cfg(feature = "use_serde")]
+            Self::ProtocolFile(err) => Some(err),
+            #[cfg(feature = "use_serde")]
+            Self::Autoprotocol(err) => Some(err),
+            Self::Templates(err) => Some(err),
+            #[cfg(feature = "history")]
+            Self::History(err) => Some(err),
+            _ => None,
+        }
+    }
+}
 
 /// A message sent to control the coordinator.
 #[derive(Debug)]
@@ -95,14 +389,154 @@ pub enum Message {
     /// If the second parameter is specified, it is used as the label for the job; otherwise, one
     /// is generated.
     Start(Protocol, Option<Uuid>),
+    /// Adds the given protocol to the end of the queue, to be started once everything before it
+    /// has finished.
+    ///
+    /// If nothing is running, the protocol starts right away. As with
+    /// [`Start`](#variant.Start), the second parameter is used as the label for the job, if given.
+    Enqueue(Protocol, Option<Uuid>),
+    /// Removes the job with the given label from the queue.
+    Dequeue(Uuid),
+    /// Schedules the given protocol to be queued at a later time, possibly repeatedly.
+    Schedule {
+        /// The protocol to run.
+        protocol: Protocol,
+        /// When to run it.
+        schedule: Schedule,
+        /// The label for the schedule; one is generated if not given.
+        id: Option<Uuid>,
+    },
+    /// Cancels the pending schedule with the given label.
+    Unschedule(Uuid),
+    /// Resumes or safely aborts a run that was interrupted by a crash or power loss.
+    Recover(Recovery),
+    /// Sets the operator recorded in the run history for the runs started from now on.
+    Operator(Option<String>),
+    /// Handles the given message on behalf of the given user, who is credited with any resulting
+    /// status updates.
+    Acting {
+        /// The user in question.
+        user: String,
+        /// The message to handle.
+        message: Box<Message>,
+    },
+    /// Moves the queued job with the given label to the given position in the queue.
+    ///
+    /// Positions past the end of the queue move the job to the end.
+    Requeue {
+        /// The label of the job to move.
+        id: Uuid,
+        /// The new position of the job (where 0 is next).
+        index: usize,
+    },
     /// Used to subscribe to coordinator updates.
     Subscribe(Box<dyn Update>),
+    /// Fills the line from the given buffer, displacing any air, and then returns to idle.
+    ///
+    /// If a volume is given, it overrides the configured priming volume.
+    Prime {
+        /// The buffer to prime the line with.
+        buffer: MotorId,
+        /// The volume to prime with, if different from the configured volume.
+        volume: Option<Volume>,
+    },
+    /// Runs the configured wash routine (see [`WashConfig`]), filling the chamber with its buffer
+    /// and draining it several times over, and then returns to idle.
+    ///
+    /// [`WashConfig`]: ../struct.WashConfig.html
+    Wash,
+    /// Begins calibrating the given motor, if we're idle.
+    Calibrate(MotorId),
+    /// Adjusts the motor being calibrated (jogging it or marking its position).
+    Adjust(Calibrate),
+    /// Finishes calibrating the motor, saving the calibration and returning to idle.
+    FinishCalibration,
+    /// Clears a latched fault (such as an emergency stop), allowing new protocols to run.
+    ///
+    /// This fails if the emergency stop is still pressed.
+    Reset,
+    /// Pauses the running program, stopping the pump and freezing the current step's timers.
+    ///
+    /// The valves are left where they are.
+    Pause,
+    /// Resumes a paused program exactly where it left off.
+    Resume,
+    /// Aborts the program, skipping its remaining steps and running the configured teardown
+    /// sequence to leave the system in a safe state.
+    Abort,
+    /// Skips the rest of the current step of a paused program.
+    ///
+    /// The next step begins once the program is resumed.
+    SkipStep,
+    /// Moves a paused program to the step with the given index (in the program's actions).
+    ///
+    /// The step begins once the program is resumed.
+    JumpTo {
+        /// The index of the step to jump to.
+        index: usize,
+        /// Whether to allow jumping backward (repeating steps).
+        force: bool,
+    },
+    /// Acts on the hardware directly, for setup and troubleshooting, if we're idle.
+    Jog(Jog),
+    /// Stops everything and latches the given fault, raised elsewhere (e.g. by the emergency stop
+    /// of the main manifold).
+    Latch(Fault),
+    /// Applies the given configuration without restarting, if we're idle.
+    ///
+    /// The notification settings, priming, washing, the drain pump, the teardown sequence, the
+    /// overrun policy, the number of restart attempts, and the calibrations are replaced, and
+    /// motors added to the end of the list are set up (on the configured backend, though the
+    /// watchdog only stops the motors it started with). The run history, queue, and schedules
+    /// are kept. Any other change to the hardware takes a restart: a configuration which uses the
+    /// pins differently is refused (with [`Error::RestartRequired`]), and other changes (e.g. to
+    /// an existing motor's angles or a sensor's thresholds) are ignored until then.
+    ///
+    /// [`Error::RestartRequired`]: enum.Error.html#variant.RestartRequired
+    ReloadConfig(Box<Config>),
+}
+
+/// A manual action on the hardware.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "use_serde", serde(rename_all = "lowercase"))]
+pub enum Jog {
+    /// Opens the given valve (where valve 0 is waste).
+    Open(MotorId),
+    /// Closes the given valve (where valve 0 is waste).
+    Close(MotorId),
+    /// Shuts the given valve (where valve 0 is waste).
+    Shut(MotorId),
+    /// Moves the given valve (where valve 0 is waste) to the given angle, in degrees.
+    Angle {
+        /// The valve to move.
+        motor: MotorId,
+        /// The angle to move it to (relative to the closed position).
+        angle: u16,
+    },
+    /// Runs the pump in the given direction for the given time, then stops it.
+    Pump {
+        /// The direction to run the pump in.
+        direction: PumpDirection,
+        /// How long to run the pump for.
+        duration: Duration,
+    },
 }
 
 impl ActixMessage for Message {
     type Result = Result<()>;
 }
 
+/// A protocol waiting in the queue to be started.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+pub struct QueuedProtocol {
+    /// The label the job will run under.
+    pub id: Uuid,
+    /// The protocol to be run.
+    pub protocol: Protocol,
+}
+
 /// Represents a coordinator state.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
@@ -119,6 +553,25 @@ pub enum State {
     },
     /// The program is actively executing.
     Running,
+    /// The program has been paused by the operator, and will pick up where it left off once
+    /// resumed.
+    Paused,
+    /// The line is being primed.
+    Priming,
+    /// The chamber is being washed.
+    Washing,
+    /// A motor is being calibrated.
+    Calibrating {
+        /// The motor being calibrated.
+        motor: MotorId,
+    },
+    /// The hardware is being controlled directly by an operator (e.g. the pump is being run).
+    Manual,
+    /// A hardware fault stopped the system, which has been driven to a safe state.
+    ///
+    /// The fault is latched: nothing new can be started until an operator resets it (with
+    /// [`Message::Reset`](enum.Message.html#variant.Reset)).
+    Faulted,
 }
 
 impl Default for State {
@@ -130,27 +583,278 @@ impl Default for State {
 /// Contains communication necessities.
 #[derive(Debug)]
 struct Addresses {
+    /// The arbiter the motors, pumps, and thermostat run in.
+    ///
+    /// They get their own thread so that the watchdog can still stop them if ours gets stuck.
+    devices: Addr<Arbiter>,
     /// The addresses of each motor.
-    motors: Vec<Addr<Motor>>,
-    /// The address of the pump.
-    pump: Addr<Pump>,
+    motors: Vec<MotorAddr>,
+    /// The addresses of each pump.
+    pumps: Vec<Addr<Pump>>,
+    /// The address of the flow sensor, if any.
+    flow: Option<Addr<FlowSensor>>,
+    /// The address of the pressure sensor, if any.
+    pressure: Option<Addr<PressureSensor>>,
+    /// The address of the reservoir level tracker, if any reservoirs are monitored.
+    reservoirs: Option<Addr<Reservoirs>>,
+    /// The address of the thermostat, if any.
+    thermostat: Option<Addr<Thermostat>>,
+    /// The bubble detector, if any (kept alive so that it continues to report bubbles).
+    bubble: Option<BubbleDetector>,
+    /// The emergency-stop input, if any (kept alive so that it continues to report presses).
+    estop: Option<InputPin>,
+    /// The address of the watchdog, if any.
+    watchdog: Option<Addr<Watchdog>>,
     /// The address of the subscriber entry point.
     subscribers: Addr<Subscribers>,
 }
 
 impl Index<MotorId> for Addresses {
-    type Output = Addr<Motor>;
+    type Output = MotorAddr;
     /// Returns the address of the motor associated with the given buffer.
     fn index(&self, i: MotorId) -> &Self::Output {
         &self.motors[i]
     }
 }
 
-/// Stores motors and pump until it's time to start them.
+/// A motor of either kind, before it has been started.
+#[derive(Debug)]
+enum MotorDevice {
+    Servo(Motor),
+    Stepper(StepperMotor),
+}
+
+impl MotorDevice {
+    /// Starts the motor with the given index in the given arbiter, reporting its failures to the
+    /// given supervisor.
+    fn start(
+        self,
+        index: MotorId,
+        arbiter: &Addr<Arbiter>,
+        supervisor: Recipient<DeviceFailed>,
+    ) -> MotorAddr {
+        let device = Device::Motor(index);
+        match self {
+            Self::Servo(motor) => MotorAddr::Servo(Motor::start_in_arbiter(arbiter, move |_| {
+                motor.supervised(device, supervisor)
+            })),
+            Self::Stepper(motor) => {
+                MotorAddr::Stepper(StepperMotor::start_in_arbiter(arbiter, move |_| {
+                    motor.supervised(device, supervisor)
+                }))
+            }
+        }
+    }
+}
+
+/// Strips the given configuration down for a simulated run (see [`Coordinator::simulate`]).
+///
+/// [`Coordinator::simulate`]: struct.Coordinator.html#method.simulate
+fn simulated(mut config: Config) -> Config {
+    config.gpio = BackendConfig::Mock;
+    config.admins.clear();
+    config.chat.clear();
+    config.sms = None;
+    config.push.clear();
+    config.calibration = None;
+    config.flow_sensor = None;
+    config.pressure_sensor = None;
+    config.bubble_detector = None;
+    config.reservoirs.clear();
+    config.thermal = None;
+    config.estop = None;
+    config.watchdog = None;
+    config.journal = None;
+    config.history = None;
+    config.event_log = None;
+    config.mqtt = None;
+    config.webhooks.clear();
+    config.grpc = None;
+    config.modbus = None;
+    config
+}
+
+/// Sets up the notifiers described by the given configuration.
+fn notifiers(config: &Config) -> Result<Notifiers> {
+    let templates = config
+        .mail_templates
+        .as_ref()
+        .map(Templates::load)
+        .transpose()
+        .map_err(Error::Templates)?
+        .unwrap_or_default();
+    #[cfg(not(feature = "templates"))]
+    {
+        if config.mail_templates.is_some() {
+            log::warn!("Mail templates require the `templates` feature; they won't be used.");
+        }
+    }
+    #[cfg(not(feature = "smtp"))]
+    {
+        if config.mail.is_some() {
+            log::warn!("SMTP requires the `smtp` feature; no mail will be sent.");
+        }
+    }
+    let mut notifiers = Notifiers::new(config.notification_throttle);
+    notifiers.register(Email::new(
+        Mailer::new(config.mail.clone(), templates),
+        config.admins.clone(),
+    ));
+    #[cfg(feature = "chat")]
+    {
+        for chat in config.chat.clone() {
+            notifiers.register(crate::notify::Chat::new(chat));
+        }
+    }
+    #[cfg(not(feature = "chat"))]
+    {
+        if !config.chat.is_empty() {
+            log::warn!("Chat notifications require the `chat` feature; they won't be posted.");
+        }
+    }
+    #[cfg(feature = "sms")]
+    {
+        if let Some(sms) = config.sms.clone() {
+            notifiers.register(crate::notify::Sms::new(sms));
+        }
+    }
+    #[cfg(not(feature = "sms"))]
+    {
+        if config.sms.is_some() {
+            log::warn!("Texting requires the `sms` feature; faults won't be texted.");
+        }
+    }
+    #[cfg(feature = "push")]
+    {
+        for push in config.push.clone() {
+            notifiers.register(crate::notify::Push::new(push));
+        }
+    }
+    #[cfg(not(feature = "push"))]
+    {
+        if !config.push.is_empty() {
+            log::warn!("Push notifications require the `push` feature; they won't be sent.");
+        }
+    }
+    Ok(notifiers)
+}
+
+/// Sets up the given motor (with the given calibration, if any) on the given backend.
+fn open_motor(
+    spec: ValveConfig,
+    calibration: Option<Calibration>,
+    backend: &dyn GpioBackend,
+) -> Result<MotorDevice> {
+    match spec {
+        ValveConfig::Servo(spec) => {
+            // TODO: Implement labels
+            let period = spec.period;
+            let range = spec.range[0]..=spec.range[1];
+            let motor = Motor::with_pin(period, range, spec.pin(backend)?)
+                .with_range_of_motion(spec.range_of_motion)?
+                .with_positions(spec.positions.clone())?;
+            let motor = match spec.angles {
+                Some(angles) => motor.with_angles(angles)?,
+                None => motor,
+            };
+            let motor = match spec.ramp {
+                Some(ramp) => motor.with_ramp(ramp),
+                None => motor,
+            };
+            let motor = match spec.detach_after {
+                Some(delay) => motor.with_detach(delay),
+                None => motor,
+            };
+            let motor = match spec.feedback()? {
+                Some(feedback) => motor.with_feedback(feedback),
+                None => motor,
+            };
+            Ok(MotorDevice::Servo(match calibration {
+                Some(calibration) => motor.with_calibration(calibration),
+                None => motor,
+            }))
+        }
+        ValveConfig::Stepper(spec) => Ok(MotorDevice::Stepper(spec.motor(backend)?)),
+    }
+}
+
+/// A configuration file watched for changes.
+#[cfg(feature = "use_serde")]
+#[derive(Debug)]
+struct ConfigFile {
+    path: PathBuf,
+    /// When the file was last modified, as of the last check (if known).
+    modified: Option<SystemTime>,
+}
+
+/// The address of a motor of either kind.
+#[derive(Debug)]
+enum MotorAddr {
+    Servo(Addr<Motor>),
+    Stepper(Addr<StepperMotor>),
+}
+
+impl MotorAddr {
+    /// Sends the given message to the motor.
+    fn send(
+        &self,
+        message: MotorMessage,
+    ) -> ResponseFuture<std::result::Result<(), DeviceError>, MailboxError> {
+        match self {
+            Self::Servo(addr) => Box::new(addr.send(message)),
+            Self::Stepper(addr) => Box::new(addr.send(message)),
+        }
+    }
+    /// Returns a recipient for messages to the motor.
+    fn recipient(&self) -> Recipient<MotorMessage> {
+        match self {
+            Self::Servo(addr) => addr.clone().recipient(),
+            Self::Stepper(addr) => addr.clone().recipient(),
+        }
+    }
+    /// Whether the motor is still running.
+    fn connected(&self) -> bool {
+        match self {
+            Self::Servo(addr) => addr.connected(),
+            Self::Stepper(addr) => addr.connected(),
+        }
+    }
+}
+
+/// Remembers how to restart each motor and pump, and what it was last told to do.
+#[derive(Debug)]
+struct Supervision {
+    config: SupervisionConfig,
+    /// The backend the devices' pins were acquired from (and are reacquired from on restart).
+    backend: Arc<dyn GpioBackend>,
+    motors: Vec<ValveConfig>,
+    pumps: Vec<PumpConfig>,
+    /// The last position each motor was sent to, by index.
+    positions: HashMap<MotorId, MotorMessage>,
+    /// The last command given to each pump, by index.
+    commands: HashMap<PumpId, PumpMessage>,
+    /// How many times each device has been restarted since it last carried out a command.
+    restarts: HashMap<Device, u32>,
+}
+
+impl Supervision {
+    /// Records that the given device carried out a command, so that it's working again.
+    fn recovered(&mut self, device: Device) {
+        self.restarts.remove(&device);
+    }
+}
+
+/// Stores motors and pumps until it's time to start them.
 #[derive(Debug)]
 struct Devices {
-    motors: Vec<Motor>,
-    pump: Pump,
+    motors: Vec<MotorDevice>,
+    pumps: Vec<Pump>,
+    flow: Option<FlowSensor>,
+    pressure: Option<PressureSensor>,
+    bubble: Option<BubbleDetector>,
+    reservoirs: Option<Reservoirs>,
+    thermostat: Option<Thermostat>,
+    estop: Option<InputPin>,
 }
 
 /// Contains program and buffer states.
@@ -170,6 +874,177 @@ pub(crate) struct CoordState {
     pub(crate) completed: Vec<Action>,
     /// The uuid associated with the running (or most recently-completed) job.
     pub(crate) uuid: Option<Uuid>,
+    /// The pump currently in use.
+    pub(crate) pump: PumpId,
+    /// Whether buffer is currently being pumped into the sample.
+    pub(crate) perfusing: bool,
+    /// Whether perfusion has been paused due to a detected bubble.
+    pub(crate) bubble: bool,
+    /// The direction the pump is running in, if it's running.
+    pub(crate) pumping: Option<PumpDirection>,
+    /// When the current program was started, if one has been.
+    pub(crate) started: Option<Instant>,
+    /// How long the current program has spent paused (not counting any current pause).
+    pub(crate) paused_for: Duration,
+    /// When the program was paused (and what the pump was doing), if it's paused.
+    pub(crate) paused: Option<(Instant, Option<PumpDirection>)>,
+    /// Whether the steps being run are the teardown of an aborted program.
+    pub(crate) aborted: bool,
+    /// The protocols waiting to be run after the current one, in order.
+    pub(crate) queue: Vec<QueuedProtocol>,
+    /// Whether a program is about to start (once the valves have closed).
+    pub(crate) starting: bool,
+    /// The protocols waiting for their scheduled starts.
+    pub(crate) scheduled: Vec<ScheduledProtocol>,
+    /// When the current step began, and how long the program had spent paused by then.
+    pub(crate) step_started: Option<(Instant, Duration)>,
+    /// The time-scale factor, if the hardware is simulated.
+    pub(crate) simulation: Option<f64>,
+    /// The run found in the journal on startup, if it has yet to be recovered.
+    pub(crate) interrupted: Option<journal::Entry>,
+    /// How long each step of the current program took, in order.
+    pub(crate) timings: Vec<StepTiming>,
+    /// Anything which went wrong during the current program without stopping it.
+    pub(crate) warnings: Vec<String>,
+    /// The name of the current program's protocol, if it came from the library.
+    pub(crate) name: Option<String>,
+}
+
+impl CoordState {
+    /// How long the current program has been running, not counting time spent paused.
+    ///
+    /// For simulated runs, this is the time that would have passed on real hardware.
+    pub(crate) fn elapsed(&self) -> Option<Duration> {
+        let started = self.started?;
+        let end = self.paused.map_or_else(Instant::now, |(since, _)| since);
+        Some(
+            self.simulated(
+                (end - started)
+                    .checked_sub(self.paused_for)
+                    .unwrap_or_default(),
+            ),
+        )
+    }
+    /// Converts a delay on real hardware to the time actually waited (in a simulation, shorter).
+    pub(crate) fn scaled(&self, delay: Duration) -> Duration {
+        match self.simulation {
+            Some(scale) => Duration::from_secs_f64(delay.as_secs_f64() / scale),
+            None => delay,
+        }
+    }
+    /// Converts the time actually waited to the corresponding time on real hardware.
+    pub(crate) fn simulated(&self, waited: Duration) -> Duration {
+        match self.simulation {
+            Some(scale) => Duration::from_secs_f64(waited.as_secs_f64() * scale),
+            None => waited,
+        }
+    }
+    /// The index of the step being run (or about to be run), if a program has been started.
+    pub(crate) fn position(&self) -> Option<usize> {
+        self.started?;
+        let done = self.completed.len();
+        Some(if self.current.is_some() {
+            done.saturating_sub(1)
+        } else {
+            done
+        })
+    }
+    /// The pass through a repeat being run, if the current step is repeated.
+    pub(crate) fn iteration(&self) -> Option<Iteration> {
+        self.started?;
+        self.completed
+            .iter()
+            .rev()
+            .find_map(|action| match action {
+                Action::Iterate(iteration) => Some(*iteration),
+                _ => None,
+            })?
+    }
+    /// The overrun policy of the step being run, if it has its own (see [`Step::Timed`]).
+    ///
+    /// [`Step::Timed`]: ../enum.Step.html#variant.Timed
+    pub(crate) fn overrun(&self) -> Option<Overrun> {
+        self.started?;
+        self.completed
+            .iter()
+            .rev()
+            .find_map(|action| match action {
+                Action::Overrun(overrun) => Some(*overrun),
+                _ => None,
+            })?
+    }
+    /// How long the current step has been running, not counting time spent paused.
+    pub(crate) fn step_elapsed(&self) -> Option<Duration> {
+        let (since, paused_before) = self.step_started?;
+        let end = self.paused.map_or_else(Instant::now, |(since, _)| since);
+        let paused = self
+            .paused_for
+            .checked_sub(paused_before)
+            .unwrap_or_default();
+        Some(self.simulated((end - since).checked_sub(paused).unwrap_or_default()))
+    }
+    /// Roughly how long until the current step finishes, if a step is running.
+    ///
+    /// Time spent waiting on the user isn't (and can't be) accounted for.
+    pub(crate) fn step_remaining(&self) -> Option<Duration> {
+        let action = self.current.as_ref()?;
+        let spent = self.step_elapsed()?;
+        Some(
+            estimated_duration(action)
+                .checked_sub(spent)
+                .unwrap_or_default(),
+        )
+    }
+    /// Records how long the current step took (if one is running), ending its timing.
+    pub(crate) fn lap(&mut self) {
+        if let (Some(action), Some(duration)) = (self.current.clone(), self.step_elapsed()) {
+            self.timings.push(StepTiming { action, duration });
+        }
+        self.step_started = None;
+    }
+    /// Roughly how long until the program finishes, if one is running.
+    ///
+    /// As with [`step_remaining`](#method.step_remaining), waits for the user aren't counted.
+    pub(crate) fn time_remaining(&self) -> Option<Duration> {
+        match self.status {
+            State::Running | State::Paused | State::Waiting => {}
+            _ => return None,
+        }
+        let current = self.step_remaining().unwrap_or_default();
+        Some(
+            self.remaining
+                .iter()
+                .map(estimated_duration)
+                .fold(current, |total, step| total + step),
+        )
+    }
+    /// How long the program has been paused, if it is.
+    pub(crate) fn current_pause(&self) -> Option<Duration> {
+        self.paused.map(|(since, _)| since.elapsed())
+    }
+}
+
+/// A callback run when a step timer fires.
+type Callback = Box<dyn FnOnce(&mut Coordinator, &mut CoordContext)>;
+
+/// The pending step timers, which are frozen while the program is paused.
+#[derive(Default)]
+struct Timers {
+    /// The ID of the next timer to be scheduled.
+    next: usize,
+    /// The scheduled timers (by ID), with their handles and due times.
+    pending: Vec<(usize, SpawnHandle, Instant, Callback)>,
+    /// The timers frozen by a pause, with the time each had left to run.
+    frozen: Vec<(Duration, Callback)>,
+}
+
+impl fmt::Debug for Timers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Timers")
+            .field("pending", &self.pending.len())
+            .field("frozen", &self.frozen.len())
+            .finish()
+    }
 }
 
 /// Contains all the actual logic for controlling the system based on a specified program.
@@ -186,33 +1061,387 @@ pub struct Coordinator {
     addresses: Option<Addresses>,
     /// Encodes the state of the coordinator.
     pub(crate) state: CoordState,
-    /// The contact emails of the administrators of this machine.
-    admins: Vec<String>,
+    /// Passes notifications on to the administrators (and anyone else interested).
+    notifiers: Notifiers,
+    /// Gathers the logs attached to fault notifications.
+    report: FaultReport,
+    /// The priming configuration.
+    prime: PrimeConfig,
+    /// The wash routine, if one is configured.
+    wash: Option<WashConfig>,
+    /// The pump used for draining, if different from the pump in use.
+    drain_pump: Option<PumpId>,
+    /// The flow sensor configuration, if a flow sensor is installed.
+    flow: Option<FlowSensorConfig>,
+    /// The pressure sensor configuration, if a pressure transducer is installed.
+    pressure: Option<PressureSensorConfig>,
+    /// Whether the pressure limit is currently exceeded.
+    overpressure: bool,
+    /// The bubble detector configuration, if an air-in-line detector is installed.
+    bubble: Option<BubbleDetectorConfig>,
+    /// The emergency-stop configuration, if a button is installed.
+    estop: Option<EStopConfig>,
+    /// The buffers whose reservoirs have level sensors.
+    monitored: Vec<MotorId>,
+    /// The most recently measured reservoir levels.
+    levels: Vec<Level>,
+    /// The path to the calibration file, if any.
+    calibration_file: Option<PathBuf>,
+    /// The path to the journal file, if any.
+    journal: Option<PathBuf>,
+    /// The run history, if one is kept.
+    #[cfg(feature = "history")]
+    history: Option<History>,
+    /// The operator starting new runs, if known.
+    operator: Option<String>,
+    /// The user on whose behalf the current message is being handled, if any.
+    acting: Option<String>,
+    /// The library of stored protocols, if one is kept.
+    #[cfg(feature = "use_serde")]
+    library: Option<Library>,
+    /// The catalog of protocol files, if one is kept.
+    #[cfg(feature = "use_serde")]
+    catalog: Option<Catalog>,
+    /// The MQTT broker to connect to once the coordinator starts, if any.
+    #[cfg(feature = "mqtt")]
+    mqtt: Option<MqttConfig>,
+    /// The webhooks to notify once the coordinator starts.
+    #[cfg(feature = "webhooks")]
+    webhooks: Vec<WebhookConfig>,
+    /// The address to serve the gRPC control interface on once the coordinator starts, if any,
+    /// along with the API tokens it accepts.
+    #[cfg(feature = "grpc")]
+    grpc: Option<(SocketAddr, Vec<TokenConfig>)>,
+    /// The address to serve Modbus TCP on once the coordinator starts, if any.
+    #[cfg(feature = "modbus")]
+    modbus: Option<SocketAddr>,
+    /// The event log, until it's started alongside the coordinator.
+    #[cfg(feature = "use_serde")]
+    event_log: Option<EventLog>,
+    /// The calibrated pulse widths of the motors.
+    calibrations: Calibrations,
+    /// The names of each motor's additional positions.
+    positions: Vec<Vec<String>>,
+    /// Each pump's flow rate at full speed, if calibrated.
+    flow_rates: Vec<Option<FlowRate>>,
+    /// The largest angle each motor can be moved to, in degrees.
+    ranges: Vec<u16>,
+    /// Whether we're shutting down (having been asked to exit).
+    shutting_down: bool,
+    /// The name of the manifold this coordinator runs, if it's one of several.
+    manifold: Option<String>,
+    /// The watchdog configuration, if the coordinator should be supervised.
+    watchdog: Option<WatchdogConfig>,
+    /// The overrun policy for timed steps without their own.
+    overrun: Overrun,
+    /// The pending step timers.
+    timers: Timers,
+    /// The timers for the pending schedules, by label.
+    schedules: Vec<(Uuid, SpawnHandle)>,
+    /// The actions run when a program is aborted.
+    teardown: Vec<Action>,
+    /// The configured buffers, for referring to them by name.
+    buffers: Buffers,
+    /// The pins used by the hardware, as configured (to tell whether a reloaded configuration
+    /// changes them).
+    pins: Vec<u16>,
+    /// The configuration file to reload when it changes, if any.
+    #[cfg(feature = "use_serde")]
+    config_file: Option<ConfigFile>,
+    /// How stopped motors and pumps are restarted.
+    supervision: Supervision,
+    /// Whether to shut down when the process is asked to exit (see
+    /// [`handle_signals`](#method.handle_signals)).
+    signals: bool,
 }
 
 impl Coordinator {
+    /// Initializes a coordinator which runs protocols against simulated hardware, with time sped
+    /// up by the given factor (so that a twelve-hour protocol takes under eight minutes at 100×).
+    ///
+    /// The pins are mocked, the sensors, thermostat, and emergency stop are left out (temperature
+    /// holds just wait out their hold time), and no notifications are sent. Subscribers receive the same
+    /// updates as for a real run, with durations given in real-hardware time.
+    pub fn simulate(config: Config, time_scale: f64) -> Result<Self> {
+        if !time_scale.is_finite() || time_scale <= 0.0 {
+            return Err(Error::InvalidTimeScale(time_scale));
+        }
+        let mut coordinator = Self::try_new(simulated(config))?;
+        coordinator.state.simulation = Some(time_scale);
+        Ok(coordinator)
+    }
+    /// Names the manifold this coordinator runs, so that its status updates can be told apart from
+    /// those of other manifolds.
+    pub fn in_manifold(mut self, name: impl Into<String>) -> Self {
+        self.manifold = Some(name.into());
+        self
+    }
+    /// Shuts down safely when the process is asked to exit (by SIGINT, SIGTERM, or SIGQUIT).
+    ///
+    /// This takes over the process's handling of those signals, so it's meant for programs built
+    /// around the coordinator rather than for hosts embedding it (such as the FFI bindings).
+    pub fn handle_signals(mut self) -> Self {
+        self.signals = true;
+        self
+    }
+    /// Watches the given configuration file (in any format [`Config::load`] reads), reloading it
+    /// whenever it changes (see [`Message::ReloadConfig`]).
+    ///
+    /// The file is reloaded with the environment's overrides and profile (but not those given on
+    /// the command line).
+    ///
+    /// Changes made while a protocol is running are picked up once we're idle again; a file which
+    /// can't be read or applied is reported in the log and otherwise ignored.
+    ///
+    /// [`Config::load`]: struct.Config.html#method.load
+    /// [`Message::ReloadConfig`]: enum.Message.html#variant.ReloadConfig
+    #[cfg(feature = "use_serde")]
+    pub fn watch_config(mut self, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let modified = fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        self.config_file = Some(ConfigFile { path, modified });
+        self
+    }
     /// Initializes a coordinator and prepares it for running.
+    ///
+    /// Pins are acquired from the backend specified in the configuration.
     pub fn try_new(config: Config) -> Result<Self> {
-        let mut pump = Pump::try_new(config.pump.pins)?;
-        pump.invert = config.pump.invert;
+        let backend = Arc::from(config.gpio.open()?);
+        Self::try_with_backend(config, backend)
+    }
+    /// Initializes a coordinator using the given backend, ignoring the configured one.
+    ///
+    /// This is mostly useful for driving a coordinator against a [`Mock`](pin/struct.Mock.html)
+    /// backend (clones of which share a timeline, so one can be kept to inspect it). The backend
+    /// is kept for reacquiring the pins of any device that has to be restarted.
+    pub fn try_with_backend(config: Config, backend: Arc<dyn GpioBackend>) -> Result<Self> {
+        config.validate()?;
+        let shared = Arc::clone(&backend);
+        let backend = &*backend;
+        if !config.manifolds.is_empty() {
+            log::warn!("Only the main manifold will be run; use `Manifolds` to run them all.");
+        }
+        let pumps = config
+            .pumps
+            .iter()
+            .map(|spec| Ok(Pump::try_from_config(spec, backend)?))
+            .collect::<Result<Vec<_>>>()?;
+        let calibrations = match &config.calibration {
+            Some(path) => calibration::load(path).map_err(Error::Calibration)?,
+            None => Calibrations::new(),
+        };
+        let positions = config
+            .motors
+            .iter()
+            .map(|spec| spec.positions().keys().cloned().collect())
+            .collect();
+        let flow_rates = config.pumps.iter().map(|pump| pump.flow_rate).collect();
+        let ranges = config
+            .motors
+            .iter()
+            .map(ValveConfig::range_of_motion)
+            .collect();
+        let notifiers = notifiers(&config)?;
+        let pins = config.pins();
         let motors = config
             .motors
-            .into_iter()
-            .map(|spec| {
-                // TODO: Implement labels
-                let period = spec.period;
-                let range = spec.range[0]..=spec.range[1];
-                let pin = spec.pin;
-                Motor::try_new(period, range, pin)
+            .iter()
+            .enumerate()
+            .map(|(index, spec)| {
+                open_motor(spec.clone(), calibrations.get(&index).cloned(), backend)
             })
-            .collect::<std::result::Result<Vec<_>, _>>()?;
-        let devices = Some(Devices { motors, pump });
-        Ok(Self {
+            .collect::<Result<Vec<_>>>()?;
+        let supervision = Supervision {
+            config: config.supervision,
+            backend: shared,
+            motors: config.motors,
+            pumps: config.pumps.clone(),
+            positions: HashMap::new(),
+            commands: HashMap::new(),
+            restarts: HashMap::new(),
+        };
+        let flow = config
+            .flow_sensor
+            .as_ref()
+            .map(|spec| FlowSensor::try_new(spec, backend))
+            .transpose()?;
+        let pressure = config
+            .pressure_sensor
+            .as_ref()
+            .map(|spec| -> Result<_> {
+                Ok(PressureSensor::new(
+                    spec.adc.open()?,
+                    spec.channel,
+                    spec.range(),
+                ))
+            })
+            .transpose()?;
+        let bubble = config
+            .bubble_detector
+            .as_ref()
+            .map(|spec| BubbleDetector::try_new(spec, backend))
+            .transpose()?;
+        let reservoirs = config
+            .reservoirs
+            .iter()
+            .map(|spec| Ok(Reservoir::try_new(spec, backend)?))
+            .collect::<Result<Vec<_>>>()?;
+        let reservoirs = if reservoirs.is_empty() {
+            None
+        } else {
+            Some(Reservoirs::new(reservoirs))
+        };
+        let thermostat = config
+            .thermal
+            .as_ref()
+            .map(|spec| spec.thermostat(backend))
+            .transpose()?;
+        let estop = config.estop.map(|spec| spec.input(backend)).transpose()?;
+        let devices = Some(Devices {
+            motors,
+            pumps,
+            flow,
+            pressure,
+            bubble,
+            reservoirs,
+            thermostat,
+            estop,
+        });
+        #[cfg(feature = "history")]
+        let history = config.history.as_ref().map(History::open).transpose()?;
+        #[cfg(not(feature = "history"))]
+        {
+            if config.history.is_some() {
+                log::warn!("Run history requires the `history` feature; runs won't be recorded.");
+            }
+        }
+        let report = FaultReport::new(
+            config.fault_report.clone(),
+            config.event_log.as_ref().map(|log| log.path.clone()),
+        );
+        #[cfg(feature = "use_serde")]
+        let event_log = config
+            .event_log
+            .map(EventLog::open)
+            .transpose()
+            .map_err(Error::EventLog)?;
+        #[cfg(feature = "use_serde")]
+        let library = config
+            .library
+            .map(Library::open)
+            .transpose()
+            .map_err(Error::Library)?;
+        #[cfg(feature = "use_serde")]
+        let catalog = config
+            .protocols
+            .map(Catalog::open)
+            .transpose()
+            .map_err(Error::Catalog)?;
+        #[cfg(not(feature = "use_serde"))]
+        {
+            if config.library.is_some() {
+                log::warn!("The protocol library requires the `use_serde` feature.");
+            }
+            if config.protocols.is_some() {
+                log::warn!("The protocol catalog requires the `use_serde` feature.");
+            }
+            if config.event_log.is_some() {
+                log::warn!(
+                    "The event log requires the `use_serde` feature; events won't be logged."
+                );
+            }
+        }
+        #[cfg(not(feature = "mqtt"))]
+        {
+            if config.mqtt.is_some() {
+                log::warn!("MQTT requires the `mqtt` feature; updates won't be published.");
+            }
+        }
+        #[cfg(not(feature = "webhooks"))]
+        {
+            if !config.webhooks.is_empty() {
+                log::warn!("Webhooks require the `webhooks` feature; they won't be notified.");
+            }
+        }
+        #[cfg(not(feature = "grpc"))]
+        {
+            if config.grpc.is_some() {
+                log::warn!("gRPC requires the `grpc` feature; the interface won't be served.");
+            }
+        }
+        #[cfg(not(feature = "modbus"))]
+        {
+            if config.modbus.is_some() {
+                log::warn!("Modbus requires the `modbus` feature; registers won't be served.");
+            }
+        }
+        #[cfg(feature = "grpc")]
+        let grpc = config
+            .grpc
+            .map(|grpc| (grpc.address, config.tokens.clone()));
+        let teardown = config
+            .teardown
+            .iter()
+            .flat_map(TeardownStep::actions)
+            .collect();
+        let coordinator = Self {
             devices,
             addresses: None,
             state: CoordState::default(),
-            admins: config.admins,
-        })
+            notifiers,
+            report,
+            prime: config.prime,
+            wash: config.wash,
+            drain_pump: config.drain_pump,
+            flow: config.flow_sensor,
+            pressure: config.pressure_sensor,
+            overpressure: false,
+            bubble: config.bubble_detector,
+            estop: config.estop,
+            monitored: config.reservoirs.iter().map(|spec| spec.buffer).collect(),
+            levels: Vec::new(),
+            calibration_file: config.calibration,
+            journal: config.journal,
+            #[cfg(feature = "history")]
+            history,
+            operator: None,
+            acting: None,
+            #[cfg(feature = "use_serde")]
+            library,
+            #[cfg(feature = "use_serde")]
+            catalog,
+            #[cfg(feature = "mqtt")]
+            mqtt: config.mqtt,
+            #[cfg(feature = "webhooks")]
+            webhooks: config.webhooks,
+            #[cfg(feature = "grpc")]
+            grpc,
+            #[cfg(feature = "modbus")]
+            modbus: config.modbus.map(|modbus| modbus.address),
+            #[cfg(feature = "use_serde")]
+            event_log,
+            calibrations,
+            positions,
+            flow_rates,
+            ranges,
+            shutting_down: false,
+            manifold: None,
+            watchdog: config.watchdog,
+            overrun: config.overrun,
+            timers: Timers::default(),
+            schedules: vec![],
+            teardown,
+            buffers: Buffers::new(config.buffers),
+            pins,
+            #[cfg(feature = "use_serde")]
+            config_file: None,
+            supervision,
+            signals: false,
+        };
+        coordinator.check_actions(&coordinator.teardown)?;
+        Ok(coordinator)
     }
     /// The in-progress program, if appropriate.
     pub fn program(&self) -> Option<&Program> {
@@ -223,164 +1452,1595 @@ impl Coordinator {
         self.state.status
     }
     /// Closes all valves, shutting the waste valve.
-    fn close_all(&self, context: &mut CoordContext) {
-        if let Some(ref addresses) = self.addresses {
-            addresses[0].do_send(MotorMessage::Shut);
-            for addr in addresses.motors.iter().skip(1) {
-                addr.do_send(MotorMessage::Close);
-            }
+    fn close_all(&mut self, context: &mut CoordContext) {
+        let count = self
+            .addresses
+            .as_ref()
+            .map_or(0, |addresses| addresses.motors.len());
+        for index in 0..count {
+            let message = if index == 0 {
+                MotorMessage::Shut
+            } else {
+                MotorMessage::Close
+            };
+            self.command(index, message, context);
         }
-        context.run_later(Duration::new(5, 0), move |coord, _| {
-            if let Some(ref addresses) = coord.addresses {
-                for addr in &addresses.motors {
-                    addr.do_send(MotorMessage::Stop);
-                }
+        context.run_later(Duration::new(5, 0), move |coord, context| {
+            let count = coord
+                .addresses
+                .as_ref()
+                .map_or(0, |addresses| addresses.motors.len());
+            for index in 0..count {
+                coord.settle(index, context);
             }
         });
     }
-    fn _close(&self, index: usize, context: &mut CoordContext) {
-        if let Some(ref addresses) = self.addresses {
-            addresses[index].do_send(MotorMessage::Close);
-            context.run_later(Duration::new(5, 0), move |coord, _| {
-                if let Some(ref addresses) = coord.addresses {
-                    addresses[index].do_send(MotorMessage::Stop);
-                }
+    fn _close(&mut self, index: usize, context: &mut CoordContext) {
+        if self.addresses.is_some() {
+            self.command(index, MotorMessage::Close, context);
+            context.run_later(Duration::new(5, 0), move |coord, context| {
+                coord.settle(index, context);
             });
         }
     }
-    fn close(&self, valve: usize, context: &mut CoordContext) {
+    /// Verifies the position of the given motor once it has finished moving, then stops it.
+    fn settle(&mut self, index: usize, context: &mut CoordContext) {
+        let addresses = match self.addresses {
+            Some(ref addresses) => addresses,
+            None => return,
+        };
+        if let MotorAddr::Servo(addr) = &addresses[index] {
+            let request = addr.send(Verify);
+            context.spawn(
+                request
+                    .into_actor(self)
+                    .then(move |result, coord, context| {
+                        match result {
+                            Ok(Ok(Some(Position { target, actual }))) => {
+                                let fault = Fault::ValvePosition {
+                                    motor: index,
+                                    target,
+                                    actual,
+                                };
+                                // Don't fault repeatedly while shutting everything down.
+                                if coord.is_stopped() {
+                                    log::error!("{}", fault);
+                                } else {
+                                    coord.fault(fault, context);
+                                }
+                            }
+                            Ok(Ok(None)) => {}
+                            Ok(Err(err)) => {
+                                log::error!("Failed to verify motor {}: {}", index, err)
+                            }
+                            Err(err) => log::error!("Failed to reach motor {}: {}", index, err),
+                        }
+                        fut::ok(())
+                    }),
+            );
+        }
+        self.command(index, MotorMessage::Stop, context);
+    }
+    /// Sends the given message to the given motor, faulting if the motor fails to carry it out
+    /// (or restarting it if it has stopped).
+    fn command(&mut self, index: usize, message: MotorMessage, context: &mut CoordContext) {
+        let addresses = match self.addresses {
+            Some(ref addresses) => addresses,
+            None => return,
+        };
+        let request = addresses[index].send(message.clone());
+        // Stopping a motor only turns off its signal, leaving it where it was sent.
+        if !matches!(message, MotorMessage::Stop) {
+            self.supervision.positions.insert(index, message);
+        }
+        context.spawn(
+            request
+                .into_actor(self)
+                .then(move |result, coord, context| {
+                    let device = Device::Motor(index);
+                    let fault = Fault::Motor { motor: index };
+                    match result {
+                        Ok(Ok(())) => coord.supervision.recovered(device),
+                        Ok(Err(err)) => coord.device_failed(fault, &err, context),
+                        Err(err) => {
+                            log::error!("Failed to reach motor {}: {}", index, err);
+                            coord.restart(device, context);
+                        }
+                    }
+                    fut::ok(())
+                }),
+        );
+    }
+    /// Sends the given message to the given pump, faulting if the pump fails to carry it out (or
+    /// restarting it if it has stopped).
+    fn command_pump(&mut self, id: PumpId, message: PumpMessage, context: &mut CoordContext) {
+        let pump = match self
+            .addresses
+            .as_ref()
+            .and_then(|addresses| addresses.pumps.get(id))
+        {
+            Some(pump) => pump,
+            None => return,
+        };
+        let request = pump.send(message);
+        self.supervision.commands.insert(id, message);
+        context.spawn(
+            request
+                .into_actor(self)
+                .then(move |result, coord, context| {
+                    let device = Device::Pump(id);
+                    let fault = Fault::Pump { pump: id };
+                    match result {
+                        Ok(Ok(_)) => coord.supervision.recovered(device),
+                        Ok(Err(err)) => coord.device_failed(fault, &err, context),
+                        Err(err) => {
+                            log::error!("Failed to reach pump {}: {}", id, err);
+                            coord.restart(device, context);
+                        }
+                    }
+                    fut::ok(())
+                }),
+        );
+    }
+    /// Responds to a device failing to carry out a command by driving the system to a safe state.
+    ///
+    /// Failures while already faulted (e.g. while shutting everything down) are only logged.
+    fn device_failed(&mut self, fault: Fault, err: &dyn fmt::Display, context: &mut CoordContext) {
+        log::error!("{} ({})", fault, err);
+        if self.state.status != State::Faulted {
+            self.fault(fault, context);
+        }
+    }
+    /// Restarts any motors and pumps which have stopped.
+    fn check_devices(&mut self, context: &mut CoordContext) {
+        let stopped = match &self.addresses {
+            Some(addresses) => {
+                let motors = addresses.motors.iter().enumerate();
+                let pumps = addresses.pumps.iter().enumerate();
+                motors
+                    .filter(|(_, motor)| !motor.connected())
+                    .map(|(index, _)| Device::Motor(index))
+                    .chain(
+                        pumps
+                            .filter(|(_, pump)| !pump.connected())
+                            .map(|(index, _)| Device::Pump(index)),
+                    )
+                    .collect::<Vec<_>>()
+            }
+            None => return,
+        };
+        for device in stopped {
+            self.restart(device, context);
+        }
+    }
+    /// Restarts the given (stopped) device, or faults once it has been restarted too many times
+    /// without carrying out a command.
+    ///
+    /// A restart which fails is only logged; the device is tried again at the next check.
+    fn restart(&mut self, device: Device, context: &mut CoordContext) {
+        let attempts = self.supervision.config.attempts;
+        let restarts = self.supervision.restarts.entry(device).or_insert(0);
+        *restarts = restarts.saturating_add(1);
+        let attempt = *restarts;
+        if attempt > attempts {
+            // Only fault the first time we give up.
+            if attempt == attempts + 1 {
+                let fault = match device {
+                    Device::Motor(motor) => Fault::Motor { motor },
+                    Device::Pump(pump) => Fault::Pump { pump },
+                };
+                let reason = format!("stopped, and not restarted after {} attempts", attempts);
+                self.device_failed(fault, &reason, context);
+            }
+            return;
+        }
+        log::warn!(
+            "{} stopped; restarting it (attempt {} of {})",
+            device,
+            attempt,
+            attempts
+        );
+        if let Err(err) = self.respawn(device, context) {
+            log::error!("Failed to restart {}: {}", device, err);
+        }
+    }
+    /// Acquires the given device's pins afresh and starts it again, then sends it its last
+    /// command.
+    fn respawn(&mut self, device: Device, context: &mut CoordContext) -> Result<()> {
+        let addresses = match self.addresses.as_mut() {
+            Some(addresses) => addresses,
+            None => return Ok(()),
+        };
+        let backend = Arc::clone(&self.supervision.backend);
+        let supervisor = context.address().recipient::<DeviceFailed>();
+        match device {
+            Device::Motor(index) => {
+                let spec = self.supervision.motors.get(index).cloned();
+                let spec = spec.ok_or(Error::NoSuchMotor(index))?;
+                let calibration = self.calibrations.get(&index).cloned();
+                let motor = open_motor(spec, calibration, &*backend)?.start(
+                    index,
+                    &addresses.devices,
+                    supervisor,
+                );
+                if let Some(watchdog) = &addresses.watchdog {
+                    watchdog.do_send(watchdog::Replace::Motor(index, motor.recipient()));
+                }
+                addresses.motors[index] = motor;
+                if let Some(message) = self.supervision.positions.get(&index).cloned() {
+                    self.command(index, message, context);
+                    context.run_later(*SETTLE_DELAY, move |coord, context| {
+                        coord.settle(index, context);
+                    });
+                }
+            }
+            Device::Pump(index) => {
+                let spec = self.supervision.pumps.get(index);
+                let spec = spec.ok_or(Error::NoSuchPump(index))?;
+                let pump = Pump::try_from_config(spec, &*backend)?;
+                let pump = Pump::start_in_arbiter(&addresses.devices, move |_| {
+                    pump.supervised(device, supervisor)
+                });
+                if let Some(watchdog) = &addresses.watchdog {
+                    watchdog.do_send(watchdog::Replace::Pump(index, pump.clone()));
+                }
+                addresses.pumps[index] = pump;
+                if let Some(message) = self.supervision.commands.get(&index).cloned() {
+                    self.command_pump(index, message, context);
+                }
+            }
+        }
+        Ok(())
+    }
+    fn close(&mut self, valve: usize, context: &mut CoordContext) {
         let index = valve + 1; // Valve 0 is waste
         self._close(index, context);
     }
-    fn _open(&self, index: usize, context: &mut CoordContext) {
-        if let Some(ref addresses) = self.addresses {
-            addresses[index].do_send(MotorMessage::Open);
-            context.run_later(Duration::new(5, 0), move |coord, _| {
-                if let Some(ref addresses) = coord.addresses {
-                    addresses[index].do_send(MotorMessage::Stop);
-                }
+    fn _open(&mut self, index: usize, context: &mut CoordContext) {
+        if self.addresses.is_some() {
+            self.command(index, MotorMessage::Open, context);
+            context.run_later(Duration::new(5, 0), move |coord, context| {
+                coord.settle(index, context);
             });
         }
     }
-    fn open(&self, valve: usize, context: &mut CoordContext) {
+    fn open(&mut self, valve: usize, context: &mut CoordContext) {
         let index = valve + 1; // Valve 0 is waste
         self._open(index, context);
     }
-    fn shut_waste(&self, context: &mut CoordContext) {
-        if let Some(ref addresses) = self.addresses {
-            addresses[0].do_send(MotorMessage::Shut);
-            context.run_later(Duration::new(5, 0), move |coord, _| {
-                if let Some(ref addresses) = coord.addresses {
-                    addresses[0].do_send(MotorMessage::Stop);
-                }
+    fn _shut(&mut self, index: usize, context: &mut CoordContext) {
+        if self.addresses.is_some() {
+            self.command(index, MotorMessage::Shut, context);
+            context.run_later(Duration::new(5, 0), move |coord, context| {
+                coord.settle(index, context);
+            });
+        }
+    }
+    fn shut_waste(&mut self, context: &mut CoordContext) {
+        self._shut(0, context);
+    }
+    /// Moves the given motor to the given angle (which must be within its range of motion).
+    fn _set_angle(&mut self, index: usize, angle: u16, context: &mut CoordContext) {
+        if self.addresses.is_some() {
+            self.command(index, MotorMessage::SetAngle(angle), context);
+            context.run_later(Duration::new(5, 0), move |coord, context| {
+                coord.settle(index, context);
             });
         }
     }
-    fn open_waste(&self, context: &mut CoordContext) {
+    fn open_waste(&mut self, context: &mut CoordContext) {
         self._open(0, context);
     }
-    fn close_waste(&self, context: &mut CoordContext) {
+    fn close_waste(&mut self, context: &mut CoordContext) {
         self._close(0, context);
     }
-    fn perfuse(&self) {
-        if let Some(ref addresses) = self.addresses {
-            addresses.pump.do_send(PumpMessage::Perfuse);
+    /// The number of pumps this coordinator controls.
+    fn pump_count(&self) -> usize {
+        match (&self.addresses, &self.devices) {
+            (Some(addresses), _) => addresses.pumps.len(),
+            (None, Some(devices)) => devices.pumps.len(),
+            (None, None) => 0,
         }
     }
-    fn drain(&self) {
-        if let Some(ref addresses) = self.addresses {
-            addresses.pump.do_send(PumpMessage::Drain);
+    /// The address of the pump currently in use.
+    fn pump(&self) -> Option<&Addr<Pump>> {
+        self.addresses
+            .as_ref()
+            .and_then(|addresses| addresses.pumps.get(self.state.pump))
+    }
+    fn perfuse(&mut self, context: &mut CoordContext) {
+        self.state.pumping = Some(PumpDirection::Forward);
+        self.command_pump(self.state.pump, PumpMessage::Perfuse, context);
+    }
+    fn drain(&mut self, context: &mut CoordContext) {
+        self.state.pumping = Some(PumpDirection::Backward);
+        // Drain using the dedicated waste pump, if any.
+        let id = self.drain_pump.unwrap_or(self.state.pump);
+        self.command_pump(id, PumpMessage::Drain, context);
+    }
+    /// Stops all pumps.
+    fn stop_pump(&mut self, context: &mut CoordContext) {
+        self.state.pumping = None;
+        let count = self
+            .addresses
+            .as_ref()
+            .map_or(0, |addresses| addresses.pumps.len());
+        for id in 0..count {
+            self.command_pump(id, PumpMessage::Stop, context);
         }
     }
-    fn stop_pump(&self) {
-        if let Some(ref addresses) = self.addresses {
-            addresses.pump.do_send(PumpMessage::Stop);
+    /// Whether the given motor has an additional position with the given name.
+    fn has_position(&self, motor: MotorId, position: &str) -> bool {
+        self.positions
+            .get(motor)
+            .map_or(false, |names| names.iter().any(|name| name == position))
+    }
+    /// Whether this coordinator controls a thermostat.
+    fn has_thermostat(&self) -> bool {
+        match (&self.addresses, &self.devices) {
+            (Some(addresses), _) => addresses.thermostat.is_some(),
+            (None, Some(devices)) => devices.thermostat.is_some(),
+            (None, None) => false,
         }
     }
-    /// Attempts to run the next step of the program, aborting and cleaning up on failure.
-    fn try_advance(&mut self, context: &mut CoordContext) {
-        let result = self.advance(context);
-        if let Err(err) = result {
-            // TODO: Notify user
-            log::error!("Aborting due to program advance error: {:?}", err);
-            let mut tries = 0;
-            let mut result = self.hcf();
-            while tries < 5 && result.is_err() {
-                std::thread::sleep(Duration::from_millis(200));
-                result = self.hcf();
-                tries += 1;
-            }
-            if result.is_err() {
-                log::error!("Could not fully stop program; please take caution!");
-            }
+    /// The address of the thermostat, if any.
+    fn thermostat(&self) -> Option<&Addr<Thermostat>> {
+        self.addresses
+            .as_ref()
+            .and_then(|addresses| addresses.thermostat.as_ref())
+    }
+    /// Stops holding any temperature.
+    fn release_temperature(&self) {
+        if let Some(thermostat) = self.thermostat() {
+            thermostat.do_send(ThermalMessage::Release);
         }
     }
-    /// Moves to the next step of the program, returning the new current action.
-    fn advance(&mut self, context: &mut CoordContext) -> Result<Option<Action>> {
-        if !self.state.remaining.is_empty() {
-            self.state.status = State::Running;
-            let action = self.state.remaining.remove(0);
-            // Make sure to message something that will call advance again later!
+    /// Waits for the temperature to come within tolerance of the target, then holds it for the
+    /// given duration before moving on.
+    fn await_temperature(
+        &self,
+        target: f64,
+        tolerance: f64,
+        duration: Duration,
+        context: &mut CoordContext,
+    ) {
+        let thermostat = match self.thermostat() {
+            Some(thermostat) => thermostat,
+            None => return,
+        };
+        let request = thermostat.send(ThermalMessage::Measure);
+        context.spawn(
+            request
+                .into_actor(self)
+                .then(move |result, coord, context| {
+                    let holding = match coord.state.current {
+                        Some(Action::HoldTemperature { .. }) => true,
+                        _ => false,
+                    };
+                    // While paused, the next check is frozen along with the other step timers.
+                    let active =
+                        coord.state.status == State::Running || coord.state.status == State::Paused;
+                    if holding && active {
+                        if let Ok(Ok(temperature)) = &result {
+                            let value = temperature.get::<degree_celsius>();
+                            coord.report(Sensor::Temperature, value, context);
+                        }
+                        match result {
+                            Ok(Ok(temperature))
+                                if (temperature.get::<degree_celsius>() - target).abs()
+                                    <= tolerance =>
+                            {
+                                coord.publish(
+                                    StatusMessage::TemperatureReached(temperature),
+                                    context,
+                                );
+                                coord.after(duration, context, |coord, context| {
+                                    if coord.state.status == State::Running {
+                                        coord.try_advance(context);
+                                    }
+                                });
+                            }
+                            result => {
+                                match result {
+                                    Ok(Ok(_)) => {}
+                                    Ok(Err(err)) => {
+                                        log::error!("Failed to read temperature: {}", err)
+                                    }
+                                    Err(err) => log::error!("Failed to reach thermostat: {}", err),
+                                }
+                                coord.after(
+                                    *TEMPERATURE_INTERVAL,
+                                    context,
+                                    move |coord, context| {
+                                        coord.await_temperature(
+                                            target, tolerance, duration, context,
+                                        );
+                                    },
+                                );
+                            }
+                        }
+                    }
+                    fut::ok(())
+                }),
+        );
+    }
+    /// Reads the given sensor, reporting the reading and passing it (or `None` if the sensor
+    /// couldn't be read) to the given callback.
+    fn read<F>(&self, sensor: Sensor, context: &mut CoordContext, callback: F)
+    where
+        F: FnOnce(&mut Self, Option<f64>, &mut CoordContext) + 'static,
+    {
+        let addresses = self.addresses.as_ref();
+        let reading: Box<dyn Future<Item = Option<f64>, Error = ()>> = match sensor {
+            Sensor::Pressure => match addresses.and_then(|a| a.pressure.as_ref()) {
+                Some(sensor) => Box::new(sensor.send(PressureMessage::Measure).then(|result| {
+                    Ok(match result {
+                        Ok(Ok(pressure)) => Some(pressure.get::<kilopascal>()),
+                        Ok(Err(err)) => {
+                            log::error!("Failed to read pressure: {}", err);
+                            None
+                        }
+                        Err(err) => {
+                            log::error!("Failed to reach pressure sensor: {}", err);
+                            None
+                        }
+                    })
+                })),
+                None => Box::new(future::ok(None)),
+            },
+            Sensor::Flow => match addresses.and_then(|a| a.flow.as_ref()) {
+                Some(flow) => Box::new(flow.send(FlowMessage::Measure).then(|result| {
+                    Ok(match result {
+                        Ok(measured) => Some(measured.get::<milliliter>()),
+                        Err(err) => {
+                            log::error!("Failed to reach flow sensor: {}", err);
+                            None
+                        }
+                    })
+                })),
+                None => Box::new(future::ok(None)),
+            },
+            Sensor::Temperature => match self.thermostat() {
+                Some(thermostat) => {
+                    Box::new(thermostat.send(ThermalMessage::Measure).then(|result| {
+                        Ok(match result {
+                            Ok(Ok(temperature)) => Some(temperature.get::<degree_celsius>()),
+                            Ok(Err(err)) => {
+                                log::error!("Failed to read temperature: {}", err);
+                                None
+                            }
+                            Err(err) => {
+                                log::error!("Failed to reach thermostat: {}", err);
+                                None
+                            }
+                        })
+                    }))
+                }
+                None => Box::new(future::ok(None)),
+            },
+        };
+        context.spawn(
+            reading
+                .into_actor(self)
+                .then(move |result, coord, context| {
+                    let value = result.unwrap_or_default();
+                    if let Some(value) = value {
+                        coord.report(sensor, value, context);
+                    }
+                    callback(coord, value, context);
+                    fut::ok(())
+                }),
+        );
+    }
+    /// Waits for the given sensor's reading to satisfy the given predicate, reading it every so
+    /// often, or (once the timeout, if any, has passed) for the operator.
+    ///
+    /// Simulated runs take the predicate to be satisfied right away.
+    fn await_condition(
+        &mut self,
+        sensor: Sensor,
+        predicate: Predicate,
+        timeout: Option<Duration>,
+        context: &mut CoordContext,
+    ) {
+        if self.state.simulation.is_some() {
+            log::trace!("Simulating the {} coming {}.", sensor, predicate);
+            self.publish(
+                StatusMessage::ConditionMet {
+                    sensor,
+                    value: None,
+                },
+                context,
+            );
+            self.try_advance(context);
+            return;
+        }
+        self.read(sensor, context, move |coord, value, context| {
+            let waiting = match coord.state.current {
+                Some(Action::WaitUntil { .. }) => true,
+                _ => false,
+            };
+            // While paused, the next check is frozen along with the other step timers.
+            let active =
+                coord.state.status == State::Running || coord.state.status == State::Paused;
+            if !waiting || !active {
+                return;
+            }
+            match value {
+                Some(value) if predicate.holds(value) => {
+                    let value = Some(value);
+                    coord.publish(StatusMessage::ConditionMet { sensor, value }, context);
+                    coord.after(Duration::new(0, 0), context, |coord, context| {
+                        if coord.state.status == State::Running {
+                            coord.try_advance(context);
+                        }
+                    });
+                }
+                _ => match timeout.map(|timeout| timeout.checked_sub(*CONDITION_INTERVAL)) {
+                    // The timeout has passed.
+                    Some(None) => {
+                        coord.after(Duration::new(0, 0), context, move |coord, context| {
+                            if coord.state.status == State::Running {
+                                coord.give_up(sensor, predicate, context);
+                            }
+                        })
+                    }
+                    remaining => {
+                        let timeout = remaining.and_then(|remaining| remaining);
+                        coord.after(*CONDITION_INTERVAL, context, move |coord, context| {
+                            coord.await_condition(sensor, predicate, timeout, context)
+                        })
+                    }
+                },
+            }
+        });
+    }
+    /// Stops waiting on the given sensor, notifying everyone and waiting for the operator instead.
+    fn give_up(&mut self, sensor: Sensor, predicate: Predicate, context: &mut CoordContext) {
+        let message = format!(
+            "Timed out waiting for the {} to be {} {}.",
+            sensor,
+            predicate,
+            sensor.unit()
+        );
+        log::warn!("{}", message);
+        self.message(NotificationEvent::Notification, "Timed out", &message);
+        self.warn(message);
+        self.state.status = State::Waiting;
+        self.publish(StatusMessage::ConditionTimedOut { sensor }, context);
+        self.publish(StatusMessage::Paused, context);
+    }
+    /// Reads the given sensor, skipping the given number of actions (the first branch of a
+    /// conditional step) unless its reading satisfies the given predicate.
+    ///
+    /// The sensor is read again until it can be read. Simulated runs take the first branch.
+    fn branch(
+        &mut self,
+        sensor: Sensor,
+        predicate: Predicate,
+        skip: usize,
+        context: &mut CoordContext,
+    ) {
+        if self.state.simulation.is_some() {
+            log::trace!("Simulating the {} being {}.", sensor, predicate);
+            let (value, taken) = (None, true);
+            self.publish(
+                StatusMessage::Branched {
+                    sensor,
+                    value,
+                    taken,
+                },
+                context,
+            );
+            self.try_advance(context);
+            return;
+        }
+        self.read(sensor, context, move |coord, value, context| {
+            let branching = match coord.state.current {
+                Some(Action::Branch { .. }) => true,
+                _ => false,
+            };
+            let active =
+                coord.state.status == State::Running || coord.state.status == State::Paused;
+            if !branching || !active {
+                return;
+            }
+            let value = match value {
+                Some(value) => value,
+                None => {
+                    coord.after(*CONDITION_INTERVAL, context, move |coord, context| {
+                        coord.branch(sensor, predicate, skip, context)
+                    });
+                    return;
+                }
+            };
+            let taken = predicate.holds(value);
+            log::debug!(
+                "The {} is {:.1} {}, so the {} branch was taken.",
+                sensor,
+                value,
+                sensor.unit(),
+                if taken { "first" } else { "second" }
+            );
+            let message = StatusMessage::Branched {
+                sensor,
+                value: Some(value),
+                taken,
+            };
+            coord.publish(message, context);
+            if !taken {
+                coord.skip(skip);
+            }
+            coord.after(Duration::new(0, 0), context, |coord, context| {
+                if coord.state.status == State::Running {
+                    coord.try_advance(context);
+                }
+            });
+        });
+    }
+    /// Skips the given number of actions (the branch of a conditional step not taken).
+    fn skip(&mut self, count: usize) {
+        let count = count.min(self.state.remaining.len());
+        self.state.remaining.drain(..count);
+    }
+    /// Resets the volume measured by the flow sensor, if any.
+    fn reset_flow(&self) {
+        if let Some(flow) = self.addresses.as_ref().and_then(|a| a.flow.as_ref()) {
+            flow.do_send(FlowMessage::Reset);
+        }
+    }
+    /// The volume the active pump should move over a perfusion, if its flow rate is calibrated.
+    ///
+    /// Programs run the pumps at full speed, so this is the flow rate over the perfusion's length.
+    fn expected_flow(&self) -> Option<Volume> {
+        let rate = self.flow_rates.get(self.state.pump).cloned().flatten()?;
+        let volume = rate.millilitres_per_second() * DURATION.as_secs_f64();
+        Some(Volume::new::<milliliter>(volume))
+    }
+    /// Compares the volume measured by the flow sensor (if any) against the expected volume.
+    ///
+    /// If they diverge by more than the configured tolerance (e.g. due to a clogged line or an
+    /// empty reservoir), subscribers are notified and, if so configured, the program is aborted.
+    fn verify_flow(&self, expected: Volume, context: &mut CoordContext) {
+        let (flow, config) = match (
+            self.addresses.as_ref().and_then(|a| a.flow.as_ref()),
+            self.flow,
+        ) {
+            (Some(flow), Some(config)) => (flow, config),
+            _ => return,
+        };
+        let request = flow.send(FlowMessage::Measure);
+        context.spawn(
+            request
+                .into_actor(self)
+                .then(move |result, coord, context| {
+                    match result {
+                        Ok(measured) => {
+                            coord.report(Sensor::Flow, measured.get::<milliliter>(), context);
+                            let deviation = ((measured - expected) / expected).value.abs();
+                            log::debug!(
+                                "Measured {:?} of flow (expected {:?})",
+                                measured,
+                                expected
+                            );
+                            if deviation > config.tolerance {
+                                log::warn!(
+                                    "Flow mismatch: expected {:?}, measured {:?}",
+                                    expected,
+                                    measured
+                                );
+                                coord.warn(format!(
+                                    "Expected {:.1} mL of flow, but measured {:.1} mL.",
+                                    expected.get::<milliliter>(),
+                                    measured.get::<milliliter>()
+                                ));
+                                coord.publish(
+                                    StatusMessage::FlowMismatch { expected, measured },
+                                    context,
+                                );
+                                if config.alarm == FlowAlarm::Abort {
+                                    coord.state.remaining.clear();
+                                    if let Err(err) = coord.hcf(context) {
+                                        log::error!("Could not fully stop program: {:?}", err);
+                                    }
+                                    coord.publish(StatusMessage::Halted, context);
+                                }
+                            }
+                        }
+                        Err(err) => log::error!("Failed to reach flow sensor: {}", err),
+                    }
+                    fut::ok(())
+                }),
+        );
+    }
+    /// Checks the pressure sensor (if any), faulting if the configured limit is exceeded.
+    fn check_pressure(&self, context: &mut CoordContext) {
+        let (sensor, limit) = match (
+            self.addresses.as_ref().and_then(|a| a.pressure.as_ref()),
+            self.pressure,
+        ) {
+            (Some(sensor), Some(config)) => (sensor, config.limit()),
+            _ => return,
+        };
+        let request = sensor.send(PressureMessage::Measure);
+        context.spawn(
+            request
+                .into_actor(self)
+                .then(move |result, coord, context| {
+                    match result {
+                        Ok(Ok(pressure)) => {
+                            coord.report(Sensor::Pressure, pressure.get::<kilopascal>(), context);
+                            if pressure > limit {
+                                // Only fault once per excursion above the limit.
+                                if !coord.overpressure {
+                                    coord.overpressure = true;
+                                    coord.fault(Fault::Overpressure { pressure, limit }, context);
+                                }
+                            } else {
+                                coord.overpressure = false;
+                            }
+                        }
+                        Ok(Err(err)) => log::error!("Failed to read pressure: {}", err),
+                        Err(err) => log::error!("Failed to reach pressure sensor: {}", err),
+                    }
+                    fut::ok(())
+                }),
+        );
+    }
+    /// Publishes a sensor reading, recording it in the run history if a program is running.
+    fn report(&self, sensor: Sensor, value: f64, context: &mut CoordContext) {
+        #[cfg(feature = "history")]
+        {
+            let running = match self.state.status {
+                State::Running | State::Paused | State::Waiting => true,
+                _ => false,
+            };
+            if running {
+                let quantity = format!("{} ({})", sensor, sensor.unit());
+                self.chronicle(|history, id| {
+                    history.reading(id, &quantity, value, SystemTime::now())
+                });
+            }
+        }
+        self.publish(StatusMessage::Reading { sensor, value }, context);
+    }
+    /// Passes the given message about the given event on to the notifiers.
+    fn message(&self, event: NotificationEvent, subject: &str, message: &str) {
+        let summary = self.summary();
+        let notification = Notification::message(event, subject, message, &summary);
+        self.notifiers.broadcast(&notification);
+    }
+    /// Passes the given status change on to the notifiers, describing the current run.
+    fn notify(&self, status: Notice) {
+        self.notifiers
+            .broadcast(&Notification::new(status, &self.summary()));
+    }
+    /// Summarizes the current (or most recent) program for notifications.
+    fn summary(&self) -> Summary {
+        Summary {
+            protocol: self.state.name.clone(),
+            job: self.state.uuid,
+            buffers: self.buffers.clone(),
+            steps: self.state.timings.clone(),
+            step: self
+                .state
+                .position()
+                .and_then(|index| Some((index, self.state.current.clone()?))),
+            total: self.state.elapsed(),
+            warnings: self.state.warnings.clone(),
+            fault: None,
+            remaining: self.state.time_remaining(),
+        }
+    }
+    /// Notes something which went wrong without stopping the program, for the summary.
+    fn warn(&mut self, warning: String) {
+        if self.is_stopped() {
+            return;
+        }
+        self.state.warnings.push(match self.state.position() {
+            Some(step) => format!("Step {}: {}", step + 1, warning),
+            None => warning,
+        });
+    }
+    /// Checks whether the timed step which just ended (if any) overran, responding according to
+    /// its overrun policy (or the configured one).
+    ///
+    /// Unlike the step timings, the time spent paused counts toward the step. Returns whether the
+    /// program was halted.
+    fn check_overrun(&mut self, context: &mut CoordContext) -> bool {
+        let expected = match self.state.current {
+            Some(Action::Sleep(duration)) | Some(Action::HoldTemperature { duration, .. }) => {
+                duration
+            }
+            _ => return false,
+        };
+        let since = match self.state.step_started {
+            Some((since, _)) if !self.state.aborted => since,
+            _ => return false,
+        };
+        let overrun = self.state.overrun().unwrap_or(self.overrun);
+        let taken = self.state.simulated(since.elapsed());
+        let over = match taken.checked_sub(expected) {
+            Some(over) if over > overrun.tolerance => over,
+            _ => return false,
+        };
+        log::info!(
+            "Step ran {} past its {} (tolerating {}); policy: {}.",
+            format_duration(over),
+            format_duration(expected),
+            format_duration(overrun.tolerance),
+            overrun.policy
+        );
+        if overrun.policy == OverrunPolicy::Continue {
+            return false;
+        }
+        let warning = format!(
+            "Ran {} past its {} (tolerating {}).",
+            format_duration(over),
+            format_duration(expected),
+            format_duration(overrun.tolerance)
+        );
+        self.warn(warning.clone());
+        self.message(NotificationEvent::Notification, "Step overran", &warning);
+        self.publish(
+            StatusMessage::Overrun {
+                expected,
+                taken,
+                policy: overrun.policy,
+            },
+            context,
+        );
+        if overrun.policy != OverrunPolicy::Abort {
+            return false;
+        }
+        self.state.remaining.clear();
+        if let Err(err) = self.hcf(context) {
+            log::error!("Could not fully stop program: {:?}", err);
+        }
+        self.publish(StatusMessage::Halted, context);
+        true
+    }
+    /// Responds to a hardware fault by stopping the pump, releasing the temperature, and shutting
+    /// all valves.
+    ///
+    /// Any running program is aborted, the coordinator enters the faulted state, and subscribers
+    /// and administrators are notified.
+    fn fault(&mut self, fault: Fault, context: &mut CoordContext) {
+        let running = !self.is_stopped();
+        self.latch(fault, context);
+        let mut summary = if running {
+            self.summary()
+        } else {
+            Summary::default()
+        };
+        summary.fault = Some(fault.to_string());
+        // Give the event log a moment to record the fault before attaching it.
+        context.run_later(Duration::new(1, 0), move |coord, _| {
+            let attachments = coord.report.attachments();
+            let notification =
+                Notification::new(Notice::Faulted, &summary).with_attachments(&attachments);
+            coord.notifiers.broadcast(&notification);
+        });
+    }
+    /// Does everything [`fault`](#method.fault) does except notify the administrators.
+    fn latch(&mut self, fault: Fault, context: &mut CoordContext) {
+        log::error!("Fault detected: {}", fault);
+        self.stop_pump(context);
+        self.release_temperature();
+        self.close_all(context);
+        if !self.is_stopped() {
+            self.state.remaining.clear();
+            self.state.lap();
+            // We didn't finish the last step, so remove it from the list
+            self.state.completed.pop();
+            #[cfg(feature = "history")]
+            self.chronicle(|history, id| {
+                let now = SystemTime::now();
+                history.fault(id, &fault.to_string(), now)?;
+                history.end(id, Outcome::Faulted, now)
+            });
+        }
+        self.state.status = State::Faulted;
+        self.state.paused = None;
+        self.timers.frozen.clear();
+        self.watch(None);
+        self.publish(StatusMessage::Fault(fault), context);
+    }
+    /// Tells the watchdog (if any) how long we expect to take before moving on, if at all.
+    fn watch(&self, expected: Option<Duration>) {
+        if let Some(watchdog) = self.addresses.as_ref().and_then(|a| a.watchdog.as_ref()) {
+            watchdog.do_send(match expected {
+                Some(duration) => watchdog::Message::Expect(duration),
+                None => watchdog::Message::Idle,
+            });
+        }
+    }
+    /// Refuses to start anything new while a fault is latched.
+    fn check_fault(&self) -> Result<()> {
+        if self.state.status == State::Faulted {
+            Err(Error::Faulted)
+        } else {
+            Ok(())
+        }
+    }
+    /// Whether the emergency-stop button (if any) is currently pressed.
+    fn estop_pressed(&self) -> bool {
+        let input = self
+            .addresses
+            .as_ref()
+            .and_then(|addresses| addresses.estop.as_ref());
+        match (self.estop, input) {
+            (Some(config), Some(input)) => input.is_high() != config.invert,
+            _ => false,
+        }
+    }
+    /// Clears a latched fault, unless the emergency stop is still pressed.
+    fn reset(&mut self, context: &mut CoordContext) -> Result<()> {
+        if self.estop_pressed() {
+            return Err(Error::EmergencyStop);
+        }
+        if self.state.status == State::Faulted {
+            log::info!("Fault reset by operator.");
+            self.state.status = State::Stopped { early: true };
+            // Devices we gave up on get a fresh set of attempts.
+            self.supervision.restarts.clear();
+            self.publish(StatusMessage::Reset, context);
+        }
+        Ok(())
+    }
+    /// Measures the reservoir levels (if any), notifying everyone when one runs low mid-run.
+    fn check_levels(&self, context: &mut CoordContext) {
+        let reservoirs = match self.addresses.as_ref().and_then(|a| a.reservoirs.as_ref()) {
+            Some(reservoirs) => reservoirs,
+            None => return,
+        };
+        let request = reservoirs.send(LevelMessage::Measure);
+        context.spawn(request.into_actor(self).then(|result, coord, context| {
+            match result {
+                Ok(Ok(levels)) => {
+                    for level in &levels {
+                        let was_low = coord
+                            .levels
+                            .iter()
+                            .any(|old| old.buffer == level.buffer && old.low);
+                        if level.low && !was_low && !coord.is_stopped() {
+                            let name = coord.buffers.name(level.buffer);
+                            log::warn!("Reservoir for {} is running low.", name);
+                            coord.publish(
+                                StatusMessage::ReservoirLow {
+                                    buffer: level.buffer,
+                                    volume: level.volume,
+                                },
+                                context,
+                            );
+                            let message = format!(
+                                "The reservoir for {} is running low ({} mL remaining).",
+                                name,
+                                level.volume.get::<milliliter>()
+                            );
+                            coord.message(NotificationEvent::LowBuffer, "Low buffer", &message);
+                            coord.warn(message);
+                        }
+                    }
+                    coord.levels = levels;
+                }
+                Ok(Err(err)) => log::error!("Failed to measure reservoir levels: {}", err),
+                Err(err) => log::error!("Failed to reach reservoirs: {}", err),
+            }
+            fut::ok(())
+        }));
+    }
+    /// Ensures that each monitored reservoir holds enough buffer for the given program.
+    ///
+    /// Until a reservoir has been measured (just after starting up, say), programs drawing from it
+    /// are refused.
+    fn check_supply(&self, program: &Program) -> Result<()> {
+        let actions: Vec<Action> = program.clone().into();
+        for &buffer in &self.monitored {
+            let perfusions = actions
+                .iter()
+                .filter(|action| **action == Action::Perfuse(buffer))
+                .count();
+            if perfusions == 0 {
+                continue;
+            }
+            let level = self
+                .levels
+                .iter()
+                .find(|level| level.buffer == buffer)
+                .ok_or(Error::LevelUnknown(buffer))?;
+            let required = *VOLUME * perfusions as f64;
+            if required > level.volume {
+                return Err(Error::InsufficientBuffer {
+                    buffer: level.buffer,
+                    required,
+                    available: level.volume,
+                });
+            }
+        }
+        Ok(())
+    }
+    /// Pauses perfusion in response to a detected bubble.
+    ///
+    /// If a purge duration is configured, the line is purged through the waste valve and perfusion
+    /// resumes automatically; otherwise, the coordinator waits for the operator to continue. The
+    /// length of the perfusion step is not extended to make up for the pause.
+    fn bubble_detected(&mut self, context: &mut CoordContext) {
+        if !self.state.perfusing || self.state.bubble {
+            return;
+        }
+        log::warn!("Bubble detected; pausing perfusion.");
+        self.warn("A bubble was detected, and perfusion was paused.".into());
+        self.stop_pump(context);
+        self.state.bubble = true;
+        let purge = self.bubble.and_then(|config| config.purge);
+        self.publish(
+            StatusMessage::BubbleDetected {
+                purge: purge.is_some(),
+            },
+            context,
+        );
+        match purge {
+            Some(purge) => {
+                // The purge holds up the perfusion step.
+                let expected = expected_duration(&Action::Perfuse(0))
+                    .map(|duration| duration + purge + *PUMP_DELAY * 2);
+                self.watch(expected);
+                self.open_waste(context);
+                self.after(*PUMP_DELAY, context, move |coord, context| {
+                    coord.perfuse(context);
+                    coord.after(purge, context, |coord, context| {
+                        coord.stop_pump(context);
+                        coord.state.bubble = false;
+                        coord.publish(StatusMessage::BubbleCleared, context);
+                        if coord.state.perfusing {
+                            coord.shut_waste(context);
+                            coord.after(*PUMP_DELAY, context, |coord, context| {
+                                if coord.state.perfusing && !coord.state.bubble {
+                                    coord.perfuse(context);
+                                }
+                            });
+                        }
+                    });
+                });
+            }
+            None => {
+                self.state.status = State::Waiting;
+                self.watch(None);
+                self.publish(StatusMessage::Paused, context);
+            }
+        }
+    }
+    /// Resumes after the operator has confirmed that a bubble has been cleared.
+    fn clear_bubble(&mut self, context: &mut CoordContext) {
+        self.state.bubble = false;
+        self.state.status = State::Running;
+        self.publish(StatusMessage::BubbleCleared, context);
+        if self.state.perfusing {
+            self.watch(expected_duration(&Action::Perfuse(0)));
+            self.perfuse(context);
+        } else {
+            // The perfusion step wound down while we were waiting.
+            self.try_advance(context);
+        }
+    }
+    /// Attempts to run the next step of the program, aborting and cleaning up on failure.
+    fn try_advance(&mut self, context: &mut CoordContext) {
+        let result = self.advance(context);
+        if let Err(err) = result {
+            // TODO: Notify user
+            log::error!("Aborting due to program advance error: {:?}", err);
+            #[cfg(feature = "history")]
+            self.chronicle(|history, id| history.end(id, Outcome::Halted, SystemTime::now()));
+            let mut tries = 0;
+            let mut result = self.hcf(context);
+            while tries < 5 && result.is_err() {
+                std::thread::sleep(Duration::from_millis(200));
+                result = self.hcf(context);
+                tries += 1;
+            }
+            if result.is_err() {
+                log::error!("Could not fully stop program; please take caution!");
+            }
+        }
+    }
+    /// Moves to the next step of the program, returning the new current action.
+    fn advance(&mut self, context: &mut CoordContext) -> Result<Option<Action>> {
+        if !self.state.remaining.is_empty() {
+            if self.check_overrun(context) {
+                return Ok(None);
+            }
+            self.state.status = State::Running;
+            let action = self.state.remaining.remove(0);
+            self.state.lap();
+            // Record the step before running it, since some steps advance again immediately.
+            self.watch(expected_duration(&action));
+            self.state.completed.push(action.clone());
+            self.state.current = Some(action.clone());
+            self.state.step_started = Some((Instant::now(), self.state.paused_for));
+            if let Some(step) = self.state.position() {
+                self.publish(StatusMessage::Step(step), context);
+                #[cfg(feature = "history")]
+                self.chronicle(|history, id| history.step(id, step, &action, SystemTime::now()));
+            }
+            // Make sure to message something that will call advance again later!
             // Usually this will be try_advance.
-            match action.clone() {
+            match action {
                 Action::Perfuse(buffer) => {
                     self.shut_waste(context);
                     self.open(buffer, context);
-                    context.run_later(*PUMP_DELAY, move |coord, context| {
-                        coord.perfuse();
-                        context.run_later(*DURATION, move |coord, context| {
+                    self.after(*PUMP_DELAY, context, move |coord, context| {
+                        coord.perfuse(context);
+                        coord.reset_flow();
+                        coord.state.perfusing = true;
+                        coord.after(*DURATION, context, move |coord, context| {
+                            coord.state.perfusing = false;
+                            // Without a calibrated flow rate, there's nothing to compare to.
+                            if let Some(expected) = coord.expected_flow() {
+                                coord.verify_flow(expected, context);
+                            }
                             coord.close(buffer, context);
                             coord.open_waste(context);
-                            // Clear the line for ten seconds
-                            context.run_later(Duration::new(10, 0), move |coord, context| {
-                                coord.stop_pump();
+                            // Clear the line
+                            coord.after(*CLEAR_DURATION, context, move |coord, context| {
+                                coord.stop_pump(context);
                                 coord.close_waste(context);
-                                coord.try_advance(context);
+                                // The program may have been aborted in the meantime
+                                if coord.state.status == State::Running {
+                                    coord.try_advance(context);
+                                }
                             });
                         });
                     });
                 }
+                Action::Gradient {
+                    from,
+                    to,
+                    duration,
+                    period,
+                } => {
+                    log::trace!("Ramping from buffer {} to buffer {}.", from, to);
+                    self.shut_waste(context);
+                    self.open(from, context);
+                    self.after(*PUMP_DELAY, context, move |coord, context| {
+                        coord.perfuse(context);
+                        coord.state.perfusing = true;
+                        let slices = match period.as_secs_f64() {
+                            period if period > 0.0 => {
+                                ((duration.as_secs_f64() / period).ceil() as usize).max(1)
+                            }
+                            _ => 1,
+                        };
+                        coord.gradient(0, slices, from, to, duration / slices as u32, context);
+                    });
+                }
                 Action::Sleep(duration) => {
-                    context.run_later(duration, Self::try_advance);
+                    self.after(duration, context, Self::try_advance);
                 }
                 Action::Hail => {
                     self.state.status = State::Waiting;
-                    // TODO: Publish for other actions as well
                     self.publish(StatusMessage::Paused, context);
                 }
+                Action::Mix {
+                    cycles,
+                    forward,
+                    backward,
+                } => {
+                    log::trace!("Mixing ({} cycle(s)).", cycles);
+                    self.close_waste(context);
+                    self.after(*PUMP_DELAY, context, move |coord, context| {
+                        coord.mix(1, cycles, forward, backward, context);
+                    });
+                }
                 Action::Drain => {
                     self.close_waste(context);
-                    context.run_later(*PUMP_DELAY, move |coord, context| {
-                        coord.drain();
-                        context.run_later(*DURATION * 2, |coord, context| {
-                            coord.stop_pump();
+                    self.after(*PUMP_DELAY, context, move |coord, context| {
+                        coord.drain(context);
+                        coord.after(*DURATION * 2, context, |coord, context| {
+                            coord.stop_pump(context);
                             coord.shut_waste(context);
-                            coord.try_advance(context);
+                            if coord.state.status == State::Running {
+                                coord.try_advance(context);
+                            }
                         });
                     });
                 }
                 Action::Finish => {
-                    self.stop_pump();
+                    self.stop_pump(context);
+                    self.release_temperature();
                     self.close_all(context);
-                    // TODO: Handle error
-                    let _ = mail::notify(&self.admins, mail::Status::Finished);
-                    // TODO: Update coordinator state
+                    self.notify(Notice::Finished);
+                    self.state.status = State::Stopped { early: false };
+                    #[cfg(feature = "history")]
+                    self.chronicle(|history, id| {
+                        history.end(id, Outcome::Completed, SystemTime::now())
+                    });
+                    self.publish(StatusMessage::Completed, context);
+                    self.start_next(context);
                 }
                 Action::Notify(msg) => {
                     log::trace!("Notifying user (subject: {}).", msg.subject);
-                    // TODO: Handle error
-                    let _ = mail::mail(&self.admins, msg.subject, msg.message);
+                    let mut message = msg.message;
+                    if let Some(remaining) = self.state.time_remaining() {
+                        message.push_str(&format!(
+                            "\n\nThe run should finish in about {}.",
+                            format_duration(remaining)
+                        ));
+                    }
+                    self.message(NotificationEvent::Notification, &msg.subject, &message);
+                    self.try_advance(context);
+                }
+                Action::UsePump(pump) => {
+                    log::trace!("Switching to pump {}.", pump);
+                    self.state.pump = pump;
+                    self.try_advance(context);
+                }
+                Action::Iterate(iteration) => {
+                    match iteration {
+                        Some(iteration) => log::debug!("Beginning {}.", iteration),
+                        None => log::trace!("Leaving repeat."),
+                    }
+                    self.try_advance(context);
+                }
+                Action::HoldTemperature {
+                    target, duration, ..
+                } if self.state.simulation.is_some() => {
+                    let temperature = ThermodynamicTemperature::new::<degree_celsius>(target);
+                    log::trace!("Simulating temperature hold at {} °C.", target);
+                    self.publish(StatusMessage::TemperatureReached(temperature), context);
+                    self.after(duration, context, |coord, context| {
+                        if coord.state.status == State::Running {
+                            coord.try_advance(context);
+                        }
+                    });
+                }
+                Action::HoldTemperature {
+                    target,
+                    tolerance,
+                    duration,
+                } => {
+                    let thermostat = self.thermostat().ok_or(Error::NoThermostat)?;
+                    log::trace!("Holding temperature at {} ± {} °C.", target, tolerance);
+                    thermostat.do_send(ThermalMessage::Hold { target, tolerance });
+                    self.await_temperature(target, tolerance, duration, context);
+                }
+                Action::WaitUntil {
+                    sensor,
+                    predicate,
+                    timeout,
+                } => {
+                    log::trace!("Waiting for the {} to be {}.", sensor, predicate);
+                    self.await_condition(sensor, predicate, timeout, context);
+                }
+                Action::Branch {
+                    sensor,
+                    predicate,
+                    skip,
+                } => self.branch(sensor, predicate, skip, context),
+                Action::Skip(count) => {
+                    log::trace!("Skipping {} action(s).", count);
+                    self.skip(count);
+                    self.try_advance(context);
+                }
+                Action::Hook(event) => {
+                    log::trace!("Firing hook {}.", event);
+                    self.publish(StatusMessage::Hook(event), context);
                     self.try_advance(context);
                 }
+                Action::Overrun(overrun) => {
+                    match overrun {
+                        Some(overrun) => log::trace!("Applying overrun policy {:?}.", overrun),
+                        None => log::trace!("Applying the configured overrun policy."),
+                    }
+                    self.try_advance(context);
+                }
+                Action::SetPosition { motor, position } => {
+                    log::trace!("Moving motor {} to {}.", motor, position);
+                    self.command(motor, MotorMessage::SetPosition(position), context);
+                    self.after(*SETTLE_DELAY, context, move |coord, context| {
+                        coord.settle(motor, context);
+                        if coord.state.status == State::Running {
+                            coord.try_advance(context);
+                        }
+                    });
+                }
             }
-            self.state.completed.push(action.clone());
-            self.state.current = Some(action);
         } else {
-            self.state.status = State::Stopped { early: false };
+            let aborted = mem::replace(&mut self.state.aborted, false);
+            if aborted {
+                // The teardown always leaves everything safe.
+                self.stop_pump(context);
+                self.release_temperature();
+                self.close_all(context);
+            }
+            self.state.status = State::Stopped { early: aborted };
             self.state.current = None;
+            self.watch(None);
+            #[cfg(feature = "history")]
+            self.chronicle(|history, id| {
+                let outcome = if aborted {
+                    Outcome::Aborted
+                } else {
+                    Outcome::Stopped
+                };
+                history.end(id, outcome, SystemTime::now())
+            });
         }
+        self.record();
         Ok(self.state.current.clone())
     }
+    /// Runs the given time slice of a gradient (counting from 0) and those after it, opening the
+    /// first buffer's valve for a shrinking share of each slice and the second's for the rest.
+    ///
+    /// Once the last slice has run, the line is cleared and the program moves on.
+    fn gradient(
+        &mut self,
+        slice: usize,
+        slices: usize,
+        from: MotorId,
+        to: MotorId,
+        period: Duration,
+        context: &mut CoordContext,
+    ) {
+        let percent = slice as f64 * 100.0 / slices as f64;
+        self.publish(StatusMessage::Gradient { from, to, percent }, context);
+        if slice == slices {
+            self.state.perfusing = false;
+            self.close(to, context);
+            self.open_waste(context);
+            // Clear the line
+            self.after(*CLEAR_DURATION, context, |coord, context| {
+                coord.stop_pump(context);
+                coord.close_waste(context);
+                if coord.state.status == State::Running {
+                    coord.try_advance(context);
+                }
+            });
+            return;
+        }
+        // Each slice is timed by its midpoint, so the ramp is symmetric.
+        let share = (slice as f64 + 0.5) / slices as f64;
+        let first = Duration::from_secs_f64(period.as_secs_f64() * (1.0 - share));
+        self.open(from, context);
+        self.close(to, context);
+        self.after(first, context, move |coord, context| {
+            coord.open(to, context);
+            coord.close(from, context);
+            let second = period.checked_sub(first).unwrap_or_default();
+            coord.after(second, context, move |coord, context| {
+                coord.gradient(slice + 1, slices, from, to, period, context);
+            });
+        });
+    }
+    /// Runs the given cycle of a mix (counting from 1) and those after it, running the pump forward
+    /// and then backward for the given durations each time.
+    fn mix(
+        &mut self,
+        cycle: usize,
+        cycles: usize,
+        forward: Duration,
+        backward: Duration,
+        context: &mut CoordContext,
+    ) {
+        self.publish(StatusMessage::Mixing { cycle, cycles }, context);
+        self.perfuse(context);
+        self.after(forward, context, move |coord, context| {
+            coord.drain(context);
+            coord.after(backward, context, move |coord, context| {
+                if cycle < cycles {
+                    coord.mix(cycle + 1, cycles, forward, backward, context);
+                    return;
+                }
+                coord.stop_pump(context);
+                coord.shut_waste(context);
+                if coord.state.status == State::Running {
+                    coord.try_advance(context);
+                }
+            });
+        });
+    }
+    /// Records something about the current job in the run history, if one is kept.
+    #[cfg(feature = "history")]
+    fn chronicle<F>(&self, record: F)
+    where
+        F: FnOnce(&History, Uuid) -> history::Result<()>,
+    {
+        if let (Some(history), Some(id)) = (&self.history, self.state.uuid) {
+            if let Err(err) = record(history, id) {
+                log::error!("Failed to record run history: {}", err);
+            }
+        }
+    }
+    /// Lists the recorded runs, most recent first.
+    #[cfg(feature = "history")]
+    pub fn runs(&self) -> Result<Vec<Run>> {
+        let history = self.history.as_ref().ok_or(Error::NoHistory)?;
+        Ok(history.runs()?)
+    }
+    /// Fetches the recorded run with the given label, if there is one.
+    #[cfg(feature = "history")]
+    pub fn run(&self, id: Uuid) -> Result<Option<Run>> {
+        let history = self.history.as_ref().ok_or(Error::NoHistory)?;
+        Ok(history.run(id)?)
+    }
+    /// The library of stored protocols.
+    #[cfg(feature = "use_serde")]
+    pub fn library(&self) -> Result<&Library> {
+        self.library.as_ref().ok_or(Error::NoLibrary)
+    }
+    /// The catalog of protocol files.
+    #[cfg(feature = "use_serde")]
+    pub fn catalog(&self) -> Result<&Catalog> {
+        self.catalog.as_ref().ok_or(Error::NoCatalog)
+    }
+    /// The protocol file with the given name in the catalog, converted for this system's hardware
+    /// with the given values for its parameters (see [`ProtocolFile::bind`]).
+    ///
+    /// [`ProtocolFile::bind`]: ../struct.ProtocolFile.html#method.bind
+    #[cfg(feature = "use_serde")]
+    pub fn cataloged(&self, name: &str, values: &Bindings) -> Result<Protocol> {
+        let entry = self
+            .catalog()?
+            .get(name)
+            .ok_or_else(|| Error::NoSuchProtocol(name.into()))?;
+        entry
+            .file
+            .resolve_with(&self.hardware(), values)
+            .map_err(Error::ProtocolFile)
+    }
+    /// The given Autoprotocol protocol, converted for this system's hardware (see
+    /// [`Autoprotocol::to_file`]).
+    ///
+    /// [`Autoprotocol::to_file`]: ../struct.Autoprotocol.html#method.to_file
+    #[cfg(feature = "use_serde")]
+    pub fn import(&self, autoprotocol: &Autoprotocol) -> Result<Protocol> {
+        autoprotocol
+            .to_file()
+            .map_err(Error::Autoprotocol)?
+            .resolve(&self.hardware())
+            .map_err(Error::ProtocolFile)
+    }
+    /// The given protocol in Autoprotocol, naming buffers as configured (see
+    /// [`Autoprotocol::from_protocol`]).
+    ///
+    /// [`Autoprotocol::from_protocol`]: ../struct.Autoprotocol.html#method.from_protocol
+    #[cfg(feature = "use_serde")]
+    pub fn to_autoprotocol(&self, protocol: &Protocol) -> Result<Autoprotocol> {
+        Autoprotocol::from_protocol(protocol, &self.hardware()).map_err(Error::Autoprotocol)
+    }
+    /// Exports the recorded run with the given label in the given format, if there is one.
+    #[cfg(feature = "history")]
+    pub fn export(&self, id: Uuid, format: history::Format) -> Result<Option<String>> {
+        let history = self.history.as_ref().ok_or(Error::NoHistory)?;
+        Ok(history.export(id, format)?)
+    }
+    /// Saves the state of the running program to the journal (or removes the journal if no
+    /// program is running).
+    ///
+    /// The journal is left alone while an interrupted run awaits recovery.
+    fn record(&self) {
+        let path = match &self.journal {
+            Some(path) if self.state.interrupted.is_none() => path,
+            _ => return,
+        };
+        let running = match self.state.status {
+            State::Running | State::Paused | State::Waiting => true,
+            _ => false,
+        };
+        let result = match (self.state.uuid, self.state.position()) {
+            (Some(id), Some(step)) if running => {
+                let mut actions = self.state.completed.clone();
+                actions.extend(self.state.remaining.iter().cloned());
+                let entry = journal::Entry {
+                    id,
+                    actions,
+                    step,
+                    elapsed: self.state.elapsed().unwrap_or_default(),
+                    buffer: self.state.buffer,
+                    pump: self.state.pump,
+                    aborted: self.state.aborted,
+                };
+                journal::save(path, &entry)
+            }
+            _ => journal::clear(path),
+        };
+        if let Err(err) = result {
+            log::error!("Failed to update the journal: {}", err);
+        }
+    }
+    /// Checks the journal for a run that was interrupted, announcing it if there is one.
+    fn check_journal(&mut self, context: &mut CoordContext) {
+        let path = match &self.journal {
+            Some(path) => path,
+            None => return,
+        };
+        match journal::load(path) {
+            Ok(Some(entry)) => {
+                log::warn!(
+                    "Job {} was interrupted at step {}; it must be resumed or aborted.",
+                    entry.id,
+                    entry.step
+                );
+                let message = StatusMessage::Interrupted {
+                    id: entry.id,
+                    step: entry.step,
+                };
+                self.state.interrupted = Some(entry);
+                self.publish(message, context);
+            }
+            Ok(None) => {}
+            Err(err) => log::error!("Failed to read the journal: {}", err),
+        }
+    }
+    /// Resumes or safely aborts the interrupted run.
+    ///
+    /// Either way, the run's state is restored first, with the interrupted step to be run again.
+    ///
+    /// The configuration may have changed since the run was interrupted, so a run can only be
+    /// resumed if its steps still fit the hardware (it can always be aborted).
+    fn recover(&mut self, recovery: Recovery, context: &mut CoordContext) -> Result<()> {
+        self.check_fault()?;
+        let entry = self
+            .state
+            .interrupted
+            .as_ref()
+            .ok_or(Error::NotInterrupted)?;
+        if matches!(recovery, Recovery::Resume) {
+            self.check_actions(&entry.actions)?;
+            if entry.pump >= self.pump_count() {
+                return Err(Error::NoSuchPump(entry.pump));
+            }
+        }
+        let entry = self.state.interrupted.take().ok_or(Error::NotInterrupted)?;
+        let mut actions = entry.actions;
+        let step = entry.step.min(actions.len());
+        self.state.remaining = actions.split_off(step);
+        self.state.completed = actions;
+        self.state.current = None;
+        self.state.program = None;
+        self.state.uuid = Some(entry.id);
+        self.state.buffer = entry.buffer;
+        // Only a resumed run's pump has been checked, so the teardown falls back to the first.
+        self.state.pump = if entry.pump < self.pump_count() {
+            entry.pump
+        } else {
+            0
+        };
+        self.state.aborted = entry.aborted;
+        self.state.perfusing = false;
+        self.state.bubble = false;
+        let now = Instant::now();
+        self.state.started = Some(now.checked_sub(entry.elapsed).unwrap_or(now));
+        self.state.paused_for = Duration::new(0, 0);
+        self.state.paused = None;
+        self.timers.frozen.clear();
+        match recovery {
+            Recovery::Resume => {
+                log::info!("Resuming job {} at step {}.", entry.id, step);
+                self.stop_pump(context);
+                self.close_all(context);
+                self.state.starting = true;
+                context.run_later(Duration::new(10, 0), |coord, context| {
+                    coord.state.starting = false;
+                    // A device may have failed while we were getting ready.
+                    if coord.state.status == State::Faulted {
+                        return;
+                    }
+                    coord.state.status = State::Running;
+                    coord.try_advance(context);
+                });
+            }
+            Recovery::Abort => {
+                log::warn!("Aborting interrupted job {}; running teardown.", entry.id);
+                self.state.remaining = self.teardown.clone();
+                self.state.aborted = true;
+                self.state.status = State::Running;
+                self.notify(Notice::Aborted);
+                self.try_advance(context);
+            }
+        }
+        Ok(())
+    }
     /// Clears the remaining program queue after the next perfusion.
     fn clear(&mut self) -> Result<()> {
         if let Some(index) = self.state.remaining.iter().position(Action::is_disjoint) {
@@ -418,26 +3078,455 @@ impl Coordinator {
             log::warn!("Coordinator told to resume while not paused; ignoring.");
             return Ok(());
         }
-        self.state.status = State::Running;
-        self.advance(context)?;
+        self.state.status = State::Running;
+        self.advance(context)?;
+        Ok(())
+    }
+    /// Pause the program, stopping the pump and freezing the step timers.
+    fn pause(&mut self, context: &mut CoordContext) -> Result<()> {
+        if self.status() != State::Running {
+            log::warn!("Coordinator told to pause while not running; ignoring.");
+            return Ok(());
+        }
+        self.state.paused = Some((Instant::now(), self.state.pumping));
+        self.stop_pump(context);
+        self.freeze(context);
+        self.state.status = State::Paused;
+        self.watch(None);
+        self.publish(StatusMessage::Suspended, context);
+        self.record();
+        Ok(())
+    }
+    /// Resume a paused program where it left off.
+    fn unpause(&mut self, context: &mut CoordContext) -> Result<()> {
+        let (since, pumping) = match (self.status(), self.state.paused.take()) {
+            (State::Paused, Some(paused)) => paused,
+            _ => {
+                log::warn!("Coordinator told to resume while not paused; ignoring.");
+                return Ok(());
+            }
+        };
+        self.state.paused_for += since.elapsed();
+        self.state.status = State::Running;
+        match pumping {
+            Some(PumpDirection::Forward) => self.perfuse(context),
+            Some(PumpDirection::Backward) => self.drain(context),
+            None => {}
+        }
+        self.thaw(context);
+        self.publish(StatusMessage::Unsuspended, context);
+        self.record();
+        if self.state.current.is_none() {
+            // We jumped to a new step while paused.
+            self.try_advance(context);
+        } else {
+            // The step may have been paused at any point, so expect it to take its full time.
+            let expected = self.state.current.as_ref().and_then(expected_duration);
+            self.watch(expected);
+        }
+        Ok(())
+    }
+    /// Moves a paused program to the given step, abandoning the current one.
+    ///
+    /// Unless forced, only jumps forward are allowed.
+    fn jump(&mut self, index: usize, force: bool, context: &mut CoordContext) -> Result<()> {
+        let (since, from) = match (self.state.paused, self.state.position()) {
+            (Some((since, _)), Some(from)) if self.status() == State::Paused => (since, from),
+            _ => return Err(Error::NotPaused),
+        };
+        if index >= self.state.completed.len() + self.state.remaining.len() {
+            return Err(Error::NoSuchStep(index));
+        }
+        if index <= from && !force {
+            return Err(Error::Backward(index));
+        }
+        log::info!("Jumping from step {} to step {}.", from, index);
+        let mut actions = mem::take(&mut self.state.completed);
+        actions.append(&mut self.state.remaining);
+        self.state.remaining = actions.split_off(index);
+        self.state.completed = actions;
+        self.state.current = None;
+        // Whatever the abandoned step was doing is left undone.
+        self.timers.frozen.clear();
+        self.state.paused = Some((since, None));
+        self.state.perfusing = false;
+        self.state.bubble = false;
+        self.close_all(context);
+        self.publish(StatusMessage::Jumped { from, to: index }, context);
+        self.record();
+        Ok(())
+    }
+    /// Runs the given callback after the given delay, as part of the current step.
+    ///
+    /// Unlike `AsyncContext::run_later`, the delay doesn't elapse while the program is paused.
+    fn after<F>(&mut self, delay: Duration, context: &mut CoordContext, callback: F)
+    where
+        F: FnOnce(&mut Self, &mut CoordContext) + 'static,
+    {
+        if self.state.status == State::Paused {
+            self.timers.frozen.push((delay, Box::new(callback)));
+            return;
+        }
+        let id = self.timers.next;
+        self.timers.next += 1;
+        let delay = self.state.scaled(delay);
+        let handle = context.run_later(delay, move |coord, context| {
+            let pending = &mut coord.timers.pending;
+            if let Some(index) = pending.iter().position(|timer| timer.0 == id) {
+                let (_, _, _, callback) = pending.remove(index);
+                callback(coord, context);
+            }
+        });
+        let due = Instant::now() + delay;
+        self.timers
+            .pending
+            .push((id, handle, due, Box::new(callback)));
+    }
+    /// Cancels the pending step timers, remembering how long each had left.
+    fn freeze(&mut self, context: &mut CoordContext) {
+        let now = Instant::now();
+        for (_, handle, due, callback) in self.timers.pending.drain(..) {
+            context.cancel_future(handle);
+            let left = if due > now {
+                due - now
+            } else {
+                Duration::new(0, 0)
+            };
+            // Frozen timers are kept in real-hardware time, like newly-scheduled ones.
+            self.timers
+                .frozen
+                .push((self.state.simulated(left), callback));
+        }
+    }
+    /// Reschedules the step timers frozen by [`freeze`](#method.freeze).
+    fn thaw(&mut self, context: &mut CoordContext) {
+        for (left, callback) in mem::take(&mut self.timers.frozen) {
+            self.after(left, context, callback);
+        }
+    }
+    /// Abort the program, skipping the remaining steps and running the teardown sequence.
+    fn abort(&mut self, context: &mut CoordContext) -> Result<()> {
+        self.check_fault()?;
+        match self.status() {
+            State::Running | State::Waiting | State::Paused => {}
+            State::Stopped { .. }
+            | State::Priming
+            | State::Washing
+            | State::Calibrating { .. }
+            | State::Manual
+            | State::Faulted => {
+                log::warn!("Coordinator told to abort while no program is running; ignoring.");
+                return Ok(());
+            }
+        }
+        log::warn!("Aborting program; running teardown.");
+        self.hcf(context)?;
+        // The aborted step must not carry on.
+        for (_, handle, _, _) in self.timers.pending.drain(..) {
+            context.cancel_future(handle);
+        }
+        self.state.perfusing = false;
+        self.state.bubble = false;
+        self.state.remaining = self.teardown.clone();
+        self.state.aborted = true;
+        self.try_advance(context);
+        Ok(())
+    }
+    /// Checks that the given actions only refer to pumps and positions that exist.
+    fn check_actions(&self, actions: &[Action]) -> Result<()> {
+        for action in actions {
+            match action {
+                Action::Perfuse(buffer) if !has_buffer(self.positions.len(), *buffer) => {
+                    return Err(Error::NoSuchMotor(*buffer))
+                }
+                Action::Gradient { from, to, .. }
+                    if !has_buffer(self.positions.len(), *from.max(to)) =>
+                {
+                    return Err(Error::NoSuchMotor(*from.max(to)))
+                }
+                Action::UsePump(id) if *id >= self.pump_count() => {
+                    return Err(Error::NoSuchPump(*id))
+                }
+                Action::SetPosition { motor, position } if !self.has_position(*motor, position) => {
+                    return Err(Error::NoSuchPosition {
+                        motor: *motor,
+                        position: position.clone(),
+                    })
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+    /// Abort the program no matter where we are.
+    fn hcf(&mut self, context: &mut CoordContext) -> Result<()> {
+        self.stop_pump(context);
+        self.release_temperature();
+        // TODO: Reset motors?
+        // Time the last step before its pause (if any) is forgotten.
+        self.state.lap();
+        self.state.status = State::Stopped { early: true };
+        self.state.paused = None;
+        self.state.aborted = false;
+        self.timers.frozen.clear();
+        self.watch(None);
+        // We didn't finish the last step, so remove it from the list
+        self.state.completed.pop();
+        self.record();
+        self.notify(Notice::Aborted);
+        Ok(())
+    }
+    /// Drives everything to a safe state and then stops the actix system.
+    ///
+    /// The pumps are stopped, the temperature released, and the valves shut; once the valves have
+    /// had time to move, their signals are turned off and the system is stopped. Asking again
+    /// while shutting down stops the system immediately.
+    fn shutdown(&mut self, context: &mut CoordContext) {
+        if self.shutting_down {
+            log::warn!("Shutdown requested again; exiting immediately.");
+            System::current().stop();
+            return;
+        }
+        log::info!("Shutting down.");
+        self.shutting_down = true;
+        if self.is_stopped() {
+            self.stop_pump(context);
+            self.release_temperature();
+        } else {
+            // hcf can't fail
+            let _ = self.hcf(context);
+            self.state.remaining.clear();
+            self.publish(StatusMessage::Halted, context);
+        }
+        self.close_all(context);
+        context.run_later(*SHUTDOWN_DELAY, |coord, context| {
+            let stops = coord
+                .addresses
+                .iter()
+                .flat_map(|addresses| addresses.motors.iter())
+                .map(|motor| motor.send(MotorMessage::Stop).then(|_| Ok::<_, ()>(())))
+                .collect::<Vec<_>>();
+            context.spawn(
+                future::join_all(stops)
+                    .map(|_| System::current().stop())
+                    .into_actor(coord),
+            );
+        });
+    }
+    /// Whether we're in the stopped state.
+    pub fn is_stopped(&self) -> bool {
+        match self.state.status {
+            State::Stopped { .. } | State::Faulted => true,
+            State::Running
+            | State::Paused
+            | State::Waiting
+            | State::Priming
+            | State::Washing
+            | State::Calibrating { .. }
+            | State::Manual => false,
+        }
+    }
+    /// Describes the hardware this coordinator controls, for checking protocols against it.
+    ///
+    /// Simulated coordinators can hold temperatures without a thermostat.
+    pub fn hardware(&self) -> Hardware {
+        Hardware {
+            positions: self.positions.clone(),
+            pumps: self.pump_count(),
+            thermostat: self.has_thermostat() || self.state.simulation.is_some(),
+            buffers: self
+                .buffers
+                .iter()
+                .map(|buffer| (buffer.name.clone(), buffer.valve))
+                .collect(),
+            flow_rates: self.flow_rates.clone(),
+            sensors: self.sensors(),
+        }
+    }
+    /// The sensors installed (or, when simulating, every sensor).
+    fn sensors(&self) -> Vec<Sensor> {
+        if self.state.simulation.is_some() {
+            return vec![Sensor::Pressure, Sensor::Flow, Sensor::Temperature];
+        }
+        let (pressure, flow) = match (&self.addresses, &self.devices) {
+            (Some(addresses), _) => (addresses.pressure.is_some(), addresses.flow.is_some()),
+            (None, Some(devices)) => (devices.pressure.is_some(), devices.flow.is_some()),
+            (None, None) => (false, false),
+        };
+        let installed = [
+            (Sensor::Pressure, pressure),
+            (Sensor::Flow, flow),
+            (Sensor::Temperature, self.has_thermostat()),
+        ];
+        installed
+            .iter()
+            .filter(|(_, installed)| *installed)
+            .map(|(sensor, _)| *sensor)
+            .collect()
+    }
+    /// The configured buffers, for referring to them by name.
+    pub fn buffers(&self) -> &Buffers {
+        &self.buffers
+    }
+    /// The name of the given protocol in the library or the catalog, if it's kept there.
+    #[cfg(feature = "use_serde")]
+    fn protocol_name(&self, protocol: &Protocol) -> Option<String> {
+        let stored = self
+            .library
+            .as_ref()
+            .and_then(|library| library.list().ok())
+            .unwrap_or_default();
+        let stored = stored
+            .into_iter()
+            .find(|stored| stored.protocol == *protocol)
+            .map(|stored| stored.name);
+        stored.or_else(|| {
+            let hardware = self.hardware();
+            self.catalog
+                .as_ref()?
+                .list()
+                .into_iter()
+                .find(|entry| entry.file.resolve(&hardware).ok().as_ref() == Some(protocol))
+                .map(|entry| entry.file.name.unwrap_or(entry.name))
+        })
+    }
+    /// Checks that the given protocol can be run on this system, converting it to a program.
+    fn check_protocol(&self, protocol: &Protocol) -> Result<Program> {
+        let validation = protocol.check(&self.hardware());
+        for warning in &validation.warnings {
+            log::warn!("Protocol warning: {}", warning);
+        }
+        if !validation.is_ok() {
+            return Err(Error::Invalid(validation));
+        }
+        let program = protocol.as_program()?;
+        self.check_supply(&program)?;
+        Ok(program)
+    }
+    /// Adds the given protocol to the queue, starting it right away if nothing else is running.
+    fn enqueue(
+        &mut self,
+        protocol: Protocol,
+        label: Option<Uuid>,
+        context: &mut CoordContext,
+    ) -> Result<Uuid> {
+        self.check_protocol(&protocol)?;
+        let id = label.unwrap_or_else(Uuid::new_v4);
+        self.state.queue.push(QueuedProtocol { id, protocol });
+        if self.is_stopped() && self.state.status != State::Faulted && !self.state.starting {
+            self.start_next(context);
+        }
+        Ok(id)
+    }
+    /// Removes the job with the given label from the queue.
+    fn dequeue(&mut self, id: Uuid) -> Result<QueuedProtocol> {
+        let index = self.queue_position(id)?;
+        Ok(self.state.queue.remove(index))
+    }
+    /// Moves the queued job with the given label to the given position.
+    fn requeue(&mut self, id: Uuid, index: usize) -> Result<()> {
+        let job = self.dequeue(id)?;
+        let index = index.min(self.state.queue.len());
+        self.state.queue.insert(index, job);
+        Ok(())
+    }
+    /// Finds the position of the job with the given label in the queue.
+    fn queue_position(&self, id: Uuid) -> Result<usize> {
+        self.state
+            .queue
+            .iter()
+            .position(|job| job.id == id)
+            .ok_or(Error::NotQueued(id))
+    }
+    /// Schedules the given protocol, returning the label of the schedule.
+    fn schedule(
+        &mut self,
+        protocol: Protocol,
+        schedule: Schedule,
+        label: Option<Uuid>,
+        context: &mut CoordContext,
+    ) -> Result<Uuid> {
+        if let Some(interval) = schedule.interval.filter(|_| schedule.too_frequent()) {
+            return Err(Error::IntervalTooShort(interval));
+        }
+        self.check_protocol(&protocol)?;
+        let id = label.unwrap_or_else(Uuid::new_v4);
+        self.arm(
+            ScheduledProtocol {
+                id,
+                protocol,
+                schedule,
+            },
+            context,
+        );
+        Ok(id)
+    }
+    /// Sets the timer for the given scheduled protocol.
+    fn arm(&mut self, job: ScheduledProtocol, context: &mut CoordContext) {
+        let id = job.id;
+        let delay = job.schedule.delay(SystemTime::now());
+        let handle = context.run_later(delay, move |coord, context| coord.fire(id, context));
+        self.schedules.push((id, handle));
+        self.state.scheduled.push(job);
+    }
+    /// Cancels the pending schedule with the given label.
+    fn unschedule(&mut self, id: Uuid, context: &mut CoordContext) -> Result<()> {
+        let index = self
+            .state
+            .scheduled
+            .iter()
+            .position(|job| job.id == id)
+            .ok_or(Error::NotScheduled(id))?;
+        self.state.scheduled.remove(index);
+        if let Some(index) = self.schedules.iter().position(|(other, _)| *other == id) {
+            let (_, handle) = self.schedules.remove(index);
+            context.cancel_future(handle);
+        }
         Ok(())
     }
-    /// Abort the program no matter where we are.
-    fn hcf(&mut self) -> Result<()> {
-        self.stop_pump();
-        // TODO: Reset motors?
-        self.state.status = State::Stopped { early: true };
-        // We didn't finish the last step, so remove it from the list
-        self.state.completed.pop();
-        // TODO: Handle error
-        let _ = mail::notify(&self.admins, mail::Status::Aborted);
-        Ok(())
+    /// Queues the scheduled protocol with the given label, rearming the schedule if it recurs.
+    fn fire(&mut self, id: Uuid, context: &mut CoordContext) {
+        self.schedules.retain(|(other, _)| *other != id);
+        let index = match self.state.scheduled.iter().position(|job| job.id == id) {
+            Some(index) => index,
+            None => return,
+        };
+        let job = self.state.scheduled.remove(index);
+        if let Some(schedule) = job.schedule.next(SystemTime::now()) {
+            self.arm(
+                ScheduledProtocol {
+                    schedule,
+                    ..job.clone()
+                },
+                context,
+            );
+        }
+        log::info!("Queueing scheduled protocol {}.", id);
+        match self.enqueue(job.protocol, None, context) {
+            Ok(job) => self.publish(StatusMessage::Enqueued(job), context),
+            Err(err) => {
+                log::error!("Could not queue scheduled protocol {}: {}", id, err);
+                let message = format!("The scheduled protocol {} could not be run: {}", id, err);
+                self.message(NotificationEvent::Fault, "Scheduled run failed", &message);
+            }
+        }
     }
-    /// Whether we're in the stopped state.
-    pub fn is_stopped(&self) -> bool {
-        match self.state.status {
-            State::Stopped { .. } => true,
-            State::Running | State::Waiting => false,
+    /// Starts the next job in the queue, if there is one.
+    ///
+    /// If the job can't be started (because there isn't enough buffer for it, say), it's dropped
+    /// from the queue (and the operator notified), and the one after it is started instead.
+    fn start_next(&mut self, context: &mut CoordContext) {
+        while !self.state.queue.is_empty() {
+            let job = self.state.queue.remove(0);
+            match self.start(&job.protocol, Some(job.id), context) {
+                Ok(()) => return self.publish(StatusMessage::Started(job.protocol), context),
+                Err(err) => {
+                    log::error!("Could not start queued job {}: {}", job.id, err);
+                    let message = format!("The queued job {} could not be run: {}", job.id, err);
+                    self.message(NotificationEvent::Fault, "Queued run failed", &message);
+                    self.publish(StatusMessage::Dequeued(job.id), context);
+                }
+            }
         }
     }
     /// Start the given protocol, if we can.
@@ -447,11 +3536,34 @@ impl Coordinator {
         label: Option<Uuid>,
         context: &mut CoordContext,
     ) -> Result<()> {
-        let program = protocol.as_program()?;
-        if self.is_stopped() {
-            self.stop_pump();
+        self.check_fault()?;
+        if self.state.interrupted.is_some() {
+            return Err(Error::Interrupted);
+        }
+        let program = self.check_protocol(protocol)?;
+        let warnings = protocol
+            .check(&self.hardware())
+            .warnings
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        #[cfg(feature = "use_serde")]
+        let name = self.protocol_name(protocol);
+        #[cfg(not(feature = "use_serde"))]
+        let name = None;
+        #[cfg(feature = "history")]
+        let protocol = protocol.clone();
+        if self.is_stopped() && !self.state.starting {
+            self.stop_pump(context);
             self.close_all(context);
-            context.run_later(Duration::new(10, 0), move |coord, context| {
+            self.state.starting = true;
+            let delay = self.state.scaled(Duration::new(10, 0));
+            context.run_later(delay, move |coord, context| {
+                coord.state.starting = false;
+                // A device may have failed while we were getting ready.
+                if coord.state.status == State::Faulted {
+                    return;
+                }
                 let id = label.unwrap_or_else(Uuid::new_v4);
                 coord.state.program = Some(program.clone());
                 coord.state.remaining = program.into();
@@ -459,9 +3571,416 @@ impl Coordinator {
                 coord.state.buffer = None;
                 coord.state.status = State::Running;
                 coord.state.completed.clear();
+                coord.state.timings.clear();
+                coord.state.warnings = warnings;
+                coord.state.name = name;
                 coord.state.uuid = Some(id);
-                coord.advance(context).unwrap();
+                coord.state.pump = 0;
+                coord.state.perfusing = false;
+                coord.state.bubble = false;
+                coord.state.started = Some(Instant::now());
+                coord.state.paused_for = Duration::new(0, 0);
+                coord.state.paused = None;
+                coord.state.aborted = false;
+                coord.timers.frozen.clear();
+                #[cfg(feature = "history")]
+                {
+                    let operator = coord.operator.clone();
+                    coord.chronicle(|history, id| {
+                        let operator = operator.as_ref().map(String::as_str);
+                        history.begin(id, &protocol, operator, SystemTime::now())
+                    });
+                }
+                coord.try_advance(context);
+                coord.notify(Notice::Started);
+            });
+        }
+        Ok(())
+    }
+    /// Primes the line with the given buffer, if we're idle.
+    ///
+    /// If a volume is known (either given or configured), the pump dispenses it; otherwise, the
+    /// pump runs for the configured duration.
+    fn prime(
+        &mut self,
+        buffer: MotorId,
+        volume: Option<Volume>,
+        context: &mut CoordContext,
+    ) -> Result<()> {
+        self.check_fault()?;
+        if !self.is_stopped() {
+            return Err(Error::Busy);
+        }
+        if !has_buffer(self.positions.len(), buffer) {
+            return Err(Error::NoSuchMotor(buffer));
+        }
+        // Volumes can only be dispensed by a pump with a known flow rate; otherwise, the configured
+        // volume gives way to the configured duration.
+        let id = self.state.pump;
+        let calibrated = self.flow_rates.get(id).map_or(false, Option::is_some);
+        if volume.is_some() && !calibrated {
+            return Err(DeviceError::NoFlowRate.into());
+        }
+        let volume = volume.or_else(|| match self.prime.volume() {
+            Some(_) if !calibrated => {
+                log::warn!(
+                    "Pump {} has no flow rate, so priming for {:?} instead.",
+                    id,
+                    self.prime.duration
+                );
+                None
+            }
+            volume => volume,
+        });
+        log::debug!("Priming line with buffer {} ({:?})", buffer, volume);
+        self.state.status = State::Priming;
+        self.publish(StatusMessage::Priming { buffer }, context);
+        self.shut_waste(context);
+        self.open(buffer, context);
+        context.run_later(*PUMP_DELAY, move |coord, context| match volume {
+            Some(volume) => {
+                if let Some(pump) = coord.pump() {
+                    let request = pump.send(PumpMessage::Dispense(volume));
+                    context.spawn(
+                        request
+                            .into_actor(coord)
+                            .then(move |result, coord, context| {
+                                let fault = Fault::Pump { pump: id };
+                                match result {
+                                    // Priming still ends if something else stopped the pump.
+                                    Ok(Ok(_)) | Ok(Err(DeviceError::Interrupted)) => {
+                                        coord.finish_priming(buffer, context)
+                                    }
+                                    Ok(Err(err)) => coord.device_failed(fault, &err, context),
+                                    Err(err) => coord.device_failed(fault, &err, context),
+                                }
+                                fut::ok(())
+                            }),
+                    );
+                }
+            }
+            None => {
+                coord.perfuse(context);
+                let duration = coord.prime.duration;
+                context.run_later(duration, move |coord, context| {
+                    coord.finish_priming(buffer, context);
+                });
+            }
+        });
+        Ok(())
+    }
+    /// Stops the pump and closes the valve after priming, returning to idle.
+    fn finish_priming(&mut self, buffer: MotorId, context: &mut CoordContext) {
+        self.stop_pump(context);
+        self.close(buffer, context);
+        self.state.status = State::Stopped { early: false };
+        self.publish(StatusMessage::Primed { buffer }, context);
+    }
+    /// Runs the configured wash routine, if we're idle.
+    fn wash(&mut self, context: &mut CoordContext) -> Result<()> {
+        self.check_fault()?;
+        if !self.is_stopped() {
+            return Err(Error::Busy);
+        }
+        let wash = self.wash.ok_or(Error::NoWash)?;
+        if !has_buffer(self.positions.len(), wash.buffer) {
+            return Err(Error::NoSuchMotor(wash.buffer));
+        }
+        log::debug!("Washing with buffer {} ({:?})", wash.buffer, wash);
+        self.state.status = State::Washing;
+        self.rinse(1, wash, context);
+        Ok(())
+    }
+    /// Runs the given cycle of a wash (counting from 1) and those after it, filling the chamber
+    /// and then draining it each time.
+    ///
+    /// The wash is abandoned if the coordinator is halted (or faults) in the meantime.
+    fn rinse(&mut self, cycle: usize, wash: WashConfig, context: &mut CoordContext) {
+        let cycles = wash.repetitions;
+        self.publish(StatusMessage::Washing { cycle, cycles }, context);
+        self.shut_waste(context);
+        self.open(wash.buffer, context);
+        context.run_later(*PUMP_DELAY, move |coord, context| {
+            if coord.state.status != State::Washing {
+                return;
+            }
+            coord.perfuse(context);
+            context.run_later(wash.fill, move |coord, context| {
+                if coord.state.status != State::Washing {
+                    return;
+                }
+                coord.stop_pump(context);
+                coord.close(wash.buffer, context);
+                coord.close_waste(context);
+                context.run_later(*PUMP_DELAY, move |coord, context| {
+                    if coord.state.status != State::Washing {
+                        return;
+                    }
+                    coord.drain(context);
+                    context.run_later(wash.drain, move |coord, context| {
+                        if coord.state.status != State::Washing {
+                            return;
+                        }
+                        coord.stop_pump(context);
+                        coord.shut_waste(context);
+                        if cycle < cycles {
+                            coord.rinse(cycle + 1, wash, context);
+                        } else {
+                            coord.state.status = State::Stopped { early: false };
+                            coord.publish(StatusMessage::Washed, context);
+                        }
+                    });
+                });
             });
+        });
+    }
+    /// Begins calibrating the given motor, if we're idle.
+    fn calibrate(&mut self, motor: MotorId, context: &mut CoordContext) -> Result<()> {
+        self.check_fault()?;
+        if !self.is_stopped() {
+            return Err(Error::Busy);
+        }
+        let addresses = self.addresses.as_ref().ok_or(Error::NoSuchMotor(motor))?;
+        match addresses.motors.get(motor) {
+            Some(MotorAddr::Servo(_)) => {}
+            Some(MotorAddr::Stepper(_)) | None => return Err(Error::NoSuchMotor(motor)),
+        }
+        log::debug!("Calibrating motor {}", motor);
+        self.state.status = State::Calibrating { motor };
+        self.adjust(Calibrate::Move(calibration::Mark::Close), context)
+    }
+    /// Applies the given configuration, as far as it can be without restarting (see
+    /// [`Message::ReloadConfig`](enum.Message.html#variant.ReloadConfig)), if we're idle.
+    fn reload(&mut self, config: Config, context: &mut CoordContext) -> Result<()> {
+        if !self.is_stopped() {
+            return Err(Error::Busy);
+        }
+        let config = match self.state.simulation {
+            Some(_) => simulated(config),
+            None => config,
+        };
+        config.validate()?;
+        let count = self.ranges.len();
+        let mut existing = config.clone();
+        existing.motors.truncate(count);
+        if config.motors.len() < count || existing.pins() != self.pins {
+            return Err(Error::RestartRequired);
+        }
+        let calibrations = match &config.calibration {
+            Some(path) => calibration::load(path).map_err(Error::Calibration)?,
+            None => Calibrations::new(),
+        };
+        let notifiers = notifiers(&config)?;
+        let added = &config.motors[count..];
+        let motors = if added.is_empty() {
+            vec![]
+        } else {
+            let backend = Arc::clone(&self.supervision.backend);
+            added
+                .iter()
+                .enumerate()
+                .map(|(offset, spec)| {
+                    let calibration = calibrations.get(&(count + offset)).cloned();
+                    open_motor(spec.clone(), calibration, &*backend)
+                })
+                .collect::<Result<Vec<_>>>()?
+        };
+        let mut positions = self.positions.clone();
+        positions.extend(
+            added
+                .iter()
+                .map(|spec| spec.positions().keys().cloned().collect()),
+        );
+        let teardown = config
+            .teardown
+            .iter()
+            .flat_map(TeardownStep::actions)
+            .collect::<Vec<_>>();
+        let previous = mem::replace(&mut self.positions, positions);
+        if let Err(err) = self.check_actions(&teardown) {
+            self.positions = previous;
+            return Err(err);
+        }
+        if let Some(addresses) = &mut self.addresses {
+            for (index, motor) in addresses.motors.iter().enumerate() {
+                if let MotorAddr::Servo(addr) = motor {
+                    addr.do_send(Recalibrate(calibrations.get(&index).cloned()));
+                }
+            }
+            let supervisor = context.address().recipient();
+            let count = addresses.motors.len();
+            let arbiter = &addresses.devices;
+            addresses.motors.extend(
+                motors.into_iter().enumerate().map(|(offset, motor)| {
+                    motor.start(count + offset, arbiter, supervisor.clone())
+                }),
+            );
+        }
+        // Anything held back by the old limits is summarized before they're replaced.
+        self.notifiers.flush();
+        self.notifiers.replace(notifiers);
+        self.ranges
+            .extend(added.iter().map(ValveConfig::range_of_motion));
+        self.supervision.motors.extend(added.iter().cloned());
+        self.supervision.config.attempts = config.supervision.attempts;
+        self.pins = config.pins();
+        self.buffers = Buffers::new(config.buffers);
+        self.calibrations = calibrations;
+        self.calibration_file = config.calibration;
+        self.prime = config.prime;
+        self.wash = config.wash;
+        self.drain_pump = config.drain_pump;
+        self.overrun = config.overrun;
+        self.teardown = teardown;
+        log::info!(
+            "Reloaded the configuration ({} motor(s) added).",
+            self.ranges.len() - count
+        );
+        self.publish(StatusMessage::Reloaded, context);
+        Ok(())
+    }
+    /// Indexes the catalog's directory again (if there is one) if its files have changed.
+    #[cfg(feature = "use_serde")]
+    fn check_catalog(&mut self) {
+        let catalog = match &self.catalog {
+            Some(catalog) => catalog,
+            None => return,
+        };
+        match catalog.refresh() {
+            Ok(true) => log::info!(
+                "Indexed {} protocol file(s) in {}.",
+                catalog.list().len(),
+                catalog.dir().display()
+            ),
+            Ok(false) => {}
+            Err(err) => log::debug!("Failed to check {}: {}", catalog.dir().display(), err),
+        }
+    }
+    /// Reloads the watched configuration file (if any) if it's changed, once we're idle.
+    #[cfg(feature = "use_serde")]
+    fn check_config(&mut self, context: &mut CoordContext) {
+        if !self.is_stopped() {
+            return;
+        }
+        let file = match &mut self.config_file {
+            Some(file) => file,
+            None => return,
+        };
+        let modified = match fs::metadata(&file.path).and_then(|metadata| metadata.modified()) {
+            Ok(modified) => modified,
+            Err(err) => {
+                log::debug!("Failed to check {}: {}", file.path.display(), err);
+                return;
+            }
+        };
+        if file.modified == Some(modified) {
+            return;
+        }
+        file.modified = Some(modified);
+        let path = file.path.clone();
+        let config = match Config::load(&path, std::iter::empty()) {
+            Ok(config) => config,
+            Err(err) => {
+                log::error!("{} ({})", err, path.display());
+                return;
+            }
+        };
+        if let Err(err) = self.reload(config, context) {
+            log::error!("Failed to reload {}: {}", path.display(), err);
+        }
+    }
+    /// Carries out the given manual action, if we're idle.
+    fn jog(&mut self, jog: Jog, context: &mut CoordContext) -> Result<()> {
+        self.check_fault()?;
+        if !self.is_stopped() {
+            return Err(Error::Busy);
+        }
+        log::debug!("Jogging: {:?}", jog);
+        let count = self
+            .addresses
+            .as_ref()
+            .map_or(0, |addresses| addresses.motors.len());
+        match jog {
+            Jog::Open(motor) | Jog::Close(motor) | Jog::Shut(motor) | Jog::Angle { motor, .. }
+                if motor >= count =>
+            {
+                return Err(Error::NoSuchMotor(motor));
+            }
+            Jog::Open(motor) => self._open(motor, context),
+            Jog::Close(motor) => self._close(motor, context),
+            Jog::Shut(motor) => self._shut(motor, context),
+            Jog::Angle { motor, angle } => {
+                if self.ranges.get(motor).map_or(true, |&range| angle > range) {
+                    return Err(Error::OutOfRange { motor, angle });
+                }
+                self._set_angle(motor, angle, context);
+            }
+            Jog::Pump {
+                direction,
+                duration,
+            } => {
+                self.state.status = State::Manual;
+                match direction {
+                    PumpDirection::Forward => self.perfuse(context),
+                    PumpDirection::Backward => self.drain(context),
+                }
+                context.run_later(duration, |coord, context| coord.finish_jog(context));
+            }
+        }
+        self.publish(StatusMessage::Jogged(jog), context);
+        Ok(())
+    }
+    /// Stops the pump after a manual run, returning to idle.
+    fn finish_jog(&mut self, context: &mut CoordContext) {
+        // The run may have been cut short (e.g. by a halt).
+        if self.state.status != State::Manual {
+            return;
+        }
+        self.stop_pump(context);
+        self.state.status = State::Stopped { early: false };
+        self.publish(StatusMessage::JogFinished, context);
+    }
+    /// Forwards the given calibration request to the motor being calibrated.
+    fn adjust(&mut self, request: Calibrate, context: &mut CoordContext) -> Result<()> {
+        let motor = match self.state.status {
+            State::Calibrating { motor } => motor,
+            _ => return Err(Error::NotCalibrating),
+        };
+        let addr = match self.addresses.as_ref().map(|a| &a[motor]) {
+            Some(MotorAddr::Servo(addr)) => addr,
+            _ => return Err(Error::NoSuchMotor(motor)),
+        };
+        let request = addr.send(request);
+        context.spawn(
+            request
+                .into_actor(self)
+                .then(move |result, coord, context| {
+                    match result {
+                        Ok(Ok(state)) => {
+                            coord.calibrations.insert(motor, state.calibration);
+                            coord.publish(StatusMessage::Calibrating { motor, state }, context);
+                        }
+                        Ok(Err(err)) => log::error!("Failed to calibrate motor {}: {}", motor, err),
+                        Err(err) => log::error!("Failed to reach motor {}: {}", motor, err),
+                    }
+                    fut::ok(())
+                }),
+        );
+        Ok(())
+    }
+    /// Finishes calibrating the current motor, saving the calibration file (if any).
+    fn finish_calibration(&mut self, context: &mut CoordContext) -> Result<()> {
+        let motor = match self.state.status {
+            State::Calibrating { motor } => motor,
+            _ => return Err(Error::NotCalibrating),
+        };
+        self.state.status = State::Stopped { early: false };
+        self.settle(motor, context);
+        if let Some(path) = &self.calibration_file {
+            calibration::save(path, &self.calibrations).map_err(Error::Calibration)?;
+        }
+        if let Some(&calibration) = self.calibrations.get(&motor) {
+            self.publish(StatusMessage::Calibrated { motor, calibration }, context);
         }
         Ok(())
     }
@@ -477,6 +3996,22 @@ impl Coordinator {
             let message = Status {
                 address: context.address(),
                 message,
+                user: self.acting.clone(),
+                manifold: self.manifold.clone(),
+                buffers: self.buffers.clone(),
+                state: self.state.status,
+                step: self.state.position(),
+                iteration: self.state.iteration(),
+                elapsed: self.state.elapsed(),
+                paused: self.state.current_pause(),
+                queue: self.state.queue.clone(),
+                scheduled: self.state.scheduled.clone(),
+                step_remaining: self.state.step_remaining(),
+                remaining: self.state.time_remaining(),
+                finishes: self
+                    .state
+                    .time_remaining()
+                    .map(|remaining| SystemTime::now() + remaining),
             };
             addr.subscribers
                 .do_send(SubscribersMessage::Forward(message));
@@ -492,20 +4027,148 @@ impl Actor for Coordinator {
             coord: ctx.address(),
         }
         .start();
+        #[cfg(feature = "use_serde")]
+        {
+            if let Some(log) = self.event_log.take() {
+                let log = log.start();
+                subscribers.do_send(SubscribersMessage::Add(Box::new(log)));
+            }
+        }
+        #[cfg(feature = "mqtt")]
+        {
+            if let Some(config) = self.mqtt.take() {
+                match Mqtt::connect(config, ctx.address(), self.library.clone()) {
+                    Ok(mqtt) => subscribers.do_send(SubscribersMessage::Add(Box::new(mqtt))),
+                    Err(err) => log::error!("Failed to connect to MQTT broker: {:?}", err),
+                }
+            }
+        }
+        #[cfg(feature = "webhooks")]
+        {
+            if !self.webhooks.is_empty() {
+                let hooks = Webhooks::new(std::mem::replace(&mut self.webhooks, vec![])).start();
+                subscribers.do_send(SubscribersMessage::Add(Box::new(hooks)));
+            }
+        }
+        #[cfg(feature = "grpc")]
+        {
+            if let Some((address, tokens)) = self.grpc.take() {
+                grpc::serve(address, &tokens, ctx.address());
+            }
+        }
+        #[cfg(feature = "modbus")]
+        {
+            if let Some(address) = self.modbus.take() {
+                match Modbus::serve(address, ctx.address()) {
+                    Ok(modbus) => subscribers.do_send(SubscribersMessage::Add(Box::new(modbus))),
+                    Err(err) => log::error!("Failed to serve Modbus TCP: {}", err),
+                }
+            }
+        }
         if let Some(devices) = self.devices.take() {
+            let supervisor = ctx.address().recipient::<DeviceFailed>();
+            let arbiter = Arbiter::new("devices");
             let motors = devices
                 .motors
                 .into_iter()
-                .map(Actor::start)
+                .enumerate()
+                .map(|(index, motor)| motor.start(index, &arbiter, supervisor.clone()))
+                .collect::<Vec<_>>();
+            let pumps = devices
+                .pumps
+                .into_iter()
+                .enumerate()
+                .map(|(index, pump)| {
+                    let supervisor = supervisor.clone();
+                    Pump::start_in_arbiter(&arbiter, move |_| {
+                        pump.supervised(Device::Pump(index), supervisor)
+                    })
+                })
                 .collect::<Vec<_>>();
-            let pump = devices.pump.start();
+            let thermostat = devices
+                .thermostat
+                .map(|thermostat| Thermostat::start_in_arbiter(&arbiter, move |_| thermostat));
+            let flow = devices.flow.map(Actor::start);
+            let pressure = devices.pressure.map(Actor::start);
             let addresses = Addresses {
-                pump,
+                devices: arbiter,
+                thermostat,
+                pumps,
+                flow,
+                pressure,
+                reservoirs: devices.reservoirs.map(Actor::start),
+                bubble: devices.bubble,
+                estop: devices.estop,
+                watchdog: None,
                 motors,
                 subscribers,
             };
             self.addresses = Some(addresses);
         }
+        if let (Some(config), Some(addresses)) = (self.watchdog, self.addresses.as_mut()) {
+            let coord = ctx.address();
+            let pumps = addresses.pumps.clone();
+            let motors = addresses
+                .motors
+                .iter()
+                .map(MotorAddr::recipient)
+                .collect::<Vec<_>>();
+            let thermostat = addresses.thermostat.clone();
+            let notifiers = self.notifiers.clone();
+            // The watchdog gets its own thread so that it notices if ours gets stuck.
+            addresses.watchdog = Some(Arbiter::start(move |_| {
+                Watchdog::new(coord, pumps, motors, thermostat, notifiers, config)
+            }));
+        }
+        if let Some(config) = self.pressure {
+            ctx.run_interval(config.interval, |coord, ctx| coord.check_pressure(ctx));
+        }
+        ctx.run_interval(self.supervision.config.interval, |coord, ctx| {
+            coord.check_devices(ctx)
+        });
+        self.check_levels(ctx);
+        ctx.run_interval(*LEVEL_INTERVAL, |coord, ctx| coord.check_levels(ctx));
+        ctx.run_interval(*NOTIFICATION_FLUSH_INTERVAL, |coord, _| {
+            coord.notifiers.flush()
+        });
+        #[cfg(feature = "use_serde")]
+        {
+            if self.config_file.is_some() {
+                ctx.run_interval(*CONFIG_POLL_INTERVAL, |coord, ctx| coord.check_config(ctx));
+            }
+            if self.catalog.is_some() {
+                ctx.run_interval(*CONFIG_POLL_INTERVAL, |coord, _| coord.check_catalog());
+            }
+        }
+        if let Some(detector) = self
+            .addresses
+            .as_mut()
+            .and_then(|addresses| addresses.bubble.as_mut())
+        {
+            let addr = ctx.address();
+            if let Err(err) = detector.watch(move || addr.do_send(BubbleDetected)) {
+                log::error!("Failed to watch bubble detector: {}", err);
+            }
+        }
+        if let Some(input) = self
+            .addresses
+            .as_mut()
+            .and_then(|addresses| addresses.estop.as_mut())
+        {
+            match input.events(Edge::Both) {
+                Ok(events) => {
+                    ctx.add_message_stream(events);
+                }
+                Err(err) => log::error!("Failed to watch emergency stop: {}", err),
+            }
+        }
+        if self.estop_pressed() {
+            self.fault(Fault::EmergencyStop, ctx);
+        }
+        if self.signals {
+            ProcessSignals::from_registry().do_send(Subscribe(ctx.address().recipient()));
+        }
+        self.check_journal(ctx);
     }
     fn stopped(&mut self, _ctx: &mut Self::Context) {
         // Redundant due to the impending drop, but I like to be explicit
@@ -513,12 +4176,191 @@ impl Actor for Coordinator {
     }
 }
 
+impl Handle<Ping> for Coordinator {
+    type Result = ();
+    fn handle(&mut self, _: Ping, _context: &mut Self::Context) {}
+}
+
+/// Asks the coordinator to check that it and its hardware are ready to run protocols.
+#[derive(Clone, Copy, Debug)]
+pub struct CheckHealth;
+
+impl ActixMessage for CheckHealth {
+    type Result = std::result::Result<Health, ()>;
+}
+
+/// The health of a single component.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Serialize))]
+pub struct ComponentHealth {
+    /// The name of the component (e.g. `thermostat` or `pump 0`).
+    pub name: String,
+    /// Whether the component is working.
+    pub ok: bool,
+    /// What's wrong with the component, if anything.
+    #[cfg_attr(feature = "use_serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub detail: Option<String>,
+}
+
+impl ComponentHealth {
+    /// Describes the named component, given the outcome of checking it.
+    pub fn new<E: fmt::Display>(
+        name: impl Into<String>,
+        result: std::result::Result<(), E>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            ok: result.is_ok(),
+            detail: result.err().map(|err| err.to_string()),
+        }
+    }
+}
+
+/// The health of the coordinator and its hardware.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Serialize))]
+pub struct Health {
+    /// Whether every component is working.
+    pub ready: bool,
+    /// The health of each component.
+    pub components: Vec<ComponentHealth>,
+}
+
+impl Health {
+    /// Summarizes the health of the given components.
+    pub fn new(components: Vec<ComponentHealth>) -> Self {
+        Self {
+            ready: components.iter().all(|component| component.ok),
+            components,
+        }
+    }
+}
+
+/// Reduces the response to a sensor reading to whether the sensor could be read.
+fn readable<T, E: fmt::Display>(
+    response: std::result::Result<std::result::Result<T, E>, MailboxError>,
+) -> std::result::Result<(), String> {
+    match response {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(err)) => Err(err.to_string()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+impl Handle<CheckHealth> for Coordinator {
+    type Result = ResponseFuture<Health, ()>;
+    fn handle(&mut self, _: CheckHealth, _context: &mut Self::Context) -> Self::Result {
+        type Check = Box<dyn Future<Item = ComponentHealth, Error = ()>>;
+        let done = |health: ComponentHealth| -> Check { Box::new(future::ok(health)) };
+        let status = match self.state.status {
+            State::Faulted => Err("a fault is latched"),
+            _ => Ok(()),
+        };
+        let mut checks = vec![done(ComponentHealth::new("coordinator", status))];
+        if self.estop.is_some() {
+            let pressed = if self.estop_pressed() {
+                Err("pressed")
+            } else {
+                Ok(())
+            };
+            checks.push(done(ComponentHealth::new("emergency stop", pressed)));
+        }
+        if let Some(addresses) = &self.addresses {
+            for (pump, addr) in addresses.pumps.iter().enumerate() {
+                let running = if addr.connected() {
+                    Ok(())
+                } else {
+                    Err("stopped")
+                };
+                checks.push(done(ComponentHealth::new(
+                    format!("pump {}", pump),
+                    running,
+                )));
+            }
+            if let Some(thermostat) = &addresses.thermostat {
+                checks.push(Box::new(thermostat.send(ThermalMessage::Measure).then(
+                    |response| Ok(ComponentHealth::new("thermostat", readable(response))),
+                )));
+            }
+            if let Some(sensor) = &addresses.pressure {
+                checks.push(Box::new(sensor.send(PressureMessage::Measure).then(
+                    |response| Ok(ComponentHealth::new("pressure sensor", readable(response))),
+                )));
+            }
+            if let Some(reservoirs) = &addresses.reservoirs {
+                checks.push(Box::new(reservoirs.send(LevelMessage::Measure).then(
+                    |response| Ok(ComponentHealth::new("reservoirs", readable(response))),
+                )));
+            }
+            if let Some(flow) = &addresses.flow {
+                checks.push(Box::new(flow.send(FlowMessage::Measure).then(|response| {
+                    let response = response.map(Ok::<_, PinError>);
+                    Ok(ComponentHealth::new("flow sensor", readable(response)))
+                })));
+            }
+        }
+        Box::new(future::join_all(checks).map(Health::new))
+    }
+}
+
+impl Handle<Tripped> for Coordinator {
+    type Result = ();
+    /// Latches the fault; the watchdog has already made everything safe and alerted the
+    /// administrators.
+    fn handle(&mut self, Tripped(reason): Tripped, context: &mut Self::Context) {
+        self.latch(Fault::Watchdog(reason), context);
+    }
+}
+
+impl Handle<DeviceFailed> for Coordinator {
+    type Result = ();
+    /// Responds to a motor or pump failing on its own (e.g. partway through a ramp) as it would to
+    /// one failing to carry out a command.
+    fn handle(&mut self, failed: DeviceFailed, context: &mut Self::Context) {
+        let fault = match failed.device {
+            Device::Motor(motor) => Fault::Motor { motor },
+            Device::Pump(pump) => Fault::Pump { pump },
+        };
+        self.device_failed(fault, &failed.error, context);
+    }
+}
+
+impl Handle<Signal> for Coordinator {
+    type Result = ();
+    /// Shuts down safely when the process is asked to exit.
+    fn handle(&mut self, signal: Signal, context: &mut Self::Context) -> Self::Result {
+        match signal.0 {
+            SignalType::Int | SignalType::Term | SignalType::Quit => self.shutdown(context),
+            SignalType::Hup | SignalType::Child => {}
+        }
+    }
+}
+
+impl Handle<EdgeEvent> for Coordinator {
+    type Result = ();
+    /// Responds to the emergency-stop button changing state.
+    fn handle(&mut self, event: EdgeEvent, context: &mut Self::Context) -> Self::Result {
+        let pressed = self
+            .estop
+            .map_or(false, |config| event.high != config.invert);
+        if pressed {
+            self.fault(Fault::EmergencyStop, context);
+        } else {
+            log::info!("Emergency stop released; awaiting reset.");
+        }
+    }
+}
+
 impl Handle<Message> for Coordinator {
     type Result = Result<()>;
     fn handle(&mut self, message: Message, context: &mut Self::Context) -> Self::Result {
         match message {
             Message::Continue => {
-                self.resume(context)?;
+                if self.state.bubble {
+                    self.clear_bubble(context);
+                } else {
+                    self.resume(context)?;
+                }
                 self.publish(StatusMessage::Continued, context);
             }
             Message::Stop => {
@@ -526,7 +4368,9 @@ impl Handle<Message> for Coordinator {
                 self.publish(StatusMessage::StopQueued { early: false }, context);
             }
             Message::Halt => {
-                self.hcf()?;
+                #[cfg(feature = "history")]
+                self.chronicle(|history, id| history.end(id, Outcome::Halted, SystemTime::now()));
+                self.hcf(context)?;
                 self.publish(StatusMessage::Halted, context);
             }
             Message::ExchangeStop(id) => {
@@ -537,12 +4381,92 @@ impl Handle<Message> for Coordinator {
                 self.start(&proto, label, context)?;
                 self.publish(StatusMessage::Started(proto), context);
             }
-            Message::Subscribe(sub) => self.subscribe(sub),
+            Message::Enqueue(proto, label) => {
+                let id = self.enqueue(proto, label, context)?;
+                self.publish(StatusMessage::Enqueued(id), context);
+            }
+            Message::Dequeue(id) => {
+                self.dequeue(id)?;
+                self.publish(StatusMessage::Dequeued(id), context);
+            }
+            Message::Schedule {
+                protocol,
+                schedule,
+                id,
+            } => {
+                let id = self.schedule(protocol, schedule, id, context)?;
+                self.publish(StatusMessage::Scheduled(id), context);
+            }
+            Message::Operator(operator) => self.operator = operator,
+            Message::Acting { user, message } => {
+                let previous = self.acting.replace(user);
+                let result = <Self as Handle<Message>>::handle(self, *message, context);
+                self.acting = previous;
+                result?;
+            }
+            Message::Recover(recovery) => {
+                self.recover(recovery, context)?;
+                self.publish(StatusMessage::Recovered(recovery), context);
+            }
+            Message::Unschedule(id) => {
+                self.unschedule(id, context)?;
+                self.publish(StatusMessage::Unscheduled(id), context);
+            }
+            Message::Requeue { id, index } => {
+                self.requeue(id, index)?;
+                self.publish(StatusMessage::Requeued(id), context);
+            }
+            Message::Subscribe(sub) => {
+                self.subscribe(sub);
+                // Make sure the new subscriber hears about a run awaiting recovery.
+                if let Some(entry) = &self.state.interrupted {
+                    let message = StatusMessage::Interrupted {
+                        id: entry.id,
+                        step: entry.step,
+                    };
+                    self.publish(message, context);
+                }
+            }
+            Message::Prime { buffer, volume } => self.prime(buffer, volume, context)?,
+            Message::Wash => self.wash(context)?,
+            Message::Calibrate(motor) => self.calibrate(motor, context)?,
+            Message::Jog(jog) => self.jog(jog, context)?,
+            Message::ReloadConfig(config) => self.reload(*config, context)?,
+            Message::Latch(fault) => self.latch(fault, context),
+            Message::Adjust(request) => self.adjust(request, context)?,
+            Message::FinishCalibration => self.finish_calibration(context)?,
+            Message::Reset => self.reset(context)?,
+            Message::Pause => self.pause(context)?,
+            Message::Resume => self.unpause(context)?,
+            Message::SkipStep => {
+                let next = self.state.position().map_or(0, |position| position + 1);
+                self.jump(next, false, context)?;
+            }
+            Message::JumpTo { index, force } => self.jump(index, force, context)?,
+            Message::Abort => {
+                self.abort(context)?;
+                self.publish(StatusMessage::Aborted, context);
+            }
         }
         Ok(())
     }
 }
 
+/// Sent (from the detector's thread) when the bubble detector is triggered.
+#[derive(Clone, Copy, Debug)]
+struct BubbleDetected;
+
+impl ActixMessage for BubbleDetected {
+    type Result = ();
+}
+
+impl Handle<BubbleDetected> for Coordinator {
+    type Result = ();
+    fn handle(&mut self, _: BubbleDetected, context: &mut Self::Context) {
+        self.bubble_detected(context);
+    }
+}
+
 #[derive(Debug)]
 enum SubscribersMessage {
     /// Register a new listener.
@@ -574,6 +4498,7 @@ impl Handle<SubscribersMessage> for Subscribers {
                 for sub in self.subs.iter() {
                     sub.handle(&message, &self);
                 }
+                self.subs.retain(|sub| sub.is_connected());
             }
             SubscribersMessage::Add(listener) => {
                 self.subs.push(listener);
@@ -596,6 +4521,10 @@ impl Respond for Subscribers {
 pub trait Update: std::fmt::Debug + Send {
     /// Handles the change in coordinator status.
     fn handle(&self, msg: &Status, coord: &Subscribers);
+    /// Whether the subscriber still wants updates (once it doesn't, it's dropped).
+    fn is_connected(&self) -> bool {
+        true
+    }
 }
 
 #[derive(Debug)]
@@ -605,6 +4534,35 @@ pub struct Status {
     pub address: Addr<Coordinator>,
     /// The information the coordinator wishes to convey.
     pub message: StatusMessage,
+    /// The user whose request prompted the update, if any.
+    pub user: Option<String>,
+    /// The name of the manifold the coordinator runs, if it's one of several.
+    pub manifold: Option<String>,
+    /// The configured buffers, for referring to them by name (instead of by their valves'
+    /// indices).
+    pub buffers: Buffers,
+    /// The state of the coordinator once the update happened.
+    pub state: State,
+    /// The index of the step being run (or about to be run), if a program has been started.
+    pub step: Option<usize>,
+    /// The pass through a repeat being run (e.g. iteration 2 of 6), if the step is repeated.
+    pub iteration: Option<Iteration>,
+    /// How long the current (or most recent) program has been running, not counting pauses.
+    pub elapsed: Option<Duration>,
+    /// How long the program has been paused, if it is.
+    pub paused: Option<Duration>,
+    /// The jobs waiting to be run after the current one, in order.
+    pub queue: Vec<QueuedProtocol>,
+    /// The protocols waiting for their scheduled starts.
+    pub scheduled: Vec<ScheduledProtocol>,
+    /// Roughly how long until the current step finishes, if a step is running.
+    pub step_remaining: Option<Duration>,
+    /// Roughly how long until the program finishes, if one is running.
+    ///
+    /// Waits for user confirmation aren't included.
+    pub remaining: Option<Duration>,
+    /// Roughly when the program will finish, if one is running.
+    pub finishes: Option<SystemTime>,
 }
 
 #[derive(Debug)]
@@ -614,8 +4572,44 @@ pub enum StatusMessage {
     Continued,
     /// The coordinator has started the given protocol.
     Started(Protocol),
+    /// The step with the given index has begun.
+    Step(usize),
+    /// The program has run to completion.
+    Completed,
+    /// A run was interrupted (by a crash or power loss) and awaits recovery.
+    Interrupted {
+        /// The label of the interrupted job.
+        id: Uuid,
+        /// The index of the interrupted step.
+        step: usize,
+    },
+    /// The interrupted run has been recovered as requested.
+    Recovered(Recovery),
     /// The coordinator has paused and will await user confirmation to continue.
     Paused,
+    /// The operator has paused the program (see [`Message::Pause`](enum.Message.html#variant.Pause)).
+    Suspended,
+    /// The operator has resumed a paused program.
+    Unsuspended,
+    /// The program has been aborted, and the teardown sequence is running.
+    Aborted,
+    /// A paused program has been moved to a different step, which begins once it's resumed.
+    Jumped {
+        /// The index of the abandoned step.
+        from: usize,
+        /// The index of the new step.
+        to: usize,
+    },
+    /// The job with the given label has been added to the queue.
+    Enqueued(Uuid),
+    /// The job with the given label has been removed from the queue.
+    Dequeued(Uuid),
+    /// The job with the given label has been moved within the queue.
+    Requeued(Uuid),
+    /// A protocol has been scheduled under the given label.
+    Scheduled(Uuid),
+    /// The schedule with the given label has been cancelled.
+    Unscheduled(Uuid),
     /// The coordinator has been told to stop, either early (aborted) or not (completed).
     StopQueued {
         /// Whether the stop was premature.
@@ -623,6 +4617,246 @@ pub enum StatusMessage {
     },
     /// The coordinator has been halted.
     Halted,
+    /// The coordinator has begun priming the line with the given buffer.
+    Priming {
+        /// The buffer the line is being primed with.
+        buffer: MotorId,
+    },
+    /// The coordinator has finished priming the line and is idle.
+    Primed {
+        /// The buffer the line was primed with.
+        buffer: MotorId,
+    },
+    /// The coordinator has begun the given cycle of the wash routine.
+    Washing {
+        /// The cycle begun, counting from 1.
+        cycle: usize,
+        /// How many cycles there are.
+        cycles: usize,
+    },
+    /// The coordinator has finished the wash routine and is idle.
+    Washed,
+    /// The volume measured by the flow sensor diverged from the expected volume.
+    FlowMismatch {
+        /// The volume that should have flowed.
+        expected: Volume,
+        /// The volume that was measured.
+        measured: Volume,
+    },
+    /// A hardware fault was detected; the pump has been stopped and all valves shut.
+    Fault(Fault),
+    /// A bubble was detected during perfusion, and the pump has been paused.
+    BubbleDetected {
+        /// Whether the line is being purged automatically (rather than awaiting the operator).
+        purge: bool,
+    },
+    /// Perfusion has resumed after a bubble was detected.
+    BubbleCleared,
+    /// The target temperature has been reached (and will now be held).
+    TemperatureReached(ThermodynamicTemperature),
+    /// A gradient has moved on to its next time slice.
+    Gradient {
+        /// The buffer the gradient began with.
+        from: MotorId,
+        /// The buffer the gradient ends with.
+        to: MotorId,
+        /// How much of the gradient has run, as a percentage.
+        percent: f64,
+    },
+    /// A mix has begun the given cycle.
+    Mixing {
+        /// The cycle begun, counting from 1.
+        cycle: usize,
+        /// How many cycles there are.
+        cycles: usize,
+    },
+    /// The reading a program was waiting on has satisfied its predicate.
+    ConditionMet {
+        /// The sensor in question.
+        sensor: Sensor,
+        /// The reading (or `None` if the run is simulated).
+        value: Option<f64>,
+    },
+    /// The program gave up waiting on a sensor and now awaits the operator.
+    ConditionTimedOut {
+        /// The sensor in question.
+        sensor: Sensor,
+    },
+    /// A conditional step has chosen which of its branches to run.
+    Branched {
+        /// The sensor read.
+        sensor: Sensor,
+        /// The reading (or `None` if the run is simulated).
+        value: Option<f64>,
+        /// Whether the first branch was taken (rather than the second).
+        taken: bool,
+    },
+    /// A motor is being calibrated.
+    Calibrating {
+        /// The motor being calibrated.
+        motor: MotorId,
+        /// The state of the calibration.
+        state: CalibrationState,
+    },
+    /// A motor has been calibrated.
+    Calibrated {
+        /// The motor that was calibrated.
+        motor: MotorId,
+        /// The resulting calibration.
+        calibration: calibration::Calibration,
+    },
+    /// A buffer reservoir is running low.
+    ReservoirLow {
+        /// The buffer held in the reservoir.
+        buffer: MotorId,
+        /// The (estimated) volume remaining.
+        volume: Volume,
+    },
+    /// A latched fault has been reset by an operator.
+    Reset,
+    /// An operator has acted on the hardware directly.
+    Jogged(Jog),
+    /// A manual pump run has finished.
+    JogFinished,
+    /// The configuration has been reloaded (see
+    /// [`Message::ReloadConfig`](enum.Message.html#variant.ReloadConfig)).
+    Reloaded,
+    /// A step has fired a hook posting the given event to the webhooks.
+    Hook(String),
+    /// A timed step ran longer than its overrun policy tolerates.
+    Overrun {
+        /// How long the step should have taken.
+        expected: Duration,
+        /// How long it took, including any time spent paused.
+        taken: Duration,
+        /// What's being done about it.
+        policy: OverrunPolicy,
+    },
+    /// A sensor has been read.
+    Reading {
+        /// The sensor in question.
+        sensor: Sensor,
+        /// The value read, in the sensor's unit (see [`Sensor::unit`]).
+        ///
+        /// [`Sensor::unit`]: enum.Sensor.html#method.unit
+        value: f64,
+    },
+}
+
+/// How long the given action should take before the next one begins, if it ends by itself.
+///
+/// Waiting for the operator (or for the temperature to be reached) can take arbitrarily long, so
+/// no expectation is given for such actions.
+fn expected_duration(action: &Action) -> Option<Duration> {
+    match action {
+        Action::Perfuse(_) => Some(*PUMP_DELAY + *DURATION + *CLEAR_DURATION),
+        Action::Gradient { duration, .. } => Some(*PUMP_DELAY + *duration + *CLEAR_DURATION),
+        Action::Drain => Some(*PUMP_DELAY + *DURATION * 2),
+        Action::Mix {
+            cycles,
+            forward,
+            backward,
+        } => Some(*PUMP_DELAY + (*forward + *backward) * *cycles as u32),
+        Action::Sleep(duration) => Some(*duration),
+        Action::SetPosition { .. } => Some(*SETTLE_DELAY),
+        Action::Notify(_)
+        | Action::UsePump(_)
+        | Action::Iterate(_)
+        | Action::Branch { .. }
+        | Action::Skip(_)
+        | Action::Hook(_)
+        | Action::Overrun(_) => Some(Duration::new(0, 0)),
+        Action::Hail
+        | Action::Finish
+        | Action::HoldTemperature { .. }
+        | Action::WaitUntil { .. } => None,
+    }
+}
+
+/// Roughly how long the given action should take, for the purpose of estimating completion times.
+///
+/// Unlike [`expected_duration`](fn.expected_duration.html), this counts a temperature hold as its
+/// hold time (ignoring the time taken to reach the target), and steps waiting on the user (or on a
+/// sensor) as taking no time. Both branches of a conditional step are counted.
+fn estimated_duration(action: &Action) -> Duration {
+    match action {
+        Action::HoldTemperature { duration, .. } => *duration,
+        _ => expected_duration(action).unwrap_or_default(),
+    }
+}
+
+/// Formats the given duration for display (e.g. "1h 23m" or "4m 10s").
+pub(crate) fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (hours, minutes, secs) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m {}s", minutes, secs)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+/// A hardware fault detected by the coordinator.
+#[derive(Clone, Copy, Debug)]
+pub enum Fault {
+    /// The line pressure exceeded the configured limit.
+    Overpressure {
+        /// The measured pressure.
+        pressure: Pressure,
+        /// The configured limit.
+        limit: Pressure,
+    },
+    /// The emergency-stop button was pressed.
+    EmergencyStop,
+    /// The watchdog tripped.
+    Watchdog(Reason),
+    /// A motor failed to carry out a command (or could not be reached).
+    Motor {
+        /// The motor in question (where motor 0 controls the waste valve).
+        motor: MotorId,
+    },
+    /// A pump failed to carry out a command (or could not be reached).
+    Pump {
+        /// The pump in question.
+        pump: PumpId,
+    },
+    /// A motor did not reach its target position (e.g. a jammed valve).
+    ValvePosition {
+        /// The motor in question (where motor 0 controls the waste valve).
+        motor: MotorId,
+        /// The angle the motor was told to move to, in degrees.
+        target: f64,
+        /// The angle the motor was measured at, in degrees.
+        actual: f64,
+    },
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Overpressure { pressure, limit } => write!(
+                f,
+                "Line pressure ({} kPa) exceeded the limit of {} kPa.",
+                pressure.get::<kilopascal>(),
+                limit.get::<kilopascal>()
+            ),
+            Self::EmergencyStop => write!(f, "Emergency stop pressed."),
+            Self::Watchdog(reason) => write!(f, "Watchdog tripped: {}.", reason),
+            Self::Motor { motor } => write!(f, "Motor {} failed.", motor),
+            Self::Pump { pump } => write!(f, "Pump {} failed.", pump),
+            Self::ValvePosition {
+                motor,
+                target,
+                actual,
+            } => write!(
+                f,
+                "Motor {} is at {:.1}º instead of {}º.",
+                motor, actual, target
+            ),
+        }
+    }
 }
 
 impl ActixMessage for Status {
@@ -631,7 +4865,201 @@ impl ActixMessage for Status {
 
 #[allow(clippy::print_stdout)]
 pub mod tui {
-    use super::{Message, Respond, Status, StatusMessage, Subscribers, Update};
+    use super::{
+        format_duration, Message, Recovery, Respond, Status, StatusMessage, Subscribers, Update,
+    };
+    use log::Level;
+    use uom::si::{thermodynamic_temperature::degree_celsius, volume::milliliter};
+
+    #[cfg(feature = "dashboard")]
+    mod dashboard;
+
+    #[cfg(feature = "dashboard")]
+    pub use self::dashboard::Dashboard;
+
+    /// Describes the given status update for the operator, along with how important it is.
+    pub(crate) fn describe(status: &Status) -> (Level, String) {
+        match &status.message {
+            StatusMessage::Paused => (Level::Info, "Waiting for the operator to continue.".into()),
+            StatusMessage::Continued => (Level::Debug, "Coordinator continuing.".into()),
+            StatusMessage::Suspended => (
+                Level::Info,
+                format!(
+                    "Program paused after {:?}.",
+                    status.elapsed.unwrap_or_default()
+                ),
+            ),
+            StatusMessage::Unsuspended => (Level::Info, "Program resumed.".into()),
+            StatusMessage::Aborted => (Level::Warn, "Program aborted; tearing down.".into()),
+            StatusMessage::Jumped { from, to } => (
+                Level::Info,
+                format!("Jumped from step {} to step {}.", from, to),
+            ),
+            StatusMessage::Enqueued(id) => (Level::Info, format!("Job {} queued.", id)),
+            StatusMessage::Dequeued(id) => {
+                (Level::Info, format!("Job {} removed from the queue.", id))
+            }
+            StatusMessage::Requeued(id) => (Level::Info, format!("Job {} moved in the queue.", id)),
+            StatusMessage::Scheduled(id) => (Level::Info, format!("Protocol scheduled ({}).", id)),
+            StatusMessage::Unscheduled(id) => (Level::Info, format!("Schedule {} cancelled.", id)),
+            StatusMessage::Started(proto) => (
+                Level::Debug,
+                format!("Coordinator starting protocol: {:?}", proto),
+            ),
+            StatusMessage::Interrupted { id, step } => (
+                Level::Warn,
+                format!("Job {} was interrupted at step {}.", id, step),
+            ),
+            StatusMessage::Recovered(recovery) => (
+                Level::Info,
+                format!("Interrupted job recovered ({:?}).", recovery),
+            ),
+            StatusMessage::Step(step) => match status.remaining {
+                Some(remaining) => (
+                    Level::Info,
+                    format!(
+                        "Starting step {} (about {} remaining).",
+                        step,
+                        format_duration(remaining)
+                    ),
+                ),
+                None => (Level::Info, format!("Starting step {}.", step)),
+            },
+            StatusMessage::StopQueued { early } => (
+                Level::Debug,
+                format!("Coordinator stop queued (early: {})", early),
+            ),
+            StatusMessage::Halted => (Level::Warn, "Coordinator halted!".into()),
+            StatusMessage::Priming { buffer } => (
+                Level::Info,
+                format!("Priming line with {}.", status.buffers.name(*buffer)),
+            ),
+            StatusMessage::Primed { buffer } => (
+                Level::Info,
+                format!("Line primed with {}.", status.buffers.name(*buffer)),
+            ),
+            StatusMessage::Washing { cycle, cycles } => (
+                Level::Info,
+                format!("Washing (cycle {} of {}).", cycle, cycles),
+            ),
+            StatusMessage::Washed => (Level::Info, "Wash complete.".into()),
+            StatusMessage::FlowMismatch { expected, measured } => (
+                Level::Warn,
+                format!(
+                    "Flow mismatch: expected {} mL, measured {} mL.",
+                    expected.get::<milliliter>(),
+                    measured.get::<milliliter>()
+                ),
+            ),
+            StatusMessage::Fault(fault) => (Level::Error, format!("Fault: {}", fault)),
+            StatusMessage::BubbleDetected { purge } => (
+                Level::Warn,
+                format!("Bubble detected (purging: {}).", purge),
+            ),
+            StatusMessage::BubbleCleared => {
+                (Level::Info, "Bubble cleared; perfusion resumed.".into())
+            }
+            StatusMessage::TemperatureReached(temperature) => (
+                Level::Info,
+                format!(
+                    "Temperature reached ({} °C).",
+                    temperature.get::<degree_celsius>()
+                ),
+            ),
+            StatusMessage::Gradient { from, to, percent } => (
+                Level::Info,
+                format!(
+                    "Gradient from {} to {}: {:.0}% complete.",
+                    status.buffers.name(*from),
+                    status.buffers.name(*to),
+                    percent
+                ),
+            ),
+            StatusMessage::Mixing { cycle, cycles } => (
+                Level::Info,
+                format!("Mixing (cycle {} of {}).", cycle, cycles),
+            ),
+            StatusMessage::ConditionMet { sensor, value } => match value {
+                Some(value) => (
+                    Level::Info,
+                    format!(
+                        "Done waiting: {} is {:.1} {}.",
+                        sensor,
+                        value,
+                        sensor.unit()
+                    ),
+                ),
+                None => (Level::Info, format!("Done waiting on {}.", sensor)),
+            },
+            StatusMessage::ConditionTimedOut { sensor } => (
+                Level::Warn,
+                format!("Timed out waiting on {}; awaiting the operator.", sensor),
+            ),
+            StatusMessage::Branched {
+                sensor,
+                value,
+                taken,
+            } => {
+                let branch = if *taken { "first" } else { "second" };
+                match value {
+                    Some(value) => (
+                        Level::Info,
+                        format!(
+                            "Took the {} branch ({} is {:.1} {}).",
+                            branch,
+                            sensor,
+                            value,
+                            sensor.unit()
+                        ),
+                    ),
+                    None => (Level::Info, format!("Took the {} branch.", branch)),
+                }
+            }
+            StatusMessage::Calibrating { motor, state } => (
+                Level::Info,
+                format!(
+                    "Calibrating motor {} (pulse width: {:?}).",
+                    motor, state.pulse_width
+                ),
+            ),
+            StatusMessage::Calibrated { motor, calibration } => (
+                Level::Info,
+                format!("Motor {} calibrated: {:?}", motor, calibration),
+            ),
+            StatusMessage::ReservoirLow { buffer, volume } => (
+                Level::Warn,
+                format!(
+                    "Reservoir for {} is low ({} mL remaining).",
+                    status.buffers.name(*buffer),
+                    volume.get::<milliliter>()
+                ),
+            ),
+            StatusMessage::Reset => (Level::Info, "Fault reset.".into()),
+            StatusMessage::Completed => (Level::Info, "Program complete.".into()),
+            StatusMessage::Jogged(jog) => (Level::Info, format!("Jogged: {:?}", jog)),
+            StatusMessage::JogFinished => (Level::Info, "Manual pump run finished.".into()),
+            StatusMessage::Reloaded => (Level::Info, "Configuration reloaded.".into()),
+            StatusMessage::Hook(event) => (Level::Info, format!("Fired hook {}.", event)),
+            StatusMessage::Overrun {
+                expected,
+                taken,
+                policy,
+            } => (
+                Level::Warn,
+                format!(
+                    "Step took {} (expected {}); policy: {}.",
+                    format_duration(*taken),
+                    format_duration(*expected),
+                    policy
+                ),
+            ),
+            StatusMessage::Reading { sensor, value } => (
+                Level::Debug,
+                format!("Read {}: {:.1} {}", sensor, value, sensor.unit()),
+            ),
+        }
+    }
+
     /// A helper which allows the user to continue the coordinator by sending a newline.
     // Don't impl Clone or Copy; we don't want multiple responders of this type.
     #[allow(missing_copy_implementations)]
@@ -639,6 +5067,8 @@ pub mod tui {
     pub struct Tui {}
     impl Update for Tui {
         fn handle(&self, status: &Status, coord: &Subscribers) {
+            let (level, description) = describe(status);
+            log::log!(level, "{}", description);
             match &status.message {
                 StatusMessage::Paused => {
                     log::trace!("Prompting user to unpause.");
@@ -655,14 +5085,34 @@ pub mod tui {
                     }
                     coord.respond(Message::Continue);
                 }
-                StatusMessage::Continued => log::debug!("Coordinator continuing."),
-                StatusMessage::Started(proto) => {
-                    log::debug!("Coordinator starting protocol: {:?}", proto)
-                }
-                StatusMessage::StopQueued { early } => {
-                    log::debug!("Coordinator stop queued (early: {})", early)
+                StatusMessage::Interrupted { .. } => {
+                    use std::io::{stdin, stdout, BufRead, BufReader, Write};
+                    let stdin = stdin();
+                    let mut stdin = BufReader::new(stdin.lock());
+                    let recovery = loop {
+                        print!("Resume the interrupted job (r) or abort it (a)? ");
+                        let _ = stdout().lock().flush();
+                        let mut s = String::new();
+                        // Without a terminal (e.g. running as a daemon), nobody can answer.
+                        match stdin.read_line(&mut s) {
+                            Ok(0) | Err(_) => break None,
+                            Ok(_) => {}
+                        }
+                        match s.trim() {
+                            "r" => break Some(Recovery::Resume),
+                            "a" => break Some(Recovery::Abort),
+                            _ => {}
+                        }
+                    };
+                    match recovery {
+                        Some(recovery) => coord.respond(Message::Recover(recovery)),
+                        None => {
+                            println!();
+                            log::warn!("No answer; recover the interrupted job through the API.");
+                        }
+                    }
                 }
-                StatusMessage::Halted => log::warn!("Coordinator halted!"),
+                _ => {}
             }
         }
     }
@@ -0,0 +1,724 @@
+//! A full-screen terminal dashboard, for operating headless rigs over SSH.
+//!
+//! The dashboard runs on its own thread, redrawing as updates arrive from the coordinator (and at
+//! least a few times a second, so that progress bars and estimates keep moving). It shows the
+//! coordinator's state, the current step with its progress and the estimated time remaining, which
+//! buffers the program uses (and which is flowing), the latest sensor readings, and the most recent
+//! events, and it takes single-key commands:
+//!
+//! - `p` pauses the program, and `r` resumes it;
+//! - `c` (or enter) continues past a step waiting for the operator;
+//! - `a` aborts the program (after asking for confirmation with `y`);
+//! - `l` opens the protocol library, where protocols can be edited and run (see the `editor`
+//!   module);
+//! - `f` opens the catalog of protocol files, any of which can be run (see the `files` module);
+//! - `m` opens the maintenance screen, where valves and the pump can be moved by hand (see the
+//!   `manual` module);
+//! - `v` opens the log pane, showing captured log records (see the `logs` module); and
+//! - `q` closes the dashboard, leaving the coordinator running.
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind},
+    execute,
+    terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use futures::Future;
+use log::{Level, LevelFilter, SetLoggerError};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Gauge, List, ListItem, Paragraph},
+    Frame, Terminal,
+};
+use uom::si::volume::milliliter;
+
+use super::{
+    super::{estimated_duration, Coordinator, Sensor, State},
+    describe, format_duration, Message, Status, StatusMessage, Subscribers, Update,
+};
+use crate::{
+    actix::Addr, mail::template::describe as describe_action, Action, Buffers, Catalog, Hardware,
+    Iteration, Library, MotorId,
+};
+
+mod editor;
+mod files;
+mod logs;
+mod manual;
+
+use self::{
+    editor::{Browser, Outcome},
+    files::Files,
+    logs::Viewer,
+    manual::{Manual, Outcome as ManualOutcome},
+};
+
+/// How often the screen is redrawn when nothing happens.
+const TICK: Duration = Duration::from_millis(250);
+/// How many recent events are kept.
+const EVENTS: usize = 200;
+
+/// What the dashboard is told by the coordinator.
+#[derive(Debug)]
+struct Snapshot {
+    address: Addr<Coordinator>,
+    state: State,
+    step: Option<usize>,
+    iteration: Option<Iteration>,
+    elapsed: Option<Duration>,
+    step_remaining: Option<Duration>,
+    remaining: Option<Duration>,
+    queued: usize,
+    buffers: Buffers,
+    /// The program's actions, if a new one was started.
+    program: Option<Vec<Action>>,
+    reading: Option<(Sensor, f64)>,
+    low: Option<(MotorId, f64)>,
+    event: (Level, String),
+    received: Instant,
+}
+
+impl Snapshot {
+    fn new(status: &Status) -> Self {
+        let (program, reading, low) = match &status.message {
+            StatusMessage::Started(protocol) => {
+                (protocol.as_program().ok().map(Into::into), None, None)
+            }
+            StatusMessage::Reading { sensor, value } => (None, Some((*sensor, *value)), None),
+            StatusMessage::ReservoirLow { buffer, volume } => {
+                (None, None, Some((*buffer, volume.get::<milliliter>())))
+            }
+            _ => (None, None, None),
+        };
+        Self {
+            address: status.address.clone(),
+            state: status.state,
+            step: status.step,
+            iteration: status.iteration,
+            elapsed: status.elapsed,
+            step_remaining: status.step_remaining,
+            remaining: status.remaining,
+            queued: status.queue.len(),
+            buffers: status.buffers.clone(),
+            program,
+            reading,
+            low,
+            event: describe(status),
+            received: Instant::now(),
+        }
+    }
+}
+
+/// Everything the dashboard shows.
+#[derive(Debug)]
+struct View {
+    latest: Option<Snapshot>,
+    actions: Vec<Action>,
+    readings: BTreeMap<&'static str, String>,
+    /// The volumes of the reservoirs reported low, by buffer.
+    low: BTreeMap<MotorId, f64>,
+    /// The configured buffers, as last reported by the coordinator.
+    buffers: Buffers,
+    events: VecDeque<(Level, String)>,
+    /// Whether the operator has asked to abort, and must confirm.
+    confirming: bool,
+    library: Option<Library>,
+    catalog: Option<Catalog>,
+    hardware: Hardware,
+    /// The protocol library, while it's open.
+    browser: Option<Browser>,
+    /// The catalog of protocol files, while it's open.
+    files: Option<Files>,
+    /// The maintenance screen, while it's open.
+    manual: Option<Manual>,
+    /// The log pane, while it's open.
+    logs: Option<Viewer>,
+}
+
+impl View {
+    fn new(library: Option<Library>, catalog: Option<Catalog>, hardware: Hardware) -> Self {
+        Self {
+            latest: None,
+            actions: vec![],
+            readings: BTreeMap::new(),
+            low: BTreeMap::new(),
+            buffers: Buffers::default(),
+            events: VecDeque::new(),
+            confirming: false,
+            library,
+            catalog,
+            hardware,
+            browser: None,
+            files: None,
+            manual: None,
+            logs: None,
+        }
+    }
+    fn update(&mut self, mut snapshot: Snapshot) {
+        if let Some(actions) = snapshot.program.take() {
+            self.actions = actions;
+            self.low.clear();
+        }
+        if let Some((sensor, value)) = snapshot.reading {
+            let label = match sensor {
+                Sensor::Pressure => "Pressure",
+                Sensor::Flow => "Flow",
+                Sensor::Temperature => "Temperature",
+            };
+            self.readings
+                .insert(label, format!("{:.1} {}", value, sensor.unit()));
+        }
+        if let Some((buffer, volume)) = snapshot.low {
+            self.low.insert(buffer, volume);
+        }
+        self.buffers = snapshot.buffers.clone();
+        let (level, ref text) = snapshot.event;
+        if level <= Level::Info {
+            self.events.push_front((level, text.clone()));
+            self.events.truncate(EVENTS);
+        }
+        self.latest = Some(snapshot);
+    }
+    /// Sends the given message to the coordinator, if it's been heard from.
+    fn send(&self, message: Message) {
+        if let Some(latest) = &self.latest {
+            latest.address.do_send(message);
+        }
+    }
+    /// Sends the given message to the coordinator, waiting for it to be carried out.
+    fn request(&self, message: Message) -> Result<(), String> {
+        let latest = self
+            .latest
+            .as_ref()
+            .ok_or("The coordinator hasn't been heard from yet.")?;
+        match latest.address.send(message).wait() {
+            Ok(result) => result.map_err(|err| err.to_string()),
+            Err(err) => Err(format!("The coordinator couldn't be reached: {}", err)),
+        }
+    }
+    /// Whether the coordinator is idle (so that the hardware may be controlled by hand).
+    fn is_idle(&self) -> bool {
+        match &self.latest {
+            Some(latest) => match latest.state {
+                State::Stopped { .. } => true,
+                _ => false,
+            },
+            None => false,
+        }
+    }
+    /// Responds to the given key, returning whether the dashboard should close.
+    fn key(&mut self, key: KeyCode) -> bool {
+        if let Some(logs) = &mut self.logs {
+            if logs.key(key) {
+                self.logs = None;
+            }
+            return false;
+        }
+        let idle = self.is_idle();
+        if let Some(manual) = &mut self.manual {
+            let jog = match manual.key(key, idle) {
+                ManualOutcome::Stay => return false,
+                ManualOutcome::Close => {
+                    self.manual = None;
+                    return false;
+                }
+                ManualOutcome::Jog(jog) => jog,
+            };
+            let (level, report) = match self.request(Message::Jog(jog)) {
+                Ok(()) => (Level::Info, format!("{}.", manual::describe(jog))),
+                Err(err) => (Level::Error, err),
+            };
+            if let Some(manual) = &mut self.manual {
+                manual.report(level, report);
+            }
+            return false;
+        }
+        if let Some(browser) = &mut self.browser {
+            match browser.key(key) {
+                Outcome::Stay => {}
+                Outcome::Close => self.browser = None,
+                Outcome::Launch(protocol) => {
+                    self.browser = None;
+                    // Anything already running finishes first.
+                    self.send(Message::Enqueue(protocol, None));
+                }
+            }
+            return false;
+        }
+        if let Some(files) = &mut self.files {
+            match files.key(key) {
+                Outcome::Stay => {}
+                Outcome::Close => self.files = None,
+                Outcome::Launch(protocol) => {
+                    self.files = None;
+                    self.send(Message::Enqueue(protocol, None));
+                }
+            }
+            return false;
+        }
+        if self.confirming {
+            self.confirming = false;
+            if key == KeyCode::Char('y') {
+                self.send(Message::Abort);
+            }
+            return false;
+        }
+        match key {
+            KeyCode::Char('q') => return true,
+            KeyCode::Char('p') => self.send(Message::Pause),
+            KeyCode::Char('r') => self.send(Message::Resume),
+            KeyCode::Char('c') | KeyCode::Enter => self.send(Message::Continue),
+            KeyCode::Char('a') => self.confirming = true,
+            KeyCode::Char('l') => match &self.library {
+                Some(library) => {
+                    self.browser = Some(Browser::new(
+                        library.clone(),
+                        self.hardware.clone(),
+                        self.buffers.clone(),
+                    ))
+                }
+                None => self.events.push_front((
+                    Level::Warn,
+                    "No protocol library is kept (see the `library` option).".into(),
+                )),
+            },
+            KeyCode::Char('f') => match &self.catalog {
+                Some(catalog) => {
+                    self.files = Some(Files::new(
+                        catalog.clone(),
+                        self.hardware.clone(),
+                        self.buffers.clone(),
+                    ))
+                }
+                None => self.events.push_front((
+                    Level::Warn,
+                    "No protocol files are cataloged (see the `protocols` option).".into(),
+                )),
+            },
+            KeyCode::Char('m') => self.manual = Some(Manual::new(self.hardware.positions.len())),
+            KeyCode::Char('v') => self.logs = Some(Viewer::new()),
+            KeyCode::Char('w') => {
+                if let Err(err) = self.request(Message::Wash) {
+                    self.events.push_front((Level::Error, err));
+                }
+            }
+            _ => {}
+        }
+        false
+    }
+    /// The estimate in the latest snapshot, counted down since it arrived (while running).
+    fn countdown(&self, estimate: Option<Duration>) -> Option<Duration> {
+        let latest = self.latest.as_ref()?;
+        let estimate = estimate?;
+        Some(match latest.state {
+            State::Running => estimate
+                .checked_sub(latest.received.elapsed())
+                .unwrap_or_default(),
+            _ => estimate,
+        })
+    }
+    fn render(&self, frame: &mut Frame) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Min(8),
+                Constraint::Length(10),
+                Constraint::Length(1),
+            ])
+            .split(frame.size());
+        self.render_header(frame, rows[0]);
+        self.render_progress(frame, rows[1], rows[2]);
+        if let Some(browser) = &self.browser {
+            let area = rows[3].union(rows[4]);
+            browser.render(frame, area);
+            frame.render_widget(Paragraph::new(browser.help()), rows[5]);
+            return;
+        }
+        if let Some(files) = &self.files {
+            files.render(frame, rows[3].union(rows[4]));
+            frame.render_widget(Paragraph::new(files.help()), rows[5]);
+            return;
+        }
+        if let Some(logs) = &self.logs {
+            logs.render(frame, rows[3].union(rows[4]));
+            frame.render_widget(Paragraph::new(logs.help()), rows[5]);
+            return;
+        }
+        if let Some(manual) = &self.manual {
+            manual.render(frame, rows[3].union(rows[4]), self.is_idle());
+            frame.render_widget(Paragraph::new(manual.help()), rows[5]);
+            return;
+        }
+        let middle = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(35), Constraint::Percentage(65)])
+            .split(rows[3]);
+        self.render_buffers(frame, middle[0]);
+        self.render_steps(frame, middle[1]);
+        self.render_events(frame, rows[4]);
+        let help = if self.confirming {
+            Span::styled(
+                "Abort the program? Press y to confirm, or any other key to cancel.",
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            )
+        } else {
+            Span::raw("p pause · r resume · c continue · a abort · l library · f files · m manual · w wash · v logs · q quit")
+        };
+        frame.render_widget(Paragraph::new(Line::from(help)), rows[5]);
+    }
+    fn render_header(&self, frame: &mut Frame, area: Rect) {
+        let mut spans = vec![];
+        match &self.latest {
+            Some(latest) => {
+                let (name, color) = state(latest.state);
+                spans.push(Span::styled(
+                    name,
+                    Style::default().fg(color).add_modifier(Modifier::BOLD),
+                ));
+                if let Some(elapsed) = latest.elapsed {
+                    spans.push(Span::raw(format!(
+                        "  ·  running for {}",
+                        format_duration(elapsed)
+                    )));
+                }
+                if latest.queued > 0 {
+                    spans.push(Span::raw(format!("  ·  {} queued", latest.queued)));
+                }
+                for (label, reading) in &self.readings {
+                    spans.push(Span::raw(format!("  ·  {} {}", label, reading)));
+                }
+            }
+            None => spans.push(Span::raw("Waiting for the coordinator…")),
+        }
+        let header = Paragraph::new(Line::from(spans))
+            .block(Block::default().borders(Borders::ALL).title("deoxy"));
+        frame.render_widget(header, area);
+    }
+    fn render_progress(&self, frame: &mut Frame, step_area: Rect, total_area: Rect) {
+        let step = self.latest.as_ref().and_then(|latest| latest.step);
+        let action = step.and_then(|step| self.actions.get(step));
+        let (ratio, label) = match (step, action) {
+            (Some(step), Some(action)) => {
+                let expected = estimated_duration(action);
+                let remaining = self.countdown(self.latest.as_ref().and_then(|l| l.step_remaining));
+                let ratio = match remaining {
+                    Some(remaining) if expected > Duration::new(0, 0) => {
+                        1.0 - remaining.as_secs_f64() / expected.as_secs_f64()
+                    }
+                    _ => 0.0,
+                };
+                let left = remaining
+                    .map(|remaining| format!(" · {} left", format_duration(remaining)))
+                    .unwrap_or_default();
+                let pass = self
+                    .latest
+                    .as_ref()
+                    .and_then(|latest| latest.iteration)
+                    .map(|iteration| format!(" ({})", iteration))
+                    .unwrap_or_default();
+                (
+                    ratio,
+                    format!(
+                        "Step {} of {}{}: {}{}",
+                        step + 1,
+                        self.actions.len(),
+                        pass,
+                        describe_action(action, &self.buffers),
+                        left
+                    ),
+                )
+            }
+            _ => (0.0, "No step running".into()),
+        };
+        let gauge = Gauge::default()
+            .block(Block::default().borders(Borders::ALL).title("Current step"))
+            .gauge_style(Style::default().fg(Color::Cyan))
+            .ratio(ratio.max(0.0).min(1.0))
+            .label(label);
+        frame.render_widget(gauge, step_area);
+        let elapsed = self.latest.as_ref().and_then(|latest| latest.elapsed);
+        let remaining = self.countdown(self.latest.as_ref().and_then(|l| l.remaining));
+        let (ratio, label) = match (elapsed, remaining) {
+            (Some(elapsed), Some(remaining)) => {
+                let total = elapsed + remaining;
+                (
+                    elapsed.as_secs_f64() / total.as_secs_f64().max(1.0),
+                    format!("About {} remaining", format_duration(remaining)),
+                )
+            }
+            _ => (0.0, "No program running".into()),
+        };
+        let gauge = Gauge::default()
+            .block(Block::default().borders(Borders::ALL).title("Program"))
+            .gauge_style(Style::default().fg(Color::Green))
+            .ratio(ratio.max(0.0).min(1.0))
+            .label(label);
+        frame.render_widget(gauge, total_area);
+    }
+    fn render_buffers(&self, frame: &mut Frame, area: Rect) {
+        let current = self
+            .latest
+            .as_ref()
+            .and_then(|latest| latest.step)
+            .and_then(|step| self.actions.get(step));
+        let mut buffers = BTreeMap::<MotorId, Vec<usize>>::new();
+        for (index, action) in self.actions.iter().enumerate() {
+            if let Action::Perfuse(buffer) = action {
+                buffers.entry(*buffer).or_default().push(index + 1);
+            }
+        }
+        let items = buffers
+            .iter()
+            .map(|(&buffer, steps)| {
+                let flowing = current == Some(&Action::Perfuse(buffer));
+                let marker = if flowing { "▶" } else { " " };
+                let steps = steps
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let config = self.buffers.get(buffer);
+                let name = config.map_or_else(
+                    || format!("Buffer {}", buffer),
+                    |config| config.name.clone(),
+                );
+                let mut spans = vec![Span::raw(format!("{} {}  (steps {})", marker, name, steps))];
+                if let Some(volume) = self.low.get(&buffer) {
+                    spans.push(Span::styled(
+                        format!("  low: {:.0} mL", volume),
+                        Style::default().fg(Color::Yellow),
+                    ));
+                }
+                if let Some(hazards) = config.and_then(|config| config.hazards.as_ref()) {
+                    spans.push(Span::styled(
+                        format!("  ⚠ {}", hazards),
+                        Style::default().fg(Color::Red),
+                    ));
+                }
+                let style = if flowing {
+                    Style::default().add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                ListItem::new(Line::from(spans)).style(style)
+            })
+            .collect::<Vec<_>>();
+        let list = List::new(items).block(Block::default().borders(Borders::ALL).title("Buffers"));
+        frame.render_widget(list, area);
+    }
+    fn render_steps(&self, frame: &mut Frame, area: Rect) {
+        let current = self.latest.as_ref().and_then(|latest| latest.step);
+        // Keep the current step in view.
+        let visible = area.height.saturating_sub(2) as usize;
+        let first = current
+            .map_or(0, |step| step.saturating_sub(visible / 3))
+            .min(self.actions.len().saturating_sub(visible));
+        let items = self
+            .actions
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(visible)
+            .map(|(index, action)| {
+                let style = match current {
+                    Some(step) if index == step => Style::default()
+                        .fg(Color::Cyan)
+                        .add_modifier(Modifier::BOLD),
+                    Some(step) if index < step => Style::default().fg(Color::DarkGray),
+                    _ => Style::default(),
+                };
+                let description = describe_action(action, &self.buffers);
+                ListItem::new(format!("{:>3}. {}", index + 1, description)).style(style)
+            })
+            .collect::<Vec<_>>();
+        let list = List::new(items).block(Block::default().borders(Borders::ALL).title("Steps"));
+        frame.render_widget(list, area);
+    }
+    fn render_events(&self, frame: &mut Frame, area: Rect) {
+        let items = self
+            .events
+            .iter()
+            .take(area.height.saturating_sub(2) as usize)
+            .map(|(level, text)| {
+                let color = match level {
+                    Level::Error => Color::Red,
+                    Level::Warn => Color::Yellow,
+                    _ => Color::Reset,
+                };
+                ListItem::new(text.as_str()).style(Style::default().fg(color))
+            })
+            .collect::<Vec<_>>();
+        let list = List::new(items).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Recent events"),
+        );
+        frame.render_widget(list, area);
+    }
+}
+
+/// How the given state is shown.
+fn state(state: State) -> (&'static str, Color) {
+    match state {
+        State::Waiting => ("Waiting for the operator", Color::Yellow),
+        State::Stopped { early: false } => ("Idle", Color::Reset),
+        State::Stopped { early: true } => ("Stopped early", Color::Yellow),
+        State::Running => ("Running", Color::Green),
+        State::Paused => ("Paused", Color::Yellow),
+        State::Priming => ("Priming", Color::Cyan),
+        State::Washing => ("Washing", Color::Cyan),
+        State::Calibrating { .. } => ("Calibrating", Color::Cyan),
+        State::Manual => ("Manual control", Color::Cyan),
+        State::Faulted => ("Faulted", Color::Red),
+    }
+}
+
+/// Runs the dashboard until the operator quits or the coordinator goes away.
+fn run(updates: &Receiver<Snapshot>, mut view: View) -> io::Result<()> {
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+    loop {
+        loop {
+            match updates.try_recv() {
+                Ok(snapshot) => view.update(snapshot),
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => return Ok(()),
+            }
+        }
+        terminal.draw(|frame| view.render(frame))?;
+        if event::poll(TICK)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && view.key(key.code) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// Puts the terminal into full-screen mode for the dashboard, and restores it afterward.
+fn session(updates: &Receiver<Snapshot>, view: View) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    terminal::enable_raw_mode()?;
+    execute!(stdout, EnterAlternateScreen)?;
+    let result = run(updates, view);
+    let _ = execute!(stdout, LeaveAlternateScreen);
+    let _ = terminal::disable_raw_mode();
+    result
+}
+
+/// A full-screen dashboard, which shows the coordinator's progress and takes commands from the
+/// keyboard.
+///
+/// Since the dashboard takes over the terminal, logs should be captured for its log pane (see
+/// [`capture_logs`](#method.capture_logs)) rather than printed while it's open.
+#[derive(Debug)]
+pub struct Dashboard {
+    updates: Sender<Snapshot>,
+    open: Arc<AtomicBool>,
+}
+
+impl Dashboard {
+    /// Installs a logger keeping records up to the given level for the dashboard's log pane,
+    /// instead of printing them.
+    ///
+    /// This fails if a logger has already been installed.
+    pub fn capture_logs(level: LevelFilter) -> Result<(), SetLoggerError> {
+        logs::capture(level)
+    }
+    /// Opens the dashboard on the terminal (on its own thread).
+    ///
+    /// Protocols are edited in the given library (if any), run from the given catalog of protocol
+    /// files (if any), and checked against the given hardware (see [`Coordinator::hardware`]).
+    /// Subscribe the dashboard to the coordinator to start showing updates.
+    ///
+    /// [`Coordinator::hardware`]: struct.Coordinator.html#method.hardware
+    pub fn open(
+        library: Option<Library>,
+        catalog: Option<Catalog>,
+        hardware: Hardware,
+    ) -> io::Result<Self> {
+        let (updates, receiver) = mpsc::channel();
+        let view = View::new(library, catalog, hardware);
+        let open = Arc::new(AtomicBool::new(true));
+        let running = open.clone();
+        thread::Builder::new()
+            .name("dashboard".into())
+            .spawn(move || {
+                if let Err(err) = session(&receiver, view) {
+                    log::error!("The dashboard failed: {}", err);
+                }
+                running.store(false, Ordering::SeqCst);
+            })?;
+        Ok(Self { updates, open })
+    }
+}
+
+impl Update for Dashboard {
+    fn handle(&self, status: &Status, _coord: &Subscribers) {
+        let _ = self.updates.send(Snapshot::new(status));
+    }
+    fn is_connected(&self) -> bool {
+        self.open.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BufferConfig;
+    use ratatui::backend::TestBackend;
+
+    #[test]
+    fn renders() {
+        let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        let mut view = View::new(None, None, Hardware::default());
+        view.actions = vec![Action::Perfuse(1), Action::Hail, Action::Perfuse(2)];
+        view.low.insert(2, 40.0);
+        let mut sds = BufferConfig::new(1, "1% SDS");
+        sds.hazards = Some("Irritant".into());
+        view.buffers = Buffers::new(vec![sds]);
+        view.events
+            .push_front((Level::Warn, "Bubble detected.".into()));
+        terminal.draw(|frame| view.render(frame)).unwrap();
+        let screen = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect::<String>();
+        assert!(screen.contains("Waiting for the coordinator"));
+        assert!(screen.contains("1% SDS  (steps 1)  ⚠ Irritant"));
+        assert!(screen.contains("Buffer 2  (steps 3)  low: 40 mL"));
+        assert!(screen.contains("1. Perfuse with 1% SDS"));
+        assert!(screen.contains("2. Wait for the operator"));
+        assert!(screen.contains("Bubble detected."));
+    }
+
+    #[test]
+    fn confirms_abort() {
+        let mut view = View::new(None, None, Hardware::default());
+        assert!(!view.key(KeyCode::Char('a')));
+        assert!(view.confirming);
+        assert!(!view.key(KeyCode::Char('n')));
+        assert!(!view.confirming);
+        assert!(view.key(KeyCode::Char('q')));
+    }
+}
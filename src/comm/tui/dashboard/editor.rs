@@ -0,0 +1,905 @@
+//! The dashboard's protocol library, where stored protocols can be browsed, edited, and launched.
+//!
+//! In the list of protocols, `↑`/`↓` choose one, enter edits it, `n` creates a new one, `g` runs
+//! it, and escape returns to the dashboard. While editing, `↑`/`↓` choose a step and `←`/`→` one of
+//! its fields, enter changes the chosen field, `i` duplicates the step and `d` deletes it, `m`
+//! renames the protocol, `w` saves it to the library, and `g` runs it. The protocol is checked
+//! against the hardware after every change, so mistakes show up as soon as they're made.
+
+use std::time::Duration;
+
+use crossterm::event::KeyCode;
+use log::Level;
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, Paragraph},
+    Frame,
+};
+use uuid::Uuid;
+
+use super::super::format_duration;
+use crate::{
+    Buffers, Hardware, Hook, Library, Protocol, ProtocolIssue, Step, StoredProtocol,
+    ValidateProtocolError, Validation,
+};
+
+/// What the dashboard should do after a key is handled.
+#[derive(Debug, PartialEq)]
+pub(super) enum Outcome {
+    /// Keep showing the library.
+    Stay,
+    /// Return to the dashboard.
+    Close,
+    /// Run the given protocol (and return to the dashboard).
+    Launch(Protocol),
+}
+
+/// An editable part of a step.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Field {
+    Buffer,
+    Duration,
+}
+
+impl Field {
+    fn name(self) -> &'static str {
+        match self {
+            Field::Buffer => "Buffer",
+            Field::Duration => "Duration",
+        }
+    }
+}
+
+/// The editable fields of the given step.
+fn fields(step: &Step) -> &'static [Field] {
+    match step {
+        Step::Perfuse(..) | Step::PerfusePrompt(..) => &[Field::Buffer, Field::Duration],
+        Step::HoldTemperature { .. } | Step::Gradient { .. } => &[Field::Duration],
+        Step::Hooked { step, .. } | Step::Timed { step, .. } => fields(step),
+        Step::UsePump(_)
+        | Step::Mix { .. }
+        | Step::SetPosition { .. }
+        | Step::Repeat { .. }
+        | Step::WaitUntil { .. }
+        | Step::IfElse { .. } => &[],
+    }
+}
+
+/// The current value of the given field of the given step, as it's typed (naming buffers as
+/// configured).
+fn value(step: &Step, field: Field, buffers: &Buffers) -> String {
+    match (step, field) {
+        (Step::Hooked { step, .. }, field) | (Step::Timed { step, .. }, field) => {
+            value(step, field, buffers)
+        }
+        (Step::Perfuse(buffer, _), Field::Buffer)
+        | (Step::PerfusePrompt(buffer, ..), Field::Buffer) => buffers
+            .get(*buffer)
+            .map_or_else(|| buffer.to_string(), |config| config.name.clone()),
+        (Step::Perfuse(_, duration), Field::Duration) => {
+            duration.map(exact_duration).unwrap_or_default()
+        }
+        (Step::PerfusePrompt(_, _, duration, _), Field::Duration)
+        | (Step::HoldTemperature { duration, .. }, Field::Duration)
+        | (Step::Gradient { duration, .. }, Field::Duration) => exact_duration(*duration),
+        _ => String::new(),
+    }
+}
+
+/// Sets the given field of the given step from what was typed (where buffers may be given by
+/// number or by their configured names).
+fn set(step: &mut Step, field: Field, input: &str, buffers: &Buffers) -> Result<(), String> {
+    if let Step::Hooked { step, .. } | Step::Timed { step, .. } = step {
+        return set(step, field, input, buffers);
+    }
+    match field {
+        Field::Buffer => {
+            let input = input.trim();
+            let value = match input.parse() {
+                Ok(value) => value,
+                Err(_) => buffers
+                    .iter()
+                    .find(|buffer| buffer.name.eq_ignore_ascii_case(input))
+                    .map(|buffer| buffer.valve)
+                    .ok_or_else(|| format!("\"{}\" isn't a buffer's name or number.", input))?,
+            };
+            match step {
+                Step::Perfuse(buffer, _) | Step::PerfusePrompt(buffer, ..) => *buffer = value,
+                _ => {}
+            }
+        }
+        Field::Duration => {
+            let value = parse_duration(input)?;
+            match (step, value) {
+                (Step::Perfuse(_, duration), value) => *duration = value,
+                (Step::PerfusePrompt(_, _, duration, _), Some(value))
+                | (Step::HoldTemperature { duration, .. }, Some(value))
+                | (Step::Gradient { duration, .. }, Some(value)) => *duration = value,
+                _ => return Err("This step needs a duration.".into()),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Formats the given duration so that it can be parsed back exactly (e.g. "1h 30m 5s").
+fn exact_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let parts = [(secs / 3600, "h"), (secs / 60 % 60, "m"), (secs % 60, "s")];
+    let text = parts
+        .iter()
+        .filter(|(amount, _)| *amount > 0)
+        .map(|(amount, unit)| format!("{}{}", amount, unit))
+        .collect::<Vec<_>>()
+        .join(" ");
+    if text.is_empty() {
+        "0s".into()
+    } else {
+        text
+    }
+}
+
+/// Parses a duration typed like "1h 30m" or "45s" (where nothing means no duration).
+fn parse_duration(input: &str) -> Result<Option<Duration>, String> {
+    let invalid = || format!("\"{}\" isn't a duration (try e.g. 1h 30m).", input.trim());
+    if input.trim().is_empty() {
+        return Ok(None);
+    }
+    let mut secs = 0;
+    let mut amount = None::<u64>;
+    for c in input.chars() {
+        match c {
+            '0'..='9' => {
+                let digit = u64::from(c.to_digit(10).unwrap_or_default());
+                amount = Some(amount.unwrap_or(0) * 10 + digit);
+            }
+            'h' | 'm' | 's' => {
+                let unit = match c {
+                    'h' => 3600,
+                    'm' => 60,
+                    _ => 1,
+                };
+                secs += amount.take().ok_or_else(invalid)? * unit;
+            }
+            c if c.is_whitespace() => {}
+            _ => return Err(invalid()),
+        }
+    }
+    if amount.is_some() {
+        return Err(invalid());
+    }
+    Ok(Some(Duration::from_secs(secs)))
+}
+
+/// Describes the given hooks for a person (e.g. "confirming \"Load the sample\"").
+fn describe_hooks(hooks: &[Hook]) -> String {
+    hooks
+        .iter()
+        .map(|hook| match hook {
+            Hook::Notify(notification) => format!("notifying \"{}\"", notification.subject),
+            Hook::Webhook(event) => format!("posting \"{}\"", event),
+            Hook::Confirm(notification) => format!("confirming \"{}\"", notification.subject),
+        })
+        .collect::<Vec<_>>()
+        .join(" and ")
+}
+
+/// Describes the given step for a person, naming buffers as configured.
+pub(super) fn describe(step: &Step, buffers: &Buffers) -> String {
+    match step {
+        Step::Perfuse(buffer, Some(duration)) => format!(
+            "Perfuse with {} for {}",
+            buffers.name(*buffer),
+            format_duration(*duration)
+        ),
+        Step::Perfuse(buffer, None) => {
+            format!("Perfuse with {} until continued", buffers.name(*buffer))
+        }
+        Step::PerfusePrompt(buffer, begin, duration, _) => format!(
+            "Perfuse with {} for {}, prompting \"{}\"",
+            buffers.name(*buffer),
+            format_duration(*duration),
+            begin.subject
+        ),
+        Step::Gradient {
+            from, to, duration, ..
+        } => format!(
+            "Ramp from {} to {} over {}",
+            buffers.name(*from),
+            buffers.name(*to),
+            format_duration(*duration)
+        ),
+        Step::UsePump(pump) => format!("Switch to pump {}", pump),
+        Step::HoldTemperature {
+            target, duration, ..
+        } => format!(
+            "Hold at {:.1} °C for {}",
+            target,
+            format_duration(*duration)
+        ),
+        Step::Mix {
+            cycles,
+            forward,
+            backward,
+        } => format!(
+            "Mix {} time(s) ({} forward, {} back)",
+            cycles,
+            format_duration(*forward),
+            format_duration(*backward)
+        ),
+        Step::SetPosition { motor, position } => format!("Move motor {} to {}", motor, position),
+        Step::Repeat { count, steps } => {
+            let steps = steps
+                .iter()
+                .map(|step| describe(step, buffers))
+                .collect::<Vec<_>>()
+                .join("; ");
+            format!("Repeat {} times: {}", count, steps)
+        }
+        Step::WaitUntil {
+            sensor,
+            predicate,
+            timeout,
+        } => {
+            let until = format!(
+                "Wait until the {} is {} {}",
+                sensor,
+                predicate,
+                sensor.unit()
+            );
+            match timeout {
+                Some(timeout) => format!("{} (at most {})", until, format_duration(*timeout)),
+                None => until,
+            }
+        }
+        Step::IfElse {
+            sensor,
+            predicate,
+            then,
+            otherwise,
+        } => {
+            let list = |steps: &[Step]| {
+                steps
+                    .iter()
+                    .map(|step| describe(step, buffers))
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            };
+            let mut text = format!(
+                "If the {} is {} {}: {}",
+                sensor,
+                predicate,
+                sensor.unit(),
+                list(then)
+            );
+            if !otherwise.is_empty() {
+                text.push_str(&format!("; otherwise: {}", list(otherwise)));
+            }
+            text
+        }
+        Step::Hooked { step, begin, end } => {
+            let mut text = describe(step, buffers);
+            if !begin.is_empty() {
+                text.push_str(&format!(", {} first", describe_hooks(begin)));
+            }
+            if !end.is_empty() {
+                text.push_str(&format!(", {} after", describe_hooks(end)));
+            }
+            text
+        }
+        Step::Timed { step, overrun } => format!(
+            "{} (overrunning by up to {}, then {})",
+            describe(step, buffers),
+            format_duration(overrun.tolerance),
+            overrun.policy
+        ),
+    }
+}
+
+/// The index of the step the given issue concerns, if any.
+fn step_of(issue: &ProtocolIssue) -> Option<usize> {
+    match issue {
+        ProtocolIssue::Structure(_) => None,
+        ProtocolIssue::NoSuchBuffer { step, .. }
+        | ProtocolIssue::NoSuchPump { step, .. }
+        | ProtocolIssue::NoSuchPosition { step, .. }
+        | ProtocolIssue::NoThermostat { step }
+        | ProtocolIssue::ZeroDuration { step }
+        | ProtocolIssue::InvalidTemperature { step }
+        | ProtocolIssue::RepeatedBuffer { step, .. }
+        | ProtocolIssue::RedundantPump { step, .. }
+        | ProtocolIssue::NoSuchSensor { step, .. }
+        | ProtocolIssue::InvalidPredicate { step } => Some(*step),
+    }
+}
+
+/// Explains the given issue, numbering steps as they're shown (from 1).
+pub(super) fn explain(issue: &ProtocolIssue) -> String {
+    match issue {
+        ProtocolIssue::Structure(ValidateProtocolError::Empty) => {
+            "The protocol has no steps.".into()
+        }
+        ProtocolIssue::Structure(ValidateProtocolError::Last(_)) => {
+            "The last step must perfuse until continued (with no duration).".into()
+        }
+        ProtocolIssue::Structure(ValidateProtocolError::ZeroDuration) => {
+            "A perfusion has a duration of zero.".into()
+        }
+        ProtocolIssue::Structure(ValidateProtocolError::EmptyRepeat) => {
+            "A repeat has no steps or runs zero times.".into()
+        }
+        ProtocolIssue::Structure(ValidateProtocolError::EmptyBranch) => {
+            "A conditional step has no steps in either branch.".into()
+        }
+        ProtocolIssue::Structure(ValidateProtocolError::TooLong) => {
+            "The protocol has too many steps to run (check the repeat counts).".into()
+        }
+        _ => {
+            let text = issue.to_string();
+            match step_of(issue) {
+                Some(step) => text.replacen(
+                    &format!("step {}: ", step),
+                    &format!("Step {}: ", step + 1),
+                    1,
+                ),
+                None => text,
+            }
+        }
+    }
+}
+
+/// What's being typed, and where it goes.
+#[derive(Clone, Debug, PartialEq)]
+enum Input {
+    /// A new value for the given field of the chosen step.
+    Field(Field, String),
+    /// A new name for the protocol.
+    Name(String),
+}
+
+/// A stored protocol being edited.
+#[derive(Debug)]
+struct Editor {
+    stored: StoredProtocol,
+    /// The chosen step.
+    step: usize,
+    /// The chosen field of the chosen step (as an index into its fields).
+    field: usize,
+    input: Option<Input>,
+    validation: Validation,
+    /// Whether there are unsaved changes.
+    modified: bool,
+    /// Whether the operator has been warned that leaving discards unsaved changes.
+    warned: bool,
+}
+
+impl Editor {
+    fn new(stored: StoredProtocol, hardware: &Hardware) -> Self {
+        let validation = stored.protocol.check(hardware);
+        Self {
+            stored,
+            step: 0,
+            field: 0,
+            input: None,
+            validation,
+            modified: false,
+            warned: false,
+        }
+    }
+    fn steps(&self) -> &[Step] {
+        &self.stored.protocol.steps
+    }
+    /// The chosen field, if the chosen step has any.
+    fn field(&self) -> Option<Field> {
+        let step = self.steps().get(self.step)?;
+        fields(step).get(self.field).copied()
+    }
+    /// Notes that the protocol was changed, checking it again.
+    fn changed(&mut self, hardware: &Hardware) {
+        self.modified = true;
+        self.warned = false;
+        self.validation = self.stored.protocol.check(hardware);
+    }
+    /// Finishes typing, applying what was typed.
+    fn commit(
+        &mut self,
+        input: Input,
+        hardware: &Hardware,
+        buffers: &Buffers,
+    ) -> Result<(), String> {
+        match input {
+            Input::Name(name) => {
+                let name = name.trim();
+                if name.is_empty() {
+                    return Err("The protocol needs a name.".into());
+                }
+                self.stored.name = name.into();
+            }
+            Input::Field(field, text) => {
+                let step = self
+                    .stored
+                    .protocol
+                    .steps
+                    .get_mut(self.step)
+                    .ok_or("There's no step to change.")?;
+                set(step, field, &text, buffers)?;
+            }
+        }
+        self.changed(hardware);
+        Ok(())
+    }
+}
+
+/// The protocol library screen.
+#[derive(Debug)]
+pub(super) struct Browser {
+    library: Library,
+    hardware: Hardware,
+    buffers: Buffers,
+    protocols: Vec<StoredProtocol>,
+    /// The chosen protocol.
+    selected: usize,
+    editor: Option<Editor>,
+    /// The outcome of the last thing done, for the operator.
+    message: Option<(Level, String)>,
+}
+
+impl Browser {
+    /// Opens the given library, reading the protocols in it.
+    pub fn new(library: Library, hardware: Hardware, buffers: Buffers) -> Self {
+        let mut browser = Self {
+            library,
+            hardware,
+            buffers,
+            protocols: vec![],
+            selected: 0,
+            editor: None,
+            message: None,
+        };
+        browser.reload();
+        browser
+    }
+    /// Reads the protocols in the library again.
+    fn reload(&mut self) {
+        match self.library.list() {
+            Ok(protocols) => self.protocols = protocols,
+            Err(err) => {
+                self.message = Some((Level::Error, format!("Couldn't read the library: {}", err)))
+            }
+        }
+        self.selected = self.selected.min(self.protocols.len().saturating_sub(1));
+    }
+    /// Responds to the given key.
+    pub fn key(&mut self, key: KeyCode) -> Outcome {
+        if self.editor.is_some() {
+            return self.edit(key);
+        }
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') => return Outcome::Close,
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected = (self.selected + 1).min(self.protocols.len().saturating_sub(1))
+            }
+            KeyCode::Enter => {
+                if let Some(stored) = self.protocols.get(self.selected) {
+                    self.editor = Some(Editor::new(stored.clone(), &self.hardware));
+                    self.message = None;
+                }
+            }
+            KeyCode::Char('n') => {
+                let stored = StoredProtocol::new(
+                    Uuid::new_v4(),
+                    "New protocol".into(),
+                    Protocol {
+                        steps: vec![
+                            Step::Perfuse(0, Some(Duration::from_secs(3600))),
+                            Step::Perfuse(1, None),
+                        ],
+                    },
+                );
+                let mut editor = Editor::new(stored, &self.hardware);
+                editor.modified = true;
+                self.editor = Some(editor);
+                self.message = None;
+            }
+            KeyCode::Char('g') => {
+                if let Some(stored) = self.protocols.get(self.selected).cloned() {
+                    return self.launch(&stored.name, &stored.protocol);
+                }
+            }
+            _ => {}
+        }
+        Outcome::Stay
+    }
+    /// Runs the given protocol, if it passes its checks.
+    fn launch(&mut self, name: &str, protocol: &Protocol) -> Outcome {
+        let validation = protocol.check(&self.hardware);
+        match validation.errors.first() {
+            Some(issue) => {
+                self.message = Some((
+                    Level::Error,
+                    format!("{} can't be run: {}", name, explain(issue)),
+                ));
+                Outcome::Stay
+            }
+            None => Outcome::Launch(protocol.clone()),
+        }
+    }
+    /// Responds to the given key while a protocol is being edited.
+    fn edit(&mut self, key: KeyCode) -> Outcome {
+        let hardware = &self.hardware;
+        let buffers = &self.buffers;
+        let editor = match &mut self.editor {
+            Some(editor) => editor,
+            None => return Outcome::Stay,
+        };
+        if let Some(input) = &mut editor.input {
+            let text = match input {
+                Input::Field(_, text) | Input::Name(text) => text,
+            };
+            match key {
+                KeyCode::Char(c) => text.push(c),
+                KeyCode::Backspace => {
+                    text.pop();
+                }
+                KeyCode::Esc => editor.input = None,
+                KeyCode::Enter => {
+                    let input = editor.input.take().expect("Input is being typed");
+                    if let Err(err) = editor.commit(input.clone(), hardware, buffers) {
+                        self.message = Some((Level::Error, err));
+                        editor.input = Some(input);
+                    } else {
+                        self.message = None;
+                    }
+                }
+                _ => {}
+            }
+            return Outcome::Stay;
+        }
+        let count = editor.steps().len();
+        match key {
+            KeyCode::Esc => {
+                if editor.modified && !editor.warned {
+                    editor.warned = true;
+                    self.message = Some((
+                        Level::Warn,
+                        "There are unsaved changes; press escape again to discard them.".into(),
+                    ));
+                } else {
+                    self.editor = None;
+                    self.message = None;
+                }
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                editor.step = editor.step.saturating_sub(1);
+                editor.field = 0;
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                editor.step = (editor.step + 1).min(count.saturating_sub(1));
+                editor.field = 0;
+            }
+            KeyCode::Left | KeyCode::Char('h') => editor.field = editor.field.saturating_sub(1),
+            KeyCode::Right | KeyCode::Char('l') | KeyCode::Tab => {
+                let last = editor
+                    .steps()
+                    .get(editor.step)
+                    .map_or(0, |step| fields(step).len().saturating_sub(1));
+                editor.field = (editor.field + 1).min(last);
+            }
+            KeyCode::Enter => {
+                if let Some(field) = editor.field() {
+                    let current = value(&editor.steps()[editor.step], field, buffers);
+                    editor.input = Some(Input::Field(field, current));
+                }
+            }
+            KeyCode::Char('m') => editor.input = Some(Input::Name(editor.stored.name.clone())),
+            KeyCode::Char('i') => {
+                if let Some(step) = editor.steps().get(editor.step).cloned() {
+                    editor.stored.protocol.steps.insert(editor.step + 1, step);
+                    editor.step += 1;
+                    editor.changed(hardware);
+                }
+            }
+            KeyCode::Char('d') if editor.step < count => {
+                editor.stored.protocol.steps.remove(editor.step);
+                editor.step = editor.step.min(count.saturating_sub(2));
+                editor.field = 0;
+                editor.changed(hardware);
+            }
+            KeyCode::Char('w') => match self.library.save(&editor.stored) {
+                Ok(()) => {
+                    editor.modified = false;
+                    editor.warned = false;
+                    self.message = Some((Level::Info, format!("Saved {}.", editor.stored.name)));
+                    let id = editor.stored.id;
+                    self.reload();
+                    if let Some(index) = self.protocols.iter().position(|stored| stored.id == id) {
+                        self.selected = index;
+                    }
+                }
+                Err(err) => self.message = Some((Level::Error, format!("Couldn't save: {}", err))),
+            },
+            KeyCode::Char('g') => {
+                let (name, protocol) = (editor.stored.name.clone(), editor.stored.protocol.clone());
+                let outcome = self.launch(&name, &protocol);
+                if let Outcome::Launch(_) = outcome {
+                    self.editor = None;
+                }
+                return outcome;
+            }
+            _ => {}
+        }
+        Outcome::Stay
+    }
+    /// The keys available right now.
+    pub fn help(&self) -> &'static str {
+        match &self.editor {
+            Some(editor) if editor.input.is_some() => "enter apply · esc cancel",
+            Some(_) => {
+                "↑↓ step · ←→ field · enter change · i duplicate · d delete · m rename · w save · g run · esc back"
+            }
+            None => "↑↓ choose · enter edit · n new · g run · esc back",
+        }
+    }
+    /// Draws the library in the given area.
+    pub fn render(&self, frame: &mut Frame, area: Rect) {
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
+            .split(area);
+        let items = self
+            .protocols
+            .iter()
+            .enumerate()
+            .map(|(index, stored)| {
+                let style = if index == self.selected {
+                    Style::default()
+                        .fg(Color::Cyan)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                ListItem::new(stored.name.as_str()).style(style)
+            })
+            .collect::<Vec<_>>();
+        let list = List::new(items).block(Block::default().borders(Borders::ALL).title("Library"));
+        frame.render_widget(list, columns[0]);
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(5), Constraint::Length(8)])
+            .split(columns[1]);
+        match &self.editor {
+            Some(editor) => self.render_editor(editor, frame, rows[0], rows[1]),
+            None => match self.protocols.get(self.selected) {
+                Some(stored) => {
+                    let steps = stored
+                        .protocol
+                        .steps
+                        .iter()
+                        .enumerate()
+                        .map(|(index, step)| {
+                            ListItem::new(format!(
+                                "{:>3}. {}",
+                                index + 1,
+                                describe(step, &self.buffers)
+                            ))
+                        })
+                        .collect::<Vec<_>>();
+                    let list = List::new(steps).block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .title(stored.name.as_str()),
+                    );
+                    frame.render_widget(list, rows[0]);
+                    let validation = stored.protocol.check(&self.hardware);
+                    self.render_details(frame, rows[1], None, &validation);
+                }
+                None => {
+                    let empty = Paragraph::new("No protocols are stored; press n to create one.")
+                        .block(Block::default().borders(Borders::ALL));
+                    frame.render_widget(empty, columns[1]);
+                }
+            },
+        }
+    }
+    fn render_editor(&self, editor: &Editor, frame: &mut Frame, steps: Rect, details: Rect) {
+        let flagged = |step| {
+            let concerns = |issue: &ProtocolIssue| step_of(issue) == Some(step);
+            if editor.validation.errors.iter().any(concerns) {
+                Some(Color::Red)
+            } else if editor.validation.warnings.iter().any(concerns) {
+                Some(Color::Yellow)
+            } else {
+                None
+            }
+        };
+        let items = editor
+            .steps()
+            .iter()
+            .enumerate()
+            .map(|(index, step)| {
+                let mut style = Style::default();
+                if let Some(color) = flagged(index) {
+                    style = style.fg(color);
+                }
+                if index == editor.step {
+                    style = style.add_modifier(Modifier::BOLD | Modifier::REVERSED);
+                }
+                ListItem::new(format!(
+                    "{:>3}. {}",
+                    index + 1,
+                    describe(step, &self.buffers)
+                ))
+                .style(style)
+            })
+            .collect::<Vec<_>>();
+        let title = match &editor.input {
+            Some(Input::Name(name)) => format!("Name: {}▏", name),
+            _ if editor.modified => format!("Editing {} (modified)", editor.stored.name),
+            _ => format!("Editing {}", editor.stored.name),
+        };
+        let list = List::new(items).block(Block::default().borders(Borders::ALL).title(title));
+        frame.render_widget(list, steps);
+        let values = editor.steps().get(editor.step).map(|step| {
+            let mut spans = vec![];
+            for (index, &field) in fields(step).iter().enumerate() {
+                let text = match &editor.input {
+                    Some(Input::Field(typing, text)) if *typing == field => format!("{}▏", text),
+                    _ => {
+                        let value = value(step, field, &self.buffers);
+                        if value.is_empty() {
+                            "until continued".into()
+                        } else {
+                            value
+                        }
+                    }
+                };
+                let style = if index == editor.field {
+                    Style::default().add_modifier(Modifier::REVERSED)
+                } else {
+                    Style::default()
+                };
+                spans.push(Span::raw(format!("{}: ", field.name())));
+                spans.push(Span::styled(text, style));
+                spans.push(Span::raw("   "));
+            }
+            if spans.is_empty() {
+                spans.push(Span::raw("This step has nothing to change."));
+            }
+            Line::from(spans)
+        });
+        self.render_details(frame, details, values, &editor.validation);
+    }
+    /// Draws the given fields (if any), the last message, and the outcome of checking a protocol.
+    fn render_details(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        fields: Option<Line>,
+        validation: &Validation,
+    ) {
+        let mut lines = fields.into_iter().collect::<Vec<_>>();
+        if let Some((level, message)) = &self.message {
+            lines.push(Line::styled(
+                message.as_str(),
+                Style::default().fg(color(*level)),
+            ));
+        }
+        if validation.errors.is_empty() && validation.warnings.is_empty() {
+            lines.push(Line::styled(
+                "The protocol is ready to run.",
+                Style::default().fg(Color::Green),
+            ));
+        }
+        let issues = validation
+            .errors
+            .iter()
+            .map(|issue| (issue, Level::Error))
+            .chain(validation.warnings.iter().map(|issue| (issue, Level::Warn)));
+        for (issue, level) in issues {
+            lines.push(Line::styled(
+                explain(issue),
+                Style::default().fg(color(level)),
+            ));
+        }
+        let details =
+            Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("Details"));
+        frame.render_widget(details, area);
+    }
+}
+
+/// The color of messages of the given level.
+pub(super) fn color(level: Level) -> Color {
+    match level {
+        Level::Error => Color::Red,
+        Level::Warn => Color::Yellow,
+        Level::Info => Color::Green,
+        _ => Color::Reset,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BufferConfig;
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration(""), Ok(None));
+        assert_eq!(
+            parse_duration("1h 30m"),
+            Ok(Some(Duration::from_secs(5400)))
+        );
+        assert_eq!(parse_duration("2m5s"), Ok(Some(Duration::from_secs(125))));
+        assert!(parse_duration("90").is_err());
+        assert!(parse_duration("1d").is_err());
+        assert_eq!(exact_duration(Duration::from_secs(5405)), "1h 30m 5s");
+        assert_eq!(
+            parse_duration(&exact_duration(Duration::from_secs(5405))),
+            Ok(Some(Duration::from_secs(5405)))
+        );
+    }
+
+    #[test]
+    fn edits_steps() {
+        let hardware = Hardware {
+            positions: vec![vec![]; 3],
+            pumps: 1,
+            thermostat: false,
+            ..Hardware::default()
+        };
+        let stored = StoredProtocol::new(
+            Uuid::new_v4(),
+            "Rat heart".into(),
+            Protocol {
+                steps: vec![
+                    Step::Perfuse(0, Some(Duration::from_secs(60))),
+                    Step::Perfuse(1, None),
+                ],
+            },
+        );
+        let buffers = Buffers::new(vec![BufferConfig::new(2, "PBS")]);
+        let mut editor = Editor::new(stored, &hardware);
+        assert!(editor.validation.is_ok());
+        editor
+            .commit(
+                Input::Field(Field::Buffer, "pbs".into()),
+                &hardware,
+                &buffers,
+            )
+            .unwrap();
+        assert_eq!(
+            editor.steps()[0],
+            Step::Perfuse(2, Some(Duration::from_secs(60)))
+        );
+        assert_eq!(value(&editor.steps()[0], Field::Buffer, &buffers), "PBS");
+        editor
+            .commit(Input::Field(Field::Buffer, "5".into()), &hardware, &buffers)
+            .unwrap();
+        assert!(editor.modified);
+        assert_eq!(
+            explain(&editor.validation.errors[0]),
+            "Step 1: buffer 5 has no valve"
+        );
+        assert!(editor
+            .commit(
+                Input::Field(Field::Duration, "soon".into()),
+                &hardware,
+                &buffers
+            )
+            .is_err());
+        editor.step = 1;
+        editor
+            .commit(
+                Input::Field(Field::Duration, "10m".into()),
+                &hardware,
+                &buffers,
+            )
+            .unwrap();
+        assert!(editor.validation.errors.contains(&ProtocolIssue::Structure(
+            ValidateProtocolError::Last(Step::Perfuse(1, Some(Duration::from_secs(600))))
+        )));
+    }
+}
@@ -0,0 +1,369 @@
+//! The dashboard's catalog of protocol files, from which any of them can be run by name.
+//!
+//! `↑`/`↓` choose a protocol, whose steps are shown as they'd be run on this system, `g` runs it,
+//! and escape returns to the dashboard. If the protocol takes parameters, tab chooses one and enter
+//! gives it a value (which lasts until another protocol is chosen). The coordinator keeps the
+//! catalog up to date, so files saved while the screen is open show up right away.
+
+use crossterm::event::KeyCode;
+use log::Level;
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, List, ListItem, Paragraph},
+    Frame,
+};
+
+use super::editor::{color, describe, explain, Outcome};
+use crate::{Bindings, Buffers, Catalog, CatalogEntry, Hardware, ParameterValue};
+
+/// The protocol files screen.
+#[derive(Debug)]
+pub(super) struct Files {
+    catalog: Catalog,
+    hardware: Hardware,
+    buffers: Buffers,
+    /// The chosen protocol.
+    selected: usize,
+    /// The values given for the chosen protocol's parameters.
+    values: Bindings,
+    /// The chosen parameter, by position.
+    parameter: usize,
+    /// The value being typed for the chosen parameter, if one is.
+    typing: Option<String>,
+    /// The outcome of the last thing done, for the operator.
+    message: Option<(Level, String)>,
+}
+
+impl Files {
+    /// Shows the given catalog, converting its protocols for the given hardware.
+    pub fn new(catalog: Catalog, hardware: Hardware, buffers: Buffers) -> Self {
+        Self {
+            catalog,
+            hardware,
+            buffers,
+            selected: 0,
+            values: Bindings::new(),
+            parameter: 0,
+            typing: None,
+            message: None,
+        }
+    }
+    /// The chosen protocol, if there are any.
+    fn chosen(&self, entries: &[CatalogEntry]) -> Option<CatalogEntry> {
+        entries
+            .get(self.selected.min(entries.len().saturating_sub(1)))
+            .cloned()
+    }
+    /// Chooses the protocol in the given position, forgetting any values given for parameters.
+    fn choose(&mut self, selected: usize) {
+        if selected != self.selected {
+            self.values.clear();
+            self.parameter = 0;
+        }
+        self.selected = selected;
+    }
+    /// Responds to the given key while a value is being typed for the given protocol's chosen
+    /// parameter.
+    fn type_value(&mut self, key: KeyCode, entry: Option<CatalogEntry>) {
+        let text = match &mut self.typing {
+            Some(text) => text,
+            None => return,
+        };
+        match key {
+            KeyCode::Char(c) => text.push(c),
+            KeyCode::Backspace => {
+                text.pop();
+            }
+            KeyCode::Esc => self.typing = None,
+            KeyCode::Enter => {
+                let text = self.typing.take().unwrap_or_default();
+                let parameters = entry.map(|entry| entry.file.parameters).unwrap_or_default();
+                let (name, parameter) = match parameters.iter().nth(self.parameter) {
+                    Some(chosen) => chosen,
+                    None => return,
+                };
+                if text.trim().is_empty() {
+                    self.values.remove(name);
+                    self.message = None;
+                    return;
+                }
+                let value = ParameterValue::Text(text.trim().into());
+                if parameter.accepts(&value) {
+                    self.values.insert(name.clone(), value);
+                    self.message = None;
+                } else {
+                    let message = format!("{} should be {}.", name, parameter.expected());
+                    self.message = Some((Level::Error, message));
+                    self.typing = Some(text);
+                }
+            }
+            _ => {}
+        }
+    }
+    /// Responds to the given key.
+    pub fn key(&mut self, key: KeyCode) -> Outcome {
+        let entries = self.catalog.list();
+        if self.typing.is_some() {
+            let entry = self.chosen(&entries);
+            self.type_value(key, entry);
+            return Outcome::Stay;
+        }
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') => return Outcome::Close,
+            KeyCode::Up | KeyCode::Char('k') => self.choose(
+                self.selected
+                    .min(entries.len().saturating_sub(1))
+                    .saturating_sub(1),
+            ),
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.choose((self.selected + 1).min(entries.len().saturating_sub(1)))
+            }
+            KeyCode::Tab => {
+                let count = self
+                    .chosen(&entries)
+                    .map_or(0, |entry| entry.file.parameters.len());
+                self.parameter = (self.parameter + 1) % count.max(1);
+            }
+            KeyCode::Enter => {
+                let entry = self.chosen(&entries);
+                let parameters = entry.map(|entry| entry.file.parameters).unwrap_or_default();
+                if let Some((name, _)) = parameters.iter().nth(self.parameter) {
+                    let current = self.values.get(name).map(ToString::to_string);
+                    self.typing = Some(current.unwrap_or_default());
+                }
+            }
+            KeyCode::Char('g') => {
+                if let Some(entry) = self.chosen(&entries) {
+                    let protocol = match entry.file.resolve_with(&self.hardware, &self.values) {
+                        Ok(protocol) => protocol,
+                        Err(err) => {
+                            let message = format!("{} can't be run: {}", entry.name, err);
+                            self.message = Some((Level::Error, message));
+                            return Outcome::Stay;
+                        }
+                    };
+                    let validation = protocol.check(&self.hardware);
+                    if let Some(issue) = validation.errors.first() {
+                        let message = format!("{} can't be run: {}", entry.name, explain(issue));
+                        self.message = Some((Level::Error, message));
+                        return Outcome::Stay;
+                    }
+                    return Outcome::Launch(protocol);
+                }
+            }
+            _ => {}
+        }
+        Outcome::Stay
+    }
+    /// The keys available right now.
+    pub fn help(&self) -> &'static str {
+        if self.typing.is_some() {
+            "enter set · esc cancel"
+        } else {
+            "↑↓ choose · tab parameter · enter set · g run · esc back"
+        }
+    }
+    /// Draws the catalog in the given area.
+    pub fn render(&self, frame: &mut Frame, area: Rect) {
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
+            .split(area);
+        let entries = self.catalog.list();
+        let chosen = self.chosen(&entries);
+        let items = entries
+            .iter()
+            .map(|entry| {
+                let style = if chosen.as_ref().map(|chosen| &chosen.name) == Some(&entry.name) {
+                    Style::default()
+                        .fg(Color::Cyan)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                ListItem::new(entry.name.as_str()).style(style)
+            })
+            .collect::<Vec<_>>();
+        let list = List::new(items).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Protocol files"),
+        );
+        frame.render_widget(list, columns[0]);
+        let entry = match chosen {
+            Some(entry) => entry,
+            None => {
+                let empty = Paragraph::new(format!(
+                    "There are no protocol files in {}.",
+                    self.catalog.dir().display()
+                ))
+                .block(Block::default().borders(Borders::ALL));
+                frame.render_widget(empty, columns[1]);
+                return;
+            }
+        };
+        let parameters = entry.file.parameters.len() as u16;
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(5),
+                Constraint::Length(if parameters > 0 { parameters + 2 } else { 0 }),
+                Constraint::Length(8),
+            ])
+            .split(columns[1]);
+        let title = entry.file.name.as_deref().unwrap_or(&entry.name);
+        let mut details = vec![];
+        if let Some(description) = &entry.file.description {
+            details.push(Line::from(description.as_str()));
+        }
+        if let Some((level, message)) = &self.message {
+            details.push(Line::styled(
+                message.as_str(),
+                Style::default().fg(color(*level)),
+            ));
+        }
+        let parameters = entry
+            .file
+            .parameters
+            .iter()
+            .enumerate()
+            .map(|(index, (name, parameter))| {
+                let chosen = index == self.parameter;
+                let value = match (&self.typing, self.values.get(name), &parameter.default) {
+                    (Some(text), _, _) if chosen => format!("{}▏", text),
+                    (_, Some(value), _) => value.to_string(),
+                    (_, None, Some(default)) => format!("{} (default)", default),
+                    (_, None, None) => "(required)".into(),
+                };
+                let description = parameter.description.as_deref().unwrap_or_default();
+                let style = if chosen {
+                    Style::default().fg(Color::Cyan)
+                } else {
+                    Style::default()
+                };
+                ListItem::new(format!("{} = {}  {}", name, value, description)).style(style)
+            })
+            .collect::<Vec<_>>();
+        let parameters =
+            List::new(parameters).block(Block::default().borders(Borders::ALL).title("Parameters"));
+        frame.render_widget(parameters, rows[1]);
+        match entry.file.resolve_with(&self.hardware, &self.values) {
+            Ok(protocol) => {
+                let steps = protocol
+                    .steps
+                    .iter()
+                    .enumerate()
+                    .map(|(index, step)| {
+                        ListItem::new(format!(
+                            "{:>3}. {}",
+                            index + 1,
+                            describe(step, &self.buffers)
+                        ))
+                    })
+                    .collect::<Vec<_>>();
+                let list =
+                    List::new(steps).block(Block::default().borders(Borders::ALL).title(title));
+                frame.render_widget(list, rows[0]);
+                let validation = protocol.check(&self.hardware);
+                if validation.errors.is_empty() && validation.warnings.is_empty() {
+                    details.push(Line::styled(
+                        "The protocol is ready to run.",
+                        Style::default().fg(Color::Green),
+                    ));
+                }
+                let issues = validation
+                    .errors
+                    .iter()
+                    .map(|issue| (issue, Level::Error))
+                    .chain(validation.warnings.iter().map(|issue| (issue, Level::Warn)));
+                for (issue, level) in issues {
+                    details.push(Line::styled(
+                        explain(issue),
+                        Style::default().fg(color(level)),
+                    ));
+                }
+            }
+            Err(err) => {
+                let path = Paragraph::new(entry.path.display().to_string())
+                    .block(Block::default().borders(Borders::ALL).title(title));
+                frame.render_widget(path, rows[0]);
+                details.push(Line::styled(
+                    err.to_string(),
+                    Style::default().fg(Color::Red),
+                ));
+            }
+        }
+        let details =
+            Paragraph::new(details).block(Block::default().borders(Borders::ALL).title("Details"));
+        frame.render_widget(details, rows[2]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BufferConfig, Protocol, Step};
+    use std::{fs, time::Duration};
+    use uuid::Uuid;
+
+    #[test]
+    fn launches_files() {
+        let dir = std::env::temp_dir().join(format!("deoxy-files-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("rinse.toml"), "[[steps]]\nbuffer = \"PBS\"\n").unwrap();
+        fs::write(dir.join("wash.toml"), "[[steps]]\nbuffer = \"SDS\"\n").unwrap();
+        let hardware = Hardware {
+            positions: vec![vec![]; 3],
+            pumps: 1,
+            buffers: vec![("PBS".to_string(), 1)].into_iter().collect(),
+            ..Hardware::default()
+        };
+        let buffers = Buffers::new(vec![BufferConfig::new(1, "PBS")]);
+        let mut files = Files::new(Catalog::open(&dir).unwrap(), hardware, buffers);
+        assert_eq!(
+            files.key(KeyCode::Char('g')),
+            Outcome::Launch(Protocol::with_step(Step::Perfuse(1, None)))
+        );
+        files.key(KeyCode::Down);
+        assert_eq!(files.key(KeyCode::Char('g')), Outcome::Stay);
+        let (level, message) = files.message.clone().unwrap();
+        assert_eq!(level, Level::Error);
+        assert!(message.starts_with("wash can't be run"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn binds_parameters() {
+        let dir = std::env::temp_dir().join(format!("deoxy-files-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = "[parameters.soak]\nkind = \"duration\"\nmax = 600\n\n\
+                    [[steps]]\nbuffer = 1\nduration = \"$soak\"\n";
+        fs::write(dir.join("soak.toml"), file).unwrap();
+        let hardware = Hardware {
+            positions: vec![vec![]; 2],
+            pumps: 1,
+            ..Hardware::default()
+        };
+        let mut files = Files::new(Catalog::open(&dir).unwrap(), hardware, Buffers::default());
+        assert_eq!(files.key(KeyCode::Char('g')), Outcome::Stay);
+        files.key(KeyCode::Enter);
+        for c in "900".chars() {
+            files.key(KeyCode::Char(c));
+        }
+        files.key(KeyCode::Enter);
+        assert_eq!(files.message.clone().unwrap().0, Level::Error);
+        files.key(KeyCode::Backspace);
+        files.key(KeyCode::Backspace);
+        files.key(KeyCode::Enter);
+        assert_eq!(
+            files.key(KeyCode::Char('g')),
+            Outcome::Launch(Protocol::with_step(Step::Perfuse(
+                1,
+                Some(Duration::from_secs(9))
+            )))
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
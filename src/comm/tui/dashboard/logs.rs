@@ -0,0 +1,278 @@
+//! Capturing log output for the dashboard, which shows it in a scrollable, filterable pane.
+//!
+//! Once captured (see [`Dashboard::capture_logs`]), the most recent log records are kept in memory
+//! instead of being printed (which would garble the dashboard). In the log pane, `↑`/`↓` and
+//! page up/down scroll (and end returns to the newest records), `e`, `w`, `i`, `d`, and `t` show
+//! records of at least that level (error, warning, info, debug, or trace), `/` filters by module
+//! (matching any part of the module path), and escape returns to the dashboard.
+//!
+//! [`Dashboard::capture_logs`]: ../struct.Dashboard.html#method.capture_logs
+
+use std::{
+    collections::VecDeque,
+    sync::{Mutex, MutexGuard},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crossterm::event::KeyCode;
+use lazy_static::lazy_static;
+use log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+
+/// How many log records are kept.
+const CAPACITY: usize = 2000;
+
+/// A captured log record.
+#[derive(Clone, Debug)]
+struct Entry {
+    level: Level,
+    /// The module the record came from.
+    target: String,
+    message: String,
+    /// When the record was logged, in seconds since the Unix epoch.
+    time: u64,
+}
+
+lazy_static! {
+    /// The most recent log records, oldest first.
+    static ref ENTRIES: Mutex<VecDeque<Entry>> = Mutex::new(VecDeque::with_capacity(CAPACITY));
+}
+
+fn entries() -> MutexGuard<'static, VecDeque<Entry>> {
+    // A panic while holding the lock can't leave the records in a bad state.
+    ENTRIES
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Keeps log records for the dashboard.
+#[derive(Clone, Copy, Debug)]
+struct Capture {
+    level: LevelFilter,
+}
+
+impl Log for Capture {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        let entry = Entry {
+            level: record.level(),
+            target: record.target().into(),
+            message: record.args().to_string(),
+            time,
+        };
+        let mut entries = entries();
+        if entries.len() == CAPACITY {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+    fn flush(&self) {}
+}
+
+/// Captures log records up to the given level (see the module documentation).
+pub(super) fn capture(level: LevelFilter) -> Result<(), SetLoggerError> {
+    log::set_boxed_logger(Box::new(Capture { level }))?;
+    log::set_max_level(level);
+    Ok(())
+}
+
+/// The color of records of the given level.
+fn color(level: Level) -> Color {
+    match level {
+        Level::Error => Color::Red,
+        Level::Warn => Color::Yellow,
+        Level::Info => Color::Reset,
+        Level::Debug | Level::Trace => Color::DarkGray,
+    }
+}
+
+/// The log pane.
+#[derive(Debug)]
+pub(super) struct Viewer {
+    /// The least severe level shown.
+    level: Level,
+    /// The part of the module path records must match, if any.
+    module: Option<String>,
+    /// The module filter being typed, if any.
+    typing: Option<String>,
+    /// How many (matching) records the view is scrolled back from the newest.
+    offset: usize,
+}
+
+impl Viewer {
+    /// Creates a pane showing records of at least info level, from every module.
+    pub fn new() -> Self {
+        Self {
+            level: Level::Info,
+            module: None,
+            typing: None,
+            offset: 0,
+        }
+    }
+    fn matches(&self, entry: &Entry) -> bool {
+        entry.level <= self.level
+            && self
+                .module
+                .as_ref()
+                .map_or(true, |module| entry.target.contains(module.as_str()))
+    }
+    /// How many records are shown (given enough room).
+    fn count(&self) -> usize {
+        entries().iter().filter(|entry| self.matches(entry)).count()
+    }
+    /// Responds to the given key, returning whether the pane should close.
+    pub fn key(&mut self, key: KeyCode) -> bool {
+        if let Some(typing) = &mut self.typing {
+            match key {
+                KeyCode::Char(c) => typing.push(c),
+                KeyCode::Backspace => {
+                    typing.pop();
+                }
+                KeyCode::Enter => {
+                    let module = self.typing.take().unwrap_or_default();
+                    self.module = Some(module).filter(|module| !module.is_empty());
+                    self.offset = 0;
+                }
+                KeyCode::Esc => self.typing = None,
+                _ => {}
+            }
+            return false;
+        }
+        let level = match key {
+            KeyCode::Esc | KeyCode::Char('q') => return true,
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.offset = (self.offset + 1).min(self.count());
+                None
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.offset = self.offset.saturating_sub(1);
+                None
+            }
+            KeyCode::PageUp => {
+                self.offset = (self.offset + 10).min(self.count());
+                None
+            }
+            KeyCode::PageDown => {
+                self.offset = self.offset.saturating_sub(10);
+                None
+            }
+            KeyCode::End => {
+                self.offset = 0;
+                None
+            }
+            KeyCode::Char('/') => {
+                self.typing = Some(self.module.clone().unwrap_or_default());
+                None
+            }
+            KeyCode::Char('e') => Some(Level::Error),
+            KeyCode::Char('w') => Some(Level::Warn),
+            KeyCode::Char('i') => Some(Level::Info),
+            KeyCode::Char('d') => Some(Level::Debug),
+            KeyCode::Char('t') => Some(Level::Trace),
+            _ => None,
+        };
+        if let Some(level) = level {
+            self.level = level;
+            self.offset = 0;
+        }
+        false
+    }
+    /// The keys available right now.
+    pub fn help(&self) -> &'static str {
+        if self.typing.is_some() {
+            "enter filter · esc cancel"
+        } else {
+            "↑↓/pgup/pgdn scroll · end newest · e/w/i/d/t level · / module · esc back"
+        }
+    }
+    /// Draws the log pane in the given area.
+    pub fn render(&self, frame: &mut Frame, area: Rect) {
+        let visible = area.height.saturating_sub(2) as usize;
+        let entries = entries();
+        let matching = entries
+            .iter()
+            .filter(|entry| self.matches(entry))
+            .collect::<Vec<_>>();
+        let offset = self.offset.min(matching.len().saturating_sub(visible));
+        let end = matching.len() - offset;
+        let lines = matching[end.saturating_sub(visible)..end]
+            .iter()
+            .map(|entry| {
+                let secs = entry.time % 86400;
+                Line::from(vec![
+                    Span::styled(
+                        format!(
+                            "{:02}:{:02}:{:02} {:<5} ",
+                            secs / 3600,
+                            secs / 60 % 60,
+                            secs % 60,
+                            entry.level
+                        ),
+                        Style::default().fg(color(entry.level)),
+                    ),
+                    Span::styled(
+                        format!("{} ", entry.target),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    Span::styled(
+                        entry.message.as_str(),
+                        Style::default().fg(color(entry.level)),
+                    ),
+                ])
+            })
+            .collect::<Vec<_>>();
+        let mut title = format!("Logs (UTC) · {} and above", self.level);
+        match (&self.typing, &self.module) {
+            (Some(typing), _) => title.push_str(&format!(" · module: {}▏", typing)),
+            (None, Some(module)) => title.push_str(&format!(" · module: {}", module)),
+            (None, None) => {}
+        }
+        if offset > 0 {
+            title.push_str(&format!(" · {} newer", offset));
+        }
+        let pane = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title));
+        frame.render_widget(pane, area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters() {
+        let entry = |level, target: &str| Entry {
+            level,
+            target: target.into(),
+            message: String::new(),
+            time: 0,
+        };
+        let mut viewer = Viewer::new();
+        assert!(viewer.matches(&entry(Level::Warn, "deoxy::comm")));
+        assert!(!viewer.matches(&entry(Level::Debug, "deoxy::comm")));
+        viewer.key(KeyCode::Char('d'));
+        assert!(viewer.matches(&entry(Level::Debug, "deoxy::comm")));
+        viewer.key(KeyCode::Char('/'));
+        for c in "sensor".chars() {
+            viewer.key(KeyCode::Char(c));
+        }
+        viewer.key(KeyCode::Enter);
+        assert!(!viewer.matches(&entry(Level::Warn, "deoxy::comm")));
+        assert!(viewer.matches(&entry(Level::Warn, "deoxy::sensor::flow")));
+        assert!(viewer.key(KeyCode::Esc));
+    }
+}
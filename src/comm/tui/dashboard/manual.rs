@@ -0,0 +1,287 @@
+//! The dashboard's maintenance screen, for moving valves and running the pump by hand.
+//!
+//! `↑`/`↓` choose a valve, which `o` opens, `c` closes, `s` shuts, and `a` moves to a typed angle;
+//! `f` and `b` run the pump forward and backward for a few seconds (adjusted with `+`/`-`). Every
+//! action must be confirmed with `y` before it's carried out, and nothing can be done while a
+//! protocol is running.
+
+use std::time::Duration;
+
+use crossterm::event::KeyCode;
+use log::Level;
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, List, ListItem, Paragraph},
+    Frame,
+};
+
+use crate::{Jog, MotorId, PumpDirection};
+
+/// The longest the pump can be run from here, in seconds.
+const LONGEST_RUN: u64 = 60;
+
+/// What the dashboard should do after a key is handled.
+#[derive(Debug)]
+pub(super) enum Outcome {
+    /// Keep showing the maintenance screen.
+    Stay,
+    /// Return to the dashboard.
+    Close,
+    /// Carry out the given (confirmed) action.
+    Jog(Jog),
+}
+
+/// The name of the given valve.
+fn valve(motor: MotorId) -> String {
+    match motor {
+        0 => "the waste valve".into(),
+        motor => format!("the valve for buffer {}", motor - 1),
+    }
+}
+
+/// Describes the given action for a person.
+pub(super) fn describe(jog: Jog) -> String {
+    match jog {
+        Jog::Open(motor) => format!("Open {}", valve(motor)),
+        Jog::Close(motor) => format!("Close {}", valve(motor)),
+        Jog::Shut(motor) => format!("Shut {}", valve(motor)),
+        Jog::Angle { motor, angle } => format!("Move {} to {}°", valve(motor), angle),
+        Jog::Pump {
+            direction,
+            duration,
+        } => format!(
+            "Run the pump {} for {}s",
+            match direction {
+                PumpDirection::Forward => "forward",
+                PumpDirection::Backward => "backward",
+            },
+            duration.as_secs()
+        ),
+    }
+}
+
+/// The maintenance screen.
+#[derive(Debug)]
+pub(super) struct Manual {
+    /// How many motors there are (where motor 0 is the waste valve).
+    motors: usize,
+    /// The chosen valve.
+    selected: MotorId,
+    /// How long to run the pump for, in seconds.
+    seconds: u64,
+    /// The angle being typed, if any.
+    angle: Option<String>,
+    /// The action awaiting confirmation, if any.
+    pending: Option<Jog>,
+    /// The outcome of the last action, for the operator.
+    message: Option<(Level, String)>,
+}
+
+impl Manual {
+    /// Creates a maintenance screen for the given number of motors.
+    pub fn new(motors: usize) -> Self {
+        Self {
+            motors,
+            selected: 0,
+            seconds: 5,
+            angle: None,
+            pending: None,
+            message: None,
+        }
+    }
+    /// Notes the outcome of the last action.
+    pub fn report(&mut self, level: Level, message: String) {
+        self.message = Some((level, message));
+    }
+    /// Responds to the given key (where nothing may be done unless idle).
+    pub fn key(&mut self, key: KeyCode, idle: bool) -> Outcome {
+        if let Some(jog) = self.pending.take() {
+            if key == KeyCode::Char('y') && idle {
+                return Outcome::Jog(jog);
+            }
+            self.message = Some((Level::Info, "Cancelled.".into()));
+            return Outcome::Stay;
+        }
+        if let Some(angle) = &mut self.angle {
+            match key {
+                KeyCode::Char(c) if c.is_ascii_digit() => angle.push(c),
+                KeyCode::Backspace => {
+                    angle.pop();
+                }
+                KeyCode::Esc => self.angle = None,
+                KeyCode::Enter => match angle.parse() {
+                    Ok(angle) => {
+                        self.angle = None;
+                        self.request(
+                            Jog::Angle {
+                                motor: self.selected,
+                                angle,
+                            },
+                            idle,
+                        );
+                    }
+                    Err(_) => self.message = Some((Level::Error, "Type an angle.".into())),
+                },
+                _ => {}
+            }
+            return Outcome::Stay;
+        }
+        let duration = Duration::from_secs(self.seconds);
+        let pump = move |direction| Jog::Pump {
+            direction,
+            duration,
+        };
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') => return Outcome::Close,
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected = (self.selected + 1).min(self.motors.saturating_sub(1))
+            }
+            KeyCode::Char('+') => self.seconds = (self.seconds + 1).min(LONGEST_RUN),
+            KeyCode::Char('-') => self.seconds = self.seconds.saturating_sub(1).max(1),
+            KeyCode::Char('o') => self.request(Jog::Open(self.selected), idle),
+            KeyCode::Char('c') => self.request(Jog::Close(self.selected), idle),
+            KeyCode::Char('s') => self.request(Jog::Shut(self.selected), idle),
+            KeyCode::Char('a') if idle => self.angle = Some(String::new()),
+            KeyCode::Char('f') => self.request(pump(PumpDirection::Forward), idle),
+            KeyCode::Char('b') => self.request(pump(PumpDirection::Backward), idle),
+            _ => {}
+        }
+        Outcome::Stay
+    }
+    /// Asks the operator to confirm the given action, if it may be done now.
+    fn request(&mut self, jog: Jog, idle: bool) {
+        if !idle {
+            self.message = Some((
+                Level::Warn,
+                "Manual control is unavailable while a protocol is running.".into(),
+            ));
+        } else if self.motors == 0 && !matches!(jog, Jog::Pump { .. }) {
+            self.message = Some((Level::Warn, "There are no valves.".into()));
+        } else {
+            self.pending = Some(jog);
+        }
+    }
+    /// The keys available right now.
+    pub fn help(&self) -> &'static str {
+        if self.pending.is_some() {
+            "y confirm · any other key cancels"
+        } else if self.angle.is_some() {
+            "enter move · esc cancel"
+        } else {
+            "↑↓ valve · o open · c close · s shut · a angle · f/b pump · +/- run time · esc back"
+        }
+    }
+    /// Draws the maintenance screen in the given area.
+    pub fn render(&self, frame: &mut Frame, area: Rect, idle: bool) {
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(35), Constraint::Percentage(65)])
+            .split(area);
+        let items = (0..self.motors)
+            .map(|motor| {
+                let mut name = valve(motor);
+                if let Some(first) = name.get_mut(0..1) {
+                    first.make_ascii_uppercase();
+                }
+                let style = if motor == self.selected {
+                    Style::default()
+                        .fg(Color::Cyan)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                ListItem::new(name).style(style)
+            })
+            .collect::<Vec<_>>();
+        let list = List::new(items).block(Block::default().borders(Borders::ALL).title("Valves"));
+        frame.render_widget(list, columns[0]);
+        let mut lines = vec![];
+        if idle {
+            lines.push(Line::from(format!(
+                "Pump runs last {}s (+/- to change).",
+                self.seconds
+            )));
+        } else {
+            lines.push(Line::styled(
+                "Manual control is unavailable while a protocol is running.",
+                Style::default().fg(Color::Yellow),
+            ));
+        }
+        if let Some(angle) = &self.angle {
+            lines.push(Line::from(format!(
+                "Move {} to: {}▏°",
+                valve(self.selected),
+                angle
+            )));
+        }
+        if let Some(jog) = self.pending {
+            lines.push(Line::styled(
+                format!("{}? Press y to confirm.", describe(jog)),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ));
+        }
+        if let Some((level, message)) = &self.message {
+            let color = match level {
+                Level::Error => Color::Red,
+                Level::Warn => Color::Yellow,
+                _ => Color::Green,
+            };
+            lines.push(Line::styled(message.as_str(), Style::default().fg(color)));
+        }
+        let details = Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Manual control"),
+        );
+        frame.render_widget(details, columns[1]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn confirms() {
+        let mut manual = Manual::new(3);
+        manual.key(KeyCode::Down, true);
+        assert!(matches!(
+            manual.key(KeyCode::Char('o'), true),
+            Outcome::Stay
+        ));
+        assert!(matches!(
+            manual.key(KeyCode::Char('y'), true),
+            Outcome::Jog(Jog::Open(1))
+        ));
+        manual.key(KeyCode::Char('a'), true);
+        for c in "45".chars() {
+            manual.key(KeyCode::Char(c), true);
+        }
+        manual.key(KeyCode::Enter, true);
+        assert!(matches!(
+            manual.key(KeyCode::Char('n'), true),
+            Outcome::Stay
+        ));
+        assert_eq!(manual.pending.map(describe), None);
+        manual.key(KeyCode::Char('f'), true);
+        assert_eq!(
+            manual.pending.map(describe).unwrap(),
+            "Run the pump forward for 5s"
+        );
+    }
+
+    #[test]
+    fn refuses_while_running() {
+        let mut manual = Manual::new(3);
+        manual.key(KeyCode::Char('s'), false);
+        assert!(manual.pending.is_none());
+        manual.key(KeyCode::Char('s'), true);
+        assert!(matches!(
+            manual.key(KeyCode::Char('y'), false),
+            Outcome::Stay
+        ));
+    }
+}
@@ -1,21 +1,800 @@
-use std::time::Duration;
+use std::{collections::BTreeMap, net::SocketAddr, path::PathBuf, sync::Arc, time::Duration};
+
+use crate::{
+    quantity::{self, FlowRate},
+    Action, Hardware, MotorId, Overrun, PumpId, Sensor,
+};
+
+#[cfg(feature = "schema")]
+use schemars::{schema::RootSchema, JsonSchema};
+
+use uom::si::{
+    f64::{Pressure, Volume},
+    pressure::kilopascal,
+    volume::milliliter,
+};
+
+use crate::{
+    device::Error as DeviceError,
+    motor::{Angles, Feedback},
+    pin::{self, Error as PinError, GpioBackend, InputPin, Pin, Pull},
+    sensor::adc::{self, Adc},
+    stepper::StepperMotor,
+    thermal::{self, TemperatureSensor, Thermostat},
+};
+
+#[cfg(feature = "use_serde")]
+mod load;
+mod validate;
+
+#[cfg(feature = "use_serde")]
+pub use self::load::LoadError;
+pub use self::validate::{ConfigError, Violation};
 
 /// Encodes the system configuration.
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct Config {
-    /// The pump configuration.
-    pub pump: PumpConfig,
+    /// The GPIO backend to use.
+    #[cfg_attr(feature = "use_serde", serde(default))]
+    pub gpio: BackendConfig,
+    /// The name of the instrument, as advertised on the network (see the `mdns` feature).
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub name: Option<String>,
+    /// The pump configurations.
+    ///
+    /// A single `[pump]` table is also accepted (though the JSON Schema only describes `pumps`, so
+    /// it isn't required there).
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(alias = "pump", deserialize_with = "one_or_many")
+    )]
+    #[cfg_attr(feature = "schema", schemars(with = "OneOrMany<PumpConfig>", default))]
+    pub pumps: Vec<PumpConfig>,
+    /// The pump to use for draining, if different from the pump in use.
+    ///
+    /// This allows rigs with separate supply and waste pumps.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(
+            default,
+            rename = "drain-pump",
+            skip_serializing_if = "Option::is_none"
+        )
+    )]
+    pub drain_pump: Option<PumpId>,
     /// The motor configurations.
-    pub motors: Vec<MotorConfig>,
-    /// The administrative users of the machine.
+    pub motors: Vec<ValveConfig>,
+    /// The buffers supplied through the valves, by which they're referred to (instead of by the
+    /// valves' indices).
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub buffers: Vec<BufferConfig>,
+    /// Any further manifolds, each running its own protocols independently of the others.
+    ///
+    /// The pumps and motors configured above make up the main manifold (named `main`), which also
+    /// has the shared peripherals (the sensors, thermal control, and emergency stop). Pressing the
+    /// emergency stop halts every manifold.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub manifolds: Vec<ManifoldConfig>,
+    /// The path to the motor calibration file, if any.
+    ///
+    /// Calibrations found interactively are saved to (and later loaded from) this file.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub calibration: Option<PathBuf>,
+    /// The path to the journal file, if any.
+    ///
+    /// The state of the running program is kept in this file so that a run interrupted by a crash
+    /// or power loss can be resumed (or safely aborted) on restart.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub journal: Option<PathBuf>,
+    /// The path to the run history database, if runs should be recorded.
+    ///
+    /// This requires the `history` feature.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub history: Option<PathBuf>,
+    /// The directory in which stored protocols are kept, if any.
+    ///
+    /// This requires the `use_serde` feature.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub library: Option<PathBuf>,
+    /// The directory of protocol files to catalog (see [`Catalog`]), if any.
+    ///
+    /// This requires the `use_serde` feature.
+    ///
+    /// [`Catalog`]: struct.Catalog.html
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub protocols: Option<PathBuf>,
+    /// The event log configuration, if transitions and faults should be logged.
+    ///
+    /// This requires the `use_serde` feature.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, rename = "event-log", skip_serializing_if = "Option::is_none")
+    )]
+    pub event_log: Option<EventLogConfig>,
+    /// The MQTT broker to publish updates to (and take commands from), if any.
+    ///
+    /// This requires the `mqtt` feature.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub mqtt: Option<MqttConfig>,
+    /// The webhooks notified of protocol events.
+    ///
+    /// This requires the `webhooks` feature.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub webhooks: Vec<WebhookConfig>,
+    /// The gRPC control interface, if it should be served.
+    ///
+    /// If any [`tokens`](#structfield.tokens) are configured, calls must bear one. This requires
+    /// the `grpc` feature.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub grpc: Option<GrpcConfig>,
+    /// The Modbus TCP server exposing the coordinator's state, if it should be served.
+    ///
+    /// This requires the `modbus` feature.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub modbus: Option<ModbusConfig>,
+    /// The SMTP server to send mail through (instead of the local `sendmail`), if any.
+    ///
+    /// This requires the `smtp` feature.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub mail: Option<MailConfig>,
+    /// The directory holding templates for notification emails, if the built-in ones should be
+    /// overridden (see the `mail::template` module).
+    ///
+    /// This requires the `templates` feature.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(
+            default,
+            rename = "mail-templates",
+            skip_serializing_if = "Option::is_none"
+        )
+    )]
+    pub mail_templates: Option<PathBuf>,
+    /// What's attached to the notifications sent when a run faults.
+    #[cfg_attr(feature = "use_serde", serde(default, rename = "fault-report"))]
+    pub fault_report: FaultReportConfig,
+    /// The administrative users of the machine, who are mailed about the events they're subscribed
+    /// to.
+    ///
+    /// Each may be given as just an address (subscribing to everything) or as a table with the
+    /// address and the events of interest.
+    #[cfg_attr(feature = "use_serde", serde(default, deserialize_with = "recipients"))]
+    #[cfg_attr(feature = "schema", schemars(with = "Vec<RecipientEntry>"))]
+    pub admins: Vec<Recipient>,
+    /// The Slack and Discord channels notified of events through incoming webhooks.
+    ///
+    /// This requires the `chat` feature.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub chat: Vec<ChatConfig>,
+    /// The Twilio account to text faults through, if any.
+    ///
+    /// This requires the `sms` feature.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub sms: Option<SmsConfig>,
+    /// The ntfy topics and Pushover users sent push notifications.
+    ///
+    /// This requires the `push` feature.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub push: Vec<PushConfig>,
+    /// How notifications are deduplicated and rate-limited.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, rename = "notification-throttle")
+    )]
+    pub notification_throttle: NotificationThrottleConfig,
+    /// The priming configuration.
+    #[cfg_attr(feature = "use_serde", serde(default))]
+    pub prime: PrimeConfig,
+    /// The flow sensor configuration, if a flow sensor is installed.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(
+            default,
+            rename = "flow-sensor",
+            skip_serializing_if = "Option::is_none"
+        )
+    )]
+    pub flow_sensor: Option<FlowSensorConfig>,
+    /// The pressure sensor configuration, if a pressure transducer is installed.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(
+            default,
+            rename = "pressure-sensor",
+            skip_serializing_if = "Option::is_none"
+        )
+    )]
+    pub pressure_sensor: Option<PressureSensorConfig>,
+    /// The bubble detector configuration, if an air-in-line detector is installed.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(
+            default,
+            rename = "bubble-detector",
+            skip_serializing_if = "Option::is_none"
+        )
+    )]
+    pub bubble_detector: Option<BubbleDetectorConfig>,
+    /// The buffer reservoirs with level sensors.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub reservoirs: Vec<ReservoirConfig>,
+    /// The temperature control configuration, if temperature control is installed.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub thermal: Option<ThermalConfig>,
+    /// The emergency-stop button configuration, if one is installed.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub estop: Option<EStopConfig>,
+    /// The watchdog configuration, if the coordinator should be supervised.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub watchdog: Option<WatchdogConfig>,
+    /// How motors and pumps which stop unexpectedly are restarted.
+    #[cfg_attr(feature = "use_serde", serde(default))]
+    pub supervision: SupervisionConfig,
+    /// How long timed steps may overrun, and what to do if they run longer, unless a step says
+    /// otherwise (see [`Step::Timed`]).
+    ///
+    /// [`Step::Timed`]: ../enum.Step.html#variant.Timed
     #[cfg_attr(feature = "use_serde", serde(default))]
-    pub admins: Vec<String>,
+    pub overrun: Overrun,
+    /// The steps run when a program is aborted, before everything is shut down.
+    ///
+    /// Different rigs may need different steps to be left in a safe state (e.g. draining the
+    /// chamber and flushing it with a final buffer).
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub teardown: Vec<TeardownStep>,
+    /// The wash routine run on request (e.g. to clean the chamber after a run), if any.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub wash: Option<WashConfig>,
+    /// The API tokens accepted by the server.
+    ///
+    /// If none are configured, the server doesn't require authentication (and anyone may do
+    /// anything).
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub tokens: Vec<TokenConfig>,
+    /// A secret which may be presented (like a token) to issue and revoke tokens, e.g. to issue
+    /// the first admin token when none are configured.
+    ///
+    /// Without one, only admins may manage tokens (so none can be issued until one is configured).
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(
+            default,
+            rename = "bootstrap-token",
+            skip_serializing_if = "Option::is_none"
+        )
+    )]
+    pub bootstrap_token: Option<String>,
+    /// The cross-origin resource sharing policy of the server, if other origins may use it.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub cors: Option<CorsConfig>,
+    /// How hard clients may drive the server's control endpoints.
+    #[cfg_attr(feature = "use_serde", serde(default))]
+    pub throttle: ThrottleConfig,
+}
+
+impl Config {
+    /// Describes the configured hardware, for checking protocols against it (see
+    /// [`Protocol::check`](struct.Protocol.html#method.check)).
+    pub fn hardware(&self) -> Hardware {
+        Hardware {
+            positions: self
+                .motors
+                .iter()
+                .map(|spec| spec.positions().keys().cloned().collect())
+                .collect(),
+            pumps: self.pumps.len(),
+            thermostat: self.thermal.is_some(),
+            buffers: self
+                .buffers
+                .iter()
+                .map(|buffer| (buffer.name.clone(), buffer.valve))
+                .collect(),
+            flow_rates: self.pumps.iter().map(|pump| pump.flow_rate).collect(),
+            sensors: vec![
+                (Sensor::Pressure, self.pressure_sensor.is_some()),
+                (Sensor::Flow, self.flow_sensor.is_some()),
+                (Sensor::Temperature, self.thermal.is_some()),
+            ]
+            .into_iter()
+            .filter(|(_, installed)| *installed)
+            .map(|(sensor, _)| sensor)
+            .collect(),
+        }
+    }
+    /// Describes the configuration file format as a JSON Schema, so that configurations can be
+    /// checked (e.g. by an editor or in CI) before they're deployed.
+    ///
+    /// This requires the `schema` feature.
+    #[cfg(feature = "schema")]
+    pub fn json_schema() -> RootSchema {
+        schemars::schema_for!(Config)
+    }
+    /// The configured buffers, for referring to them by name.
+    pub fn named_buffers(&self) -> Buffers {
+        Buffers::new(self.buffers.clone())
+    }
+    /// Splits the configuration into one for each manifold, along with its name (starting with
+    /// the main manifold).
+    ///
+    /// The further manifolds share the main manifold's settings, apart from its hardware, journal,
+    /// and calibrations. Each keeps its own event log (alongside the main one, with its name
+    /// appended) and talks to the MQTT broker under its own topics (with its name appended). Only
+    /// the main manifold serves the gRPC control interface and Modbus TCP.
+    pub fn split(mut self) -> Vec<(String, Self)> {
+        let manifolds = std::mem::replace(&mut self.manifolds, vec![]);
+        let mut shared = self.clone();
+        shared.flow_sensor = None;
+        shared.pressure_sensor = None;
+        shared.bubble_detector = None;
+        shared.reservoirs.clear();
+        shared.thermal = None;
+        shared.estop = None;
+        shared.grpc = None;
+        shared.modbus = None;
+        let mut configs = vec![(MAIN_MANIFOLD.to_string(), self)];
+        for manifold in manifolds {
+            let mut config = shared.clone();
+            config.pumps = manifold.pumps;
+            config.drain_pump = manifold.drain_pump;
+            config.motors = manifold.motors;
+            config.buffers = manifold.buffers;
+            config.calibration = manifold.calibration;
+            config.journal = manifold.journal;
+            if let Some(log) = &mut config.event_log {
+                let mut path = log.path.as_os_str().to_os_string();
+                path.push(format!("-{}", manifold.name));
+                log.path = path.into();
+            }
+            if let Some(mqtt) = &mut config.mqtt {
+                mqtt.client_id = format!("{}-{}", mqtt.client_id, manifold.name);
+                for topic in &mut [
+                    &mut mqtt.status_topic,
+                    &mut mqtt.readings_topic,
+                    &mut mqtt.command_topic,
+                ] {
+                    topic.push('/');
+                    topic.push_str(&manifold.name);
+                }
+            }
+            configs.push((manifold.name, config));
+        }
+        configs
+    }
+    /// The GPIO pins used by the configured devices.
+    ///
+    /// PCA9685 and ADC channels are not GPIO pins, so they are not included (see
+    /// [`channels`](#method.channels) for the former).
+    pub fn pins(&self) -> Vec<u16> {
+        self.pin_uses().into_iter().map(|(_, pin)| pin).collect()
+    }
+    /// The PCA9685 channels used by the configured devices, as (bus, address, channel).
+    pub fn channels(&self) -> Vec<(u8, u16, u8)> {
+        self.channel_uses()
+            .into_iter()
+            .map(|(_, channel)| channel)
+            .collect()
+    }
+    /// The PCA9685 channels used by the configured devices, each with the path of the field giving
+    /// it.
+    fn channel_uses(&self) -> Vec<(String, (u8, u16, u8))> {
+        let manifolds = self
+            .manifolds
+            .iter()
+            .enumerate()
+            .map(|(index, manifold)| (format!("manifolds[{}].", index), &manifold.motors));
+        let all = Some((String::new(), &self.motors))
+            .into_iter()
+            .chain(manifolds);
+        let mut channels = vec![];
+        for (prefix, motors) in all {
+            for (index, motor) in motors.iter().enumerate() {
+                if let ValveConfig::Servo(motor) = motor {
+                    if let PwmMode::Pca9685 {
+                        bus,
+                        address,
+                        channel,
+                    } = motor.pwm
+                    {
+                        let field = format!("{}motors[{}].pwm.channel", prefix, index);
+                        channels.push((field, (bus, address, channel)));
+                    }
+                }
+            }
+        }
+        channels
+    }
+    /// The GPIO pins used by the configured devices, each with the path of the field giving it.
+    fn pin_uses(&self) -> Vec<(String, u16)> {
+        let mut pins = vec![];
+        let manifolds = self.manifolds.iter().enumerate().map(|(index, manifold)| {
+            (
+                format!("manifolds[{}].", index),
+                &manifold.pumps,
+                &manifold.motors,
+            )
+        });
+        let main = (String::new(), &self.pumps, &self.motors);
+        let all = Some(main).into_iter().chain(manifolds).collect::<Vec<_>>();
+        for (prefix, pumps, _) in &all {
+            for (index, pump) in pumps.iter().enumerate() {
+                for (n, &pin) in pump.pins.iter().enumerate() {
+                    pins.push((format!("{}pumps[{}].pins[{}]", prefix, index, n), pin));
+                }
+            }
+        }
+        for (prefix, _, motors) in &all {
+            for (index, motor) in motors.iter().enumerate() {
+                let field = |name| format!("{}motors[{}].{}", prefix, index, name);
+                match motor {
+                    ValveConfig::Servo(motor) => {
+                        if let PwmMode::Software | PwmMode::Hardware { .. } = motor.pwm {
+                            pins.push((field("pin"), motor.pin));
+                        }
+                    }
+                    ValveConfig::Stepper(motor) => {
+                        pins.push((field("step"), motor.step));
+                        pins.push((field("dir"), motor.dir));
+                        pins.extend(motor.enable.map(|pin| (field("enable"), pin)));
+                        pins.extend(motor.home.map(|pin| (field("home"), pin)));
+                    }
+                }
+            }
+        }
+        if let Some(sensor) = &self.flow_sensor {
+            pins.push(("flow-sensor.pin".into(), sensor.pin));
+        }
+        if let Some(detector) = self.bubble_detector {
+            pins.push(("bubble-detector.pin".into(), detector.pin));
+        }
+        for (index, reservoir) in self.reservoirs.iter().enumerate() {
+            if let LevelSensorConfig::Float { pin, .. } = reservoir.sensor {
+                pins.push((format!("reservoirs[{}].sensor.pin", index), pin));
+            }
+        }
+        if let Some(thermal) = &self.thermal {
+            pins.extend(thermal.heater.map(|pin| ("thermal.heater".into(), pin)));
+            pins.extend(thermal.chiller.map(|pin| ("thermal.chiller".into(), pin)));
+        }
+        if let Some(estop) = self.estop {
+            pins.push(("estop.pin".into(), estop.pin));
+        }
+        pins
+    }
+    /// Checks the configuration for mistakes (such as two devices on the same pin, or a servo
+    /// whose pulse widths are reversed), reporting every one found (see
+    /// [`Violation`](struct.Violation.html)).
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let violations = validate::violations(self);
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(violations))
+        }
+    }
+}
+
+/// Configures how lines are primed.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct PrimeConfig {
+    /// The volume to prime with (e.g. `"2.5ml"`, or a bare number of mL).
+    ///
+    /// This is only used if the pump's flow rate has been calibrated; otherwise, lines are primed
+    /// for the configured duration.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub volume: Option<quantity::Volume>,
+    /// How long to run the pump for when no volume is specified.
+    pub duration: Duration,
+}
+
+impl PrimeConfig {
+    /// The configured priming volume, if any.
+    pub fn volume(&self) -> Option<Volume> {
+        self.volume
+            .map(|volume| Volume::new::<milliliter>(volume.millilitres()))
+    }
+}
+
+impl Default for PrimeConfig {
+    fn default() -> Self {
+        Self {
+            volume: None,
+            duration: Duration::new(10, 0),
+        }
+    }
+}
+
+/// Either a single value or a sequence of values.
+#[cfg(feature = "use_serde")]
+#[derive(Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(untagged)]
+enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+/// Deserializes either a single value or a sequence of values.
+#[cfg(feature = "use_serde")]
+fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: serde::Deserialize<'de>,
+{
+    Ok(
+        match <OneOrMany<T> as serde::Deserialize>::deserialize(deserializer)? {
+            OneOrMany::One(value) => vec![value],
+            OneOrMany::Many(values) => values,
+        },
+    )
+}
+
+/// The name of the manifold made up of the pumps and motors at the top level of the configuration.
+pub const MAIN_MANIFOLD: &str = "main";
+
+/// Configures a further manifold: a set of pumps and motors running its own protocols.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct ManifoldConfig {
+    /// The name of the manifold, by which it's addressed.
+    pub name: String,
+    /// The pump configurations.
+    ///
+    /// A single `[manifolds.pump]` table is also accepted.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(alias = "pump", deserialize_with = "one_or_many")
+    )]
+    #[cfg_attr(feature = "schema", schemars(with = "OneOrMany<PumpConfig>", default))]
+    pub pumps: Vec<PumpConfig>,
+    /// The pump to use for draining, if different from the pump in use.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(
+            default,
+            rename = "drain-pump",
+            skip_serializing_if = "Option::is_none"
+        )
+    )]
+    pub drain_pump: Option<PumpId>,
+    /// The motor configurations.
+    pub motors: Vec<ValveConfig>,
+    /// The buffers supplied through the manifold's valves.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub buffers: Vec<BufferConfig>,
+    /// The path to the manifold's motor calibration file, if any.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub calibration: Option<PathBuf>,
+    /// The path to the manifold's journal file, if any.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub journal: Option<PathBuf>,
+}
+
+/// Selects the driver used to access GPIO pins.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "use_serde", serde(tag = "driver", rename_all = "lowercase"))]
+pub enum BackendConfig {
+    /// The default backend for the enabled features.
+    Default,
+    /// The `rppal` backend (requires the `use_rppal` feature).
+    Rppal,
+    /// The Linux sysfs interface.
+    Sysfs,
+    /// An in-memory backend which records writes instead of touching hardware.
+    Mock,
+    /// A remote Pi running the pigpio daemon.
+    Pigpio {
+        /// The host running `pigpiod`.
+        #[cfg_attr(feature = "use_serde", serde(default = "default_pigpio_host"))]
+        host: String,
+        /// The port `pigpiod` is listening on.
+        #[cfg_attr(feature = "use_serde", serde(default = "default_pigpio_port"))]
+        port: u16,
+    },
+    /// The Linux GPIO character device (requires the `use_cdev` feature).
+    Cdev {
+        /// The path to the GPIO chip (e.g. `/dev/gpiochip0`).
+        chip: String,
+    },
+    /// A microcontroller running the serial bridge firmware (requires the `serial` feature).
+    Serial {
+        /// The serial port the microcontroller is connected to (e.g. `/dev/ttyACM0` or `COM3`).
+        port: String,
+        /// The baud rate to talk to the microcontroller at.
+        #[cfg_attr(feature = "use_serde", serde(default = "default_serial_baud"))]
+        baud: u32,
+    },
+}
+
+#[cfg(feature = "use_serde")]
+fn default_pigpio_host() -> String {
+    "localhost".into()
+}
+
+#[cfg(feature = "use_serde")]
+fn default_pigpio_port() -> u16 {
+    pin::PIGPIO_DEFAULT_PORT
+}
+
+#[cfg(feature = "use_serde")]
+fn default_serial_baud() -> u32 {
+    115_200
+}
+
+impl Default for BackendConfig {
+    fn default() -> Self {
+        Self::Default
+    }
+}
+
+impl BackendConfig {
+    /// Opens the configured backend.
+    pub fn open(&self) -> Result<Box<dyn GpioBackend>, PinError> {
+        match self {
+            Self::Default => Ok(pin::default_backend()),
+            #[cfg(feature = "use_rppal")]
+            Self::Rppal => Ok(Box::new(pin::Rppal)),
+            #[cfg(not(feature = "use_rppal"))]
+            Self::Rppal => Err(PinError::Unsupported(
+                "rppal (enable the use_rppal feature)",
+            )),
+            Self::Sysfs => Ok(Box::new(pin::Sysfs)),
+            Self::Mock => Ok(Box::new(pin::Mock::new())),
+            Self::Pigpio { host, port } => Ok(Box::new(pin::Pigpio::connect(host, *port)?)),
+            #[cfg(feature = "use_cdev")]
+            Self::Cdev { chip } => Ok(Box::new(pin::Cdev::open(chip)?)),
+            #[cfg(not(feature = "use_cdev"))]
+            Self::Cdev { .. } => Err(PinError::Unsupported(
+                "GPIO character device (enable the use_cdev feature)",
+            )),
+            #[cfg(feature = "serial")]
+            Self::Serial { port, baud } => Ok(Box::new(pin::Serial::open(port, *baud)?)),
+            #[cfg(not(feature = "serial"))]
+            Self::Serial { .. } => Err(PinError::Unsupported(
+                "serial bridge (enable the serial feature)",
+            )),
+        }
+    }
+}
+
+/// Specifies a single motor of either kind.
+///
+/// Entries without stepper-specific fields are servos, as before.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "use_serde", serde(untagged))]
+pub enum ValveConfig {
+    /// A hobby servo.
+    Servo(MotorConfig),
+    /// A stepper motor.
+    Stepper(StepperConfig),
+}
+
+impl ValveConfig {
+    /// The angles of the valve's additional named positions.
+    pub fn positions(&self) -> &BTreeMap<String, u16> {
+        match self {
+            Self::Servo(config) => &config.positions,
+            Self::Stepper(config) => &config.positions,
+        }
+    }
+    /// The largest angle the valve can be moved to, in degrees.
+    pub fn range_of_motion(&self) -> u16 {
+        match self {
+            Self::Servo(config) => config.range_of_motion,
+            // Stepper motors turn half a revolution from their home position.
+            Self::Stepper(_) => 180,
+        }
+    }
+}
+
+impl From<MotorConfig> for ValveConfig {
+    fn from(config: MotorConfig) -> Self {
+        Self::Servo(config)
+    }
+}
+
+impl From<StepperConfig> for ValveConfig {
+    fn from(config: StepperConfig) -> Self {
+        Self::Stepper(config)
+    }
 }
 
 /// Specifies a single motor.
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct MotorConfig {
     /// The pin associated with this motor.
     pub pin: u16,
@@ -26,15 +805,1540 @@ pub struct MotorConfig {
     pub period: Duration,
     /// The limits of acceptable signal length.
     pub range: [Duration; 2],
+    /// How the motor's PWM signal should be generated.
+    #[cfg_attr(feature = "use_serde", serde(default))]
+    pub pwm: PwmMode,
+    /// If true, the motor's pin is active-low (and its signal is inverted).
+    #[cfg_attr(feature = "use_serde", serde(default))]
+    pub inverted: bool,
+    /// The motor's range of motion, in degrees.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default = "default_range_of_motion", rename = "range-of-motion")
+    )]
+    pub range_of_motion: u16,
+    /// The angles of the motor's open, closed, and shut positions, if not spanning its range of
+    /// motion.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub angles: Option<Angles>,
+    /// The angles of any additional named positions (e.g. "bypass"), for use in protocols.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "BTreeMap::is_empty")
+    )]
+    pub positions: BTreeMap<String, u16>,
+    /// How long to take moving between positions, if not instantaneously.
+    ///
+    /// Ramping the motor's movement reduces water hammer and mechanical shock.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub ramp: Option<Duration>,
+    /// How long after moving to turn off the motor's signal, if at all.
+    ///
+    /// Detaching keeps servos from humming and heating up while holding position.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(
+            default,
+            rename = "detach-after",
+            skip_serializing_if = "Option::is_none"
+        )
+    )]
+    pub detach_after: Option<Duration>,
+    /// How the motor's actual position is read back, if at all.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub feedback: Option<FeedbackConfig>,
+}
+
+#[cfg(feature = "use_serde")]
+fn default_range_of_motion() -> u16 {
+    180
+}
+
+impl MotorConfig {
+    /// Acquires the pin for this motor from the given backend.
+    pub fn pin(&self, backend: &dyn GpioBackend) -> Result<Pin, PinError> {
+        let pin = match self.pwm {
+            PwmMode::Software => Pin::with_backend(backend, self.pin),
+            PwmMode::Hardware { channel } => Pin::hardware_pwm(backend, self.pin, channel),
+            PwmMode::Pca9685 {
+                bus,
+                address,
+                channel,
+            } => Pin::pca9685(backend, bus, address, channel),
+        }?;
+        Ok(pin.with_inversion(self.inverted))
+    }
+    /// Opens the position feedback for this motor, if configured.
+    pub fn feedback(&self) -> Result<Option<Feedback>, PinError> {
+        self.feedback
+            .map(|config| {
+                Ok(Feedback::new(
+                    config.adc.open()?,
+                    config.channel,
+                    config.range,
+                    config.tolerance,
+                ))
+            })
+            .transpose()
+    }
+}
+
+/// Configures position feedback for a motor (e.g. a potentiometer coupled to the valve).
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct FeedbackConfig {
+    /// The converter the position sensor is connected to.
+    pub adc: AdcConfig,
+    /// The converter channel the position sensor is connected to.
+    pub channel: u8,
+    /// The readings (as fractions of full scale) at either end of the motor's range of motion.
+    pub range: [f64; 2],
+    /// The largest tolerated deviation from the target angle, in degrees.
+    #[cfg_attr(feature = "use_serde", serde(default = "default_feedback_tolerance"))]
+    pub tolerance: f64,
+}
+
+#[cfg(feature = "use_serde")]
+fn default_feedback_tolerance() -> f64 {
+    5.0
+}
+
+/// Selects how a motor's PWM signal is generated.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "use_serde", serde(tag = "mode", rename_all = "lowercase"))]
+pub enum PwmMode {
+    /// The signal is generated in software by toggling the pin.
+    ///
+    /// This works on any pin, but is subject to jitter.
+    Software,
+    /// The signal is generated by a hardware PWM peripheral.
+    Hardware {
+        /// The PWM channel routed to the motor's pin.
+        channel: u8,
+    },
+    /// The signal is generated by a PCA9685 I²C PWM expander.
+    ///
+    /// The motor's pin number is ignored in this mode.
+    Pca9685 {
+        /// The I²C bus the board is connected to.
+        #[cfg_attr(feature = "use_serde", serde(default = "default_i2c_bus"))]
+        bus: u8,
+        /// The I²C address of the board.
+        #[cfg_attr(feature = "use_serde", serde(default = "default_pca9685_address"))]
+        address: u16,
+        /// The channel (0–15) the motor is connected to.
+        channel: u8,
+    },
+}
+
+#[cfg(feature = "use_serde")]
+fn default_i2c_bus() -> u8 {
+    1
+}
+
+#[cfg(feature = "use_serde")]
+fn default_pca9685_address() -> u16 {
+    0x40
+}
+
+impl Default for PwmMode {
+    fn default() -> Self {
+        Self::Software
+    }
 }
 
 /// Encodes the pump configuration.
 #[derive(Clone, Copy, Debug)]
 #[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct PumpConfig {
     /// The pins used for the pump, in order from 0–3.
     pub pins: [u16; 4],
     /// If true, the pump's "forward" direction will be the reverse direction
     #[cfg_attr(feature = "use_serde", serde(default, alias = "reverse"))]
     pub invert: bool,
+    /// Which of the pump's pins (in the same order as `pins`) are active-low.
+    #[cfg_attr(feature = "use_serde", serde(default))]
+    pub inverted: [bool; 4],
+    /// The duty cycles corresponding to zero and full speed, respectively.
+    #[cfg_attr(feature = "use_serde", serde(default = "default_pump_duty"))]
+    pub duty: [f64; 2],
+    /// The period of the PWM signal used for speed control.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default = "default_pump_pwm_period", rename = "pwm-period")
+    )]
+    pub pwm_period: Duration,
+    /// The calibrated flow rate at full speed (e.g. `"12ml/min"`, or a bare number of mL/min).
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, rename = "flow-rate", skip_serializing_if = "Option::is_none")
+    )]
+    pub flow_rate: Option<FlowRate>,
+    /// How long to take ramping the pump up to speed and back down, if at all.
+    ///
+    /// Ramping avoids pressure spikes from abrupt switching.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub ramp: Option<Duration>,
+}
+
+impl PumpConfig {
+    /// Creates a pump configuration using the given pins and default settings.
+    pub fn new(pins: [u16; 4]) -> Self {
+        Self {
+            pins,
+            invert: false,
+            inverted: [false; 4],
+            duty: [0.0, 1.0],
+            pwm_period: Duration::from_millis(1),
+            flow_rate: None,
+            ramp: None,
+        }
+    }
+}
+
+#[cfg(feature = "use_serde")]
+fn default_pump_duty() -> [f64; 2] {
+    [0.0, 1.0]
+}
+
+#[cfg(feature = "use_serde")]
+fn default_pump_pwm_period() -> Duration {
+    Duration::from_millis(1)
+}
+
+/// Configures a pulse-output flow sensor.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct FlowSensorConfig {
+    /// The input pin the sensor's pulse output is connected to.
+    pub pin: u16,
+    /// The number of pulses the sensor emits per mL of flow.
+    #[cfg_attr(feature = "use_serde", serde(rename = "pulses-per-ml"))]
+    pub pulses_per_ml: f64,
+    /// The largest tolerated relative deviation between the expected and measured volumes.
+    #[cfg_attr(feature = "use_serde", serde(default = "default_flow_tolerance"))]
+    pub tolerance: f64,
+    /// What to do when the measured volume deviates from the expected volume.
+    #[cfg_attr(feature = "use_serde", serde(default))]
+    pub alarm: FlowAlarm,
+}
+
+impl FlowSensorConfig {
+    /// Creates a flow sensor configuration using the given pin and calibration.
+    pub fn new(pin: u16, pulses_per_ml: f64) -> Self {
+        Self {
+            pin,
+            pulses_per_ml,
+            tolerance: 0.2,
+            alarm: FlowAlarm::default(),
+        }
+    }
+}
+
+#[cfg(feature = "use_serde")]
+fn default_flow_tolerance() -> f64 {
+    0.2
+}
+
+/// The response to a flow mismatch (e.g. a clogged line or an empty reservoir).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "use_serde", serde(rename_all = "lowercase"))]
+pub enum FlowAlarm {
+    /// Report the mismatch and continue.
+    Warn,
+    /// Report the mismatch and halt execution.
+    Abort,
+}
+
+impl Default for FlowAlarm {
+    fn default() -> Self {
+        Self::Warn
+    }
+}
+
+/// Configures an analog pressure transducer.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct PressureSensorConfig {
+    /// The converter the transducer is connected to.
+    pub adc: AdcConfig,
+    /// The converter channel the transducer is connected to.
+    pub channel: u8,
+    /// The pressures corresponding to zero and full-scale readings, respectively, in kPa.
+    pub range: [f64; 2],
+    /// The pressure above which the pump is stopped and all valves are shut, in kPa.
+    pub limit: f64,
+    /// How often to check the pressure.
+    #[cfg_attr(feature = "use_serde", serde(default = "default_pressure_interval"))]
+    pub interval: Duration,
+}
+
+impl PressureSensorConfig {
+    /// The configured calibration range.
+    pub fn range(&self) -> [Pressure; 2] {
+        [
+            Pressure::new::<kilopascal>(self.range[0]),
+            Pressure::new::<kilopascal>(self.range[1]),
+        ]
+    }
+    /// The configured pressure limit.
+    pub fn limit(&self) -> Pressure {
+        Pressure::new::<kilopascal>(self.limit)
+    }
+}
+
+#[cfg(feature = "use_serde")]
+fn default_pressure_interval() -> Duration {
+    Duration::new(1, 0)
+}
+
+/// Selects the analog-to-digital converter used to read an analog sensor.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "use_serde", serde(tag = "driver", rename_all = "lowercase"))]
+pub enum AdcConfig {
+    /// An MCP3008 on an SPI bus (requires the `use_rppal` feature).
+    Mcp3008 {
+        /// The SPI bus the converter is connected to.
+        #[cfg_attr(feature = "use_serde", serde(default))]
+        bus: u8,
+        /// The slave select line the converter is connected to.
+        #[cfg_attr(feature = "use_serde", serde(default, rename = "slave-select"))]
+        slave_select: u8,
+    },
+    /// An ADS1115 on an I²C bus (requires the `use_rppal` feature).
+    Ads1115 {
+        /// The I²C bus the converter is connected to.
+        #[cfg_attr(feature = "use_serde", serde(default = "default_i2c_bus"))]
+        bus: u8,
+        /// The I²C address of the converter.
+        #[cfg_attr(feature = "use_serde", serde(default = "default_ads1115_address"))]
+        address: u16,
+    },
+    /// A converter which always reads zero.
+    Mock,
+}
+
+#[cfg(feature = "use_serde")]
+fn default_ads1115_address() -> u16 {
+    0x48
+}
+
+impl AdcConfig {
+    /// Opens the configured converter.
+    pub fn open(&self) -> Result<Box<dyn Adc>, PinError> {
+        match *self {
+            #[cfg(feature = "use_rppal")]
+            Self::Mcp3008 { bus, slave_select } => {
+                Ok(Box::new(adc::Mcp3008::open(bus, slave_select)?))
+            }
+            #[cfg(feature = "use_rppal")]
+            Self::Ads1115 { bus, address } => Ok(Box::new(adc::Ads1115::open(bus, address)?)),
+            #[cfg(not(feature = "use_rppal"))]
+            Self::Mcp3008 { .. } | Self::Ads1115 { .. } => {
+                Err(PinError::Unsupported("ADCs (enable the use_rppal feature)"))
+            }
+            Self::Mock => Ok(Box::new(adc::Mock::new())),
+        }
+    }
+}
+
+/// Configures an air-in-line (bubble) detector.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct BubbleDetectorConfig {
+    /// The input pin the detector is connected to.
+    pub pin: u16,
+    /// If true, the detector drives the pin low (rather than high) while air is in the line.
+    #[cfg_attr(feature = "use_serde", serde(default))]
+    pub invert: bool,
+    /// How long to purge the line through the waste valve before automatically resuming.
+    ///
+    /// If not given, perfusion remains paused until the operator confirms that it should continue.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub purge: Option<Duration>,
+}
+
+/// Configures a hardware emergency-stop button.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct EStopConfig {
+    /// The input pin the button is connected to.
+    pub pin: u16,
+    /// The pull resistor configuration of the input.
+    #[cfg_attr(feature = "use_serde", serde(default))]
+    pub pull: Pull,
+    /// If true, the button drives the pin low (rather than high) while pressed.
+    #[cfg_attr(feature = "use_serde", serde(default))]
+    pub invert: bool,
+}
+
+impl EStopConfig {
+    /// Acquires the button's input using the given backend.
+    pub fn input(&self, backend: &dyn GpioBackend) -> Result<InputPin, PinError> {
+        InputPin::with_backend(backend, self.pin, self.pull)
+    }
+}
+
+/// A step of the teardown sequence run when a program is aborted.
+///
+/// Every teardown ends by stopping the pumps, releasing the temperature, and closing all valves
+/// (shutting the waste valve), so there is no need to do so explicitly.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "use_serde", serde(rename_all = "lowercase"))]
+pub enum TeardownStep {
+    /// Drains the chamber to waste.
+    Drain,
+    /// Flushes the chamber with the given buffer, perfusing it and then draining it.
+    Flush(MotorId),
+    /// Waits for the given duration.
+    Wait(Duration),
+    /// Uses the given pump for subsequent steps.
+    UsePump(PumpId),
+    /// Moves the given motor to the named position.
+    SetPosition {
+        /// The motor to move (motor 0 is the waste valve).
+        motor: MotorId,
+        /// The name of the position.
+        position: String,
+    },
+}
+
+impl TeardownStep {
+    /// The actions making up this step.
+    pub fn actions(&self) -> Vec<Action> {
+        match self {
+            Self::Drain => vec![Action::Drain],
+            Self::Flush(buffer) => vec![Action::Perfuse(*buffer), Action::Drain],
+            Self::Wait(duration) => vec![Action::Sleep(*duration)],
+            Self::UsePump(pump) => vec![Action::UsePump(*pump)],
+            Self::SetPosition { motor, position } => vec![Action::SetPosition {
+                motor: *motor,
+                position: position.clone(),
+            }],
+        }
+    }
+}
+
+/// Configures the wash routine, which fills the chamber with a buffer and drains it, several times
+/// over (see [`CoordMessage::Wash`]).
+///
+/// [`CoordMessage::Wash`]: ../enum.CoordMessage.html#variant.Wash
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct WashConfig {
+    /// The buffer to wash with.
+    pub buffer: MotorId,
+    /// How long to pump the buffer in each time.
+    pub fill: Duration,
+    /// How long to drain the chamber each time.
+    pub drain: Duration,
+    /// How many times to fill and drain the chamber.
+    #[cfg_attr(feature = "use_serde", serde(default = "default_wash_repetitions"))]
+    pub repetitions: usize,
+}
+
+#[cfg(feature = "use_serde")]
+fn default_wash_repetitions() -> usize {
+    3
+}
+
+/// Configures the watchdog which supervises the coordinator.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct WatchdogConfig {
+    /// How long past its expected duration a step may run before the watchdog trips.
+    #[cfg_attr(feature = "use_serde", serde(default = "default_watchdog_grace"))]
+    pub grace: Duration,
+    /// How often the coordinator is pinged.
+    #[cfg_attr(feature = "use_serde", serde(default = "default_watchdog_interval"))]
+    pub interval: Duration,
+    /// How long the coordinator has to answer a ping.
+    #[cfg_attr(feature = "use_serde", serde(default = "default_watchdog_timeout"))]
+    pub timeout: Duration,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            grace: Duration::new(60, 0),
+            interval: Duration::new(10, 0),
+            timeout: Duration::new(5, 0),
+        }
+    }
+}
+
+#[cfg(feature = "use_serde")]
+fn default_watchdog_grace() -> Duration {
+    WatchdogConfig::default().grace
+}
+
+#[cfg(feature = "use_serde")]
+fn default_watchdog_interval() -> Duration {
+    WatchdogConfig::default().interval
+}
+
+#[cfg(feature = "use_serde")]
+fn default_watchdog_timeout() -> Duration {
+    WatchdogConfig::default().timeout
+}
+
+/// Configures how the coordinator restarts motors and pumps which stop unexpectedly.
+///
+/// A restarted device has its pins acquired afresh and is sent the last command it was given.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "use_serde", serde(default))]
+pub struct SupervisionConfig {
+    /// How many times in a row a device may be restarted (without carrying out a command in
+    /// between) before the run is faulted instead; zero faults as soon as a device stops.
+    pub attempts: u32,
+    /// How often the devices are checked.
+    pub interval: Duration,
+}
+
+impl Default for SupervisionConfig {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            interval: Duration::new(5, 0),
+        }
+    }
+}
+
+/// What the holder of an API token may do.
+///
+/// Each role may do everything the roles before it may.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "use_serde", serde(rename_all = "lowercase"))]
+pub enum Role {
+    /// May see the status and history.
+    Viewer,
+    /// May also start, stop, and otherwise direct protocols.
+    Operator,
+    /// May also control the hardware directly (e.g. calibrating motors) and manage tokens.
+    Admin,
+}
+
+impl Default for Role {
+    fn default() -> Self {
+        Self::Viewer
+    }
+}
+
+impl Role {
+    /// The role needed for a request to the given API path (less any manifold prefix), which
+    /// only reads (e.g. `GET`) or doesn't.
+    ///
+    /// Looking is open to viewers, direct hardware control (calibration and manual control) and
+    /// token management are reserved for admins, and everything else (i.e. directing protocols)
+    /// needs an operator.
+    pub fn required(read_only: bool, path: &str) -> Self {
+        if read_only {
+            Self::Viewer
+        } else if ["/motors", "/manual", "/tokens"]
+            .iter()
+            .any(|prefix| path.starts_with(prefix))
+        {
+            Self::Admin
+        } else {
+            Self::Operator
+        }
+    }
+}
+
+/// Configures which other origins (e.g. a separately hosted frontend) may use the server.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "use_serde", serde(rename_all = "kebab-case"))]
+pub struct CorsConfig {
+    /// The origins allowed (e.g. `https://deoxy.lab`), or `*` for any.
+    pub origins: Vec<String>,
+    /// The methods allowed.
+    #[cfg_attr(feature = "use_serde", serde(default = "default_cors_methods"))]
+    pub methods: Vec<String>,
+    /// The request headers allowed.
+    #[cfg_attr(feature = "use_serde", serde(default = "default_cors_headers"))]
+    pub headers: Vec<String>,
+    /// How long browsers may cache the policy.
+    #[cfg_attr(feature = "use_serde", serde(default = "default_cors_max_age"))]
+    pub max_age: Duration,
+}
+
+impl CorsConfig {
+    /// Creates a policy allowing the given origins the usual methods and headers.
+    pub fn new(origins: Vec<String>) -> Self {
+        Self {
+            origins,
+            methods: ["GET", "POST", "PUT", "DELETE"]
+                .iter()
+                .map(|&method| method.into())
+                .collect(),
+            headers: ["Authorization", "Content-Type", "Last-Event-ID"]
+                .iter()
+                .map(|&header| header.into())
+                .collect(),
+            max_age: Duration::new(3600, 0),
+        }
+    }
+}
+
+#[cfg(feature = "use_serde")]
+fn default_cors_methods() -> Vec<String> {
+    CorsConfig::new(vec![]).methods
+}
+
+#[cfg(feature = "use_serde")]
+fn default_cors_headers() -> Vec<String> {
+    CorsConfig::new(vec![]).headers
+}
+
+#[cfg(feature = "use_serde")]
+fn default_cors_max_age() -> Duration {
+    CorsConfig::new(vec![]).max_age
+}
+
+/// Configures the limits on clients' use of the server's control (i.e. mutating) endpoints.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct ThrottleConfig {
+    /// How many commands a client may send per minute, on average (or zero for no limit).
+    #[cfg_attr(feature = "use_serde", serde(default = "default_throttle_rate"))]
+    pub rate: u32,
+    /// How many commands a client may send in quick succession.
+    #[cfg_attr(feature = "use_serde", serde(default = "default_throttle_burst"))]
+    pub burst: u32,
+    /// How long a repeated command is ignored for (e.g. a double-clicked button).
+    #[cfg_attr(feature = "use_serde", serde(default = "default_throttle_debounce"))]
+    pub debounce: Duration,
+}
+
+impl Default for ThrottleConfig {
+    fn default() -> Self {
+        Self {
+            rate: 60,
+            burst: 10,
+            debounce: Duration::new(1, 0),
+        }
+    }
+}
+
+#[cfg(feature = "use_serde")]
+fn default_throttle_rate() -> u32 {
+    ThrottleConfig::default().rate
+}
+
+#[cfg(feature = "use_serde")]
+fn default_throttle_burst() -> u32 {
+    ThrottleConfig::default().burst
+}
+
+#[cfg(feature = "use_serde")]
+fn default_throttle_debounce() -> Duration {
+    ThrottleConfig::default().debounce
+}
+
+/// Configures an API token accepted by the server.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct TokenConfig {
+    /// The token itself, as presented by clients (e.g. `Authorization: Bearer <token>`).
+    pub token: String,
+    /// Who (or what) holds the token.
+    pub name: String,
+    /// What the holder may do (viewing only, by default).
+    #[cfg_attr(feature = "use_serde", serde(default))]
+    pub role: Role,
+}
+
+/// Configures the connection to an MQTT broker.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "use_serde", serde(rename_all = "kebab-case"))]
+pub struct MqttConfig {
+    /// The host name or address of the broker.
+    pub host: String,
+    /// The port the broker listens on.
+    #[cfg_attr(feature = "use_serde", serde(default = "default_mqtt_port"))]
+    pub port: u16,
+    /// The client identifier to connect with.
+    #[cfg_attr(feature = "use_serde", serde(default = "default_mqtt_client_id"))]
+    pub client_id: String,
+    /// The topic status updates are published to.
+    #[cfg_attr(feature = "use_serde", serde(default = "default_mqtt_status_topic"))]
+    pub status_topic: String,
+    /// The topic sensor readings are published to (with the sensor appended, e.g.
+    /// `deoxy/readings/pressure`).
+    #[cfg_attr(feature = "use_serde", serde(default = "default_mqtt_readings_topic"))]
+    pub readings_topic: String,
+    /// The topic commands are taken from.
+    #[cfg_attr(feature = "use_serde", serde(default = "default_mqtt_command_topic"))]
+    pub command_topic: String,
+    /// Home Assistant's MQTT discovery, if the coordinator should announce itself.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub home_assistant: Option<HomeAssistantConfig>,
+}
+
+impl MqttConfig {
+    /// Creates a configuration for the given broker with the default port, client identifier, and
+    /// topics.
+    pub fn new(host: impl Into<String>) -> Self {
+        Self {
+            host: host.into(),
+            port: 1883,
+            client_id: "deoxy".into(),
+            status_topic: "deoxy/status".into(),
+            readings_topic: "deoxy/readings".into(),
+            command_topic: "deoxy/command".into(),
+            home_assistant: None,
+        }
+    }
+}
+
+#[cfg(feature = "use_serde")]
+fn default_mqtt_port() -> u16 {
+    MqttConfig::new("").port
+}
+
+#[cfg(feature = "use_serde")]
+fn default_mqtt_client_id() -> String {
+    MqttConfig::new("").client_id
+}
+
+#[cfg(feature = "use_serde")]
+fn default_mqtt_status_topic() -> String {
+    MqttConfig::new("").status_topic
+}
+
+#[cfg(feature = "use_serde")]
+fn default_mqtt_readings_topic() -> String {
+    MqttConfig::new("").readings_topic
+}
+
+#[cfg(feature = "use_serde")]
+fn default_mqtt_command_topic() -> String {
+    MqttConfig::new("").command_topic
+}
+
+/// Configures Home Assistant's MQTT discovery.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct HomeAssistantConfig {
+    /// The prefix Home Assistant takes discovery messages under.
+    #[cfg_attr(feature = "use_serde", serde(default = "default_discovery_prefix"))]
+    pub prefix: String,
+    /// The names of the stored protocols to offer switches for.
+    #[cfg_attr(feature = "use_serde", serde(default))]
+    pub protocols: Vec<String>,
+}
+
+impl Default for HomeAssistantConfig {
+    fn default() -> Self {
+        Self {
+            prefix: "homeassistant".into(),
+            protocols: vec![],
+        }
+    }
+}
+
+#[cfg(feature = "use_serde")]
+fn default_discovery_prefix() -> String {
+    HomeAssistantConfig::default().prefix
+}
+
+/// Configures the gRPC control interface.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct GrpcConfig {
+    /// The address to serve the interface on (e.g. `0.0.0.0:50051`).
+    pub address: SocketAddr,
+}
+
+/// Configures the Modbus TCP server.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct ModbusConfig {
+    /// The address to serve on (e.g. `0.0.0.0:502`).
+    pub address: SocketAddr,
+}
+
+/// Configures the SMTP server mail is sent through.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct MailConfig {
+    /// The host name or address of the server.
+    pub host: String,
+    /// The port the server listens on, if not the usual one for the security in use (25 without
+    /// TLS, 587 with STARTTLS, and 465 with implicit TLS).
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub port: Option<u16>,
+    /// How the connection is secured (STARTTLS, by default).
+    #[cfg_attr(feature = "use_serde", serde(default))]
+    pub security: MailSecurity,
+    /// The username to log in with, if the server requires authentication.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub username: Option<String>,
+    /// The password to log in with.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub password: Option<String>,
+    /// The address mail is sent from.
+    #[cfg_attr(feature = "use_serde", serde(default = "default_mail_from"))]
+    pub from: String,
+}
+
+impl MailConfig {
+    /// Creates a configuration for the given server, using STARTTLS on the usual port without
+    /// authentication.
+    pub fn new(host: impl Into<String>) -> Self {
+        Self {
+            host: host.into(),
+            port: None,
+            security: MailSecurity::default(),
+            username: None,
+            password: None,
+            from: "deoxy@localhost".into(),
+        }
+    }
+    /// The port to connect to.
+    pub fn port(&self) -> u16 {
+        self.port.unwrap_or(match self.security {
+            MailSecurity::None => 25,
+            MailSecurity::StartTls => 587,
+            MailSecurity::Tls => 465,
+        })
+    }
+}
+
+#[cfg(feature = "use_serde")]
+fn default_mail_from() -> String {
+    MailConfig::new("").from
+}
+
+/// A kind of event the administrators can be notified of.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "use_serde", serde(rename_all = "kebab-case"))]
+pub enum NotificationEvent {
+    /// A run has started.
+    Start,
+    /// A run has completed (or been aborted).
+    Completion,
+    /// A fault (or the watchdog) has stopped the system, or a scheduled run couldn't be queued.
+    Fault,
+    /// A reservoir is running low.
+    LowBuffer,
+    /// A protocol has reached one of its notification steps.
+    Notification,
+}
+
+impl NotificationEvent {
+    /// Every kind of event.
+    pub const ALL: [Self; 5] = [
+        NotificationEvent::Start,
+        NotificationEvent::Completion,
+        NotificationEvent::Fault,
+        NotificationEvent::LowBuffer,
+        NotificationEvent::Notification,
+    ];
+}
+
+/// Someone mailed about events.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct Recipient {
+    /// The email address to send to.
+    pub address: String,
+    /// The events to send (all of them, by default).
+    #[cfg_attr(feature = "use_serde", serde(default = "default_recipient_events"))]
+    pub events: Vec<NotificationEvent>,
+}
+
+impl Recipient {
+    /// Creates a recipient subscribed to every event.
+    pub fn new(address: impl Into<String>) -> Self {
+        Self {
+            address: address.into(),
+            events: NotificationEvent::ALL.to_vec(),
+        }
+    }
+    /// Whether the recipient wants to hear about the given event.
+    pub fn is_subscribed(&self, event: NotificationEvent) -> bool {
+        self.events.contains(&event)
+    }
+}
+
+#[cfg(feature = "use_serde")]
+fn default_recipient_events() -> Vec<NotificationEvent> {
+    NotificationEvent::ALL.to_vec()
+}
+
+/// A recipient, given either as just an address or in full.
+#[cfg(feature = "use_serde")]
+#[derive(Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(untagged)]
+enum RecipientEntry {
+    Address(String),
+    Recipient(Recipient),
+}
+
+/// Deserializes recipients, each of which may be given as just an address.
+#[cfg(feature = "use_serde")]
+fn recipients<'de, D>(deserializer: D) -> Result<Vec<Recipient>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(
+        <Vec<RecipientEntry> as serde::Deserialize>::deserialize(deserializer)?
+            .into_iter()
+            .map(|entry| match entry {
+                RecipientEntry::Address(address) => Recipient::new(address),
+                RecipientEntry::Recipient(recipient) => recipient,
+            })
+            .collect(),
+    )
+}
+
+/// A chat service which accepts messages through incoming webhooks.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "use_serde", serde(rename_all = "lowercase"))]
+pub enum ChatService {
+    /// [Slack](https://api.slack.com/messaging/webhooks).
+    Slack,
+    /// [Discord](https://discord.com/developers/docs/resources/webhook).
+    Discord,
+}
+
+/// Configures a chat channel notified of events through an incoming webhook.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct ChatConfig {
+    /// The channel's incoming webhook URL.
+    pub url: String,
+    /// The service the webhook belongs to, if it can't be told from the URL.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub service: Option<ChatService>,
+    /// The events to post (all of them, by default).
+    #[cfg_attr(feature = "use_serde", serde(default = "default_recipient_events"))]
+    pub events: Vec<NotificationEvent>,
+}
+
+impl ChatConfig {
+    /// Creates a configuration posting every event to the given webhook.
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            service: None,
+            events: NotificationEvent::ALL.to_vec(),
+        }
+    }
+    /// The service the webhook belongs to: the configured one, or else Discord for Discord URLs
+    /// and Slack otherwise.
+    pub fn service(&self) -> ChatService {
+        self.service.unwrap_or_else(|| {
+            if self.url.contains("discord.com/") || self.url.contains("discordapp.com/") {
+                ChatService::Discord
+            } else {
+                ChatService::Slack
+            }
+        })
+    }
+}
+
+/// Configures texting faults to the administrators through Twilio.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct SmsConfig {
+    /// The Twilio account SID.
+    pub account: String,
+    /// The Twilio auth token.
+    pub token: String,
+    /// The Twilio number to send from (in E.164 format, e.g. `+15551234567`).
+    pub from: String,
+    /// The numbers to text (in E.164 format).
+    pub to: Vec<String>,
+}
+
+/// Configures push notifications to phones, through [ntfy](https://ntfy.sh) or
+/// [Pushover](https://pushover.net).
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(
+    feature = "use_serde",
+    serde(tag = "service", rename_all = "lowercase")
+)]
+pub enum PushConfig {
+    /// An ntfy topic, on ntfy.sh or a self-hosted server.
+    Ntfy {
+        /// The server the topic is on.
+        #[cfg_attr(feature = "use_serde", serde(default = "default_ntfy_server"))]
+        server: String,
+        /// The topic to publish to.
+        topic: String,
+        /// The access token for the topic, if it's protected.
+        #[cfg_attr(
+            feature = "use_serde",
+            serde(default, skip_serializing_if = "Option::is_none")
+        )]
+        token: Option<String>,
+        /// The events to push (all of them, by default).
+        #[cfg_attr(feature = "use_serde", serde(default = "default_recipient_events"))]
+        events: Vec<NotificationEvent>,
+    },
+    /// A Pushover user (or group).
+    Pushover {
+        /// The Pushover application's API token.
+        token: String,
+        /// The user (or group) key to send to.
+        user: String,
+        /// The events to push (all of them, by default).
+        #[cfg_attr(feature = "use_serde", serde(default = "default_recipient_events"))]
+        events: Vec<NotificationEvent>,
+    },
+}
+
+impl PushConfig {
+    /// The public ntfy server.
+    pub const NTFY: &'static str = "https://ntfy.sh";
+    /// The events to push.
+    pub fn events(&self) -> &[NotificationEvent] {
+        match self {
+            PushConfig::Ntfy { events, .. } | PushConfig::Pushover { events, .. } => events,
+        }
+    }
+}
+
+#[cfg(feature = "use_serde")]
+fn default_ntfy_server() -> String {
+    PushConfig::NTFY.into()
+}
+
+/// Configures the deduplication and rate limiting of notifications, so that (e.g.) a flapping
+/// sensor doesn't flood everyone's inbox.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct NotificationThrottleConfig {
+    /// How long an identical notification is suppressed for, and how long suppression must let up
+    /// before a summary of what was suppressed is sent.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default = "default_notification_throttle_window")
+    )]
+    pub window: Duration,
+    /// How many notifications of each kind may be sent per period (or zero for no limit).
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default = "default_notification_throttle_limit")
+    )]
+    pub limit: u32,
+    /// The period over which the limit applies.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default = "default_notification_throttle_period")
+    )]
+    pub period: Duration,
+}
+
+impl Default for NotificationThrottleConfig {
+    fn default() -> Self {
+        Self {
+            window: Duration::new(600, 0),
+            limit: 10,
+            period: Duration::new(3600, 0),
+        }
+    }
+}
+
+#[cfg(feature = "use_serde")]
+fn default_notification_throttle_window() -> Duration {
+    NotificationThrottleConfig::default().window
+}
+
+#[cfg(feature = "use_serde")]
+fn default_notification_throttle_limit() -> u32 {
+    NotificationThrottleConfig::default().limit
+}
+
+#[cfg(feature = "use_serde")]
+fn default_notification_throttle_period() -> Duration {
+    NotificationThrottleConfig::default().period
+}
+
+/// Configures the logs attached to fault notifications.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct FaultReportConfig {
+    /// Whether to attach the end of the event log (if one is kept).
+    #[cfg_attr(feature = "use_serde", serde(default = "default_fault_report_events"))]
+    pub events: bool,
+    /// The application log to attach the end of, if any (e.g. the file standard error is
+    /// redirected to).
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub log: Option<PathBuf>,
+    /// How many lines of each log to attach.
+    #[cfg_attr(feature = "use_serde", serde(default = "default_fault_report_lines"))]
+    pub lines: usize,
+}
+
+impl Default for FaultReportConfig {
+    fn default() -> Self {
+        Self {
+            events: true,
+            log: None,
+            lines: 200,
+        }
+    }
+}
+
+#[cfg(feature = "use_serde")]
+fn default_fault_report_events() -> bool {
+    FaultReportConfig::default().events
+}
+
+#[cfg(feature = "use_serde")]
+fn default_fault_report_lines() -> usize {
+    FaultReportConfig::default().lines
+}
+
+/// How the connection to an SMTP server is secured.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "use_serde", serde(rename_all = "lowercase"))]
+pub enum MailSecurity {
+    /// The connection isn't encrypted.
+    None,
+    /// The connection is upgraded with STARTTLS (which the server must support).
+    StartTls,
+    /// The connection is encrypted from the start (implicit TLS, or SMTPS).
+    Tls,
+}
+
+impl Default for MailSecurity {
+    fn default() -> Self {
+        MailSecurity::StartTls
+    }
+}
+
+/// A protocol event which can trigger webhooks.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "use_serde", serde(rename_all = "lowercase"))]
+pub enum WebhookEvent {
+    /// A protocol has started.
+    Started,
+    /// A step has begun.
+    Step,
+    /// A protocol has run to completion.
+    Completed,
+    /// A hardware fault has been detected.
+    Faulted,
+    /// A step has fired a webhook hook (see [`Hook::Webhook`]).
+    ///
+    /// [`Hook::Webhook`]: ../enum.Hook.html#variant.Webhook
+    Hook,
+}
+
+/// Configures a webhook, which receives a JSON `POST` for each event it's interested in.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct WebhookConfig {
+    /// The URL to post to.
+    pub url: String,
+    /// The events to post (every event, if empty).
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub events: Vec<WebhookEvent>,
+    /// The secret used to sign each request, if any.
+    ///
+    /// Signed requests carry an `X-Deoxy-Signature: sha256=<hex>` header, giving the HMAC-SHA256 of
+    /// the body.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub secret: Option<String>,
+}
+
+/// Configures the event log, which records every transition, operator action, and fault.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "use_serde", serde(rename_all = "kebab-case"))]
+pub struct EventLogConfig {
+    /// The path to the log file.
+    pub path: PathBuf,
+    /// How large (in bytes) the log may grow before it's rotated.
+    #[cfg_attr(feature = "use_serde", serde(default = "default_event_log_max_size"))]
+    pub max_size: u64,
+    /// How many rotated logs are kept (as `<path>.1`, `<path>.2`, and so on).
+    #[cfg_attr(feature = "use_serde", serde(default = "default_event_log_keep"))]
+    pub keep: usize,
+}
+
+impl EventLogConfig {
+    /// Creates an event log configuration with the default rotation settings.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            max_size: 10 * 1024 * 1024,
+            keep: 5,
+        }
+    }
+}
+
+#[cfg(feature = "use_serde")]
+fn default_event_log_max_size() -> u64 {
+    EventLogConfig::new("").max_size
+}
+
+#[cfg(feature = "use_serde")]
+fn default_event_log_keep() -> usize {
+    EventLogConfig::new("").keep
+}
+
+/// Names the buffer supplied through a valve, describing it for the operator.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct BufferConfig {
+    /// The valve (motor) the buffer is supplied through.
+    #[cfg_attr(feature = "use_serde", serde(alias = "motor"))]
+    pub valve: MotorId,
+    /// The name of the buffer (e.g. `PBS` or `1% SDS`).
+    pub name: String,
+    /// A longer description of the buffer, if any.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub description: Option<String>,
+    /// The volume of the buffer's reservoir, if known.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub volume: Option<quantity::Volume>,
+    /// Any hazards of handling the buffer (e.g. `Corrosive; wear gloves.`).
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub hazards: Option<String>,
+}
+
+impl BufferConfig {
+    /// Names the buffer supplied through the given valve.
+    pub fn new(valve: MotorId, name: impl Into<String>) -> Self {
+        Self {
+            valve,
+            name: name.into(),
+            description: None,
+            volume: None,
+            hazards: None,
+        }
+    }
+}
+
+/// The configured buffers, for referring to them by name (see [`Config::buffers`]).
+///
+/// Buffers without a configuration are referred to by their valves' indices (as `buffer 2`).
+///
+/// [`Config::buffers`]: struct.Config.html#structfield.buffers
+#[derive(Clone, Debug)]
+pub struct Buffers(Arc<[BufferConfig]>);
+
+impl Default for Buffers {
+    fn default() -> Self {
+        Self::new(vec![])
+    }
+}
+
+impl Buffers {
+    /// Wraps the given buffer configurations.
+    pub fn new(buffers: Vec<BufferConfig>) -> Self {
+        Self(buffers.into())
+    }
+    /// The configuration of the buffer supplied through the given valve, if it has one.
+    pub fn get(&self, valve: MotorId) -> Option<&BufferConfig> {
+        self.0.iter().find(|buffer| buffer.valve == valve)
+    }
+    /// The name of the buffer supplied through the given valve.
+    pub fn name(&self, valve: MotorId) -> String {
+        self.get(valve)
+            .map_or_else(|| format!("buffer {}", valve), |buffer| buffer.name.clone())
+    }
+    /// The configured buffers.
+    pub fn iter(&self) -> impl Iterator<Item = &BufferConfig> {
+        self.0.iter()
+    }
+}
+
+/// Configures a buffer reservoir's level sensor.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct ReservoirConfig {
+    /// The buffer held in the reservoir.
+    pub buffer: MotorId,
+    /// The volume of a full reservoir.
+    pub capacity: quantity::Volume,
+    /// The volume below which the reservoir is considered low.
+    ///
+    /// For float switches, this is the volume remaining when the switch trips.
+    pub low: quantity::Volume,
+    /// The sensor measuring the level of the reservoir.
+    pub sensor: LevelSensorConfig,
+}
+
+/// Selects the kind of sensor used to measure a reservoir's level.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "use_serde", serde(tag = "kind", rename_all = "lowercase"))]
+pub enum LevelSensorConfig {
+    /// A float switch mounted at the low mark.
+    Float {
+        /// The input pin the switch is connected to.
+        pin: u16,
+        /// If true, the switch pulls the pin low (rather than high) while the level is above the
+        /// mark.
+        #[cfg_attr(feature = "use_serde", serde(default))]
+        invert: bool,
+    },
+    /// A sensor whose reading is proportional to the volume remaining.
+    Analog {
+        /// The converter the sensor is connected to.
+        adc: AdcConfig,
+        /// The converter channel the sensor is connected to.
+        channel: u8,
+    },
+}
+
+/// Configures temperature control.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct ThermalConfig {
+    /// The temperature sensor.
+    pub sensor: TemperatureSensorConfig,
+    /// The output pin switching the heater (relay), if any.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub heater: Option<u16>,
+    /// The output pin switching the chiller (relay), if any.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub chiller: Option<u16>,
+    /// How often to check the temperature.
+    #[cfg_attr(feature = "use_serde", serde(default = "default_thermal_interval"))]
+    pub interval: Duration,
+}
+
+#[cfg(feature = "use_serde")]
+fn default_thermal_interval() -> Duration {
+    Duration::new(1, 0)
+}
+
+impl ThermalConfig {
+    /// Creates a thermostat using the given backend for the heater and chiller outputs.
+    pub fn thermostat(&self, backend: &dyn GpioBackend) -> Result<Thermostat, PinError> {
+        let output = |pin: Option<u16>| {
+            pin.map(|number| Pin::with_backend(backend, number))
+                .transpose()
+        };
+        Ok(Thermostat::new(
+            self.sensor.open()?,
+            output(self.heater)?,
+            output(self.chiller)?,
+            self.interval,
+        ))
+    }
+}
+
+/// Selects the temperature sensor.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[cfg_attr(feature = "use_serde", serde(tag = "kind", rename_all = "lowercase"))]
+pub enum TemperatureSensorConfig {
+    /// A DS18B20 1-Wire thermometer.
+    Ds18b20 {
+        /// The device ID (e.g. `28-0316a2799dff`).
+        id: String,
+    },
+    /// An NTC thermistor forming the low side of a voltage divider read by an ADC.
+    Thermistor {
+        /// The converter the divider is connected to.
+        adc: AdcConfig,
+        /// The converter channel the divider is connected to.
+        channel: u8,
+        /// The β coefficient of the thermistor, in K.
+        beta: f64,
+        /// The resistance of the thermistor at 25 °C, in Ω.
+        nominal: f64,
+        /// The resistance of the fixed resistor in the divider, in Ω.
+        series: f64,
+    },
+}
+
+impl TemperatureSensorConfig {
+    /// Opens the configured sensor.
+    pub fn open(&self) -> Result<Box<dyn TemperatureSensor>, PinError> {
+        match self {
+            Self::Ds18b20 { id } => Ok(Box::new(thermal::Ds18b20::new(id))),
+            Self::Thermistor {
+                adc,
+                channel,
+                beta,
+                nominal,
+                series,
+            } => Ok(Box::new(thermal::Thermistor::new(
+                adc.open()?,
+                *channel,
+                *beta,
+                *nominal,
+                *series,
+            ))),
+        }
+    }
+}
+
+/// Specifies a stepper motor.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct StepperConfig {
+    /// The pin advancing the motor by one step on each pulse.
+    pub step: u16,
+    /// The pin selecting the direction of rotation.
+    pub dir: u16,
+    /// The (active-low) pin enabling the driver, if connected.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub enable: Option<u16>,
+    /// The input pin of the homing switch (closed at the open position), if any.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub home: Option<u16>,
+    /// The number of (micro)steps in a full revolution.
+    #[cfg_attr(feature = "use_serde", serde(rename = "steps-per-revolution"))]
+    pub steps_per_revolution: u32,
+    /// How long to hold each half of a step pulse.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default = "default_step_delay", rename = "step-delay")
+    )]
+    pub step_delay: Duration,
+    /// The angles of any additional named positions (e.g. "bypass"), for use in protocols.
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, skip_serializing_if = "BTreeMap::is_empty")
+    )]
+    pub positions: BTreeMap<String, u16>,
+    /// An optional label for the motor (perhaps the buffer associated with it?).
+    #[cfg_attr(feature = "use_serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub label: Option<String>,
+}
+
+#[cfg(feature = "use_serde")]
+fn default_step_delay() -> Duration {
+    Duration::from_millis(1)
+}
+
+impl StepperConfig {
+    /// Creates the stepper motor using the given backend.
+    pub fn motor(&self, backend: &dyn GpioBackend) -> Result<StepperMotor, DeviceError> {
+        StepperMotor::new(
+            Pin::with_backend(backend, self.step)?,
+            Pin::with_backend(backend, self.dir)?,
+            self.enable
+                .map(|number| Pin::with_backend(backend, number))
+                .transpose()?,
+            self.home.map(|number| backend.input(number)).transpose()?,
+            self.steps_per_revolution,
+            self.step_delay,
+        )
+        .with_positions(self.positions.clone())
+    }
 }
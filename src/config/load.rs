@@ -0,0 +1,490 @@
+//! Loading a configuration file, with overrides from the environment and the command line.
+//!
+//! Configuration files may be written in TOML, JSON, or YAML (with the `yaml` feature), going by
+//! their extension. Whatever the format, the file is read into a JSON value, which the selected
+//! profile and any overrides are applied to before it's deserialized.
+//!
+//! Without an explicit path, the configuration is looked for in `./deoxy.toml`,
+//! `$XDG_CONFIG_HOME/deoxy/config.toml` (`~/.config` by default), and `/etc/deoxy/config.toml`,
+//! in that order.
+
+use std::{
+    env, error,
+    ffi::OsString,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde_json::{Map, Value};
+
+use super::Config;
+
+/// The prefix of environment variables overriding configuration keys.
+const PREFIX: &str = "DEOXY_";
+/// The key of the table of profiles.
+const PROFILES: &str = "profiles";
+/// The override (`DEOXY_PROFILE` or `--profile`) selecting a profile, rather than setting a key.
+const PROFILE: &str = "profile";
+/// The configuration file in the working directory.
+const LOCAL: &str = "deoxy.toml";
+/// The system-wide configuration file.
+const SYSTEM: &str = "/etc/deoxy/config.toml";
+
+/// An error encountered in loading a configuration.
+#[derive(Debug)]
+pub enum LoadError {
+    /// The configuration file could not be read.
+    Io(io::Error),
+    /// No path was given, and there's no configuration file at any of the standard paths.
+    NotFound(Vec<PathBuf>),
+    /// The configuration file isn't valid TOML.
+    Toml(toml::de::Error),
+    /// The configuration file isn't valid JSON.
+    Json(serde_json::Error),
+    /// The configuration file isn't valid YAML.
+    #[cfg(feature = "yaml")]
+    Yaml(serde_yaml::Error),
+    /// The configuration file's format isn't supported (without enabling a feature).
+    Unsupported(&'static str),
+    /// The configuration (with any overrides) isn't laid out as expected (e.g. a key is missing or
+    /// has the wrong type).
+    Structure(serde_json::Error),
+    /// The selected profile isn't in the configuration file.
+    Profile {
+        /// The name of the profile.
+        name: String,
+        /// The profiles which are in the file.
+        available: Vec<String>,
+    },
+    /// The given override could not be applied.
+    Override {
+        /// The key overridden.
+        key: String,
+        /// Why it couldn't be.
+        reason: String,
+    },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(_) => write!(f, "Failed to read the configuration"),
+            Self::NotFound(paths) => {
+                let paths = paths
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect::<Vec<_>>();
+                write!(f, "No configuration found (tried {})", paths.join(", "))
+            }
+            Self::Toml(_) => write!(f, "Failed to parse the configuration as TOML"),
+            Self::Json(_) => write!(f, "Failed to parse the configuration as JSON"),
+            #[cfg(feature = "yaml")]
+            Self::Yaml(_) => write!(f, "Failed to parse the configuration as YAML"),
+            Self::Unsupported(what) => write!(f, "Unsupported configuration format: {}", what),
+            Self::Structure(_) => write!(f, "Invalid configuration"),
+            Self::Profile { name, available } if available.is_empty() => write!(
+                f,
+                "There is no profile named {} (the configuration has no profiles)",
+                name
+            ),
+            Self::Profile { name, available } => write!(
+                f,
+                "There is no profile named {} (try {})",
+                name,
+                available.join(", ")
+            ),
+            Self::Override { key, reason } => write!(f, "Failed to override {}: {}", key, reason),
+        }
+    }
+}
+
+impl error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Toml(err) => Some(err),
+            Self::Json(err) | Self::Structure(err) => Some(err),
+            #[cfg(feature = "yaml")]
+            Self::Yaml(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// The format of a configuration file.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Format {
+    Toml,
+    Json,
+    Yaml,
+}
+
+impl Format {
+    /// The format of the file at the given path, going by its extension (TOML unless otherwise
+    /// recognized).
+    fn of(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Self::Json,
+            Some("yaml") | Some("yml") => Self::Yaml,
+            _ => Self::Toml,
+        }
+    }
+}
+
+impl Config {
+    /// The paths at which a configuration file is looked for (see [`locate`](#method.locate)), in
+    /// order of preference.
+    pub fn search_path() -> Vec<PathBuf> {
+        search_path(env::var_os("XDG_CONFIG_HOME"), env::var_os("HOME"))
+    }
+
+    /// The configuration file to use: the given path if there is one, or else the first of the
+    /// [standard paths](#method.search_path) at which there's a file.
+    ///
+    /// The chosen path is logged, so that it's clear at startup which file is in use.
+    pub fn locate(path: Option<&Path>) -> Result<PathBuf, LoadError> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => {
+                let paths = Self::search_path();
+                match paths.iter().find(|path| path.is_file()) {
+                    Some(path) => path.clone(),
+                    None => return Err(LoadError::NotFound(paths)),
+                }
+            }
+        };
+        log::info!("Using the configuration at {}", path.display());
+        Ok(path)
+    }
+
+    /// Reads the configuration file at the given path, in TOML, JSON (if its extension is
+    /// `.json`), or YAML (if its extension is `.yaml` or `.yml`, with the `yaml` feature).
+    ///
+    /// Any profiles in the file are ignored (see [`load`](#method.load)).
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, LoadError> {
+        let mut config = read(path.as_ref())?;
+        select(&mut config, None)?;
+        deserialize(config)
+    }
+    /// Reads the configuration file at the given path (see [`from_path`](#method.from_path)),
+    /// overriding its keys with any `DEOXY_*` environment variables and then with the given
+    /// command-line flags.
+    ///
+    /// Any key can be overridden, so that (e.g.) a fleet of devices can share a configuration file
+    /// but differ in their credentials:
+    ///
+    /// - `DEOXY_MAIL__PASSWORD=hunter2` sets `password` in the `[mail]` table: the variable's name
+    ///   (after the prefix) is the key's path, with tables separated by `__`, `_` standing in for
+    ///   `-`, and array elements given by their index (as in `DEOXY_MOTORS__0__PIN=17`).
+    /// - `--mail.password=hunter2` (or `--mail.password hunter2`) does the same on the command
+    ///   line, with tables separated by `.`.
+    ///
+    /// Values are read as TOML (so `8080` is a number and `[600, 2400]` an array), whatever the
+    /// format of the file; anything else is taken as a string. Every argument must be an override
+    /// flag; anything else is an error.
+    ///
+    /// # Profiles
+    ///
+    /// A file can describe several rigs at once (e.g. a development machine, a bench rig, and the
+    /// production instrument), so that the same file can be used everywhere. Each profile is a
+    /// table under `[profiles]` holding the keys in which the rig differs, which replace those at
+    /// the top level (tables are merged key by key; anything else, such as the list of motors, is
+    /// replaced outright):
+    ///
+    /// ```toml
+    /// [[motors]]
+    /// pin = 26
+    ///
+    /// [profiles.bench-rig]
+    /// gpio = { driver = "mock" }
+    /// motors = [{ pin = 5 }]
+    /// ```
+    ///
+    /// A profile is selected with `DEOXY_PROFILE=bench-rig` or `--profile bench-rig` (the flag
+    /// taking precedence), before any other overrides are applied. Without one, the keys at the top
+    /// level are used as they are.
+    pub fn load(
+        path: impl AsRef<Path>,
+        args: impl IntoIterator<Item = String>,
+    ) -> Result<Self, LoadError> {
+        let mut config = read(path.as_ref())?;
+        let mut overrides = vec![];
+        for (name, value) in env::vars() {
+            if let Some(key) = name.strip_prefix(PREFIX) {
+                let key = key
+                    .split("__")
+                    .map(|part| part.to_lowercase().replace('_', "-"))
+                    .collect::<Vec<_>>();
+                overrides.push((key, value));
+            }
+        }
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let flag = match arg.strip_prefix("--") {
+                Some(flag) => flag,
+                None => {
+                    return Err(LoadError::Override {
+                        key: arg.clone(),
+                        reason: "expected a flag like --mail.password=hunter2".into(),
+                    })
+                }
+            };
+            let (key, value) = match flag.find('=') {
+                Some(index) => (&flag[..index], flag[index + 1..].to_string()),
+                None => match args.next() {
+                    Some(value) => (flag, value),
+                    None => {
+                        return Err(LoadError::Override {
+                            key: flag.into(),
+                            reason: "no value was given".into(),
+                        })
+                    }
+                },
+            };
+            let key = key.split('.').map(String::from).collect::<Vec<_>>();
+            overrides.push((key, value));
+        }
+        let (profiles, overrides): (Vec<_>, Vec<_>) = overrides
+            .into_iter()
+            .partition(|(key, _)| key.len() == 1 && key[0] == PROFILE);
+        let profile = profiles.into_iter().last().map(|(_, name)| name);
+        select(&mut config, profile.as_ref().map(String::as_str))?;
+        for (key, value) in overrides {
+            set(&mut config, &key, &value)?;
+        }
+        deserialize(config)
+    }
+}
+
+/// The standard configuration paths, given `$XDG_CONFIG_HOME` and `$HOME`.
+fn search_path(config_home: Option<OsString>, home: Option<OsString>) -> Vec<PathBuf> {
+    let mut paths = vec![PathBuf::from(LOCAL)];
+    // Per the XDG spec, a relative (or empty) $XDG_CONFIG_HOME is ignored.
+    let config_home = config_home
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| home.map(|home| PathBuf::from(home).join(".config")));
+    if let Some(config_home) = config_home {
+        paths.push(config_home.join("deoxy").join("config.toml"));
+    }
+    paths.push(PathBuf::from(SYSTEM));
+    paths
+}
+
+/// Applies the profile with the given name (if any) to the configuration, removing the profiles.
+fn select(config: &mut Value, profile: Option<&str>) -> Result<(), LoadError> {
+    let profiles = match config {
+        Value::Object(table) => table.remove(PROFILES),
+        _ => None,
+    };
+    let name = match profile {
+        Some(name) => name,
+        None => return Ok(()),
+    };
+    let mut profiles = match profiles {
+        Some(Value::Object(profiles)) => profiles,
+        _ => Map::new(),
+    };
+    match profiles.remove(name) {
+        Some(overlay) => {
+            merge(config, overlay);
+            Ok(())
+        }
+        None => Err(LoadError::Profile {
+            name: name.into(),
+            available: profiles.keys().cloned().collect(),
+        }),
+    }
+}
+
+/// Merges the given value into another, table by table (replacing anything else).
+fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Reads the configuration file at the given path into a JSON value.
+fn read(path: &Path) -> Result<Value, LoadError> {
+    let contents = fs::read_to_string(path)?;
+    match Format::of(path) {
+        Format::Toml => toml::from_str(&contents).map_err(LoadError::Toml),
+        Format::Json => serde_json::from_str(&contents).map_err(LoadError::Json),
+        #[cfg(feature = "yaml")]
+        Format::Yaml => serde_yaml::from_str(&contents).map_err(LoadError::Yaml),
+        #[cfg(not(feature = "yaml"))]
+        Format::Yaml => Err(LoadError::Unsupported("YAML (enable the yaml feature)")),
+    }
+}
+
+/// Deserializes the configuration (with any overrides applied).
+fn deserialize(config: Value) -> Result<Config, LoadError> {
+    serde_json::from_value(config).map_err(LoadError::Structure)
+}
+
+/// Reads an override's value as TOML, falling back to a string.
+fn parse(value: &str) -> Value {
+    toml::from_str::<Map<String, Value>>(&format!("value = {}", value))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| Value::String(value.into()))
+}
+
+/// Sets the key with the given path to the given value, creating any missing tables.
+fn set(config: &mut Value, key: &[String], value: &str) -> Result<(), LoadError> {
+    let error = |reason: String| LoadError::Override {
+        key: key.join("."),
+        reason,
+    };
+    let mut current = config;
+    for (depth, part) in key.iter().enumerate() {
+        current = match current {
+            Value::Object(table) => {
+                // Keys are written with dashes, but a few are written with underscores.
+                let name = table
+                    .keys()
+                    .find(|name| name.replace('_', "-") == part.replace('_', "-"))
+                    .cloned()
+                    .unwrap_or_else(|| part.clone());
+                table
+                    .entry(name)
+                    .or_insert_with(|| Value::Object(Map::new()))
+            }
+            Value::Array(array) => {
+                let index = part
+                    .parse::<usize>()
+                    .map_err(|_| error(format!("{} isn't an index into the array", part)))?;
+                let length = array.len();
+                array
+                    .get_mut(index)
+                    .ok_or_else(|| error(format!("there are only {} elements", length)))?
+            }
+            _ => {
+                let parent = key[..depth].join(".");
+                return Err(error(format!("{} isn't a table or an array", parent)));
+            }
+        };
+    }
+    *current = parse(value);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn key(path: &str) -> Vec<String> {
+        path.split('.').map(String::from).collect()
+    }
+
+    #[test]
+    fn parses_values() {
+        assert_eq!(parse("8080"), json!(8080));
+        assert_eq!(parse("true"), json!(true));
+        assert_eq!(parse("[600, 2400]"), json!([600, 2400]));
+        assert_eq!(parse("hunter2"), json!("hunter2"));
+        assert_eq!(parse("\"8080\""), json!("8080"));
+    }
+
+    #[test]
+    fn reads_formats() {
+        assert_eq!(Format::of(Path::new("deoxy.toml")), Format::Toml);
+        assert_eq!(Format::of(Path::new("deoxy.json")), Format::Json);
+        assert_eq!(Format::of(Path::new("deoxy.yml")), Format::Yaml);
+        assert_eq!(Format::of(Path::new("deoxy")), Format::Toml);
+        let dir = std::env::temp_dir();
+        let id = uuid::Uuid::new_v4();
+        let toml = dir.join(format!("deoxy-config-{}.toml", id));
+        let json = dir.join(format!("deoxy-config-{}.json", id));
+        fs::write(&toml, "drain-pump = 1\n[[motors]]\npin = 4\n").unwrap();
+        fs::write(&json, r#"{"drain-pump": 1, "motors": [{"pin": 4}]}"#).unwrap();
+        assert_eq!(read(&toml).unwrap(), read(&json).unwrap());
+        fs::remove_file(toml).unwrap();
+        fs::remove_file(json).unwrap();
+    }
+
+    #[test]
+    fn searches() {
+        let paths = search_path(Some("/srv/config".into()), Some("/home/pi".into()));
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("deoxy.toml"),
+                PathBuf::from("/srv/config/deoxy/config.toml"),
+                PathBuf::from("/etc/deoxy/config.toml"),
+            ]
+        );
+        let paths = search_path(Some("relative".into()), Some("/home/pi".into()));
+        assert_eq!(
+            paths[1],
+            PathBuf::from("/home/pi/.config/deoxy/config.toml")
+        );
+        assert_eq!(search_path(None, None).len(), 2);
+    }
+
+    #[test]
+    fn selects_profiles() {
+        let config = json!({
+            "drain-pump": 1,
+            "gpio": {"driver": "rppal"},
+            "motors": [{"pin": 4}, {"pin": 5}],
+            "profiles": {
+                "bench-rig": {"gpio": {"driver": "mock"}, "motors": [{"pin": 17}]},
+                "production": {"name": "rig-1"},
+            },
+        });
+        let mut base = config.clone();
+        select(&mut base, None).unwrap();
+        assert_eq!(base.get("profiles"), None);
+        assert_eq!(base["motors"], json!([{"pin": 4}, {"pin": 5}]));
+        let mut bench = config.clone();
+        select(&mut bench, Some("bench-rig")).unwrap();
+        assert_eq!(bench["gpio"]["driver"], json!("mock"));
+        assert_eq!(bench["motors"], json!([{"pin": 17}]));
+        assert_eq!(bench["drain-pump"], json!(1));
+        let mut missing = config;
+        match select(&mut missing, Some("dev")) {
+            Err(LoadError::Profile { available, .. }) => {
+                assert_eq!(available, vec!["bench-rig", "production"])
+            }
+            other => panic!("Expected a missing profile, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn overrides() {
+        let mut config = json!({
+            "drain-pump": 1,
+            "motors": [{"pin": 4}, {"pin": 5}],
+            "mail": {"user": "pi"},
+        });
+        set(&mut config, &key("drain-pump"), "0").unwrap();
+        set(&mut config, &key("motors.1.pin"), "17").unwrap();
+        set(&mut config, &key("mail.password"), "hunter2").unwrap();
+        set(&mut config, &key("prime.volume"), "2.5").unwrap();
+        assert_eq!(config["drain-pump"], json!(0));
+        assert_eq!(config["motors"][1]["pin"], json!(17));
+        assert_eq!(config["mail"]["user"], json!("pi"));
+        assert_eq!(config["mail"]["password"], json!("hunter2"));
+        assert_eq!(config["prime"]["volume"], json!(2.5));
+        assert!(set(&mut config, &key("motors.2.pin"), "6").is_err());
+        assert!(set(&mut config, &key("drain-pump.id"), "6").is_err());
+    }
+}
@@ -0,0 +1,697 @@
+//! Checking a configuration for mistakes before any hardware is touched.
+//!
+//! Rather than stopping at the first mistake, [`Config::validate`] reports every one it finds, each
+//! with the path of the field at fault (as written in the configuration file, e.g.
+//! `motors[1].range`) and a suggested fix.
+//!
+//! [`Config::validate`]: ../struct.Config.html#method.validate
+
+use std::{collections::HashMap, error, fmt, time::Duration};
+
+use super::{
+    BufferConfig, Config, ManifoldConfig, PumpConfig, PwmMode, TeardownStep, ValveConfig,
+    WashConfig, MAIN_MANIFOLD,
+};
+use crate::{
+    has_buffer,
+    quantity::{FlowRate, Volume},
+};
+
+/// A problem with the configuration.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Serialize))]
+pub struct Violation {
+    /// The path of the field at fault, as written in the configuration file (e.g.
+    /// `motors[1].range`).
+    pub field: String,
+    /// What's wrong with the field.
+    pub problem: String,
+    /// How the problem might be fixed.
+    pub suggestion: String,
+}
+
+impl Violation {
+    fn new(
+        field: impl Into<String>,
+        problem: impl Into<String>,
+        suggestion: impl Into<String>,
+    ) -> Self {
+        Self {
+            field: field.into(),
+            problem: problem.into(),
+            suggestion: suggestion.into(),
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {} ({})", self.field, self.problem, self.suggestion)
+    }
+}
+
+/// An error in the configuration.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ConfigError {
+    /// The configuration has the given problems (all of those found).
+    Invalid(Vec<Violation>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Invalid(violations) => {
+                write!(f, "Invalid configuration:")?;
+                for violation in violations {
+                    write!(f, "\n  - {}", violation)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl error::Error for ConfigError {}
+
+/// Finds every problem with the given configuration.
+pub(super) fn violations(config: &Config) -> Vec<Violation> {
+    let mut violations = vec![];
+    check_pins(config, &mut violations);
+    check_channels(config, &mut violations);
+    check_manifold(
+        "",
+        &config.pumps,
+        config.drain_pump,
+        &config.motors,
+        &mut violations,
+    );
+    check_buffers("", &config.buffers, config.motors.len(), &mut violations);
+    let mut names = HashMap::new();
+    names.insert(MAIN_MANIFOLD, "the main manifold".to_string());
+    for (index, manifold) in config.manifolds.iter().enumerate() {
+        let prefix = format!("manifolds[{}].", index);
+        check_manifold(
+            &prefix,
+            &manifold.pumps,
+            manifold.drain_pump,
+            &manifold.motors,
+            &mut violations,
+        );
+        check_buffers(
+            &prefix,
+            &manifold.buffers,
+            manifold.motors.len(),
+            &mut violations,
+        );
+        check_name(index, manifold, &mut names, &mut violations);
+    }
+    if let Some(sensor) = &config.flow_sensor {
+        if !positive(sensor.pulses_per_ml) {
+            violations.push(Violation::new(
+                "flow-sensor.pulses-per-ml",
+                format!(
+                    "{} pulses per mL isn't a usable calibration",
+                    sensor.pulses_per_ml
+                ),
+                "use the figure from the sensor's datasheet",
+            ));
+        }
+        if sensor.tolerance < 0.0 || sensor.tolerance.is_nan() {
+            violations.push(Violation::new(
+                "flow-sensor.tolerance",
+                format!("a tolerance of {} is negative", sensor.tolerance),
+                "use a fraction of the expected volume, such as 0.2",
+            ));
+        }
+    }
+    if let Some(sensor) = &config.pressure_sensor {
+        let [low, high] = sensor.range;
+        if !increasing(low, high) {
+            violations.push(Violation::new(
+                "pressure-sensor.range",
+                format!("the range [{}, {}] is empty or reversed", low, high),
+                "give the pressure at a zero reading first, then at full scale",
+            ));
+        } else if !increasing(low, sensor.limit) || sensor.limit > high {
+            violations.push(Violation::new(
+                "pressure-sensor.limit",
+                format!(
+                    "the limit ({} kPa) is outside the sensor's range ({} to {} kPa)",
+                    sensor.limit, low, high
+                ),
+                "choose a limit the sensor can measure",
+            ));
+        }
+        check_interval("pressure-sensor.interval", sensor.interval, &mut violations);
+    }
+    let motors = config.motors.len();
+    for (index, reservoir) in config.reservoirs.iter().enumerate() {
+        let field = |name| format!("reservoirs[{}].{}", index, name);
+        if reservoir.buffer >= motors {
+            violations.push(no_such_valve(field("buffer"), reservoir.buffer, motors));
+        }
+        let (capacity, low) = (
+            reservoir.capacity.millilitres(),
+            reservoir.low.millilitres(),
+        );
+        if !positive(capacity) {
+            violations.push(Violation::new(
+                field("capacity"),
+                format!("a capacity of {} can't hold anything", reservoir.capacity),
+                "give the volume of a full reservoir, e.g. \"500ml\"",
+            ));
+        } else if low < 0.0 || !increasing(low, capacity) {
+            violations.push(Violation::new(
+                field("low"),
+                format!(
+                    "the low mark ({}) isn't within the capacity ({})",
+                    reservoir.low, reservoir.capacity
+                ),
+                "give the volume below which the reservoir should be refilled",
+            ));
+        }
+    }
+    if let Some(thermal) = &config.thermal {
+        check_interval("thermal.interval", thermal.interval, &mut violations);
+    }
+    check_interval(
+        "supervision.interval",
+        config.supervision.interval,
+        &mut violations,
+    );
+    if let Some(watchdog) = &config.watchdog {
+        check_interval("watchdog.interval", watchdog.interval, &mut violations);
+        if watchdog.timeout >= watchdog.interval {
+            violations.push(Violation::new(
+                "watchdog.timeout",
+                format!(
+                    "the timeout ({:?}) is no shorter than the interval between pings ({:?})",
+                    watchdog.timeout, watchdog.interval
+                ),
+                "shorten the timeout or lengthen the interval",
+            ));
+        }
+    }
+    let throttle = &config.notification_throttle;
+    if throttle.limit > 0 && throttle.period == Duration::from_secs(0) {
+        violations.push(Violation::new(
+            "notification-throttle.period",
+            "a limit is set, but the period it applies to is zero",
+            "set a period, e.g. an hour, or a limit of zero for no limit",
+        ));
+    }
+    if let Some(volume) = config.prime.volume {
+        if !positive(volume.millilitres()) {
+            violations.push(Violation::new(
+                "prime.volume",
+                format!("priming with {} wouldn't prime anything", volume),
+                "give a positive volume, or leave it out to prime for the configured duration",
+            ));
+        }
+    } else if config.prime.duration == Duration::from_secs(0) {
+        violations.push(Violation::new(
+            "prime.duration",
+            "priming for no time wouldn't prime anything",
+            "give how long to run the pump for, e.g. 10 seconds",
+        ));
+    }
+    if let Some(wash) = &config.wash {
+        check_wash(wash, motors, &mut violations);
+    }
+    for (index, step) in config.teardown.iter().enumerate() {
+        let field = format!("teardown[{}]", index);
+        match step {
+            TeardownStep::Flush(buffer) if !has_buffer(motors, *buffer) => {
+                violations.push(no_such_buffer(field, *buffer, motors));
+            }
+            TeardownStep::UsePump(pump) if *pump >= config.pumps.len() => {
+                violations.push(no_such_pump(field, *pump, config.pumps.len()));
+            }
+            TeardownStep::SetPosition { motor, .. } if *motor >= motors => {
+                violations.push(no_such_valve(field, *motor, motors));
+            }
+            TeardownStep::SetPosition { motor, position }
+                if !config.motors[*motor].positions().contains_key(position) =>
+            {
+                violations.push(Violation::new(
+                    field,
+                    format!("motor {} has no position named \"{}\"", motor, position),
+                    format!("add it to motors[{}].positions", motor),
+                ));
+            }
+            _ => {}
+        }
+    }
+    violations
+}
+
+/// Reports any PCA9685 channel used by more than one device.
+fn check_channels(config: &Config, violations: &mut Vec<Violation>) {
+    let mut users = HashMap::new();
+    for (field, channel) in config.channel_uses() {
+        match users.get(&channel) {
+            Some(first) => {
+                let (bus, address, number) = channel;
+                violations.push(Violation::new(
+                    field,
+                    format!(
+                        "channel {} of the PCA9685 at {:#x} (bus {}) is also used by {}",
+                        number, address, bus, first
+                    ),
+                    "move one of them to a free channel",
+                ))
+            }
+            None => {
+                users.insert(channel, field);
+            }
+        }
+    }
+}
+
+/// Reports any pin used by more than one device.
+fn check_pins(config: &Config, violations: &mut Vec<Violation>) {
+    let mut users = HashMap::new();
+    for (field, pin) in config.pin_uses() {
+        match users.get(&pin) {
+            Some(first) => violations.push(Violation::new(
+                field,
+                format!("pin {} is also used by {}", pin, first),
+                "move one of them to a free pin",
+            )),
+            None => {
+                users.insert(pin, field);
+            }
+        }
+    }
+}
+
+/// Reports any problems with the pumps and motors of a manifold (whose fields start with the given
+/// prefix).
+fn check_manifold(
+    prefix: &str,
+    pumps: &[PumpConfig],
+    drain_pump: Option<usize>,
+    motors: &[ValveConfig],
+    violations: &mut Vec<Violation>,
+) {
+    if pumps.is_empty() {
+        violations.push(Violation::new(
+            format!("{}pumps", prefix),
+            "no pumps are configured",
+            "add a pump table with the pump's four pins",
+        ));
+    }
+    if let Some(id) = drain_pump.filter(|&id| id >= pumps.len()) {
+        violations.push(no_such_pump(
+            format!("{}drain-pump", prefix),
+            id,
+            pumps.len(),
+        ));
+    }
+    for (index, pump) in pumps.iter().enumerate() {
+        let field = |name| format!("{}pumps[{}].{}", prefix, index, name);
+        let [idle, full] = pump.duty;
+        let duty = 0.0..=1.0;
+        if !(duty.contains(&idle) && duty.contains(&full) && idle < full) {
+            violations.push(Violation::new(
+                field("duty"),
+                format!(
+                    "the duty cycles [{}, {}] aren't an increasing pair",
+                    idle, full
+                ),
+                "give the duty cycles (from 0 to 1) at zero and full speed, e.g. [0, 1]",
+            ));
+        }
+        if pump.pwm_period == Duration::from_secs(0) {
+            violations.push(Violation::new(
+                field("pwm-period"),
+                "the PWM period is zero",
+                "use the period the pump's driver expects, e.g. 1 ms",
+            ));
+        }
+        let stopped = |rate: &FlowRate| !positive(rate.millilitres_per_minute());
+        if let Some(rate) = pump.flow_rate.filter(stopped) {
+            violations.push(Violation::new(
+                field("flow-rate"),
+                format!("a flow rate of {} wouldn't move anything", rate),
+                "measure the flow rate at full speed, or leave it out",
+            ));
+        }
+    }
+    for (index, motor) in motors.iter().enumerate() {
+        let field = |name: &str| format!("{}motors[{}].{}", prefix, index, name);
+        let range_of_motion = motor.range_of_motion();
+        match motor {
+            ValveConfig::Servo(motor) => {
+                let [short, long] = motor.range;
+                if motor.period == Duration::from_secs(0) {
+                    violations.push(Violation::new(
+                        field("period"),
+                        "the period is zero",
+                        "most hobby servos expect a period of 20 ms",
+                    ));
+                }
+                if short >= long {
+                    violations.push(Violation::new(
+                        field("range"),
+                        format!(
+                            "the pulse widths [{:?}, {:?}] are empty or reversed",
+                            short, long
+                        ),
+                        "give the shortest pulse width first, e.g. [600, 2400] µs",
+                    ));
+                } else if long > motor.period {
+                    violations.push(Violation::new(
+                        field("range"),
+                        format!(
+                            "the longest pulse ({:?}) doesn't fit in the period ({:?})",
+                            long, motor.period
+                        ),
+                        "lengthen the period or shorten the pulse widths",
+                    ));
+                }
+                if range_of_motion == 0 {
+                    violations.push(Violation::new(
+                        field("range-of-motion"),
+                        "the range of motion is zero",
+                        "give the angle the servo turns through, usually 180",
+                    ));
+                }
+                if let Some(angles) = motor.angles {
+                    let largest = angles.open.max(angles.close).max(angles.shut);
+                    if largest > range_of_motion {
+                        violations.push(beyond_range(field("angles"), largest, range_of_motion));
+                    }
+                }
+                match motor.pwm {
+                    PwmMode::Hardware { channel } if channel > 1 => {
+                        violations.push(Violation::new(
+                            field("pwm.channel"),
+                            format!("there is no hardware PWM channel {}", channel),
+                            "use channel 0 or 1",
+                        ));
+                    }
+                    PwmMode::Pca9685 { channel, .. } if channel > 15 => {
+                        violations.push(Violation::new(
+                            field("pwm.channel"),
+                            format!("the PCA9685 has no channel {}", channel),
+                            "use one of its channels, 0–15",
+                        ));
+                    }
+                    _ => {}
+                }
+                if let Some(feedback) = motor.feedback {
+                    if feedback.range[0] == feedback.range[1] {
+                        violations.push(Violation::new(
+                            field("feedback.range"),
+                            "both ends of the range read the same",
+                            "give the readings at 0º and at the far end of the range of motion",
+                        ));
+                    }
+                }
+            }
+            ValveConfig::Stepper(motor) => {
+                if motor.steps_per_revolution == 0 {
+                    violations.push(Violation::new(
+                        field("steps-per-revolution"),
+                        "there are no steps in a revolution",
+                        "give the number of (micro)steps per revolution, e.g. 200",
+                    ));
+                }
+            }
+        }
+        for (name, &angle) in motor.positions() {
+            if angle > range_of_motion {
+                let field = field(&format!("positions.{}", name));
+                violations.push(beyond_range(field, angle, range_of_motion));
+            }
+        }
+    }
+}
+
+/// Checks the given buffers, supplied through the given number of valves.
+fn check_buffers(
+    prefix: &str,
+    buffers: &[BufferConfig],
+    motors: usize,
+    violations: &mut Vec<Violation>,
+) {
+    let mut valves = HashMap::new();
+    let mut names = HashMap::new();
+    for (index, buffer) in buffers.iter().enumerate() {
+        let field = |name| format!("{}buffers[{}].{}", prefix, index, name);
+        if buffer.valve >= motors {
+            violations.push(no_such_valve(field("valve"), buffer.valve, motors));
+        } else if let Some(other) = valves.insert(buffer.valve, index) {
+            violations.push(Violation::new(
+                field("valve"),
+                format!(
+                    "valve {} already supplies {}buffers[{}]",
+                    buffer.valve, prefix, other
+                ),
+                "give each valve at most one buffer",
+            ));
+        }
+        if buffer.name.trim().is_empty() {
+            violations.push(Violation::new(
+                field("name"),
+                "the name is empty",
+                "name the buffer as it's labelled, e.g. PBS",
+            ));
+        } else if let Some(other) = names.insert(buffer.name.as_str(), index) {
+            violations.push(Violation::new(
+                field("name"),
+                format!(
+                    "\"{}\" is also the name of {}buffers[{}]",
+                    buffer.name, prefix, other
+                ),
+                "give every buffer a different name",
+            ));
+        }
+        let empty = |volume: &Volume| !positive(volume.millilitres());
+        if let Some(volume) = buffer.volume.filter(empty) {
+            violations.push(Violation::new(
+                field("volume"),
+                format!("a volume of {} isn't positive", volume),
+                "give the volume of a full reservoir, e.g. \"500ml\"",
+            ));
+        }
+    }
+}
+
+/// Reports a manifold named after another (or the main manifold).
+fn check_name<'a>(
+    index: usize,
+    manifold: &'a ManifoldConfig,
+    names: &mut HashMap<&'a str, String>,
+    violations: &mut Vec<Violation>,
+) {
+    let field = format!("manifolds[{}].name", index);
+    match names.get(manifold.name.as_str()) {
+        Some(other) => violations.push(Violation::new(
+            field,
+            format!("\"{}\" is also the name of {}", manifold.name, other),
+            "give every manifold a different name",
+        )),
+        None => {
+            names.insert(&manifold.name, field);
+        }
+    }
+}
+
+/// Whether the given value is positive (and not NaN).
+fn positive(value: f64) -> bool {
+    value > 0.0
+}
+
+/// Whether the given values are in increasing order (and not NaN).
+fn increasing(low: f64, high: f64) -> bool {
+    low < high
+}
+
+fn check_interval(field: &str, interval: Duration, violations: &mut Vec<Violation>) {
+    if interval == Duration::from_secs(0) {
+        violations.push(Violation::new(
+            field,
+            "the interval is zero",
+            "give how often to check, e.g. every second",
+        ));
+    }
+}
+
+fn beyond_range(field: String, angle: u16, range_of_motion: u16) -> Violation {
+    Violation::new(
+        field,
+        format!(
+            "{}º is beyond the range of motion ({}º)",
+            angle, range_of_motion
+        ),
+        "use an angle within the range of motion, or widen it",
+    )
+}
+
+/// Checks the wash routine, given the number of motors.
+fn check_wash(wash: &WashConfig, motors: usize, violations: &mut Vec<Violation>) {
+    if !has_buffer(motors, wash.buffer) {
+        violations.push(no_such_buffer("wash.buffer".into(), wash.buffer, motors));
+    }
+    if wash.fill == Duration::from_secs(0) || wash.repetitions == 0 {
+        violations.push(Violation::new(
+            "wash",
+            "the wash never fills the chamber",
+            "give how long to fill it for and how many times, e.g. 30 seconds, 3 times",
+        ));
+    }
+}
+
+fn no_such_valve(field: String, motor: usize, count: usize) -> Violation {
+    Violation::new(
+        field,
+        format!("there is no valve {} ({} are configured)", motor, count),
+        "refer to a configured valve (valve 0 is waste)",
+    )
+}
+
+fn no_such_buffer(field: String, buffer: usize, count: usize) -> Violation {
+    Violation::new(
+        field,
+        format!(
+            "there is no valve for buffer {} ({} motors are configured)",
+            buffer, count
+        ),
+        format!(
+            "buffer {} needs motor {} (motor 0 is the waste valve)",
+            buffer,
+            buffer + 1
+        ),
+    )
+}
+
+fn no_such_pump(field: String, pump: usize, count: usize) -> Violation {
+    Violation::new(
+        field,
+        format!("there is no pump {} ({} are configured)", pump, count),
+        "refer to a configured pump (pumps are numbered from 0)",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MotorConfig, StepperConfig};
+
+    fn servo(pin: u16, range: [u64; 2]) -> ValveConfig {
+        ValveConfig::Servo(MotorConfig {
+            pin,
+            label: None,
+            period: Duration::from_millis(20),
+            range: [
+                Duration::from_millis(range[0]),
+                Duration::from_millis(range[1]),
+            ],
+            pwm: PwmMode::Software,
+            inverted: false,
+            range_of_motion: 180,
+            angles: None,
+            positions: Default::default(),
+            ramp: None,
+            detach_after: None,
+            feedback: None,
+        })
+    }
+
+    #[test]
+    fn accepts_sensible() {
+        let mut violations = vec![];
+        let pumps = [PumpConfig::new([1, 2, 3, 4])];
+        let motors = [servo(5, [1, 2]), servo(6, [1, 2])];
+        check_manifold("", &pumps, Some(0), &motors, &mut violations);
+        assert_eq!(violations, vec![]);
+    }
+
+    #[test]
+    fn reports_buffers() {
+        let mut sds = BufferConfig::new(2, "1% SDS");
+        sds.volume = Some(Volume::from_millilitres(0.0));
+        let buffers = [
+            BufferConfig::new(1, "PBS"),
+            BufferConfig::new(1, "PBS"),
+            sds,
+            BufferConfig::new(3, ""),
+        ];
+        let mut violations = vec![];
+        check_buffers("", &buffers, 3, &mut violations);
+        let fields = violations
+            .iter()
+            .map(|violation| violation.field.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            vec![
+                "buffers[1].valve",
+                "buffers[1].name",
+                "buffers[2].volume",
+                "buffers[3].valve",
+                "buffers[3].name",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_wash_buffers() {
+        let wash = |buffer| WashConfig {
+            buffer,
+            fill: Duration::from_secs(30),
+            drain: Duration::from_secs(30),
+            repetitions: 3,
+        };
+        let mut violations = vec![];
+        check_wash(&wash(1), 3, &mut violations);
+        assert_eq!(violations, vec![]);
+        // Buffer 2 would need a fourth motor.
+        check_wash(&wash(2), 3, &mut violations);
+        let fields = violations
+            .iter()
+            .map(|violation| violation.field.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(fields, vec!["wash.buffer"]);
+    }
+
+    #[test]
+    fn reports_everything() {
+        let mut stepper = StepperConfig {
+            step: 7,
+            dir: 8,
+            enable: None,
+            home: None,
+            steps_per_revolution: 0,
+            step_delay: Duration::from_millis(1),
+            positions: Default::default(),
+            label: None,
+        };
+        stepper.positions.insert("bypass".into(), 270);
+        let motors = [servo(5, [2, 1]), servo(6, [1, 30]), stepper.into()];
+        let mut violations = vec![];
+        check_manifold("manifolds[0].", &[], Some(1), &motors, &mut violations);
+        let fields = violations
+            .iter()
+            .map(|violation| violation.field.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            vec![
+                "manifolds[0].pumps",
+                "manifolds[0].drain-pump",
+                "manifolds[0].motors[0].range",
+                "manifolds[0].motors[1].range",
+                "manifolds[0].motors[2].steps-per-revolution",
+                "manifolds[0].motors[2].positions.bypass",
+            ]
+        );
+        assert_eq!(
+            ConfigError::Invalid(violations[2..3].to_vec()).to_string(),
+            "Invalid configuration:\n  - manifolds[0].motors[0].range: the pulse widths [2ms, 1ms] \
+             are empty or reversed (give the shortest pulse width first, e.g. [600, 2400] µs)"
+        );
+    }
+}
@@ -0,0 +1,141 @@
+//! Errors and failure reports shared by the actuators (motors and pumps).
+//!
+//! Commands sent to a device are answered with an [`Error`](enum.Error.html) if they can't be
+//! carried out. Some failures happen after the command has been answered, though (e.g. partway
+//! through a ramp), so a device may also be given a supervisor to which it reports them as
+//! [`Failed`](struct.Failed.html) messages instead of only logging them.
+
+use std::fmt;
+
+use actix_web::actix::Recipient;
+
+use crate::{actix::*, pin::Error as PinError, MotorId, PumpId};
+
+/// An error encountered by a motor or pump.
+#[derive(Debug)]
+pub enum Error {
+    /// One of the device's pins couldn't be acquired, written to, or read from.
+    Pin(PinError),
+    /// The motor on the given pin can't be moved to the given angle (in degrees).
+    OutOfRange {
+        /// The (first) pin of the motor.
+        pin: u16,
+        /// The angle requested.
+        angle: u16,
+        /// The motor's range of motion.
+        range: u16,
+    },
+    /// The motor on the given pin has no position with the given name.
+    NoSuchPosition {
+        /// The (first) pin of the motor.
+        pin: u16,
+        /// The name of the position.
+        name: String,
+    },
+    /// A motor's range of motion must be greater than zero.
+    NoRangeOfMotion,
+    /// The stepper motor on the given pin didn't reach its homing switch within a revolution.
+    NotHomed(u16),
+    /// The pump's flow rate hasn't been calibrated, so it can't dispense volumes.
+    NoFlowRate,
+    /// A dispense was interrupted by another command before it finished.
+    Interrupted,
+}
+
+impl From<PinError> for Error {
+    fn from(err: PinError) -> Self {
+        Self::Pin(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Pin(err) => err.fmt(f),
+            Self::OutOfRange { pin, angle, range } => write!(
+                f,
+                "Motor on pin {} can't reach {}° (range of motion: {}°)",
+                pin, angle, range
+            ),
+            Self::NoSuchPosition { pin, name } => {
+                write!(f, "Motor on pin {} has no position named {}", pin, name)
+            }
+            Self::NoRangeOfMotion => write!(f, "Motor range of motion must be positive"),
+            Self::NotHomed(pin) => write!(f, "Stepper motor on pin {} failed to reach home", pin),
+            Self::NoFlowRate => write!(f, "Can't dispense without a calibrated flow rate"),
+            Self::Interrupted => write!(f, "Dispense interrupted"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Pin(err) => std::error::Error::source(err),
+            _ => None,
+        }
+    }
+}
+
+/// Identifies a device to its supervisor.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Device {
+    /// The motor with the given index.
+    Motor(MotorId),
+    /// The pump with the given index.
+    Pump(PumpId),
+}
+
+impl fmt::Display for Device {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Motor(motor) => write!(f, "motor {}", motor),
+            Self::Pump(pump) => write!(f, "pump {}", pump),
+        }
+    }
+}
+
+/// Tells a device's supervisor that the device failed outside of any command.
+#[derive(Debug)]
+pub struct Failed {
+    /// The device which failed.
+    pub device: Device,
+    /// What went wrong.
+    pub error: Error,
+}
+
+impl ActixMessage for Failed {
+    type Result = ();
+}
+
+/// Where a device reports its failures, if anywhere.
+#[derive(Default)]
+pub(crate) struct Supervisor(Option<(Device, Recipient<Failed>)>);
+
+impl fmt::Debug for Supervisor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Supervisor")
+            .field(&self.0.as_ref().map(|(device, _)| device))
+            .finish()
+    }
+}
+
+impl Supervisor {
+    /// Reports failures of the given device to the given recipient.
+    pub(crate) fn new(device: Device, recipient: Recipient<Failed>) -> Self {
+        Self(Some((device, recipient)))
+    }
+    /// Logs the given failure and reports it to the supervisor (if any).
+    pub(crate) fn report(&self, error: Error) {
+        match &self.0 {
+            Some((device, recipient)) => {
+                log::error!("{} failed: {}", device, error);
+                let device = *device;
+                if recipient.do_send(Failed { device, error }).is_err() {
+                    log::error!("Failed to report failure of {} to its supervisor", device);
+                }
+            }
+            None => log::error!("Device failed: {}", error),
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! A single error type for everything the library can fail with.
+//!
+//! Each part of the library has its own error type, which is still returned where it's the only
+//! kind of failure possible. Binaries juggling several of them can convert any into an
+//! [`Error`](enum.Error.html) with `?` and handle them uniformly.
+
+use std::fmt;
+
+#[cfg(feature = "use_serde")]
+use crate::LoadError;
+use crate::{ConfigError, CoordError, DeviceError, PinError};
+
+/// Any error encountered by the library.
+///
+/// Errors are sorted by their cause rather than where they arose, so (for example) a coordinator
+/// failing to start because a pin is unavailable is an [`Error::Pin`](#variant.Pin), just as if
+/// the pin had been opened directly.
+#[derive(Debug)]
+pub enum Error {
+    /// The coordinator couldn't carry out a request.
+    Coordinator(CoordError),
+    /// A pin couldn't be acquired, written to, or read from.
+    Pin(PinError),
+    /// A motor or pump couldn't be set up as asked or failed to carry out a command.
+    Device(DeviceError),
+    /// The configuration has mistakes.
+    Config(ConfigError),
+    /// The configuration couldn't be loaded.
+    #[cfg(feature = "use_serde")]
+    Load(LoadError),
+}
+
+impl From<CoordError> for Error {
+    fn from(err: CoordError) -> Self {
+        match err {
+            CoordError::Pin(err) => Self::Pin(err),
+            CoordError::Device(err) => Self::Device(err),
+            CoordError::Config(err) => Self::Config(err),
+            err => Self::Coordinator(err),
+        }
+    }
+}
+
+impl From<PinError> for Error {
+    fn from(err: PinError) -> Self {
+        Self::Pin(err)
+    }
+}
+
+impl From<DeviceError> for Error {
+    fn from(err: DeviceError) -> Self {
+        match err {
+            DeviceError::Pin(err) => Self::Pin(err),
+            err => Self::Device(err),
+        }
+    }
+}
+
+impl From<ConfigError> for Error {
+    fn from(err: ConfigError) -> Self {
+        Self::Config(err)
+    }
+}
+
+#[cfg(feature = "use_serde")]
+impl From<LoadError> for Error {
+    fn from(err: LoadError) -> Self {
+        Self::Load(err)
+    }
+}
+
+impl Error {
+    /// The underlying error.
+    fn inner(&self) -> &(dyn std::error::Error + 'static) {
+        match self {
+            Self::Coordinator(err) => err,
+            Self::Pin(err) => err,
+            Self::Device(err) => err,
+            Self::Config(err) => err,
+            #[cfg(feature = "use_serde")]
+            Self::Load(err) => err,
+        }
+    }
+}
+
+/// Errors are displayed as the underlying error is.
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.inner().fmt(f)
+    }
+}
+
+/// Since errors are displayed as the underlying error is, their sources are its source.
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.inner().source()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{error::Error as _, io};
+    #[test]
+    fn sorts_by_cause() {
+        let io = || PinError::Io(io::Error::new(io::ErrorKind::Other, "gone"));
+        let err = Error::from(CoordError::Device(DeviceError::Pin(io())));
+        assert!(matches!(err, Error::Pin(PinError::Io(_))));
+        assert_eq!(err.to_string(), "I/O error accessing the pin");
+        assert_eq!(err.source().map(ToString::to_string), Some("gone".into()));
+        let err = Error::from(CoordError::Device(DeviceError::NoFlowRate));
+        assert!(matches!(err, Error::Device(DeviceError::NoFlowRate)));
+        let err = Error::from(CoordError::Busy);
+        assert!(err.source().is_none());
+        let err = Error::from(DeviceError::Pin(io()));
+        assert!(matches!(err, Error::Pin(_)));
+        let device = DeviceError::Pin(io());
+        assert_eq!(device.to_string(), "I/O error accessing the pin");
+        assert_eq!(
+            device.source().map(ToString::to_string),
+            Some("gone".into())
+        );
+        let coord = CoordError::Pin(io());
+        assert_eq!(coord.to_string(), "Couldn't set up the pins");
+        assert_eq!(
+            coord.source().map(ToString::to_string),
+            Some("I/O error accessing the pin".into())
+        );
+    }
+}
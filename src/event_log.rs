@@ -0,0 +1,372 @@
+//! A structured, append-only log of everything the coordinator does.
+//!
+//! The [`EventLog`](struct.EventLog.html) subscribes to the coordinator's status updates and
+//! writes each one as a line of JSON, noting when it happened and who (or what) caused it. The log
+//! is rotated once it grows past the configured size, keeping a fixed number of older logs.
+
+use std::{
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use uom::si::{thermodynamic_temperature::degree_celsius, volume::milliliter};
+
+use crate::{
+    actix::*, comm::Subscribers, Buffers, EventLogConfig, Iteration, Status, StatusMessage, Update,
+};
+
+/// What caused an event.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Origin {
+    /// The coordinator, in the course of running a program.
+    Coordinator,
+    /// An operator, overriding or directing the coordinator.
+    Operator,
+    /// The hardware (a sensor reading or a fault).
+    Hardware,
+}
+
+/// A logged event.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Event {
+    /// When the event happened, in milliseconds since the Unix epoch.
+    pub timestamp: u64,
+    /// What caused the event.
+    pub origin: Origin,
+    /// The user whose request prompted the event, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    /// The manifold the event happened on, if there are several.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifold: Option<String>,
+    /// The kind of event (e.g. `step` or `fault`).
+    pub kind: String,
+    /// The index of the step being run at the time, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub step: Option<usize>,
+    /// The pass through a repeat being run at the time, if the step was repeated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iteration: Option<Iteration>,
+    /// How long the program had been running at the time, if one was.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elapsed: Option<Duration>,
+    /// Further details of the event, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl Event {
+    /// Creates an event describing the given status update, timestamped now.
+    pub fn new(status: &Status) -> Self {
+        let (origin, kind, detail) = describe(&status.message, &status.buffers);
+        let since = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        Self {
+            timestamp: since.as_secs() * 1000 + u64::from(since.subsec_millis()),
+            origin,
+            user: status.user.clone(),
+            manifold: status.manifold.clone(),
+            kind: kind.into(),
+            step: status.step,
+            iteration: status.iteration,
+            elapsed: status.elapsed,
+            detail,
+        }
+    }
+}
+
+impl ActixMessage for Event {
+    type Result = ();
+}
+
+/// Classifies a status update, returning its origin, kind, and any details worth recording.
+fn describe(message: &StatusMessage, buffers: &Buffers) -> (Origin, &'static str, Option<String>) {
+    use self::Origin::*;
+    match message {
+        StatusMessage::Continued => (Operator, "continued", None),
+        StatusMessage::Started(protocol) => (
+            Coordinator,
+            "started",
+            Some(format!("{} steps", protocol.steps.len())),
+        ),
+        StatusMessage::Step(_) => (Coordinator, "step", None),
+        StatusMessage::Completed => (Coordinator, "completed", None),
+        StatusMessage::Interrupted { id, step } => (
+            Coordinator,
+            "interrupted",
+            Some(format!("job {} at step {}", id, step)),
+        ),
+        StatusMessage::Recovered(recovery) => {
+            (Operator, "recovered", Some(format!("{:?}", recovery)))
+        }
+        StatusMessage::Paused => (Coordinator, "paused", None),
+        StatusMessage::Suspended => (Operator, "suspended", None),
+        StatusMessage::Unsuspended => (Operator, "unsuspended", None),
+        StatusMessage::Aborted => (Coordinator, "aborted", None),
+        StatusMessage::Jumped { from, to } => (
+            Operator,
+            "jumped",
+            Some(format!("from step {} to step {}", from, to)),
+        ),
+        StatusMessage::Enqueued(id) => (Operator, "enqueued", Some(id.to_string())),
+        StatusMessage::Dequeued(id) => (Operator, "dequeued", Some(id.to_string())),
+        StatusMessage::Requeued(id) => (Operator, "requeued", Some(id.to_string())),
+        StatusMessage::Scheduled(id) => (Operator, "scheduled", Some(id.to_string())),
+        StatusMessage::Unscheduled(id) => (Operator, "unscheduled", Some(id.to_string())),
+        StatusMessage::StopQueued { early } => (
+            Operator,
+            "stop-queued",
+            Some(if *early { "early" } else { "at end" }.into()),
+        ),
+        StatusMessage::Halted => (Coordinator, "halted", None),
+        StatusMessage::Priming { buffer } => (Operator, "priming", Some(buffers.name(*buffer))),
+        StatusMessage::Primed { buffer } => (Coordinator, "primed", Some(buffers.name(*buffer))),
+        StatusMessage::Washing { cycle, cycles } => (
+            Operator,
+            "washing",
+            Some(format!("cycle {} of {}", cycle, cycles)),
+        ),
+        StatusMessage::Washed => (Coordinator, "washed", None),
+        StatusMessage::FlowMismatch { expected, measured } => (
+            Hardware,
+            "flow-mismatch",
+            Some(format!(
+                "expected {:.1} mL, measured {:.1} mL",
+                expected.get::<milliliter>(),
+                measured.get::<milliliter>()
+            )),
+        ),
+        StatusMessage::Fault(fault) => (Hardware, "fault", Some(fault.to_string())),
+        StatusMessage::BubbleDetected { purge } => (
+            Hardware,
+            "bubble-detected",
+            Some(
+                if *purge {
+                    "purging"
+                } else {
+                    "awaiting operator"
+                }
+                .into(),
+            ),
+        ),
+        StatusMessage::BubbleCleared => (Hardware, "bubble-cleared", None),
+        StatusMessage::TemperatureReached(temperature) => (
+            Hardware,
+            "temperature-reached",
+            Some(format!("{:.1} °C", temperature.get::<degree_celsius>())),
+        ),
+        StatusMessage::Gradient { from, to, percent } => (
+            Coordinator,
+            "gradient",
+            Some(format!(
+                "{} to {}: {:.0}%",
+                buffers.name(*from),
+                buffers.name(*to),
+                percent
+            )),
+        ),
+        StatusMessage::Mixing { cycle, cycles } => (
+            Coordinator,
+            "mixing",
+            Some(format!("cycle {} of {}", cycle, cycles)),
+        ),
+        StatusMessage::ConditionMet { sensor, value } => (
+            Coordinator,
+            "condition-met",
+            Some(match value {
+                Some(value) => format!("{}: {:.1} {}", sensor, value, sensor.unit()),
+                None => sensor.to_string(),
+            }),
+        ),
+        StatusMessage::ConditionTimedOut { sensor } => {
+            (Coordinator, "condition-timed-out", Some(sensor.to_string()))
+        }
+        StatusMessage::Branched {
+            sensor,
+            value,
+            taken,
+        } => {
+            let branch = if *taken { "first" } else { "second" };
+            (
+                Coordinator,
+                "branched",
+                Some(match value {
+                    Some(value) => {
+                        format!("{} ({}: {:.1} {})", branch, sensor, value, sensor.unit())
+                    }
+                    None => format!("{} (simulated)", branch),
+                }),
+            )
+        }
+        StatusMessage::Calibrating { motor, state } => (
+            Operator,
+            "calibrating",
+            Some(format!("motor {}: {:?}", motor, state)),
+        ),
+        StatusMessage::Calibrated { motor, .. } => {
+            (Operator, "calibrated", Some(format!("motor {}", motor)))
+        }
+        StatusMessage::ReservoirLow { buffer, volume } => (
+            Hardware,
+            "reservoir-low",
+            Some(format!(
+                "{}: {:.0} mL left",
+                buffers.name(*buffer),
+                volume.get::<milliliter>()
+            )),
+        ),
+        StatusMessage::Reset => (Operator, "reset", None),
+        StatusMessage::Jogged(jog) => (Operator, "jogged", Some(format!("{:?}", jog))),
+        StatusMessage::JogFinished => (Coordinator, "jog-finished", None),
+        StatusMessage::Reloaded => (Coordinator, "reloaded", None),
+        StatusMessage::Hook(event) => (Coordinator, "hook", Some(event.clone())),
+        StatusMessage::Overrun {
+            expected,
+            taken,
+            policy,
+        } => (
+            Coordinator,
+            "overrun",
+            Some(format!(
+                "took {:.0} s of {:.0} s ({})",
+                taken.as_secs_f64(),
+                expected.as_secs_f64(),
+                policy
+            )),
+        ),
+        StatusMessage::Reading { sensor, value } => (
+            Hardware,
+            "reading",
+            Some(format!("{}: {:.1} {}", sensor, value, sensor.unit())),
+        ),
+    }
+}
+
+/// Writes events to a rotating log file, one JSON object per line.
+pub struct EventLog {
+    config: EventLogConfig,
+    file: File,
+    /// The size of the current log file, in bytes.
+    size: u64,
+}
+
+impl fmt::Debug for EventLog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EventLog")
+            .field("config", &self.config)
+            .field("size", &self.size)
+            .finish()
+    }
+}
+
+impl EventLog {
+    /// Opens the configured log file for appending, creating it if necessary.
+    pub fn open(config: EventLogConfig) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&config.path)?;
+        let size = file.metadata()?.len();
+        Ok(Self { config, file, size })
+    }
+    /// The path of the `n`th most recent rotated log.
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut name = self.config.path.as_os_str().to_os_string();
+        name.push(format!(".{}", n));
+        name.into()
+    }
+    /// Moves the current log aside (discarding the oldest, if need be) and starts a new one.
+    fn rotate(&mut self) -> io::Result<()> {
+        let ignore_missing = |result: io::Result<()>| match result {
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        };
+        if self.config.keep == 0 {
+            ignore_missing(fs::remove_file(&self.config.path))?;
+        } else {
+            for n in (1..self.config.keep).rev() {
+                ignore_missing(fs::rename(self.rotated(n), self.rotated(n + 1)))?;
+            }
+            fs::rename(&self.config.path, self.rotated(1))?;
+        }
+        *self = Self::open(self.config.clone())?;
+        Ok(())
+    }
+    /// Appends the given event to the log, rotating it first if it would grow too large.
+    pub fn write(&mut self, event: &Event) -> io::Result<()> {
+        let mut line = serde_json::to_vec(event)?;
+        line.push(b'\n');
+        if self.size > 0 && self.size + line.len() as u64 > self.config.max_size {
+            self.rotate()?;
+        }
+        self.file.write_all(&line)?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+}
+
+impl Actor for EventLog {
+    type Context = Context<Self>;
+}
+
+impl Handle<Event> for EventLog {
+    type Result = ();
+    fn handle(&mut self, event: Event, _context: &mut Self::Context) {
+        if let Err(err) = self.write(&event) {
+            log::error!("Failed to write to event log: {}", err);
+        }
+    }
+}
+
+impl Update for Addr<EventLog> {
+    fn handle(&self, status: &Status, _coord: &Subscribers) {
+        // Routine readings would drown out everything else.
+        if let StatusMessage::Reading { .. } = status.message {
+            return;
+        }
+        self.do_send(Event::new(status));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn rotates() {
+        let dir = std::env::temp_dir().join(format!("deoxy-events-{}", uuid::Uuid::new_v4()));
+        fs::create_dir(&dir).unwrap();
+        let config = EventLogConfig {
+            path: dir.join("events.jsonl"),
+            max_size: 200,
+            keep: 2,
+        };
+        let mut log = EventLog::open(config.clone()).unwrap();
+        let event = Event {
+            timestamp: 0,
+            origin: Origin::Operator,
+            user: Some("alex".into()),
+            manifold: None,
+            kind: "suspended".into(),
+            step: Some(1),
+            iteration: None,
+            elapsed: None,
+            detail: None,
+        };
+        for _ in 0..20 {
+            log.write(&event).unwrap();
+        }
+        let contents = fs::read_to_string(&config.path).unwrap();
+        let first = contents.lines().next().unwrap();
+        assert_eq!(serde_json::from_str::<Event>(first).unwrap(), event);
+        assert!(fs::metadata(&config.path).unwrap().len() <= config.max_size);
+        assert!(fs::metadata(log.rotated(2)).is_ok());
+        assert!(fs::metadata(log.rotated(3)).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,230 @@
+//! A gRPC control interface, for orchestration software which speaks gRPC.
+//!
+//! The service (defined in `proto/deoxy.proto`) mirrors the coordinator's commands and streams
+//! the coordinator's status updates (as they'd be written to the [event
+//! log](../event_log/index.html)). It runs on its own thread, alongside the actix system.
+//!
+//! If any API tokens are configured, calls must bear one (as `authorization: Bearer <token>`
+//! metadata) for the role the equivalent HTTP request would need, and commands are credited to its
+//! holder; otherwise, they're carried out as if an operator named `grpc` had sent them. Tokens
+//! issued through the HTTP API aren't accepted here.
+
+use std::{collections::HashMap, net::SocketAddr, sync::Mutex, thread};
+
+use futures03::{
+    channel::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    compat::Future01CompatExt,
+};
+use tokio::runtime::Runtime;
+use tonic::{transport::Server, Request, Response, Status as RpcStatus};
+use uuid::Uuid;
+
+use crate::{
+    actix::Addr,
+    comm::{Message, Subscribers},
+    event_log::{Event, Origin},
+    Coordinator, Protocol, Role, Status, TokenConfig, Update,
+};
+
+/// The generated service and message types.
+#[allow(missing_docs, clippy::all)]
+pub mod proto {
+    tonic::include_proto!("deoxy");
+}
+
+use self::proto::{
+    deoxy_server::{Deoxy, DeoxyServer},
+    Empty, Reply, StartRequest,
+};
+
+impl From<Origin> for proto::Origin {
+    fn from(origin: Origin) -> Self {
+        match origin {
+            Origin::Coordinator => Self::Coordinator,
+            Origin::Operator => Self::Operator,
+            Origin::Hardware => Self::Hardware,
+        }
+    }
+}
+
+impl From<Event> for proto::Update {
+    fn from(event: Event) -> Self {
+        Self {
+            timestamp: event.timestamp,
+            origin: proto::Origin::from(event.origin) as i32,
+            user: event.user.unwrap_or_default(),
+            manifold: event.manifold.unwrap_or_default(),
+            kind: event.kind,
+            step: event.step.map(|step| step as u64),
+            elapsed: event
+                .elapsed
+                .map(|elapsed| elapsed.as_secs() * 1000 + u64::from(elapsed.subsec_millis())),
+            detail: event.detail.unwrap_or_default(),
+        }
+    }
+}
+
+/// Forwards coordinator updates to a client of the update stream.
+#[derive(Debug)]
+struct Feed {
+    sender: UnboundedSender<Result<proto::Update, RpcStatus>>,
+}
+
+impl Update for Feed {
+    fn handle(&self, status: &Status, _coord: &Subscribers) {
+        // This only fails once the client has gone.
+        let _ = self.sender.unbounded_send(Ok(Event::new(status).into()));
+    }
+    fn is_connected(&self) -> bool {
+        !self.sender.is_closed()
+    }
+}
+
+/// Extracts the token from the given `authorization` value (`Bearer <token>`).
+fn bearer(value: &str) -> Option<&str> {
+    let mut parts = value.trim().splitn(2, ' ');
+    match (parts.next(), parts.next()) {
+        (Some(scheme), Some(token)) if scheme.eq_ignore_ascii_case("bearer") => Some(token.trim()),
+        _ => None,
+    }
+}
+
+/// Carries out requests using the coordinator.
+#[derive(Debug)]
+struct Control {
+    // The address is only used to send messages, but the service has to be `Sync`.
+    coord: Mutex<Addr<Coordinator>>,
+    /// The accepted tokens, along with who holds each (if empty, calls aren't authenticated).
+    tokens: HashMap<String, TokenConfig>,
+}
+
+impl Control {
+    fn coord(&self) -> Addr<Coordinator> {
+        self.coord
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+    /// Checks that the given call bears a token for the role needed to make the HTTP request
+    /// (which only reads, or doesn't) to the given path (see [`Role::required`]), returning who
+    /// holds it.
+    ///
+    /// [`Role::required`]: ../enum.Role.html#method.required
+    fn authorize<T>(
+        &self,
+        request: &Request<T>,
+        read_only: bool,
+        path: &str,
+    ) -> Result<String, RpcStatus> {
+        if self.tokens.is_empty() {
+            return Ok("grpc".into());
+        }
+        let holder = request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(bearer)
+            .and_then(|token| self.tokens.get(token))
+            .ok_or_else(|| RpcStatus::unauthenticated("A valid bearer token is required"))?;
+        if holder.role < Role::required(read_only, path) {
+            return Err(RpcStatus::permission_denied(format!(
+                "{} may not do that",
+                holder.name
+            )));
+        }
+        Ok(holder.name.clone())
+    }
+    /// Sends the given message to the coordinator on behalf of the given user.
+    async fn send(&self, user: String, message: Message) -> Result<Response<Reply>, RpcStatus> {
+        let message = Message::Acting {
+            user,
+            message: Box::new(message),
+        };
+        match self.coord().send(message).compat().await {
+            Ok(Ok(())) => Ok(Response::new(Reply {})),
+            Ok(Err(err)) => Err(RpcStatus::failed_precondition(err.to_string())),
+            Err(err) => Err(RpcStatus::unavailable(err.to_string())),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl Deoxy for Control {
+    async fn start(&self, request: Request<StartRequest>) -> Result<Response<Reply>, RpcStatus> {
+        let user = self.authorize(&request, false, "/")?;
+        let request = request.into_inner();
+        let protocol = serde_json::from_str::<Protocol>(&request.protocol)
+            .map_err(|err| RpcStatus::invalid_argument(format!("Invalid protocol: {}", err)))?;
+        let id = match request.id.as_str() {
+            "" => None,
+            id => Some(
+                Uuid::parse_str(id)
+                    .map_err(|err| RpcStatus::invalid_argument(format!("Invalid id: {}", err)))?,
+            ),
+        };
+        self.send(user, Message::Start(protocol, id)).await
+    }
+    async fn pause(&self, request: Request<Empty>) -> Result<Response<Reply>, RpcStatus> {
+        let user = self.authorize(&request, false, "/{job}/pause")?;
+        self.send(user, Message::Pause).await
+    }
+    async fn resume(&self, request: Request<Empty>) -> Result<Response<Reply>, RpcStatus> {
+        let user = self.authorize(&request, false, "/{job}/unpause")?;
+        self.send(user, Message::Resume).await
+    }
+    async fn r#continue(&self, request: Request<Empty>) -> Result<Response<Reply>, RpcStatus> {
+        let user = self.authorize(&request, false, "/{job}/resume")?;
+        self.send(user, Message::Continue).await
+    }
+    async fn stop(&self, request: Request<Empty>) -> Result<Response<Reply>, RpcStatus> {
+        let user = self.authorize(&request, false, "/{job}")?;
+        self.send(user, Message::Stop).await
+    }
+    async fn abort(&self, request: Request<Empty>) -> Result<Response<Reply>, RpcStatus> {
+        let user = self.authorize(&request, false, "/{job}/abort")?;
+        self.send(user, Message::Abort).await
+    }
+    async fn halt(&self, request: Request<Empty>) -> Result<Response<Reply>, RpcStatus> {
+        let user = self.authorize(&request, false, "/{job}/halt")?;
+        self.send(user, Message::Halt).await
+    }
+    async fn reset(&self, request: Request<Empty>) -> Result<Response<Reply>, RpcStatus> {
+        let user = self.authorize(&request, false, "/reset")?;
+        self.send(user, Message::Reset).await
+    }
+    type UpdatesStream = UnboundedReceiver<Result<proto::Update, RpcStatus>>;
+    async fn updates(
+        &self,
+        request: Request<Empty>,
+    ) -> Result<Response<Self::UpdatesStream>, RpcStatus> {
+        self.authorize(&request, true, "/ws/status")?;
+        let (sender, receiver) = mpsc::unbounded();
+        self.coord()
+            .do_send(Message::Subscribe(Box::new(Feed { sender })));
+        Ok(Response::new(receiver))
+    }
+}
+
+/// Serves the control interface on the given address (on a thread of its own), carrying out
+/// requests bearing any of the given tokens using the given coordinator.
+pub fn serve(address: SocketAddr, tokens: &[TokenConfig], coord: Addr<Coordinator>) {
+    let tokens = tokens
+        .iter()
+        .map(|spec| (spec.token.clone(), spec.clone()))
+        .collect();
+    let service = DeoxyServer::new(Control {
+        coord: Mutex::new(coord),
+        tokens,
+    });
+    thread::spawn(move || {
+        let mut runtime = match Runtime::new() {
+            Ok(runtime) => runtime,
+            Err(err) => return log::error!("Failed to start gRPC runtime: {}", err),
+        };
+        log::info!("Serving gRPC on {}.", address);
+        let server = Server::builder().add_service(service).serve(address);
+        if let Err(err) = runtime.block_on(server) {
+            log::error!("gRPC server failed: {}", err);
+        }
+    });
+}
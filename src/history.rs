@@ -0,0 +1,547 @@
+//! A database of past runs.
+//!
+//! Every run is recorded with its protocol, operator, start and end times, outcome, and the
+//! timings of each of its steps, along with any sensor readings taken and faults detected while it
+//! ran. The database is a SQLite file, so it can also be inspected with ordinary tools, and runs
+//! can be exported as CSV or JSON (see [`Run::export`](struct.Run.html#method.export)).
+
+use std::{
+    fmt,
+    path::Path,
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use uuid::Uuid;
+
+use crate::{Action, Protocol};
+
+/// How a run ended.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "use_serde", serde(rename_all = "lowercase"))]
+pub enum Outcome {
+    /// The protocol ran to completion.
+    Completed,
+    /// The run was stopped early (cleanly) at the operator's request.
+    Stopped,
+    /// The run was aborted, and the teardown sequence run.
+    Aborted,
+    /// The run was halted immediately.
+    Halted,
+    /// The run was stopped by a hardware fault.
+    Faulted,
+}
+
+impl Outcome {
+    /// The name of the outcome, as stored in the database.
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Completed => "completed",
+            Self::Stopped => "stopped",
+            Self::Aborted => "aborted",
+            Self::Halted => "halted",
+            Self::Faulted => "faulted",
+        }
+    }
+    /// Parses the name of an outcome, as stored in the database.
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "completed" => Some(Self::Completed),
+            "stopped" => Some(Self::Stopped),
+            "aborted" => Some(Self::Aborted),
+            "halted" => Some(Self::Halted),
+            "faulted" => Some(Self::Faulted),
+            _ => None,
+        }
+    }
+}
+
+/// A step of a recorded run.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+pub struct StepRecord {
+    /// The index of the step (in the program's actions).
+    pub index: usize,
+    /// The action that was run.
+    pub action: Action,
+    /// When the step began.
+    pub started: SystemTime,
+    /// When the step ended, if it did.
+    pub ended: Option<SystemTime>,
+}
+
+/// A sensor reading taken during a recorded run.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+pub struct Reading {
+    /// When the reading was taken.
+    pub time: SystemTime,
+    /// What was measured, with its unit (e.g. `pressure (kPa)`).
+    pub quantity: String,
+    /// The measured value.
+    pub value: f64,
+}
+
+/// A fault detected during a recorded run.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+pub struct FaultRecord {
+    /// When the fault was detected.
+    pub time: SystemTime,
+    /// A description of the fault.
+    pub description: String,
+}
+
+/// A format in which runs can be exported.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "use_serde", serde(rename_all = "lowercase"))]
+pub enum Format {
+    /// Comma-separated values, with one row per step, reading, and fault.
+    Csv,
+    /// The run as a single JSON object.
+    Json,
+}
+
+impl Default for Format {
+    fn default() -> Self {
+        Self::Json
+    }
+}
+
+/// A recorded run.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+pub struct Run {
+    /// The label of the job.
+    pub id: Uuid,
+    /// The protocol that was run.
+    pub protocol: Protocol,
+    /// Who started the run, if known.
+    pub operator: Option<String>,
+    /// When the run began.
+    pub started: SystemTime,
+    /// When the run ended, if it has.
+    pub ended: Option<SystemTime>,
+    /// How the run ended, if it has.
+    pub outcome: Option<Outcome>,
+    /// The steps run, in order.
+    pub steps: Vec<StepRecord>,
+    /// The sensor readings taken, in order.
+    pub readings: Vec<Reading>,
+    /// The faults detected, in order.
+    pub faults: Vec<FaultRecord>,
+}
+
+/// Quotes a CSV field, if necessary.
+fn quote(field: &str) -> String {
+    if field.contains(|c: char| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.into()
+    }
+}
+
+impl Run {
+    /// Serializes the run as CSV.
+    ///
+    /// Each row is a step, reading, or fault, with the columns `record`, `time`, `ended`, `step`,
+    /// `description`, and `value`. Times are given in milliseconds since the Unix epoch.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("record,time,ended,step,description,value\n");
+        let mut row = |record: &str,
+                       time: SystemTime,
+                       ended: Option<SystemTime>,
+                       step: Option<usize>,
+                       description: &str,
+                       value: Option<f64>| {
+            let optional = |field: Option<String>| field.unwrap_or_default();
+            csv.push_str(&format!(
+                "{},{},{},{},{},{}\n",
+                record,
+                millis(time),
+                optional(ended.map(|ended| millis(ended).to_string())),
+                optional(step.map(|step| step.to_string())),
+                quote(description),
+                optional(value.map(|value| value.to_string())),
+            ));
+        };
+        for step in &self.steps {
+            row(
+                "step",
+                step.started,
+                step.ended,
+                Some(step.index),
+                &format!("{:?}", step.action),
+                None,
+            );
+        }
+        for reading in &self.readings {
+            row(
+                "reading",
+                reading.time,
+                None,
+                None,
+                &reading.quantity,
+                Some(reading.value),
+            );
+        }
+        for fault in &self.faults {
+            row("fault", fault.time, None, None, &fault.description, None);
+        }
+        csv
+    }
+    /// Serializes the run as JSON.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+    /// Serializes the run in the given format.
+    pub fn export(&self, format: Format) -> Result<String> {
+        match format {
+            Format::Csv => Ok(self.to_csv()),
+            Format::Json => self.to_json(),
+        }
+    }
+}
+
+/// An error encountered while reading or writing the run history.
+#[derive(Debug)]
+pub enum Error {
+    /// The database could not be accessed.
+    Database(rusqlite::Error),
+    /// A protocol or action could not be (de)serialized.
+    Json(serde_json::Error),
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(err: rusqlite::Error) -> Self {
+        Self::Database(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Json(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Database(_) => write!(f, "History database error"),
+            Self::Json(_) => write!(f, "History serialization error"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Database(err) => Some(err),
+            Self::Json(err) => Some(err),
+        }
+    }
+}
+
+/// Shorthand for results of history operations.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Converts a time to milliseconds since the epoch, for storage.
+fn millis(time: SystemTime) -> i64 {
+    let since = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    since.as_secs() as i64 * 1000 + i64::from(since.subsec_millis())
+}
+
+/// Converts milliseconds since the epoch (as stored) to a time.
+fn time(millis: i64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_millis(millis.max(0) as u64)
+}
+
+/// The stored fields of a run: its label, protocol, operator, start and end times, and outcome.
+type RunRow = (
+    String,
+    String,
+    Option<String>,
+    i64,
+    Option<i64>,
+    Option<String>,
+);
+
+/// The run history database.
+pub struct History {
+    connection: Mutex<Connection>,
+}
+
+impl fmt::Debug for History {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("History").finish()
+    }
+}
+
+impl History {
+    /// Opens (or creates) the history database at the given path.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS runs (
+                id TEXT PRIMARY KEY,
+                protocol TEXT NOT NULL,
+                operator TEXT,
+                started INTEGER NOT NULL,
+                ended INTEGER,
+                outcome TEXT
+            );
+            CREATE TABLE IF NOT EXISTS steps (
+                run TEXT NOT NULL REFERENCES runs (id),
+                idx INTEGER NOT NULL,
+                action TEXT NOT NULL,
+                started INTEGER NOT NULL,
+                ended INTEGER
+            );
+            CREATE TABLE IF NOT EXISTS readings (
+                run TEXT NOT NULL REFERENCES runs (id),
+                time INTEGER NOT NULL,
+                quantity TEXT NOT NULL,
+                value REAL NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS faults (
+                run TEXT NOT NULL REFERENCES runs (id),
+                time INTEGER NOT NULL,
+                description TEXT NOT NULL
+            );",
+        )?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+    /// Runs the given operation on the database connection.
+    fn with<T>(&self, operation: impl FnOnce(&Connection) -> Result<T>) -> Result<T> {
+        // A panic while holding the lock can't leave the connection in a bad state.
+        let connection = self
+            .connection
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        operation(&connection)
+    }
+    /// Records the start of a run.
+    pub fn begin(
+        &self,
+        id: Uuid,
+        protocol: &Protocol,
+        operator: Option<&str>,
+        started: SystemTime,
+    ) -> Result<()> {
+        let protocol = serde_json::to_string(protocol)?;
+        self.with(|connection| {
+            connection.execute(
+                "INSERT INTO runs (id, protocol, operator, started) VALUES (?1, ?2, ?3, ?4)",
+                params![id.to_string(), protocol, operator, millis(started)],
+            )?;
+            Ok(())
+        })
+    }
+    /// Records the start of a step (and the end of the previous one).
+    pub fn step(&self, id: Uuid, index: usize, action: &Action, started: SystemTime) -> Result<()> {
+        let action = serde_json::to_string(action)?;
+        self.with(|connection| {
+            connection.execute(
+                "UPDATE steps SET ended = ?2 WHERE run = ?1 AND ended IS NULL",
+                params![id.to_string(), millis(started)],
+            )?;
+            connection.execute(
+                "INSERT INTO steps (run, idx, action, started) VALUES (?1, ?2, ?3, ?4)",
+                params![id.to_string(), index as i64, action, millis(started)],
+            )?;
+            Ok(())
+        })
+    }
+    /// Records the end of a run (and of its last step).
+    ///
+    /// Runs which have already ended are left alone.
+    pub fn end(&self, id: Uuid, outcome: Outcome, ended: SystemTime) -> Result<()> {
+        self.with(|connection| {
+            connection.execute(
+                "UPDATE steps SET ended = ?2 WHERE run = ?1 AND ended IS NULL",
+                params![id.to_string(), millis(ended)],
+            )?;
+            connection.execute(
+                "UPDATE runs SET ended = ?2, outcome = ?3 WHERE id = ?1 AND ended IS NULL",
+                params![id.to_string(), millis(ended), outcome.as_str()],
+            )?;
+            Ok(())
+        })
+    }
+    /// Records a sensor reading taken during a run.
+    pub fn reading(&self, id: Uuid, quantity: &str, value: f64, time: SystemTime) -> Result<()> {
+        self.with(|connection| {
+            connection.execute(
+                "INSERT INTO readings (run, time, quantity, value) VALUES (?1, ?2, ?3, ?4)",
+                params![id.to_string(), millis(time), quantity, value],
+            )?;
+            Ok(())
+        })
+    }
+    /// Records a fault detected during a run.
+    pub fn fault(&self, id: Uuid, description: &str, time: SystemTime) -> Result<()> {
+        self.with(|connection| {
+            connection.execute(
+                "INSERT INTO faults (run, time, description) VALUES (?1, ?2, ?3)",
+                params![id.to_string(), millis(time), description],
+            )?;
+            Ok(())
+        })
+    }
+    /// Reads a run (without its steps) from a row of the `runs` table.
+    fn read_run(row: &Row) -> rusqlite::Result<RunRow> {
+        Ok((
+            row.get(0)?,
+            row.get(1)?,
+            row.get(2)?,
+            row.get(3)?,
+            row.get(4)?,
+            row.get(5)?,
+        ))
+    }
+    /// Assembles a run from its stored fields, loading its steps, readings, and faults.
+    fn assemble(
+        connection: &Connection,
+        (id, protocol, operator, started, ended, outcome): RunRow,
+    ) -> Result<Run> {
+        let mut statement = connection.prepare(
+            "SELECT idx, action, started, ended FROM steps WHERE run = ?1 ORDER BY rowid",
+        )?;
+        let steps = statement
+            .query_map(params![id], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)?,
+                    row.get::<_, Option<i64>>(3)?,
+                ))
+            })?
+            .map(|step| {
+                let (index, action, started, ended) = step?;
+                Ok(StepRecord {
+                    index: index as usize,
+                    action: serde_json::from_str(&action)?,
+                    started: time(started),
+                    ended: ended.map(time),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let mut statement = connection
+            .prepare("SELECT time, quantity, value FROM readings WHERE run = ?1 ORDER BY rowid")?;
+        let readings = statement
+            .query_map(params![id], |row| {
+                Ok(Reading {
+                    time: time(row.get(0)?),
+                    quantity: row.get(1)?,
+                    value: row.get(2)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let mut statement = connection
+            .prepare("SELECT time, description FROM faults WHERE run = ?1 ORDER BY rowid")?;
+        let faults = statement
+            .query_map(params![id], |row| {
+                Ok(FaultRecord {
+                    time: time(row.get(0)?),
+                    description: row.get(1)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(Run {
+            // Only valid UUIDs are ever written.
+            id: Uuid::parse_str(&id).unwrap_or_else(|_| Uuid::nil()),
+            protocol: serde_json::from_str(&protocol)?,
+            operator,
+            started: time(started),
+            ended: ended.map(time),
+            outcome: outcome.as_ref().and_then(|name| Outcome::parse(name)),
+            steps,
+            readings,
+            faults,
+        })
+    }
+    /// Lists every recorded run, most recent first.
+    pub fn runs(&self) -> Result<Vec<Run>> {
+        self.with(|connection| {
+            let mut statement = connection.prepare(
+                "SELECT id, protocol, operator, started, ended, outcome FROM runs
+                 ORDER BY started DESC",
+            )?;
+            let rows = statement
+                .query_map(params![], Self::read_run)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            rows.into_iter()
+                .map(|row| Self::assemble(connection, row))
+                .collect()
+        })
+    }
+    /// Fetches the run with the given label, if it has been recorded.
+    pub fn run(&self, id: Uuid) -> Result<Option<Run>> {
+        self.with(|connection| {
+            let row = connection
+                .query_row(
+                    "SELECT id, protocol, operator, started, ended, outcome FROM runs
+                     WHERE id = ?1",
+                    params![id.to_string()],
+                    Self::read_run,
+                )
+                .optional()?;
+            row.map(|row| Self::assemble(connection, row)).transpose()
+        })
+    }
+    /// Exports the run with the given label in the given format, if it has been recorded.
+    pub fn export(&self, id: Uuid, format: Format) -> Result<Option<String>> {
+        self.run(id)?.map(|run| run.export(format)).transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Step;
+    #[test]
+    fn record_run() {
+        let history = History::open(":memory:").unwrap();
+        let id = Uuid::new_v4();
+        let protocol = Protocol::with_step(Step::Perfuse(0, None));
+        let start = UNIX_EPOCH + Duration::from_secs(1_000_000);
+        history.begin(id, &protocol, Some("alex"), start).unwrap();
+        history.step(id, 0, &Action::Perfuse(0), start).unwrap();
+        let later = start + Duration::from_secs(60);
+        history.reading(id, "pressure (kPa)", 12.5, start).unwrap();
+        history.fault(id, "Line pressure, too high", later).unwrap();
+        history.step(id, 1, &Action::Finish, later).unwrap();
+        history.end(id, Outcome::Completed, later).unwrap();
+        let run = history.run(id).unwrap().unwrap();
+        assert_eq!(run.operator.as_ref().map(String::as_str), Some("alex"));
+        assert_eq!(run.outcome, Some(Outcome::Completed));
+        assert_eq!(run.steps.len(), 2);
+        assert_eq!(run.steps[0].ended, Some(later));
+        assert_eq!(history.runs().unwrap().len(), 1);
+        assert!(history.run(Uuid::new_v4()).unwrap().is_none());
+        let csv = history.export(id, Format::Csv).unwrap().unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some("record,time,ended,step,description,value")
+        );
+        assert_eq!(
+            lines.nth(2),
+            Some("reading,1000000000,,,pressure (kPa),12.5")
+        );
+        assert_eq!(
+            lines.next(),
+            Some("fault,1000060000,,,\"Line pressure, too high\",")
+        );
+        let json = run.to_json().unwrap();
+        assert_eq!(
+            serde_json::from_str::<Run>(&json).unwrap().faults,
+            run.faults
+        );
+    }
+}
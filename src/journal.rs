@@ -0,0 +1,144 @@
+//! A record of the running program, kept on disk so that an interrupted run can be recovered.
+//!
+//! The journal is rewritten after every transition (and removed once the program stops), so if
+//! the coordinator starts up and finds one, the previous run never finished.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+#[cfg(feature = "use_serde")]
+use std::{fs::File, io::Write};
+
+use uuid::Uuid;
+
+use crate::{Action, MotorId, PumpId};
+
+/// What to do with a run that was interrupted (by a crash or power loss).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "use_serde", serde(rename_all = "lowercase"))]
+pub enum Recovery {
+    /// Pick the run back up, restarting the step that was interrupted.
+    Resume,
+    /// Run the teardown sequence, leaving the system in a safe state.
+    Abort,
+}
+
+/// The state of a running program, as recorded in the journal.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "use_serde", serde(rename_all = "kebab-case"))]
+pub(crate) struct Entry {
+    /// The label of the job.
+    pub(crate) id: Uuid,
+    /// All of the program's actions (including those already run).
+    pub(crate) actions: Vec<Action>,
+    /// The index of the action in progress.
+    pub(crate) step: usize,
+    /// How long the program had been running, not counting pauses.
+    pub(crate) elapsed: Duration,
+    /// The most recent buffer.
+    pub(crate) buffer: Option<MotorId>,
+    /// The pump in use.
+    pub(crate) pump: PumpId,
+    /// Whether the actions are the teardown of an aborted program.
+    pub(crate) aborted: bool,
+}
+
+/// The path of the temporary file used to replace the journal atomically.
+fn temporary(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+/// Writes the given entry to the journal at the given path, replacing any previous entry.
+///
+/// The entry is written to a temporary file first, so that the journal is never left half-written,
+/// and both the file and the rename are flushed to disk before returning, so that the entry
+/// survives a power loss.
+#[cfg(feature = "use_serde")]
+pub(crate) fn save(path: &Path, entry: &Entry) -> io::Result<()> {
+    let contents = serde_json::to_vec(entry)?;
+    let temporary = temporary(path);
+    let mut file = File::create(&temporary)?;
+    file.write_all(&contents)?;
+    file.sync_all()?;
+    fs::rename(temporary, path)?;
+    sync_dir(path)
+}
+
+/// Flushes the directory containing the given path to disk, so that a rename within it is durable.
+#[cfg(all(unix, feature = "use_serde"))]
+fn sync_dir(path: &Path) -> io::Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    File::open(dir)?.sync_all()
+}
+
+/// Flushes the directory containing the given path to disk (which only Unix needs or allows).
+#[cfg(all(not(unix), feature = "use_serde"))]
+fn sync_dir(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Writes the given entry to the journal at the given path, replacing any previous entry.
+#[cfg(not(feature = "use_serde"))]
+pub(crate) fn save(_path: &Path, _entry: &Entry) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "the journal requires the use_serde feature",
+    ))
+}
+
+/// Reads the entry from the journal at the given path, if there is one.
+#[cfg(feature = "use_serde")]
+pub(crate) fn load(path: &Path) -> io::Result<Option<Entry>> {
+    match fs::read(path) {
+        Ok(contents) => Ok(Some(serde_json::from_slice(&contents)?)),
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Reads the entry from the journal at the given path, if there is one.
+#[cfg(not(feature = "use_serde"))]
+pub(crate) fn load(_path: &Path) -> io::Result<Option<Entry>> {
+    Ok(None)
+}
+
+/// Removes the journal at the given path (if there is one), since no program is running.
+pub(crate) fn clear(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+#[cfg(all(test, feature = "use_serde"))]
+mod tests {
+    use super::*;
+    #[test]
+    fn round_trip() {
+        let path = std::env::temp_dir().join(format!("deoxy-journal-{}.json", Uuid::new_v4()));
+        assert_eq!(load(&path).unwrap(), None);
+        let entry = Entry {
+            id: Uuid::new_v4(),
+            actions: vec![Action::Perfuse(0), Action::Hail, Action::Finish],
+            step: 1,
+            elapsed: Duration::new(90, 0),
+            buffer: Some(0),
+            pump: 0,
+            aborted: false,
+        };
+        save(&path, &entry).unwrap();
+        assert_eq!(load(&path).unwrap(), Some(entry));
+        clear(&path).unwrap();
+        clear(&path).unwrap();
+        assert_eq!(load(&path).unwrap(), None);
+    }
+}
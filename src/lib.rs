@@ -28,32 +28,90 @@ pub use deoxy_core::*;
 pub mod actix {
     pub use actix_web::actix::{
         Actor, Addr, Arbiter, AsyncContext, Context, Handler as Handle, Message as ActixMessage,
-        SpawnHandle, System,
+        ResponseFuture, SpawnHandle, System,
     };
 }
 
 /// Re-export of `actix-web`.
 pub use actix_web;
 
+pub mod calibration;
+#[cfg(feature = "use_serde")]
+mod catalog;
 mod comm;
 mod config;
+mod device;
+mod error;
+#[cfg(feature = "use_serde")]
+pub mod event_log;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+#[cfg(feature = "history")]
+pub mod history;
+mod journal;
+#[cfg(feature = "use_serde")]
+mod library;
 pub mod mail;
+mod manifold;
+#[cfg(feature = "modbus")]
+pub mod modbus;
 mod motor;
-pub(crate) mod pin;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+pub mod notify;
+pub mod pin;
 mod pump;
+mod schedule;
+pub mod sensor;
 #[cfg(feature = "server")]
 pub mod server;
+mod stepper;
+pub mod thermal;
+pub mod watchdog;
+#[cfg(feature = "webhooks")]
+pub mod webhook;
 
+#[cfg(feature = "use_serde")]
+pub use self::catalog::{Catalog, CatalogEntry};
+#[cfg(feature = "use_serde")]
+pub use self::config::LoadError;
+#[cfg(feature = "use_serde")]
+pub use self::library::{Library, StoredProtocol};
+pub use self::manifold::Manifolds;
 pub use self::{
     comm::{
-        Coordinator, Error as CoordError, Message as CoordMessage, State as ExecState, Status,
-        StatusMessage, Update,
+        CheckHealth, ComponentHealth, Coordinator, Error as CoordError, Fault, Health, Jog,
+        Message as CoordMessage, QueuedProtocol, State as ExecState, Status, StatusMessage,
+        Subscribers, Update,
+    },
+    config::{
+        AdcConfig, BackendConfig, BubbleDetectorConfig, BufferConfig, Buffers, ChatConfig,
+        ChatService, Config, ConfigError, CorsConfig, EStopConfig, EventLogConfig,
+        FaultReportConfig, FeedbackConfig, FlowAlarm, FlowSensorConfig, GrpcConfig,
+        HomeAssistantConfig, LevelSensorConfig, MailConfig, MailSecurity, ManifoldConfig,
+        ModbusConfig, MotorConfig, MqttConfig, NotificationEvent, NotificationThrottleConfig,
+        PressureSensorConfig, PrimeConfig, PumpConfig, PushConfig, PwmMode, Recipient,
+        ReservoirConfig, Role, SmsConfig, StepperConfig, SupervisionConfig, TeardownStep,
+        TemperatureSensorConfig, ThermalConfig, ThrottleConfig, TokenConfig, ValveConfig,
+        Violation, WashConfig, WatchdogConfig, WebhookConfig, WebhookEvent, MAIN_MANIFOLD,
+    },
+    device::{Device, Error as DeviceError, Failed as DeviceFailed},
+    error::Error,
+    journal::Recovery,
+    motor::{
+        Angles, Calibrate as CalibrateMotor, CalibrationState, Feedback, Message as MotorMessage,
+        Motor, Position as MotorPosition, Verify as VerifyMotor,
     },
-    config::{Config, MotorConfig, PumpConfig},
-    motor::{Message as MotorMessage, Motor},
-    pin::{Error as PinError, Out, Pin, Pwm},
+    pin::{EdgeEvent, Error as PinError, GpioBackend, InputPin, Out, Pin, Pull, Pwm},
     pump::{Direction as PumpDirection, Message as PumpMessage, Pump},
+    schedule::{Schedule, ScheduledProtocol, MIN_SCHEDULE_INTERVAL},
+    sensor::{BubbleDetector, FlowSensor, PressureSensor, Reservoirs},
+    stepper::StepperMotor,
+    thermal::Thermostat,
+    watchdog::Watchdog,
 };
 
+#[cfg(feature = "dashboard")]
+pub use self::comm::tui::Dashboard;
 #[cfg(not(feature = "server"))]
 pub use self::comm::tui::Tui;
@@ -0,0 +1,171 @@
+//! A library of stored protocols.
+//!
+//! Each protocol is kept as a JSON file (named for its label) in the library directory, so that
+//! protocols can be managed once and run repeatedly rather than being pasted in for every run.
+//!
+//! Protocols stored by older versions of deoxy (in older versions of the protocol format) are
+//! upgraded as they're read, and written back in the current version when next saved.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use uuid::Uuid;
+
+use crate::{migrate_protocol, Protocol, PROTOCOL_VERSION};
+
+/// A protocol kept in the library.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StoredProtocol {
+    /// The version of the protocol format the protocol was stored in (see
+    /// [`PROTOCOL_VERSION`](../constant.PROTOCOL_VERSION.html)).
+    #[serde(default = "unversioned")]
+    pub version: u32,
+    /// The label of the stored protocol.
+    pub id: Uuid,
+    /// A human-readable name for the protocol.
+    pub name: String,
+    /// The protocol itself.
+    pub protocol: Protocol,
+}
+
+impl StoredProtocol {
+    /// Labels the given protocol with the given name, in the current version of the protocol
+    /// format.
+    pub fn new(id: Uuid, name: String, protocol: Protocol) -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            id,
+            name,
+            protocol,
+        }
+    }
+}
+
+/// The version of stored protocols without one (those stored before protocols were versioned).
+fn unversioned() -> u32 {
+    1
+}
+
+/// A directory of stored protocols.
+#[derive(Clone, Debug)]
+pub struct Library {
+    dir: PathBuf,
+}
+
+impl Library {
+    /// Opens the library in the given directory, creating the directory if necessary.
+    pub fn open(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+    /// The path of the file holding the protocol with the given label.
+    fn path(&self, id: Uuid) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+    /// Reads the stored protocol at the given path, upgrading it to the current version of the
+    /// protocol format.
+    fn read(path: &Path) -> io::Result<StoredProtocol> {
+        let mut document = serde_json::from_slice(&fs::read(path)?)?;
+        migrate_protocol(&mut document, "protocol")
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        Ok(serde_json::from_value(document)?)
+    }
+    /// Lists every stored protocol, ordered by name.
+    ///
+    /// Files in the directory which aren't stored protocols are skipped (with a warning).
+    pub fn list(&self) -> io::Result<Vec<StoredProtocol>> {
+        let mut protocols = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().map(|ext| ext != "json").unwrap_or(true) {
+                continue;
+            }
+            match Self::read(&path) {
+                Ok(protocol) => protocols.push(protocol),
+                Err(err) => log::warn!("Skipping {}: {}", path.display(), err),
+            }
+        }
+        protocols.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(protocols)
+    }
+    /// Fetches the stored protocol with the given label, if there is one.
+    pub fn get(&self, id: Uuid) -> io::Result<Option<StoredProtocol>> {
+        match Self::read(&self.path(id)) {
+            Ok(protocol) => Ok(Some(protocol)),
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+    /// Stores the given protocol, replacing any stored under the same label.
+    ///
+    /// The protocol is written to a temporary file first, so that it's never left half-written.
+    pub fn save(&self, protocol: &StoredProtocol) -> io::Result<()> {
+        let path = self.path(protocol.id);
+        let temporary = path.with_extension("json.tmp");
+        fs::write(&temporary, serde_json::to_vec_pretty(protocol)?)?;
+        fs::rename(temporary, path)
+    }
+    /// Removes the stored protocol with the given label, returning whether there was one.
+    pub fn remove(&self, id: Uuid) -> io::Result<bool> {
+        match fs::remove_file(self.path(id)) {
+            Ok(()) => Ok(true),
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Step;
+    #[test]
+    fn store_protocols() {
+        let dir = std::env::temp_dir().join(format!("deoxy-library-{}", Uuid::new_v4()));
+        let library = Library::open(&dir).unwrap();
+        let mut stored = StoredProtocol::new(
+            Uuid::new_v4(),
+            "Wash".into(),
+            Protocol::with_step(Step::Perfuse(0, None)),
+        );
+        assert!(library.get(stored.id).unwrap().is_none());
+        library.save(&stored).unwrap();
+        stored.name = "Rinse".into();
+        library.save(&stored).unwrap();
+        let list = library.list().unwrap();
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].name, "Rinse");
+        assert_eq!(library.get(stored.id).unwrap().unwrap().name, "Rinse");
+        assert!(library.remove(stored.id).unwrap());
+        assert!(!library.remove(stored.id).unwrap());
+        fs::remove_dir_all(dir).unwrap();
+    }
+    #[test]
+    fn upgrade_protocols() {
+        let dir = std::env::temp_dir().join(format!("deoxy-library-{}", Uuid::new_v4()));
+        let library = Library::open(&dir).unwrap();
+        let id = Uuid::new_v4();
+        let unversioned = format!(
+            r#"{{"id": "{}", "name": "Wash", "protocol": [{{"perfuse": [0, null]}}]}}"#,
+            id
+        );
+        fs::write(library.path(id), unversioned).unwrap();
+        let stored = library.get(id).unwrap().unwrap();
+        assert_eq!(stored.version, PROTOCOL_VERSION);
+        assert_eq!(stored.protocol, Protocol::with_step(Step::Perfuse(0, None)));
+        let id = Uuid::new_v4();
+        let newer = format!(
+            r#"{{"version": {}, "id": "{}", "name": "Wash", "protocol": []}}"#,
+            PROTOCOL_VERSION + 1,
+            id
+        );
+        fs::write(library.path(id), newer).unwrap();
+        let err = library.get(id).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(library.list().unwrap().len(), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,100 @@
+//! Gathers the logs attached to fault notifications, so that failures can be diagnosed without
+//! logging into the machine.
+
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use crate::config::FaultReportConfig;
+
+/// How much of a file is read at a time when looking for its last lines.
+const CHUNK: u64 = 8192;
+
+/// A plain-text file attached to a message.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Attachment {
+    /// The file's name, as shown to the recipient.
+    pub name: String,
+    /// The file's contents.
+    pub content: String,
+}
+
+/// Reads the last `lines` lines of the file at the given path.
+pub fn tail(path: &Path, lines: usize) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut start = file.metadata()?.len();
+    let mut buffer = vec![];
+    // One more newline than lines is needed, since the first line found may be partial.
+    while start > 0 && buffer.iter().filter(|&&byte| byte == b'\n').count() <= lines {
+        let size = CHUNK.min(start);
+        start -= size;
+        file.seek(SeekFrom::Start(start))?;
+        let mut chunk = vec![0; size as usize];
+        file.read_exact(&mut chunk)?;
+        chunk.extend_from_slice(&buffer);
+        buffer = chunk;
+    }
+    let text = String::from_utf8_lossy(&buffer);
+    let found = text.lines().collect::<Vec<_>>();
+    let mut tail = found[found.len().saturating_sub(lines)..].join("\n");
+    tail.push('\n');
+    Ok(tail)
+}
+
+/// Gathers the logs attached to fault notifications.
+#[derive(Clone, Debug, Default)]
+pub struct FaultReport {
+    config: FaultReportConfig,
+    /// Where the event log is kept, if it is.
+    events: Option<PathBuf>,
+}
+
+impl FaultReport {
+    /// Creates a report using the given configuration and event log (if one is kept).
+    pub fn new(config: FaultReportConfig, events: Option<PathBuf>) -> Self {
+        Self { config, events }
+    }
+    /// Reads the end of each log, skipping (and logging) any which can't be read.
+    pub fn attachments(&self) -> Vec<Attachment> {
+        let events = self.events.as_ref().filter(|_| self.config.events);
+        let logs = [
+            (events, "events.jsonl"),
+            (self.config.log.as_ref(), "log.txt"),
+        ];
+        logs.iter()
+            .filter_map(|(path, name)| {
+                let path = path.as_ref()?;
+                match tail(path, self.config.lines) {
+                    Ok(content) => Some(Attachment {
+                        name: (*name).into(),
+                        content,
+                    }),
+                    Err(err) => {
+                        log::warn!("Couldn't attach {}: {}", path.display(), err);
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs};
+    #[test]
+    fn tails() {
+        let path = env::temp_dir().join(format!("deoxy-tail-{}", uuid::Uuid::new_v4()));
+        let lines = (0..5000)
+            .map(|n| format!("line {}\n", n))
+            .collect::<String>();
+        fs::write(&path, lines).unwrap();
+        assert_eq!(tail(&path, 3).unwrap(), "line 4997\nline 4998\nline 4999\n");
+        assert_eq!(tail(&path, 5000).unwrap().lines().count(), 5000);
+        assert_eq!(tail(&path, 9000).unwrap().lines().count(), 5000);
+        fs::remove_file(&path).unwrap();
+    }
+}
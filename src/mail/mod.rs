@@ -0,0 +1,423 @@
+//! Contains utilities for sending email notifications.
+//!
+//! The administrators are notified by [`Email`](struct.Email.html), which mails each notification
+//! to those subscribed to its event.
+//!
+//! Mail goes through the SMTP server in the `[mail]` section of the configuration (which requires
+//! the `smtp` feature) if there is one, and through the local `sendmail` otherwise. Notifications
+//! about runs are rendered from [templates](template/index.html), and those about faults carry the
+//! end of the logs as [attachments](attachment/index.html).
+//!
+//! Delivery blocks until the server has taken the message (or given up), so notifications are
+//! mailed from their own threads rather than holding up the coordinator.
+
+use std::{
+    error, fmt,
+    io::{self, BufWriter, Write},
+    process::{Command, Stdio},
+    thread,
+};
+
+use uuid::Uuid;
+
+use crate::{
+    config::{MailConfig, NotificationEvent, Recipient},
+    notify::{self, Notification, Notifier},
+};
+
+pub mod attachment;
+pub mod template;
+
+pub use self::attachment::{Attachment, FaultReport};
+pub use self::template::{StepTiming, Summary, TemplateError, Templates};
+pub use crate::notify::Status;
+
+/// The sender used when no SMTP server is configured.
+const DEFAULT_FROM: &str = "deoxy@hmltn.me";
+
+/// The addresses of the given recipients who are subscribed to the given event.
+pub fn subscribers(recipients: &[Recipient], event: NotificationEvent) -> Vec<&str> {
+    recipients
+        .iter()
+        .filter(|recipient| recipient.is_subscribed(event))
+        .map(|recipient| recipient.address.as_str())
+        .collect()
+}
+
+/// A failure to send mail.
+#[derive(Debug)]
+pub enum Error {
+    /// `sendmail` couldn't be run, or reported a failure.
+    Sendmail(io::Error),
+    /// The SMTP server couldn't be reached.
+    Connection(io::Error),
+    /// A secure connection to the SMTP server couldn't be established.
+    Tls(String),
+    /// A username was configured without a password.
+    Credentials,
+    /// The SMTP server refused the credentials, the sender, or a recipient.
+    Rejected(String),
+    /// The message couldn't be put together (e.g. because an address is invalid).
+    Message(String),
+    /// SMTP was configured, but the `smtp` feature is disabled.
+    Unsupported,
+    /// Something else went wrong talking to the SMTP server.
+    Smtp(String),
+    /// The message's template couldn't be rendered.
+    Template(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Sendmail(_) => write!(f, "sendmail failed"),
+            Error::Connection(_) => write!(f, "couldn't reach the SMTP server"),
+            Error::Tls(err) => write!(f, "couldn't secure the SMTP connection: {}", err),
+            Error::Credentials => write!(f, "an SMTP username was given without a password"),
+            Error::Rejected(err) => write!(f, "the SMTP server refused the message: {}", err),
+            Error::Message(err) => write!(f, "invalid message: {}", err),
+            Error::Unsupported => write!(f, "SMTP requires the `smtp` feature"),
+            Error::Smtp(err) => write!(f, "SMTP failed: {}", err),
+            Error::Template(err) => write!(f, "couldn't render template: {}", err),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Sendmail(err) | Error::Connection(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Sends mail, through the configured SMTP server or `sendmail`.
+#[derive(Clone, Debug, Default)]
+pub struct Mailer {
+    config: Option<MailConfig>,
+    templates: Templates,
+}
+
+impl Mailer {
+    /// Creates a mailer using the given SMTP server (or `sendmail` if there is none) and
+    /// templates.
+    pub fn new(config: Option<MailConfig>, templates: Templates) -> Self {
+        Self { config, templates }
+    }
+    /// Notify the specified recipients of a status change, describing the given run and attaching
+    /// the given files.
+    pub fn notify(
+        &self,
+        to: &[impl ToString],
+        status: Status,
+        summary: &Summary,
+        attachments: &[Attachment],
+    ) -> Result<(), Error> {
+        let (subject, message) = self.render(status, summary)?;
+        self.deliver(to, &subject, &message, attachments)
+    }
+    /// Notifies the specified recipients of a status change like [`notify`](#method.notify), but
+    /// sends the email from another thread, returning once it has been put together.
+    ///
+    /// Failures to deliver the email are logged.
+    pub fn notify_in_background(
+        &self,
+        to: &[impl ToString],
+        status: Status,
+        summary: &Summary,
+        attachments: &[Attachment],
+    ) -> Result<(), Error> {
+        let (subject, message) = self.render(status, summary)?;
+        let outgoing = match self.prepare(to, &subject, &message, attachments) {
+            Some(outgoing) => outgoing,
+            None => return Ok(()),
+        };
+        let mailer = self.clone();
+        thread::spawn(move || {
+            if let Err(err) = mailer.transmit(&outgoing) {
+                log::error!("Failed to mail {}: {}", outgoing.to.join(", "), err);
+            }
+        });
+        Ok(())
+    }
+    /// The subject and body of the email about the given status change.
+    fn render(&self, status: Status, summary: &Summary) -> Result<(String, String), Error> {
+        let template = match status {
+            Status::Started => "started",
+            Status::Finished => "completed",
+            Status::Aborted => "aborted",
+            Status::Faulted => "faulted",
+            Status::Custom { subject, message } => {
+                return Ok((subject.to_string(), message.to_string()))
+            }
+        };
+        self.templates.render(template, summary)
+    }
+    /// Send an email to the specified recipients.
+    ///
+    /// Nothing is sent (and nothing can fail) if there are no recipients.
+    pub fn send(
+        &self,
+        to: &[impl ToString],
+        subject: impl ToString,
+        message: impl ToString,
+    ) -> Result<(), Error> {
+        self.deliver(to, &subject.to_string(), &message.to_string(), &[])
+    }
+    fn deliver(
+        &self,
+        to: &[impl ToString],
+        subject: &str,
+        message: &str,
+        attachments: &[Attachment],
+    ) -> Result<(), Error> {
+        match self.prepare(to, subject, message, attachments) {
+            Some(outgoing) => self.transmit(&outgoing),
+            None => Ok(()),
+        }
+    }
+    /// Puts together an email to the specified recipients, if there are any.
+    fn prepare(
+        &self,
+        to: &[impl ToString],
+        subject: &str,
+        message: &str,
+        attachments: &[Attachment],
+    ) -> Option<Outgoing> {
+        if to.is_empty() {
+            return None;
+        }
+        let to = to.iter().map(ToString::to_string).collect::<Vec<_>>();
+        let from = self
+            .config
+            .as_ref()
+            .map_or(DEFAULT_FROM, |config| config.from.as_str());
+        let boundary = format!("deoxy-{}", Uuid::new_v4().to_simple());
+        let email = compose(from, &to, subject, message, attachments, &boundary);
+        Some(Outgoing { to, email })
+    }
+    /// Sends the given email, blocking until it has been handed off.
+    fn transmit(&self, outgoing: &Outgoing) -> Result<(), Error> {
+        match &self.config {
+            Some(config) => smtp(config, &outgoing.to, &outgoing.email),
+            None => sendmail(&outgoing.email).map_err(Error::Sendmail),
+        }
+    }
+}
+
+/// An email which has been put together, but not yet sent.
+#[derive(Debug)]
+struct Outgoing {
+    to: Vec<String>,
+    /// The headers and body.
+    email: String,
+}
+
+/// Mails notifications to the administrators subscribed to them.
+#[derive(Clone, Debug)]
+pub struct Email {
+    mailer: Mailer,
+    recipients: Vec<Recipient>,
+}
+
+impl Email {
+    /// Creates a notifier mailing the given recipients through the given mailer.
+    pub fn new(mailer: Mailer, recipients: Vec<Recipient>) -> Self {
+        Self { mailer, recipients }
+    }
+}
+
+impl Notifier for Email {
+    fn name(&self) -> &str {
+        "email"
+    }
+    fn notify(&self, notification: &Notification) -> Result<(), notify::Error> {
+        let to = subscribers(&self.recipients, notification.event);
+        self.mailer
+            .notify_in_background(
+                &to,
+                notification.status,
+                notification.summary,
+                notification.attachments,
+            )
+            .map_err(notify::Error::Mail)
+    }
+}
+
+/// Notify the specified recipients of a status change (through `sendmail`), describing the given
+/// run.
+pub fn notify(to: &[impl ToString], status: Status, summary: &Summary) -> Result<(), Error> {
+    Mailer::default().notify(to, status, summary, &[])
+}
+
+/// Send an email to the specified recipients (through `sendmail`).
+pub fn mail(
+    to: &[impl ToString],
+    subject: impl ToString,
+    message: impl ToString,
+) -> Result<(), Error> {
+    Mailer::default().send(to, subject, message)
+}
+
+/// Puts together an email (headers and body), attaching the given files (as MIME parts separated
+/// by the given boundary).
+fn compose(
+    from: &str,
+    to: &[String],
+    subject: &str,
+    message: &str,
+    attachments: &[Attachment],
+    boundary: &str,
+) -> String {
+    let mut email = format!(
+        "From: {}\nTo: {}\nSubject: {}\nMIME-Version: 1.0\n",
+        from,
+        to.join(", "),
+        subject
+    );
+    let text = "Content-Type: text/plain; charset=utf-8\nContent-Transfer-Encoding: 8bit\n";
+    if attachments.is_empty() {
+        email.push_str(&format!("{}\n{}\n", text, message));
+        return email;
+    }
+    email.push_str(&format!(
+        "Content-Type: multipart/mixed; boundary=\"{}\"\n\n--{}\n{}\n{}\n",
+        boundary, boundary, text, message
+    ));
+    for attachment in attachments {
+        email.push_str(&format!(
+            "--{boundary}\nContent-Type: text/plain; charset=utf-8; name=\"{name}\"\n\
+             Content-Disposition: attachment; filename=\"{name}\"\n\
+             Content-Transfer-Encoding: base64\n\n",
+            boundary = boundary,
+            name = attachment.name
+        ));
+        let encoded = base64::encode(attachment.content.as_bytes());
+        for line in encoded.as_bytes().chunks(76) {
+            email.push_str(&String::from_utf8_lossy(line));
+            email.push('\n');
+        }
+    }
+    email.push_str(&format!("--{}--\n", boundary));
+    email
+}
+
+// Thanks to BurntSushi.
+fn sendmail(email: &str) -> io::Result<()> {
+    let mut child = Command::new("sendmail")
+        .arg("-t")
+        .stdin(Stdio::piped())
+        .spawn()?;
+    {
+        let mut buf = BufWriter::new(child.stdin.as_mut().unwrap());
+        write!(&mut buf, "{}", email)?;
+        writeln!(&mut buf, ".")?;
+    }
+    let status = child.wait()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(match status.code() {
+            None => io::Error::new(io::ErrorKind::Interrupted, "Email sending interrupted"),
+            Some(_) => io::Error::new(io::ErrorKind::Other, status.to_string()),
+        })
+    }
+}
+
+#[cfg(feature = "smtp")]
+fn smtp(config: &MailConfig, to: &[String], email: &str) -> Result<(), Error> {
+    use crate::config::MailSecurity;
+    use lettre::{
+        smtp::authentication::Credentials, ClientSecurity, ClientTlsParameters, EmailAddress,
+        Envelope, SendableEmail, SmtpClient, Transport,
+    };
+    use native_tls::TlsConnector;
+    use std::time::Duration;
+
+    /// How long the server has to answer (including connecting) before we give up.
+    const TIMEOUT: Duration = Duration::from_secs(30);
+
+    let address = |address: &str| {
+        EmailAddress::new(address.into()).map_err(|err| Error::Message(err.to_string()))
+    };
+    let recipients = to
+        .iter()
+        .map(|recipient| address(recipient))
+        .collect::<Result<Vec<_>, _>>()?;
+    let envelope = Envelope::new(Some(address(&config.from)?), recipients)
+        .map_err(|err| Error::Message(err.to_string()))?;
+    // SMTP requires CRLF line endings.
+    let email = SendableEmail::new(
+        envelope,
+        Uuid::new_v4().to_string(),
+        email.replace('\n', "\r\n").into_bytes(),
+    );
+    let tls = || -> Result<ClientTlsParameters, Error> {
+        let connector = TlsConnector::new().map_err(|err| Error::Tls(err.to_string()))?;
+        Ok(ClientTlsParameters::new(config.host.clone(), connector))
+    };
+    let security = match config.security {
+        MailSecurity::None => ClientSecurity::None,
+        MailSecurity::StartTls => ClientSecurity::Required(tls()?),
+        MailSecurity::Tls => ClientSecurity::Wrapper(tls()?),
+    };
+    let mut client = SmtpClient::new((config.host.as_str(), config.port()), security)
+        .map_err(smtp_error)?
+        .timeout(Some(TIMEOUT));
+    if let Some(username) = &config.username {
+        let password = config.password.clone().ok_or(Error::Credentials)?;
+        client = client.credentials(Credentials::new(username.clone(), password));
+    }
+    client
+        .transport()
+        .send(email)
+        .map(|_| ())
+        .map_err(smtp_error)
+}
+
+#[cfg(feature = "smtp")]
+fn smtp_error(err: lettre::smtp::error::Error) -> Error {
+    use lettre::smtp::error::Error as SmtpError;
+    match err {
+        SmtpError::Io(err) => Error::Connection(err),
+        SmtpError::Resolution => Error::Connection(io::Error::new(
+            io::ErrorKind::NotFound,
+            "couldn't resolve the server's address",
+        )),
+        SmtpError::Tls(err) => Error::Tls(err.to_string()),
+        err @ SmtpError::Transient(_) => Error::Rejected(err.to_string()),
+        err @ SmtpError::Permanent(_) => Error::Rejected(err.to_string()),
+        err => Error::Smtp(err.to_string()),
+    }
+}
+
+#[cfg(not(feature = "smtp"))]
+fn smtp(_: &MailConfig, _: &[String], _: &str) -> Result<(), Error> {
+    Err(Error::Unsupported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn composes() {
+        let to = ["a@example.com".to_string(), "b@example.com".to_string()];
+        assert_eq!(
+            compose("deoxy@example.com", &to, "Fault", "Oops.", &[], "b"),
+            "From: deoxy@example.com\nTo: a@example.com, b@example.com\nSubject: Fault\n\
+             MIME-Version: 1.0\nContent-Type: text/plain; charset=utf-8\n\
+             Content-Transfer-Encoding: 8bit\n\nOops.\n"
+        );
+        let log = Attachment {
+            name: "log.txt".into(),
+            content: "hello\n".into(),
+        };
+        let email = compose("deoxy@example.com", &to, "Fault", "Oops.", &[log], "b");
+        assert!(email.contains("Content-Type: multipart/mixed; boundary=\"b\"\n\n--b\n"));
+        assert!(
+            email.contains("filename=\"log.txt\"\nContent-Transfer-Encoding: base64\n\naGVsbG8K\n")
+        );
+        assert!(email.ends_with("--b--\n"));
+    }
+}
@@ -0,0 +1,508 @@
+//! Renders notification emails from templates.
+//!
+//! Each notification (`started`, `completed`, `aborted`, or `faulted`) has a [Handlebars]
+//! template, which is rendered with a [`Summary`](struct.Summary.html) of the run. The first line
+//! of the rendered template is the subject, and the rest is the body. Templates found in the configured directory
+//! (as `<name>.hbs`, e.g. `completed.hbs`) replace the built-in ones.
+//!
+//! The templates are given:
+//!
+//! - `protocol`, the name of the protocol (or, if it isn't in the library, the job's label);
+//! - `job`, the job's label, if it has one;
+//! - `steps`, the steps run, each with a `number`, a `description`, and a `duration` (formatted,
+//!   as well as in `seconds`);
+//! - `table`, the steps laid out in a plain-text table;
+//! - `total`, how long the run took (formatted), if it started;
+//! - `remaining`, roughly how long until the run finishes (formatted), if it's running;
+//! - `warnings`, anything which went wrong without stopping the run; and
+//! - `fault`, the fault which stopped the run, if any.
+//!
+//! Without the `templates` feature, the built-in messages are used (and templates are ignored).
+//!
+//! [Handlebars]: https://handlebarsjs.com
+
+use std::{fmt, io, path::Path, time::Duration};
+
+#[cfg(feature = "templates")]
+use std::{fs, sync::Arc};
+
+#[cfg(feature = "templates")]
+use handlebars::Handlebars;
+use uuid::Uuid;
+
+use super::Error;
+use crate::{comm::format_duration, Action, Buffers};
+
+/// The names of the templates.
+#[cfg(feature = "templates")]
+const NAMES: [&str; 4] = ["started", "completed", "aborted", "faulted"];
+
+/// The built-in template for started runs.
+#[cfg(feature = "templates")]
+const STARTED: &str = "\
+{{protocol}} started
+The decellularization run of {{protocol}} has started{{#if remaining}}, and should finish in about {{remaining}}{{/if}}.
+
+{{#if warnings}}Warnings:
+{{#each warnings}}- {{this}}
+{{/each}}{{/if}}";
+
+/// The built-in template for completed runs.
+#[cfg(feature = "templates")]
+const COMPLETED: &str = "\
+{{protocol}} completed
+The decellularization run of {{protocol}} has completed as scheduled{{#if total}} after {{total}}{{/if}}.
+
+{{table}}
+
+{{#if warnings}}Warnings:
+{{#each warnings}}- {{this}}
+{{/each}}{{/if}}";
+
+/// The built-in template for aborted runs.
+#[cfg(feature = "templates")]
+const ABORTED: &str = "\
+{{protocol}} aborted
+The decellularization run of {{protocol}} has been aborted manually{{#if total}} after {{total}}{{/if}}.
+
+{{table}}
+
+{{#if warnings}}Warnings:
+{{#each warnings}}- {{this}}
+{{/each}}{{/if}}";
+
+/// The built-in template for faults.
+#[cfg(feature = "templates")]
+const FAULTED: &str = "\
+Fault{{#if steps}} during {{protocol}}{{/if}}
+The system has been stopped because of a fault: {{fault}}.
+
+{{#if steps}}The run of {{protocol}} was stopped{{#if total}} after {{total}}{{/if}}.
+
+{{table}}{{/if}}
+
+{{#if warnings}}Warnings:
+{{#each warnings}}- {{this}}
+{{/each}}{{/if}}";
+
+/// How long a step of a run took.
+#[derive(Clone, Debug)]
+pub struct StepTiming {
+    /// The step.
+    pub action: Action,
+    /// How long it took, not counting time spent paused.
+    pub duration: Duration,
+}
+
+/// What happened during a run, for notifications about it.
+#[derive(Clone, Debug, Default)]
+pub struct Summary {
+    /// The name of the protocol, if it came from the library.
+    pub protocol: Option<String>,
+    /// The label the run went by, if it had one.
+    pub job: Option<Uuid>,
+    /// The configured buffers, for referring to them by name.
+    pub buffers: Buffers,
+    /// The steps run (or started), in order.
+    pub steps: Vec<StepTiming>,
+    /// The step under way (numbered from zero) and what it does, if the run is in progress.
+    pub step: Option<(usize, Action)>,
+    /// How long the run took, not counting time spent paused.
+    pub total: Option<Duration>,
+    /// Roughly how long until the run finishes, if it's running.
+    pub remaining: Option<Duration>,
+    /// Anything which went wrong without stopping the run.
+    pub warnings: Vec<String>,
+    /// The fault which stopped the run, if any.
+    pub fault: Option<String>,
+}
+
+impl Summary {
+    /// How the run is referred to: by its protocol's name, or else its label.
+    pub fn name(&self) -> String {
+        match (&self.protocol, self.job) {
+            (Some(name), _) => name.clone(),
+            (None, Some(job)) => format!("job {}", job),
+            (None, None) => "the protocol".into(),
+        }
+    }
+}
+
+/// A failure to load templates.
+#[derive(Debug)]
+pub enum TemplateError {
+    /// A template couldn't be read.
+    Io(io::Error),
+    /// A template isn't valid.
+    Invalid {
+        /// The name of the template.
+        name: &'static str,
+        /// What's wrong with it.
+        message: String,
+    },
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TemplateError::Io(_) => write!(f, "couldn't read template"),
+            TemplateError::Invalid { name, message } => {
+                write!(f, "invalid {} template: {}", name, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TemplateError::Io(err) => Some(err),
+            TemplateError::Invalid { .. } => None,
+        }
+    }
+}
+
+/// Describes the given step for a person, naming buffers as configured.
+pub(crate) fn describe(action: &Action, buffers: &Buffers) -> String {
+    match action {
+        Action::Perfuse(buffer) => format!("Perfuse with {}", buffers.name(*buffer)),
+        Action::Sleep(duration) => format!("Wait {}", format_duration(*duration)),
+        Action::Hail => "Wait for the operator".into(),
+        Action::Gradient {
+            from, to, duration, ..
+        } => format!(
+            "Ramp from {} to {} over {}",
+            buffers.name(*from),
+            buffers.name(*to),
+            format_duration(*duration)
+        ),
+        Action::Drain => "Drain".into(),
+        Action::Mix {
+            cycles,
+            forward,
+            backward,
+        } => format!(
+            "Mix {} time(s) ({} forward, {} back)",
+            cycles,
+            format_duration(*forward),
+            format_duration(*backward)
+        ),
+        Action::Finish => "Finish".into(),
+        Action::Notify(notification) => format!("Notify ({})", notification.subject),
+        Action::UsePump(pump) => format!("Switch to pump {}", pump),
+        Action::HoldTemperature {
+            target, duration, ..
+        } => format!(
+            "Hold at {:.1} °C for {}",
+            target,
+            format_duration(*duration)
+        ),
+        Action::SetPosition { motor, position } => {
+            format!("Move motor {} to {}", motor, position)
+        }
+        Action::Iterate(Some(iteration)) => format!("Begin {}", iteration),
+        Action::Iterate(None) => "Finish repeating".into(),
+        Action::WaitUntil {
+            sensor,
+            predicate,
+            timeout,
+        } => {
+            let until = format!(
+                "Wait until the {} is {} {}",
+                sensor,
+                predicate,
+                sensor.unit()
+            );
+            match timeout {
+                Some(timeout) => format!("{} (at most {})", until, format_duration(*timeout)),
+                None => until,
+            }
+        }
+        Action::Branch {
+            sensor, predicate, ..
+        } => format!(
+            "Check whether the {} is {} {}",
+            sensor,
+            predicate,
+            sensor.unit()
+        ),
+        Action::Skip(count) => format!("Skip {} step(s)", count),
+        Action::Hook(event) => format!("Post \"{}\" to the webhooks", event),
+        Action::Overrun(Some(overrun)) => format!(
+            "Tolerate overruns of up to {} (then {})",
+            format_duration(overrun.tolerance),
+            overrun.policy
+        ),
+        Action::Overrun(None) => "Tolerate overruns as configured".into(),
+    }
+}
+
+/// Lays out the given steps in a plain-text table.
+pub(crate) fn table(steps: &[StepTiming], buffers: &Buffers) -> String {
+    let durations = steps
+        .iter()
+        .map(|step| format_duration(step.duration))
+        .collect::<Vec<_>>();
+    let width = durations
+        .iter()
+        .map(String::len)
+        .fold("Duration".len(), usize::max);
+    let mut table = format!("{:>4}  {:<width$}  Step", "#", "Duration", width = width);
+    for (index, (step, duration)) in steps.iter().zip(durations).enumerate() {
+        table.push_str(&format!(
+            "\n{:>4}  {:<width$}  {}",
+            index + 1,
+            duration,
+            describe(&step.action, buffers),
+            width = width
+        ));
+    }
+    table
+}
+
+/// Removes runs of blank lines (left by sections without content) and trailing whitespace.
+fn tidy(text: &str) -> String {
+    let mut tidied = String::new();
+    let mut blank = true;
+    for line in text.lines().map(str::trim_end) {
+        if line.is_empty() && blank {
+            continue;
+        }
+        blank = line.is_empty();
+        tidied.push_str(line);
+        tidied.push('\n');
+    }
+    tidied.trim_end().to_string()
+}
+
+/// What the templates are given.
+#[cfg_attr(feature = "templates", derive(Serialize))]
+#[cfg_attr(not(feature = "templates"), allow(dead_code))]
+struct Context<'a> {
+    protocol: String,
+    job: Option<String>,
+    steps: Vec<StepContext>,
+    table: String,
+    total: Option<String>,
+    remaining: Option<String>,
+    warnings: &'a [String],
+    fault: Option<&'a str>,
+}
+
+/// What the templates are given for each step.
+#[cfg_attr(feature = "templates", derive(Serialize))]
+#[cfg_attr(not(feature = "templates"), allow(dead_code))]
+struct StepContext {
+    number: usize,
+    description: String,
+    duration: String,
+    seconds: f64,
+}
+
+impl<'a> Context<'a> {
+    fn new(summary: &'a Summary) -> Self {
+        let steps = summary
+            .steps
+            .iter()
+            .enumerate()
+            .map(|(index, step)| StepContext {
+                number: index + 1,
+                description: describe(&step.action, &summary.buffers),
+                duration: format_duration(step.duration),
+                seconds: step.duration.as_secs() as f64
+                    + f64::from(step.duration.subsec_nanos()) * 1e-9,
+            })
+            .collect();
+        Self {
+            protocol: summary.name(),
+            job: summary.job.map(|job| job.to_string()),
+            steps,
+            table: table(&summary.steps, &summary.buffers),
+            total: summary.total.map(format_duration),
+            remaining: summary.remaining.map(format_duration),
+            warnings: &summary.warnings,
+            fault: summary.fault.as_ref().map(String::as_str),
+        }
+    }
+    /// The built-in message for the given notification, for when templates aren't available.
+    #[cfg(not(feature = "templates"))]
+    fn plain(&self, name: &str) -> String {
+        let took = self
+            .total
+            .as_ref()
+            .map(|total| format!(" after {}", total))
+            .unwrap_or_default();
+        let mut message = match name {
+            "started" => format!(
+                "{} started\nThe decellularization run of {} has started{}.",
+                self.protocol,
+                self.protocol,
+                self.remaining
+                    .as_ref()
+                    .map(|remaining| format!(", and should finish in about {}", remaining))
+                    .unwrap_or_default()
+            ),
+            "completed" => format!(
+                "{} completed\nThe decellularization run of {} has completed as scheduled{}.",
+                self.protocol, self.protocol, took
+            ),
+            "aborted" => format!(
+                "{} aborted\nThe decellularization run of {} has been aborted manually{}.",
+                self.protocol, self.protocol, took
+            ),
+            _ => format!(
+                "Fault\nThe system has been stopped because of a fault: {}.",
+                self.fault.unwrap_or("unknown")
+            ),
+        };
+        if !self.steps.is_empty() {
+            message.push_str(&format!("\n\n{}", self.table));
+        }
+        if !self.warnings.is_empty() {
+            message.push_str("\n\nWarnings:");
+            for warning in self.warnings {
+                message.push_str(&format!("\n- {}", warning));
+            }
+        }
+        message
+    }
+}
+
+/// The templates notifications are rendered from.
+#[derive(Clone)]
+#[cfg_attr(not(feature = "templates"), derive(Copy))]
+pub struct Templates {
+    #[cfg(feature = "templates")]
+    registry: Arc<Handlebars<'static>>,
+}
+
+impl fmt::Debug for Templates {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Templates").finish()
+    }
+}
+
+/// A registry holding the built-in templates.
+#[cfg(feature = "templates")]
+fn registry() -> Handlebars<'static> {
+    let mut registry = Handlebars::new();
+    // The messages are plain text.
+    registry.register_escape_fn(handlebars::no_escape);
+    for (name, source) in NAMES.iter().zip(&[STARTED, COMPLETED, ABORTED, FAULTED]) {
+        registry
+            .register_template_string(name, source)
+            .expect("Built-in templates are valid");
+    }
+    registry
+}
+
+impl Default for Templates {
+    #[cfg(feature = "templates")]
+    fn default() -> Self {
+        Self {
+            registry: Arc::new(registry()),
+        }
+    }
+    #[cfg(not(feature = "templates"))]
+    fn default() -> Self {
+        Self {}
+    }
+}
+
+impl Templates {
+    /// Loads the templates in the given directory, using the built-in ones for any not found.
+    #[cfg(feature = "templates")]
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self, TemplateError> {
+        let mut registry = registry();
+        for &name in &NAMES {
+            let path = dir.as_ref().join(format!("{}.hbs", name));
+            let source = match fs::read_to_string(&path) {
+                Ok(source) => source,
+                Err(ref err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(TemplateError::Io(err)),
+            };
+            log::debug!("Using the {} template at {}", name, path.display());
+            registry
+                .register_template_string(name, source)
+                .map_err(|err| TemplateError::Invalid {
+                    name,
+                    message: err.to_string(),
+                })?;
+        }
+        Ok(Self {
+            registry: Arc::new(registry),
+        })
+    }
+    /// Uses the built-in messages (templates require the `templates` feature).
+    #[cfg(not(feature = "templates"))]
+    pub fn load(_dir: impl AsRef<Path>) -> Result<Self, TemplateError> {
+        Ok(Self::default())
+    }
+    /// Renders the named template with the given summary, returning the subject and body.
+    pub(crate) fn render(&self, name: &str, summary: &Summary) -> Result<(String, String), Error> {
+        let context = Context::new(summary);
+        #[cfg(feature = "templates")]
+        let rendered = self
+            .registry
+            .render(name, &context)
+            .map_err(|err| Error::Template(err.to_string()))?;
+        #[cfg(not(feature = "templates"))]
+        let rendered = context.plain(name);
+        let mut lines = rendered.trim_start().splitn(2, '\n');
+        let subject = lines.next().unwrap_or_default().trim().to_string();
+        Ok((subject, tidy(lines.next().unwrap_or_default())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BufferConfig;
+    fn summary() -> Summary {
+        Summary {
+            protocol: Some("Standard exchange".into()),
+            job: None,
+            buffers: Buffers::default(),
+            steps: vec![
+                StepTiming {
+                    action: Action::Perfuse(1),
+                    duration: Duration::from_secs(750),
+                },
+                StepTiming {
+                    action: Action::Sleep(Duration::from_secs(3600)),
+                    duration: Duration::from_secs(3605),
+                },
+            ],
+            step: None,
+            total: Some(Duration::from_secs(4355)),
+            remaining: None,
+            warnings: vec!["A bubble was detected".into()],
+            fault: None,
+        }
+    }
+    #[test]
+    fn tabulates() {
+        assert_eq!(
+            table(&summary().steps, &Buffers::default()),
+            "   #  Duration  Step\n   1  12m 30s   Perfuse with buffer 1\n   2  1h 0m     Wait 1h 0m"
+        );
+        let buffers = Buffers::new(vec![BufferConfig::new(1, "PBS")]);
+        assert_eq!(describe(&Action::Perfuse(1), &buffers), "Perfuse with PBS");
+    }
+    #[test]
+    fn tidies() {
+        assert_eq!(tidy("\n\nOne  \n\n\n\nTwo\n\n"), "One\n\nTwo");
+    }
+    #[test]
+    fn renders() {
+        let (subject, body) = Templates::default()
+            .render("completed", &summary())
+            .unwrap();
+        assert_eq!(subject, "Standard exchange completed");
+        assert!(body.starts_with(
+            "The decellularization run of Standard exchange has completed as scheduled after 1h \
+             12m.\n\n   #  Duration"
+        ));
+        assert!(body.ends_with("Warnings:\n- A bubble was detected"));
+    }
+}
@@ -0,0 +1,91 @@
+//! Running several manifolds side by side.
+//!
+//! Each manifold (a set of pumps and motors, usually serving its own sample chamber) gets its own
+//! [`Coordinator`](../struct.Coordinator.html), so that each can run its own protocols
+//! independently of the others.
+
+use std::collections::{btree_map, BTreeMap};
+
+use crate::{
+    actix::*,
+    comm::{Error, Message, Subscribers},
+    Config, Coordinator, Fault, Status, StatusMessage, Update, MAIN_MANIFOLD,
+};
+
+/// Passes the main manifold's emergency stop on to the other manifolds.
+#[derive(Debug)]
+struct Relay {
+    others: Vec<Addr<Coordinator>>,
+}
+
+impl Update for Relay {
+    fn handle(&self, status: &Status, _coord: &Subscribers) {
+        if let StatusMessage::Fault(Fault::EmergencyStop) = status.message {
+            for other in &self.others {
+                other.do_send(Message::Latch(Fault::EmergencyStop));
+            }
+        }
+    }
+}
+
+/// The running manifolds, by name.
+#[derive(Debug)]
+pub struct Manifolds {
+    coordinators: BTreeMap<String, Addr<Coordinator>>,
+}
+
+impl Manifolds {
+    /// Starts a coordinator for each configured manifold.
+    ///
+    /// If only the main manifold is configured, its status updates aren't marked with its name.
+    /// Each coordinator shuts down when the process is asked to exit (see
+    /// [`Coordinator::handle_signals`](../struct.Coordinator.html#method.handle_signals)).
+    pub fn start(config: Config) -> Result<Self, Error> {
+        config.validate()?;
+        let configs = config.split();
+        let mut names = configs.iter().map(|(name, _)| name).collect::<Vec<_>>();
+        names.sort();
+        if let Some(pair) = names.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(Error::DuplicateManifold(pair[0].clone()));
+        }
+        let several = configs.len() > 1;
+        // Acquire everything before starting anything, so that a failure leaves nothing running.
+        let mut prepared = vec![];
+        for (name, config) in configs {
+            let mut coordinator = Coordinator::try_new(config)?.handle_signals();
+            if several {
+                coordinator = coordinator.in_manifold(name.clone());
+            }
+            prepared.push((name, coordinator));
+        }
+        let coordinators = prepared
+            .into_iter()
+            .map(|(name, coordinator)| (name, coordinator.start()))
+            .collect();
+        let manifolds = Self { coordinators };
+        let others = manifolds
+            .iter()
+            .filter(|&(name, _)| name != MAIN_MANIFOLD)
+            .map(|(_, addr)| addr.clone())
+            .collect::<Vec<_>>();
+        if !others.is_empty() {
+            let relay = Relay { others };
+            manifolds
+                .main()
+                .do_send(Message::Subscribe(Box::new(relay)));
+        }
+        Ok(manifolds)
+    }
+    /// The coordinator running the main manifold.
+    pub fn main(&self) -> &Addr<Coordinator> {
+        &self.coordinators[MAIN_MANIFOLD]
+    }
+    /// The coordinator running the manifold with the given name, if there is one.
+    pub fn get(&self, name: &str) -> Option<&Addr<Coordinator>> {
+        self.coordinators.get(name)
+    }
+    /// The manifolds, ordered by name.
+    pub fn iter(&self) -> btree_map::Iter<String, Addr<Coordinator>> {
+        self.coordinators.iter()
+    }
+}
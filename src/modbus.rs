@@ -0,0 +1,339 @@
+//! Exposing the coordinator's state (and taking basic commands) over Modbus TCP.
+//!
+//! The coordinator acts as a Modbus server (slave), answering any unit identifier, so that
+//! building automation and SCADA systems can show it on their dashboards.
+//!
+//! ## Registers
+//! The input registers (read with function 4) describe the coordinator:
+//!
+//! | Register | Contents                                                                  |
+//! |----------|---------------------------------------------------------------------------|
+//! | 0        | The state (see [`state_code`](fn.state_code.html))                        |
+//! | 1        | The step being run, counting from 1 (0 if no program has been started)    |
+//! | 2–3      | How long the program has been running, in seconds (high word first)       |
+//! | 4–5      | Roughly how long until the program finishes, in seconds (high word first) |
+//! | 6        | How many jobs are queued                                                  |
+//! | 7        | How many status updates there have been (wrapping), to notice changes     |
+//!
+//! Writing to holding register 0 (with function 6 or 16) carries out a command, as if an operator
+//! named `modbus` had sent it: 1 pauses, 2 resumes, 3 continues, 4 stops, 5 aborts, 6 halts, and
+//! 7 resets a latched fault. A command the coordinator refuses is answered with exception 4 (server
+//! device failure). The register always reads as 0.
+
+use std::{
+    fmt,
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use futures::Future;
+
+use crate::{
+    actix::Addr,
+    comm::{Message, Subscribers},
+    Coordinator, ExecState, Status, StatusMessage, Update,
+};
+
+/// Reads holding registers.
+const READ_HOLDING: u8 = 3;
+/// Reads input registers.
+const READ_INPUT: u8 = 4;
+/// Writes a single holding register.
+const WRITE_SINGLE: u8 = 6;
+/// Writes several holding registers.
+const WRITE_MULTIPLE: u8 = 16;
+/// The number of input registers.
+const INPUTS: usize = 8;
+/// The most registers which can be read at once.
+const MAX_READ: u16 = 125;
+
+/// The reasons a request can be refused.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Exception {
+    IllegalFunction = 1,
+    IllegalAddress = 2,
+    IllegalValue = 3,
+    DeviceFailure = 4,
+}
+
+/// The number representing the given state in input register 0.
+///
+/// | Code | State       |
+/// |------|-------------|
+/// | 0    | Stopped     |
+/// | 1    | Running     |
+/// | 2    | Waiting     |
+/// | 3    | Paused      |
+/// | 4    | Priming     |
+/// | 5    | Calibrating |
+/// | 6    | Manual      |
+/// | 7    | Faulted     |
+/// | 8    | Washing     |
+pub fn state_code(state: ExecState) -> u16 {
+    match state {
+        ExecState::Stopped { .. } => 0,
+        ExecState::Running => 1,
+        ExecState::Waiting => 2,
+        ExecState::Paused => 3,
+        ExecState::Priming => 4,
+        ExecState::Calibrating { .. } => 5,
+        ExecState::Manual => 6,
+        ExecState::Faulted => 7,
+        ExecState::Washing => 8,
+    }
+}
+
+/// The command written to holding register 0.
+fn command(value: u16) -> Option<Message> {
+    Some(match value {
+        1 => Message::Pause,
+        2 => Message::Resume,
+        3 => Message::Continue,
+        4 => Message::Stop,
+        5 => Message::Abort,
+        6 => Message::Halt,
+        7 => Message::Reset,
+        _ => return None,
+    })
+}
+
+/// The given duration in whole seconds, split into two registers (high word first).
+fn split(duration: Option<Duration>) -> [u16; 2] {
+    let seconds = duration.map_or(0, |duration| {
+        duration.as_secs().min(u64::from(std::u32::MAX)) as u32
+    });
+    [(seconds >> 16) as u16, seconds as u16]
+}
+
+fn word(bytes: &[u8], index: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([
+        *bytes.get(index)?,
+        *bytes.get(index + 1)?,
+    ]))
+}
+
+/// Something to call for each holding register written.
+type Writer<'a> = &'a mut dyn FnMut(u16, u16) -> Result<(), Exception>;
+
+/// Carries out the given request (a PDU: a function code and its data), returning the response.
+fn answer(request: &[u8], inputs: &[u16; INPUTS], write: Writer) -> Result<Vec<u8>, Exception> {
+    let function = request.first().cloned().unwrap_or_default();
+    let malformed = Exception::IllegalValue;
+    match function {
+        READ_HOLDING | READ_INPUT => {
+            let start = word(request, 1).ok_or(malformed)?;
+            let count = word(request, 3).ok_or(malformed)?;
+            if count == 0 || count > MAX_READ {
+                return Err(Exception::IllegalValue);
+            }
+            let (start, end) = (usize::from(start), usize::from(start) + usize::from(count));
+            let values = if function == READ_INPUT {
+                inputs
+                    .get(start..end)
+                    .ok_or(Exception::IllegalAddress)?
+                    .to_vec()
+            } else if end <= 1 {
+                vec![0]
+            } else {
+                return Err(Exception::IllegalAddress);
+            };
+            let mut response = vec![function, (values.len() * 2) as u8];
+            for value in values {
+                response.extend_from_slice(&value.to_be_bytes());
+            }
+            Ok(response)
+        }
+        WRITE_SINGLE => {
+            let address = word(request, 1).ok_or(malformed)?;
+            let value = word(request, 3).ok_or(malformed)?;
+            write(address, value)?;
+            Ok(request[..5].to_vec())
+        }
+        WRITE_MULTIPLE => {
+            let start = word(request, 1).ok_or(malformed)?;
+            let count = word(request, 3).ok_or(malformed)?;
+            let values = request
+                .get(6..6 + usize::from(count) * 2)
+                .ok_or(malformed)?;
+            for (offset, value) in values.chunks(2).enumerate() {
+                let value = u16::from_be_bytes([value[0], value[1]]);
+                write(start + offset as u16, value)?;
+            }
+            Ok(request[..5].to_vec())
+        }
+        _ => Err(Exception::IllegalFunction),
+    }
+}
+
+/// Answers the given request (a PDU: a function code and its data) using the given input
+/// registers, calling `write` for each holding register written.
+fn respond(request: &[u8], inputs: &[u16; INPUTS], write: Writer) -> Vec<u8> {
+    answer(request, inputs, write).unwrap_or_else(|exception| {
+        let function = request.first().cloned().unwrap_or_default();
+        vec![function | 0x80, exception as u8]
+    })
+}
+
+/// The coordinator's state, as exposed in the input registers.
+type Registers = Arc<Mutex<[u16; INPUTS]>>;
+
+/// Serves requests from a single client until it disconnects.
+fn serve(
+    mut stream: TcpStream,
+    registers: &Registers,
+    coord: &Addr<Coordinator>,
+) -> io::Result<()> {
+    let mut write = |address: u16, value: u16| {
+        if address != 0 {
+            return Err(Exception::IllegalAddress);
+        }
+        let message = command(value).ok_or(Exception::IllegalValue)?;
+        let message = Message::Acting {
+            user: "modbus".into(),
+            message: Box::new(message),
+        };
+        match coord.send(message).wait() {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(err)) => {
+                log::warn!("Modbus command failed: {}", err);
+                Err(Exception::DeviceFailure)
+            }
+            Err(err) => {
+                log::error!("Failed to reach coordinator: {}", err);
+                Err(Exception::DeviceFailure)
+            }
+        }
+    };
+    loop {
+        // The MBAP header: transaction, protocol, length (of what follows), and unit.
+        let mut header = [0; 7];
+        match stream.read_exact(&mut header) {
+            Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            result => result?,
+        }
+        let length = usize::from(u16::from_be_bytes([header[4], header[5]]));
+        let mut request = vec![0; length.saturating_sub(1)];
+        stream.read_exact(&mut request)?;
+        let inputs = *registers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let response = respond(&request, &inputs, &mut write);
+        let mut frame = header[..4].to_vec();
+        frame.extend_from_slice(&(response.len() as u16 + 1).to_be_bytes());
+        frame.push(header[6]);
+        frame.extend_from_slice(&response);
+        stream.write_all(&frame)?;
+    }
+}
+
+/// A Modbus TCP server, which exposes the coordinator's updates.
+pub struct Modbus {
+    registers: Registers,
+    address: SocketAddr,
+}
+
+impl fmt::Debug for Modbus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Modbus")
+            .field("address", &self.address)
+            .finish()
+    }
+}
+
+impl Modbus {
+    /// Listens on the given address, carrying out commands using the given coordinator.
+    pub fn serve(address: SocketAddr, coord: Addr<Coordinator>) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let registers = Registers::default();
+        let shared = Arc::clone(&registers);
+        log::info!("Serving Modbus TCP on {}.", address);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(err) => {
+                        log::warn!("Failed to accept Modbus connection: {}", err);
+                        continue;
+                    }
+                };
+                let (registers, coord) = (Arc::clone(&shared), coord.clone());
+                thread::spawn(move || {
+                    if let Err(err) = serve(stream, &registers, &coord) {
+                        log::warn!("Modbus connection failed: {}", err);
+                    }
+                });
+            }
+        });
+        Ok(Self { registers, address })
+    }
+}
+
+impl Update for Modbus {
+    fn handle(&self, status: &Status, _coord: &Subscribers) {
+        if let StatusMessage::Reading { .. } = status.message {
+            return;
+        }
+        let mut registers = self
+            .registers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let [elapsed_high, elapsed_low] = split(status.elapsed);
+        let [remaining_high, remaining_low] = split(status.remaining);
+        *registers = [
+            state_code(status.state),
+            status.step.map_or(0, |step| step as u16 + 1),
+            elapsed_high,
+            elapsed_low,
+            remaining_high,
+            remaining_low,
+            status.queue.len() as u16,
+            registers[7].wrapping_add(1),
+        ];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn responds() {
+        let inputs = [1, 3, 0, 90, 0, 600, 0, 12];
+        let mut written = vec![];
+        let mut write = |address: u16, value: u16| {
+            written.push((address, value));
+            if value == 7 {
+                Err(Exception::DeviceFailure)
+            } else {
+                Ok(())
+            }
+        };
+        assert_eq!(
+            respond(&[4, 0, 1, 0, 3], &inputs, &mut write),
+            vec![4, 6, 0, 3, 0, 0, 0, 90]
+        );
+        assert_eq!(
+            respond(&[4, 0, 6, 0, 3], &inputs, &mut write),
+            vec![0x84, 2]
+        );
+        assert_eq!(
+            respond(&[3, 0, 0, 0, 1], &inputs, &mut write),
+            vec![3, 2, 0, 0]
+        );
+        assert_eq!(
+            respond(&[6, 0, 0, 0, 5], &inputs, &mut write),
+            vec![6, 0, 0, 0, 5]
+        );
+        assert_eq!(
+            respond(&[6, 0, 0, 0, 7], &inputs, &mut write),
+            vec![0x86, 4]
+        );
+        assert_eq!(
+            respond(&[1, 0, 0, 0, 1], &inputs, &mut write),
+            vec![0x81, 1]
+        );
+        assert_eq!(written, vec![(0, 5), (0, 7)]);
+    }
+}
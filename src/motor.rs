@@ -1,14 +1,19 @@
 //! Motor management.
 
-use std::{ops::RangeInclusive, time::Duration};
+use std::{collections::BTreeMap, ops::RangeInclusive, time::Duration};
+
+use actix_web::actix::Recipient;
 
 use crate::{
     actix::*,
+    calibration::{Calibration, Mark},
+    device::{Device, Error, Failed, Supervisor},
     pin::{Error as PinError, Pin, Pwm},
+    sensor::adc::Adc,
 };
 
 /// A message that can be sent to a motor to change its position.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub enum Message {
     /// Requests that the motor be set to the closed position.
     Close,
@@ -18,12 +23,153 @@ pub enum Message {
     Shut,
     /// Turns off the motor's output signal.
     Stop,
+    /// Requests that the motor be set to the named position (as configured for the motor).
+    SetPosition(String),
+    /// Requests that the motor be set to the given angle, in degrees (relative to the closed
+    /// position).
+    SetAngle(u16),
 }
 
 impl ActixMessage for Message {
+    type Result = Result<(), Error>;
+}
+
+/// The angles (in degrees) of a motor's positions.
+///
+/// The defaults suit a valve whose open, closed, and shut positions are 90º apart, in that order;
+/// mechanically mirrored or offset valves can use different angles. Motors with no configured
+/// angles use positions spanning their range of motion (see [`spanning`](#method.spanning)).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "use_serde", serde(default))]
+pub struct Angles {
+    /// The angle of the open position.
+    pub open: u16,
+    /// The angle of the closed position.
+    pub close: u16,
+    /// The angle of the shut position.
+    pub shut: u16,
+}
+
+impl Default for Angles {
+    fn default() -> Self {
+        Self::spanning(180)
+    }
+}
+
+impl Angles {
+    /// Positions spanning the given range of motion: open at one end, closed halfway, and shut at
+    /// the other end.
+    pub fn spanning(range: u16) -> Self {
+        Self {
+            open: 0,
+            close: range / 2,
+            shut: range,
+        }
+    }
+    /// The angle of the given position.
+    pub fn get(self, mark: Mark) -> u16 {
+        match mark {
+            Mark::Open => self.open,
+            Mark::Close => self.close,
+            Mark::Shut => self.shut,
+        }
+    }
+}
+
+/// A message used to calibrate a motor interactively.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "use_serde", serde(rename_all = "lowercase"))]
+pub enum Calibrate {
+    /// Lengthens (if positive) or shortens (if negative) the pulse width by the given number of
+    /// microseconds.
+    Jog(i32),
+    /// Moves the motor to the given position.
+    Move(Mark),
+    /// Marks the current pulse width as the given position.
+    Mark(Mark),
+}
+
+impl ActixMessage for Calibrate {
+    type Result = Result<CalibrationState, Error>;
+}
+
+/// The state of a motor being calibrated.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Serialize))]
+pub struct CalibrationState {
+    /// The current pulse width.
+    pub pulse_width: Duration,
+    /// The current calibration.
+    pub calibration: Calibration,
+}
+
+/// Requests that a motor check (using its position feedback) that it reached its target angle.
+///
+/// The response contains the motor's position if it missed its target, and `None` otherwise (or
+/// if the motor has no position feedback).
+#[derive(Clone, Copy, Debug)]
+pub struct Verify;
+
+impl ActixMessage for Verify {
+    type Result = Result<Option<Position>, Error>;
+}
+
+/// Replaces a motor's calibration (or forgets it, if `None`), as when the calibration file is
+/// reloaded.
+///
+/// The new calibration is used from the motor's next move.
+#[derive(Clone, Copy, Debug)]
+pub struct Recalibrate(pub Option<Calibration>);
+
+impl ActixMessage for Recalibrate {
     type Result = ();
 }
 
+/// The target and measured angles of a motor, in degrees.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Position {
+    /// The angle the motor was told to move to.
+    pub target: f64,
+    /// The angle the motor was measured at.
+    pub actual: f64,
+}
+
+/// Reads a motor's actual position through an ADC (e.g. from a potentiometer coupled to the
+/// valve).
+#[derive(Debug)]
+pub struct Feedback {
+    adc: Box<dyn Adc>,
+    channel: u8,
+    /// The readings at either end of the motor's range of motion.
+    range: [f64; 2],
+    /// The largest tolerated deviation from the target angle, in degrees.
+    tolerance: f64,
+}
+
+impl Feedback {
+    /// Creates position feedback on the given converter channel.
+    ///
+    /// The range gives the readings (as fractions of full scale) at 0º and at the far end of the
+    /// motor's range of motion, respectively.
+    pub fn new(adc: Box<dyn Adc>, channel: u8, range: [f64; 2], tolerance: f64) -> Self {
+        Self {
+            adc,
+            channel,
+            range,
+            tolerance,
+        }
+    }
+    /// Reads the current position, as a fraction of the motor's range of motion.
+    pub fn fraction(&mut self) -> Result<f64, PinError> {
+        let reading = self.adc.read(self.channel)?;
+        let [start, end] = self.range;
+        Ok((reading - start) / (end - start))
+    }
+}
+
 /// A motor connected to the syringe manifold.
 ///
 /// Moving a motor (physically) will cause the control knob to rotate.
@@ -35,17 +181,36 @@ pub struct Motor {
     pin: Pin,
     /// The range of acceptable signal lengths.
     ///
-    /// The motor is assumed to have 180º of motion, meaning the minimum and signals should
-    /// correspond to antiparallel positions.
-    ///
-    /// The closed position is assumed to be 0º; the open position is at 90º.
+    /// The minimum and maximum signals correspond to either end of the motor's range of motion.
     signal_range: RangeInclusive<Duration>,
+    /// The motor's range of motion, in degrees (180º by default).
+    range_of_motion: u16,
     /// The duration for which the signal should be high in each period.
     ///
     /// Changing this property will change the position of the motor.
     pulse_width: Duration,
     /// The handle to the main loop for this motor (for cancellation).
     main_handle: Option<SpawnHandle>,
+    /// The angle the motor was last told to move to.
+    target: Option<u16>,
+    /// The motor's position feedback, if any.
+    feedback: Option<Feedback>,
+    /// The calibrated pulse width of each position, if the motor has been calibrated.
+    calibration: Option<Calibration>,
+    /// The angle of each position, if not spanning the range of motion.
+    angles: Option<Angles>,
+    /// The angles of any additional named positions.
+    positions: BTreeMap<String, u16>,
+    /// How long to take moving between positions, if not instantaneously.
+    ramp: Option<Duration>,
+    /// How long after moving to turn off the motor's signal, if at all.
+    detach_after: Option<Duration>,
+    /// The handle to the pending detachment, if any (for cancellation).
+    detach_handle: Option<SpawnHandle>,
+    /// Whether the motor's signal has been turned off without forgetting its position.
+    detached: bool,
+    /// Where failures outside of any command (e.g. partway through a ramp) are reported.
+    supervisor: Supervisor,
 }
 
 impl PartialEq for Motor {
@@ -57,57 +222,278 @@ impl PartialEq for Motor {
 impl Eq for Motor {}
 
 impl Motor {
-    fn set_pulse_width(&mut self, width: Duration) -> Result<(), PinError> {
+    fn set_pulse_width(&mut self, width: Duration) -> Result<(), Error> {
         log::debug!(
             "Setting pulse width of motor on pin {} to {:?}",
             self.pin.number,
             width
         );
         self.pulse_width = width;
-        self.pin.set_pwm(self.period, width)
+        self.detached = false;
+        Ok(self.pin.set_pwm(self.period, width)?)
+    }
+    /// Turns off the motor's signal, leaving the motor where it is.
+    ///
+    /// Unlike stopping the motor, this remembers the motor's position, so the next move starts
+    /// from there (re-attaching the signal first).
+    pub fn detach(&mut self) -> Result<(), Error> {
+        log::debug!("Detaching motor on pin {}", self.pin.number);
+        self.detached = true;
+        Ok(self.pin.set_pwm(self.period, Duration::new(0, 0))?)
     }
 
     /// Sets the motor's angle in degrees (relative to the closed position).
     ///
-    /// ## Panics
-    /// This method will panic if `angle` is greater than 180.
-    pub fn set_angle(&mut self, angle: u16) -> Result<(), PinError> {
-        assert!(angle <= 180);
+    /// Angles beyond the motor's range of motion are rejected (see
+    /// [`Error::OutOfRange`](enum.DeviceError.html#variant.OutOfRange)).
+    pub fn set_angle(&mut self, angle: u16) -> Result<(), Error> {
+        self.check_angle(angle)?;
+        self.target = Some(angle);
+        let width = self.pulse_width_at(angle);
+        log::trace!(
+            "Setting motor angle to {} (pulse width: {:?})",
+            angle,
+            width
+        );
+        self.set_pulse_width(width)
+    }
+    /// The pulse width corresponding to the given angle.
+    fn pulse_width_at(&self, angle: u16) -> Duration {
         let (start, end) = (self.signal_range.start(), self.signal_range.end());
         // Dereference, since auto-deref doesn't seem to work for std::ops::Sub?
         let (start, end) = (*start, *end);
         let delta = end - start;
-        // Assume a range of motion of 180º.
-        let range = 180;
+        let range = u32::from(self.range_of_motion);
         // Calculate the change in signal per unit angle (dT/dθ).
         let step = delta / range;
         // Multiply the step by the desired angle to get the offset from the baseline (∆T).
         let offset = step * angle.into();
-        log::trace!(
-            "Setting motor angle to {} (pulse width: {:?})",
-            angle,
-            start + offset
-        );
-        self.set_pulse_width(start + offset)
+        start + offset
     }
-    /// Sets the motor to the closed position (angle of 90º).
+    /// Sets the motor to the closed position (halfway through the range of motion by default).
     ///
     /// Fluid will flow through the valve, but not from the associated buffer.
-    pub fn close(&mut self) -> Result<(), PinError> {
+    pub fn close(&mut self) -> Result<(), Error> {
         log::trace!("Closing motor on pin {}.", self.pin.number);
-        self.set_angle(90)
+        self.move_to(Mark::Close)
     }
-    /// Sets the motor to the shut position, where no fluid will flow through it.
-    pub fn shut(&mut self) -> Result<(), PinError> {
+    /// Sets the motor to the shut position (at the end of the range of motion by default), where no
+    /// fluid will flow through it.
+    pub fn shut(&mut self) -> Result<(), Error> {
         log::trace!("Shutting motor on pin {}.", self.pin.number);
-        self.set_angle(180)
+        self.move_to(Mark::Shut)
     }
-    /// Sets the motor to the open position (angle of 0º).
+    /// Sets the motor to the open position (angle of 0º by default).
     ///
     /// Fluid from the associated buffer will flow through the valve.
-    pub fn open(&mut self) -> Result<(), PinError> {
+    pub fn open(&mut self) -> Result<(), Error> {
         log::trace!("Opening motor on pin {}.", self.pin.number);
-        self.set_angle(0)
+        self.move_to(Mark::Open)
+    }
+    /// Moves the motor to the given position, using the calibrated pulse width if available.
+    fn move_to(&mut self, mark: Mark) -> Result<(), Error> {
+        self.target = Some(self.angles().get(mark));
+        let width = self.calibration().get(mark);
+        self.set_pulse_width(width)
+    }
+    /// Moves the motor to the given position, ramping its movement if configured.
+    fn ramp_to_mark(&mut self, mark: Mark, context: &mut Context<Self>) -> Result<(), Error> {
+        let (angle, width) = (self.angles().get(mark), self.calibration().get(mark));
+        self.ramp_to(angle, width, context)
+    }
+    /// Moves the motor to the given angle (using the given pulse width), ramping the pulse width
+    /// over the configured ramp duration (once per period) if there is one.
+    ///
+    /// If the motor's signal is off, its position is unknown, so it is moved directly. If the
+    /// motor was detached, its signal is re-attached first (and detached again afterwards).
+    ///
+    /// A failure partway through the ramp abandons it and is reported to the supervisor.
+    fn ramp_to(
+        &mut self,
+        angle: u16,
+        to: Duration,
+        context: &mut Context<Self>,
+    ) -> Result<(), Error> {
+        if let Some(handle) = self.main_handle.take() {
+            context.cancel_future(handle);
+        }
+        if self.detached {
+            self.set_pulse_width(self.pulse_width)?;
+        }
+        self.schedule_detach(context);
+        self.target = Some(angle);
+        let from = self.pulse_width;
+        let ramp = match self.ramp {
+            Some(ramp) if ramp > Duration::new(0, 0) && from > Duration::new(0, 0) => ramp,
+            _ => return self.set_pulse_width(to),
+        };
+        let steps = (micros(ramp) / micros(self.period).max(1)).max(1);
+        log::trace!(
+            "Ramping motor on pin {} from {:?} to {:?} over {:?}",
+            self.pin.number,
+            from,
+            to,
+            ramp
+        );
+        let (from, to) = (micros(from) as i64, micros(to) as i64);
+        let mut step = 0;
+        let handle = context.run_interval(self.period, move |motor, context| {
+            step += 1;
+            let width = from + (to - from) * step as i64 / steps as i64;
+            let result = motor.set_pulse_width(Duration::from_micros(width as u64));
+            if step >= steps || result.is_err() {
+                if let Some(handle) = motor.main_handle.take() {
+                    context.cancel_future(handle);
+                }
+            }
+            if let Err(err) = result {
+                motor.supervisor.report(err);
+            }
+        });
+        self.main_handle = Some(handle);
+        Ok(())
+    }
+    /// Turns off the motor's signal once it has had time to reach its position, if configured.
+    fn schedule_detach(&mut self, context: &mut Context<Self>) {
+        if let Some(handle) = self.detach_handle.take() {
+            context.cancel_future(handle);
+        }
+        let delay = match self.detach_after {
+            Some(delay) => delay + self.ramp.unwrap_or_default(),
+            None => return,
+        };
+        let handle = context.run_later(delay, |motor, _context| {
+            motor.detach_handle = None;
+            if let Err(err) = motor.detach() {
+                motor.supervisor.report(err);
+            }
+        });
+        self.detach_handle = Some(handle);
+    }
+    /// Turns off the motor's signal the given time after each move (when driven as an actor).
+    ///
+    /// Holding a constant signal can make some servos hum and heat up; the motor is re-attached
+    /// automatically before its next move.
+    pub fn with_detach(mut self, delay: Duration) -> Self {
+        self.detach_after = Some(delay);
+        self
+    }
+    /// Moves the motor between positions gradually over the given duration (when driven as an
+    /// actor), reducing mechanical shock.
+    pub fn with_ramp(mut self, ramp: Duration) -> Self {
+        self.ramp = Some(ramp);
+        self
+    }
+    /// The calibration of the motor.
+    ///
+    /// If the motor has not been calibrated, the pulse widths are derived from the signal range
+    /// and the angle of each position.
+    pub fn calibration(&self) -> Calibration {
+        self.calibration.unwrap_or_else(|| {
+            let angles = self.angles();
+            Calibration {
+                open: self.pulse_width_at(angles.open),
+                close: self.pulse_width_at(angles.close),
+                shut: self.pulse_width_at(angles.shut),
+            }
+        })
+    }
+    /// The angle of each of the motor's positions.
+    pub fn angles(&self) -> Angles {
+        self.angles
+            .unwrap_or_else(|| Angles::spanning(self.range_of_motion))
+    }
+    /// Sets the motor's range of motion in degrees, which its signal range spans.
+    ///
+    /// This fails if the range is zero or if any configured angle is beyond it.
+    pub fn with_range_of_motion(mut self, range: u16) -> Result<Self, Error> {
+        if range == 0 {
+            return Err(Error::NoRangeOfMotion);
+        }
+        self.range_of_motion = range;
+        self.check_angles()?;
+        Ok(self)
+    }
+    /// Uses the given angles for the motor's positions.
+    ///
+    /// This fails if any of the angles is beyond the motor's range of motion.
+    pub fn with_angles(mut self, angles: Angles) -> Result<Self, Error> {
+        self.angles = Some(angles);
+        self.check_angles()?;
+        Ok(self)
+    }
+    /// Ensures that the given angle is within the motor's range of motion.
+    fn check_angle(&self, angle: u16) -> Result<(), Error> {
+        if angle > self.range_of_motion {
+            Err(Error::OutOfRange {
+                pin: self.pin.number,
+                angle,
+                range: self.range_of_motion,
+            })
+        } else {
+            Ok(())
+        }
+    }
+    /// Ensures that all of the motor's positions are within its range of motion.
+    fn check_angles(&self) -> Result<(), Error> {
+        let angles = self.angles();
+        [angles.open, angles.close, angles.shut]
+            .iter()
+            .chain(self.positions.values())
+            .try_for_each(|&angle| self.check_angle(angle))
+    }
+    /// Adds the given named positions (in addition to open, closed, and shut).
+    ///
+    /// This fails if any of the angles is beyond the motor's range of motion.
+    pub fn with_positions(mut self, positions: BTreeMap<String, u16>) -> Result<Self, Error> {
+        self.positions = positions;
+        self.check_angles()?;
+        Ok(self)
+    }
+    /// Reports failures outside of any command (e.g. partway through a ramp) to the given
+    /// recipient, as the given device.
+    pub fn supervised(mut self, device: Device, recipient: Recipient<Failed>) -> Self {
+        self.supervisor = Supervisor::new(device, recipient);
+        self
+    }
+    /// The angle of the named position, if the motor has such a position.
+    pub fn position(&self, name: &str) -> Option<u16> {
+        self.positions.get(name).cloned()
+    }
+    /// Uses the given calibrated pulse widths for the motor's positions.
+    pub fn with_calibration(mut self, calibration: Calibration) -> Self {
+        self.calibration = Some(calibration);
+        self
+    }
+    /// Handles a calibration request, returning the resulting state.
+    pub fn calibrate(&mut self, request: Calibrate) -> Result<CalibrationState, Error> {
+        match request {
+            Calibrate::Jog(micros) => {
+                let delta = Duration::from_micros(i64::from(micros).abs() as u64);
+                let width = if micros < 0 {
+                    self.pulse_width.checked_sub(delta).unwrap_or_default()
+                } else {
+                    self.pulse_width + delta
+                };
+                self.set_pulse_width(width)?;
+            }
+            Calibrate::Move(mark) => self.move_to(mark)?,
+            Calibrate::Mark(mark) => {
+                log::debug!(
+                    "Marking {:?} position of motor on pin {} at {:?}",
+                    mark,
+                    self.pin.number,
+                    self.pulse_width
+                );
+                let mut calibration = self.calibration();
+                calibration.set(mark, self.pulse_width);
+                self.calibration = Some(calibration);
+            }
+        }
+        Ok(CalibrationState {
+            pulse_width: self.pulse_width,
+            calibration: self.calibration(),
+        })
     }
     ///
     /// Constructs a new motor with the given period and signal range on the given pin number, if
@@ -119,14 +505,60 @@ impl Motor {
         R: Into<RangeInclusive<Duration>>,
     {
         let pin = Pin::try_new(pin)?;
+        Ok(Self::with_pin(period, range, pin))
+    }
+    /// Constructs a new motor with the given period and signal range on an already-acquired pin.
+    ///
+    /// The motor will be set to the closed position initially.
+    pub fn with_pin<R>(period: Duration, range: R, pin: Pin) -> Self
+    where
+        R: Into<RangeInclusive<Duration>>,
+    {
         let signal_range = range.into();
-        Ok(Self {
+        Self {
             period,
             pin,
             pulse_width: *signal_range.start(),
             signal_range,
             main_handle: None,
-        })
+            target: None,
+            feedback: None,
+            calibration: None,
+            range_of_motion: 180,
+            angles: None,
+            positions: BTreeMap::new(),
+            ramp: None,
+            detach_after: None,
+            detach_handle: None,
+            detached: false,
+            supervisor: Supervisor::default(),
+        }
+    }
+    /// Adds position feedback to the motor, allowing its position to be verified.
+    pub fn with_feedback(mut self, feedback: Feedback) -> Self {
+        self.feedback = Some(feedback);
+        self
+    }
+    /// Checks that the motor reached its target angle, returning its position if it didn't.
+    ///
+    /// If the motor has no position feedback (or has not been moved), this always succeeds.
+    pub fn verify(&mut self) -> Result<Option<Position>, Error> {
+        let (feedback, target) = match (&mut self.feedback, self.target) {
+            (Some(feedback), Some(target)) => (feedback, f64::from(target)),
+            _ => return Ok(None),
+        };
+        let actual = feedback.fraction()? * f64::from(self.range_of_motion);
+        log::trace!(
+            "Motor on pin {} is at {:.1}º (target: {}º)",
+            self.pin.number,
+            actual,
+            target
+        );
+        if (actual - target).abs() > feedback.tolerance {
+            Ok(Some(Position { target, actual }))
+        } else {
+            Ok(None)
+        }
     }
     /// Constructs a new motor with the given period and signal range on the given pin number.
     ///
@@ -143,29 +575,82 @@ impl Motor {
     }
 }
 
+/// Converts a duration to whole microseconds.
+fn micros(duration: Duration) -> u64 {
+    duration.as_secs() * 1_000_000 + u64::from(duration.subsec_micros())
+}
+
 impl Actor for Motor {
     type Context = Context<Self>;
 }
 
 impl Handle<Message> for Motor {
-    type Result = ();
-    fn handle(&mut self, message: Message, _context: &mut Self::Context) -> Self::Result {
+    type Result = Result<(), Error>;
+    fn handle(&mut self, message: Message, context: &mut Self::Context) -> Self::Result {
         match message {
-            Message::Open => self.open().unwrap(),
-            Message::Close => self.close().unwrap(),
-            Message::Shut => self.shut().unwrap(),
+            Message::Open => self.ramp_to_mark(Mark::Open, context),
+            Message::Close => self.ramp_to_mark(Mark::Close, context),
+            Message::Shut => self.ramp_to_mark(Mark::Shut, context),
+            Message::SetPosition(name) => match self.position(&name) {
+                Some(angle) => {
+                    log::trace!("Moving motor on pin {} to {}.", self.pin.number, name);
+                    let width = self.pulse_width_at(angle);
+                    self.ramp_to(angle, width, context)
+                }
+                None => Err(Error::NoSuchPosition {
+                    pin: self.pin.number,
+                    name,
+                }),
+            },
+            Message::SetAngle(angle) => {
+                self.check_angle(angle)?;
+                log::trace!("Moving motor on pin {} to {}°.", self.pin.number, angle);
+                let width = self.pulse_width_at(angle);
+                self.ramp_to(angle, width, context)
+            }
             Message::Stop => {
                 log::trace!("Stopping motor motion.");
-                self.set_pulse_width(Duration::new(0, 0)).unwrap()
+                if let Some(handle) = self.main_handle.take() {
+                    context.cancel_future(handle);
+                }
+                if let Some(handle) = self.detach_handle.take() {
+                    context.cancel_future(handle);
+                }
+                self.set_pulse_width(Duration::new(0, 0))
             }
         }
     }
 }
 
+impl Handle<Calibrate> for Motor {
+    type Result = Result<CalibrationState, Error>;
+    fn handle(&mut self, request: Calibrate, context: &mut Self::Context) -> Self::Result {
+        // Calibration moves are made directly, so any ramp in progress must not interfere.
+        if let Some(handle) = self.main_handle.take() {
+            context.cancel_future(handle);
+        }
+        self.calibrate(request)
+    }
+}
+
+impl Handle<Verify> for Motor {
+    type Result = Result<Option<Position>, Error>;
+    fn handle(&mut self, _: Verify, _context: &mut Self::Context) -> Self::Result {
+        self.verify()
+    }
+}
+
+impl Handle<Recalibrate> for Motor {
+    type Result = ();
+    fn handle(&mut self, Recalibrate(calibration): Recalibrate, _context: &mut Self::Context) {
+        self.calibration = calibration;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    // This test makes sure the panic in validate_motor_angle isn't from constructing the motor and unwrapping it.
+    // This test makes sure the error in validate_motor_angle isn't from constructing the motor.
     #[test]
     fn make_fake_motor() {
         let _motor = Motor::try_new(
@@ -176,7 +661,6 @@ mod tests {
         .unwrap();
     }
     #[test]
-    #[should_panic]
     fn validate_motor_angle() {
         let mut motor = Motor::try_new(
             Duration::new(2, 0),
@@ -184,6 +668,87 @@ mod tests {
             1,
         )
         .unwrap();
-        let _ = motor.set_angle(181);
+        assert!(matches!(
+            motor.set_angle(181),
+            Err(Error::OutOfRange {
+                angle: 181,
+                range: 180,
+                ..
+            })
+        ));
+        assert!(matches!(
+            motor.with_range_of_motion(0),
+            Err(Error::NoRangeOfMotion)
+        ));
+    }
+    #[test]
+    fn reports_pin_failures() {
+        use crate::pin::Mock;
+        let mock = Mock::new();
+        let pin = Pin::with_backend(&mock, 1).unwrap();
+        let mut motor = Motor::with_pin(
+            Duration::from_millis(20),
+            Duration::from_micros(600)..=Duration::from_micros(2400),
+            pin,
+        );
+        mock.fail(1, true);
+        assert!(matches!(motor.open(), Err(Error::Pin(_))));
+        mock.fail(1, false);
+        motor.open().unwrap();
+    }
+    #[test]
+    fn verify_position() {
+        use crate::{pin::Mock, sensor::adc::Mock as MockAdc};
+        let adc = MockAdc::new();
+        let pin = Pin::with_backend(&Mock::new(), 1).unwrap();
+        let feedback = Feedback::new(Box::new(adc.clone()), 0, [0.1, 0.9], 5.0);
+        let mut motor = Motor::with_pin(
+            Duration::from_millis(20),
+            Duration::from_micros(600)..=Duration::from_micros(2400),
+            pin,
+        )
+        .with_feedback(feedback);
+        assert_eq!(motor.verify().unwrap(), None);
+        motor.close().unwrap();
+        adc.set(0, 0.5);
+        assert_eq!(motor.verify().unwrap(), None);
+        motor.shut().unwrap();
+        let position = motor.verify().unwrap().unwrap();
+        assert_eq!(position.target, 180.0);
+    }
+    #[test]
+    fn configured_angles() {
+        let pin = Pin::with_backend(&crate::pin::Mock::new(), 1).unwrap();
+        let motor = Motor::with_pin(
+            Duration::from_millis(20),
+            Duration::from_micros(600)..=Duration::from_micros(2400),
+            pin,
+        )
+        .with_angles(Angles {
+            open: 180,
+            close: 90,
+            shut: 0,
+        })
+        .unwrap();
+        let calibration = motor.calibration();
+        assert_eq!(calibration.open, Duration::from_micros(2400));
+        assert_eq!(calibration.close, Duration::from_micros(1500));
+        assert_eq!(calibration.shut, Duration::from_micros(600));
+    }
+    #[test]
+    fn range_of_motion() {
+        let pin = Pin::with_backend(&crate::pin::Mock::new(), 1).unwrap();
+        let mut motor = Motor::with_pin(
+            Duration::from_millis(20),
+            Duration::from_micros(600)..=Duration::from_micros(2400),
+            pin,
+        )
+        .with_range_of_motion(90)
+        .unwrap();
+        let calibration = motor.calibration();
+        assert_eq!(calibration.close, Duration::from_micros(1500));
+        assert_eq!(calibration.shut, Duration::from_micros(2400));
+        motor.set_angle(90).unwrap();
+        assert_eq!(motor.pulse_width, Duration::from_micros(2400));
     }
 }
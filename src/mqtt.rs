@@ -0,0 +1,431 @@
+//! Publishing status updates to (and taking commands from) an MQTT broker.
+//!
+//! Status updates are published to the status topic as the same JSON objects written to the
+//! [event log](../event_log/index.html), and sensor readings to a subtopic of the readings topic
+//! for each sensor. Commands published to the command topic are carried out as if an operator
+//! named `mqtt` had sent them.
+//!
+//! ## Home Assistant
+//! If configured, the coordinator announces itself to Home Assistant through MQTT discovery, as
+//! sensors (its state, the step being run, and the minutes remaining) and a switch for each of the
+//! configured stored protocols (which starts the protocol when turned on, and aborts it when turned
+//! off). The sensors and switches read a summary published (and retained) on the `summary`
+//! subtopic of the status topic; the switches are commanded on the `switch/<label>` subtopics of
+//! the command topic.
+
+use std::{
+    fmt,
+    sync::{Arc, Mutex, MutexGuard},
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use futures::Future;
+use rumqtt::{ConnectError, MqttClient, MqttOptions, Notification, QoS};
+use uuid::Uuid;
+
+use crate::{
+    actix::Addr,
+    comm::{Message, Subscribers},
+    event_log::Event,
+    Coordinator, ExecState, HomeAssistantConfig, Library, MqttConfig, Protocol, Sensor, Status,
+    StatusMessage, Update,
+};
+
+/// The payload turning a Home Assistant switch on.
+const ON: &[u8] = b"ON";
+/// The payload turning a Home Assistant switch off.
+const OFF: &[u8] = b"OFF";
+
+/// A command taken from the command topic.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Command {
+    /// Starts the given protocol.
+    Start(Protocol),
+    /// Pauses the running program.
+    Pause,
+    /// Resumes the paused program.
+    Resume,
+    /// Continues a program waiting for confirmation.
+    Continue,
+    /// Stops the program once the current step is done.
+    Stop,
+    /// Aborts the program, running the teardown sequence.
+    Abort,
+    /// Halts everything immediately.
+    Halt,
+}
+
+impl From<Command> for Message {
+    fn from(command: Command) -> Self {
+        match command {
+            Command::Start(protocol) => Message::Start(protocol, None),
+            Command::Pause => Message::Pause,
+            Command::Resume => Message::Resume,
+            Command::Continue => Message::Continue,
+            Command::Stop => Message::Stop,
+            Command::Abort => Message::Abort,
+            Command::Halt => Message::Halt,
+        }
+    }
+}
+
+/// A sensor reading, as published.
+#[derive(Clone, Copy, Debug, Serialize)]
+struct Reading {
+    /// When the reading was taken, in milliseconds since the Unix epoch.
+    timestamp: u64,
+    /// The value read.
+    value: f64,
+    /// The unit of the value.
+    unit: &'static str,
+}
+
+/// A summary of the coordinator's status, for Home Assistant.
+#[derive(Clone, Copy, Debug, Serialize)]
+struct Summary {
+    /// The state of the coordinator (e.g. `running`).
+    state: &'static str,
+    /// The step being run, counting from 1.
+    step: Option<usize>,
+    /// Roughly how many minutes until the program finishes.
+    remaining: Option<u64>,
+    /// The label of the stored protocol started with a switch, while it runs.
+    protocol: Option<Uuid>,
+}
+
+/// The name of the given state, as summarized.
+fn state_name(state: ExecState) -> &'static str {
+    match state {
+        ExecState::Waiting => "waiting",
+        ExecState::Stopped { .. } => "stopped",
+        ExecState::Running => "running",
+        ExecState::Paused => "paused",
+        ExecState::Priming => "priming",
+        ExecState::Washing => "washing",
+        ExecState::Calibrating { .. } => "calibrating",
+        ExecState::Manual => "manual",
+        ExecState::Faulted => "faulted",
+    }
+}
+
+/// The device the Home Assistant entities belong to.
+#[derive(Clone, Debug, Serialize)]
+struct Device {
+    identifiers: Vec<String>,
+    name: String,
+    manufacturer: &'static str,
+}
+
+/// An entity announced to Home Assistant.
+#[derive(Clone, Debug, Serialize)]
+struct Discovery {
+    name: String,
+    unique_id: String,
+    state_topic: String,
+    value_template: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    command_topic: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unit_of_measurement: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    icon: Option<&'static str>,
+    device: Device,
+}
+
+/// The stored protocols offered as Home Assistant switches.
+#[derive(Debug)]
+struct Switches {
+    library: Option<Library>,
+    /// The stored protocol started with a switch, while it runs.
+    running: Mutex<Option<Uuid>>,
+}
+
+impl Switches {
+    fn running(&self) -> MutexGuard<Option<Uuid>> {
+        // A panic while holding the lock can't leave the label in a bad state.
+        self.running
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+    /// Turns the switch for the given stored protocol on or off.
+    fn switch(&self, id: Uuid, on: bool, coord: &Addr<Coordinator>) {
+        if !on {
+            if *self.running() == Some(id) {
+                Mqtt::command(Message::Abort, coord);
+            }
+            return;
+        }
+        let stored = match self.library.as_ref().map(|library| library.get(id)) {
+            Some(Ok(Some(stored))) => stored,
+            Some(Ok(None)) => return log::warn!("No stored protocol {} to start.", id),
+            Some(Err(err)) => return log::error!("Failed to read stored protocol {}: {}", id, err),
+            None => return log::warn!("No protocol library is kept, so {} can't start.", id),
+        };
+        if Mqtt::command(Message::Start(stored.protocol, None), coord) {
+            *self.running() = Some(id);
+        }
+    }
+}
+
+/// A connection to an MQTT broker, which publishes the coordinator's updates.
+pub struct Mqtt {
+    client: MqttClient,
+    config: MqttConfig,
+    switches: Arc<Switches>,
+}
+
+impl fmt::Debug for Mqtt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Mqtt")
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+impl Mqtt {
+    /// Connects to the configured broker, carrying out commands using the given coordinator (and
+    /// starting stored protocols from the given library).
+    pub fn connect(
+        config: MqttConfig,
+        coord: Addr<Coordinator>,
+        library: Option<Library>,
+    ) -> Result<Self, ConnectError> {
+        let options = MqttOptions::new(config.client_id.clone(), config.host.clone(), config.port);
+        let (mut client, notifications) = MqttClient::start(options)?;
+        let switch_topic = format!("{}/switch/", config.command_topic);
+        let mut topics = vec![config.command_topic.clone()];
+        if config.home_assistant.is_some() {
+            topics.push(format!("{}+", switch_topic));
+        }
+        for topic in topics {
+            if let Err(err) = client.subscribe(topic, QoS::AtLeastOnce) {
+                log::error!("Failed to subscribe to MQTT commands: {:?}", err);
+            }
+        }
+        let switches = Arc::new(Switches {
+            library,
+            running: Mutex::new(None),
+        });
+        let switching = Arc::clone(&switches);
+        let command_topic = config.command_topic.clone();
+        thread::spawn(move || {
+            for notification in notifications {
+                if let Notification::Publish(publish) = notification {
+                    if publish.topic_name == command_topic {
+                        match serde_json::from_slice::<Command>(&publish.payload) {
+                            Ok(command) => {
+                                Self::command(command.into(), &coord);
+                            }
+                            Err(err) => log::warn!("Ignoring malformed MQTT command: {}", err),
+                        }
+                    } else if publish.topic_name.starts_with(&switch_topic) {
+                        let id = &publish.topic_name[switch_topic.len()..];
+                        match (Uuid::parse_str(id), publish.payload.as_slice()) {
+                            (Ok(id), ON) => switching.switch(id, true, &coord),
+                            (Ok(id), OFF) => switching.switch(id, false, &coord),
+                            _ => log::warn!("Ignoring malformed switch command for {}", id),
+                        }
+                    }
+                }
+            }
+        });
+        let mqtt = Self {
+            client,
+            config,
+            switches,
+        };
+        if let Some(home_assistant) = &mqtt.config.home_assistant {
+            mqtt.discover(home_assistant);
+        }
+        Ok(mqtt)
+    }
+    /// Carries out the given command, logging any failure, and returns whether it succeeded.
+    fn command(message: Message, coord: &Addr<Coordinator>) -> bool {
+        let message = Message::Acting {
+            user: "mqtt".into(),
+            message: Box::new(message),
+        };
+        match coord.send(message).wait() {
+            Ok(Ok(())) => true,
+            Ok(Err(err)) => {
+                log::warn!("MQTT command failed: {}", err);
+                false
+            }
+            Err(err) => {
+                log::error!("Failed to reach coordinator: {}", err);
+                false
+            }
+        }
+    }
+    /// The topic the summary is published to.
+    fn summary_topic(&self) -> String {
+        format!("{}/summary", self.config.status_topic)
+    }
+    /// Announces the sensors and switches to Home Assistant.
+    fn discover(&self, config: &HomeAssistantConfig) {
+        let node = &self.config.client_id;
+        let device = Device {
+            identifiers: vec![node.clone()],
+            name: node.clone(),
+            manufacturer: "deoxy",
+        };
+        let entity = |object: &str, name: String, template: String| Discovery {
+            name,
+            unique_id: format!("{}-{}", node, object),
+            state_topic: self.summary_topic(),
+            value_template: template,
+            command_topic: None,
+            unit_of_measurement: None,
+            icon: None,
+            device: device.clone(),
+        };
+        let mut entities = vec![
+            (
+                "sensor",
+                "state".to_string(),
+                Discovery {
+                    icon: Some("mdi:water-pump"),
+                    ..entity(
+                        "state",
+                        format!("{} state", node),
+                        "{{ value_json.state }}".into(),
+                    )
+                },
+            ),
+            (
+                "sensor",
+                "step".to_string(),
+                entity(
+                    "step",
+                    format!("{} step", node),
+                    "{{ value_json.step }}".into(),
+                ),
+            ),
+            (
+                "sensor",
+                "remaining".to_string(),
+                Discovery {
+                    unit_of_measurement: Some("min"),
+                    icon: Some("mdi:timer-sand"),
+                    ..entity(
+                        "remaining",
+                        format!("{} remaining", node),
+                        "{{ value_json.remaining }}".into(),
+                    )
+                },
+            ),
+        ];
+        let stored = match &self.switches.library {
+            Some(library) => library.list().unwrap_or_else(|err| {
+                log::error!("Failed to list stored protocols: {}", err);
+                vec![]
+            }),
+            None if config.protocols.is_empty() => vec![],
+            None => {
+                log::warn!("No protocol library is kept, so no protocols can be switched on.");
+                vec![]
+            }
+        };
+        for name in &config.protocols {
+            let protocol = match stored.iter().find(|protocol| protocol.name == *name) {
+                Some(protocol) => protocol,
+                None => {
+                    log::warn!("No stored protocol named {:?} to offer as a switch.", name);
+                    continue;
+                }
+            };
+            let id = protocol.id.to_string();
+            let template = format!(
+                "{{% if value_json.protocol == '{}' %}}ON{{% else %}}OFF{{% endif %}}",
+                id
+            );
+            let switch = Discovery {
+                command_topic: Some(format!("{}/switch/{}", self.config.command_topic, id)),
+                icon: Some("mdi:play-circle"),
+                ..entity(&id, name.clone(), template)
+            };
+            entities.push(("switch", id, switch));
+        }
+        for (component, object, entity) in entities {
+            let topic = format!("{}/{}/{}/{}/config", config.prefix, component, node, object);
+            match serde_json::to_vec(&entity) {
+                Ok(payload) => self.publish_retained(topic, payload),
+                Err(err) => log::error!("Failed to serialize discovery message: {}", err),
+            }
+        }
+    }
+    /// Publishes the given payload to the given topic, to be kept for later subscribers.
+    fn publish_retained(&self, topic: String, payload: Vec<u8>) {
+        if let Err(err) = self
+            .client
+            .clone()
+            .publish(topic, QoS::AtLeastOnce, true, payload)
+        {
+            log::error!("Failed to publish to MQTT: {:?}", err);
+        }
+    }
+    /// Publishes a summary of the given status for Home Assistant.
+    fn summarize(&self, status: &Status) {
+        let mut running = self.switches.running();
+        match status.state {
+            ExecState::Stopped { .. } | ExecState::Faulted => *running = None,
+            _ => {}
+        }
+        let summary = Summary {
+            state: state_name(status.state),
+            step: status.step.map(|step| step + 1),
+            remaining: status
+                .remaining
+                .map(|remaining| (remaining.as_secs() + 59) / 60),
+            protocol: *running,
+        };
+        match serde_json::to_vec(&summary) {
+            Ok(payload) => self.publish_retained(self.summary_topic(), payload),
+            Err(err) => log::error!("Failed to serialize summary: {}", err),
+        }
+    }
+    /// Publishes the given payload to the given topic.
+    fn publish(&self, topic: String, payload: Vec<u8>) {
+        // The client is just a handle to the connection, so cloning it is cheap.
+        if let Err(err) = self
+            .client
+            .clone()
+            .publish(topic, QoS::AtMostOnce, false, payload)
+        {
+            log::error!("Failed to publish to MQTT: {:?}", err);
+        }
+    }
+    /// Publishes the given sensor reading.
+    fn reading(&self, sensor: Sensor, value: f64) {
+        let since = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let reading = Reading {
+            timestamp: since.as_secs() * 1000 + u64::from(since.subsec_millis()),
+            value,
+            unit: sensor.unit(),
+        };
+        let topic = format!("{}/{}", self.config.readings_topic, sensor);
+        match serde_json::to_vec(&reading) {
+            Ok(payload) => self.publish(topic, payload),
+            Err(err) => log::error!("Failed to serialize reading: {}", err),
+        }
+    }
+}
+
+impl Update for Mqtt {
+    fn handle(&self, status: &Status, _coord: &Subscribers) {
+        if let StatusMessage::Reading { sensor, value } = status.message {
+            return self.reading(sensor, value);
+        }
+        match serde_json::to_vec(&Event::new(status)) {
+            Ok(payload) => self.publish(self.config.status_topic.clone(), payload),
+            Err(err) => log::error!("Failed to serialize status update: {}", err),
+        }
+        if self.config.home_assistant.is_some() {
+            self.summarize(status);
+        }
+    }
+}
@@ -0,0 +1,300 @@
+//! Posting notifications to Slack and Discord channels through their incoming webhooks.
+//!
+//! Each notification becomes a single message, color-coded by what happened (blue for a run
+//! starting, green for one completing, amber for aborted runs and low buffers, and red for faults),
+//! with the run's current step, timing, and any warnings alongside it.
+
+use std::time::Duration;
+
+use actix_web::client;
+use futures::Future;
+
+use crate::{
+    actix::Arbiter,
+    comm::format_duration,
+    config::{ChatConfig, ChatService, NotificationEvent},
+    mail::template::{describe, table},
+};
+
+use super::{Error, Notification, Notifier, Status};
+
+/// How long to wait for a response.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The color of messages about runs starting.
+const BLUE: u32 = 0x21_96_f3;
+/// The color of messages about runs completing.
+const GREEN: u32 = 0x2e_b6_7d;
+/// The color of messages about aborted runs and other things needing attention.
+const AMBER: u32 = 0xec_b2_2e;
+/// The color of messages about faults.
+const RED: u32 = 0xe0_1e_5a;
+/// The color of other messages.
+const GRAY: u32 = 0x86_86_86;
+
+/// A notification, laid out for a chat message.
+#[derive(Clone, Debug, PartialEq)]
+struct Message {
+    title: String,
+    text: String,
+    color: u32,
+    /// Short labelled values shown alongside the text.
+    fields: Vec<(&'static str, String)>,
+}
+
+impl Message {
+    fn new(notification: &Notification) -> Self {
+        let summary = notification.summary;
+        let (title, mut text) = notification.describe();
+        let color = match (notification.status, notification.event) {
+            (Status::Started, _) => BLUE,
+            (Status::Finished, _) => GREEN,
+            (Status::Aborted, _) | (_, NotificationEvent::LowBuffer) => AMBER,
+            (_, NotificationEvent::Fault) => RED,
+            _ => GRAY,
+        };
+        let finished = match notification.status {
+            Status::Finished | Status::Aborted | Status::Faulted => true,
+            _ => false,
+        };
+        if finished && !summary.steps.is_empty() {
+            text.push_str(&format!(
+                "\n```\n{}\n```",
+                table(&summary.steps, &summary.buffers)
+            ));
+        }
+        let mut fields = vec![];
+        if let Some((index, action)) = &summary.step {
+            fields.push((
+                "Step",
+                format!("{}: {}", index + 1, describe(action, &summary.buffers)),
+            ));
+        }
+        if let Some(total) = summary.total {
+            fields.push(("Elapsed", format_duration(total)));
+        }
+        if let Some(remaining) = summary.remaining {
+            fields.push(("Remaining", format_duration(remaining)));
+        }
+        if let Some(job) = summary.job {
+            fields.push(("Job", job.to_string()));
+        }
+        if !summary.warnings.is_empty() {
+            let warnings = summary
+                .warnings
+                .iter()
+                .map(|warning| format!("- {}", warning))
+                .collect::<Vec<_>>();
+            fields.push(("Warnings", warnings.join("\n")));
+        }
+        Self {
+            title,
+            text,
+            color,
+            fields,
+        }
+    }
+    /// The body of a Slack incoming webhook request carrying this message.
+    fn slack(&self) -> SlackPayload {
+        SlackPayload {
+            attachments: vec![SlackAttachment {
+                fallback: &self.title,
+                color: format!("#{:06x}", self.color),
+                title: &self.title,
+                text: &self.text,
+                fields: self
+                    .fields
+                    .iter()
+                    .map(|(title, value)| SlackField {
+                        title,
+                        value: value.as_str(),
+                        short: !value.contains('\n'),
+                    })
+                    .collect(),
+            }],
+        }
+    }
+    /// The body of a Discord webhook request carrying this message.
+    fn discord(&self) -> DiscordPayload {
+        DiscordPayload {
+            embeds: vec![Embed {
+                title: &self.title,
+                description: &self.text,
+                color: self.color,
+                fields: self
+                    .fields
+                    .iter()
+                    .map(|(name, value)| EmbedField {
+                        name,
+                        value: value.as_str(),
+                        inline: !value.contains('\n'),
+                    })
+                    .collect(),
+            }],
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct SlackPayload<'a> {
+    attachments: Vec<SlackAttachment<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct SlackAttachment<'a> {
+    fallback: &'a str,
+    color: String,
+    title: &'a str,
+    text: &'a str,
+    fields: Vec<SlackField<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct SlackField<'a> {
+    title: &'a str,
+    value: &'a str,
+    short: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct DiscordPayload<'a> {
+    embeds: Vec<Embed<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct Embed<'a> {
+    title: &'a str,
+    description: &'a str,
+    color: u32,
+    fields: Vec<EmbedField<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct EmbedField<'a> {
+    name: &'a str,
+    value: &'a str,
+    inline: bool,
+}
+
+/// Posts notifications to a Slack or Discord channel.
+///
+/// Messages are posted in the background, so delivery failures are logged rather than returned.
+#[derive(Clone, Debug)]
+pub struct Chat {
+    config: ChatConfig,
+}
+
+impl Chat {
+    /// Creates a notifier posting to the given channel.
+    pub fn new(config: ChatConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl Notifier for Chat {
+    fn name(&self) -> &str {
+        match self.config.service() {
+            ChatService::Slack => "Slack",
+            ChatService::Discord => "Discord",
+        }
+    }
+    fn notify(&self, notification: &Notification) -> Result<(), Error> {
+        if !self.config.events.contains(&notification.event) {
+            return Ok(());
+        }
+        let message = Message::new(notification);
+        let body = match self.config.service() {
+            ChatService::Slack => serde_json::to_vec(&message.slack()),
+            ChatService::Discord => serde_json::to_vec(&message.discord()),
+        }
+        .map_err(|err| Error::Chat(err.to_string()))?;
+        let mut builder = client::post(&self.config.url);
+        builder.content_type("application/json");
+        let request = builder
+            .body(body)
+            .map_err(|err| Error::Chat(err.to_string()))?;
+        // The URL is a secret, so failures are attributed to the service instead.
+        let service = self.name().to_string();
+        Arbiter::spawn(
+            request
+                .send()
+                .timeout(TIMEOUT)
+                .then(move |result| -> Result<(), ()> {
+                    match result {
+                        Ok(ref response) if response.status().is_success() => {}
+                        Ok(response) => log::error!(
+                            "{} refused a notification (status {}).",
+                            service,
+                            response.status()
+                        ),
+                        Err(err) => {
+                            log::error!("Failed to post a notification to {}: {}", service, err)
+                        }
+                    }
+                    Ok(())
+                }),
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{mail::Summary, Action};
+
+    fn summary() -> Summary {
+        Summary {
+            protocol: Some("Rat heart".into()),
+            step: Some((1, Action::Perfuse(2))),
+            remaining: Some(Duration::from_secs(5400)),
+            warnings: vec!["Step 1: A bubble was detected".into()],
+            ..Summary::default()
+        }
+    }
+
+    #[test]
+    fn lays_out() {
+        let summary = summary();
+        let message = Message::new(&Notification::new(Status::Started, &summary));
+        assert_eq!(message.title, "Rat heart started");
+        assert_eq!(message.color, BLUE);
+        assert_eq!(
+            message.fields,
+            vec![
+                ("Step", "2: Perfuse with buffer 2".into()),
+                ("Remaining", "1h 30m".into()),
+                ("Warnings", "- Step 1: A bubble was detected".into()),
+            ]
+        );
+        let low = Notification::message(
+            NotificationEvent::LowBuffer,
+            "Low buffer",
+            "The reservoir for buffer 2 is running low.",
+            &summary,
+        );
+        assert_eq!(Message::new(&low).color, AMBER);
+    }
+
+    #[test]
+    fn formats() {
+        let summary = summary();
+        let message = Message::new(&Notification::new(Status::Started, &summary));
+        let slack = serde_json::to_value(&message.slack()).unwrap();
+        assert_eq!(slack["attachments"][0]["color"], "#2196f3");
+        assert_eq!(slack["attachments"][0]["fields"][0]["title"], "Step");
+        assert_eq!(slack["attachments"][0]["fields"][0]["short"], true);
+        let discord = serde_json::to_value(&message.discord()).unwrap();
+        assert_eq!(discord["embeds"][0]["color"], BLUE);
+        assert_eq!(discord["embeds"][0]["title"], "Rat heart started");
+        assert_eq!(discord["embeds"][0]["fields"][1]["name"], "Remaining");
+    }
+
+    #[test]
+    fn infers_service() {
+        let discord = ChatConfig::new("https://discord.com/api/webhooks/1/abc");
+        assert_eq!(discord.service(), ChatService::Discord);
+        let slack = ChatConfig::new("https://hooks.slack.com/services/T0/B0/xyz");
+        assert_eq!(slack.service(), ChatService::Slack);
+    }
+}
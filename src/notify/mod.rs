@@ -0,0 +1,357 @@
+//! Notifying people of what the machine is doing.
+//!
+//! The coordinator broadcasts each [`Notification`](struct.Notification.html) (a run starting,
+//! finishing, or faulting, a reservoir running low, and so on) to every registered
+//! [`Notifier`](trait.Notifier.html), each of which decides for itself whether (and how) to pass it
+//! on. [Email](../mail/struct.Email.html) is one such notifier; the others are
+//! [chat](chat/index.html) (Slack and Discord), [SMS](sms/index.html) (for faults), and
+//! [push](push/index.html) (ntfy and Pushover). More can be registered alongside them without the
+//! coordinator knowing anything about them.
+//!
+//! Broadcasts are [throttled](throttle/index.html), so that repeated notifications (e.g. from a
+//! flapping sensor) are summarized rather than sent one by one.
+
+use std::{
+    error, fmt,
+    sync::{Arc, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard},
+    time::Instant,
+};
+
+use crate::{
+    config::{NotificationEvent, NotificationThrottleConfig},
+    mail::{self, Attachment, Summary},
+};
+
+use self::throttle::Throttle;
+
+#[cfg(feature = "chat")]
+pub mod chat;
+#[cfg(feature = "push")]
+pub mod push;
+#[cfg(feature = "sms")]
+pub mod sms;
+mod throttle;
+
+#[cfg(feature = "chat")]
+pub use self::chat::Chat;
+#[cfg(feature = "push")]
+pub use self::push::Push;
+#[cfg(feature = "sms")]
+pub use self::sms::Sms;
+
+/// Encodes the status of the decell machine.
+#[derive(Clone, Copy, Debug)]
+pub enum Status<'a> {
+    /// The run has started.
+    Started,
+    /// The run has finished.
+    Finished,
+    /// The run has been aborted.
+    Aborted,
+    /// The system has been stopped by a fault.
+    Faulted,
+    /// A custom status message.
+    Custom {
+        /// The message's subject.
+        subject: &'a str,
+        /// The message's body.
+        message: &'a str,
+    },
+}
+
+impl<'a> Status<'a> {
+    /// The kind of event this status is.
+    pub fn event(self) -> NotificationEvent {
+        match self {
+            Status::Started => NotificationEvent::Start,
+            Status::Finished | Status::Aborted => NotificationEvent::Completion,
+            Status::Faulted => NotificationEvent::Fault,
+            Status::Custom { .. } => NotificationEvent::Notification,
+        }
+    }
+}
+
+/// Something worth telling people about.
+#[derive(Clone, Copy, Debug)]
+pub struct Notification<'a> {
+    /// The kind of event, which determines who's interested.
+    pub event: NotificationEvent,
+    /// What happened.
+    pub status: Status<'a>,
+    /// The current (or most recent) run.
+    pub summary: &'a Summary,
+    /// Any files which help explain what happened.
+    pub attachments: &'a [Attachment],
+}
+
+impl<'a> Notification<'a> {
+    /// A notification of the given status change, describing the given run.
+    pub fn new(status: Status<'a>, summary: &'a Summary) -> Self {
+        Self {
+            event: status.event(),
+            status,
+            summary,
+            attachments: &[],
+        }
+    }
+    /// A message with the given subject about the given event.
+    pub fn message(
+        event: NotificationEvent,
+        subject: &'a str,
+        message: &'a str,
+        summary: &'a Summary,
+    ) -> Self {
+        Self {
+            event,
+            ..Self::new(Status::Custom { subject, message }, summary)
+        }
+    }
+    /// A title and a sentence describing the notification, for notifiers which don't use the mail
+    /// templates.
+    pub fn describe(&self) -> (String, String) {
+        let name = self.summary.name();
+        match self.status {
+            Status::Started => (
+                format!("{} started", name),
+                format!("The decellularization run of {} has started.", name),
+            ),
+            Status::Finished => (
+                format!("{} completed", name),
+                format!(
+                    "The decellularization run of {} has completed as scheduled.",
+                    name
+                ),
+            ),
+            Status::Aborted => (
+                format!("{} aborted", name),
+                format!(
+                    "The decellularization run of {} has been aborted manually.",
+                    name
+                ),
+            ),
+            Status::Faulted => (
+                "Fault".into(),
+                format!(
+                    "The system has been stopped because of a fault: {}",
+                    self.summary
+                        .fault
+                        .as_ref()
+                        .map_or("unknown.", String::as_str)
+                ),
+            ),
+            Status::Custom { subject, message } => (subject.into(), message.into()),
+        }
+    }
+    /// Attaches the given files to the notification.
+    pub fn with_attachments(self, attachments: &'a [Attachment]) -> Self {
+        Self {
+            attachments,
+            ..self
+        }
+    }
+}
+
+/// A failure to deliver a notification.
+#[derive(Debug)]
+pub enum Error {
+    /// The notification couldn't be mailed.
+    Mail(mail::Error),
+    /// The notification couldn't be posted to a chat service.
+    Chat(String),
+    /// The notification couldn't be texted.
+    Sms(String),
+    /// The notification couldn't be pushed.
+    Push(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Mail(err) => write!(f, "{}", err),
+            Error::Chat(err) => write!(f, "couldn't post to chat: {}", err),
+            Error::Sms(err) => write!(f, "couldn't send text: {}", err),
+            Error::Push(err) => write!(f, "couldn't push notification: {}", err),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Mail(err) => error::Error::source(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<mail::Error> for Error {
+    fn from(err: mail::Error) -> Self {
+        Error::Mail(err)
+    }
+}
+
+/// Trait representing a way of passing notifications on to people.
+pub trait Notifier: fmt::Debug + Send + Sync {
+    /// A short description of the notifier (e.g. `"email"`), used when logging failures.
+    fn name(&self) -> &str;
+    /// Passes the given notification on to whoever is interested in it (if anyone).
+    fn notify(&self, notification: &Notification) -> Result<(), Error>;
+}
+
+/// The notifiers to broadcast to.
+///
+/// Cloning yields a handle to the same notifiers, which share a throttle.
+#[derive(Clone, Debug, Default)]
+pub struct Notifiers {
+    notifiers: Arc<RwLock<Vec<Arc<dyn Notifier>>>>,
+    throttle: Arc<Mutex<Throttle>>,
+}
+
+impl Notifiers {
+    /// Creates an empty set of notifiers, throttled as configured.
+    pub fn new(throttle: NotificationThrottleConfig) -> Self {
+        Self {
+            notifiers: Arc::default(),
+            throttle: Arc::new(Mutex::new(Throttle::new(throttle))),
+        }
+    }
+    /// Registers the given notifier.
+    pub fn register(&mut self, notifier: impl Notifier + 'static) {
+        self.list_mut().push(Arc::new(notifier));
+    }
+    /// Whether no notifiers are registered.
+    pub fn is_empty(&self) -> bool {
+        self.list().is_empty()
+    }
+    /// Replaces the notifiers (for every handle) with the given ones, adopting their limits but
+    /// keeping track of what's been sent recently.
+    pub fn replace(&self, other: Notifiers) {
+        let notifiers = other.list().clone();
+        *self.list_mut() = notifiers;
+        let config = match other.throttle.lock() {
+            Ok(throttle) => throttle.config(),
+            Err(_) => return,
+        };
+        if let Ok(mut throttle) = self.throttle.lock() {
+            throttle.reconfigure(config);
+        }
+    }
+    fn list(&self) -> RwLockReadGuard<'_, Vec<Arc<dyn Notifier>>> {
+        // A panic while holding the lock can't leave the list in a bad state.
+        self.notifiers
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+    fn list_mut(&self) -> RwLockWriteGuard<'_, Vec<Arc<dyn Notifier>>> {
+        self.notifiers
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+    /// Passes the given notification on to every notifier (unless it's throttled), logging any
+    /// failures.
+    pub fn broadcast(&self, notification: &Notification) {
+        let (title, text) = notification.describe();
+        let admitted = match self.throttle.lock() {
+            Ok(mut throttle) => throttle.admit(notification.event, title, text, Instant::now()),
+            Err(_) => true,
+        };
+        if admitted {
+            self.deliver(notification);
+        } else {
+            log::info!("Suppressed a repeated or excessive notification.");
+        }
+    }
+    /// Sends a summary of the notifications suppressed during each storm which has since died
+    /// down.
+    ///
+    /// This should be called periodically.
+    pub fn flush(&self) {
+        let storms = match self.throttle.lock() {
+            Ok(mut throttle) => throttle.calmed(Instant::now()),
+            Err(_) => return,
+        };
+        let summary = Summary::default();
+        for storm in storms {
+            let (subject, message) = storm.describe();
+            self.deliver(&Notification::message(
+                storm.event,
+                &subject,
+                &message,
+                &summary,
+            ));
+        }
+    }
+    /// Passes the given notification on to every notifier, logging any failures.
+    fn deliver(&self, notification: &Notification) {
+        let notifiers = self.list().clone();
+        for notifier in &notifiers {
+            if let Err(err) = notifier.notify(notification) {
+                log::error!("Failed to notify by {}: {}", notifier.name(), err);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Debug, Default)]
+    struct Recorder {
+        events: Arc<Mutex<Vec<NotificationEvent>>>,
+        fails: bool,
+    }
+
+    impl Notifier for Recorder {
+        fn name(&self) -> &str {
+            "recorder"
+        }
+        fn notify(&self, notification: &Notification) -> Result<(), Error> {
+            self.events.lock().unwrap().push(notification.event);
+            if self.fails {
+                Err(mail::Error::Unsupported.into())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    #[test]
+    fn broadcasts() {
+        let (first, second) = (Recorder::default(), Recorder::default());
+        let events = (first.events.clone(), second.events.clone());
+        let mut notifiers = Notifiers::default();
+        notifiers.register(Recorder {
+            fails: true,
+            ..first
+        });
+        notifiers.register(second);
+        let summary = Summary::default();
+        notifiers.broadcast(&Notification::new(Status::Started, &summary));
+        notifiers.broadcast(&Notification::message(
+            NotificationEvent::LowBuffer,
+            "Low buffer",
+            "Reservoir 1 is running low.",
+            &summary,
+        ));
+        let expected = vec![NotificationEvent::Start, NotificationEvent::LowBuffer];
+        assert_eq!(*events.0.lock().unwrap(), expected);
+        assert_eq!(*events.1.lock().unwrap(), expected);
+    }
+
+    #[test]
+    fn replaces() {
+        let (first, second) = (Recorder::default(), Recorder::default());
+        let events = (first.events.clone(), second.events.clone());
+        let mut notifiers = Notifiers::default();
+        notifiers.register(first);
+        let handle = notifiers.clone();
+        let mut replacement = Notifiers::default();
+        replacement.register(second);
+        notifiers.replace(replacement);
+        handle.broadcast(&Notification::new(Status::Started, &Summary::default()));
+        assert!(events.0.lock().unwrap().is_empty());
+        assert_eq!(*events.1.lock().unwrap(), vec![NotificationEvent::Start]);
+    }
+}
@@ -0,0 +1,233 @@
+//! Pushing notifications to phones through [ntfy](https://ntfy.sh) or
+//! [Pushover](https://pushover.net), neither of which needs a mail server.
+//!
+//! Faults are pushed with the highest priority either service allows without acknowledgement, and
+//! low buffers with a raised one, so that phones can be set to let only those through at night.
+
+use std::time::Duration;
+
+use actix_web::client;
+use futures::Future;
+
+use crate::{
+    actix::Arbiter,
+    config::{NotificationEvent, PushConfig},
+};
+
+use super::{Error, Notification, Notifier, Status};
+
+/// How long to wait for a response.
+const TIMEOUT: Duration = Duration::from_secs(10);
+/// Where Pushover messages are posted.
+const PUSHOVER: &str = "https://api.pushover.net/1/messages.json";
+
+/// How urgent a notification is.
+#[derive(Clone, Copy, Debug)]
+enum Priority {
+    Low,
+    Normal,
+    High,
+    Urgent,
+}
+
+impl Priority {
+    fn of(event: NotificationEvent) -> Self {
+        match event {
+            NotificationEvent::Fault => Priority::Urgent,
+            NotificationEvent::LowBuffer => Priority::High,
+            NotificationEvent::Completion => Priority::Normal,
+            NotificationEvent::Start | NotificationEvent::Notification => Priority::Low,
+        }
+    }
+    /// The ntfy priority (1–5, where 3 is the default).
+    fn ntfy(self) -> u8 {
+        match self {
+            Priority::Low => 2,
+            Priority::Normal => 3,
+            Priority::High => 4,
+            Priority::Urgent => 5,
+        }
+    }
+    /// The Pushover priority (-2–2, where 0 is the default and 2 must be acknowledged).
+    fn pushover(self) -> i8 {
+        match self {
+            Priority::Low => -1,
+            Priority::Normal | Priority::High => 0,
+            Priority::Urgent => 1,
+        }
+    }
+}
+
+/// The ntfy tag (shown as an emoji) for the given notification.
+fn tag(notification: &Notification) -> &'static str {
+    match (notification.status, notification.event) {
+        (Status::Started, _) => "arrow_forward",
+        (Status::Finished, _) => "white_check_mark",
+        (Status::Aborted, _) => "stop_button",
+        (_, NotificationEvent::Fault) => "rotating_light",
+        (_, NotificationEvent::LowBuffer) => "warning",
+        _ => "bell",
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct NtfyMessage<'a> {
+    topic: &'a str,
+    title: &'a str,
+    message: &'a str,
+    priority: u8,
+    tags: [&'a str; 1],
+}
+
+#[derive(Debug, Serialize)]
+struct PushoverMessage<'a> {
+    token: &'a str,
+    user: &'a str,
+    title: &'a str,
+    message: &'a str,
+    priority: i8,
+}
+
+/// Pushes notifications to an ntfy topic or Pushover user.
+///
+/// Notifications are pushed in the background, so delivery failures are logged rather than
+/// returned.
+#[derive(Clone, Debug)]
+pub struct Push {
+    config: PushConfig,
+}
+
+impl Push {
+    /// Creates a notifier pushing to the given topic or user.
+    pub fn new(config: PushConfig) -> Self {
+        Self { config }
+    }
+    /// The URL, authorization (if any), and body of the request pushing the given notification.
+    fn request(&self, notification: &Notification) -> (String, Option<String>, serde_json::Value) {
+        let (title, message) = notification.describe();
+        let priority = Priority::of(notification.event);
+        match &self.config {
+            PushConfig::Ntfy {
+                server,
+                topic,
+                token,
+                ..
+            } => (
+                server.clone(),
+                token.as_ref().map(|token| format!("Bearer {}", token)),
+                json(&NtfyMessage {
+                    topic,
+                    title: &title,
+                    message: &message,
+                    priority: priority.ntfy(),
+                    tags: [tag(notification)],
+                }),
+            ),
+            PushConfig::Pushover { token, user, .. } => (
+                PUSHOVER.into(),
+                None,
+                json(&PushoverMessage {
+                    token,
+                    user,
+                    title: &title,
+                    message: &message,
+                    priority: priority.pushover(),
+                }),
+            ),
+        }
+    }
+}
+
+/// Converts the given message to JSON (which can't fail for these messages).
+fn json(message: &impl serde::Serialize) -> serde_json::Value {
+    serde_json::to_value(message).expect("Push messages are valid JSON")
+}
+
+impl Notifier for Push {
+    fn name(&self) -> &str {
+        match self.config {
+            PushConfig::Ntfy { .. } => "ntfy",
+            PushConfig::Pushover { .. } => "Pushover",
+        }
+    }
+    fn notify(&self, notification: &Notification) -> Result<(), Error> {
+        if !self.config.events().contains(&notification.event) {
+            return Ok(());
+        }
+        let (url, authorization, body) = self.request(notification);
+        let mut builder = client::post(&url);
+        if let Some(authorization) = authorization {
+            builder.header("Authorization", authorization);
+        }
+        let request = builder
+            .json(body)
+            .map_err(|err| Error::Push(err.to_string()))?;
+        let service = self.name().to_string();
+        Arbiter::spawn(
+            request
+                .send()
+                .timeout(TIMEOUT)
+                .then(move |result| -> Result<(), ()> {
+                    match result {
+                        Ok(ref response) if response.status().is_success() => {}
+                        Ok(response) => log::error!(
+                            "{} refused a notification (status {}).",
+                            service,
+                            response.status()
+                        ),
+                        Err(err) => {
+                            log::error!("Failed to push a notification to {}: {}", service, err)
+                        }
+                    }
+                    Ok(())
+                }),
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mail::Summary;
+
+    #[test]
+    fn builds_requests() {
+        let summary = Summary {
+            protocol: Some("Rat heart".into()),
+            ..Summary::default()
+        };
+        let done = Notification::new(Status::Finished, &summary);
+        let ntfy = Push::new(PushConfig::Ntfy {
+            server: PushConfig::NTFY.into(),
+            topic: "decell".into(),
+            token: Some("tk_secret".into()),
+            events: vec![NotificationEvent::Completion],
+        });
+        let (url, authorization, body) = ntfy.request(&done);
+        assert_eq!(url, "https://ntfy.sh");
+        assert_eq!(authorization.unwrap(), "Bearer tk_secret");
+        assert_eq!(body["topic"], "decell");
+        assert_eq!(body["title"], "Rat heart completed");
+        assert_eq!(body["priority"], 3);
+        assert_eq!(body["tags"][0], "white_check_mark");
+        let pushover = Push::new(PushConfig::Pushover {
+            token: "app".into(),
+            user: "user".into(),
+            events: vec![NotificationEvent::Fault],
+        });
+        let fault = Summary {
+            fault: Some("Emergency stop pressed.".into()),
+            ..Summary::default()
+        };
+        let (url, authorization, body) =
+            pushover.request(&Notification::new(Status::Faulted, &fault));
+        assert_eq!(url, PUSHOVER);
+        assert_eq!(authorization, None);
+        assert_eq!(
+            body["message"],
+            "The system has been stopped because of a fault: Emergency stop pressed."
+        );
+        assert_eq!(body["priority"], 1);
+    }
+}
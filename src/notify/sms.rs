@@ -0,0 +1,141 @@
+//! Texting the administrators about faults through [Twilio](https://www.twilio.com/docs/sms).
+//!
+//! Only critical events (faults, including emergency stops and watchdog trips) are texted, since
+//! the point is to wake someone up; everything else is left to quieter notifiers.
+
+use std::time::Duration;
+
+use actix_web::client;
+use futures::Future;
+
+use crate::{
+    actix::Arbiter,
+    config::{NotificationEvent, SmsConfig},
+};
+
+use super::{Error, Notification, Notifier, Status};
+
+/// How long to wait for a response.
+const TIMEOUT: Duration = Duration::from_secs(10);
+/// The longest text sent (two SMS segments, as long as the text is plain ASCII), in characters.
+const LIMIT: usize = 306;
+
+/// The text sent for the given notification, if it's critical enough to send.
+fn text(notification: &Notification) -> Option<String> {
+    if notification.event != NotificationEvent::Fault {
+        return None;
+    }
+    let summary = notification.summary;
+    let mut text = match notification.status {
+        Status::Custom { subject, message } => format!("deoxy: {}: {}", subject, message),
+        _ => {
+            let fault = summary
+                .fault
+                .as_ref()
+                .map_or("Unknown fault.", String::as_str);
+            let mut text = format!("deoxy fault: {}", fault);
+            if summary.total.is_some() {
+                text.push_str(&format!(" The run of {} has been stopped.", summary.name()));
+            }
+            text
+        }
+    };
+    if text.chars().count() > LIMIT {
+        text = text.chars().take(LIMIT - 3).collect();
+        text.push_str("...");
+    }
+    Some(text)
+}
+
+/// Texts faults to the configured phone numbers.
+///
+/// Texts are sent in the background, so delivery failures are logged rather than returned.
+#[derive(Clone, Debug)]
+pub struct Sms {
+    config: SmsConfig,
+}
+
+impl Sms {
+    /// Creates a notifier sending texts through the given Twilio account.
+    pub fn new(config: SmsConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl Notifier for Sms {
+    fn name(&self) -> &str {
+        "SMS"
+    }
+    fn notify(&self, notification: &Notification) -> Result<(), Error> {
+        let text = match text(notification) {
+            Some(text) => text,
+            None => return Ok(()),
+        };
+        let url = format!(
+            "https://api.twilio.com/2010-04-01/Accounts/{}/Messages.json",
+            self.config.account
+        );
+        let credentials = format!("{}:{}", self.config.account, self.config.token);
+        let authorization = format!("Basic {}", base64::encode(credentials.as_bytes()));
+        for to in &self.config.to {
+            let form = [
+                ("To", to.as_str()),
+                ("From", self.config.from.as_str()),
+                ("Body", text.as_str()),
+            ];
+            let request = client::post(&url)
+                .header("Authorization", authorization.as_str())
+                .form(&form)
+                .map_err(|err| Error::Sms(err.to_string()))?;
+            let to = to.clone();
+            Arbiter::spawn(
+                request
+                    .send()
+                    .timeout(TIMEOUT)
+                    .then(move |result| -> Result<(), ()> {
+                        match result {
+                            Ok(ref response) if response.status().is_success() => {}
+                            Ok(response) => log::error!(
+                                "Twilio refused a text to {} (status {}).",
+                                to,
+                                response.status()
+                            ),
+                            Err(err) => log::error!("Failed to text {}: {}", to, err),
+                        }
+                        Ok(())
+                    }),
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mail::Summary;
+
+    #[test]
+    fn texts() {
+        let mut summary = Summary {
+            protocol: Some("Rat heart".into()),
+            total: Some(Duration::from_secs(600)),
+            fault: Some("Emergency stop pressed.".into()),
+            ..Summary::default()
+        };
+        let fault = Notification::new(Status::Faulted, &summary);
+        assert_eq!(
+            text(&fault).unwrap(),
+            "deoxy fault: Emergency stop pressed. The run of Rat heart has been stopped."
+        );
+        let done = Notification::new(Status::Finished, &summary);
+        assert_eq!(text(&done), None);
+        let low = Notification::message(NotificationEvent::LowBuffer, "Low", "Low.", &summary);
+        assert_eq!(text(&low), None);
+        summary.fault = Some("x".repeat(1000));
+        let long = Notification::new(Status::Faulted, &summary);
+        let long = text(&long).unwrap();
+        assert_eq!(long.chars().count(), LIMIT);
+        assert!(long.ends_with("..."));
+    }
+}
@@ -0,0 +1,251 @@
+//! Keeps a misbehaving sensor from flooding everyone with notifications.
+//!
+//! A notification identical to one sent within the deduplication window is dropped, as is any
+//! beyond the limit for its kind of event. Once a storm of dropped notifications has been quiet
+//! for a window, a single summary of what was dropped goes out in their place.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Instant,
+};
+
+use crate::config::{NotificationEvent, NotificationThrottleConfig};
+
+/// The notifications of one kind dropped since the last summary.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct Storm {
+    /// The kind of event dropped.
+    pub event: NotificationEvent,
+    /// How many were dropped.
+    pub count: usize,
+    /// The title of the last one dropped.
+    pub last: String,
+}
+
+impl Storm {
+    /// The subject and message of the summary sent once the storm is over.
+    pub fn describe(&self) -> (String, String) {
+        let kind = match self.event {
+            NotificationEvent::Start => "start",
+            NotificationEvent::Completion => "completion",
+            NotificationEvent::Fault => "fault",
+            NotificationEvent::LowBuffer => "low-buffer",
+            NotificationEvent::Notification => "protocol",
+        };
+        let (plural, verb) = if self.count == 1 {
+            ("", "was")
+        } else {
+            ("s", "were")
+        };
+        (
+            "Notifications suppressed".into(),
+            format!(
+                "{} repeated or excessive {} notification{} {} suppressed (the last was \"{}\").",
+                self.count, kind, plural, verb, self.last
+            ),
+        )
+    }
+}
+
+/// What's been sent (and dropped) recently for one kind of event.
+#[derive(Clone, Debug, Default)]
+struct Recent {
+    /// When each notification still counting against the limit was sent.
+    sent: VecDeque<Instant>,
+    /// How many notifications have been dropped since the last summary.
+    dropped: usize,
+    /// When the last notification was dropped.
+    last_dropped: Option<Instant>,
+    /// The title of the last notification dropped.
+    last_title: String,
+}
+
+/// Decides which notifications go out.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Throttle {
+    config: NotificationThrottleConfig,
+    recent: HashMap<NotificationEvent, Recent>,
+    /// When each distinct notification (by event, title, and text) was last sent.
+    seen: HashMap<(NotificationEvent, String, String), Instant>,
+}
+
+impl Throttle {
+    /// Creates a throttle with the given limits, which hasn't seen anything yet.
+    pub fn new(config: NotificationThrottleConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+    /// The limits in force.
+    pub fn config(&self) -> NotificationThrottleConfig {
+        self.config
+    }
+    /// Adopts the given limits, keeping track of what's been sent (and dropped) so far.
+    pub fn reconfigure(&mut self, config: NotificationThrottleConfig) {
+        self.config = config;
+    }
+    /// Whether a notification of the given event, title, and text may be sent now (noting that it
+    /// was, if so, or that it was dropped, if not).
+    pub fn admit(
+        &mut self,
+        event: NotificationEvent,
+        title: String,
+        text: String,
+        now: Instant,
+    ) -> bool {
+        let NotificationThrottleConfig {
+            window,
+            limit,
+            period,
+        } = self.config;
+        self.seen.retain(|_, &mut sent| now - sent < window);
+        let recent = self.recent.entry(event).or_default();
+        while recent
+            .sent
+            .front()
+            .map_or(false, |&sent| now - sent >= period)
+        {
+            recent.sent.pop_front();
+        }
+        let key = (event, title, text);
+        let repeated = self.seen.contains_key(&key);
+        let excessive = limit > 0 && recent.sent.len() >= limit as usize;
+        if repeated || excessive {
+            recent.dropped += 1;
+            recent.last_dropped = Some(now);
+            recent.last_title = key.1;
+            return false;
+        }
+        recent.sent.push_back(now);
+        self.seen.insert(key, now);
+        true
+    }
+    /// Ends (and returns) the storms which have been quiet for a window.
+    pub fn calmed(&mut self, now: Instant) -> Vec<Storm> {
+        let window = self.config.window;
+        let mut storms = self
+            .recent
+            .iter_mut()
+            .filter(|(_, recent)| {
+                recent
+                    .last_dropped
+                    .map_or(false, |dropped| now - dropped >= window)
+            })
+            .map(|(&event, recent)| {
+                let storm = Storm {
+                    event,
+                    count: recent.dropped,
+                    last: recent.last_title.clone(),
+                };
+                recent.dropped = 0;
+                recent.last_dropped = None;
+                storm
+            })
+            .collect::<Vec<_>>();
+        storms.sort_by_key(|storm| {
+            NotificationEvent::ALL
+                .iter()
+                .position(|&e| e == storm.event)
+        });
+        storms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn throttle() -> Throttle {
+        Throttle::new(NotificationThrottleConfig {
+            window: Duration::from_secs(60),
+            limit: 3,
+            period: Duration::from_secs(600),
+        })
+    }
+
+    #[test]
+    fn deduplicates() {
+        let mut throttle = throttle();
+        let start = Instant::now();
+        let fault = |throttle: &mut Throttle, secs| {
+            let now = start + Duration::from_secs(secs);
+            throttle.admit(
+                NotificationEvent::Fault,
+                "Fault".into(),
+                "Bubble.".into(),
+                now,
+            )
+        };
+        assert!(fault(&mut throttle, 0));
+        assert!(!fault(&mut throttle, 30));
+        assert!(fault(&mut throttle, 60));
+        let other = throttle.admit(
+            NotificationEvent::Fault,
+            "Fault".into(),
+            "Overpressure.".into(),
+            start + Duration::from_secs(61),
+        );
+        assert!(other);
+    }
+
+    #[test]
+    fn limits() {
+        let mut throttle = throttle();
+        let start = Instant::now();
+        let admitted = (0..10)
+            .filter(|&n| {
+                throttle.admit(
+                    NotificationEvent::LowBuffer,
+                    "Low buffer".into(),
+                    format!("{} mL remaining", 100 - n),
+                    start + Duration::from_secs(n),
+                )
+            })
+            .count();
+        assert_eq!(admitted, 3);
+        // Other events have their own limit.
+        assert!(throttle.admit(
+            NotificationEvent::Completion,
+            "Done".into(),
+            "Done.".into(),
+            start + Duration::from_secs(10)
+        ));
+        assert!(throttle.admit(
+            NotificationEvent::LowBuffer,
+            "Low buffer".into(),
+            "Nearly empty".into(),
+            start + Duration::from_secs(600),
+        ));
+    }
+
+    #[test]
+    fn summarizes() {
+        let mut throttle = throttle();
+        let start = Instant::now();
+        for n in 0..5 {
+            throttle.admit(
+                NotificationEvent::Fault,
+                format!("Fault {}", n),
+                "Flapping.".into(),
+                start + Duration::from_secs(n),
+            );
+        }
+        assert_eq!(throttle.calmed(start + Duration::from_secs(30)), vec![]);
+        let storms = throttle.calmed(start + Duration::from_secs(64));
+        assert_eq!(
+            storms,
+            vec![Storm {
+                event: NotificationEvent::Fault,
+                count: 2,
+                last: "Fault 4".into(),
+            }]
+        );
+        assert_eq!(
+            storms[0].describe().1,
+            "2 repeated or excessive fault notifications were suppressed (the last was \"Fault 4\")."
+        );
+        assert_eq!(throttle.calmed(start + Duration::from_secs(200)), vec![]);
+    }
+}
@@ -0,0 +1,87 @@
+//! GPIO access through the Linux GPIO character device (the interface used by libgpiod).
+use super::{Error, GpioBackend, Out, Output, Pwm};
+use gpio_cdev::{errors::Error as CdevError, Chip, LineHandle, LineRequestFlags};
+use std::{fmt, sync::Mutex, time::Duration};
+
+/// The consumer label reported to the kernel for requested lines.
+const CONSUMER: &str = "deoxy";
+
+impl From<CdevError> for Error {
+    fn from(err: CdevError) -> Self {
+        Self::Backend(err.to_string())
+    }
+}
+
+/// Backend using a GPIO character device (e.g. `/dev/gpiochip0`).
+///
+/// ## Notes
+/// The character device interface has no notion of PWM, so pins acquired through this backend
+/// cannot drive motors.
+pub struct Cdev {
+    chip: Mutex<Chip>,
+}
+
+impl Cdev {
+    /// Opens the GPIO chip at the given path.
+    pub fn open(path: &str) -> Result<Self, Error> {
+        Ok(Self {
+            chip: Mutex::new(Chip::new(path)?),
+        })
+    }
+}
+
+impl fmt::Debug for Cdev {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Cdev").finish()
+    }
+}
+
+impl GpioBackend for Cdev {
+    fn output(&self, number: u16) -> Result<Box<dyn Output>, Error> {
+        let mut chip = self.chip.lock().map_err(|_| Error::Panic)?;
+        let handle =
+            chip.get_line(u32::from(number))?
+                .request(LineRequestFlags::OUTPUT, 0, CONSUMER)?;
+        Ok(Box::new(CdevPin { number, handle }))
+    }
+}
+
+/// An output line requested from a character device.
+struct CdevPin {
+    number: u16,
+    handle: LineHandle,
+}
+
+impl fmt::Debug for CdevPin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CdevPin")
+            .field("number", &self.number)
+            .finish()
+    }
+}
+
+impl CdevPin {
+    fn write(&self, value: u8) -> Result<(), Error> {
+        self.handle.set_value(value).map_err(|err| {
+            Error::Backend(format!(
+                "Failed to write to cdev line {}: {}",
+                self.number, err
+            ))
+        })
+    }
+}
+
+impl Pwm for CdevPin {
+    fn set_pwm(&mut self, _: Duration, _: Duration) -> Result<(), Error> {
+        Err(Error::Unsupported("PWM over the GPIO character device"))
+    }
+}
+
+impl Out for CdevPin {
+    fn set_high(&mut self) -> Result<(), Error> {
+        self.write(1)
+    }
+    fn set_low(&mut self) -> Result<(), Error> {
+        self.write(0)
+    }
+}
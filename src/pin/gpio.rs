@@ -0,0 +1,139 @@
+//! GPIO access through `rppal`.
+use super::{Edge, EdgeCallback, Error, GpioBackend, Input, Out, Output, Pull, Pwm};
+use lazy_static::lazy_static;
+use rppal::{
+    gpio::{Gpio, InputPin, Level, OutputPin, Trigger},
+    pwm::{Channel, Pwm as HardwarePwm},
+};
+use std::{sync::Mutex, time::Duration};
+
+lazy_static! {
+    /// The GPIO peripheral, once opened.
+    static ref GPIO: Mutex<Option<Gpio>> = Mutex::new(None);
+}
+
+/// The GPIO peripheral, opened the first time it's needed (and tried again if that failed).
+fn gpio() -> Result<Gpio, Error> {
+    let mut gpio = GPIO.lock().map_err(|_| Error::Panic)?;
+    if let Some(gpio) = &*gpio {
+        return Ok(gpio.clone());
+    }
+    let opened = Gpio::new()?;
+    *gpio = Some(opened.clone());
+    Ok(opened)
+}
+
+/// Backend using the `rppal` crate's memory-mapped GPIO interface on the Raspberry Pi.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Rppal;
+
+impl GpioBackend for Rppal {
+    fn output(&self, number: u16) -> Result<Box<dyn Output>, Error> {
+        let pin = gpio()?.get(number as u8).map(|pin| pin.into_output())?;
+        Ok(Box::new(pin))
+    }
+    fn input(&self, number: u16) -> Result<Box<dyn Input>, Error> {
+        self.input_with_pull(number, Pull::Off)
+    }
+    fn input_with_pull(&self, number: u16, pull: Pull) -> Result<Box<dyn Input>, Error> {
+        let pin = gpio()?.get(number as u8)?;
+        let pin = match pull {
+            Pull::Off => pin.into_input(),
+            Pull::Up => pin.into_input_pullup(),
+            Pull::Down => pin.into_input_pulldown(),
+        };
+        Ok(Box::new(pin))
+    }
+    fn hardware_pwm(&self, _: u16, channel: u8) -> Result<Box<dyn Output>, Error> {
+        let channel = match channel {
+            0 => Channel::Pwm0,
+            1 => Channel::Pwm1,
+            _ => return Err(Error::InvalidChannel(channel)),
+        };
+        let pwm = HardwarePwm::new(channel)?;
+        Ok(Box::new(PwmChannel {
+            pwm,
+            period: Duration::new(0, 0),
+        }))
+    }
+    fn pca9685(&self, bus: u8, address: u16, channel: u8) -> Result<Box<dyn Output>, Error> {
+        super::pca9685::channel(bus, address, channel)
+    }
+}
+
+/// A hardware PWM channel.
+///
+/// ## Notes
+/// The channel must be routed to its pin (e.g. with `dtoverlay=pwm-2chan`) for the output to
+/// appear.
+#[derive(Debug)]
+struct PwmChannel {
+    pwm: HardwarePwm,
+    /// The most recently-set period.
+    period: Duration,
+}
+
+impl Pwm for PwmChannel {
+    fn set_pwm(&mut self, period: Duration, pulse_width: Duration) -> Result<(), Error> {
+        if pulse_width == Duration::new(0, 0) {
+            self.pwm.disable()?;
+        } else {
+            log::trace!("Setting hardware pulse width to {:?}", pulse_width);
+            // The pulse width may never exceed the period, so clear it before changing the period.
+            self.pwm.set_pulse_width(Duration::new(0, 0))?;
+            self.pwm.set_period(period)?;
+            self.pwm.set_pulse_width(pulse_width)?;
+            self.pwm.enable()?;
+        }
+        self.period = period;
+        Ok(())
+    }
+}
+
+impl Out for PwmChannel {
+    fn set_high(&mut self) -> Result<(), Error> {
+        let period = self.period;
+        self.set_pwm(period, period)
+    }
+    fn set_low(&mut self) -> Result<(), Error> {
+        Ok(self.pwm.disable()?)
+    }
+}
+
+impl Pwm for OutputPin {
+    fn set_pwm(&mut self, period: Duration, pulse_width: Duration) -> Result<(), Error> {
+        if pulse_width == Duration::new(0, 0) {
+            self.clear_pwm()?;
+        } else {
+            log::trace!("Setting output pin pulse width to {:?}", pulse_width);
+            self.set_pwm(period, pulse_width)?;
+        }
+        Ok(())
+    }
+}
+
+impl Out for OutputPin {
+    fn set_high(&mut self) -> Result<(), Error> {
+        Self::set_high(self);
+        Ok(())
+    }
+    fn set_low(&mut self) -> Result<(), Error> {
+        Self::set_low(self);
+        Ok(())
+    }
+}
+
+impl Input for InputPin {
+    fn is_high(&self) -> bool {
+        Self::is_high(self)
+    }
+    fn on_edge(&mut self, edge: Edge, mut callback: EdgeCallback) -> Result<(), Error> {
+        let trigger = match edge {
+            Edge::Rising => Trigger::RisingEdge,
+            Edge::Falling => Trigger::FallingEdge,
+            Edge::Both => Trigger::Both,
+        };
+        self.set_async_interrupt(trigger, move |level| callback(level == Level::High))?;
+        Ok(())
+    }
+}
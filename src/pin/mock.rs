@@ -0,0 +1,414 @@
+//! An in-memory backend for developing and testing without hardware.
+//!
+//! Every write to a mock pin is recorded (with a timestamp) in a shared
+//! [`Timeline`](struct.Timeline.html), which can be inspected after the fact. Input levels are
+//! simulated through [`Inputs`](struct.Inputs.html). Writes to a pin can be made to fail (see
+//! [`Mock::fail`](struct.Mock.html#method.fail)) to exercise error handling.
+use super::{Edge, EdgeCallback, Error, GpioBackend, Input, Out, Output, Pull, Pwm};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// A single recorded write to a mock pin.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Event {
+    /// When the write occurred.
+    pub time: Instant,
+    /// The number of the pin written to.
+    pub pin: u16,
+    /// What was written.
+    pub kind: EventKind,
+}
+
+/// Describes a write to a mock pin.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EventKind {
+    /// The pin was set high.
+    High,
+    /// The pin was set low.
+    Low,
+    /// The pin's PWM signal was changed.
+    Pwm {
+        /// The period of the signal.
+        period: Duration,
+        /// The pulse width of the signal.
+        pulse_width: Duration,
+    },
+}
+
+/// A shared, append-only record of pin writes.
+///
+/// Cloning a timeline yields a handle to the same underlying record.
+#[derive(Clone, Debug, Default)]
+pub struct Timeline {
+    events: Arc<Mutex<Vec<Event>>>,
+}
+
+impl Timeline {
+    fn record(&self, pin: u16, kind: EventKind) {
+        let event = Event {
+            time: Instant::now(),
+            pin,
+            kind,
+        };
+        log::trace!("Mock pin event: {:?}", event);
+        if let Ok(mut events) = self.events.lock() {
+            events.push(event);
+        }
+    }
+    /// Returns a copy of every event recorded so far, in order.
+    pub fn events(&self) -> Vec<Event> {
+        self.events
+            .lock()
+            .map(|events| events.clone())
+            .unwrap_or_default()
+    }
+    /// Returns a copy of every event recorded so far on the given pin, in order.
+    pub fn pin(&self, number: u16) -> Vec<Event> {
+        self.events()
+            .into_iter()
+            .filter(|event| event.pin == number)
+            .collect()
+    }
+    /// Returns the most recent event recorded on the given pin, if any.
+    pub fn last(&self, number: u16) -> Option<Event> {
+        self.pin(number).pop()
+    }
+    /// Discards all recorded events.
+    pub fn clear(&self) {
+        if let Ok(mut events) = self.events.lock() {
+            events.clear();
+        }
+    }
+}
+
+/// The simulated state of a single input.
+#[derive(Default)]
+struct InputState {
+    high: bool,
+    callback: Option<(Edge, EdgeCallback)>,
+}
+
+/// Shared, simulated input levels.
+///
+/// Cloning yields a handle to the same underlying inputs.
+#[derive(Clone, Default)]
+pub struct Inputs {
+    pins: Arc<Mutex<HashMap<u16, InputState>>>,
+}
+
+impl fmt::Debug for Inputs {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Inputs").finish()
+    }
+}
+
+impl Inputs {
+    /// Sets the simulated level of the given input.
+    ///
+    /// If the level changes, any callback registered for the corresponding edge is invoked (on
+    /// the calling thread).
+    pub fn set(&self, number: u16, high: bool) {
+        if let Ok(mut pins) = self.pins.lock() {
+            let state = pins.entry(number).or_default();
+            let changed = state.high != high;
+            state.high = high;
+            if let Some((edge, callback)) = state.callback.as_mut() {
+                if changed && edge.matches(high) {
+                    callback(high);
+                }
+            }
+        }
+    }
+    /// Simulates a pulse (a rising edge followed by a falling edge) on the given input.
+    pub fn pulse(&self, number: u16) {
+        self.set(number, true);
+        self.set(number, false);
+    }
+    /// The simulated level of the given input.
+    pub fn is_high(&self, number: u16) -> bool {
+        self.pins
+            .lock()
+            .ok()
+            .and_then(|pins| pins.get(&number).map(|state| state.high))
+            .unwrap_or(false)
+    }
+    /// Sets the initial level of the given input, unless it has already been set.
+    fn init(&self, number: u16, high: bool) {
+        if let Ok(mut pins) = self.pins.lock() {
+            pins.entry(number).or_insert_with(|| InputState {
+                high,
+                callback: None,
+            });
+        }
+    }
+    fn register(&self, number: u16, edge: Edge, callback: EdgeCallback) {
+        if let Ok(mut pins) = self.pins.lock() {
+            pins.entry(number).or_default().callback = Some((edge, callback));
+        }
+    }
+}
+
+/// The pins whose writes currently fail.
+type Failing = Arc<Mutex<HashSet<u16>>>;
+
+/// Backend whose pins exist only in memory, recording all writes to a shared timeline.
+#[derive(Clone, Debug, Default)]
+pub struct Mock {
+    timeline: Timeline,
+    inputs: Inputs,
+    failing: Failing,
+}
+
+impl Mock {
+    /// Creates a new mock backend with an empty timeline.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Returns a handle to the timeline of writes made through this backend.
+    pub fn timeline(&self) -> Timeline {
+        self.timeline.clone()
+    }
+    /// Returns a handle to the simulated inputs of this backend.
+    pub fn inputs(&self) -> Inputs {
+        self.inputs.clone()
+    }
+    /// Makes writes to the given pin fail (or succeed again), as if its hardware had been lost.
+    ///
+    /// Failed writes aren't recorded on the timeline.
+    pub fn fail(&self, number: u16, failing: bool) {
+        if let Ok(mut pins) = self.failing.lock() {
+            if failing {
+                pins.insert(number);
+            } else {
+                pins.remove(&number);
+            }
+        }
+    }
+}
+
+impl GpioBackend for Mock {
+    fn output(&self, number: u16) -> Result<Box<dyn Output>, Error> {
+        Ok(Box::new(MockPin {
+            number,
+            timeline: self.timeline(),
+            failing: Arc::clone(&self.failing),
+        }))
+    }
+    /// Mock pins are simulated, so any number of mocks may hand out the same pin.
+    fn exclusive(&self) -> bool {
+        false
+    }
+    fn input(&self, number: u16) -> Result<Box<dyn Input>, Error> {
+        Ok(Box::new(MockInput {
+            number,
+            inputs: self.inputs(),
+        }))
+    }
+    /// Pulled-up inputs start out high.
+    fn input_with_pull(&self, number: u16, pull: Pull) -> Result<Box<dyn Input>, Error> {
+        self.inputs.init(number, pull == Pull::Up);
+        self.input(number)
+    }
+    fn hardware_pwm(&self, number: u16, _: u8) -> Result<Box<dyn Output>, Error> {
+        self.output(number)
+    }
+    fn pca9685(&self, _: u8, _: u16, channel: u8) -> Result<Box<dyn Output>, Error> {
+        self.output(channel.into())
+    }
+}
+
+/// An output pin which records its writes.
+#[derive(Debug)]
+struct MockPin {
+    number: u16,
+    timeline: Timeline,
+    failing: Failing,
+}
+
+impl MockPin {
+    /// Records the write, unless writes to this pin are failing.
+    fn write(&self, kind: EventKind) -> Result<(), Error> {
+        let failing = self
+            .failing
+            .lock()
+            .map(|pins| pins.contains(&self.number))
+            .unwrap_or(false);
+        if failing {
+            return Err(Error::Backend(format!(
+                "Simulated failure writing to mock pin {}",
+                self.number
+            )));
+        }
+        self.timeline.record(self.number, kind);
+        Ok(())
+    }
+}
+
+impl Pwm for MockPin {
+    fn set_pwm(&mut self, period: Duration, pulse_width: Duration) -> Result<(), Error> {
+        self.write(EventKind::Pwm {
+            period,
+            pulse_width,
+        })
+    }
+}
+
+impl Out for MockPin {
+    fn set_high(&mut self) -> Result<(), Error> {
+        self.write(EventKind::High)
+    }
+    fn set_low(&mut self) -> Result<(), Error> {
+        self.write(EventKind::Low)
+    }
+}
+
+/// An input pin whose level is simulated.
+#[derive(Debug)]
+struct MockInput {
+    number: u16,
+    inputs: Inputs,
+}
+
+impl Input for MockInput {
+    fn is_high(&self) -> bool {
+        self.inputs.is_high(self.number)
+    }
+    fn on_edge(&mut self, edge: Edge, callback: EdgeCallback) -> Result<(), Error> {
+        self.inputs.register(self.number, edge, callback);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pin::Pin;
+    #[test]
+    fn records_writes() {
+        let mock = Mock::new();
+        let timeline = mock.timeline();
+        let mut a = Pin::with_backend(&mock, 1).unwrap();
+        let mut b = Pin::with_backend(&mock, 2).unwrap();
+        a.set_high().unwrap();
+        b.set_pwm(Duration::from_millis(20), Duration::from_millis(1))
+            .unwrap();
+        a.set_low().unwrap();
+        let kinds = timeline
+            .events()
+            .into_iter()
+            .map(|event| (event.pin, event.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                (1, EventKind::High),
+                (
+                    2,
+                    EventKind::Pwm {
+                        period: Duration::from_millis(20),
+                        pulse_width: Duration::from_millis(1),
+                    }
+                ),
+                (1, EventKind::Low),
+            ]
+        );
+        assert_eq!(
+            timeline.last(1).map(|event| event.kind),
+            Some(EventKind::Low)
+        );
+        timeline.clear();
+        assert!(timeline.events().is_empty());
+    }
+    #[test]
+    fn inverts_outputs() {
+        let mock = Mock::new();
+        let timeline = mock.timeline();
+        let mut pin = Pin::with_backend(&mock, 1).unwrap().with_inversion(true);
+        pin.set_high().unwrap();
+        pin.set_pwm(Duration::from_millis(20), Duration::from_millis(5))
+            .unwrap();
+        let kinds = timeline
+            .events()
+            .into_iter()
+            .map(|event| event.kind)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                EventKind::Low,
+                EventKind::Pwm {
+                    period: Duration::from_millis(20),
+                    pulse_width: Duration::from_millis(15),
+                },
+            ]
+        );
+    }
+    #[test]
+    fn fails_writes() {
+        let mock = Mock::new();
+        let timeline = mock.timeline();
+        let mut pin = Pin::with_backend(&mock, 1).unwrap();
+        mock.fail(1, true);
+        assert!(pin.set_high().is_err());
+        assert!(pin
+            .set_pwm(Duration::from_millis(20), Duration::from_millis(1))
+            .is_err());
+        assert!(timeline.events().is_empty());
+        mock.fail(1, false);
+        pin.set_high().unwrap();
+        assert_eq!(
+            timeline.last(1).map(|event| event.kind),
+            Some(EventKind::High)
+        );
+    }
+    #[test]
+    fn simulates_inputs() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let mock = Mock::new();
+        let inputs = mock.inputs();
+        let mut input = mock.input(3).unwrap();
+        let count = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&count);
+        input
+            .on_edge(
+                Edge::Rising,
+                Box::new(move |_| {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                }),
+            )
+            .unwrap();
+        inputs.pulse(3);
+        inputs.set(3, true);
+        assert!(input.is_high());
+        inputs.set(3, true);
+        inputs.pulse(4);
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+    }
+    #[test]
+    fn streams_edges() {
+        use crate::pin::{EdgeEvent, InputPin};
+        use futures::Stream;
+        let mock = Mock::new();
+        let inputs = mock.inputs();
+        let mut pin = InputPin::with_backend(&mock, 3, Pull::Up).unwrap();
+        assert!(pin.is_high());
+        let events = pin.events(Edge::Falling).unwrap();
+        inputs.set(3, false);
+        inputs.set(3, true);
+        inputs.set(3, false);
+        let events = events
+            .take(2)
+            .wait()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let event = EdgeEvent {
+            pin: 3,
+            high: false,
+        };
+        assert_eq!(events, vec![event, event]);
+    }
+}
@@ -0,0 +1,530 @@
+//! Utilities for working with GPIO pins.
+//!
+//! Pins are acquired through a [`GpioBackend`](trait.GpioBackend.html), which abstracts over the
+//! driver actually used to talk to the hardware. The backend is chosen when the pin is
+//! constructed; [`Pin::try_new`](struct.Pin.html#method.try_new) uses the default backend for the
+//! enabled features.
+//!
+//! Each GPIO pin (and each PCA9685 channel) may only be held by one [`Pin`](struct.Pin.html) at a
+//! time (across all backends that drive real hardware); acquiring a pin that is already held fails
+//! with [`Error::AlreadyInUse`](enum.Error.html#variant.AlreadyInUse).
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::{fmt, io::Error as IoError};
+
+use futures::sync::mpsc::{self, UnboundedReceiver};
+use lazy_static::lazy_static;
+
+use crate::actix::ActixMessage;
+
+#[cfg(all(feature = "stub", feature = "use_rppal"))]
+compile_error!("Cannot stub and use rppal simultaneously");
+
+#[cfg(feature = "use_cdev")]
+mod cdev;
+#[cfg(feature = "use_rppal")]
+mod gpio;
+pub mod mock;
+#[cfg(feature = "use_rppal")]
+mod pca9685;
+mod pigpio;
+#[cfg(feature = "serial")]
+mod serial;
+#[cfg(feature = "stub")]
+mod stub;
+mod sysfs;
+
+#[cfg(feature = "use_cdev")]
+pub use self::cdev::Cdev;
+#[cfg(feature = "use_rppal")]
+pub use self::gpio::Rppal;
+pub use self::mock::Mock;
+pub use self::pigpio::{Pigpio, DEFAULT_PORT as PIGPIO_DEFAULT_PORT};
+#[cfg(feature = "serial")]
+pub use self::serial::{Serial, DEFAULT_BAUD as SERIAL_DEFAULT_BAUD};
+#[cfg(feature = "stub")]
+pub use self::stub::Stub;
+pub use self::sysfs::Sysfs;
+
+/// Trait representing an output device capable of (software) PWM.
+pub trait Pwm {
+    /// Sets the pulse width and period for the device.
+    fn set_pwm(&mut self, period: Duration, pulse_width: Duration) -> Result<(), Error>;
+}
+
+/// Trait representing a general output device.
+pub trait Out {
+    /// Sets the output device high.
+    fn set_high(&mut self) -> Result<(), Error>;
+    /// Sets the output device low.
+    fn set_low(&mut self) -> Result<(), Error>;
+    /// Sets the output device high/low.
+    fn set(&mut self, value: bool) -> Result<(), Error> {
+        if value {
+            self.set_high()
+        } else {
+            self.set_low()
+        }
+    }
+}
+
+/// Trait representing an output pin handed out by a [`GpioBackend`](trait.GpioBackend.html).
+///
+/// This trait is implemented automatically for anything that is both [`Out`](trait.Out.html) and
+/// [`Pwm`](trait.Pwm.html).
+pub trait Output: Out + Pwm + fmt::Debug + Send {}
+
+impl<T> Output for T where T: Out + Pwm + fmt::Debug + Send {}
+
+/// The edges on which an input should report changes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Edge {
+    /// Report transitions from low to high.
+    Rising,
+    /// Report transitions from high to low.
+    Falling,
+    /// Report all transitions.
+    Both,
+}
+
+impl Edge {
+    /// Whether a transition to the given level matches this edge.
+    pub fn matches(self, high: bool) -> bool {
+        match self {
+            Self::Rising => high,
+            Self::Falling => !high,
+            Self::Both => true,
+        }
+    }
+}
+
+/// A callback invoked with the new level of an input when it changes.
+pub type EdgeCallback = Box<dyn FnMut(bool) + Send>;
+
+/// The internal pull resistor configuration of an input.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "use_serde", serde(rename_all = "lowercase"))]
+pub enum Pull {
+    /// The input floats unless driven externally.
+    Off,
+    /// The input is pulled high (e.g. for a switch to ground).
+    Up,
+    /// The input is pulled low (e.g. for a switch to 3.3 V).
+    Down,
+}
+
+impl Default for Pull {
+    fn default() -> Self {
+        Self::Off
+    }
+}
+
+/// A change in the level of an input, delivered to actors as a message.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct EdgeEvent {
+    /// The number of the input pin.
+    pub pin: u16,
+    /// Whether the input is now high.
+    pub high: bool,
+}
+
+impl ActixMessage for EdgeEvent {
+    type Result = ();
+}
+
+/// Trait representing an input pin handed out by a [`GpioBackend`](trait.GpioBackend.html).
+pub trait Input: fmt::Debug + Send {
+    /// Whether the input is currently high.
+    fn is_high(&self) -> bool;
+    /// Registers a callback to be invoked (possibly on another thread) with the new level whenever
+    /// the given edge occurs, replacing any previously-registered callback.
+    fn on_edge(&mut self, edge: Edge, callback: EdgeCallback) -> Result<(), Error>;
+}
+
+/// Trait representing a driver capable of providing access to GPIO pins.
+pub trait GpioBackend: fmt::Debug {
+    /// Acquires the given pin as an output.
+    fn output(&self, number: u16) -> Result<Box<dyn Output>, Error>;
+    /// Whether pins acquired from this backend are real (and so may only be held once in the
+    /// process).
+    ///
+    /// By default, backends are assumed to drive real hardware.
+    fn exclusive(&self) -> bool {
+        true
+    }
+    /// Acquires the given pin as an input.
+    ///
+    /// By default, backends do not support inputs.
+    fn input(&self, number: u16) -> Result<Box<dyn Input>, Error> {
+        let _ = number;
+        Err(Error::Unsupported("inputs"))
+    }
+    /// Acquires the given pin as an input with the given pull resistor configuration.
+    ///
+    /// By default, only inputs without pull resistors are supported.
+    fn input_with_pull(&self, number: u16, pull: Pull) -> Result<Box<dyn Input>, Error> {
+        match pull {
+            Pull::Off => self.input(number),
+            Pull::Up | Pull::Down => Err(Error::Unsupported("pull resistors")),
+        }
+    }
+    /// Acquires the given hardware PWM channel, which is routed to the given pin.
+    ///
+    /// Hardware PWM is generated by a dedicated peripheral rather than a thread toggling the pin,
+    /// so it does not suffer from jitter. By default, backends do not support hardware PWM.
+    fn hardware_pwm(&self, number: u16, channel: u8) -> Result<Box<dyn Output>, Error> {
+        let _ = (number, channel);
+        Err(Error::Unsupported("hardware PWM"))
+    }
+    /// Acquires a channel on a PCA9685 PWM expander at the given address on the given I²C bus.
+    ///
+    /// By default, backends do not support PWM expanders.
+    fn pca9685(&self, bus: u8, address: u16, channel: u8) -> Result<Box<dyn Output>, Error> {
+        let _ = (bus, address, channel);
+        Err(Error::Unsupported("PCA9685 PWM expanders"))
+    }
+}
+
+/// Returns the default backend for the enabled features.
+///
+/// If the `stub` feature is enabled, writes will be ignored. Otherwise, `rppal` will be used if
+/// available, falling back on the sysfs interface.
+pub fn default_backend() -> Box<dyn GpioBackend> {
+    #[cfg(feature = "stub")]
+    {
+        Box::new(Stub)
+    }
+    #[cfg(all(not(feature = "stub"), feature = "use_rppal"))]
+    {
+        Box::new(Rppal)
+    }
+    #[cfg(all(not(feature = "stub"), not(feature = "use_rppal")))]
+    {
+        Box::new(Sysfs)
+    }
+}
+
+/// GPIO operation error type.
+#[derive(Debug)]
+pub enum Error {
+    /// The model of the device couldn't be identified.
+    Model,
+    /// The given pin is in use or unavailable.
+    Unavailable(u8),
+    /// Permission was denied to access the device.
+    Permission(String),
+    /// An I/O error occured.
+    Io(IoError),
+    /// A thread panicked.
+    Panic,
+    /// The backend does not support the requested operation.
+    Unsupported(&'static str),
+    /// The backend reported an error of its own.
+    Backend(String),
+    /// The given pin (or PCA9685 channel) is already held by another device in this process.
+    AlreadyInUse(u16),
+    /// The PWM device (e.g. the SoC or an expander) has no channel with the given number.
+    InvalidChannel(u8),
+}
+
+impl From<IoError> for Error {
+    fn from(err: IoError) -> Self {
+        Self::Io(err)
+    }
+}
+
+#[cfg(feature = "use_rppal")]
+use rppal::{gpio::Error as RppalError, pwm::Error as RppalPwmError};
+#[cfg(feature = "use_rppal")]
+impl From<RppalError> for Error {
+    fn from(err: RppalError) -> Self {
+        match err {
+            RppalError::Io(err) => Self::Io(err),
+            RppalError::UnknownModel => Self::Model,
+            RppalError::PinNotAvailable(pin) => Self::Unavailable(pin),
+            RppalError::PermissionDenied(path) => Self::Permission(path),
+            RppalError::ThreadPanic => Self::Panic,
+        }
+    }
+}
+
+#[cfg(feature = "use_rppal")]
+impl From<RppalPwmError> for Error {
+    fn from(err: RppalPwmError) -> Self {
+        Self::Backend(err.to_string())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(_) => write!(f, "I/O error accessing the pin"),
+            Self::Model => write!(f, "Unknown Pi model/SoC"),
+            Self::Unavailable(pin) => write!(f, "Pin {} unavailable (in use or nonexistent)", pin),
+            Self::Permission(path) => write!(f, "Permission denied when accessing path {}", path),
+            Self::Panic => write!(f, "Thread panicked."),
+            Self::Unsupported(what) => write!(f, "Operation not supported by backend: {}", what),
+            Self::Backend(err) => write!(f, "Backend error: {}", err),
+            Self::AlreadyInUse(pin) => write!(f, "Pin {} is already in use by another device", pin),
+            Self::InvalidChannel(channel) => write!(f, "There is no PWM channel {}", channel),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+lazy_static! {
+    /// The GPIO pins and expander channels currently held, across all exclusive backends.
+    static ref CLAIMED: Mutex<HashSet<Resource>> = Mutex::new(HashSet::new());
+}
+
+/// Something a pin can be driven by, as held in the registry.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Resource {
+    /// A GPIO pin (by BCM number).
+    Gpio(u16),
+    /// A channel of the PCA9685 expander at the given bus and address.
+    Pca9685 { bus: u8, address: u16, channel: u8 },
+}
+
+impl Resource {
+    /// The number reported should the resource already be held.
+    fn number(self) -> u16 {
+        match self {
+            Self::Gpio(number) => number,
+            Self::Pca9685 { channel, .. } => channel.into(),
+        }
+    }
+}
+
+/// Holds a GPIO pin (or expander channel) in the process-wide registry, releasing it when dropped.
+#[derive(Debug)]
+struct Claim(Resource);
+
+impl Claim {
+    /// Claims the given resource, failing if it is already held.
+    fn new(resource: Resource) -> Result<Self, Error> {
+        let mut claimed = CLAIMED.lock().map_err(|_| Error::Panic)?;
+        if claimed.insert(resource) {
+            Ok(Self(resource))
+        } else {
+            Err(Error::AlreadyInUse(resource.number()))
+        }
+    }
+    /// Claims the given resource if the backend is exclusive.
+    fn for_backend(backend: &dyn GpioBackend, resource: Resource) -> Result<Option<Self>, Error> {
+        if backend.exclusive() {
+            Self::new(resource).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl Drop for Claim {
+    fn drop(&mut self) {
+        if let Ok(mut claimed) = CLAIMED.lock() {
+            claimed.remove(&self.0);
+        }
+    }
+}
+
+/// Represents a GPIO pin.
+#[derive(Debug)]
+pub struct Pin {
+    pub(crate) number: u16,
+    output: Box<dyn Output>,
+    /// Whether the pin is active-low (so that "high" drives the physical pin low).
+    inverted: bool,
+    /// The pin's entry in the registry (released after the output is dropped).
+    _claim: Option<Claim>,
+}
+
+impl Pin {
+    /// Attempts to create an output Pin struct on the given pin number using the default backend.
+    pub fn try_new(number: u16) -> Result<Self, Error> {
+        Self::with_backend(&*default_backend(), number)
+    }
+    /// Attempts to create an output Pin struct on the given pin number using the given backend.
+    ///
+    /// This fails with [`Error::AlreadyInUse`](enum.Error.html#variant.AlreadyInUse) if the pin is
+    /// already held.
+    pub fn with_backend(backend: &dyn GpioBackend, number: u16) -> Result<Self, Error> {
+        log::trace!("Acquiring pin {} from backend {:?}", number, backend);
+        let claim = Claim::for_backend(backend, Resource::Gpio(number))?;
+        Ok(Self {
+            output: backend.output(number)?,
+            number,
+            inverted: false,
+            _claim: claim,
+        })
+    }
+    /// Attempts to create an output Pin struct driven by the given hardware PWM channel.
+    ///
+    /// The channel must be routed to the given pin number.
+    pub fn hardware_pwm(
+        backend: &dyn GpioBackend,
+        number: u16,
+        channel: u8,
+    ) -> Result<Self, Error> {
+        log::trace!(
+            "Acquiring PWM channel {} (pin {}) from backend {:?}",
+            channel,
+            number,
+            backend
+        );
+        let claim = Claim::for_backend(backend, Resource::Gpio(number))?;
+        Ok(Self {
+            output: backend.hardware_pwm(number, channel)?,
+            number,
+            inverted: false,
+            _claim: claim,
+        })
+    }
+    /// Attempts to create an output Pin struct driven by a channel of a PCA9685 PWM expander.
+    ///
+    /// The channel number is used as the pin number. Expander channels are held in the registry
+    /// apart from GPIO pins (so that channel 4 doesn't conflict with GPIO 4), and this fails with
+    /// [`Error::AlreadyInUse`](enum.Error.html#variant.AlreadyInUse) if the channel is already
+    /// held.
+    pub fn pca9685(
+        backend: &dyn GpioBackend,
+        bus: u8,
+        address: u16,
+        channel: u8,
+    ) -> Result<Self, Error> {
+        log::trace!(
+            "Acquiring PCA9685 channel {} (bus {}, address {:#x}) from backend {:?}",
+            channel,
+            bus,
+            address,
+            backend
+        );
+        let resource = Resource::Pca9685 {
+            bus,
+            address,
+            channel,
+        };
+        let claim = Claim::for_backend(backend, resource)?;
+        Ok(Self {
+            output: backend.pca9685(bus, address, channel)?,
+            number: channel.into(),
+            inverted: false,
+            _claim: claim,
+        })
+    }
+    /// Marks the pin as active-low (or not).
+    ///
+    /// An inverted pin drives the physical pin low when set high (and vice versa), and its PWM
+    /// duty cycle is complemented, so that callers need not know about the inversion.
+    pub fn with_inversion(mut self, inverted: bool) -> Self {
+        self.inverted = inverted;
+        self
+    }
+    /// Whether the pin is active-low.
+    pub fn is_inverted(&self) -> bool {
+        self.inverted
+    }
+    /// Sets the pin to the desired (logical) state.
+    pub fn set(&mut self, high: bool) -> Result<(), Error> {
+        self.output.set(high != self.inverted)
+    }
+    /// Sets the pin high.
+    pub fn set_high(&mut self) -> Result<(), Error> {
+        self.set(true)
+    }
+    /// Sets the pin low.
+    pub fn set_low(&mut self) -> Result<(), Error> {
+        self.set(false)
+    }
+}
+
+/// Represents a GPIO input pin.
+#[derive(Debug)]
+pub struct InputPin {
+    number: u16,
+    input: Box<dyn Input>,
+    /// The pin's entry in the registry (released after the input is dropped).
+    _claim: Option<Claim>,
+}
+
+impl InputPin {
+    /// Attempts to acquire the given pin as an input (with the given pull resistor configuration)
+    /// using the given backend.
+    ///
+    /// Like outputs, inputs are held in the registry while acquired.
+    pub fn with_backend(backend: &dyn GpioBackend, number: u16, pull: Pull) -> Result<Self, Error> {
+        log::trace!(
+            "Acquiring input pin {} ({:?}) from backend {:?}",
+            number,
+            pull,
+            backend
+        );
+        let claim = Claim::for_backend(backend, Resource::Gpio(number))?;
+        Ok(Self {
+            input: backend.input_with_pull(number, pull)?,
+            number,
+            _claim: claim,
+        })
+    }
+    /// The number of the pin.
+    pub fn number(&self) -> u16 {
+        self.number
+    }
+    /// Returns a stream of the given edges as messages, replacing any registered callback.
+    ///
+    /// The stream can be attached to an actor with `AsyncContext::add_message_stream`, in which
+    /// case the actor should handle [`EdgeEvent`](struct.EdgeEvent.html)s.
+    pub fn events(&mut self, edge: Edge) -> Result<UnboundedReceiver<EdgeEvent>, Error> {
+        let (tx, rx) = mpsc::unbounded();
+        let pin = self.number;
+        self.on_edge(
+            edge,
+            Box::new(move |high| {
+                // The receiver going away just means nobody is listening anymore.
+                let _ = tx.unbounded_send(EdgeEvent { pin, high });
+            }),
+        )?;
+        Ok(rx)
+    }
+}
+
+impl Input for InputPin {
+    fn is_high(&self) -> bool {
+        self.input.is_high()
+    }
+    fn on_edge(&mut self, edge: Edge, callback: EdgeCallback) -> Result<(), Error> {
+        self.input.on_edge(edge, callback)
+    }
+}
+
+impl Out for Pin {
+    fn set_high(&mut self) -> Result<(), Error> {
+        self.set(true)
+    }
+    fn set_low(&mut self) -> Result<(), Error> {
+        self.set(false)
+    }
+}
+
+impl Pwm for Pin {
+    fn set_pwm(&mut self, period: Duration, pulse_width: Duration) -> Result<(), Error> {
+        let pulse_width = if self.inverted {
+            period.checked_sub(pulse_width).unwrap_or_default()
+        } else {
+            pulse_width
+        };
+        self.output.set_pwm(period, pulse_width)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,161 @@
+//! Support for the PCA9685 16-channel, 12-bit I²C PWM controller.
+//!
+//! Each board is shared between all of the channels acquired from it; boards are opened lazily
+//! and identified by their bus and address.
+use super::{Error, Out, Output, Pwm};
+use lazy_static::lazy_static;
+use rppal::i2c::{Error as I2cError, I2c};
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+/// Mode register 1.
+const MODE1: u8 = 0x00;
+/// The register controlling the output frequency.
+const PRESCALE: u8 = 0xFE;
+/// The first of the (four) registers controlling channel 0.
+const LED0_ON_L: u8 = 0x06;
+/// MODE1 bit which puts the oscillator to sleep (required to change the prescaler).
+const SLEEP: u8 = 0x10;
+/// MODE1 bit enabling register auto-increment (required for block writes).
+const AUTO_INCREMENT: u8 = 0x20;
+/// MODE1 bit which restarts the PWM channels after sleep.
+const RESTART: u8 = 0x80;
+/// The frequency of the internal oscillator, in hertz.
+const OSCILLATOR: f64 = 25_000_000.0;
+/// The number of steps in each period.
+const STEPS: u16 = 4096;
+/// The "full on"/"full off" bit of the ON/OFF registers.
+const FULL: u16 = 0x1000;
+
+impl From<I2cError> for Error {
+    fn from(err: I2cError) -> Self {
+        Self::Backend(err.to_string())
+    }
+}
+
+lazy_static! {
+    static ref BOARDS: Mutex<HashMap<(u8, u16), Arc<Mutex<Board>>>> = Mutex::new(HashMap::new());
+}
+
+/// A single PCA9685 board.
+struct Board {
+    i2c: I2c,
+    /// The currently-configured period, if it has been set.
+    period: Option<Duration>,
+}
+
+impl fmt::Debug for Board {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Board")
+            .field("period", &self.period)
+            .finish()
+    }
+}
+
+impl Board {
+    fn open(bus: u8, address: u16) -> Result<Self, Error> {
+        log::debug!(
+            "Opening PCA9685 at address {:#x} on I²C bus {}",
+            address,
+            bus
+        );
+        let mut i2c = I2c::with_bus(bus)?;
+        i2c.set_slave_address(address)?;
+        i2c.smbus_write_byte(MODE1, AUTO_INCREMENT)?;
+        Ok(Self { i2c, period: None })
+    }
+    /// Sets the period shared by all channels on the board.
+    fn set_period(&mut self, period: Duration) -> Result<(), Error> {
+        if self.period == Some(period) {
+            return Ok(());
+        }
+        let secs = period.as_secs() as f64 + f64::from(period.subsec_nanos()) * 1e-9;
+        let prescale = (OSCILLATOR * secs / f64::from(STEPS)).round() - 1.0;
+        // The hardware enforces a minimum prescale value of 3.
+        let prescale = prescale.max(3.0).min(255.0) as u8;
+        log::trace!("Setting PCA9685 prescale to {} ({:?})", prescale, period);
+        let mode = self.i2c.smbus_read_byte(MODE1)? & !RESTART;
+        self.i2c.smbus_write_byte(MODE1, mode | SLEEP)?;
+        self.i2c.smbus_write_byte(PRESCALE, prescale)?;
+        self.i2c.smbus_write_byte(MODE1, mode)?;
+        // The oscillator takes up to 500 µs to stabilize.
+        thread::sleep(Duration::from_micros(500));
+        self.i2c.smbus_write_byte(MODE1, mode | RESTART)?;
+        self.period = Some(period);
+        Ok(())
+    }
+    /// Sets the ON and OFF counts of the given channel.
+    fn set_channel(&mut self, channel: u8, on: u16, off: u16) -> Result<(), Error> {
+        let register = LED0_ON_L + 4 * channel;
+        let bytes = [on as u8, (on >> 8) as u8, off as u8, (off >> 8) as u8];
+        self.i2c.block_write(register, &bytes)?;
+        Ok(())
+    }
+}
+
+/// Acquires a channel on the PCA9685 at the given address on the given I²C bus.
+pub(crate) fn channel(bus: u8, address: u16, channel: u8) -> Result<Box<dyn Output>, Error> {
+    if channel >= 16 {
+        return Err(Error::InvalidChannel(channel));
+    }
+    let mut boards = BOARDS.lock().map_err(|_| Error::Panic)?;
+    let board = match boards.get(&(bus, address)) {
+        Some(board) => Arc::clone(board),
+        None => {
+            let board = Arc::new(Mutex::new(Board::open(bus, address)?));
+            boards.insert((bus, address), Arc::clone(&board));
+            board
+        }
+    };
+    Ok(Box::new(Channel { board, channel }))
+}
+
+/// A single output channel on a PCA9685.
+#[derive(Debug)]
+struct Channel {
+    board: Arc<Mutex<Board>>,
+    channel: u8,
+}
+
+impl Channel {
+    fn write(&self, on: u16, off: u16) -> Result<(), Error> {
+        let mut board = self.board.lock().map_err(|_| Error::Panic)?;
+        board.set_channel(self.channel, on, off)
+    }
+}
+
+impl Pwm for Channel {
+    fn set_pwm(&mut self, period: Duration, pulse_width: Duration) -> Result<(), Error> {
+        if pulse_width == Duration::new(0, 0) {
+            return self.write(0, FULL);
+        }
+        {
+            let mut board = self.board.lock().map_err(|_| Error::Panic)?;
+            board.set_period(period)?;
+        }
+        let nanos = |d: Duration| d.as_secs() as f64 * 1e9 + f64::from(d.subsec_nanos());
+        let ratio = nanos(pulse_width) / nanos(period);
+        let off = (ratio * f64::from(STEPS)).round().min(f64::from(STEPS - 1)) as u16;
+        log::trace!(
+            "Setting PCA9685 channel {} pulse width to {:?} ({} steps)",
+            self.channel,
+            pulse_width,
+            off
+        );
+        self.write(0, off)
+    }
+}
+
+impl Out for Channel {
+    fn set_high(&mut self) -> Result<(), Error> {
+        self.write(FULL, 0)
+    }
+    fn set_low(&mut self) -> Result<(), Error> {
+        self.write(0, FULL)
+    }
+}
@@ -0,0 +1,194 @@
+//! Remote GPIO access through the pigpio daemon's socket interface.
+//!
+//! This allows the coordinator to run on a workstation while the Pi only runs `pigpiod`.
+use super::{Error, GpioBackend, Out, Output, Pwm};
+use std::{
+    io::{Error as IoError, ErrorKind, Read, Write},
+    net::{Shutdown, TcpStream, ToSocketAddrs},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// The default port `pigpiod` listens on.
+pub const DEFAULT_PORT: u16 = 8888;
+/// How long to wait on the daemon (to connect, or to send or answer a command) before giving up.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Sets the mode of a GPIO.
+const MODES: u32 = 0;
+/// Writes a level to a GPIO.
+const WRITE: u32 = 4;
+/// Sets the PWM duty cycle of a GPIO.
+const PWM: u32 = 5;
+/// Sets the PWM range of a GPIO.
+const PRS: u32 = 6;
+/// Sets the PWM frequency of a GPIO.
+const PFS: u32 = 7;
+/// Starts hardware PWM on a GPIO.
+const HP: u32 = 86;
+/// The mode value for outputs.
+const OUTPUT: u32 = 1;
+/// The largest PWM range pigpio accepts.
+const MAX_RANGE: u32 = 40_000;
+/// The smallest PWM range pigpio accepts.
+const MIN_RANGE: u32 = 25;
+/// The duty cycle corresponding to "fully on" for hardware PWM.
+const HARDWARE_RANGE: u64 = 1_000_000;
+
+fn micros(duration: Duration) -> u64 {
+    duration.as_secs() * 1_000_000 + u64::from(duration.subsec_micros())
+}
+
+/// A connection to a pigpio daemon.
+#[derive(Debug)]
+struct Connection {
+    stream: TcpStream,
+}
+
+impl Connection {
+    /// Sends a command and returns the daemon's (non-negative) response.
+    ///
+    /// If the daemon can't be reached in time, the connection is closed (since a late response
+    /// would be taken for the answer to the next command), so later commands fail too.
+    fn command(&mut self, command: u32, p1: u32, p2: u32, extension: &[u8]) -> Result<u32, Error> {
+        let mut request = Vec::with_capacity(16 + extension.len());
+        for word in &[command, p1, p2, extension.len() as u32] {
+            request.extend_from_slice(&word.to_le_bytes());
+        }
+        request.extend_from_slice(extension);
+        let mut response = [0; 16];
+        let exchange = self
+            .stream
+            .write_all(&request)
+            .and_then(|_| self.stream.read_exact(&mut response));
+        if let Err(err) = exchange {
+            let _ = self.stream.shutdown(Shutdown::Both);
+            return Err(Error::Io(err));
+        }
+        let mut result = [0; 4];
+        result.copy_from_slice(&response[12..]);
+        let result = i32::from_le_bytes(result);
+        if result < 0 {
+            Err(Error::Backend(format!(
+                "pigpio command {} failed with error {}",
+                command, result
+            )))
+        } else {
+            Ok(result as u32)
+        }
+    }
+}
+
+/// Backend driving pins on a remote Pi through `pigpiod`.
+#[derive(Clone, Debug)]
+pub struct Pigpio {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl Pigpio {
+    /// Connects to the pigpio daemon at the given host and port.
+    ///
+    /// Connecting, and each command sent afterward, fails with an I/O error if the daemon doesn't
+    /// respond within a few seconds.
+    pub fn connect(host: &str, port: u16) -> Result<Self, Error> {
+        log::info!("Connecting to pigpiod at {}:{}", host, port);
+        let mut last = None;
+        let mut stream = None;
+        for address in (host, port).to_socket_addrs()? {
+            match TcpStream::connect_timeout(&address, TIMEOUT) {
+                Ok(connected) => {
+                    stream = Some(connected);
+                    break;
+                }
+                Err(err) => last = Some(err),
+            }
+        }
+        let stream = match (stream, last) {
+            (Some(stream), _) => stream,
+            (None, Some(err)) => return Err(Error::Io(err)),
+            (None, None) => {
+                let message = format!("{} has no addresses", host);
+                return Err(Error::Io(IoError::new(ErrorKind::NotFound, message)));
+            }
+        };
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        stream.set_nodelay(true)?;
+        Ok(Self {
+            connection: Arc::new(Mutex::new(Connection { stream })),
+        })
+    }
+    fn command(&self, command: u32, p1: u32, p2: u32, extension: &[u8]) -> Result<u32, Error> {
+        let mut connection = self.connection.lock().map_err(|_| Error::Panic)?;
+        connection.command(command, p1, p2, extension)
+    }
+}
+
+impl GpioBackend for Pigpio {
+    fn output(&self, number: u16) -> Result<Box<dyn Output>, Error> {
+        self.command(MODES, number.into(), OUTPUT, &[])?;
+        Ok(Box::new(PigpioPin {
+            backend: self.clone(),
+            number,
+            hardware: false,
+        }))
+    }
+    fn hardware_pwm(&self, number: u16, _: u8) -> Result<Box<dyn Output>, Error> {
+        // pigpio selects the channel from the pin number itself.
+        self.command(MODES, number.into(), OUTPUT, &[])?;
+        Ok(Box::new(PigpioPin {
+            backend: self.clone(),
+            number,
+            hardware: true,
+        }))
+    }
+}
+
+/// A pin on a remote Pi.
+#[derive(Debug)]
+struct PigpioPin {
+    backend: Pigpio,
+    number: u16,
+    /// Whether PWM should be generated by the hardware peripheral.
+    hardware: bool,
+}
+
+impl PigpioPin {
+    fn write(&self, level: u32) -> Result<(), Error> {
+        self.backend
+            .command(WRITE, self.number.into(), level, &[])
+            .map(|_| ())
+    }
+}
+
+impl Pwm for PigpioPin {
+    fn set_pwm(&mut self, period: Duration, pulse_width: Duration) -> Result<(), Error> {
+        let gpio = u32::from(self.number);
+        let (period, pulse_width) = (micros(period).max(1), micros(pulse_width));
+        if self.hardware {
+            let frequency = (1_000_000 / period) as u32;
+            let duty = (pulse_width * HARDWARE_RANGE / period).min(HARDWARE_RANGE) as u32;
+            self.backend
+                .command(HP, gpio, frequency, &duty.to_le_bytes())?;
+        } else if pulse_width == 0 {
+            self.backend.command(PWM, gpio, 0, &[])?;
+        } else {
+            let frequency = (1_000_000 / period).max(1) as u32;
+            let range = (period as u32).max(MIN_RANGE).min(MAX_RANGE);
+            let duty = (pulse_width * u64::from(range) / period).min(range.into()) as u32;
+            self.backend.command(PFS, gpio, frequency, &[])?;
+            self.backend.command(PRS, gpio, range, &[])?;
+            self.backend.command(PWM, gpio, duty, &[])?;
+        }
+        Ok(())
+    }
+}
+
+impl Out for PigpioPin {
+    fn set_high(&mut self) -> Result<(), Error> {
+        self.write(1)
+    }
+    fn set_low(&mut self) -> Result<(), Error> {
+        self.write(0)
+    }
+}
@@ -0,0 +1,333 @@
+//! GPIO access through a microcontroller (e.g. an Arduino or RP2040) on a serial port.
+//!
+//! This allows the coordinator to run on a host without GPIO (such as a lab PC) while the
+//! microcontroller generates the signals, PWM included, in real time.
+//!
+//! ## Protocol
+//! Commands and replies are lines of ASCII text, with fields separated by spaces. The host sends:
+//!
+//! - `V`, to which the firmware replies with `OK <version>` (currently `1`);
+//! - `O <pin>`, configuring the pin as an output;
+//! - `I <pin> <off|up|down>`, configuring the pin as an input with the given pull resistor;
+//! - `W <pin> <0|1>`, setting an output low or high;
+//! - `P <pin> <period> <pulse width>`, starting PWM on an output (both in microseconds, where a
+//!   pulse width of zero stops it);
+//! - `R <pin>`, to which the firmware replies with `OK <0|1>`, the level of an input; and
+//! - `N <pin>`, asking the firmware to report changes in the level of an input.
+//!
+//! The firmware answers each command in order with `OK` (possibly followed by a value) or
+//! `ERR <message>`. Once asked to, it reports level changes as `C <pin> <0|1>` whenever they
+//! happen, in between replies.
+use super::{Edge, EdgeCallback, Error, GpioBackend, Input, Out, Output, Pull, Pwm};
+use serialport::{SerialPort, SerialPortSettings};
+use std::{
+    collections::HashMap,
+    fmt,
+    io::{BufRead, BufReader, ErrorKind, Write},
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+/// The default baud rate.
+pub const DEFAULT_BAUD: u32 = 115_200;
+
+/// The version of the protocol spoken.
+const VERSION: &str = "1";
+/// How long the firmware has to reply to a command.
+const TIMEOUT: Duration = Duration::from_secs(1);
+/// How many times the firmware is asked for its version before giving up (boards which reset when
+/// the port is opened take a moment to boot).
+const ATTEMPTS: u32 = 5;
+
+fn micros(duration: Duration) -> u64 {
+    duration.as_secs() * 1_000_000 + u64::from(duration.subsec_micros())
+}
+
+/// A line sent by the firmware.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Line {
+    /// The command succeeded (with the given value, if any).
+    Ok(Option<String>),
+    /// The command failed for the given reason.
+    Err(String),
+    /// The level of an input changed.
+    Change {
+        /// The number of the pin.
+        pin: u16,
+        /// Whether the input is now high.
+        high: bool,
+    },
+}
+
+impl Line {
+    /// Parses a line sent by the firmware.
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        let (kind, rest) = match line.find(' ') {
+            Some(index) => (&line[..index], Some(line[index + 1..].trim())),
+            None => (line, None),
+        };
+        match (kind, rest) {
+            ("OK", rest) => Some(Self::Ok(rest.map(String::from))),
+            ("ERR", rest) => Some(Self::Err(rest.unwrap_or("unknown error").into())),
+            ("C", Some(rest)) => {
+                let mut fields = rest.split_whitespace();
+                let pin = fields.next()?.parse().ok()?;
+                let high = match fields.next()? {
+                    "0" => false,
+                    "1" => true,
+                    _ => return None,
+                };
+                Some(Self::Change { pin, high })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The callbacks registered for changes in each input's level.
+type Callbacks = Arc<Mutex<HashMap<u16, (Edge, EdgeCallback)>>>;
+
+/// Reads lines from the firmware, passing replies on and invoking callbacks for level changes.
+fn listen(port: Box<dyn SerialPort>, replies: Sender<Line>, callbacks: Callbacks) {
+    let mut reader = BufReader::new(port);
+    // Reads can time out partway through a line, so the line is kept until it's complete.
+    let mut buffer = vec![];
+    loop {
+        match reader.read_until(b'\n', &mut buffer) {
+            Ok(0) => break,
+            Ok(_) if buffer.ends_with(b"\n") => {}
+            Ok(_) => continue,
+            Err(ref err) if err.kind() == ErrorKind::TimedOut => continue,
+            Err(err) => {
+                log::error!("Lost the serial bridge: {}", err);
+                break;
+            }
+        }
+        let line = String::from_utf8_lossy(&buffer).into_owned();
+        buffer.clear();
+        match Line::parse(&line) {
+            Some(Line::Change { pin, high }) => {
+                if let Ok(mut callbacks) = callbacks.lock() {
+                    if let Some((edge, callback)) = callbacks.get_mut(&pin) {
+                        if edge.matches(high) {
+                            callback(high);
+                        }
+                    }
+                }
+            }
+            Some(line) => {
+                if replies.send(line).is_err() {
+                    break;
+                }
+            }
+            None => log::warn!("Ignoring unexpected line from serial bridge: {:?}", line),
+        }
+    }
+}
+
+/// A connection to the firmware.
+struct Link {
+    port: Box<dyn SerialPort>,
+    replies: Receiver<Line>,
+}
+
+impl Link {
+    /// Sends a command and returns the firmware's reply (if it included a value).
+    fn command(&mut self, command: &str) -> Result<Option<String>, Error> {
+        // Replies which arrive after their command timed out would be mistaken for later ones.
+        while self.replies.try_recv().is_ok() {}
+        self.port.write_all(format!("{}\n", command).as_bytes())?;
+        self.port.flush()?;
+        match self.replies.recv_timeout(TIMEOUT) {
+            Ok(Line::Ok(value)) => Ok(value),
+            Ok(Line::Err(err)) => Err(Error::Backend(format!(
+                "serial bridge command {:?} failed: {}",
+                command, err
+            ))),
+            Ok(Line::Change { .. }) => unreachable!("level changes aren't passed on as replies"),
+            Err(RecvTimeoutError::Timeout) => Err(Error::Backend(format!(
+                "serial bridge didn't answer {:?}",
+                command
+            ))),
+            Err(RecvTimeoutError::Disconnected) => {
+                Err(Error::Backend("serial bridge disconnected".into()))
+            }
+        }
+    }
+}
+
+/// Backend driving pins on a microcontroller connected over a serial port.
+#[derive(Clone)]
+pub struct Serial {
+    link: Arc<Mutex<Link>>,
+    callbacks: Callbacks,
+}
+
+impl fmt::Debug for Serial {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Serial").finish()
+    }
+}
+
+impl Serial {
+    /// Opens the given serial port at the given baud rate and checks that the firmware speaks the
+    /// same protocol.
+    pub fn open(path: &str, baud: u32) -> Result<Self, Error> {
+        log::info!("Opening serial bridge on {} at {} baud", path, baud);
+        let settings = SerialPortSettings {
+            baud_rate: baud,
+            timeout: TIMEOUT,
+            ..SerialPortSettings::default()
+        };
+        let port = serialport::open_with_settings(path, &settings)
+            .map_err(|err| Error::Backend(err.to_string()))?;
+        let reader = port
+            .try_clone()
+            .map_err(|err| Error::Backend(err.to_string()))?;
+        let (sender, replies) = mpsc::channel();
+        let callbacks = Callbacks::default();
+        let listening = Arc::clone(&callbacks);
+        thread::Builder::new()
+            .name("serial-bridge".into())
+            .spawn(move || listen(reader, sender, listening))?;
+        let backend = Self {
+            link: Arc::new(Mutex::new(Link { port, replies })),
+            callbacks,
+        };
+        let mut attempts = 0;
+        let version = loop {
+            attempts += 1;
+            match backend.command("V") {
+                Ok(version) => break version,
+                Err(_) if attempts < ATTEMPTS => continue,
+                Err(err) => return Err(err),
+            }
+        };
+        match version.as_ref().map(String::as_str) {
+            Some(VERSION) => Ok(backend),
+            other => Err(Error::Backend(format!(
+                "serial bridge speaks protocol version {:?} (expected {})",
+                other, VERSION
+            ))),
+        }
+    }
+    fn command(&self, command: &str) -> Result<Option<String>, Error> {
+        let mut link = self.link.lock().map_err(|_| Error::Panic)?;
+        link.command(command)
+    }
+}
+
+impl GpioBackend for Serial {
+    fn output(&self, number: u16) -> Result<Box<dyn Output>, Error> {
+        self.command(&format!("O {}", number))?;
+        Ok(Box::new(SerialPin {
+            backend: self.clone(),
+            number,
+        }))
+    }
+    fn input_with_pull(&self, number: u16, pull: Pull) -> Result<Box<dyn Input>, Error> {
+        let pull = match pull {
+            Pull::Off => "off",
+            Pull::Up => "up",
+            Pull::Down => "down",
+        };
+        self.command(&format!("I {} {}", number, pull))?;
+        Ok(Box::new(SerialPin {
+            backend: self.clone(),
+            number,
+        }))
+    }
+    fn input(&self, number: u16) -> Result<Box<dyn Input>, Error> {
+        self.input_with_pull(number, Pull::Off)
+    }
+    fn hardware_pwm(&self, number: u16, _: u8) -> Result<Box<dyn Output>, Error> {
+        // All PWM is generated by the microcontroller.
+        self.output(number)
+    }
+}
+
+/// A pin on the microcontroller.
+#[derive(Debug)]
+struct SerialPin {
+    backend: Serial,
+    number: u16,
+}
+
+impl SerialPin {
+    fn write(&self, level: u8) -> Result<(), Error> {
+        self.backend
+            .command(&format!("W {} {}", self.number, level))
+            .map(|_| ())
+    }
+}
+
+impl Pwm for SerialPin {
+    fn set_pwm(&mut self, period: Duration, pulse_width: Duration) -> Result<(), Error> {
+        self.backend.command(&format!(
+            "P {} {} {}",
+            self.number,
+            micros(period),
+            micros(pulse_width)
+        ))?;
+        Ok(())
+    }
+}
+
+impl Out for SerialPin {
+    fn set_high(&mut self) -> Result<(), Error> {
+        self.write(1)
+    }
+    fn set_low(&mut self) -> Result<(), Error> {
+        self.write(0)
+    }
+}
+
+impl Input for SerialPin {
+    fn is_high(&self) -> bool {
+        match self.backend.command(&format!("R {}", self.number)) {
+            Ok(Some(ref level)) if level == "1" => true,
+            Ok(_) => false,
+            Err(err) => {
+                log::error!("Failed to read bridged pin {}: {}", self.number, err);
+                false
+            }
+        }
+    }
+    fn on_edge(&mut self, edge: Edge, callback: EdgeCallback) -> Result<(), Error> {
+        self.backend
+            .callbacks
+            .lock()
+            .map_err(|_| Error::Panic)?
+            .insert(self.number, (edge, callback));
+        self.backend.command(&format!("N {}", self.number))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn parse() {
+        assert_eq!(Line::parse("OK\r\n"), Some(Line::Ok(None)));
+        assert_eq!(Line::parse("OK 1\n"), Some(Line::Ok(Some("1".into()))));
+        assert_eq!(
+            Line::parse("ERR no such pin\n"),
+            Some(Line::Err("no such pin".into()))
+        );
+        assert_eq!(
+            Line::parse("C 17 1\n"),
+            Some(Line::Change {
+                pin: 17,
+                high: true
+            })
+        );
+        assert_eq!(Line::parse("C 17 2\n"), None);
+        assert_eq!(Line::parse("hello\n"), None);
+    }
+}
@@ -0,0 +1,57 @@
+//! GPIO access through the (deprecated, but ubiquitous) Linux sysfs interface.
+use super::{Error, GpioBackend, Out, Output, Pwm};
+use std::{fs, path::PathBuf, time::Duration};
+
+/// The root of the sysfs GPIO interface.
+const ROOT: &str = "/sys/class/gpio";
+
+/// Backend using the Linux sysfs GPIO interface (`/sys/class/gpio`).
+///
+/// ## Notes
+/// The sysfs interface has no notion of PWM, so pins acquired through this backend cannot drive
+/// motors.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sysfs;
+
+impl GpioBackend for Sysfs {
+    fn output(&self, number: u16) -> Result<Box<dyn Output>, Error> {
+        let dir = PathBuf::from(format!("{}/gpio{}", ROOT, number));
+        if !dir.exists() {
+            fs::write(format!("{}/export", ROOT), number.to_string())?;
+        }
+        fs::write(dir.join("direction"), "out")?;
+        Ok(Box::new(SysfsPin {
+            number,
+            value: dir.join("value"),
+        }))
+    }
+}
+
+/// An output pin exported through sysfs.
+#[derive(Debug)]
+struct SysfsPin {
+    number: u16,
+    /// The path to the pin's `value` file.
+    value: PathBuf,
+}
+
+impl SysfsPin {
+    fn write(&self, value: &str) -> Result<(), Error> {
+        Ok(fs::write(&self.value, value)?)
+    }
+}
+
+impl Pwm for SysfsPin {
+    fn set_pwm(&mut self, _: Duration, _: Duration) -> Result<(), Error> {
+        Err(Error::Unsupported("PWM over sysfs"))
+    }
+}
+
+impl Out for SysfsPin {
+    fn set_high(&mut self) -> Result<(), Error> {
+        self.write("1")
+    }
+    fn set_low(&mut self) -> Result<(), Error> {
+        self.write("0")
+    }
+}
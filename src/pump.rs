@@ -1,9 +1,18 @@
 //! Pump management.
 use std::ops::Not;
 use std::thread;
+use std::time::Duration;
+
+use actix_web::actix::Recipient;
+use futures::{future, sync::oneshot, Future};
+use uom::si::f64::{Volume, VolumeRate};
+use uom::si::time::second;
+use uom::si::volume_rate::milliliter_per_second;
 
 use crate::actix::*;
-use crate::pin::{Error as PinError, Pin};
+use crate::device::{Device, Error, Failed, Supervisor};
+use crate::pin::{GpioBackend, Pin, Pwm};
+use crate::PumpConfig;
 
 /// Messages that can be sent to the pump to change its direction or turn it off.
 #[derive(Clone, Copy, Debug)]
@@ -14,6 +23,14 @@ pub enum Message {
     Drain,
     /// Asks the pump to stop.
     Stop,
+    /// Asks the pump to change its speed (as a fraction of full speed, from 0 to 1).
+    SetSpeed(f64),
+    /// Asks the pump to perfuse the given volume and then stop.
+    ///
+    /// The response is delivered once the pump has stopped. Any other command but a change of
+    /// speed interrupts the dispense (a change of speed leaves it running for as long as it was
+    /// going to).
+    Dispense(Volume),
 }
 
 impl ActixMessage for Message {
@@ -21,7 +38,9 @@ impl ActixMessage for Message {
 }
 
 /// The direction of a pump.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "use_serde", serde(rename_all = "lowercase"))]
 pub enum Direction {
     /// The pump should run in the forward direction (toward the sample), perfusing any sample.
     Forward,
@@ -39,8 +58,11 @@ impl Not for Direction {
     }
 }
 
+/// The number of increments used when ramping the pump up or down.
+const RAMP_STEPS: u32 = 10;
+
 /// Pump movement result type.
-pub type Result<T> = std::result::Result<T, PinError>;
+pub type Result<T> = std::result::Result<T, Error>;
 
 /// Represents a pump.
 ///
@@ -69,6 +91,42 @@ pub struct Pump {
     direction: Option<Direction>,
     /// Whether directions should be reversed.
     pub invert: bool,
+    /// The current speed, as a fraction of full speed.
+    speed: f64,
+    /// The duty cycles corresponding to zero and full speed, respectively.
+    duty: [f64; 2],
+    /// The period of the PWM signal used for speed control.
+    pwm_period: Duration,
+    /// The pin currently being driven with a PWM signal, if any.
+    modulated: Option<usize>,
+    /// The duty cycle currently applied to the driven (top) pin, if any.
+    applied: f64,
+    /// The calibrated flow rate at full speed, if known.
+    flow_rate: Option<VolumeRate>,
+    /// The handle to the scheduled stop of an in-progress dispense, if any.
+    dispensing: Option<SpawnHandle>,
+    /// How long to take ramping the pump up to speed (and back down), if at all.
+    ramp: Option<Duration>,
+    /// The handle to the in-progress ramp (or pause before reversing), if any.
+    ramping: Option<SpawnHandle>,
+    /// Whether the in-progress ramp is bringing the pump up to speed (rather than winding it down).
+    accelerating: bool,
+    /// Where failures outside of any command (e.g. stopping after a dispense) are reported.
+    supervisor: Supervisor,
+}
+
+/// Converts a (non-negative) number of seconds to a duration.
+pub(crate) fn duration_from_secs(secs: f64) -> Duration {
+    let secs = secs.max(0.0);
+    let nanos = ((secs - secs.floor()) * 1.0_E9).floor() as u32;
+    Duration::new(secs.floor() as u64, nanos)
+}
+
+/// Scales a duration by the given factor.
+fn scale(duration: Duration, factor: f64) -> Duration {
+    let nanos = duration.as_secs() as f64 * 1e9 + f64::from(duration.subsec_nanos());
+    let nanos = (nanos * factor).max(0.0) as u64;
+    Duration::new(nanos / 1_000_000_000, (nanos % 1_000_000_000) as u32)
 }
 
 impl PartialEq for Pump {
@@ -91,11 +149,49 @@ impl Pump {
             Pin::try_new(pins[2])?,
             Pin::try_new(pins[3])?,
         ];
-        Ok(Self {
+        Ok(Self::with_pins(pins))
+    }
+    /// Attempts to create a new pump as described by the given configuration, acquiring its pins
+    /// from the given backend.
+    pub fn try_from_config(config: &PumpConfig, backend: &dyn GpioBackend) -> Result<Self> {
+        let pin = |i: usize| {
+            Pin::with_backend(backend, config.pins[i])
+                .map(|pin| pin.with_inversion(config.inverted[i]))
+        };
+        let mut pump = Self::with_pins([pin(0)?, pin(1)?, pin(2)?, pin(3)?]);
+        pump.invert = config.invert;
+        pump.duty = config.duty;
+        pump.pwm_period = config.pwm_period;
+        pump.flow_rate = config
+            .flow_rate
+            .map(|rate| VolumeRate::new::<milliliter_per_second>(rate.millilitres_per_second()));
+        pump.ramp = config.ramp;
+        Ok(pump)
+    }
+    /// Creates a new pump using already-acquired pins.
+    pub fn with_pins(pins: [Pin; 4]) -> Self {
+        Self {
             direction: None,
             pins,
             invert: false,
-        })
+            speed: 1.0,
+            duty: [0.0, 1.0],
+            pwm_period: Duration::from_millis(1),
+            modulated: None,
+            applied: 0.0,
+            flow_rate: None,
+            dispensing: None,
+            ramp: None,
+            ramping: None,
+            accelerating: false,
+            supervisor: Supervisor::default(),
+        }
+    }
+    /// Reports failures outside of any command (e.g. stopping after a dispense nobody is waiting
+    /// on) to the given recipient, as the given device.
+    pub fn supervised(mut self, device: Device, recipient: Recipient<Failed>) -> Self {
+        self.supervisor = Supervisor::new(device, recipient);
+        self
     }
     /// Creates a new pump using the given GPIO pin numbers.
     ///
@@ -110,6 +206,9 @@ impl Pump {
     /// If the pump is not already stopped, it will be stopped and a wait of 20 ms will be added to
     /// prevent sparks, short-circuits, etc.
     ///
+    /// This switches the pump immediately; when driven as an actor with a ramp duration
+    /// configured, the pump is instead brought up to speed (and back down) gradually.
+    ///
     /// ## Notes
     /// If [`invert`](#structfield.invert) is `true`, `direction` will be inverted.
     pub fn set_direction<D>(&mut self, direction: D) -> Result<Option<Direction>>
@@ -123,22 +222,258 @@ impl Pump {
                 // Sleep to make sure we avoid Bad Things™️
                 thread::sleep(std::time::Duration::from_millis(20));
             }
-            let direction = if self.invert { !direction } else { direction };
-            let pins = match direction {
-                Direction::Forward => (0, 3),
-                Direction::Backward => (1, 2),
-            };
-            let (top, bottom) = (pins.0, pins.1);
-            self.pins[top].set_high();
-            self.pins[bottom].set_high();
+            let (top, bottom) = self.legs(direction);
+            self.pins[bottom].set_high()?;
+            self.drive(top)?;
         } else {
-            for i in 0..4 {
-                self.pins[i].set_low();
+            if let Some(pin) = self.modulated.take() {
+                self.pins[pin].set_pwm(self.pwm_period, Duration::new(0, 0))?;
+            }
+            // Every pin is switched off, even if switching off another fails.
+            let mut result = Ok(());
+            for pin in &mut self.pins {
+                result = result.and(pin.set_low());
             }
+            result?;
+            self.applied = 0.0;
         }
         self.direction = direction;
         Ok(direction)
     }
+    /// Returns the indices of the (top, bottom) pins which must be high to run in the given
+    /// direction, taking [`invert`](#structfield.invert) into account.
+    fn legs(&self, direction: Direction) -> (usize, usize) {
+        let direction = if self.invert { !direction } else { direction };
+        match direction {
+            Direction::Forward => (0, 3),
+            Direction::Backward => (1, 2),
+        }
+    }
+    /// The duty cycle corresponding to the current speed.
+    fn duty_cycle(&self) -> f64 {
+        let [min, max] = self.duty;
+        min + (max - min) * self.speed
+    }
+    /// Drives the given (top) pin of the H-bridge according to the current speed.
+    ///
+    /// At full duty, the pin is simply set high; otherwise, it is driven with a PWM signal.
+    fn drive(&mut self, pin: usize) -> Result<()> {
+        let duty = self.duty_cycle();
+        if duty >= 1.0 {
+            if let Some(pin) = self.modulated.take() {
+                self.pins[pin].set_pwm(self.pwm_period, Duration::new(0, 0))?;
+            }
+            self.pins[pin].set_high()?;
+            self.applied = duty;
+        } else {
+            log::trace!("Driving pump pin {} at duty cycle {}", pin, duty);
+            self.modulate(pin, duty)?;
+        }
+        Ok(())
+    }
+    /// Drives the given (top) pin with a PWM signal at the given duty cycle.
+    fn modulate(&mut self, pin: usize, duty: f64) -> Result<()> {
+        self.pins[pin].set_pwm(self.pwm_period, scale(self.pwm_period, duty))?;
+        self.modulated = Some(pin);
+        self.applied = duty;
+        Ok(())
+    }
+    /// The configured ramp duration, if the pump should be ramped at all.
+    fn ramp_duration(&self) -> Option<Duration> {
+        self.ramp.filter(|ramp| *ramp > Duration::new(0, 0))
+    }
+    /// Gradually changes the duty cycle of the given (top) pin from the one currently applied to
+    /// the given one over the configured ramp duration, then calls `then`.
+    ///
+    /// Any ramp already in progress is abandoned, so this one picks up where it left off. A
+    /// failure partway through the ramp abandons it and is reported to the supervisor.
+    fn ramp<F>(&mut self, pin: usize, to: f64, context: &mut Context<Self>, then: F)
+    where
+        F: FnOnce(&mut Self, &mut Context<Self>) -> Result<()> + 'static,
+    {
+        if let Some(handle) = self.ramping.take() {
+            context.cancel_future(handle);
+        }
+        let ramp = self.ramp.unwrap_or_default();
+        let from = self.applied;
+        log::trace!(
+            "Ramping pump pin {} from {} to {} over {:?}",
+            pin,
+            from,
+            to,
+            ramp
+        );
+        let mut then = Some(then);
+        let mut step = 0;
+        let handle = context.run_interval(ramp / RAMP_STEPS, move |pump, context| {
+            step += 1;
+            let duty = from + (to - from) * f64::from(step) / f64::from(RAMP_STEPS);
+            let mut result = pump.modulate(pin, duty);
+            if step >= RAMP_STEPS || result.is_err() {
+                if let Some(handle) = pump.ramping.take() {
+                    context.cancel_future(handle);
+                }
+                if let Some(then) = then.take().filter(|_| result.is_ok()) {
+                    result = then(pump, context);
+                }
+            }
+            if let Err(err) = result {
+                pump.supervisor.report(err);
+            }
+        });
+        self.ramping = Some(handle);
+    }
+    /// Runs the pump in the given direction, ramping it up to speed (after ramping it back down,
+    /// if it's running the other way) if a ramp duration has been configured.
+    fn run(
+        &mut self,
+        direction: Direction,
+        context: &mut Context<Self>,
+    ) -> Result<Option<Direction>> {
+        if self.ramp_duration().is_none() {
+            return self.set_direction(direction);
+        }
+        match self.direction {
+            Some(current) if current != direction => {
+                let (top, _) = self.legs(current);
+                self.accelerating = false;
+                self.ramp(top, 0.0, context, move |pump, context| {
+                    pump.stop()?;
+                    // Pause to make sure we avoid Bad Things™️
+                    let pause = Duration::from_millis(20);
+                    let handle = context.run_later(pause, move |pump, context| {
+                        pump.ramping = None;
+                        if let Err(err) = pump.engage(direction, context) {
+                            pump.supervisor.report(err);
+                        }
+                    });
+                    pump.ramping = Some(handle);
+                    Ok(())
+                });
+            }
+            _ => self.engage(direction, context)?,
+        }
+        Ok(Some(direction))
+    }
+    /// Switches on the bottom leg of the H-bridge for the given direction and ramps the top one up
+    /// to speed, from the duty cycle currently applied.
+    fn engage(&mut self, direction: Direction, context: &mut Context<Self>) -> Result<()> {
+        let (top, bottom) = self.legs(direction);
+        self.pins[bottom].set_high()?;
+        self.direction = Some(direction);
+        let duty = self.duty_cycle();
+        self.accelerating = true;
+        self.ramp(top, duty, context, move |pump, _| pump.drive(top));
+        Ok(())
+    }
+    /// Stops the pump, ramping it down from the duty cycle currently applied if a ramp duration
+    /// has been configured.
+    fn wind_down(&mut self, context: &mut Context<Self>) -> Result<Option<Direction>> {
+        match (self.direction, self.ramp_duration()) {
+            (Some(current), Some(_)) => {
+                let (top, _) = self.legs(current);
+                self.accelerating = false;
+                self.ramp(top, 0.0, context, |pump, _| pump.stop().map(|_| ()));
+                Ok(None)
+            }
+            _ => self.stop(),
+        }
+    }
+    /// Changes the speed of the pump, ramping to it if a ramp duration has been configured.
+    fn change_speed(
+        &mut self,
+        speed: f64,
+        context: &mut Context<Self>,
+    ) -> Result<Option<Direction>> {
+        if self.ramp_duration().is_none() {
+            return self.set_speed(speed);
+        }
+        self.store_speed(speed);
+        // A ramp up to speed is retargeted from wherever it's got to. One winding the pump down
+        // (or a pause before reversing) carries on, and the pump comes back up to the new speed.
+        let winding_down = self.ramping.is_some() && !self.accelerating;
+        if let Some(direction) = self.direction.filter(|_| !winding_down) {
+            let (top, _) = self.legs(direction);
+            let duty = self.duty_cycle();
+            self.accelerating = true;
+            self.ramp(top, duty, context, move |pump, _| pump.drive(top));
+        }
+        Ok(self.direction)
+    }
+    /// Records the given speed, clamped to the range 0–1.
+    fn store_speed(&mut self, speed: f64) {
+        let speed = if speed.is_nan() { 0.0 } else { speed };
+        self.speed = speed.max(0.0).min(1.0);
+        log::trace!("Setting pump speed to {}", self.speed);
+    }
+    /// Sets the speed of the pump as a fraction of full speed (clamped to the range 0–1).
+    ///
+    /// If the pump is running, the new speed takes effect immediately.
+    pub fn set_speed(&mut self, speed: f64) -> Result<Option<Direction>> {
+        self.store_speed(speed);
+        if let Some(direction) = self.direction {
+            let (top, _) = self.legs(direction);
+            self.drive(top)?;
+        }
+        Ok(self.direction)
+    }
+    /// The current speed of the pump, as a fraction of full speed.
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+    /// The time it takes to dispense the given volume at the current speed.
+    ///
+    /// Returns `None` if the pump's flow rate has not been calibrated or the pump is set to a speed
+    /// of zero.
+    pub fn dispense_duration(&self, volume: Volume) -> Option<Duration> {
+        let rate = self.flow_rate? * self.speed;
+        if self.speed > 0.0 {
+            Some(duration_from_secs((volume / rate).get::<second>()))
+        } else {
+            None
+        }
+    }
+    /// How many seconds longer than [`dispense_duration`](#method.dispense_duration) the pump
+    /// must be left running (before winding down) to dispense a volume when it's ramped.
+    ///
+    /// The pump delivers less than it would at speed while ramping up (from the duty cycle
+    /// currently applied, after winding down and pausing if it's running the other way), and
+    /// more while winding down afterward; this makes up the difference (which may be negative).
+    fn ramp_allowance(&self) -> f64 {
+        let ramp = match self.ramp_duration() {
+            Some(ramp) => ramp.as_secs_f64(),
+            None => return 0.0,
+        };
+        let [min, max] = self.duty;
+        // The time it would take at speed to deliver what the pump does over a ramp between the
+        // given duty cycles (which are applied in steps; see `ramp`).
+        let at_speed = |from: f64, to: f64| {
+            let delivered = (0..RAMP_STEPS)
+                .map(|step| {
+                    let duty = from + (to - from) * f64::from(step) / f64::from(RAMP_STEPS);
+                    ((duty - min) / (max - min)).max(0.0)
+                })
+                .sum::<f64>();
+            ramp / f64::from(RAMP_STEPS) * delivered / self.speed
+        };
+        let (from, reversal) = match self.direction {
+            Some(Direction::Backward) => (0.0, ramp + 0.02),
+            _ => (self.applied, 0.0),
+        };
+        let duty = self.duty_cycle();
+        reversal + ramp - at_speed(from, duty) - at_speed(duty, 0.0)
+    }
+    /// Starts perfusing in order to dispense the given volume, returning how long the pump must
+    /// run to do so.
+    ///
+    /// The pump is not stopped automatically; when running as an actor, use
+    /// [`Message::Dispense`](enum.Message.html#variant.Dispense) instead.
+    pub fn dispense(&mut self, volume: Volume) -> Result<Duration> {
+        let duration = self.dispense_duration(volume).ok_or(Error::NoFlowRate)?;
+        log::trace!("Dispensing {:?} over {:?}", volume, duration);
+        self.perfuse()?;
+        Ok(duration)
+    }
     /// Switches the pump to the forward direction.
     pub fn perfuse(&mut self) -> Result<Option<Direction>> {
         log::trace!("Setting pump to perfuse");
@@ -165,12 +500,47 @@ impl Actor for Pump {
 }
 
 impl Handle<Message> for Pump {
-    type Result = Result<Option<Direction>>;
-    fn handle(&mut self, message: Message, _context: &mut Self::Context) -> Self::Result {
-        match message {
-            Message::Perfuse => self.perfuse(),
-            Message::Drain => self.drain(),
-            Message::Stop => self.stop(),
+    type Result = ResponseFuture<Option<Direction>, Error>;
+    fn handle(&mut self, message: Message, context: &mut Self::Context) -> Self::Result {
+        // Running, stopping, or dispensing anew supersedes an in-progress dispense or ramp (though
+        // a speed change doesn't).
+        if !matches!(message, Message::SetSpeed(_)) {
+            if let Some(handle) = self.dispensing.take() {
+                log::debug!("Interrupting in-progress dispense");
+                context.cancel_future(handle);
+            }
+            if let Some(handle) = self.ramping.take() {
+                context.cancel_future(handle);
+            }
         }
+        let result = match message {
+            Message::Perfuse => self.run(Direction::Forward, context),
+            Message::Drain => self.run(Direction::Backward, context),
+            Message::Stop => self.wind_down(context),
+            Message::SetSpeed(speed) => self.change_speed(speed, context),
+            Message::Dispense(volume) => match self.dispense_duration(volume) {
+                Some(duration) => {
+                    let duration =
+                        duration_from_secs(duration.as_secs_f64() + self.ramp_allowance());
+                    log::trace!("Dispensing {:?} over {:?}", volume, duration);
+                    if let Err(err) = self.run(Direction::Forward, context) {
+                        return Box::new(future::err(err));
+                    }
+                    let (tx, rx) = oneshot::channel();
+                    let handle = context.run_later(duration, move |pump, context| {
+                        pump.dispensing = None;
+                        // If nobody is waiting to hear whether the pump stopped, tell the
+                        // supervisor instead.
+                        if let Err(Err(err)) = tx.send(pump.wind_down(context)) {
+                            pump.supervisor.report(err);
+                        }
+                    });
+                    self.dispensing = Some(handle);
+                    return Box::new(rx.map_err(|_| Error::Interrupted).and_then(|r| r));
+                }
+                None => Err(Error::NoFlowRate),
+            },
+        };
+        Box::new(future::result(result))
     }
 }
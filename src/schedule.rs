@@ -0,0 +1,113 @@
+//! Deferred and recurring protocol starts.
+//!
+//! Recurring starts are a fixed interval apart (e.g. every 24 hours from 02:00); calendar-based
+//! recurrences (e.g. weekdays only) aren't supported.
+
+use std::{
+    convert::TryFrom,
+    time::{Duration, SystemTime},
+};
+
+use uuid::Uuid;
+
+use crate::Protocol;
+
+/// The shortest interval at which a schedule may recur.
+pub const MIN_SCHEDULE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// When a scheduled protocol should be started.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+pub struct Schedule {
+    /// The time of the (next) start.
+    pub at: SystemTime,
+    /// The interval between starts, if the protocol should be run repeatedly (at least
+    /// [`MIN_SCHEDULE_INTERVAL`](constant.MIN_SCHEDULE_INTERVAL.html)).
+    #[cfg_attr(
+        feature = "use_serde",
+        serde(default, alias = "every", skip_serializing_if = "Option::is_none")
+    )]
+    pub interval: Option<Duration>,
+}
+
+impl Schedule {
+    /// Creates a schedule that starts once, at the given time.
+    pub fn once(at: SystemTime) -> Self {
+        Self { at, interval: None }
+    }
+    /// Creates a schedule that starts at the given time and then repeatedly at the given interval.
+    pub fn every(at: SystemTime, interval: Duration) -> Self {
+        Self {
+            at,
+            interval: Some(interval),
+        }
+    }
+    /// Whether the schedule recurs at an interval shorter than
+    /// [`MIN_SCHEDULE_INTERVAL`](constant.MIN_SCHEDULE_INTERVAL.html).
+    pub fn too_frequent(&self) -> bool {
+        self.interval
+            .map_or(false, |interval| interval < MIN_SCHEDULE_INTERVAL)
+    }
+    /// How long from the given time until the start is due (zero if it's overdue).
+    pub fn delay(&self, now: SystemTime) -> Duration {
+        self.at.duration_since(now).unwrap_or_default()
+    }
+    /// Returns the schedule for the next start after the given time, if the schedule recurs (and
+    /// the next start can be represented).
+    ///
+    /// Occurrences that have already been missed are skipped rather than run back-to-back.
+    pub fn next(&self, now: SystemTime) -> Option<Self> {
+        let interval = self.interval?.as_nanos();
+        if interval == 0 {
+            return None;
+        }
+        let elapsed = now.duration_since(self.at).unwrap_or_default().as_nanos();
+        let offset = (elapsed / interval + 1).checked_mul(interval)?;
+        let offset = Duration::new(
+            u64::try_from(offset / 1_000_000_000).ok()?,
+            (offset % 1_000_000_000) as u32,
+        );
+        let at = self.at.checked_add(offset)?;
+        Some(Self { at, ..*self })
+    }
+}
+
+/// A protocol waiting for its scheduled start.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+pub struct ScheduledProtocol {
+    /// The label of the schedule (each start is given a fresh job label).
+    pub id: Uuid,
+    /// The protocol to be run.
+    pub protocol: Protocol,
+    /// When the protocol is to be run.
+    pub schedule: Schedule,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn skips_missed_occurrences() {
+        let hour = Duration::from_secs(3600);
+        let start = SystemTime::UNIX_EPOCH + hour;
+        assert_eq!(Schedule::once(start).next(start), None);
+        let schedule = Schedule::every(start, hour);
+        assert_eq!(schedule.next(start).map(|s| s.at), Some(start + hour));
+        let late = start + hour * 3 + Duration::from_secs(1);
+        assert_eq!(schedule.next(late).map(|s| s.at), Some(start + hour * 4));
+        assert_eq!(schedule.delay(late), Duration::new(0, 0));
+        let exact = start + hour * 2;
+        assert_eq!(schedule.next(exact).map(|s| s.at), Some(start + hour * 3));
+    }
+    #[test]
+    fn skips_far_ahead() {
+        let schedule = Schedule::every(SystemTime::UNIX_EPOCH, Duration::new(0, 1));
+        assert!(schedule.too_frequent());
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        assert_eq!(
+            schedule.next(now).map(|s| s.at),
+            Some(now + Duration::new(0, 1))
+        );
+    }
+}
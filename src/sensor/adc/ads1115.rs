@@ -0,0 +1,76 @@
+//! Support for the ADS1115 4-channel, 16-bit I²C ADC.
+use super::Adc;
+use crate::pin::Error;
+use rppal::i2c::I2c;
+use std::{fmt, thread, time::Duration};
+
+/// The register holding the result of the last conversion.
+const CONVERSION: u8 = 0x00;
+/// The configuration register.
+const CONFIG: u8 = 0x01;
+/// Config bit starting a single-shot conversion.
+const START: u16 = 0x8000;
+/// Config bits selecting single-ended input on AIN0 (channels follow consecutively).
+const SINGLE_ENDED: u16 = 0b100 << 12;
+/// Config bits selecting the ±4.096 V range.
+const GAIN: u16 = 0b001 << 9;
+/// Config bit selecting single-shot mode.
+const SINGLE_SHOT: u16 = 1 << 8;
+/// Config bits selecting 128 samples per second.
+const RATE: u16 = 0b100 << 5;
+/// Config bits disabling the comparator.
+const NO_COMPARATOR: u16 = 0b11;
+/// How long a single conversion takes at the selected rate (plus a margin).
+const CONVERSION_TIME: Duration = Duration::from_millis(9);
+/// The largest possible (positive) reading.
+const MAX: f64 = 32767.0;
+
+/// An ADS1115 connected to one of the I²C buses.
+pub struct Ads1115 {
+    i2c: I2c,
+    address: u16,
+}
+
+impl fmt::Debug for Ads1115 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Ads1115")
+            .field("address", &self.address)
+            .finish()
+    }
+}
+
+impl Ads1115 {
+    /// Opens the ADS1115 at the given address on the given I²C bus.
+    pub fn open(bus: u8, address: u16) -> Result<Self, Error> {
+        log::debug!(
+            "Opening ADS1115 at address {:#x} on I²C bus {}",
+            address,
+            bus
+        );
+        let mut i2c = I2c::with_bus(bus)?;
+        i2c.set_slave_address(address)?;
+        Ok(Self { i2c, address })
+    }
+}
+
+impl Adc for Ads1115 {
+    fn read(&mut self, channel: u8) -> Result<f64, Error> {
+        if channel > 3 {
+            return Err(Error::Backend(format!("No such ADC channel: {}", channel)));
+        }
+        let config = START
+            | (SINGLE_ENDED + (u16::from(channel) << 12))
+            | GAIN
+            | SINGLE_SHOT
+            | RATE
+            | NO_COMPARATOR;
+        let [high, low] = config.to_be_bytes();
+        self.i2c.write(&[CONFIG, high, low])?;
+        thread::sleep(CONVERSION_TIME);
+        let mut buffer = [0; 2];
+        self.i2c.write_read(&[CONVERSION], &mut buffer)?;
+        let value = i16::from_be_bytes(buffer);
+        // Single-ended readings should never be negative, but noise can push them slightly below.
+        Ok(f64::from(value.max(0)) / MAX)
+    }
+}
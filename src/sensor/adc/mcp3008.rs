@@ -0,0 +1,65 @@
+//! Support for the MCP3008 8-channel, 10-bit SPI ADC.
+use super::Adc;
+use crate::pin::Error;
+use rppal::spi::{Bus, Error as SpiError, Mode, SlaveSelect, Spi};
+
+/// The SPI clock speed, in hertz (the MCP3008 supports 1.35 MHz at 3.3 V).
+const CLOCK_SPEED: u32 = 1_000_000;
+/// The largest possible reading.
+const MAX: f64 = 1023.0;
+
+impl From<SpiError> for Error {
+    fn from(err: SpiError) -> Self {
+        Self::Backend(err.to_string())
+    }
+}
+
+/// An MCP3008 connected to one of the SPI buses.
+#[derive(Debug)]
+pub struct Mcp3008 {
+    spi: Spi,
+}
+
+impl Mcp3008 {
+    /// Opens the MCP3008 on the given SPI bus and slave select line.
+    pub fn open(bus: u8, slave_select: u8) -> Result<Self, Error> {
+        log::debug!(
+            "Opening MCP3008 on SPI bus {} (slave select {})",
+            bus,
+            slave_select
+        );
+        let bus = match bus {
+            0 => Bus::Spi0,
+            1 => Bus::Spi1,
+            2 => Bus::Spi2,
+            _ => return Err(Error::Backend(format!("No such SPI bus: {}", bus))),
+        };
+        let slave_select = match slave_select {
+            0 => SlaveSelect::Ss0,
+            1 => SlaveSelect::Ss1,
+            2 => SlaveSelect::Ss2,
+            _ => {
+                return Err(Error::Backend(format!(
+                    "No such SPI slave select line: {}",
+                    slave_select
+                )))
+            }
+        };
+        let spi = Spi::new(bus, slave_select, CLOCK_SPEED, Mode::Mode0)?;
+        Ok(Self { spi })
+    }
+}
+
+impl Adc for Mcp3008 {
+    fn read(&mut self, channel: u8) -> Result<f64, Error> {
+        if channel > 7 {
+            return Err(Error::Backend(format!("No such ADC channel: {}", channel)));
+        }
+        // Start bit, then single-ended mode and the channel number.
+        let request = [0x01, (0x08 | channel) << 4, 0x00];
+        let mut response = [0; 3];
+        self.spi.transfer(&mut response, &request)?;
+        let value = (u16::from(response[1] & 0x03) << 8) | u16::from(response[2]);
+        Ok(f64::from(value) / MAX)
+    }
+}
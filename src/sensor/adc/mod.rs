@@ -0,0 +1,53 @@
+//! Analog-to-digital converters.
+//!
+//! Readings are reported as a fraction of the converter's full-scale range, leaving calibration to
+//! the sensors built on top of them.
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use crate::pin::Error;
+
+#[cfg(feature = "use_rppal")]
+mod ads1115;
+#[cfg(feature = "use_rppal")]
+mod mcp3008;
+
+#[cfg(feature = "use_rppal")]
+pub use self::{ads1115::Ads1115, mcp3008::Mcp3008};
+
+/// Trait representing an analog-to-digital converter with one or more channels.
+pub trait Adc: fmt::Debug + Send {
+    /// Reads the given channel, returning the reading as a fraction of full scale (`0.0..=1.0`).
+    fn read(&mut self, channel: u8) -> Result<f64, Error>;
+}
+
+/// A converter whose readings are set manually.
+///
+/// Cloning yields a handle to the same underlying readings. Unset channels read zero.
+#[derive(Clone, Debug, Default)]
+pub struct Mock {
+    readings: Arc<Mutex<HashMap<u8, f64>>>,
+}
+
+impl Mock {
+    /// Creates a new mock converter.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Sets the reading of the given channel.
+    pub fn set(&self, channel: u8, reading: f64) {
+        if let Ok(mut readings) = self.readings.lock() {
+            readings.insert(channel, reading);
+        }
+    }
+}
+
+impl Adc for Mock {
+    fn read(&mut self, channel: u8) -> Result<f64, Error> {
+        let readings = self.readings.lock().map_err(|_| Error::Panic)?;
+        Ok(readings.get(&channel).cloned().unwrap_or(0.0))
+    }
+}
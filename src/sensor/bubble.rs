@@ -0,0 +1,46 @@
+//! Air-in-line (bubble) detectors.
+
+use crate::{
+    config::BubbleDetectorConfig,
+    pin::{Edge, Error as PinError, GpioBackend, Input},
+};
+
+/// A detector which drives an input high while air is in the line.
+#[derive(Debug)]
+pub struct BubbleDetector {
+    /// The input the detector is connected to.
+    input: Box<dyn Input>,
+    /// Whether the detector drives the input low (rather than high) while air is in the line.
+    invert: bool,
+}
+
+impl BubbleDetector {
+    /// Attempts to create a bubble detector using the given configuration and backend.
+    pub fn try_new(
+        config: &BubbleDetectorConfig,
+        backend: &dyn GpioBackend,
+    ) -> Result<Self, PinError> {
+        Ok(Self {
+            input: backend.input(config.pin)?,
+            invert: config.invert,
+        })
+    }
+
+    /// Whether air is currently detected.
+    pub fn is_triggered(&self) -> bool {
+        self.input.is_high() != self.invert
+    }
+
+    /// Registers a callback to be invoked (possibly on another thread) whenever air is detected.
+    pub fn watch<F>(&mut self, mut callback: F) -> Result<(), PinError>
+    where
+        F: FnMut() + Send + 'static,
+    {
+        let edge = if self.invert {
+            Edge::Falling
+        } else {
+            Edge::Rising
+        };
+        self.input.on_edge(edge, Box::new(move |_| callback()))
+    }
+}
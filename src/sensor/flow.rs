@@ -0,0 +1,107 @@
+//! Pulse-output flow sensors.
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use uom::si::{f64::Volume, volume::milliliter};
+
+use actix_web::actix::MessageResult;
+
+use crate::{
+    actix::*,
+    config::FlowSensorConfig,
+    pin::{Edge, Error as PinError, GpioBackend, Input},
+};
+
+/// A message that can be sent to a flow sensor.
+#[derive(Clone, Copy, Debug)]
+pub enum Message {
+    /// Resets the measured volume to zero.
+    Reset,
+    /// Requests the volume measured since the last reset.
+    Measure,
+}
+
+impl ActixMessage for Message {
+    type Result = Volume;
+}
+
+/// A flow sensor emitting a fixed number of pulses per unit volume.
+#[derive(Debug)]
+pub struct FlowSensor {
+    /// The input the sensor is connected to (kept alive so that pulses continue to be counted).
+    _input: Box<dyn Input>,
+    /// The number of pulses counted since the last reset.
+    pulses: Arc<AtomicUsize>,
+    /// The number of pulses the sensor emits per mL of flow.
+    pulses_per_ml: f64,
+}
+
+impl FlowSensor {
+    /// Attempts to create a flow sensor using the given configuration and backend.
+    pub fn try_new(config: &FlowSensorConfig, backend: &dyn GpioBackend) -> Result<Self, PinError> {
+        let mut input = backend.input(config.pin)?;
+        let pulses = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&pulses);
+        input.on_edge(
+            Edge::Rising,
+            Box::new(move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            }),
+        )?;
+        Ok(Self {
+            _input: input,
+            pulses,
+            pulses_per_ml: config.pulses_per_ml,
+        })
+    }
+
+    /// Resets the measured volume to zero.
+    pub fn reset(&mut self) {
+        self.pulses.store(0, Ordering::SeqCst);
+    }
+
+    /// The volume measured since the last reset.
+    pub fn volume(&self) -> Volume {
+        let pulses = self.pulses.load(Ordering::SeqCst) as f64;
+        Volume::new::<milliliter>(pulses / self.pulses_per_ml)
+    }
+}
+
+impl Actor for FlowSensor {
+    type Context = Context<Self>;
+}
+
+impl Handle<Message> for FlowSensor {
+    type Result = MessageResult<Message>;
+    fn handle(&mut self, message: Message, _context: &mut Self::Context) -> Self::Result {
+        match message {
+            Message::Reset => {
+                log::trace!("Resetting flow sensor");
+                self.reset();
+            }
+            Message::Measure => (),
+        }
+        MessageResult(self.volume())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pin::Mock;
+    #[test]
+    fn counts_pulses() {
+        let mock = Mock::new();
+        let inputs = mock.inputs();
+        let mut sensor = FlowSensor::try_new(&FlowSensorConfig::new(5, 2.0), &mock).unwrap();
+        for _ in 0..5 {
+            inputs.pulse(5);
+        }
+        assert_eq!(sensor.volume().get::<milliliter>(), 2.5);
+        sensor.reset();
+        assert_eq!(sensor.volume().get::<milliliter>(), 0.0);
+    }
+}
@@ -0,0 +1,165 @@
+//! Buffer reservoir level sensing.
+
+use uom::si::{f64::Volume, volume::milliliter};
+
+use super::adc::Adc;
+use crate::{
+    actix::*,
+    config::{LevelSensorConfig, ReservoirConfig},
+    pin::{Error as PinError, GpioBackend, Input},
+    MotorId,
+};
+
+/// A message that can be sent to the reservoirs.
+#[derive(Clone, Copy, Debug)]
+pub enum Message {
+    /// Requests the current level of each reservoir.
+    Measure,
+}
+
+impl ActixMessage for Message {
+    type Result = Result<Vec<Level>, PinError>;
+}
+
+/// The measured level of a single reservoir.
+#[derive(Clone, Copy, Debug)]
+pub struct Level {
+    /// The buffer held in the reservoir.
+    pub buffer: MotorId,
+    /// The (estimated) volume remaining in the reservoir.
+    ///
+    /// Float switches can only tell whether the level is above or below the low mark, so the
+    /// estimate is either the capacity or the low volume, respectively.
+    pub volume: Volume,
+    /// Whether the reservoir is running low.
+    pub low: bool,
+}
+
+/// The sensor measuring the level of a reservoir.
+#[derive(Debug)]
+enum Sensor {
+    /// A float switch, which is high while the level is above the low mark.
+    Float { input: Box<dyn Input>, invert: bool },
+    /// A level sensor whose reading is proportional to the volume remaining.
+    Analog { adc: Box<dyn Adc>, channel: u8 },
+}
+
+/// A buffer reservoir with a level sensor.
+#[derive(Debug)]
+pub struct Reservoir {
+    buffer: MotorId,
+    sensor: Sensor,
+    /// The volume of a full reservoir.
+    capacity: Volume,
+    /// The volume below which the reservoir is considered low.
+    low: Volume,
+}
+
+impl Reservoir {
+    /// Attempts to create a reservoir using the given configuration and backend.
+    pub fn try_new(config: &ReservoirConfig, backend: &dyn GpioBackend) -> Result<Self, PinError> {
+        let sensor = match config.sensor {
+            LevelSensorConfig::Float { pin, invert } => Sensor::Float {
+                input: backend.input(pin)?,
+                invert,
+            },
+            LevelSensorConfig::Analog { adc, channel } => Sensor::Analog {
+                adc: adc.open()?,
+                channel,
+            },
+        };
+        Ok(Self {
+            buffer: config.buffer,
+            sensor,
+            capacity: Volume::new::<milliliter>(config.capacity.millilitres()),
+            low: Volume::new::<milliliter>(config.low.millilitres()),
+        })
+    }
+
+    /// Measures the current level of the reservoir.
+    pub fn level(&mut self) -> Result<Level, PinError> {
+        let (volume, low) = match &mut self.sensor {
+            Sensor::Float { input, invert } => {
+                if input.is_high() != *invert {
+                    (self.capacity, false)
+                } else {
+                    (self.low, true)
+                }
+            }
+            Sensor::Analog { adc, channel } => {
+                let volume = self.capacity * adc.read(*channel)?;
+                (volume, volume <= self.low)
+            }
+        };
+        Ok(Level {
+            buffer: self.buffer,
+            volume,
+            low,
+        })
+    }
+}
+
+/// Tracks the levels of all buffer reservoirs.
+#[derive(Debug)]
+pub struct Reservoirs {
+    reservoirs: Vec<Reservoir>,
+}
+
+impl Reservoirs {
+    /// Creates a tracker for the given reservoirs.
+    pub fn new(reservoirs: Vec<Reservoir>) -> Self {
+        Self { reservoirs }
+    }
+
+    /// Measures the current level of each reservoir.
+    pub fn levels(&mut self) -> Result<Vec<Level>, PinError> {
+        self.reservoirs.iter_mut().map(Reservoir::level).collect()
+    }
+}
+
+impl Actor for Reservoirs {
+    type Context = Context<Self>;
+}
+
+impl Handle<Message> for Reservoirs {
+    type Result = Result<Vec<Level>, PinError>;
+    fn handle(&mut self, message: Message, _context: &mut Self::Context) -> Self::Result {
+        match message {
+            Message::Measure => self.levels(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::AdcConfig, pin::Mock, quantity};
+    #[test]
+    fn measures_float_switches() {
+        let mock = Mock::new();
+        let config = ReservoirConfig {
+            buffer: 1,
+            capacity: "1l".parse().unwrap(),
+            low: quantity::Volume::from_millilitres(100.0),
+            sensor: LevelSensorConfig::Float {
+                pin: 4,
+                invert: false,
+            },
+        };
+        let mut reservoir = Reservoir::try_new(&config, &mock).unwrap();
+        assert!(reservoir.level().unwrap().low);
+        mock.inputs().set(4, true);
+        let level = reservoir.level().unwrap();
+        assert!(!level.low);
+        assert_eq!(level.volume.get::<milliliter>(), 1000.0);
+        let config = ReservoirConfig {
+            sensor: LevelSensorConfig::Analog {
+                adc: AdcConfig::Mock,
+                channel: 0,
+            },
+            ..config
+        };
+        let mut reservoir = Reservoir::try_new(&config, &mock).unwrap();
+        assert!(reservoir.level().unwrap().low);
+    }
+}
@@ -0,0 +1,15 @@
+//! Sensor support.
+//!
+//! Digital sensors are read through inputs acquired from a
+//! [`GpioBackend`](../pin/trait.GpioBackend.html); analog sensors are read through an
+//! [`Adc`](adc/trait.Adc.html).
+
+pub mod adc;
+pub mod bubble;
+pub mod flow;
+pub mod level;
+pub mod pressure;
+
+pub use self::{
+    bubble::BubbleDetector, flow::FlowSensor, level::Reservoirs, pressure::PressureSensor,
+};
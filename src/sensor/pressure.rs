@@ -0,0 +1,83 @@
+//! Analog pressure transducers.
+
+use uom::si::{f64::Pressure, pressure::kilopascal};
+
+use super::adc::Adc;
+use crate::{actix::*, pin::Error as PinError};
+
+/// A message that can be sent to a pressure sensor.
+#[derive(Clone, Copy, Debug)]
+pub enum Message {
+    /// Requests the current pressure.
+    Measure,
+}
+
+impl ActixMessage for Message {
+    type Result = Result<Pressure, PinError>;
+}
+
+/// A pressure transducer whose output varies linearly with pressure, read through an ADC.
+#[derive(Debug)]
+pub struct PressureSensor {
+    /// The converter the transducer is connected to.
+    adc: Box<dyn Adc>,
+    /// The converter channel the transducer is connected to.
+    channel: u8,
+    /// The pressures corresponding to zero and full-scale readings, respectively.
+    range: [Pressure; 2],
+}
+
+impl PressureSensor {
+    /// Creates a pressure sensor on the given converter channel.
+    ///
+    /// The range gives the pressures corresponding to zero and full-scale readings, respectively.
+    pub fn new(adc: Box<dyn Adc>, channel: u8, range: [Pressure; 2]) -> Self {
+        Self {
+            adc,
+            channel,
+            range,
+        }
+    }
+
+    /// Reads the current pressure.
+    pub fn pressure(&mut self) -> Result<Pressure, PinError> {
+        let reading = self.adc.read(self.channel)?;
+        let [low, high] = self.range;
+        Ok(low + (high - low) * reading)
+    }
+}
+
+impl Actor for PressureSensor {
+    type Context = Context<Self>;
+}
+
+impl Handle<Message> for PressureSensor {
+    type Result = Result<Pressure, PinError>;
+    fn handle(&mut self, message: Message, _context: &mut Self::Context) -> Self::Result {
+        match message {
+            Message::Measure => {
+                let pressure = self.pressure()?;
+                log::trace!("Measured {} kPa", pressure.get::<kilopascal>());
+                Ok(pressure)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensor::adc::Mock;
+    #[test]
+    fn interpolates_readings() {
+        let adc = Mock::new();
+        let range = [
+            Pressure::new::<kilopascal>(-100.0),
+            Pressure::new::<kilopascal>(100.0),
+        ];
+        let mut sensor = PressureSensor::new(Box::new(adc.clone()), 2, range);
+        assert_eq!(sensor.pressure().unwrap().get::<kilopascal>(), -100.0);
+        adc.set(2, 0.75);
+        assert_eq!(sensor.pressure().unwrap().get::<kilopascal>(), 50.0);
+    }
+}
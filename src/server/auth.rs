@@ -0,0 +1,232 @@
+//! API token authentication and role-based access control.
+use super::{job::Error, state::State as AppState};
+use crate::{Role, TokenConfig};
+use actix_web::{
+    http::{header, Method},
+    middleware::{Middleware, Started},
+    HttpRequest, HttpResponse, Json, Path,
+};
+
+use std::{
+    collections::HashMap,
+    sync::{RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+use uuid::Uuid;
+
+/// The holder of an API token.
+#[derive(Clone, Debug)]
+pub struct Holder {
+    /// Who (or what) holds the token.
+    pub name: String,
+    /// What the holder may do.
+    pub role: Role,
+}
+
+/// The tokens accepted by the server, along with who holds each.
+#[derive(Debug, Default)]
+pub struct Tokens {
+    tokens: RwLock<HashMap<String, Holder>>,
+    /// The secret which may also be presented to manage tokens, if any.
+    bootstrap: Option<String>,
+}
+
+impl Tokens {
+    /// Creates the set of tokens given in the configuration, which may also be managed by
+    /// presenting the given bootstrap secret (if any).
+    ///
+    /// Without any tokens, the server is open to anyone who can reach it, which is logged as a
+    /// warning.
+    pub fn new(config: &[TokenConfig], bootstrap: Option<String>) -> Self {
+        if config.is_empty() {
+            log::warn!(
+                "No API tokens are configured, so anyone who can reach the server can control the \
+                 hardware; configure tokens (or issue one{}) to require authentication.",
+                if bootstrap.is_some() {
+                    " with the bootstrap token"
+                } else {
+                    " after setting bootstrap-token"
+                }
+            );
+        }
+        let tokens = config
+            .iter()
+            .map(|spec| {
+                let holder = Holder {
+                    name: spec.name.clone(),
+                    role: spec.role,
+                };
+                (spec.token.clone(), holder)
+            })
+            .collect();
+        Self {
+            tokens: RwLock::new(tokens),
+            bootstrap,
+        }
+    }
+    /// Whether authentication is required (i.e. any tokens have been configured or issued).
+    pub fn enabled(&self) -> bool {
+        !self.read().is_empty()
+    }
+    /// Who holds the given token, if it's valid.
+    ///
+    /// The bootstrap secret is held by an admin, but is only accepted for managing tokens.
+    pub fn holder(&self, token: &str, managing: bool) -> Option<Holder> {
+        if managing
+            && self
+                .bootstrap
+                .as_ref()
+                .map_or(false, |secret| constant_time_eq(secret, token))
+        {
+            return Some(Holder {
+                name: "bootstrap".into(),
+                role: Role::Admin,
+            });
+        }
+        self.read().get(token).cloned()
+    }
+    /// Issues a new token to the given holder.
+    pub fn issue(&self, holder: Holder) -> String {
+        let token = Uuid::new_v4().to_simple().to_string();
+        self.write().insert(token.clone(), holder);
+        token
+    }
+    /// Revokes the given token, returning whether it was valid.
+    pub fn revoke(&self, token: &str) -> bool {
+        self.write().remove(token).is_some()
+    }
+    fn read(&self) -> RwLockReadGuard<HashMap<String, Holder>> {
+        // A panic while holding the lock can't leave the map in a bad state.
+        self.tokens
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+    fn write(&self) -> RwLockWriteGuard<HashMap<String, Holder>> {
+        self.tokens
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Compares the given strings in time depending only on their lengths, so that a secret can't be
+/// guessed a character at a time by timing failed attempts.
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Extracts the token presented with the given request, if any.
+///
+/// Tokens are taken from the `Authorization` header (as `Bearer <token>`) or, since browsers can't
+/// set headers on WebSocket connections, from the `token` query parameter.
+fn presented<S>(req: &HttpRequest<S>) -> Option<String> {
+    let bearer = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| {
+            let mut parts = value.trim().splitn(2, ' ');
+            match (parts.next(), parts.next()) {
+                (Some(scheme), Some(token)) if scheme.eq_ignore_ascii_case("bearer") => {
+                    Some(token.trim().to_string())
+                }
+                _ => None,
+            }
+        });
+    bearer.or_else(|| req.query().get("token").cloned())
+}
+
+/// The given path, less any prefix addressing a particular manifold.
+fn unscoped(path: &str) -> &str {
+    if !path.starts_with("/manifolds/") {
+        return path;
+    }
+    let rest = &path["/manifolds/".len()..];
+    rest.find('/').map_or("/", |index| &rest[index..])
+}
+
+/// Whether the given request manages tokens.
+fn manages_tokens<S>(req: &HttpRequest<S>) -> bool {
+    unscoped(req.path()).starts_with("/tokens")
+}
+
+/// The role needed for the given request (see [`Role::required`]).
+///
+/// [`Role::required`]: ../../enum.Role.html#method.required
+fn required<S>(req: &HttpRequest<S>) -> Role {
+    let read_only = matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+    Role::required(read_only, unscoped(req.path()))
+}
+
+/// Middleware which refuses requests lacking a valid token for a sufficient role.
+///
+/// Without any tokens, requests are let through, except those managing tokens: otherwise, anyone
+/// could issue themselves the first admin token.
+///
+/// The holder of the token is stored in the request's extensions, so that actions can be credited
+/// to them (see [`dispatch`](../state/fn.dispatch.html)).
+#[derive(Clone, Copy, Debug)]
+pub struct Authenticate;
+
+impl Middleware<AppState> for Authenticate {
+    fn start(&self, req: &HttpRequest<AppState>) -> actix_web::Result<Started> {
+        let tokens = &req.state().tokens;
+        // Supervisors and uptime monitors shouldn't need tokens.
+        let path = unscoped(req.path());
+        let exempt = path == "/healthz" || path == "/readyz";
+        // Nor should loading the interface itself (though the requests it makes still do).
+        #[cfg(feature = "ui")]
+        let exempt = exempt || super::ui::is_asset(req);
+        let managing = manages_tokens(req);
+        if exempt || !(tokens.enabled() || managing) {
+            return Ok(Started::Done);
+        }
+        match presented(req).and_then(|token| tokens.holder(&token, managing)) {
+            Some(holder) => {
+                if holder.role < required(req) {
+                    return Ok(Started::Response(HttpResponse::Forbidden().finish()));
+                }
+                req.extensions_mut().insert(holder);
+                Ok(Started::Done)
+            }
+            None => Ok(Started::Response(
+                HttpResponse::Unauthorized()
+                    .header(header::WWW_AUTHENTICATE, "Bearer")
+                    .finish(),
+            )),
+        }
+    }
+}
+
+/// A request for a new token.
+#[derive(Clone, Debug, Deserialize)]
+pub struct TokenRequest {
+    /// Who (or what) will hold the token.
+    name: String,
+    /// What the holder may do (viewing only, by default).
+    #[serde(default)]
+    role: Role,
+}
+
+/// Issues a new token (to an admin, or a holder of the bootstrap secret).
+///
+/// If no tokens have been configured, issuing the first one turns authentication on.
+#[allow(clippy::needless_pass_by_value)]
+pub fn issue(
+    request: Json<TokenRequest>,
+    req: HttpRequest<AppState>,
+) -> Result<HttpResponse, Error> {
+    let TokenRequest { name, role } = request.into_inner();
+    let token = req.state().tokens.issue(Holder { name, role });
+    Ok(HttpResponse::Created().json(token))
+}
+
+/// Revokes a token.
+#[allow(clippy::needless_pass_by_value)]
+pub fn revoke(token: Path<String>, req: HttpRequest<AppState>) -> Result<HttpResponse, Error> {
+    if req.state().tokens.revoke(&token) {
+        Ok(HttpResponse::NoContent().finish())
+    } else {
+        Err(Error::NotFound)
+    }
+}
@@ -0,0 +1,51 @@
+//! Cross-origin resource sharing.
+use crate::CorsConfig;
+use actix_web::{
+    http::{header::HeaderName, Method, Uri},
+    middleware::cors::Cors,
+};
+
+/// Builds the middleware enforcing the given policy.
+///
+/// Invalid origins, methods, and headers are skipped (with a warning), rather than taking down
+/// the server.
+pub fn policy(config: &CorsConfig) -> Cors {
+    let mut cors = Cors::build();
+    if config.origins.iter().any(|origin| origin == "*") {
+        cors.send_wildcard();
+    } else {
+        for origin in &config.origins {
+            if origin.parse::<Uri>().is_ok() {
+                cors.allowed_origin(origin);
+            } else {
+                log::warn!("Ignoring invalid CORS origin {:?}.", origin);
+            }
+        }
+    }
+    let methods = config
+        .methods
+        .iter()
+        .filter_map(|method| match Method::from_bytes(method.as_bytes()) {
+            Ok(method) => Some(method),
+            Err(_) => {
+                log::warn!("Ignoring invalid CORS method {:?}.", method);
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+    let headers = config
+        .headers
+        .iter()
+        .filter_map(|header| match HeaderName::from_bytes(header.as_bytes()) {
+            Ok(header) => Some(header),
+            Err(_) => {
+                log::warn!("Ignoring invalid CORS header {:?}.", header);
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+    cors.allowed_methods(methods)
+        .allowed_headers(headers)
+        .max_age(config.max_age.as_secs() as usize)
+        .finish()
+}
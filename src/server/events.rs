@@ -0,0 +1,192 @@
+//! Live status updates as server-sent events, for clients which can't use WebSockets.
+//!
+//! The stream carries the same frames as the WebSocket: a `snapshot` event on connecting, then an
+//! `update` event for each change. Updates are numbered, and recent ones are kept so that a client
+//! reconnecting with `Last-Event-ID` picks up where it left off.
+use super::{job::Job, state::State as AppState};
+use crate::{
+    comm::{Message, Subscribers},
+    event_log::Event,
+    Status, Update,
+};
+use actix_web::{error, http::header, HttpRequest, HttpResponse};
+use bytes::Bytes;
+use futures::{
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    Stream,
+};
+use serde::Serialize;
+
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+};
+
+/// How many recent updates are kept for reconnecting clients.
+const CAPACITY: usize = 1024;
+/// How long clients should wait before reconnecting, in milliseconds.
+const RETRY: u64 = 3000;
+
+/// Formats an event for the stream.
+fn chunk<T: Serialize>(id: Option<u64>, event: &str, data: &T) -> Option<Bytes> {
+    let data = match serde_json::to_string(data) {
+        Ok(data) => data,
+        Err(err) => {
+            log::error!("Failed to serialize {} event: {}", event, err);
+            return None;
+        }
+    };
+    let id = id.map(|id| format!("id: {}\n", id)).unwrap_or_default();
+    Some(format!("{}event: {}\ndata: {}\n\n", id, event, data).into())
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    /// The number of the next update.
+    next: u64,
+    /// The most recent updates, along with their numbers.
+    recent: VecDeque<(u64, Bytes)>,
+    /// The connected clients.
+    listeners: Vec<UnboundedSender<Bytes>>,
+}
+
+/// Recent status updates, and the clients listening for more.
+#[derive(Debug, Default)]
+pub struct Backlog {
+    inner: Mutex<Inner>,
+    /// Whether the backlog is receiving the coordinator's updates yet.
+    subscribed: AtomicBool,
+}
+
+impl Backlog {
+    fn lock(&self) -> MutexGuard<Inner> {
+        // A panic while holding the lock can't leave the backlog in a bad state.
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+    /// Numbers the given update, keeps it, and sends it to every connected client.
+    fn record(&self, event: &Event) {
+        let mut inner = self.lock();
+        let id = inner.next;
+        inner.next += 1;
+        let chunk = match chunk(Some(id), "update", event) {
+            Some(chunk) => chunk,
+            None => return,
+        };
+        inner.recent.push_back((id, chunk.clone()));
+        if inner.recent.len() > CAPACITY {
+            inner.recent.pop_front();
+        }
+        // Sending only fails once the client has gone.
+        inner
+            .listeners
+            .retain(|listener| listener.unbounded_send(chunk.clone()).is_ok());
+    }
+    /// Adds a client, which last saw the given update (if any).
+    ///
+    /// A client which has missed updates no longer kept (or which is new) is sent a snapshot from
+    /// the given function instead.
+    fn listen(
+        &self,
+        last: Option<u64>,
+        snapshot: impl FnOnce() -> Option<Job>,
+    ) -> UnboundedReceiver<Bytes> {
+        let mut inner = self.lock();
+        let (sender, receiver) = mpsc::unbounded();
+        let send = |chunk: Bytes| {
+            // The receiver is still in hand, so this can't fail.
+            let _ = sender.unbounded_send(chunk);
+        };
+        send(format!("retry: {}\n\n", RETRY).into());
+        let first = inner.next - inner.recent.len() as u64;
+        match last {
+            // A number from before a restart could be ahead of us.
+            Some(last) if last < inner.next && last + 1 >= first => {
+                for (_, chunk) in inner.recent.iter().filter(|(id, _)| *id > last) {
+                    send(chunk.clone());
+                }
+            }
+            _ => {
+                if let Some(chunk) = chunk(None, "snapshot", &snapshot()) {
+                    send(chunk);
+                }
+            }
+        }
+        inner.listeners.push(sender);
+        receiver
+    }
+}
+
+/// Passes coordinator updates on to the backlog.
+#[derive(Debug)]
+struct Recorder(Arc<Backlog>);
+
+impl Update for Recorder {
+    fn handle(&self, status: &Status, _coord: &Subscribers) {
+        self.0.record(&Event::new(status));
+    }
+}
+
+/// Streams the coordinator's status updates as server-sent events.
+pub fn stream(req: &HttpRequest<AppState>) -> HttpResponse {
+    let state = req.state();
+    if !state.events.subscribed.swap(true, Ordering::SeqCst) {
+        let recorder = Recorder(Arc::clone(&state.events));
+        state.addr.do_send(Message::Subscribe(Box::new(recorder)));
+    }
+    let last = req
+        .headers()
+        .get("Last-Event-ID")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok());
+    let updates = state.events.listen(last, || Job::current(&state.coord));
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .header(header::CACHE_CONTROL, "no-cache")
+        // Keep reverse proxies from holding updates back.
+        .header("X-Accel-Buffering", "no")
+        .streaming(updates.map_err(|()| error::ErrorInternalServerError("status feed closed")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event_log::Origin;
+    use futures::Future;
+    fn received(receiver: UnboundedReceiver<Bytes>, count: u64) -> Vec<String> {
+        let chunks = receiver.take(count).collect().wait().unwrap();
+        chunks
+            .into_iter()
+            .map(|chunk| String::from_utf8(chunk.to_vec()).unwrap())
+            .collect()
+    }
+    #[test]
+    fn resumes() {
+        let backlog = Backlog::default();
+        let event = Event {
+            timestamp: 0,
+            origin: Origin::Coordinator,
+            user: None,
+            manifold: None,
+            kind: "step".into(),
+            step: Some(0),
+            iteration: None,
+            elapsed: None,
+            detail: None,
+        };
+        for _ in 0..3 {
+            backlog.record(&event);
+        }
+        let fresh = received(backlog.listen(None, || None), 2);
+        assert!(fresh[1].starts_with("event: snapshot\n"));
+        let resumed = received(backlog.listen(Some(0), || None), 3);
+        assert!(resumed[1].starts_with("id: 1\nevent: update\n"));
+        assert!(resumed[2].starts_with("id: 2\n"));
+        let restarted = received(backlog.listen(Some(7), || None), 2);
+        assert!(restarted[1].starts_with("event: snapshot\n"));
+    }
+}
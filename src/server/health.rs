@@ -0,0 +1,74 @@
+//! Health, readiness, and information endpoints, for supervisors and uptime monitors.
+use super::{job::Error, state::State as AppState};
+use crate::{watchdog::Ping, CheckHealth, ComponentHealth, Health};
+use actix_web::{AsyncResponder, HttpRequest, HttpResponse, Json};
+use futures::prelude::*;
+
+use std::{path::PathBuf, time::Duration};
+
+/// How long the coordinator has to answer before it's considered unresponsive.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What's running, for telling deployments apart.
+#[derive(Debug, Serialize)]
+pub struct Info {
+    /// The version of deoxy.
+    version: &'static str,
+    /// The configuration file in use, if the configuration was read from one.
+    config: Option<PathBuf>,
+}
+
+/// Describes what's running (e.g. which configuration file was found).
+#[allow(clippy::needless_pass_by_value)]
+pub fn info(req: HttpRequest<AppState>) -> Json<Info> {
+    Json(Info {
+        version: env!("CARGO_PKG_VERSION"),
+        config: req.state().config.as_ref().map(|path| path.to_path_buf()),
+    })
+}
+
+/// Checks that the coordinator is alive (i.e. answers a ping).
+#[allow(clippy::needless_pass_by_value)]
+pub fn healthz(req: HttpRequest<AppState>) -> Box<dyn Future<Item = HttpResponse, Error = Error>> {
+    req.state()
+        .addr
+        .send(Ping)
+        .timeout(TIMEOUT)
+        .then(|result| {
+            let health = Health::new(vec![ComponentHealth::new("coordinator", result)]);
+            Ok(respond(&health))
+        })
+        .responder()
+}
+
+/// Checks that the coordinator and its hardware are ready to run protocols.
+#[allow(clippy::needless_pass_by_value)]
+pub fn readyz(req: HttpRequest<AppState>) -> Box<dyn Future<Item = HttpResponse, Error = Error>> {
+    req.state()
+        .addr
+        .send(CheckHealth)
+        .timeout(TIMEOUT)
+        .then(|result| {
+            let health = match result {
+                Ok(Ok(health)) => health,
+                result => {
+                    let err = match result {
+                        Err(err) => err.to_string(),
+                        _ => "health check failed".into(),
+                    };
+                    Health::new(vec![ComponentHealth::new("coordinator", Err(err))])
+                }
+            };
+            Ok(respond(&health))
+        })
+        .responder()
+}
+
+/// Responds with the given health, as a success only if everything is working.
+fn respond(health: &Health) -> HttpResponse {
+    if health.ready {
+        HttpResponse::Ok().json(health)
+    } else {
+        HttpResponse::ServiceUnavailable().json(health)
+    }
+}
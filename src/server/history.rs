@@ -0,0 +1,48 @@
+//! Run history endpoints.
+use super::{job::Error, state::State as AppState};
+use crate::history::{Format, Run};
+use actix_web::{HttpRequest, HttpResponse, Json, Query};
+
+use super::job::UUID;
+
+/// Lists the recorded runs, most recent first.
+#[allow(clippy::needless_pass_by_value)]
+pub fn runs(req: HttpRequest<AppState>) -> Result<Json<Vec<Run>>, Error> {
+    Ok(Json(req.state().coord.runs()?))
+}
+
+/// Fetches a single recorded run.
+#[allow(clippy::needless_pass_by_value)]
+pub fn run(uuid: UUID, req: HttpRequest<AppState>) -> Result<Json<Run>, Error> {
+    match req.state().coord.run(*uuid)? {
+        Some(run) => Ok(Json(run)),
+        None => Err(Error::NotFound),
+    }
+}
+
+/// The query string of an export request.
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct ExportQuery {
+    /// The format to export the run in (JSON by default).
+    #[serde(default)]
+    format: Format,
+}
+
+/// Exports a single recorded run as CSV or JSON.
+#[allow(clippy::needless_pass_by_value)]
+pub fn export(
+    uuid: UUID,
+    query: Query<ExportQuery>,
+    req: HttpRequest<AppState>,
+) -> Result<HttpResponse, Error> {
+    let format = query.format;
+    match req.state().coord.export(*uuid, format)? {
+        Some(body) => Ok(HttpResponse::Ok()
+            .content_type(match format {
+                Format::Csv => "text/csv",
+                Format::Json => "application/json",
+            })
+            .body(body)),
+        None => Err(Error::NotFound),
+    }
+}
@@ -1,7 +1,8 @@
-use super::state::State as AppState;
+use super::state::{dispatch, State as AppState};
 use crate::{
-    comm::{Message, State},
-    Action, MotorId, Program, Protocol,
+    comm::{Message, QueuedProtocol, State},
+    Action, BufferConfig, Coordinator, MotorId, Program, Protocol, Recovery, Schedule,
+    ScheduledProtocol,
 };
 use actix_web::{
     http::header, AsyncResponder, FromRequest, HttpMessage, HttpRequest, HttpResponse, Json, Path,
@@ -10,7 +11,7 @@ use actix_web::{
 use futures::prelude::*;
 use uuid::Uuid;
 
-use std::{fmt, ops::Deref};
+use std::{collections::BTreeMap, fmt, ops::Deref};
 
 /// Represents a (buffer-exchange) job to be run.
 #[derive(Deserialize, Serialize)]
@@ -21,6 +22,27 @@ pub struct Job {
     program: Option<Program>,
     remaining: Vec<Action>,
     buffer: Option<MotorId>,
+    /// The name of the most recent buffer, as configured (or `buffer 2`, if it isn't).
+    #[serde(default)]
+    buffer_name: Option<String>,
+}
+
+impl Job {
+    /// The job the coordinator is running (or most recently ran), if any.
+    pub fn current(coord: &Coordinator) -> Option<Self> {
+        let id = coord.state.uuid?;
+        Some(Self {
+            id,
+            state: coord.status(),
+            program: coord.state.program.clone(),
+            remaining: coord.state.remaining.clone(),
+            buffer: coord.state.buffer,
+            buffer_name: coord
+                .state
+                .buffer
+                .map(|buffer| coord.buffers().name(buffer)),
+        })
+    }
 }
 
 /// Job request error type.
@@ -31,6 +53,7 @@ pub enum Error {
     Mailbox(actix_web::actix::MailboxError),
     InvalidUuid,
     IncorrectUuid,
+    NotFound,
     ActixWeb(actix_web::Error),
 }
 
@@ -61,17 +84,25 @@ impl From<actix_web::Error> for Error {
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Self::Coordinator(_e) => unimplemented!(),
+            Self::Coordinator(e) => e.fmt(f),
             Self::Json(e) => e.fmt(f),
             Self::Mailbox(e) => e.fmt(f),
             Self::InvalidUuid => write!(f, "Invalid UUID"),
             Self::IncorrectUuid => write!(f, "Specified job is no longer active."),
+            Self::NotFound => write!(f, "Not found"),
             Self::ActixWeb(e) => e.fmt(f),
         }
     }
 }
 
-impl std::error::Error for Error {}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Coordinator(e) => std::error::Error::source(e),
+            _ => None,
+        }
+    }
+}
 
 impl ResponseError for Error {
     fn error_response(&self) -> HttpResponse {
@@ -83,23 +114,25 @@ impl ResponseError for Error {
 // TODO: HEAD support
 #[allow(clippy::needless_pass_by_value)]
 pub fn status(req: HttpRequest<AppState>) -> Json<Option<Job>> {
-    let coord = &req.state().coord;
-    if let Some(uuid) = coord.state.uuid {
-        let state = coord.status();
-        let program = coord.state.program.clone();
-        let remaining = coord.state.remaining.clone();
-        let buffer = coord.state.buffer;
-        let job = Job {
-            id: uuid,
-            state,
-            program,
-            remaining,
-            buffer,
-        };
-        Json(Some(job))
-    } else {
-        Json(None)
-    }
+    Json(Job::current(&req.state().coord))
+}
+
+/// The current status of each manifold, by name.
+#[allow(clippy::needless_pass_by_value)]
+pub fn manifolds(req: HttpRequest<AppState>) -> Json<BTreeMap<String, Option<Job>>> {
+    let manifolds = req
+        .state()
+        .manifolds
+        .iter()
+        .map(|(name, manifold)| (name.clone(), Job::current(&manifold.coord)))
+        .collect();
+    Json(manifolds)
+}
+
+/// The configured buffers, for referring to them by name.
+#[allow(clippy::needless_pass_by_value)]
+pub fn buffers(req: HttpRequest<AppState>) -> Json<Vec<BufferConfig>> {
+    Json(req.state().coord.buffers().iter().cloned().collect())
 }
 
 /// Creates and starts a new job if the system is ready.
@@ -112,10 +145,8 @@ pub fn start(req: HttpRequest<AppState>) -> Box<dyn Future<Item = HttpResponse,
             if !coord.is_stopped() {
                 Err(Error::from(crate::comm::Error::Busy))
             } else {
-                let addr = &req.state().addr;
                 let id = Uuid::new_v4();
-                let result = addr
-                    .send(Message::Start(proto, Some(id)))
+                let result = dispatch(&req, Message::Start(proto, Some(id)))
                     .map(move |_| {
                         HttpResponse::Created()
                             .header(self::header::LOCATION, format!("{}", id))
@@ -129,6 +160,140 @@ pub fn start(req: HttpRequest<AppState>) -> Box<dyn Future<Item = HttpResponse,
         .responder()
 }
 
+/// The jobs waiting to be run, in order.
+#[allow(clippy::needless_pass_by_value)]
+pub fn queue(req: HttpRequest<AppState>) -> Json<Vec<QueuedProtocol>> {
+    Json(req.state().coord.state.queue.clone())
+}
+
+/// Adds a new job to the end of the queue, starting it right away if the system is idle.
+#[allow(clippy::needless_pass_by_value)]
+pub fn enqueue(req: HttpRequest<AppState>) -> Box<dyn Future<Item = HttpResponse, Error = Error>> {
+    req.json()
+        .from_err()
+        .and_then(move |proto: Protocol| {
+            let id = Uuid::new_v4();
+            dispatch(&req, Message::Enqueue(proto, Some(id)))
+                .from_err()
+                .and_then(|result| result.map_err(Error::from))
+                .map(move |_| {
+                    HttpResponse::Created()
+                        .header(self::header::LOCATION, format!("{}", id))
+                        .finish()
+                })
+        })
+        .responder()
+}
+
+/// Removes a job from the queue.
+#[allow(clippy::needless_pass_by_value)]
+pub fn dequeue(
+    uuid: UUID,
+    req: HttpRequest<AppState>,
+) -> Box<dyn Future<Item = HttpResponse, Error = Error>> {
+    dispatch(&req, Message::Dequeue(*uuid))
+        .from_err()
+        .and_then(|result| result.map_err(Error::from))
+        .map(|_| HttpResponse::NoContent().finish())
+        .responder()
+}
+
+/// Moves a queued job to the given position in the queue (where 0 is next).
+#[allow(clippy::needless_pass_by_value)]
+pub fn requeue(
+    uuid: UUID,
+    index: Json<usize>,
+    req: HttpRequest<AppState>,
+) -> Box<dyn Future<Item = HttpResponse, Error = Error>> {
+    let message = Message::Requeue {
+        id: *uuid,
+        index: index.into_inner(),
+    };
+    dispatch(&req, message)
+        .from_err()
+        .and_then(|result| result.map_err(Error::from))
+        .map(|_| HttpResponse::NoContent().finish())
+        .responder()
+}
+
+/// Resumes or safely aborts a job that was interrupted by a crash or power loss.
+#[allow(clippy::needless_pass_by_value)]
+pub fn recover(req: HttpRequest<AppState>) -> Box<dyn Future<Item = HttpResponse, Error = Error>> {
+    req.json()
+        .from_err()
+        .and_then(move |recovery: Recovery| {
+            dispatch(&req, Message::Recover(recovery))
+                .from_err()
+                .and_then(|result| result.map_err(Error::from))
+                .map(|_| HttpResponse::NoContent().finish())
+        })
+        .responder()
+}
+
+/// Sets the operator recorded for the runs started from now on.
+#[allow(clippy::needless_pass_by_value)]
+pub fn operator(req: HttpRequest<AppState>) -> Box<dyn Future<Item = HttpResponse, Error = Error>> {
+    req.json()
+        .from_err()
+        .and_then(move |operator: Option<String>| {
+            dispatch(&req, Message::Operator(operator))
+                .from_err()
+                .and_then(|result| result.map_err(Error::from))
+                .map(|_| HttpResponse::NoContent().finish())
+        })
+        .responder()
+}
+
+/// A request to run a protocol at a later time.
+#[derive(Deserialize)]
+pub struct ScheduleRequest {
+    protocol: Protocol,
+    schedule: Schedule,
+}
+
+/// The protocols waiting for their scheduled starts.
+#[allow(clippy::needless_pass_by_value)]
+pub fn schedules(req: HttpRequest<AppState>) -> Json<Vec<ScheduledProtocol>> {
+    Json(req.state().coord.state.scheduled.clone())
+}
+
+/// Schedules a protocol to be queued at the given time (and, optionally, repeatedly thereafter).
+#[allow(clippy::needless_pass_by_value)]
+pub fn schedule(req: HttpRequest<AppState>) -> Box<dyn Future<Item = HttpResponse, Error = Error>> {
+    req.json()
+        .from_err()
+        .and_then(move |request: ScheduleRequest| {
+            let id = Uuid::new_v4();
+            let message = Message::Schedule {
+                protocol: request.protocol,
+                schedule: request.schedule,
+                id: Some(id),
+            };
+            dispatch(&req, message)
+                .from_err()
+                .and_then(|result| result.map_err(Error::from))
+                .map(move |_| {
+                    HttpResponse::Created()
+                        .header(self::header::LOCATION, format!("{}", id))
+                        .finish()
+                })
+        })
+        .responder()
+}
+
+/// Cancels a pending schedule.
+#[allow(clippy::needless_pass_by_value)]
+pub fn unschedule(
+    uuid: UUID,
+    req: HttpRequest<AppState>,
+) -> Box<dyn Future<Item = HttpResponse, Error = Error>> {
+    dispatch(&req, Message::Unschedule(*uuid))
+        .from_err()
+        .and_then(|result| result.map_err(Error::from))
+        .map(|_| HttpResponse::NoContent().finish())
+        .responder()
+}
+
 /// Wrapper type around `Uuid`.
 ///
 /// This struct implements some convenience methods and helps us avoid the orphan rules.
@@ -173,8 +338,7 @@ fn message_uuid(
 ) -> Box<dyn Future<Item = HttpResponse, Error = Error>> {
     let state = &req.state();
     (if uuid.is_current(&state) {
-        let addr = &state.addr;
-        Ok(addr.send(message))
+        Ok(dispatch(&req, message))
     } else {
         Err(Error::IncorrectUuid)
     })
@@ -194,6 +358,42 @@ pub fn resume(
     message_uuid(Message::Continue, uuid, req)
 }
 
+/// Pauses the running job, stopping the pump until it is unpaused.
+#[allow(clippy::needless_pass_by_value)]
+pub fn pause(
+    uuid: UUID,
+    req: HttpRequest<AppState>,
+) -> Box<dyn Future<Item = HttpResponse, Error = Error>> {
+    message_uuid(Message::Pause, uuid, req)
+}
+
+/// Picks a paused job back up where it left off.
+#[allow(clippy::needless_pass_by_value)]
+pub fn unpause(
+    uuid: UUID,
+    req: HttpRequest<AppState>,
+) -> Box<dyn Future<Item = HttpResponse, Error = Error>> {
+    message_uuid(Message::Resume, uuid, req)
+}
+
+/// Skips the rest of the current step of a paused job.
+#[allow(clippy::needless_pass_by_value)]
+pub fn skip(
+    uuid: UUID,
+    req: HttpRequest<AppState>,
+) -> Box<dyn Future<Item = HttpResponse, Error = Error>> {
+    message_uuid(Message::SkipStep, uuid, req)
+}
+
+/// Aborts the running job, running the configured teardown sequence.
+#[allow(clippy::needless_pass_by_value)]
+pub fn abort(
+    uuid: UUID,
+    req: HttpRequest<AppState>,
+) -> Box<dyn Future<Item = HttpResponse, Error = Error>> {
+    message_uuid(Message::Abort, uuid, req)
+}
+
 /// Immediately stops the running job.
 #[allow(clippy::needless_pass_by_value)]
 pub fn halt(
@@ -214,3 +414,13 @@ pub fn stop(
     let message = Message::Stop;
     message_uuid(message, uuid, req)
 }
+
+/// Clears a latched fault (such as an emergency stop) so that new jobs may be started.
+#[allow(clippy::needless_pass_by_value)]
+pub fn reset(req: HttpRequest<AppState>) -> Box<dyn Future<Item = HttpResponse, Error = Error>> {
+    dispatch(&req, Message::Reset)
+        .from_err()
+        .and_then(|result| result.map_err(Error::from))
+        .map(|_| HttpResponse::NoContent().finish())
+        .responder()
+}
@@ -0,0 +1,76 @@
+//! Manual control endpoints, for setup and troubleshooting.
+//!
+//! These only work while no protocol is running.
+use super::{
+    job::Error,
+    state::{dispatch, State as AppState},
+};
+use crate::{comm::Message, Jog, MotorId, PumpDirection};
+use actix_web::{AsyncResponder, HttpMessage, HttpRequest, HttpResponse, Path};
+use futures::prelude::*;
+
+use std::time::Duration;
+
+/// A request to run the pump.
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct PumpRun {
+    /// The direction to run the pump in.
+    direction: PumpDirection,
+    /// How long to run the pump for, in seconds.
+    seconds: u64,
+}
+
+/// Sends the given manual action to the coordinator, responding with no content on success.
+fn send(
+    jog: Jog,
+    req: &HttpRequest<AppState>,
+) -> Box<dyn Future<Item = HttpResponse, Error = Error>> {
+    dispatch(req, Message::Jog(jog))
+        .from_err()
+        .and_then(|result| result.map_err(Error::from))
+        .map(|_| HttpResponse::NoContent().finish())
+        .responder()
+}
+
+/// Opens the given valve (where valve 0 is waste).
+#[allow(clippy::needless_pass_by_value)]
+pub fn open(
+    valve: Path<MotorId>,
+    req: HttpRequest<AppState>,
+) -> Box<dyn Future<Item = HttpResponse, Error = Error>> {
+    send(Jog::Open(valve.into_inner()), &req)
+}
+
+/// Closes the given valve (where valve 0 is waste).
+#[allow(clippy::needless_pass_by_value)]
+pub fn close(
+    valve: Path<MotorId>,
+    req: HttpRequest<AppState>,
+) -> Box<dyn Future<Item = HttpResponse, Error = Error>> {
+    send(Jog::Close(valve.into_inner()), &req)
+}
+
+/// Runs the configured wash routine.
+#[allow(clippy::needless_pass_by_value)]
+pub fn wash(req: HttpRequest<AppState>) -> Box<dyn Future<Item = HttpResponse, Error = Error>> {
+    dispatch(&req, Message::Wash)
+        .from_err()
+        .and_then(|result| result.map_err(Error::from))
+        .map(|_| HttpResponse::NoContent().finish())
+        .responder()
+}
+
+/// Runs the pump in the given direction for the given time.
+#[allow(clippy::needless_pass_by_value)]
+pub fn pump(req: HttpRequest<AppState>) -> Box<dyn Future<Item = HttpResponse, Error = Error>> {
+    req.json()
+        .from_err()
+        .and_then(move |run: PumpRun| {
+            let jog = Jog::Pump {
+                direction: run.direction,
+                duration: Duration::from_secs(run.seconds),
+            };
+            send(jog, &req)
+        })
+        .responder()
+}
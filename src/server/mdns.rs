@@ -0,0 +1,42 @@
+//! Advertisement of the server over mDNS/DNS-SD, so that clients can find instruments without
+//! knowing their addresses.
+use libmdns::{Responder, Service};
+
+use std::{fmt, io};
+
+/// The DNS-SD service type under which instruments are advertised.
+pub const SERVICE_TYPE: &str = "_deoxy._tcp";
+
+/// The name instruments are advertised under if none is configured.
+pub const DEFAULT_NAME: &str = "deoxy";
+
+/// An active advertisement, which lasts until it's dropped.
+pub struct Advertisement {
+    // The service must be dropped before the responder it was registered with.
+    _service: Service,
+    _responder: Responder,
+}
+
+impl fmt::Debug for Advertisement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Advertisement").finish()
+    }
+}
+
+/// Advertises the server listening on the given port as `<name>._deoxy._tcp.local`.
+///
+/// The TXT record gives the version of the server.
+pub fn advertise(name: Option<&str>, port: u16) -> io::Result<Advertisement> {
+    let responder = Responder::new()?;
+    let version = format!("version={}", env!("CARGO_PKG_VERSION"));
+    let service = responder.register(
+        SERVICE_TYPE.into(),
+        name.unwrap_or(DEFAULT_NAME).into(),
+        port,
+        &[&version],
+    );
+    Ok(Advertisement {
+        _service: service,
+        _responder: responder,
+    })
+}
@@ -1,24 +1,148 @@
 //! Web server utilities.
+mod auth;
+mod cors;
+mod events;
+mod health;
+#[cfg(feature = "history")]
+mod history;
 mod job;
+mod manual;
+#[cfg(feature = "mdns")]
+mod mdns;
+mod motor;
+mod protocol;
+mod socket;
 mod state;
+mod throttle;
+#[cfg(feature = "ui")]
+mod ui;
 use actix_web::{http::Method, App};
 
-/// Returns an actix-web app for handling jobs.
-fn job_app(state: state::State) -> App<state::State> {
-    App::with_state(state)
-        .route("/", Method::GET, job::status)
-        .route("/", Method::HEAD, job::status)
+#[cfg(feature = "mdns")]
+pub use self::mdns::{advertise, Advertisement, SERVICE_TYPE};
+
+/// Returns an actix-web app for handling jobs under the given prefix.
+fn job_app(state: state::State, prefix: &str) -> App<state::State> {
+    let app = App::with_state(state).prefix(prefix);
+    #[cfg(not(feature = "ui"))]
+    let app = app.route("/", Method::GET, job::status);
+    #[cfg(feature = "ui")]
+    let app = app
+        .route("/", Method::GET, ui::root)
+        .default_resource(|r| r.f(ui::asset));
+    app.route("/", Method::HEAD, job::status)
         .route("/", Method::POST, job::start)
+        .route("/healthz", Method::GET, health::healthz)
+        .route("/readyz", Method::GET, health::readyz)
+        .route("/info", Method::GET, health::info)
+        .route("/reset", Method::POST, job::reset)
+        .route("/recover", Method::POST, job::recover)
+        .route("/operator", Method::PUT, job::operator)
+        .resource("/ws/status", |r| r.method(Method::GET).f(socket::status))
+        .resource("/events", |r| r.method(Method::GET).f(events::stream))
+        .route("/schedule", Method::GET, job::schedules)
+        .route("/schedule", Method::POST, job::schedule)
+        .resource("/schedule/{job}", |r| {
+            r.method(Method::DELETE).with(job::unschedule)
+        })
+        .route("/buffers", Method::GET, job::buffers)
+        .route("/queue", Method::GET, job::queue)
+        .route("/queue", Method::POST, job::enqueue)
+        .resource("/queue/{job}", |r| {
+            r.method(Method::DELETE).with(job::dequeue)
+        })
+        .resource("/queue/{job}/move", |r| {
+            r.method(Method::POST).with(job::requeue)
+        })
         .resource("/{job}", |r| r.method(Method::DELETE).with(job::stop))
         .resource("/{job}/halt", |r| r.method(Method::POST).with(job::stop))
         .resource("/{job}/resume", |r| {
             r.method(Method::POST).with(job::resume)
         })
+        .resource("/{job}/skip", |r| r.method(Method::POST).with(job::skip))
+        .resource("/{job}/abort", |r| r.method(Method::POST).with(job::abort))
+        .resource("/{job}/pause", |r| r.method(Method::POST).with(job::pause))
+        .resource("/{job}/unpause", |r| {
+            r.method(Method::POST).with(job::unpause)
+        })
+}
+
+/// Returns an actix-web app for calibrating motors under the given prefix.
+fn motor_app(state: state::State, prefix: &str) -> App<state::State> {
+    App::with_state(state)
+        .prefix(format!("{}/motors", prefix))
+        .resource("/{motor}/calibrate", |r| {
+            r.method(Method::POST).with(motor::calibrate)
+        })
+        .route("/calibration", Method::POST, motor::adjust)
+        .route("/calibration/finish", Method::POST, motor::finish)
+}
+
+/// Returns an actix-web app for controlling the hardware directly under the given prefix.
+fn manual_app(state: state::State, prefix: &str) -> App<state::State> {
+    App::with_state(state)
+        .prefix(format!("{}/manual", prefix))
+        .resource("/valves/{valve}/open", |r| {
+            r.method(Method::POST).with(manual::open)
+        })
+        .resource("/valves/{valve}/close", |r| {
+            r.method(Method::POST).with(manual::close)
+        })
+        .route("/pump", Method::POST, manual::pump)
+        .route("/wash", Method::POST, manual::wash)
+}
+
+/// Returns an actix-web app for listing the manifolds.
+fn manifold_app(state: state::State) -> App<state::State> {
+    App::with_state(state)
+        .prefix("/manifolds")
+        .route("/", Method::GET, job::manifolds)
+}
+
+/// Returns an actix-web app for browsing past runs.
+#[cfg(feature = "history")]
+fn history_app(state: state::State) -> App<state::State> {
+    App::with_state(state)
+        .prefix("/runs")
+        .route("/", Method::GET, history::runs)
+        .resource("/{job}", |r| r.method(Method::GET).with(history::run))
+        .resource("/{job}/export", |r| {
+            r.method(Method::GET).with(history::export)
+        })
+}
+
+/// Returns an actix-web app for managing API tokens.
+fn token_app(state: state::State) -> App<state::State> {
+    App::with_state(state)
+        .prefix("/tokens")
+        .route("/", Method::POST, auth::issue)
+        .resource("/{token}", |r| r.method(Method::DELETE).with(auth::revoke))
+}
+
+/// Returns an actix-web app for launching cataloged protocol files.
+fn catalog_app(state: state::State) -> App<state::State> {
+    App::with_state(state)
+        .prefix("/catalog")
+        .route("/", Method::GET, protocol::catalog)
+        .resource("/{name}", |r| r.method(Method::POST).with(protocol::launch))
 }
 
 /// Returns an actix-web app for handling protocols.
 fn protocol_app(state: state::State) -> App<state::State> {
     App::with_state(state)
+        .prefix("/protocols")
+        .route("/", Method::GET, protocol::list)
+        .route("/", Method::POST, protocol::create)
+        .route("/validate", Method::POST, protocol::validate)
+        .route("/import", Method::POST, protocol::import)
+        .resource("/{id}", |r| {
+            r.method(Method::GET).with(protocol::fetch);
+            r.method(Method::PUT).with(protocol::update);
+            r.method(Method::DELETE).with(protocol::delete);
+        })
+        .resource("/{id}/autoprotocol", |r| {
+            r.method(Method::GET).with(protocol::export)
+        })
 }
 
 fn state() -> state::State {
@@ -28,5 +152,36 @@ fn state() -> state::State {
 /// Returns the list of actix-web apps to be used with the server.
 pub fn apps() -> Vec<App<state::State>> {
     let state = state();
-    vec![job_app(state.clone()), protocol_app(state.clone())]
+    let mut apps = vec![];
+    // Each manifold can be addressed by name; the main manifold is also at the top level.
+    for name in state.manifolds.keys() {
+        if let Some(scoped) = state.manifold(name) {
+            let prefix = format!("/manifolds/{}", name);
+            apps.push(motor_app(scoped.clone(), &prefix));
+            apps.push(manual_app(scoped.clone(), &prefix));
+            apps.push(job_app(scoped, &prefix));
+        }
+    }
+    apps.extend(vec![
+        manifold_app(state.clone()),
+        motor_app(state.clone(), ""),
+        manual_app(state.clone(), ""),
+        protocol_app(state.clone()),
+        catalog_app(state.clone()),
+        token_app(state.clone()),
+    ]);
+    #[cfg(feature = "history")]
+    apps.push(history_app(state.clone()));
+    // Without a prefix, the job app takes every request that reaches it, so it has to come last.
+    apps.push(job_app(state.clone(), ""));
+    // Preflight requests carry no token, so CORS has to be handled before authentication.
+    let cors = state.cors.as_ref().map(cors::policy);
+    apps.into_iter()
+        .map(|app| match cors {
+            Some(ref cors) => app.middleware(cors.clone()),
+            None => app,
+        })
+        .map(|app| app.middleware(auth::Authenticate))
+        .map(|app| app.middleware(throttle::Throttle))
+        .collect()
 }
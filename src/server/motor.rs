@@ -0,0 +1,44 @@
+//! Motor calibration endpoints.
+use super::{
+    job::Error,
+    state::{dispatch, State as AppState},
+};
+use crate::{comm::Message, motor::Calibrate, MotorId};
+use actix_web::{AsyncResponder, HttpMessage, HttpRequest, HttpResponse, Path};
+use futures::prelude::*;
+
+/// Sends the given message to the coordinator, responding with no content on success.
+fn send(
+    message: Message,
+    req: &HttpRequest<AppState>,
+) -> Box<dyn Future<Item = HttpResponse, Error = Error>> {
+    dispatch(req, message)
+        .from_err()
+        .and_then(|result| result.map_err(Error::from))
+        .map(|_| HttpResponse::NoContent().finish())
+        .responder()
+}
+
+/// Begins calibrating the given motor.
+#[allow(clippy::needless_pass_by_value)]
+pub fn calibrate(
+    motor: Path<MotorId>,
+    req: HttpRequest<AppState>,
+) -> Box<dyn Future<Item = HttpResponse, Error = Error>> {
+    send(Message::Calibrate(motor.into_inner()), &req)
+}
+
+/// Jogs the motor being calibrated or marks its position.
+#[allow(clippy::needless_pass_by_value)]
+pub fn adjust(req: HttpRequest<AppState>) -> Box<dyn Future<Item = HttpResponse, Error = Error>> {
+    req.json()
+        .from_err()
+        .and_then(move |request: Calibrate| send(Message::Adjust(request), &req))
+        .responder()
+}
+
+/// Finishes calibrating the motor, saving the calibration.
+#[allow(clippy::needless_pass_by_value)]
+pub fn finish(req: HttpRequest<AppState>) -> Box<dyn Future<Item = HttpResponse, Error = Error>> {
+    send(Message::FinishCalibration, &req)
+}
@@ -0,0 +1,177 @@
+//! Protocol endpoints.
+use super::{
+    job::{Error, UUID},
+    state::{dispatch, State as AppState},
+};
+use crate::{
+    comm::{Error as CoordError, Message},
+    Autoprotocol, Bindings, CatalogEntry, Protocol, StoredProtocol, Validation,
+};
+use actix_web::{
+    error::JsonPayloadError, http::header, AsyncResponder, HttpMessage, HttpRequest, HttpResponse,
+    Json, Path,
+};
+use futures::prelude::*;
+use uuid::Uuid;
+
+/// A request to store a protocol in the library.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ProtocolRequest {
+    /// A human-readable name for the protocol.
+    name: String,
+    /// The protocol itself.
+    protocol: Protocol,
+}
+
+/// Checks a protocol against the system's hardware without running it.
+#[allow(clippy::needless_pass_by_value)]
+pub fn validate(
+    req: HttpRequest<AppState>,
+) -> Box<dyn Future<Item = Json<Validation>, Error = Error>> {
+    req.json()
+        .from_err()
+        .map(move |proto: Protocol| Json(proto.check(&req.state().coord.hardware())))
+        .responder()
+}
+
+/// Validates and stores the given protocol under the given label.
+fn store(
+    id: Uuid,
+    request: ProtocolRequest,
+    req: &HttpRequest<AppState>,
+) -> Result<StoredProtocol, Error> {
+    let coord = &req.state().coord;
+    let validation = request.protocol.check(&coord.hardware());
+    if !validation.is_ok() {
+        return Err(CoordError::Invalid(validation).into());
+    }
+    let stored = StoredProtocol::new(id, request.name, request.protocol);
+    coord
+        .library()?
+        .save(&stored)
+        .map_err(CoordError::Library)?;
+    Ok(stored)
+}
+
+/// Lists the stored protocols, ordered by name.
+#[allow(clippy::needless_pass_by_value)]
+pub fn list(req: HttpRequest<AppState>) -> Result<Json<Vec<StoredProtocol>>, Error> {
+    let library = req.state().coord.library()?;
+    Ok(Json(library.list().map_err(CoordError::Library)?))
+}
+
+/// Stores a new protocol, responding with its location.
+#[allow(clippy::needless_pass_by_value)]
+pub fn create(req: HttpRequest<AppState>) -> Box<dyn Future<Item = HttpResponse, Error = Error>> {
+    req.json()
+        .from_err()
+        .and_then(move |request: ProtocolRequest| {
+            let stored = store(Uuid::new_v4(), request, &req)?;
+            Ok(HttpResponse::Created()
+                .header(header::LOCATION, format!("{}", stored.id))
+                .json(stored))
+        })
+        .responder()
+}
+
+/// Fetches a stored protocol.
+#[allow(clippy::needless_pass_by_value)]
+pub fn fetch(uuid: UUID, req: HttpRequest<AppState>) -> Result<Json<StoredProtocol>, Error> {
+    let library = req.state().coord.library()?;
+    match library.get(*uuid).map_err(CoordError::Library)? {
+        Some(stored) => Ok(Json(stored)),
+        None => Err(Error::NotFound),
+    }
+}
+
+/// Converts a protocol in Autoprotocol for the system's hardware, responding with the converted
+/// protocol (which may then be checked, stored, or run).
+#[allow(clippy::needless_pass_by_value)]
+pub fn import(req: HttpRequest<AppState>) -> Box<dyn Future<Item = Json<Protocol>, Error = Error>> {
+    req.json()
+        .from_err()
+        .and_then(move |autoprotocol: Autoprotocol| {
+            Ok(Json(req.state().coord.import(&autoprotocol)?))
+        })
+        .responder()
+}
+
+/// Fetches a stored protocol in Autoprotocol.
+#[allow(clippy::needless_pass_by_value)]
+pub fn export(uuid: UUID, req: HttpRequest<AppState>) -> Result<Json<Autoprotocol>, Error> {
+    let coord = &req.state().coord;
+    match coord.library()?.get(*uuid).map_err(CoordError::Library)? {
+        Some(stored) => Ok(Json(coord.to_autoprotocol(&stored.protocol)?)),
+        None => Err(Error::NotFound),
+    }
+}
+
+/// Replaces a stored protocol.
+#[allow(clippy::needless_pass_by_value)]
+pub fn update(
+    uuid: UUID,
+    req: HttpRequest<AppState>,
+) -> Box<dyn Future<Item = Json<StoredProtocol>, Error = Error>> {
+    req.json()
+        .from_err()
+        .and_then(move |request: ProtocolRequest| {
+            let library = req.state().coord.library()?;
+            if library.get(*uuid).map_err(CoordError::Library)?.is_none() {
+                return Err(Error::NotFound);
+            }
+            Ok(Json(store(*uuid, request, &req)?))
+        })
+        .responder()
+}
+
+/// Lists the cataloged protocol files, ordered by name.
+#[allow(clippy::needless_pass_by_value)]
+pub fn catalog(req: HttpRequest<AppState>) -> Result<Json<Vec<CatalogEntry>>, Error> {
+    Ok(Json(req.state().coord.catalog()?.list()))
+}
+
+/// Queues the cataloged protocol file with the given name (starting it right away if the system is
+/// idle), responding with the location of the new job.
+///
+/// Values for the file's parameters may be given as a JSON object in the body (e.g.
+/// `{"soak": 1800, "detergent": "1% SDS"}`); parameters not given are left at their defaults.
+#[allow(clippy::needless_pass_by_value)]
+pub fn launch(
+    name: Path<String>,
+    req: HttpRequest<AppState>,
+) -> Box<dyn Future<Item = HttpResponse, Error = Error>> {
+    req.body()
+        .map_err(|err| Error::from(actix_web::Error::from(err)))
+        .and_then(move |body| {
+            let values = if body.is_empty() {
+                Bindings::new()
+            } else {
+                serde_json::from_slice(&body).map_err(JsonPayloadError::Deserialize)?
+            };
+            let protocol = req.state().coord.cataloged(&name, &values)?;
+            Ok((protocol, req))
+        })
+        .and_then(|(protocol, req)| {
+            let id = Uuid::new_v4();
+            dispatch(&req, Message::Enqueue(protocol, Some(id)))
+                .from_err()
+                .and_then(|result| result.map_err(Error::from))
+                .map(move |_| {
+                    HttpResponse::Created()
+                        .header(header::LOCATION, format!("/{}", id))
+                        .finish()
+                })
+        })
+        .responder()
+}
+
+/// Deletes a stored protocol.
+#[allow(clippy::needless_pass_by_value)]
+pub fn delete(uuid: UUID, req: HttpRequest<AppState>) -> Result<HttpResponse, Error> {
+    let library = req.state().coord.library()?;
+    if library.remove(*uuid).map_err(CoordError::Library)? {
+        Ok(HttpResponse::NoContent().finish())
+    } else {
+        Err(Error::NotFound)
+    }
+}
@@ -0,0 +1,97 @@
+//! Live status updates over a WebSocket.
+use super::{job::Job, state::State as AppState};
+use crate::{
+    actix::*,
+    comm::{Message, Subscribers},
+    event_log::Event,
+    Status, Update,
+};
+use actix_web::{
+    actix::{Recipient, StreamHandler},
+    ws, HttpRequest, HttpResponse,
+};
+
+use std::cell::Cell;
+
+/// A frame sent to status stream clients.
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Frame {
+    /// The state of the coordinator when the client connected.
+    Snapshot(Option<Job>),
+    /// A change in the coordinator's state.
+    Update(Event),
+}
+
+/// Forwards coordinator updates to a connected client.
+#[derive(Debug)]
+struct Feed {
+    socket: Recipient<Event>,
+    connected: Cell<bool>,
+}
+
+impl Update for Feed {
+    fn handle(&self, status: &Status, _coord: &Subscribers) {
+        // `do_send` ignores mailbox capacity, so this only fails once the socket has closed.
+        if self.socket.do_send(Event::new(status)).is_err() {
+            self.connected.set(false);
+        }
+    }
+    fn is_connected(&self) -> bool {
+        self.connected.get()
+    }
+}
+
+/// A client connected to the status stream.
+struct StatusSocket {
+    /// The snapshot to send on connecting.
+    snapshot: Option<Job>,
+}
+
+impl StatusSocket {
+    /// Sends the given frame to the client.
+    fn send(frame: &Frame, context: &mut <Self as Actor>::Context) {
+        match serde_json::to_string(frame) {
+            Ok(text) => context.text(text),
+            Err(err) => log::error!("Failed to serialize status frame: {}", err),
+        }
+    }
+}
+
+impl Actor for StatusSocket {
+    type Context = ws::WebsocketContext<Self, AppState>;
+    fn started(&mut self, context: &mut Self::Context) {
+        Self::send(&Frame::Snapshot(self.snapshot.take()), context);
+        let feed = Feed {
+            socket: context.address().recipient(),
+            connected: Cell::new(true),
+        };
+        context
+            .state()
+            .addr
+            .do_send(Message::Subscribe(Box::new(feed)));
+    }
+}
+
+impl Handle<Event> for StatusSocket {
+    type Result = ();
+    fn handle(&mut self, event: Event, context: &mut Self::Context) {
+        Self::send(&Frame::Update(event), context);
+    }
+}
+
+impl StreamHandler<ws::Message, ws::ProtocolError> for StatusSocket {
+    fn handle(&mut self, message: ws::Message, context: &mut Self::Context) {
+        match message {
+            ws::Message::Ping(message) => context.pong(&message),
+            ws::Message::Close(_) => context.stop(),
+            _ => {}
+        }
+    }
+}
+
+/// Streams the coordinator's status updates, beginning with a snapshot of its current state.
+pub fn status(req: &HttpRequest<AppState>) -> Result<HttpResponse, actix_web::Error> {
+    let snapshot = Job::current(&req.state().coord);
+    ws::start(req, StatusSocket { snapshot })
+}
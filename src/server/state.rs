@@ -1,7 +1,13 @@
 //! App state management.
-use crate::{actix::Addr, Coordinator};
+use super::{
+    auth::{Holder, Tokens},
+    events::Backlog,
+    throttle::Limiter,
+};
+use crate::{actix::Addr, comm::Message, Config, Coordinator, CorsConfig, MAIN_MANIFOLD};
+use actix_web::{actix::dev::Request, HttpRequest};
 
-use std::sync::Arc;
+use std::{collections::BTreeMap, path::PathBuf, sync::Arc};
 
 /// Contains the coordinator and other required state components.
 #[derive(Clone, Debug)]
@@ -11,4 +17,77 @@ pub struct State {
     pub coord: Arc<Coordinator>,
     /// The address of the coordinator.
     pub addr: Addr<Coordinator>,
+    /// The API tokens accepted.
+    pub tokens: Arc<Tokens>,
+    /// Recent status updates, for the server-sent event stream.
+    pub events: Arc<Backlog>,
+    /// The CORS policy, if other origins may use the server.
+    pub cors: Option<CorsConfig>,
+    /// The limits on clients' control requests.
+    pub limits: Arc<Limiter>,
+    /// Every manifold, by name (including the main manifold, to which the fields above belong).
+    pub manifolds: Arc<BTreeMap<String, Manifold>>,
+    /// The configuration file in use (see [`Config::locate`](../struct.Config.html#method.locate)),
+    /// if the configuration was read from one.
+    pub config: Option<Arc<PathBuf>>,
+}
+
+impl State {
+    /// Creates the state for serving the given (main manifold's) coordinator, with the tokens,
+    /// CORS policy, and limits given in its configuration.
+    pub fn new(coord: Arc<Coordinator>, addr: Addr<Coordinator>, config: &Config) -> Self {
+        let events = Arc::new(Backlog::default());
+        let main = Manifold {
+            coord: Arc::clone(&coord),
+            addr: addr.clone(),
+            events: Arc::clone(&events),
+        };
+        let mut manifolds = BTreeMap::new();
+        manifolds.insert(MAIN_MANIFOLD.to_string(), main);
+        Self {
+            coord,
+            addr,
+            tokens: Arc::new(Tokens::new(&config.tokens, config.bootstrap_token.clone())),
+            events,
+            cors: config.cors.clone(),
+            limits: Arc::new(Limiter::new(config.throttle)),
+            manifolds: Arc::new(manifolds),
+            config: None,
+        }
+    }
+    /// The state for handling requests addressed to the manifold with the given name, if there is
+    /// one.
+    pub fn manifold(&self, name: &str) -> Option<Self> {
+        let manifold = self.manifolds.get(name)?;
+        Some(Self {
+            coord: Arc::clone(&manifold.coord),
+            addr: manifold.addr.clone(),
+            events: Arc::clone(&manifold.events),
+            ..self.clone()
+        })
+    }
+}
+
+/// A manifold's coordinator, as seen by the server.
+#[derive(Clone, Debug)]
+pub struct Manifold {
+    /// The coordinator.
+    pub coord: Arc<Coordinator>,
+    /// The address of the coordinator.
+    pub addr: Addr<Coordinator>,
+    /// Recent status updates, for the server-sent event stream.
+    pub events: Arc<Backlog>,
+}
+
+/// Sends the given message to the coordinator, crediting it to the holder of the request's token
+/// (if any).
+pub fn dispatch(req: &HttpRequest<State>, message: Message) -> Request<Coordinator, Message> {
+    let message = match req.extensions().get::<Holder>() {
+        Some(holder) => Message::Acting {
+            user: holder.name.clone(),
+            message: Box::new(message),
+        },
+        None => message,
+    };
+    req.state().addr.send(message)
 }
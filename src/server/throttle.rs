@@ -0,0 +1,192 @@
+//! Rate limiting and debouncing of control requests.
+//!
+//! Each client (identified by its token, if it presented one, or by its address) gets a bucket of
+//! commands which refills at the configured rate, so that a misbehaving script can't flood the
+//! coordinator. Repeating a command within the debounce period (e.g. double-clicking "start") is
+//! refused outright.
+use super::{auth::Holder, state::State as AppState};
+use crate::ThrottleConfig;
+use actix_web::{
+    http::{header, Method},
+    middleware::{Middleware, Started},
+    HttpRequest, HttpResponse,
+};
+
+use std::{
+    collections::HashMap,
+    sync::{Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+/// The given duration, in seconds.
+fn seconds(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) * 1e-9
+}
+
+/// Why a command was refused.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Refusal {
+    /// The client has sent too many commands, and should wait for the given time.
+    Limited(Duration),
+    /// The client sent the same command moments ago.
+    Duplicate,
+}
+
+/// What's known about a client's recent commands.
+#[derive(Clone, Debug)]
+struct Client {
+    /// How many commands the client may send right now.
+    allowance: f64,
+    /// When the allowance was last brought up to date.
+    updated: Instant,
+    /// The client's last command, and when it was sent.
+    last: Option<(String, Instant)>,
+}
+
+/// Tracks clients' commands, deciding which to let through.
+#[derive(Debug)]
+pub struct Limiter {
+    config: ThrottleConfig,
+    clients: Mutex<HashMap<String, Client>>,
+}
+
+impl Limiter {
+    /// Creates a limiter enforcing the given limits.
+    pub fn new(config: ThrottleConfig) -> Self {
+        Self {
+            config,
+            clients: Mutex::default(),
+        }
+    }
+    fn lock(&self) -> MutexGuard<HashMap<String, Client>> {
+        // A panic while holding the lock can't leave the map in a bad state.
+        self.clients
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+    /// How many commands a client's allowance grows by each second.
+    fn refill(&self) -> f64 {
+        f64::from(self.config.rate) / 60.0
+    }
+    /// Whether the given client may send the given command at the given time.
+    pub fn check(&self, client: &str, command: &str, now: Instant) -> Result<(), Refusal> {
+        let burst = f64::from(self.config.burst.max(1));
+        let limited = self.config.rate > 0;
+        let refill = self.refill();
+        let debounce = self.config.debounce;
+        let mut clients = self.lock();
+        // Forget clients whose allowance has refilled and who can't be sending duplicates.
+        clients.retain(|_, client| {
+            let idle = now.saturating_duration_since(client.updated);
+            let refilled = !limited || client.allowance + seconds(idle) * refill >= burst;
+            let recent = client
+                .last
+                .as_ref()
+                .map(|&(_, at)| now.saturating_duration_since(at) < debounce)
+                .unwrap_or(false);
+            !refilled || recent
+        });
+        let client = clients.entry(client.into()).or_insert_with(|| Client {
+            allowance: burst,
+            updated: now,
+            last: None,
+        });
+        if let Some((ref last, at)) = client.last {
+            if last == command && now.saturating_duration_since(at) < debounce {
+                return Err(Refusal::Duplicate);
+            }
+        }
+        if limited {
+            let elapsed = seconds(now.saturating_duration_since(client.updated));
+            client.allowance = (client.allowance + elapsed * refill).min(burst);
+            client.updated = now;
+            if client.allowance < 1.0 {
+                let wait = (1.0 - client.allowance) / refill;
+                return Err(Refusal::Limited(Duration::from_millis(
+                    (wait * 1000.0).ceil() as u64,
+                )));
+            }
+            client.allowance -= 1.0;
+        }
+        client.last = Some((command.into(), now));
+        Ok(())
+    }
+}
+
+/// Middleware which refuses control requests from clients exceeding their limits.
+///
+/// This needs to come after [`Authenticate`](../auth/struct.Authenticate.html), so that clients
+/// presenting tokens can be told apart.
+#[derive(Clone, Copy, Debug)]
+pub struct Throttle;
+
+impl Middleware<AppState> for Throttle {
+    fn start(&self, req: &HttpRequest<AppState>) -> actix_web::Result<Started> {
+        match *req.method() {
+            Method::GET | Method::HEAD | Method::OPTIONS => return Ok(Started::Done),
+            _ => {}
+        }
+        let client = match req.extensions().get::<Holder>() {
+            Some(holder) => format!("token:{}", holder.name),
+            None => match req.peer_addr() {
+                Some(addr) => format!("addr:{}", addr.ip()),
+                None => "unknown".into(),
+            },
+        };
+        let command = format!("{} {}", req.method(), req.path());
+        match req.state().limits.check(&client, &command, Instant::now()) {
+            Ok(()) => Ok(Started::Done),
+            Err(Refusal::Limited(wait)) => {
+                let seconds = wait.as_secs() + if wait.subsec_nanos() > 0 { 1 } else { 0 };
+                Ok(Started::Response(
+                    HttpResponse::TooManyRequests()
+                        .header(header::RETRY_AFTER, seconds.to_string())
+                        .finish(),
+                ))
+            }
+            Err(Refusal::Duplicate) => Ok(Started::Response(
+                HttpResponse::TooManyRequests().json("Duplicate command"),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn limits() {
+        let limiter = Limiter::new(ThrottleConfig {
+            rate: 60,
+            burst: 2,
+            debounce: Duration::new(1, 0),
+        });
+        let start = Instant::now();
+        let later = |millis| start + Duration::from_millis(millis);
+        assert_eq!(limiter.check("a", "POST /", start), Ok(()));
+        assert_eq!(
+            limiter.check("a", "POST /", later(500)),
+            Err(Refusal::Duplicate)
+        );
+        assert_eq!(limiter.check("a", "POST /pause", later(500)), Ok(()));
+        assert_eq!(
+            limiter.check("a", "POST /resume", later(600)),
+            Err(Refusal::Limited(Duration::from_millis(400)))
+        );
+        assert_eq!(limiter.check("b", "POST /resume", later(600)), Ok(()));
+        assert_eq!(limiter.check("a", "POST /resume", later(1000)), Ok(()));
+    }
+    #[test]
+    fn tolerates_out_of_order_times() {
+        let limiter = Limiter::new(ThrottleConfig {
+            rate: 60,
+            burst: 2,
+            debounce: Duration::new(1, 0),
+        });
+        let start = Instant::now();
+        let later = start + Duration::from_millis(500);
+        assert_eq!(limiter.check("a", "POST /", later), Ok(()));
+        assert_eq!(limiter.check("a", "POST /", start), Err(Refusal::Duplicate));
+        assert_eq!(limiter.check("a", "POST /pause", start), Ok(()));
+    }
+}
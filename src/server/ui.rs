@@ -0,0 +1,83 @@
+//! The web interface, embedded in the server.
+//!
+//! The compiled frontend (the output of `cargo web deploy` in `web/`) is included in the binary at
+//! build time, so a deployment needs nothing but the binary and its configuration. Browsers asking
+//! for `/` get the interface; everything else asking for `/` gets the job status, as before.
+use super::{job::Job, state::State as AppState};
+use actix_web::{
+    http::{header, Method},
+    HttpRequest, HttpResponse,
+};
+use include_dir::{include_dir, Dir};
+
+/// The compiled frontend.
+static ASSETS: Dir = include_dir!("web/target/deploy");
+
+/// The media type of the asset at the given path, judging by its extension.
+fn content_type(path: &str) -> &'static str {
+    match path.rsplit('.').next() {
+        Some("html") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js") => "application/javascript",
+        Some("wasm") => "application/wasm",
+        Some("json") => "application/json",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("ico") => "image/x-icon",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Whether the given request comes from a browser wanting a page.
+fn wants_page<S>(req: &HttpRequest<S>) -> bool {
+    req.headers()
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.contains("text/html"))
+        .unwrap_or(false)
+}
+
+/// The embedded asset requested, if the request is for one.
+fn requested<S>(req: &HttpRequest<S>) -> Option<&'static [u8]> {
+    if *req.method() != Method::GET {
+        return None;
+    }
+    let path = match req.path().trim_start_matches('/') {
+        "" if wants_page(req) => "index.html",
+        path => path,
+    };
+    ASSETS.get_file(path).map(|file| file.contents())
+}
+
+/// Whether the given request is for part of the interface (which anyone may load).
+pub fn is_asset<S>(req: &HttpRequest<S>) -> bool {
+    requested(req).is_some()
+}
+
+/// Serves the requested asset.
+fn serve<S>(req: &HttpRequest<S>, contents: &'static [u8]) -> HttpResponse {
+    let path = match req.path().trim_start_matches('/') {
+        "" => "index.html",
+        path => path,
+    };
+    HttpResponse::Ok()
+        .content_type(content_type(path))
+        .body(contents)
+}
+
+/// Serves the interface to browsers, and the current status of the device to everyone else.
+#[allow(clippy::needless_pass_by_value)]
+pub fn root(req: HttpRequest<AppState>) -> HttpResponse {
+    match requested(&req) {
+        Some(contents) => serve(&req, contents),
+        None => HttpResponse::Ok().json(Job::current(&req.state().coord)),
+    }
+}
+
+/// Serves any other part of the interface.
+pub fn asset(req: &HttpRequest<AppState>) -> HttpResponse {
+    match requested(req) {
+        Some(contents) => serve(req, contents),
+        None => HttpResponse::NotFound().finish(),
+    }
+}
@@ -0,0 +1,257 @@
+//! Stepper motor management.
+
+use std::{collections::BTreeMap, thread, time::Duration};
+
+use actix_web::actix::Recipient;
+
+use crate::{
+    actix::*,
+    device::{Device, Error, Failed, Supervisor},
+    motor::Message,
+    pin::{Input, Pin},
+};
+
+/// The range of motion of a stepper motor, in degrees.
+const RANGE_OF_MOTION: u16 = 180;
+
+/// A stepper motor (driven through a step/direction driver such as the A4988) turning a rotary
+/// valve.
+///
+/// Positions mirror those of a servo-driven [`Motor`](struct.Motor.html): the open position is at
+/// 0º, the closed position is at 90º, and the shut position is at 180º.
+#[derive(Debug)]
+pub struct StepperMotor {
+    /// The output advancing the motor by one step on each rising edge.
+    step: Pin,
+    /// The output selecting the direction of rotation (high for forward).
+    dir: Pin,
+    /// The (active-low) output enabling the driver, if connected.
+    enable: Option<Pin>,
+    /// The switch closed when the motor is at the home (0º) position, if any.
+    home: Option<Box<dyn Input>>,
+    /// The number of (micro)steps in a full revolution.
+    steps_per_revolution: u32,
+    /// How long to hold each half of a step pulse.
+    step_delay: Duration,
+    /// The current position, in steps from home.
+    position: i64,
+    /// The angles of any additional named positions.
+    positions: BTreeMap<String, u16>,
+    /// Where failures outside of any command (e.g. homing) are reported.
+    supervisor: Supervisor,
+}
+
+impl StepperMotor {
+    /// Constructs a new stepper motor on already-acquired pins.
+    ///
+    /// If a homing switch is given, the motor will be homed when started; otherwise, its current
+    /// position is assumed to be home.
+    pub fn new(
+        step: Pin,
+        dir: Pin,
+        enable: Option<Pin>,
+        home: Option<Box<dyn Input>>,
+        steps_per_revolution: u32,
+        step_delay: Duration,
+    ) -> Self {
+        Self {
+            step,
+            dir,
+            enable,
+            home,
+            steps_per_revolution,
+            step_delay,
+            position: 0,
+            positions: BTreeMap::new(),
+            supervisor: Supervisor::default(),
+        }
+    }
+    /// Adds the given named positions (in addition to open, closed, and shut).
+    ///
+    /// This fails if any of the angles is greater than 180.
+    pub fn with_positions(mut self, positions: BTreeMap<String, u16>) -> Result<Self, Error> {
+        positions
+            .values()
+            .try_for_each(|&angle| self.check_angle(angle))?;
+        self.positions = positions;
+        Ok(self)
+    }
+    /// Reports failures outside of any command (e.g. homing) to the given recipient, as the given
+    /// device.
+    pub fn supervised(mut self, device: Device, recipient: Recipient<Failed>) -> Self {
+        self.supervisor = Supervisor::new(device, recipient);
+        self
+    }
+    /// Ensures that the given angle is within the motor's range of motion.
+    fn check_angle(&self, angle: u16) -> Result<(), Error> {
+        if angle > RANGE_OF_MOTION {
+            Err(Error::OutOfRange {
+                pin: self.step.number,
+                angle,
+                range: RANGE_OF_MOTION,
+            })
+        } else {
+            Ok(())
+        }
+    }
+    /// Enables or disables the driver (a disabled motor does not hold its position).
+    fn set_enabled(&mut self, enabled: bool) -> Result<(), Error> {
+        if let Some(enable) = &mut self.enable {
+            enable.set(!enabled)?;
+        }
+        Ok(())
+    }
+    /// Moves the motor by a single step in the given direction.
+    fn step(&mut self, forward: bool) -> Result<(), Error> {
+        self.dir.set(forward)?;
+        self.step.set_high()?;
+        thread::sleep(self.step_delay);
+        self.step.set_low()?;
+        thread::sleep(self.step_delay);
+        self.position += if forward { 1 } else { -1 };
+        Ok(())
+    }
+    /// Whether the homing switch (if any) is closed.
+    fn is_home(&self) -> bool {
+        self.home.as_ref().map_or(true, |home| home.is_high())
+    }
+    /// Returns the motor to its home position using the homing switch, if any.
+    ///
+    /// At most one full revolution is attempted before giving up.
+    pub fn home(&mut self) -> Result<(), Error> {
+        if self.home.is_some() {
+            log::trace!("Homing stepper motor on pin {}.", self.step.number);
+            self.set_enabled(true)?;
+            let mut steps = 0;
+            while !self.is_home() {
+                if steps == self.steps_per_revolution {
+                    return Err(Error::NotHomed(self.step.number));
+                }
+                self.step(false)?;
+                steps += 1;
+            }
+        }
+        self.position = 0;
+        Ok(())
+    }
+    /// Sets the motor's angle in degrees (relative to the home/open position).
+    ///
+    /// Angles greater than 180 are rejected (see
+    /// [`Error::OutOfRange`](enum.DeviceError.html#variant.OutOfRange)).
+    pub fn set_angle(&mut self, angle: u16) -> Result<(), Error> {
+        self.check_angle(angle)?;
+        let target = i64::from(self.steps_per_revolution) * i64::from(angle) / 360;
+        log::trace!(
+            "Setting stepper motor angle to {} (step {} → {})",
+            angle,
+            self.position,
+            target
+        );
+        self.set_enabled(true)?;
+        while self.position != target {
+            let forward = target > self.position;
+            self.step(forward)?;
+        }
+        Ok(())
+    }
+}
+
+impl Actor for StepperMotor {
+    type Context = Context<Self>;
+    fn started(&mut self, _context: &mut Self::Context) {
+        if let Err(err) = self.home() {
+            self.supervisor.report(err);
+        }
+    }
+    fn stopped(&mut self, _context: &mut Self::Context) {
+        if let Err(err) = self.set_enabled(false) {
+            log::error!(
+                "Failed to disable stepper motor on pin {}: {}",
+                self.step.number,
+                err
+            );
+        }
+    }
+}
+
+impl Handle<Message> for StepperMotor {
+    type Result = Result<(), Error>;
+    fn handle(&mut self, message: Message, _context: &mut Self::Context) -> Self::Result {
+        match message {
+            Message::Open => {
+                log::trace!("Opening stepper motor on pin {}.", self.step.number);
+                self.set_angle(0)
+            }
+            Message::Close => {
+                log::trace!("Closing stepper motor on pin {}.", self.step.number);
+                self.set_angle(90)
+            }
+            Message::Shut => {
+                log::trace!("Shutting stepper motor on pin {}.", self.step.number);
+                self.set_angle(180)
+            }
+            Message::Stop => {
+                log::trace!("Disabling stepper motor driver.");
+                self.set_enabled(false)
+            }
+            Message::SetPosition(name) => match self.positions.get(&name) {
+                Some(&angle) => {
+                    log::trace!(
+                        "Moving stepper motor on pin {} to {}.",
+                        self.step.number,
+                        name
+                    );
+                    self.set_angle(angle)
+                }
+                None => Err(Error::NoSuchPosition {
+                    pin: self.step.number,
+                    name,
+                }),
+            },
+            Message::SetAngle(angle) => {
+                log::trace!(
+                    "Moving stepper motor on pin {} to {}°.",
+                    self.step.number,
+                    angle
+                );
+                self.set_angle(angle)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pin::{
+        mock::{EventKind, Timeline},
+        Mock,
+    };
+    #[test]
+    fn steps_to_position() {
+        let mock = Mock::new();
+        let timeline = mock.timeline();
+        let pin = |number| Pin::with_backend(&mock, number).unwrap();
+        let mut motor = StepperMotor::new(pin(1), pin(2), None, None, 200, Duration::new(0, 0));
+        motor.set_angle(90).unwrap();
+        let pulses = |timeline: &Timeline| {
+            timeline
+                .pin(1)
+                .into_iter()
+                .filter(|event| event.kind == EventKind::High)
+                .count()
+        };
+        assert_eq!(pulses(&timeline), 50);
+        assert_eq!(
+            timeline.last(2).map(|event| event.kind),
+            Some(EventKind::High)
+        );
+        timeline.clear();
+        motor.set_angle(0).unwrap();
+        assert_eq!(pulses(&timeline), 50);
+        assert_eq!(
+            timeline.last(2).map(|event| event.kind),
+            Some(EventKind::Low)
+        );
+    }
+}
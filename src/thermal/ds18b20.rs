@@ -0,0 +1,61 @@
+//! Support for the DS18B20 1-Wire digital thermometer (through the Linux `w1-therm` driver).
+use super::TemperatureSensor;
+use crate::pin::Error;
+use std::{fs, path::PathBuf};
+use uom::si::{f64::ThermodynamicTemperature, thermodynamic_temperature::degree_celsius};
+
+/// The directory in which 1-Wire devices appear.
+const ROOT: &str = "/sys/bus/w1/devices";
+
+/// A DS18B20 attached to the 1-Wire bus.
+#[derive(Clone, Debug)]
+pub struct Ds18b20 {
+    /// The path to the device's `w1_slave` file.
+    path: PathBuf,
+}
+
+impl Ds18b20 {
+    /// Creates a handle to the device with the given ID (e.g. `28-0316a2799dff`).
+    pub fn new(id: &str) -> Self {
+        Self {
+            path: PathBuf::from(ROOT).join(id).join("w1_slave"),
+        }
+    }
+}
+
+/// Parses the contents of a `w1_slave` file, returning the temperature in m°C.
+fn parse(contents: &str) -> Option<i32> {
+    let mut lines = contents.lines();
+    // The first line ends with YES if the CRC check passed.
+    if !lines.next()?.trim_end().ends_with("YES") {
+        return None;
+    }
+    let line = lines.next()?;
+    let index = line.find("t=")?;
+    line[index + 2..].trim().parse().ok()
+}
+
+impl TemperatureSensor for Ds18b20 {
+    fn read(&mut self) -> Result<ThermodynamicTemperature, Error> {
+        let contents = fs::read_to_string(&self.path)?;
+        let millis = parse(&contents)
+            .ok_or_else(|| Error::Backend(format!("Invalid DS18B20 reading: {:?}", contents)))?;
+        Ok(ThermodynamicTemperature::new::<degree_celsius>(
+            f64::from(millis) / 1000.0,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn parses_readings() {
+        let valid = "72 01 4b 46 7f ff 0e 10 57 : crc=57 YES\n\
+                     72 01 4b 46 7f ff 0e 10 57 t=23125\n";
+        assert_eq!(parse(valid), Some(23125));
+        let invalid = "72 01 4b 46 7f ff 0e 10 57 : crc=00 NO\n\
+                       72 01 4b 46 7f ff 0e 10 57 t=23125\n";
+        assert_eq!(parse(invalid), None);
+    }
+}
@@ -0,0 +1,151 @@
+//! Temperature control.
+//!
+//! A [`Thermostat`](struct.Thermostat.html) reads a [`TemperatureSensor`](trait.TemperatureSensor.html)
+//! and switches an optional heater and chiller (e.g. through relays) to hold a target temperature.
+
+use std::time::Duration;
+
+use uom::si::{f64::ThermodynamicTemperature, thermodynamic_temperature::degree_celsius};
+
+use crate::{
+    actix::*,
+    pin::{Error as PinError, Pin},
+};
+
+mod ds18b20;
+mod thermistor;
+
+pub use self::{ds18b20::Ds18b20, thermistor::Thermistor};
+
+/// Trait representing a temperature sensor.
+pub trait TemperatureSensor: std::fmt::Debug + Send {
+    /// Reads the current temperature.
+    fn read(&mut self) -> Result<ThermodynamicTemperature, PinError>;
+}
+
+/// A message that can be sent to a thermostat.
+#[derive(Clone, Copy, Debug)]
+pub enum Message {
+    /// Holds the given temperature (in °C) within the given tolerance (in K).
+    Hold {
+        /// The target temperature, in °C.
+        target: f64,
+        /// The largest tolerated deviation from the target, in K.
+        tolerance: f64,
+    },
+    /// Stops holding any temperature, turning off the heater and chiller.
+    Release,
+    /// Requests the current temperature.
+    Measure,
+}
+
+impl ActixMessage for Message {
+    type Result = Result<ThermodynamicTemperature, PinError>;
+}
+
+/// Holds a target temperature using on/off control of a heater and/or chiller.
+///
+/// The heater is switched on once the temperature falls below the tolerance band, and off again
+/// once the target is reached; the chiller behaves analogously above the band.
+#[derive(Debug)]
+pub struct Thermostat {
+    sensor: Box<dyn TemperatureSensor>,
+    heater: Option<Pin>,
+    chiller: Option<Pin>,
+    /// The target temperature and tolerance (in °C and K, respectively), if any.
+    setpoint: Option<(f64, f64)>,
+    /// How often the temperature is checked.
+    interval: Duration,
+}
+
+impl Thermostat {
+    /// Creates a thermostat using the given sensor and (optional) heater and chiller outputs.
+    pub fn new(
+        sensor: Box<dyn TemperatureSensor>,
+        heater: Option<Pin>,
+        chiller: Option<Pin>,
+        interval: Duration,
+    ) -> Self {
+        Self {
+            sensor,
+            heater,
+            chiller,
+            setpoint: None,
+            interval,
+        }
+    }
+
+    /// Reads the temperature and switches the heater and chiller accordingly.
+    pub fn regulate(&mut self) -> Result<ThermodynamicTemperature, PinError> {
+        let temperature = self.sensor.read()?;
+        let celsius = temperature.get::<degree_celsius>();
+        match self.setpoint {
+            Some((target, tolerance)) => {
+                if let Some(heater) = &mut self.heater {
+                    if celsius < target - tolerance {
+                        heater.set_high()?;
+                    } else if celsius >= target {
+                        heater.set_low()?;
+                    }
+                }
+                if let Some(chiller) = &mut self.chiller {
+                    if celsius > target + tolerance {
+                        chiller.set_high()?;
+                    } else if celsius <= target {
+                        chiller.set_low()?;
+                    }
+                }
+            }
+            None => self.off()?,
+        }
+        Ok(temperature)
+    }
+
+    /// Turns off the heater and chiller (trying both, even if the first fails).
+    fn off(&mut self) -> Result<(), PinError> {
+        let heater = self.heater.as_mut().map_or(Ok(()), Pin::set_low);
+        let chiller = self.chiller.as_mut().map_or(Ok(()), Pin::set_low);
+        heater.and(chiller)
+    }
+
+    /// Turns off the heater and chiller, logging any failure (for when there's nobody to tell).
+    fn shut_off(&mut self) {
+        if let Err(err) = self.off() {
+            log::error!("Failed to turn off heating and cooling: {}", err);
+        }
+    }
+}
+
+impl Actor for Thermostat {
+    type Context = Context<Self>;
+    fn started(&mut self, context: &mut Self::Context) {
+        self.shut_off();
+        context.run_interval(self.interval, |thermostat, _| {
+            if let Err(err) = thermostat.regulate() {
+                log::error!("Failed to regulate temperature: {}", err);
+                thermostat.shut_off();
+            }
+        });
+    }
+    fn stopped(&mut self, _context: &mut Self::Context) {
+        self.shut_off();
+    }
+}
+
+impl Handle<Message> for Thermostat {
+    type Result = Result<ThermodynamicTemperature, PinError>;
+    fn handle(&mut self, message: Message, _context: &mut Self::Context) -> Self::Result {
+        match message {
+            Message::Hold { target, tolerance } => {
+                log::debug!("Holding temperature at {} ± {} °C", target, tolerance);
+                self.setpoint = Some((target, tolerance));
+            }
+            Message::Release => {
+                log::debug!("Releasing temperature control");
+                self.setpoint = None;
+            }
+            Message::Measure => return self.sensor.read(),
+        }
+        self.regulate()
+    }
+}
@@ -0,0 +1,56 @@
+//! Support for NTC thermistors read through an ADC.
+use super::TemperatureSensor;
+use crate::{pin::Error, sensor::adc::Adc};
+use uom::si::{f64::ThermodynamicTemperature, thermodynamic_temperature::kelvin};
+
+/// The temperature at which thermistors' nominal resistances are specified (25 °C), in K.
+const NOMINAL_TEMPERATURE: f64 = 298.15;
+
+/// An NTC thermistor forming the low side of a voltage divider read by an ADC.
+///
+/// The temperature is computed from the resistance using the β parameter equation.
+#[derive(Debug)]
+pub struct Thermistor {
+    adc: Box<dyn Adc>,
+    channel: u8,
+    /// The β coefficient of the thermistor, in K.
+    beta: f64,
+    /// The resistance of the thermistor at 25 °C, in Ω.
+    nominal: f64,
+    /// The resistance of the fixed (high-side) resistor in the divider, in Ω.
+    series: f64,
+}
+
+impl Thermistor {
+    /// Creates a thermistor on the given converter channel.
+    pub fn new(adc: Box<dyn Adc>, channel: u8, beta: f64, nominal: f64, series: f64) -> Self {
+        Self {
+            adc,
+            channel,
+            beta,
+            nominal,
+            series,
+        }
+    }
+}
+
+/// Computes the temperature (in K) from a divider reading using the β parameter equation.
+fn temperature(reading: f64, beta: f64, nominal: f64, series: f64) -> Option<f64> {
+    if reading <= 0.0 || reading >= 1.0 {
+        // The thermistor is shorted or disconnected.
+        return None;
+    }
+    let resistance = series * reading / (1.0 - reading);
+    Some(1.0 / (1.0 / NOMINAL_TEMPERATURE + (resistance / nominal).ln() / beta))
+}
+
+impl TemperatureSensor for Thermistor {
+    fn read(&mut self) -> Result<ThermodynamicTemperature, Error> {
+        let reading = self.adc.read(self.channel)?;
+        let kelvins =
+            temperature(reading, self.beta, self.nominal, self.series).ok_or_else(|| {
+                Error::Backend(format!("Thermistor reading out of range: {}", reading))
+            })?;
+        Ok(ThermodynamicTemperature::new::<kelvin>(kelvins))
+    }
+}
@@ -0,0 +1,228 @@
+//! Supervision of the coordinator.
+//!
+//! A [`Watchdog`](struct.Watchdog.html) is told by the coordinator how long each step should
+//! take. If the coordinator hasn't moved on within that time (plus a grace period), or if it stops
+//! answering pings, the watchdog trips: it drives the hardware to a safe state itself, alerts the
+//! administrators, and tells the coordinator (should it ever recover) to latch a fault.
+
+use std::{fmt, time::Duration};
+
+use actix_web::actix::{fut, ActorFuture, Recipient, WrapFuture};
+
+use crate::{
+    actix::*,
+    comm::Coordinator,
+    config::NotificationEvent,
+    mail::Summary,
+    notify::{Notification, Notifiers},
+    thermal::{Message as ThermalMessage, Thermostat},
+    MotorId, MotorMessage, Pump, PumpId, PumpMessage, WatchdogConfig,
+};
+
+/// A message from the coordinator describing its progress.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Message {
+    /// The coordinator expects to move on within the given duration.
+    Expect(Duration),
+    /// The coordinator is not expecting to move on by itself (it is stopped or waiting).
+    Idle,
+}
+
+impl ActixMessage for Message {
+    type Result = ();
+}
+
+/// A liveness check, which the coordinator answers immediately.
+#[derive(Clone, Copy, Debug)]
+pub struct Ping;
+
+impl ActixMessage for Ping {
+    type Result = ();
+}
+
+/// Why the watchdog tripped.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Reason {
+    /// A step ran past its expected duration (and the grace period).
+    Stalled,
+    /// The coordinator stopped answering pings.
+    Unresponsive,
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Stalled => write!(f, "a step did not finish in time"),
+            Self::Unresponsive => write!(f, "the coordinator stopped responding"),
+        }
+    }
+}
+
+/// Tells the coordinator that the watchdog has tripped (and already driven everything to a safe
+/// state).
+#[derive(Clone, Copy, Debug)]
+pub struct Tripped(pub Reason);
+
+impl ActixMessage for Tripped {
+    type Result = ();
+}
+
+/// Monitors the coordinator's progress, forcing a safe state if it stalls.
+///
+/// The watchdog should be run in its own arbiter, so that it can still raise the alarm if the
+/// coordinator's thread is stuck.
+pub struct Watchdog {
+    coord: Addr<Coordinator>,
+    pumps: Vec<Addr<Pump>>,
+    /// The motors, in order (where motor 0 controls the waste valve).
+    motors: Vec<Recipient<MotorMessage>>,
+    thermostat: Option<Addr<Thermostat>>,
+    notifiers: Notifiers,
+    config: WatchdogConfig,
+    /// The pending deadline for the current step, if any.
+    deadline: Option<SpawnHandle>,
+    /// Whether the last ping went unanswered.
+    unresponsive: bool,
+}
+
+impl fmt::Debug for Watchdog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Watchdog")
+            .field("config", &self.config)
+            .field("unresponsive", &self.unresponsive)
+            .finish()
+    }
+}
+
+impl Watchdog {
+    /// Creates a watchdog for the given coordinator, which controls the given devices.
+    pub fn new(
+        coord: Addr<Coordinator>,
+        pumps: Vec<Addr<Pump>>,
+        motors: Vec<Recipient<MotorMessage>>,
+        thermostat: Option<Addr<Thermostat>>,
+        notifiers: Notifiers,
+        config: WatchdogConfig,
+    ) -> Self {
+        Self {
+            coord,
+            pumps,
+            motors,
+            thermostat,
+            notifiers,
+            config,
+            deadline: None,
+            unresponsive: false,
+        }
+    }
+    /// Checks that the coordinator is still responding.
+    fn ping(&mut self, context: &mut Context<Self>) {
+        let request = self.coord.send(Ping).timeout(self.config.timeout);
+        context.spawn(request.into_actor(self).then(|result, dog, _| {
+            match result {
+                Ok(()) => dog.unresponsive = false,
+                Err(err) => {
+                    log::error!("Coordinator did not answer ping: {}", err);
+                    // Only trip once per outage.
+                    if !dog.unresponsive {
+                        dog.unresponsive = true;
+                        dog.trip(Reason::Unresponsive);
+                    }
+                }
+            }
+            fut::ok(())
+        }));
+    }
+    /// Stops the pumps, releases the temperature, and shuts all valves, then notifies the
+    /// coordinator and the administrators.
+    fn trip(&self, reason: Reason) {
+        log::error!("Watchdog tripped: {}.", reason);
+        for pump in &self.pumps {
+            pump.do_send(PumpMessage::Stop);
+        }
+        if let Some(thermostat) = &self.thermostat {
+            thermostat.do_send(ThermalMessage::Release);
+        }
+        for (index, motor) in self.motors.iter().enumerate() {
+            let message = if index == 0 {
+                MotorMessage::Shut
+            } else {
+                MotorMessage::Close
+            };
+            if let Err(err) = motor.do_send(message) {
+                log::error!("Failed to reach motor {}: {}", index, err);
+            }
+        }
+        self.coord.do_send(Tripped(reason));
+        let message = format!(
+            "The watchdog tripped because {}; the system has been stopped.",
+            reason
+        );
+        let summary = Summary::default();
+        let notification =
+            Notification::message(NotificationEvent::Fault, "Watchdog", &message, &summary);
+        self.notifiers.broadcast(&notification);
+    }
+}
+
+impl Actor for Watchdog {
+    type Context = Context<Self>;
+    fn started(&mut self, context: &mut Self::Context) {
+        context.run_interval(self.config.interval, Self::ping);
+    }
+}
+
+impl Handle<Message> for Watchdog {
+    type Result = ();
+    fn handle(&mut self, message: Message, context: &mut Self::Context) -> Self::Result {
+        if let Some(handle) = self.deadline.take() {
+            context.cancel_future(handle);
+        }
+        if let Message::Expect(duration) = message {
+            let handle = context.run_later(duration + self.config.grace, |dog, _| {
+                dog.deadline = None;
+                dog.trip(Reason::Stalled);
+            });
+            self.deadline = Some(handle);
+        }
+    }
+}
+
+/// Tells the watchdog that a motor or pump was restarted, so that it drives the new one.
+pub enum Replace {
+    /// The motor with the given index was restarted.
+    Motor(MotorId, Recipient<MotorMessage>),
+    /// The pump with the given index was restarted.
+    Pump(PumpId, Addr<Pump>),
+}
+
+impl fmt::Debug for Replace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Motor(index, _) => f.debug_tuple("Motor").field(index).finish(),
+            Self::Pump(index, _) => f.debug_tuple("Pump").field(index).finish(),
+        }
+    }
+}
+
+impl ActixMessage for Replace {
+    type Result = ();
+}
+
+impl Handle<Replace> for Watchdog {
+    type Result = ();
+    fn handle(&mut self, message: Replace, _: &mut Self::Context) -> Self::Result {
+        match message {
+            Replace::Motor(index, motor) => {
+                if let Some(entry) = self.motors.get_mut(index) {
+                    *entry = motor;
+                }
+            }
+            Replace::Pump(index, pump) => {
+                if let Some(entry) = self.pumps.get_mut(index) {
+                    *entry = pump;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,208 @@
+//! Notifying other services of protocol events by webhook.
+//!
+//! Each configured webhook receives a JSON `POST` (of the form `{"event": ..., "update": ...}`,
+//! where the update is the same object written to the [event log](../event_log/index.html)) for
+//! each event it's interested in. Requests are signed if the webhook has a secret, and failed
+//! deliveries are retried with exponential backoff.
+//!
+//! Protocol steps may also post events of their own (see [`Hook::Webhook`]), whose names are given
+//! as the update's `detail`.
+//!
+//! [`Hook::Webhook`]: ../enum.Hook.html#variant.Webhook
+
+use std::time::Duration;
+
+use actix_web::{
+    actix::{fut, ActorFuture, WrapFuture},
+    client,
+};
+use futures::Future;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::{
+    actix::*, comm::Subscribers, event_log::Event, Status, StatusMessage, Update, WebhookConfig,
+    WebhookEvent,
+};
+
+/// How many times delivery is attempted before giving up.
+const ATTEMPTS: u32 = 5;
+/// How long to wait before the first retry (doubling for each subsequent retry).
+const BACKOFF: Duration = Duration::from_secs(2);
+/// How long to wait for a response.
+const TIMEOUT: Duration = Duration::from_secs(10);
+/// The header carrying the signature of a request.
+pub const SIGNATURE_HEADER: &str = "X-Deoxy-Signature";
+
+/// The body of a webhook request.
+#[derive(Clone, Debug, Serialize)]
+struct Payload<'a> {
+    event: WebhookEvent,
+    update: &'a Event,
+}
+
+/// A protocol event, to be passed on to interested webhooks.
+#[derive(Clone, Debug)]
+struct Notice {
+    event: WebhookEvent,
+    update: Event,
+}
+
+impl ActixMessage for Notice {
+    type Result = ();
+}
+
+/// A request to be delivered to a webhook.
+#[derive(Clone, Debug)]
+struct Delivery {
+    url: String,
+    signature: Option<String>,
+    body: Vec<u8>,
+    /// How many times delivery has already been attempted.
+    attempts: u32,
+}
+
+impl ActixMessage for Delivery {
+    type Result = ();
+}
+
+/// The webhook event corresponding to the given status update, if any.
+fn classify(message: &StatusMessage) -> Option<WebhookEvent> {
+    match message {
+        StatusMessage::Started(_) => Some(WebhookEvent::Started),
+        StatusMessage::Step(_) => Some(WebhookEvent::Step),
+        StatusMessage::Completed => Some(WebhookEvent::Completed),
+        StatusMessage::Fault(_) => Some(WebhookEvent::Faulted),
+        StatusMessage::Hook(_) => Some(WebhookEvent::Hook),
+        _ => None,
+    }
+}
+
+/// Signs the given body with the given secret, as `sha256=<hex-encoded HMAC-SHA256>`.
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    // HMAC accepts keys of any length.
+    let mut mac = Hmac::<Sha256>::new_varkey(secret.as_bytes()).expect("invalid key length");
+    mac.input(body);
+    let code = mac.result().code();
+    let hex = code
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+    format!("sha256={}", hex)
+}
+
+/// Delivers protocol events to the configured webhooks.
+#[derive(Debug)]
+pub struct Webhooks {
+    hooks: Vec<WebhookConfig>,
+}
+
+impl Webhooks {
+    /// Creates a notifier for the given webhooks.
+    pub fn new(hooks: Vec<WebhookConfig>) -> Self {
+        Self { hooks }
+    }
+}
+
+impl Actor for Webhooks {
+    type Context = Context<Self>;
+}
+
+impl Handle<Notice> for Webhooks {
+    type Result = ();
+    fn handle(&mut self, notice: Notice, context: &mut Self::Context) {
+        let payload = Payload {
+            event: notice.event,
+            update: &notice.update,
+        };
+        let body = match serde_json::to_vec(&payload) {
+            Ok(body) => body,
+            Err(err) => return log::error!("Failed to serialize webhook payload: {}", err),
+        };
+        let interested = self
+            .hooks
+            .iter()
+            .filter(|hook| hook.events.is_empty() || hook.events.contains(&notice.event));
+        for hook in interested {
+            context.notify(Delivery {
+                url: hook.url.clone(),
+                signature: hook.secret.as_ref().map(|secret| sign(secret, &body)),
+                body: body.clone(),
+                attempts: 0,
+            });
+        }
+    }
+}
+
+impl Handle<Delivery> for Webhooks {
+    type Result = ();
+    fn handle(&mut self, delivery: Delivery, context: &mut Self::Context) {
+        let mut builder = client::post(&delivery.url);
+        builder.content_type("application/json");
+        if let Some(ref signature) = delivery.signature {
+            builder.header(SIGNATURE_HEADER, signature.as_str());
+        }
+        let request = match builder.body(delivery.body.clone()) {
+            Ok(request) => request,
+            Err(err) => return log::error!("Invalid webhook request to {}: {}", delivery.url, err),
+        };
+        context.spawn(request.send().timeout(TIMEOUT).into_actor(self).then(
+            move |result, _hooks, context| {
+                let failure = match result {
+                    Ok(ref response) if response.status().is_success() => return fut::ok(()),
+                    Ok(response) => format!("status {}", response.status()),
+                    Err(err) => err.to_string(),
+                };
+                let attempts = delivery.attempts + 1;
+                if attempts < ATTEMPTS {
+                    let delay = BACKOFF * 2u32.pow(delivery.attempts);
+                    log::warn!(
+                        "Webhook delivery to {} failed ({}); retrying in {} s.",
+                        delivery.url,
+                        failure,
+                        delay.as_secs()
+                    );
+                    context.notify_later(
+                        Delivery {
+                            attempts,
+                            ..delivery
+                        },
+                        delay,
+                    );
+                } else {
+                    log::error!(
+                        "Webhook delivery to {} failed ({}); giving up after {} attempts.",
+                        delivery.url,
+                        failure,
+                        attempts
+                    );
+                }
+                fut::ok(())
+            },
+        ));
+    }
+}
+
+impl Update for Addr<Webhooks> {
+    fn handle(&self, status: &Status, _coord: &Subscribers) {
+        if let Some(event) = classify(&status.message) {
+            self.do_send(Notice {
+                event,
+                update: Event::new(status),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn signs() {
+        // From RFC 4231, test case 2.
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use serde_derive::Deserialize;
 use yew::html;
 use yew::prelude::*;
 
@@ -74,6 +75,16 @@ impl Renderable<Self> for Buffers {
     }
 }
 
+/// A buffer as configured on the server (see `GET /buffers`).
+#[derive(Clone, Debug, Deserialize)]
+pub struct ConfiguredBuffer {
+    pub valve: usize,
+    pub name: String,
+    /// The volume of the buffer's reservoir, in mL.
+    #[serde(default)]
+    pub volume: Option<f32>,
+}
+
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct Buffer {
     pub(crate) label: String,
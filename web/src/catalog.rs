@@ -0,0 +1,69 @@
+use serde_derive::Deserialize;
+use std::{collections::BTreeMap, fmt};
+
+/// A protocol file cataloged on the server (see `GET /catalog`).
+#[derive(Clone, Debug, Deserialize)]
+pub struct CatalogEntry {
+    /// The name the protocol is launched by.
+    pub name: String,
+    pub file: CatalogFile,
+}
+
+/// The parts of a protocol file shown in the catalog.
+#[derive(Clone, Debug, Deserialize)]
+pub struct CatalogFile {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// The parameters the protocol takes, by name.
+    #[serde(default)]
+    pub parameters: BTreeMap<String, CatalogParameter>,
+}
+
+/// A parameter taken by a protocol file, given a value when it's run.
+#[derive(Clone, Debug, Deserialize)]
+pub struct CatalogParameter {
+    /// What the parameter stands for (e.g. "duration").
+    pub kind: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// The value used if none is given.
+    #[serde(default)]
+    pub default: Option<ParameterValue>,
+}
+
+/// The value of a parameter.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum ParameterValue {
+    Number(f64),
+    Text(String),
+}
+
+impl fmt::Display for ParameterValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParameterValue::Number(number) => write!(f, "{}", number),
+            ParameterValue::Text(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+impl CatalogParameter {
+    /// The units the parameter is given in, if any.
+    pub fn units(&self) -> &'static str {
+        match self.kind.as_str() {
+            "duration" => "s",
+            "volume" => "mL",
+            _ => "",
+        }
+    }
+}
+
+impl CatalogEntry {
+    /// The title shown for the protocol (the name given in the file, if any).
+    pub fn title(&self) -> &str {
+        self.file.name.as_ref().unwrap_or(&self.name)
+    }
+}
@@ -1,18 +1,27 @@
+use yew::format::{Json, Nothing};
 use yew::html;
 use yew::prelude::*;
+use yew::services::fetch::{FetchService, FetchTask, Request, Response};
 
 use deoxy_core::Step as CStep;
 
-use uom::si::{f32::*, volume::liter};
+use failure::Error;
+use uom::si::{
+    f32::*,
+    volume::{liter, milliliter},
+};
 
 use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::rc::Rc;
 use std::time::Duration;
 
 mod messages;
 use self::messages::*;
 mod buffers;
-use self::buffers::{Buffer, Buffers};
+use self::buffers::{Buffer, Buffers, ConfiguredBuffer};
+mod catalog;
+use self::catalog::{CatalogEntry, CatalogParameter};
 
 const SLOTS: usize = 10;
 const WASTE: usize = 1;
@@ -193,6 +202,20 @@ impl Renderable<Protocol> for Step {
 struct Root {
     buffers: Rc<RefCell<[Buffer; BUFFERS]>>,
     steps: Rc<RefCell<Vec<Step>>>,
+    /// The request for the configured buffers, while it's outstanding.
+    fetch: Option<FetchTask>,
+    /// The protocol files cataloged on the server.
+    catalog: Vec<CatalogEntry>,
+    /// The values given for each protocol file's parameters, by protocol and then by parameter.
+    values: BTreeMap<String, BTreeMap<String, String>>,
+    /// The request for the catalog, while it's outstanding.
+    listing: Option<FetchTask>,
+    /// The request to run a protocol file, while it's outstanding.
+    launch: Option<FetchTask>,
+    /// Called with the server's answer to a request to run a protocol file.
+    launched: Option<Callback<Response<Result<String, Error>>>>,
+    /// The outcome of the last request to run a protocol file.
+    status: Option<String>,
 }
 
 impl Default for Root {
@@ -204,18 +227,128 @@ impl Default for Root {
         let buffers = Rc::new(RefCell::new(buffers));
         let steps = vec![Step(0, None, vec![])];
         let steps = Rc::new(RefCell::new(steps));
-        Self { buffers, steps }
+        Self {
+            buffers,
+            steps,
+            fetch: None,
+            catalog: vec![],
+            values: BTreeMap::new(),
+            listing: None,
+            launch: None,
+            launched: None,
+            status: None,
+        }
     }
 }
 
 impl Component for Root {
     type Message = Message;
     type Properties = ();
-    fn create(_: Self::Properties, _: ComponentLink<Self>) -> Self {
-        Self::default()
+    fn create(_: Self::Properties, mut link: ComponentLink<Self>) -> Self {
+        // Label the buffers as they're configured on the server.
+        let callback = link.send_back(
+            |response: Response<Json<Result<Vec<ConfiguredBuffer>, Error>>>| {
+                let (meta, Json(body)) = response.into_parts();
+                match body {
+                    Ok(buffers) if meta.status.is_success() => Message::Configured(buffers),
+                    _ => Message::Configured(vec![]),
+                }
+            },
+        );
+        let request = Request::get("/buffers")
+            .body(Nothing)
+            .expect("The request is well-formed");
+        let fetch = FetchService::new().fetch(request, callback);
+        // List the protocol files which can be run by name.
+        let callback = link.send_back(
+            |response: Response<Json<Result<Vec<CatalogEntry>, Error>>>| {
+                let (meta, Json(body)) = response.into_parts();
+                match body {
+                    Ok(catalog) if meta.status.is_success() => Message::Cataloged(catalog),
+                    _ => Message::Cataloged(vec![]),
+                }
+            },
+        );
+        let request = Request::get("/catalog")
+            .body(Nothing)
+            .expect("The request is well-formed");
+        let listing = FetchService::new().fetch(request, callback);
+        let launched = link.send_back(|response: Response<Result<String, Error>>| {
+            Message::Launched(response.status().is_success())
+        });
+        Self {
+            fetch: Some(fetch),
+            listing: Some(listing),
+            launched: Some(launched),
+            ..Self::default()
+        }
     }
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
         match msg {
+            Message::Cataloged(catalog) => {
+                self.listing = None;
+                self.catalog = catalog;
+                true
+            }
+            Message::Bind(name, parameter, value) => {
+                let values = self.values.entry(name).or_default();
+                if value.trim().is_empty() {
+                    values.remove(&parameter);
+                } else {
+                    values.insert(parameter, value.trim().to_string());
+                }
+                false
+            }
+            Message::Launch(name) => {
+                let callback = match &self.launched {
+                    Some(callback) => callback.clone(),
+                    None => return false,
+                };
+                // Parameters left blank take their defaults.
+                let values = self.values.get(&name).cloned().unwrap_or_default();
+                let request = Request::post(format!("/catalog/{}", name))
+                    .header("Content-Type", "application/json")
+                    .body(Json(&values))
+                    .expect("The request is well-formed");
+                self.launch = Some(FetchService::new().fetch(request, callback));
+                self.status = Some(format!("Queueing {}…", name));
+                true
+            }
+            Message::Launched(queued) => {
+                self.launch = None;
+                self.status = Some(if queued {
+                    "The protocol has been queued.".into()
+                } else {
+                    "The protocol couldn't be run.".into()
+                });
+                true
+            }
+            Message::Configured(configured) => {
+                self.fetch = None;
+                let mut buffers = loop {
+                    let buffers = self.buffers.try_borrow_mut();
+                    if let Ok(buffers) = buffers {
+                        break buffers;
+                    }
+                };
+                for buffer in configured {
+                    if let Some(slot) = buffers.get_mut(buffer.valve) {
+                        slot.label = buffer.name;
+                        slot.volume = buffer.volume.map(Volume::new::<milliliter>);
+                    }
+                }
+                let buffers = buffers.clone().to_vec();
+                let mut steps = loop {
+                    let steps = self.steps.try_borrow_mut();
+                    if let Ok(steps) = steps {
+                        break steps;
+                    }
+                };
+                for step in steps.iter_mut() {
+                    step.2 = buffers.clone();
+                }
+                true
+            }
             Message::Buffer(msg) => match msg {
                 BufferMessage::Input(index, label) => {
                     let mut buffers = loop {
@@ -302,11 +435,74 @@ impl Renderable<Self> for Root {
             <>
             <Buffers: onchange=|e: BufferMessage| e.into(), buffers=self.buffers.clone(), />
             <Protocol: onchange=|e: ProtocolMessage| e.into(), steps=self.steps.clone(), buffers=self.buffers.clone(), />
+            { self.view_catalog() }
             </>
         }
     }
 }
 
+impl Root {
+    /// Lists the cataloged protocol files, each with a form for its parameters and a button to run
+    /// it.
+    fn view_catalog(&self) -> Html<Self> {
+        if self.catalog.is_empty() {
+            return html! { <></> };
+        }
+        let entry = |entry: &CatalogEntry| {
+            let name = entry.name.clone();
+            let description = entry.file.description.clone().unwrap_or_default();
+            let parameter = |(parameter, spec): (&String, &CatalogParameter)| {
+                let (protocol, key) = (entry.name.clone(), parameter.clone());
+                let value = self
+                    .values
+                    .get(&entry.name)
+                    .and_then(|values| values.get(parameter))
+                    .cloned()
+                    .unwrap_or_default();
+                // Blank parameters without defaults are refused by the server.
+                let placeholder = spec
+                    .default
+                    .as_ref()
+                    .map(ToString::to_string)
+                    .unwrap_or_else(|| "required".into());
+                html! {
+                    <li>
+                        <label>{parameter}</label>
+                        {" "}
+                        <input type="text", class="parameter", value=value, placeholder=placeholder,
+                            oninput=|e| Message::Bind(protocol.clone(), key.clone(), e.value), />
+                        {" "}
+                        <span class="units",>{spec.units()}</span>
+                        {" "}
+                        <span class="description",>{spec.description.clone().unwrap_or_default()}</span>
+                    </li>
+                }
+            };
+            html! {
+                <li>
+                    <span class="name",>{entry.title()}</span>
+                    {" "}
+                    <span class="description",>{description}</span>
+                    {" "}
+                    <input type={"button"}, value={"Run"}, onclick=|_| Message::Launch(name.clone()), />
+                    <ul class="parameters",>
+                    { for entry.file.parameters.iter().map(parameter) }
+                    </ul>
+                </li>
+            }
+        };
+        html! {
+            <div id={"catalog"},>
+            <h1>{"Protocol files"}</h1>
+            <ul>
+            { for self.catalog.iter().map(entry) }
+            </ul>
+            <p class="status",>{self.status.clone().unwrap_or_default()}</p>
+            </div>
+        }
+    }
+}
+
 /// Initializes an app and the driving run loop.
 pub fn run() {
     yew::initialize();
@@ -1,6 +1,18 @@
+use crate::{buffers::ConfiguredBuffer, catalog::CatalogEntry};
+
 pub enum Message {
     Buffer(BufferMessage),
     Protocol(ProtocolMessage),
+    /// The buffers configured on the server have arrived (or couldn't be fetched, if empty).
+    Configured(Vec<ConfiguredBuffer>),
+    /// The protocol files cataloged on the server have arrived (or couldn't be fetched, if empty).
+    Cataloged(Vec<CatalogEntry>),
+    /// The user has given a value for a parameter (the second) of a protocol file (the first).
+    Bind(String, String, String),
+    /// The user has asked to run the cataloged protocol file with the given name.
+    Launch(String),
+    /// The server has answered a request to run a protocol file (with whether it was queued).
+    Launched(bool),
 }
 
 pub enum BufferMessage {